@@ -0,0 +1,86 @@
+//! The account access the processor needs.
+//!
+//! On chain the processor takes pinocchio's zero-copy accounts. The certora
+//! rules and the unit tests call it with solana-program's `AccountInfo`s,
+//! which `cvlr` and the tests know how to create, so both implement
+//! [`Account`] and the processor functions are generic over it.
+
+use core::ops::{Deref, DerefMut};
+use pinocchio::{program_error::ProgramError, pubkey::Pubkey};
+
+/// An account passed to an instruction.
+pub trait Account {
+    type Data<'a>: Deref<Target = [u8]>
+    where
+        Self: 'a;
+    type DataMut<'a>: DerefMut<Target = [u8]>
+    where
+        Self: 'a;
+
+    fn key(&self) -> &Pubkey;
+
+    fn is_signer(&self) -> bool;
+
+    /// Borrows the account data.
+    fn data(&self) -> Result<Self::Data<'_>, ProgramError>;
+
+    /// Borrows the account data mutably.
+    fn data_mut(&self) -> Result<Self::DataMut<'_>, ProgramError>;
+}
+
+impl Account for pinocchio::account_info::AccountInfo {
+    type Data<'a> = pinocchio::account_info::Ref<'a, [u8]>;
+    type DataMut<'a> = pinocchio::account_info::RefMut<'a, [u8]>;
+
+    fn key(&self) -> &Pubkey {
+        pinocchio::account_info::AccountInfo::key(self)
+    }
+
+    fn is_signer(&self) -> bool {
+        pinocchio::account_info::AccountInfo::is_signer(self)
+    }
+
+    fn data(&self) -> Result<Self::Data<'_>, ProgramError> {
+        self.try_borrow_data()
+    }
+
+    fn data_mut(&self) -> Result<Self::DataMut<'_>, ProgramError> {
+        self.try_borrow_mut_data()
+    }
+}
+
+#[cfg(any(test, feature = "solana-program"))]
+impl<'info> Account for solana_program::account_info::AccountInfo<'info> {
+    type Data<'a>
+        = core::cell::Ref<'a, [u8]>
+    where
+        Self: 'a;
+    type DataMut<'a>
+        = core::cell::RefMut<'a, [u8]>
+    where
+        Self: 'a;
+
+    fn key(&self) -> &Pubkey {
+        self.key.as_ref().try_into().expect("keys are 32 bytes")
+    }
+
+    fn is_signer(&self) -> bool {
+        self.is_signer
+    }
+
+    fn data(&self) -> Result<Self::Data<'_>, ProgramError> {
+        let data = self
+            .data
+            .try_borrow()
+            .map_err(|_| ProgramError::AccountBorrowFailed)?;
+        Ok(core::cell::Ref::map(data, |data| &**data))
+    }
+
+    fn data_mut(&self) -> Result<Self::DataMut<'_>, ProgramError> {
+        let data = self
+            .data
+            .try_borrow_mut()
+            .map_err(|_| ProgramError::AccountBorrowFailed)?;
+        Ok(core::cell::RefMut::map(data, |data| &mut **data))
+    }
+}
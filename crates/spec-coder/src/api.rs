@@ -0,0 +1,809 @@
+//! An HTTP API driving the pipeline.
+//!
+//! `spec-coder api` takes specs over REST/JSON, so that services and bots
+//! request materializations without shelling out to the CLI:
+//!
+//! | method | path                          |                                      |
+//! |--------|-------------------------------|--------------------------------------|
+//! | `POST` | `/runs`                       | submits a [`RunRequest`], `202`      |
+//! | `GET`  | `/runs`                       | the status of every run              |
+//! | `GET`  | `/runs/{id}`                  | the [`RunStatus`] of a run           |
+//! | `POST` | `/runs/{id}/abort`            | stops a run at its next step         |
+//! | `GET`  | `/runs/{id}/artifacts`        | the files of the crate               |
+//! | `GET`  | `/runs/{id}/artifacts/{path}` | a file of the crate                  |
+//!
+//! A submitted spec is parsed and validated before it is queued; the runs are
+//! carried out one at a time by an [`Engine`], which materializes and
+//! implements the spec as `implement` does. Nobody is there to review the
+//! files of the model, so they are written without approval. With a token,
+//! every request must carry it as a bearer token; without one, the API only
+//! listens on a loopback address. A crate is only written under the
+//! examples directory of the engine. Runs are kept for as long
+//! as the server runs; what they leave in the crates, the session, results
+//! and metrics, is what the CLI and the dashboard read.
+
+use crate::driver::{Control, Progress, RunReport};
+use crate::llm::Usage;
+use crate::prover::RuleStatus;
+use axum::extract::{Path as UrlPath, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use spec::{Format, Spec, SpecError, Target};
+use std::collections::BTreeMap;
+use std::net::TcpListener;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use subtle::ConstantTimeEq;
+
+/// The default address of the API.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:8081";
+
+/// Directories of a crate that are not artifacts.
+const SKIPPED_DIRS: &[&str] = &["target", ".git"];
+
+/// An error of an engine, shown as the error of the run.
+pub type EngineError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Materializes and implements specs.
+pub trait Engine: Send + Sync {
+    /// The directory the crates are written under.
+    fn examples_dir(&self) -> &Path;
+
+    /// The crate `spec` is implemented in for `target`.
+    fn crate_dir(&self, spec: &Spec, target: Target) -> PathBuf;
+
+    /// Materializes `spec` for `target` in `dir`, unless it is there, and
+    /// implements it within the budget of `request`, reporting progress and
+    /// stopping when `control` is aborted.
+    fn run(
+        &self,
+        spec: &Spec,
+        target: Target,
+        dir: &Path,
+        request: &RunRequest,
+        control: &Control,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<RunReport, EngineError>;
+}
+
+/// The format of a submitted spec.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecFormat {
+    #[default]
+    Yaml,
+    Toml,
+    #[serde(alias = "md")]
+    Markdown,
+}
+
+impl From<SpecFormat> for Format {
+    fn from(format: SpecFormat) -> Format {
+        match format {
+            SpecFormat::Yaml => Format::Yaml,
+            SpecFormat::Toml => Format::Toml,
+            SpecFormat::Markdown => Format::Markdown,
+        }
+    }
+}
+
+/// A request to implement a spec.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunRequest {
+    /// The text of the spec
+    pub spec: String,
+    #[serde(default)]
+    pub format: SpecFormat,
+    /// The target platform; defaults to the one of the server, or the one
+    /// the spec declares
+    #[serde(default)]
+    pub target: Option<Target>,
+    /// Materialize the crate again even if it exists
+    #[serde(default)]
+    pub force: bool,
+    /// Skip formal verification and stop once the build and tests pass
+    #[serde(default)]
+    pub no_fv: bool,
+    #[serde(default)]
+    pub max_iterations: Option<usize>,
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+    #[serde(default)]
+    pub max_prover_jobs: Option<usize>,
+}
+
+/// Where a run is.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunState {
+    Queued,
+    Running,
+    Passed,
+    /// The run did not pass within its budget, or could not be carried out
+    Failed,
+    Aborted,
+}
+
+/// The status of a run, as `GET /runs/{id}` answers it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunStatus {
+    pub id: u64,
+    /// The name of the spec
+    pub spec: String,
+    pub target: Target,
+    /// The crate directory
+    pub dir: PathBuf,
+    pub state: RunState,
+    pub iteration: usize,
+    pub usage: Usage,
+    pub prover_jobs: usize,
+    /// The verdicts so far, by rule
+    pub rules: BTreeMap<String, RuleStatus>,
+    /// The budget that ran out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exhausted: Option<String>,
+    /// The feedback the last response received, unless the run passed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback: Option<String>,
+    /// Why the run could not be carried out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RunStatus {
+    /// Whether the run is over.
+    pub fn finished(&self) -> bool {
+        !matches!(self.state, RunState::Queued | RunState::Running)
+    }
+
+    /// Follows the run through `progress`.
+    fn observe(&mut self, progress: &Progress) {
+        match progress {
+            Progress::Iteration(iteration) => self.iteration = *iteration,
+            Progress::Rule(result) => {
+                self.rules.insert(result.rule.clone(), result.status);
+            }
+            _ => {}
+        }
+    }
+
+    /// Settles the run with its `report`.
+    fn finish(&mut self, report: RunReport) {
+        self.state = match (report.success, report.aborted) {
+            (true, _) => RunState::Passed,
+            (false, true) => RunState::Aborted,
+            (false, false) => RunState::Failed,
+        };
+        self.iteration = report.iterations;
+        self.usage = report.usage;
+        self.prover_jobs = report.prover_jobs;
+        self.rules = report.rules;
+        self.exhausted = report.exhausted.map(|limit| limit.to_string());
+        self.feedback = report.feedback;
+    }
+}
+
+/// An error answering a request.
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("invalid spec: {0}")]
+    InvalidSpec(#[from] SpecError),
+    #[error("there is no run {0}")]
+    NotFound(u64),
+    #[error("run {id} has no artifact {path}")]
+    NoArtifact { id: u64, path: String },
+    #[error("run {0} is over")]
+    Finished(u64),
+    #[error("the request needs a valid bearer token")]
+    Unauthorized,
+    #[error("the crate {} is outside the examples directory", .0.display())]
+    OutsideExamples(PathBuf),
+    #[error("failed to read {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            ApiError::InvalidSpec(_) | ApiError::OutsideExamples(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ApiError::NotFound(_) | ApiError::NoArtifact { .. } => StatusCode::NOT_FOUND,
+            ApiError::Finished(_) => StatusCode::CONFLICT,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::Io { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let body = serde_json::json!({ "error": self.to_string() });
+        (status, Json(body)).into_response()
+    }
+}
+
+/// A submitted run.
+struct Run {
+    status: RunStatus,
+    spec: Spec,
+    request: RunRequest,
+    control: Arc<Control>,
+}
+
+/// What the server and its worker share.
+struct Inner {
+    engine: Box<dyn Engine>,
+    runs: Mutex<Vec<Run>>,
+}
+
+impl Inner {
+    /// Updates the status of the run `id`.
+    fn update(&self, id: u64, update: impl FnOnce(&mut RunStatus)) {
+        let mut runs = self.runs.lock().expect("not poisoned");
+        if let Some(run) = runs.iter_mut().find(|run| run.status.id == id) {
+            update(&mut run.status);
+        }
+    }
+
+    /// Carries out the run `id`, unless it was aborted while queued.
+    fn execute(&self, id: u64) {
+        let (spec, request, control, target, dir) = {
+            let mut runs = self.runs.lock().expect("not poisoned");
+            let Some(run) = runs.iter_mut().find(|run| run.status.id == id) else {
+                return;
+            };
+            if run.status.state != RunState::Queued {
+                return;
+            }
+            run.status.state = RunState::Running;
+            (
+                run.spec.clone(),
+                run.request.clone(),
+                run.control.clone(),
+                run.status.target,
+                run.status.dir.clone(),
+            )
+        };
+        let result = self
+            .engine
+            .run(&spec, target, &dir, &request, &control, &mut |progress| {
+                self.update(id, |status| status.observe(progress))
+            });
+        self.update(id, |status| match result {
+            Ok(report) => status.finish(report),
+            Err(err) => {
+                status.state = RunState::Failed;
+                status.error = Some(err.to_string());
+            }
+        });
+    }
+}
+
+/// The runs of the server, carried out in order on a worker thread.
+pub struct Api {
+    inner: Arc<Inner>,
+    /// The default target of submitted specs
+    target: Option<Target>,
+    queue: mpsc::Sender<u64>,
+}
+
+/// The files under `dir`, relative to `root`, without build outputs.
+fn files(root: &Path, dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            let skipped = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| SKIPPED_DIRS.contains(&name));
+            if !skipped {
+                files(root, &path, out)?;
+            }
+        } else {
+            out.push(
+                path.strip_prefix(root)
+                    .expect("under the root")
+                    .to_path_buf(),
+            );
+        }
+    }
+    Ok(())
+}
+
+impl Api {
+    /// The API carrying out runs with `engine`, for `target` unless a
+    /// request names one.
+    pub fn new(engine: impl Engine + 'static, target: Option<Target>) -> Api {
+        let inner = Arc::new(Inner {
+            engine: Box::new(engine),
+            runs: Mutex::new(Vec::new()),
+        });
+        let (queue, queued) = mpsc::channel::<u64>();
+        let worker = inner.clone();
+        // The worker stops once the API is dropped with its queue
+        std::thread::spawn(move || {
+            for id in queued {
+                worker.execute(id);
+            }
+        });
+        Api {
+            inner,
+            target,
+            queue,
+        }
+    }
+
+    fn with_run<T>(&self, id: u64, f: impl FnOnce(&Run) -> T) -> Result<T, ApiError> {
+        let runs = self.inner.runs.lock().expect("not poisoned");
+        let run = runs
+            .iter()
+            .find(|run| run.status.id == id)
+            .ok_or(ApiError::NotFound(id))?;
+        Ok(f(run))
+    }
+
+    /// Validates the spec of `request` and queues it. Its name, which the
+    /// crate is named after, is an identifier once validated, and the crate
+    /// must resolve to a directory under the examples directory.
+    pub fn submit(&self, request: RunRequest) -> Result<RunStatus, ApiError> {
+        let spec = Spec::parse(&request.spec, request.format.into())?;
+        let target = request.target.or(self.target).unwrap_or(spec.target);
+        let dir = self.inner.engine.crate_dir(&spec, target);
+        let examples_dir = self.inner.engine.examples_dir();
+        std::fs::create_dir_all(examples_dir).map_err(|source| ApiError::Io {
+            path: examples_dir.to_path_buf(),
+            source,
+        })?;
+        if !within(examples_dir, &dir) {
+            return Err(ApiError::OutsideExamples(dir));
+        }
+        let status = {
+            let mut runs = self.inner.runs.lock().expect("not poisoned");
+            let status = RunStatus {
+                id: runs.len() as u64 + 1,
+                spec: spec.name.clone(),
+                target,
+                dir,
+                state: RunState::Queued,
+                iteration: 0,
+                usage: Usage::default(),
+                prover_jobs: 0,
+                rules: BTreeMap::new(),
+                exhausted: None,
+                feedback: None,
+                error: None,
+            };
+            runs.push(Run {
+                status: status.clone(),
+                spec,
+                request,
+                control: Arc::new(Control::default()),
+            });
+            status
+        };
+        self.queue
+            .send(status.id)
+            .expect("the worker runs while the API does");
+        Ok(status)
+    }
+
+    /// The status of every run, in the order submitted.
+    pub fn runs(&self) -> Vec<RunStatus> {
+        let runs = self.inner.runs.lock().expect("not poisoned");
+        runs.iter().map(|run| run.status.clone()).collect()
+    }
+
+    /// The status of the run `id`.
+    pub fn status(&self, id: u64) -> Result<RunStatus, ApiError> {
+        self.with_run(id, |run| run.status.clone())
+    }
+
+    /// Stops the run `id`: a queued run is not carried out, a running one
+    /// stops at its next step.
+    pub fn abort(&self, id: u64) -> Result<RunStatus, ApiError> {
+        let mut runs = self.inner.runs.lock().expect("not poisoned");
+        let run = runs
+            .iter_mut()
+            .find(|run| run.status.id == id)
+            .ok_or(ApiError::NotFound(id))?;
+        match run.status.state {
+            RunState::Queued => run.status.state = RunState::Aborted,
+            RunState::Running => run.control.abort(),
+            _ => return Err(ApiError::Finished(id)),
+        }
+        Ok(run.status.clone())
+    }
+
+    /// The files of the crate of the run `id`, relative to it.
+    pub fn artifacts(&self, id: u64) -> Result<Vec<PathBuf>, ApiError> {
+        let dir = self.with_run(id, |run| run.status.dir.clone())?;
+        let mut out = Vec::new();
+        if dir.is_dir() {
+            files(&dir, &dir, &mut out).map_err(|source| ApiError::Io { path: dir, source })?;
+        }
+        Ok(out)
+    }
+
+    /// The contents of the file `path` of the crate of the run `id`.
+    pub fn artifact(&self, id: u64, path: &str) -> Result<Vec<u8>, ApiError> {
+        let dir = self.with_run(id, |run| run.status.dir.clone())?;
+        let relative = Path::new(path);
+        let missing = || ApiError::NoArtifact {
+            id,
+            path: path.to_string(),
+        };
+        let inside = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        let skipped = relative
+            .components()
+            .any(|component| SKIPPED_DIRS.iter().any(|dir| component.as_os_str() == *dir));
+        if !inside || skipped {
+            return Err(missing());
+        }
+        let path = dir.join(relative);
+        match std::fs::read(&path) {
+            Ok(contents) => Ok(contents),
+            Err(err) if matches!(err.kind(), std::io::ErrorKind::NotFound) || path.is_dir() => {
+                Err(missing())
+            }
+            Err(source) => Err(ApiError::Io { path, source }),
+        }
+    }
+}
+
+/// Whether `dir` lies under `root` once both are resolved, following
+/// symbolic links as far as `dir` exists.
+fn within(root: &Path, dir: &Path) -> bool {
+    if dir
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        return false;
+    }
+    let (Ok(root), Ok(dir)) = (root.canonicalize(), std::path::absolute(dir)) else {
+        return false;
+    };
+    let mut existing = dir.as_path();
+    while !existing.exists() {
+        match existing.parent() {
+            Some(parent) => existing = parent,
+            None => return false,
+        }
+    }
+    let missing = dir.strip_prefix(existing).expect("an ancestor");
+    existing
+        .canonicalize()
+        .is_ok_and(|existing| existing.join(missing).starts_with(root))
+}
+
+/// Rejects requests without `token` as their bearer token, compared in
+/// constant time so that the time taken does not leak how much of it matched.
+async fn authorize(
+    State(token): State<Option<Arc<str>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(token) = token else {
+        return next.run(request).await;
+    };
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match given {
+        Some(given) if bool::from(given.as_bytes().ct_eq(token.as_bytes())) => {
+            next.run(request).await
+        }
+        _ => ApiError::Unauthorized.into_response(),
+    }
+}
+
+type Shared = State<Arc<Api>>;
+
+async fn submit(State(api): Shared, Json(request): Json<RunRequest>) -> Response {
+    match api.submit(request) {
+        Ok(status) => (StatusCode::ACCEPTED, Json(status)).into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+async fn artifact(
+    State(api): Shared,
+    UrlPath((id, path)): UrlPath<(u64, String)>,
+) -> Result<Vec<u8>, ApiError> {
+    api.artifact(id, &path)
+}
+
+/// The routes of the API; with `token`, every request must carry it.
+pub fn router(api: Api, token: Option<String>) -> Router {
+    Router::new()
+        .route(
+            "/runs",
+            post(submit).get(|State(api): Shared| async move { Json(api.runs()) }),
+        )
+        .route(
+            "/runs/{id}",
+            get(|State(api): Shared, UrlPath(id): UrlPath<u64>| async move {
+                api.status(id).map(Json)
+            }),
+        )
+        .route(
+            "/runs/{id}/abort",
+            post(|State(api): Shared, UrlPath(id): UrlPath<u64>| async move {
+                api.abort(id).map(Json)
+            }),
+        )
+        .route(
+            "/runs/{id}/artifacts",
+            get(|State(api): Shared, UrlPath(id): UrlPath<u64>| async move {
+                api.artifacts(id).map(Json)
+            }),
+        )
+        .route("/runs/{id}/artifacts/{*path}", get(artifact))
+        .layer(middleware::from_fn_with_state(
+            token.map(Arc::<str>::from),
+            authorize,
+        ))
+        .with_state(Arc::new(api))
+}
+
+/// Serves `api` on `listener` until the process is stopped. Without a
+/// token, anyone who can reach the API could have crates written and
+/// implemented, so `listener` must be on a loopback address.
+pub fn serve(listener: TcpListener, api: Api, token: Option<String>) -> std::io::Result<()> {
+    let addr = listener.local_addr()?;
+    if token.is_none() && !addr.ip().is_loopback() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("refusing to serve on {addr} without a token; set SPEC_CODER_API_TOKEN"),
+        ));
+    }
+    listener.set_nonblocking(true)?;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        axum::serve(listener, router(api, token)).await
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::RuleResult;
+    use std::time::Duration;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    /// Writes a crate and passes, once each run is let through.
+    struct Fake {
+        examples: PathBuf,
+        gate: Mutex<mpsc::Receiver<()>>,
+    }
+
+    impl Engine for Fake {
+        fn examples_dir(&self) -> &Path {
+            &self.examples
+        }
+
+        fn crate_dir(&self, spec: &Spec, target: Target) -> PathBuf {
+            self.examples.join(target.to_string()).join(&spec.name)
+        }
+
+        fn run(
+            &self,
+            spec: &Spec,
+            _target: Target,
+            dir: &Path,
+            _request: &RunRequest,
+            control: &Control,
+            on_progress: &mut dyn FnMut(&Progress),
+        ) -> Result<RunReport, EngineError> {
+            self.gate.lock().unwrap().recv()?;
+            for path in ["src/lib.rs", "target/debug/build.log"] {
+                std::fs::create_dir_all(dir.join(path).parent().unwrap())?;
+                std::fs::write(dir.join(path), format!("// {}\n", spec.name))?;
+            }
+            on_progress(&Progress::Iteration(1));
+            let result = RuleResult {
+                rule: "rule_deposit_solvency".to_string(),
+                status: RuleStatus::Verified,
+                output: String::new(),
+                counterexample: None,
+                cached: false,
+            };
+            on_progress(&Progress::Rule(result));
+            Ok(RunReport {
+                success: !control.is_aborted(),
+                iterations: 1,
+                usage: Usage {
+                    input_tokens: 100,
+                    output_tokens: 20,
+                },
+                prover_jobs: 1,
+                rules: BTreeMap::from([(
+                    "rule_deposit_solvency".to_string(),
+                    RuleStatus::Verified,
+                )]),
+                unproved: Vec::new(),
+                feedback: None,
+                exhausted: None,
+                skipped: Vec::new(),
+                aborted: control.is_aborted(),
+            })
+        }
+    }
+
+    fn api(examples: &Path) -> (Api, mpsc::Sender<()>) {
+        let (open, gate) = mpsc::channel();
+        let engine = Fake {
+            examples: examples.to_path_buf(),
+            gate: Mutex::new(gate),
+        };
+        (Api::new(engine, Some(Target::Svm)), open)
+    }
+
+    fn wait(api: &Api, id: u64) -> RunStatus {
+        for _ in 0..500 {
+            let status = api.status(id).unwrap();
+            if status.finished() {
+                return status;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("run {id} did not finish");
+    }
+
+    fn request() -> RunRequest {
+        RunRequest {
+            spec: VAULT.to_string(),
+            ..RunRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_runs() {
+        let examples = tempfile::tempdir().unwrap();
+        let (api, open) = api(examples.path());
+        assert!(matches!(
+            api.submit(RunRequest {
+                spec: "name: vault\nfunctions: 3\n".to_string(),
+                ..RunRequest::default()
+            }),
+            Err(ApiError::InvalidSpec(_))
+        ));
+        assert!(matches!(
+            api.submit(RunRequest {
+                spec: VAULT.replace("name: vault", "name: ../../../tmp/evil"),
+                ..RunRequest::default()
+            }),
+            Err(ApiError::InvalidSpec(_))
+        ));
+
+        let first = api.submit(request()).unwrap();
+        assert_eq!(first.id, 1);
+        assert_eq!(first.spec, "vault");
+        assert_eq!(first.target, Target::Svm);
+        assert_eq!(first.dir, examples.path().join("svm/vault"));
+        let second = api.submit(request()).unwrap();
+        assert_eq!(api.abort(second.id).unwrap().state, RunState::Aborted);
+        open.send(()).unwrap();
+        let first = wait(&api, first.id);
+        assert_eq!(first.state, RunState::Passed);
+        assert_eq!(first.iteration, 1);
+        assert_eq!(first.usage.input_tokens, 100);
+        assert_eq!(first.rules["rule_deposit_solvency"], RuleStatus::Verified);
+        assert!(matches!(api.abort(first.id), Err(ApiError::Finished(1))));
+        let states: Vec<RunState> = api.runs().iter().map(|run| run.state).collect();
+        assert_eq!(states, [RunState::Passed, RunState::Aborted]);
+        assert!(matches!(api.status(3), Err(ApiError::NotFound(3))));
+
+        assert_eq!(api.artifacts(1).unwrap(), [PathBuf::from("src/lib.rs")]);
+        assert_eq!(api.artifact(1, "src/lib.rs").unwrap(), b"// vault\n");
+        for path in [
+            "../svm/vault/src/lib.rs",
+            "/etc/passwd",
+            "target/debug/build.log",
+            "src",
+        ] {
+            assert!(
+                matches!(api.artifact(1, path), Err(ApiError::NoArtifact { .. })),
+                "{path}"
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_crates_stay_in_examples() {
+        let examples = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        assert!(within(examples.path(), &examples.path().join("svm/vault")));
+        assert!(!within(examples.path(), &examples.path().join("../vault")));
+        std::os::unix::fs::symlink(outside.path(), examples.path().join("svm")).unwrap();
+        let (api, _open) = api(examples.path());
+        let err = api.submit(request()).unwrap_err();
+        assert!(matches!(&err, ApiError::OutsideExamples(dir) if dir.ends_with("svm/vault")));
+        assert!(api.runs().is_empty());
+    }
+
+    #[test]
+    fn test_serve() {
+        let examples = tempfile::tempdir().unwrap();
+        let (api, open) = api(examples.path());
+        open.send(()).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || serve(listener, api, Some("secret".to_string())));
+        let (open_api, _open) = self::api(examples.path());
+        let err = serve(TcpListener::bind("0.0.0.0:0").unwrap(), open_api, None).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+        let body = serde_json::to_string(&request()).unwrap();
+        match ureq::post(&format!("{url}/runs")).send_string(&body) {
+            Err(ureq::Error::Status(status, _)) => assert_eq!(status, 401),
+            other => panic!("expected a 401, got {other:?}"),
+        }
+        for wrong in ["Bearer secreT", "Bearer secret2", "Bearer secre", "Bearer "] {
+            match ureq::post(&format!("{url}/runs"))
+                .set("authorization", wrong)
+                .send_string(&body)
+            {
+                Err(ureq::Error::Status(status, _)) => assert_eq!(status, 401),
+                other => panic!("expected a 401, got {other:?}"),
+            }
+        }
+        let submitted = ureq::post(&format!("{url}/runs"))
+            .set("authorization", "Bearer secret")
+            .set("content-type", "application/json")
+            .send_string(&body)
+            .unwrap();
+        assert_eq!(submitted.status(), 202);
+        let status: RunStatus = serde_json::from_str(&submitted.into_string().unwrap()).unwrap();
+        assert_eq!(status.id, 1);
+
+        let mut status = status;
+        for _ in 0..500 {
+            if status.finished() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+            let response = ureq::get(&format!("{url}/runs/1"))
+                .set("authorization", "Bearer secret")
+                .call()
+                .unwrap();
+            status = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+        }
+        assert_eq!(status.state, RunState::Passed);
+        let contents = ureq::get(&format!("{url}/runs/1/artifacts/src/lib.rs"))
+            .set("authorization", "Bearer secret")
+            .call()
+            .unwrap()
+            .into_string()
+            .unwrap();
+        assert_eq!(contents, "// vault\n");
+        match ureq::get(&format!("{url}/runs/2"))
+            .set("authorization", "Bearer secret")
+            .call()
+        {
+            Err(ureq::Error::Status(status, response)) => {
+                assert_eq!(status, 404);
+                assert!(response
+                    .into_string()
+                    .unwrap()
+                    .contains("there is no run 2"));
+            }
+            other => panic!("expected a 404, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,463 @@
+//! Removal of the artifacts runs leave behind.
+//!
+//! Every crate a run works on keeps its [`Kind`]s of artifacts: the saved
+//! [session](crate::session), the output directories of the prover, the
+//! prover [cache](crate::prover::cache) and the target directory of
+//! [sandboxed](crate::sandbox) builds. Long repair loops grow the prover
+//! outputs and the builds to gigabytes. A [`Retention`] policy decides which
+//! artifacts go: those older than its age, then the oldest of the rest until
+//! they fit its size. Without a policy, every artifact goes. The records of
+//! what was verified and materialized, and the results, are not artifacts
+//! and are kept.
+
+use crate::prover::cache::CACHE_JSON;
+use crate::sandbox::SANDBOX_TARGET;
+use crate::session::SESSION_JSON;
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// The directory of the intermediate files of a prover run, relative to the
+/// crate root.
+const CERTORA_INTERNAL: &str = ".certora_internal";
+
+/// An error finding or removing artifacts.
+#[derive(Debug, thiserror::Error)]
+#[error("failed to {action} {}: {source}", .path.display())]
+pub struct CleanError {
+    action: &'static str,
+    path: PathBuf,
+    source: std::io::Error,
+}
+
+/// A kind of artifact.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    /// The saved state of the last run, which `implement --resume` continues
+    Session,
+    /// The `emv-*` output directories and the intermediate files of the
+    /// prover, with its call traces
+    ProverOutput,
+    /// Prover results reused while their inputs are unchanged
+    Cache,
+    /// The target directory of sandboxed builds
+    Sandbox,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 4] = [
+        Kind::Session,
+        Kind::ProverOutput,
+        Kind::Cache,
+        Kind::Sandbox,
+    ];
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Session => "session",
+            Kind::ProverOutput => "prover-output",
+            Kind::Cache => "cache",
+            Kind::Sandbox => "sandbox",
+        })
+    }
+}
+
+impl std::str::FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Kind, String> {
+        Kind::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == s)
+            .ok_or_else(|| {
+                format!("unknown artifact `{s}`, expected `session`, `prover-output`, `cache` or `sandbox`")
+            })
+    }
+}
+
+/// An age, written as a number of `s`econds, `m`inutes, `h`ours, `d`ays or
+/// `w`eeks, e.g. `12h` or `7d`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Age(pub Duration);
+
+impl std::str::FromStr for Age {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Age, String> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let number: u64 = number
+            .parse()
+            .map_err(|_| format!("invalid age `{s}`, expected e.g. `12h` or `7d`"))?;
+        let secs = match unit.trim() {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            _ => {
+                return Err(format!(
+                    "invalid age `{s}`, expected a unit of s, m, h, d or w"
+                ))
+            }
+        };
+        number
+            .checked_mul(secs)
+            .map(|secs| Age(Duration::from_secs(secs)))
+            .ok_or_else(|| format!("age `{s}` is out of range"))
+    }
+}
+
+impl<'de> Deserialize<'de> for Age {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Age, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// A size in bytes, written with an optional `K`, `M`, `G` or `T` suffix for
+/// powers of 1024, e.g. `500M` or `2G`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Size(pub u64);
+
+impl std::str::FromStr for Size {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Size, String> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let number: u64 = number
+            .parse()
+            .map_err(|_| format!("invalid size `{s}`, expected e.g. `500M` or `2G`"))?;
+        let unit = unit.trim().trim_end_matches("iB").trim_end_matches('B');
+        let shift = match unit {
+            "" => 0,
+            "K" | "k" => 10,
+            "M" => 20,
+            "G" => 30,
+            "T" => 40,
+            _ => {
+                return Err(format!(
+                    "invalid size `{s}`, expected a suffix of K, M, G or T"
+                ))
+            }
+        };
+        // Shifting would drop the high bits of a size too large
+        number
+            .checked_mul(1 << shift)
+            .map(Size)
+            .ok_or_else(|| format!("size `{s}` is out of range"))
+    }
+}
+
+impl<'de> Deserialize<'de> for Size {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Size, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for Size {
+    /// The size in the largest unit it has at least one of, e.g. `1.5 GiB`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut size = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while size >= 1024.0 && unit + 1 < UNITS.len() {
+            size /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Which artifacts to keep.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Retention {
+    /// Remove artifacts last written longer ago than this
+    pub max_age: Option<Age>,
+    /// Remove the oldest artifacts until the rest take up at most this
+    pub max_size: Option<Size>,
+}
+
+/// An artifact of a crate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Artifact {
+    pub kind: Kind,
+    /// The file or directory
+    pub path: PathBuf,
+    /// The bytes of its files
+    pub size: u64,
+    /// When any of its files was last written
+    pub modified: SystemTime,
+}
+
+/// The total size and the latest modification of the files under `path`.
+fn measure(path: &Path) -> Result<(u64, SystemTime), CleanError> {
+    let io = |source| CleanError {
+        action: "read",
+        path: path.to_path_buf(),
+        source,
+    };
+    let metadata = std::fs::symlink_metadata(path).map_err(io)?;
+    let mut size = 0;
+    let mut modified = metadata.modified().map_err(io)?;
+    if metadata.is_dir() {
+        for entry in std::fs::read_dir(path).map_err(io)? {
+            let (entry_size, entry_modified) = measure(&entry.map_err(io)?.path())?;
+            size += entry_size;
+            modified = modified.max(entry_modified);
+        }
+    } else {
+        size = metadata.len();
+    }
+    Ok((size, modified))
+}
+
+/// The artifacts of `kinds` in the crate in `dir`.
+pub fn artifacts(dir: &Path, kinds: &[Kind]) -> Result<Vec<Artifact>, CleanError> {
+    let mut paths = Vec::new();
+    for &kind in kinds {
+        match kind {
+            Kind::Session => paths.push((kind, dir.join(SESSION_JSON))),
+            Kind::Cache => paths.push((kind, dir.join(CACHE_JSON))),
+            Kind::Sandbox => paths.push((kind, dir.join(SANDBOX_TARGET))),
+            Kind::ProverOutput => {
+                paths.push((kind, dir.join(CERTORA_INTERNAL)));
+                let entries = match std::fs::read_dir(dir) {
+                    Ok(entries) => entries,
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(source) => {
+                        return Err(CleanError {
+                            action: "read",
+                            path: dir.to_path_buf(),
+                            source,
+                        })
+                    }
+                };
+                let mut runs: Vec<PathBuf> = entries
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.file_name().to_string_lossy().starts_with("emv-"))
+                    .map(|entry| entry.path())
+                    .collect();
+                runs.sort();
+                paths.extend(runs.into_iter().map(|path| (kind, path)));
+            }
+        }
+    }
+    let mut artifacts = Vec::new();
+    for (kind, path) in paths {
+        if std::fs::symlink_metadata(&path).is_err() {
+            continue;
+        }
+        let (size, modified) = measure(&path)?;
+        artifacts.push(Artifact {
+            kind,
+            path,
+            size,
+            modified,
+        });
+    }
+    Ok(artifacts)
+}
+
+/// The artifacts `retention` does not keep as of `now`: every one without a
+/// policy, else those older than its age and, newest first, those past its
+/// size.
+pub fn expired<'a>(
+    artifacts: &'a [Artifact],
+    retention: &Retention,
+    now: SystemTime,
+) -> Vec<&'a Artifact> {
+    if retention.max_age.is_none() && retention.max_size.is_none() {
+        return artifacts.iter().collect();
+    }
+    let too_old = |artifact: &Artifact| {
+        retention.max_age.is_some_and(|Age(age)| {
+            now.duration_since(artifact.modified)
+                .is_ok_and(|elapsed| elapsed > age)
+        })
+    };
+    let mut kept: Vec<&Artifact> = artifacts
+        .iter()
+        .filter(|artifact| !too_old(artifact))
+        .collect();
+    kept.sort_by_key(|artifact| std::cmp::Reverse(artifact.modified));
+    let mut total = 0;
+    let mut over = Vec::new();
+    for artifact in kept {
+        total += artifact.size;
+        if retention.max_size.is_some_and(|Size(max)| total > max) {
+            over.push(artifact.path.clone());
+        }
+    }
+    artifacts
+        .iter()
+        .filter(|artifact| too_old(artifact) || over.contains(&artifact.path))
+        .collect()
+}
+
+/// Removes `artifact`.
+pub fn remove(artifact: &Artifact) -> Result<(), CleanError> {
+    let removed = if artifact.path.is_dir() {
+        std::fs::remove_dir_all(&artifact.path)
+    } else {
+        std::fs::remove_file(&artifact.path)
+    };
+    removed.map_err(|source| CleanError {
+        action: "remove",
+        path: artifact.path.clone(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, len: usize) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, vec![b'x'; len]).unwrap();
+    }
+
+    fn artifact(path: &str, size: u64, age_secs: u64, now: SystemTime) -> Artifact {
+        Artifact {
+            kind: Kind::ProverOutput,
+            path: PathBuf::from(path),
+            size,
+            modified: now - Duration::from_secs(age_secs),
+        }
+    }
+
+    #[test]
+    fn test_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        write(&dir.path().join(SESSION_JSON), 10);
+        write(&dir.path().join(CACHE_JSON), 20);
+        write(
+            &dir.path().join(SANDBOX_TARGET).join("debug/libvault.rlib"),
+            300,
+        );
+        write(&dir.path().join(SANDBOX_TARGET).join("debug/deps/a.o"), 400);
+        write(
+            &dir.path()
+                .join("emv-2-certora-01-Jan--00-00/Reports/a.json"),
+            50,
+        );
+        write(
+            &dir.path()
+                .join("emv-1-certora-01-Jan--00-00/Reports/a.json"),
+            60,
+        );
+        write(&dir.path().join(".spec-coder/verified.json"), 5);
+        write(&dir.path().join("src/lib.rs"), 5);
+
+        let found = artifacts(dir.path(), &Kind::ALL).unwrap();
+        let found: Vec<(Kind, String, u64)> = found
+            .iter()
+            .map(|artifact| {
+                let path = artifact.path.strip_prefix(dir.path()).unwrap();
+                (artifact.kind, path.display().to_string(), artifact.size)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Kind::Session, SESSION_JSON.to_string(), 10),
+                (
+                    Kind::ProverOutput,
+                    "emv-1-certora-01-Jan--00-00".to_string(),
+                    60
+                ),
+                (
+                    Kind::ProverOutput,
+                    "emv-2-certora-01-Jan--00-00".to_string(),
+                    50
+                ),
+                (Kind::Cache, CACHE_JSON.to_string(), 20),
+                (Kind::Sandbox, SANDBOX_TARGET.to_string(), 700),
+            ]
+        );
+
+        let sandbox = artifacts(dir.path(), &[Kind::Sandbox]).unwrap();
+        assert_eq!(sandbox.len(), 1);
+        remove(&sandbox[0]).unwrap();
+        assert!(!dir.path().join(SANDBOX_TARGET).exists());
+        assert!(dir.path().join(".spec-coder/verified.json").exists());
+        assert!(artifacts(dir.path(), &[Kind::Sandbox]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_expired() {
+        let now = SystemTime::now();
+        let artifacts = [
+            artifact("old", 10, 10 * 24 * 3600, now),
+            artifact("big", 900, 3600, now),
+            artifact("new", 200, 60, now),
+            artifact("newest", 100, 1, now),
+        ];
+        let paths = |retention: Retention| -> Vec<String> {
+            expired(&artifacts, &retention, now)
+                .iter()
+                .map(|artifact| artifact.path.display().to_string())
+                .collect()
+        };
+        assert_eq!(paths(Retention::default()).len(), 4);
+        let week = Some("1w".parse().unwrap());
+        let max_age = Retention {
+            max_age: week,
+            max_size: None,
+        };
+        assert_eq!(paths(max_age), ["old"]);
+        let max_size = Retention {
+            max_age: None,
+            max_size: Some(Size(400)),
+        };
+        assert_eq!(paths(max_size), ["old", "big"]);
+        let both = Retention {
+            max_age: week,
+            max_size: Some(Size(100)),
+        };
+        assert_eq!(paths(both), ["old", "big", "new"]);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("90s".parse(), Ok(Age(Duration::from_secs(90))));
+        assert_eq!("12h".parse(), Ok(Age(Duration::from_secs(12 * 3600))));
+        assert_eq!("7d".parse(), Ok(Age(Duration::from_secs(7 * 24 * 3600))));
+        assert!("7".parse::<Age>().is_err());
+        assert!("d".parse::<Age>().is_err());
+        assert_eq!("512".parse(), Ok(Size(512)));
+        assert_eq!("500M".parse(), Ok(Size(500 << 20)));
+        assert_eq!("2GiB".parse(), Ok(Size(2 << 30)));
+        assert!("2X".parse::<Size>().is_err());
+        assert_eq!(
+            "99999999999999999w".parse::<Age>(),
+            Err("age `99999999999999999w` is out of range".to_string())
+        );
+        assert_eq!(
+            "18446744073709551615s".parse(),
+            Ok(Age(Duration::from_secs(u64::MAX)))
+        );
+        assert_eq!(
+            "17179869184G".parse::<Size>(),
+            Err("size `17179869184G` is out of range".to_string())
+        );
+        assert_eq!("17179869183G".parse(), Ok(Size(17179869183 << 30)));
+        assert_eq!(Size(512).to_string(), "512 B");
+        assert_eq!(Size(3 << 29).to_string(), "1.5 GiB");
+        assert_eq!("prover-output".parse(), Ok(Kind::ProverOutput));
+        assert!("traces".parse::<Kind>().is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Compute-unit budgets of Solana programs.
+//!
+//! A function of the spec may declare the most compute units a call may
+//! consume on chain. Verifying an implementation says nothing about its
+//! cost, so once the rules pass, the calls measured by the generated
+//! `tests/compute_units.rs` are checked against the budgets, and a run
+//! whose implementation is too expensive does not succeed.
+
+use spec::Spec;
+
+/// The prefix of the lines the generated tests print a measurement on.
+const PREFIX: &str = "compute units: ";
+
+/// The compute units one call consumed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Measurement {
+    pub function: String,
+    /// The example of the spec the call was made as, or `zeroed`
+    pub example: String,
+    pub units: u64,
+}
+
+/// The most expensive call of a function with a budget.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cost {
+    pub function: String,
+    pub example: String,
+    pub units: u64,
+    pub budget: u64,
+}
+
+impl Cost {
+    pub fn over_budget(&self) -> bool {
+        self.units > self.budget
+    }
+}
+
+/// The measurements printed in `output`, e.g. by
+/// `cargo test-sbf --test compute_units -- --nocapture`.
+pub fn parse(output: &str) -> Vec<Measurement> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.trim().strip_prefix(PREFIX)?.split_whitespace();
+            let (Some(function), Some(example), Some(units), None) =
+                (words.next(), words.next(), words.next(), words.next())
+            else {
+                return None;
+            };
+            Some(Measurement {
+                function: function.to_string(),
+                example: example.to_string(),
+                units: units.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// The cost of every function of `spec` with a budget that was measured,
+/// in the order of the spec.
+pub fn costs(spec: &Spec, measurements: &[Measurement]) -> Vec<Cost> {
+    spec.functions
+        .iter()
+        .filter_map(|function| {
+            let budget = function.compute_units?;
+            let most = measurements
+                .iter()
+                .filter(|measurement| measurement.function == function.name)
+                .max_by_key(|measurement| measurement.units)?;
+            Some(Cost {
+                function: function.name.clone(),
+                example: most.example.clone(),
+                units: most.units,
+                budget,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_costs() {
+        let output = "\
+running 3 tests
+compute units: withdraw everything 3120
+compute units: withdraw partial 2980
+compute units: deposit into_empty_vault 1500
+compute units: deposit
+test withdraw_everything ... ok
+";
+        let measurements = parse(output);
+        assert_eq!(measurements.len(), 3);
+        assert_eq!(
+            measurements[1],
+            Measurement {
+                function: "withdraw".to_string(),
+                example: "partial".to_string(),
+                units: 2980,
+            }
+        );
+
+        let mut spec =
+            Spec::from_yaml_str(include_str!("../../../examples/specs/vault.yaml")).unwrap();
+        spec.functions[1].compute_units = Some(3000);
+        spec.functions[2].compute_units = Some(3000);
+        let costs = costs(&spec, &measurements);
+        // Deposit has no budget and reward was not measured
+        assert_eq!(
+            costs,
+            [Cost {
+                function: "withdraw".to_string(),
+                example: "everything".to_string(),
+                units: 3120,
+                budget: 3000,
+            }]
+        );
+        assert!(costs[0].over_budget());
+    }
+}
@@ -0,0 +1,405 @@
+//! Project configuration.
+//!
+//! Settings are read from [`CONFIG_TOML`] in the project root, the directory
+//! `spec-coder` runs in; command-line flags take precedence over them. A
+//! project without the file uses the defaults.
+//!
+//! ```toml
+//! # The target to implement specs for, instead of the one they declare
+//! target = "anchor"
+//!
+//! [model]
+//! provider = "openai"
+//! name = "gpt-4o"
+//! base_url = "http://localhost:8080/v1"
+//! timeout_secs = 300
+//! max_retries = 8
+//! context_tokens = 128_000
+//!
+//! [budget]
+//! max_iterations = 12
+//! max_tokens = 2_000_000
+//! max_prover_jobs = 200
+//!
+//! [prover]
+//! jobs = 4
+//! cache = true
+//! fast_tag = "fast"
+//! poll_interval_secs = 10
+//! job_timeout_secs = 7200
+//! cloud_rules = ["slow", "rule_*_solvency"]
+//!
+//! [mutation]
+//! min_score = 80
+//!
+//! [build]
+//! sandbox = "docker:rust:1.80"
+//! clippy = true
+//!
+//! [templates]
+//! dir = "prompts"
+//!
+//! [clean]
+//! max_age = "7d"
+//! max_size = "2G"
+//!
+//! [output]
+//! examples_dir = "examples"
+//! metrics = ".spec-coder/metrics.jsonl"
+//! license = "SPDX-License-Identifier: MIT"
+//! ```
+//!
+//! Environment variables override the file: [`ENV_PREFIX`] followed by the
+//! key, upper-cased, with `__` between a table and its key, e.g.
+//! `SPEC_CODER_TARGET=svm` or `SPEC_CODER_BUDGET__MAX_TOKENS=500000`. A
+//! value is read as TOML if it parses as such and as a string otherwise.
+
+use crate::clean::{Age, Size};
+use crate::llm::ProviderKind;
+use crate::metrics::METRICS_JSONL;
+use crate::sandbox::Sandbox;
+use serde::Deserialize;
+use spec::Target;
+use std::path::{Path, PathBuf};
+
+/// The configuration file, relative to the project root.
+pub const CONFIG_TOML: &str = "spec-coder.toml";
+
+/// The prefix of the environment variables overriding the file.
+pub const ENV_PREFIX: &str = "SPEC_CODER_";
+
+/// The examples directory unless configured otherwise.
+const EXAMPLES_DIR: &str = "examples";
+
+/// An error reading the configuration.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("malformed {}: {source}", .path.display())]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("invalid configuration in the environment: {0}")]
+    Env(toml::de::Error),
+}
+
+/// The model implementing the specs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelConfig {
+    /// Anthropic by default
+    pub provider: Option<ProviderKind>,
+    /// The model name; one suited to the provider by default
+    pub name: Option<String>,
+    /// The API root, e.g. of a local OpenAI-compatible server
+    pub base_url: Option<String>,
+    /// Seconds a request may take
+    pub timeout_secs: Option<u64>,
+    /// Retries of a request failing transiently
+    pub max_retries: Option<u32>,
+    /// The context window of the model, in tokens
+    pub context_tokens: Option<u64>,
+}
+
+/// The limits of a run of the verify-and-fix loop; unset ones are the
+/// defaults of [`DriverOptions`](crate::driver::DriverOptions).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Budget {
+    pub max_iterations: Option<usize>,
+    /// Input and output tokens together
+    pub max_tokens: Option<u64>,
+    /// Rules proved, each being one prover job
+    pub max_prover_jobs: Option<usize>,
+}
+
+/// How the prover is run.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProverConfig {
+    /// How many rules to prove at once; one by default
+    pub jobs: Option<usize>,
+    /// Whether to reuse results of rules whose inputs did not change; true
+    /// by default
+    pub cache: Option<bool>,
+    /// The tag of the rules to prove before the others, see
+    /// [`crate::driver::DriverOptions::fast_tag`]; none by default
+    pub fast_tag: Option<String>,
+    /// The wait between two polls of a cloud job; 10 seconds by default
+    pub poll_interval_secs: Option<u64>,
+    /// How long a cloud job may run before it is cancelled; two hours by
+    /// default, and no limit with 0
+    pub job_timeout_secs: Option<u64>,
+    /// The key of the Certora cloud, best set through
+    /// `SPEC_CODER_PROVER__CLOUD_KEY`; read from `CERTORAKEY` by default
+    pub cloud_key: Option<String>,
+    /// The rules the `auto` backend proves in the cloud, as patterns of
+    /// their names or tags, see [`crate::prover::route::Router`]; none by
+    /// default
+    #[serde(default)]
+    pub cloud_rules: Vec<String>,
+}
+
+/// The mutation gate of a run, see [`crate::mutation`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MutationConfig {
+    /// The percentage of mutants the tests and rules must kill; no mutants
+    /// are checked by default
+    pub min_score: Option<u8>,
+}
+
+/// How the crate is built and tested.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildConfig {
+    /// Where cargo runs, see [`Sandbox`]; a temporary copy of the crate by
+    /// default
+    pub sandbox: Option<Sandbox>,
+    /// Format the crate and fix its lints once it builds, denying the
+    /// lints left; on by default
+    pub clippy: Option<bool>,
+}
+
+/// Where prompt templates are looked up, see [`crate::templates`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplatesConfig {
+    /// Templates overriding the built-in ones
+    pub dir: Option<PathBuf>,
+}
+
+/// Which artifacts `spec-coder clean` keeps, see [`crate::clean`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CleanConfig {
+    /// Remove artifacts last written longer ago than this, e.g. `7d`
+    pub max_age: Option<Age>,
+    /// Remove the oldest artifacts until the rest take up at most this,
+    /// e.g. `2G`
+    pub max_size: Option<Size>,
+}
+
+/// Where outputs are written, relative to the project root.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    /// The directory of the examples; `examples` by default
+    pub examples_dir: Option<PathBuf>,
+    /// The metrics store; [`METRICS_JSONL`] by default
+    pub metrics: Option<PathBuf>,
+    /// The license header generated files start with, above their
+    /// provenance stamp, see [`crate::provenance`]
+    pub license: Option<String>,
+}
+
+/// The contents of [`CONFIG_TOML`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The target specs are implemented for, instead of the one they declare
+    pub target: Option<Target>,
+    #[serde(default)]
+    pub model: ModelConfig,
+    #[serde(default)]
+    pub budget: Budget,
+    #[serde(default)]
+    pub prover: ProverConfig,
+    #[serde(default)]
+    pub mutation: MutationConfig,
+    #[serde(default)]
+    pub build: BuildConfig,
+    #[serde(default)]
+    pub templates: TemplatesConfig,
+    #[serde(default)]
+    pub clean: CleanConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+}
+
+impl Config {
+    /// Reads the configuration of the project in `root`, if it has one, with
+    /// the overrides of the environment.
+    pub fn load(root: &Path) -> Result<Config, ConfigError> {
+        Config::load_with_env(root, std::env::vars())
+    }
+
+    /// Reads the configuration of the project in `root` with the overrides
+    /// among `vars`.
+    pub fn load_with_env(
+        root: &Path,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Config, ConfigError> {
+        let path = root.join(CONFIG_TOML);
+        let mut table = match std::fs::read_to_string(&path) {
+            Ok(src) => toml::from_str::<toml::Table>(&src).map_err(|source| ConfigError::Toml {
+                path: path.clone(),
+                source,
+            })?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+            Err(source) => return Err(ConfigError::Io { path, source }),
+        };
+        // Checked before the overrides, so that its errors name the file
+        Config::deserialize(table.clone()).map_err(|source| ConfigError::Toml { path, source })?;
+        let mut overridden = false;
+        for (var, value) in vars {
+            let Some(key) = var.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let key: Vec<String> = key.split("__").map(str::to_lowercase).collect();
+            set(&mut table, &key, env_value(&value));
+            overridden = true;
+        }
+        if !overridden {
+            return Ok(Config::deserialize(table).expect("checked above"));
+        }
+        Config::deserialize(table).map_err(ConfigError::Env)
+    }
+
+    /// The directory of the examples.
+    pub fn examples_dir(&self) -> PathBuf {
+        self.output
+            .examples_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(EXAMPLES_DIR))
+    }
+
+    /// The metrics store.
+    pub fn metrics(&self) -> PathBuf {
+        self.output
+            .metrics
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(METRICS_JSONL))
+    }
+}
+
+/// Sets the value at the path `key` of `table`, replacing what is in the
+/// way of it.
+fn set(table: &mut toml::Table, key: &[String], value: toml::Value) {
+    match key {
+        [] => {}
+        [field] => {
+            table.insert(field.clone(), value);
+        }
+        [first, rest @ ..] => {
+            let entry = table
+                .entry(first.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if !entry.is_table() {
+                *entry = toml::Value::Table(toml::Table::new());
+            }
+            if let toml::Value::Table(inner) = entry {
+                set(inner, rest, value);
+            }
+        }
+    }
+}
+
+/// The TOML value of an environment variable, or the variable as a string.
+fn env_value(value: &str) -> toml::Value {
+    match toml::from_str::<toml::Table>(&format!("value = {value}")) {
+        Ok(mut table) => table.remove("value").expect("just parsed"),
+        Err(_) => toml::Value::String(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Config::load(dir.path()).unwrap(), Config::default());
+
+        std::fs::write(
+            dir.path().join(CONFIG_TOML),
+            "[budget]\nmax_tokens = 1_000\nmax_prover_jobs = 3\n\n[prover]\njobs = 4\ncache = false\nfast_tag = \"fast\"\n\
+             poll_interval_secs = 30\njob_timeout_secs = 0\ncloud_rules = [\"slow\"]\n\n\
+             [mutation]\nmin_score = 80\n\n[build]\nsandbox = \"podman\"\nclippy = false\n\n\
+             [clean]\nmax_age = \"7d\"\nmax_size = \"2G\"\n",
+        )
+        .unwrap();
+        let config = Config::load(dir.path()).unwrap();
+        assert_eq!(config.budget.max_tokens, Some(1000));
+        assert_eq!(config.budget.max_prover_jobs, Some(3));
+        assert_eq!(config.budget.max_iterations, None);
+        assert_eq!(config.prover.jobs, Some(4));
+        assert_eq!(config.prover.cache, Some(false));
+        assert_eq!(config.prover.fast_tag.as_deref(), Some("fast"));
+        assert_eq!(config.prover.poll_interval_secs, Some(30));
+        assert_eq!(config.prover.job_timeout_secs, Some(0));
+        assert_eq!(config.prover.cloud_rules, ["slow"]);
+        assert_eq!(config.prover.cloud_key, None);
+        assert_eq!(config.mutation.min_score, Some(80));
+        assert!(matches!(
+            config.build.sandbox,
+            Some(Sandbox::Container { ref runtime, .. }) if runtime == "podman"
+        ));
+        assert_eq!(config.build.clippy, Some(false));
+        assert_eq!(
+            config.clean.max_age,
+            Some(Age(std::time::Duration::from_secs(7 * 24 * 3600)))
+        );
+        assert_eq!(config.clean.max_size, Some(Size(2 << 30)));
+
+        assert_eq!(config.examples_dir(), PathBuf::from("examples"));
+        assert_eq!(config.metrics(), PathBuf::from(METRICS_JSONL));
+
+        std::fs::write(dir.path().join(CONFIG_TOML), "[budget]\nmax_cost = 1\n").unwrap();
+        let err = Config::load(dir.path()).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `max_cost`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(CONFIG_TOML),
+            "target = \"anchor\"\n\n[model]\nprovider = \"openai\"\nname = \"gpt-4o\"\nmax_retries = 2\n\n\
+             [templates]\ndir = \"prompts\"\n\n[output]\nexamples_dir = \"programs\"\nlicense = \"SPDX-License-Identifier: MIT\"\n",
+        )
+        .unwrap();
+        let var = |key: &str, value: &str| (key.to_string(), value.to_string());
+        let config = Config::load_with_env(
+            dir.path(),
+            [
+                var("SPEC_CODER_TARGET", "svm"),
+                var("SPEC_CODER_BUDGET__MAX_TOKENS", "500000"),
+                var("SPEC_CODER_PROVER__CACHE", "false"),
+                var("SPEC_CODER_PROVER__CLOUD_KEY", "secret"),
+                var("SPEC_CODER_BUILD__SANDBOX", "none"),
+                var("SPEC_CODER_OUTPUT__METRICS", "runs.jsonl"),
+                var("HOME", "/root"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.target, Some(Target::Svm));
+        assert_eq!(config.model.provider, Some(ProviderKind::OpenAi));
+        assert_eq!(config.model.name.as_deref(), Some("gpt-4o"));
+        assert_eq!(config.model.max_retries, Some(2));
+        assert_eq!(config.budget.max_tokens, Some(500_000));
+        assert_eq!(config.prover.cache, Some(false));
+        assert_eq!(config.prover.cloud_key.as_deref(), Some("secret"));
+        assert_eq!(config.build.sandbox, Some(Sandbox::None));
+        assert_eq!(config.templates.dir, Some(PathBuf::from("prompts")));
+        assert_eq!(config.examples_dir(), PathBuf::from("programs"));
+        assert_eq!(
+            config.output.license.as_deref(),
+            Some("SPDX-License-Identifier: MIT")
+        );
+        assert_eq!(config.metrics(), PathBuf::from("runs.jsonl"));
+
+        let err = Config::load_with_env(dir.path(), [var("SPEC_CODER_BUDGET__MAX_TOKENS", "many")])
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::Env(_)), "{err}");
+    }
+}
@@ -0,0 +1,611 @@
+//! A local web dashboard of runs and results.
+//!
+//! `spec-coder serve` shows what the CLI records, read again on every
+//! request, so a team reviewing materialization runs together sees them as
+//! they go on. The index lists the examples of the [registry] with the
+//! results last recorded in each and the state of its session. The page of
+//! an example shows its session, the outcome of every rule, the call traces
+//! of the counterexamples in the [prover cache](crate::prover::cache) and,
+//! for every iteration, the diff of each file its response wrote, against
+//! what the earlier responses wrote, with the review of the change. The
+//! metrics page charts the tokens and wall time of every recorded run and
+//! aggregates the runs by model.
+//!
+//! The dashboard is behind the `dashboard` feature, on by default; building
+//! without it leaves out axum and tokio.
+
+use crate::driver::parse_edits;
+use crate::llm::Role;
+use crate::metrics::{self, GroupBy, MetricsError, RunMetrics, Stats};
+use crate::patch;
+use crate::prover::cache::Cache;
+use crate::registry::{Registry, RegistryError};
+use crate::results::overview::{Overview, REPORT_HTML};
+use crate::results::{self, xml_escape, Outcome, ResultsError};
+use crate::review::unified_diff;
+use crate::session::{Session, SessionError};
+use axum::extract::{Path as UrlPath, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use std::collections::BTreeMap;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// The default address of the dashboard.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:8080";
+
+/// An error rendering a page.
+#[derive(Debug, thiserror::Error)]
+pub enum DashboardError {
+    #[error("there is no example `{0}`")]
+    NotFound(String),
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
+    #[error(transparent)]
+    Results(#[from] ResultsError),
+    #[error(transparent)]
+    Session(#[from] SessionError),
+    #[error(transparent)]
+    Metrics(#[from] MetricsError),
+}
+
+/// Where the dashboard reads what the CLI records.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dashboard {
+    pub examples_dir: PathBuf,
+    /// The metrics store
+    pub metrics: PathBuf,
+}
+
+/// A file a response wrote.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    /// The change as a unified diff
+    pub diff: String,
+    /// Whether the change was approved, if it was reviewed
+    pub approved: Option<bool>,
+}
+
+/// What a response of a session changed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Iteration {
+    pub number: usize,
+    pub changes: Vec<FileChange>,
+}
+
+/// The changes of every iteration of `session`. A file is diffed against
+/// what an earlier response wrote to it, or shown whole if none did; a patch
+/// is shown as written, and applied for the diffs of later iterations.
+pub fn iterations(session: &Session) -> Vec<Iteration> {
+    let mut written: BTreeMap<PathBuf, String> = BTreeMap::new();
+    let responses = session
+        .messages
+        .iter()
+        .filter(|message| message.role == Role::Assistant);
+    let mut iterations = Vec::new();
+    for (number, response) in (1..).zip(responses) {
+        let mut changes = Vec::new();
+        for edit in parse_edits(&response.text()) {
+            let before = written.get(&edit.path).cloned();
+            let (diff, after) = match &before {
+                Some(before) if patch::is_patch(&edit.contents) => (
+                    edit.contents.clone(),
+                    patch::apply(before, &edit.contents).unwrap_or_else(|_| before.clone()),
+                ),
+                _ => (
+                    unified_diff(&edit.path, before.as_deref(), &edit.contents),
+                    edit.contents.clone(),
+                ),
+            };
+            let approved = session
+                .reviews
+                .iter()
+                .find(|review| review.iteration == number && review.path == edit.path)
+                .map(|review| review.approved);
+            if approved != Some(false) {
+                written.insert(edit.path.clone(), after);
+            }
+            changes.push(FileChange {
+                path: edit.path,
+                diff,
+                approved,
+            });
+        }
+        iterations.push(Iteration { number, changes });
+    }
+    iterations
+}
+
+/// The CSS of every page.
+const STYLE: &str = "
+      body { font-family: sans-serif; margin: 2em; }
+      nav a { margin-right: 1em; }
+      table { border-collapse: collapse; margin-bottom: 1em; }
+      th, td { padding: 0.3em 0.8em; border-bottom: 1px solid #ddd; text-align: left; }
+      pre { background: #f6f8fa; padding: 0.8em; overflow-x: auto; }
+      .pass, .add { color: #1a7f37; }
+      .fail, .del { color: #cf222e; }
+      .hunk { color: #8250df; }
+      .bar { fill: #0969da; }";
+
+/// A page with the navigation and `body`.
+fn layout(title: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <title>{title} - spec-coder</title>
+    <style>{STYLE}
+    </style>
+  </head>
+  <body>
+    <nav><a href="/">Examples</a><a href="/metrics">Metrics</a></nav>
+    <h1>{title}</h1>
+{body}  </body>
+</html>
+"#,
+        title = xml_escape(title),
+    )
+}
+
+fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Pass => "pass",
+        Outcome::Fail => "fail",
+        Outcome::Timeout => "timeout",
+        Outcome::Error => "error",
+        Outcome::NotRun => "not run",
+    }
+}
+
+/// `diff` with its added, removed and hunk lines marked.
+fn diff_html(diff: &str) -> String {
+    let mut html = String::from("<pre>");
+    for line in diff.lines() {
+        let class = match line.chars().next() {
+            _ if line.starts_with("+++") || line.starts_with("---") => None,
+            Some('+') => Some("add"),
+            Some('-') => Some("del"),
+            Some('@') => Some("hunk"),
+            _ => None,
+        };
+        match class {
+            Some(class) => html.push_str(&format!(
+                "<span class=\"{class}\">{}</span>\n",
+                xml_escape(line)
+            )),
+            None => html.push_str(&format!("{}\n", xml_escape(line))),
+        }
+    }
+    html.push_str("</pre>\n");
+    html
+}
+
+/// A bar chart of the `value` of each of `items` as inline SVG, with the
+/// `label` of each as the title of its bar.
+fn bar_chart<T>(items: &[T], value: impl Fn(&T) -> f64, label: impl Fn(&T) -> String) -> String {
+    const HEIGHT: f64 = 120.0;
+    const WIDTH: f64 = 24.0;
+    let max = items.iter().map(&value).fold(0.0, f64::max);
+    let mut bars = String::new();
+    for (i, item) in items.iter().enumerate() {
+        let height = match max > 0.0 {
+            true => value(item) / max * HEIGHT,
+            false => 0.0,
+        };
+        bars.push_str(&format!(
+            "<rect class=\"bar\" x=\"{:.0}\" y=\"{:.1}\" width=\"{:.0}\" height=\"{height:.1}\">\
+             <title>{}</title></rect>",
+            i as f64 * WIDTH,
+            HEIGHT - height,
+            WIDTH - 4.0,
+            xml_escape(&label(item)),
+        ));
+    }
+    format!(
+        "    <svg width=\"{:.0}\" height=\"{HEIGHT:.0}\" role=\"img\">{bars}</svg>\n",
+        (items.len() as f64 * WIDTH).max(WIDTH),
+    )
+}
+
+impl Dashboard {
+    /// The examples with their results and sessions.
+    pub fn index(&self) -> Result<String, DashboardError> {
+        let registry = Registry::scan(&self.examples_dir)?;
+        let overview = Overview::collect(&registry)?;
+        let mut rows = String::new();
+        for example in &overview.examples {
+            let session = match Session::find(&example.dir)? {
+                Some(session) if session.success => {
+                    format!("green after {} iterations", session.iterations)
+                }
+                Some(session) => format!("{} iterations, not green", session.iterations),
+                None => "none".to_string(),
+            };
+            let counts = &example.counts;
+            let class = match example.green() {
+                true => "pass",
+                false if example.recorded => "fail",
+                false => "",
+            };
+            rows.push_str(&format!(
+                "      <tr class=\"{class}\"><td><a href=\"/examples/{name}\">{name}</a></td><td>{}</td>\
+                 <td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{session}</td></tr>\n",
+                example.target,
+                counts.total(),
+                counts.pass,
+                counts.fail,
+                counts.timeout + counts.error,
+                counts.not_run,
+                example.status,
+                name = xml_escape(&example.name),
+            ));
+        }
+        Ok(layout(
+            "Examples",
+            &format!(
+                "    <p>{} of {} examples green; <code>report --all</code> writes this overview to <code>{REPORT_HTML}</code>.</p>\n    <table>\n      <tr><th>example</th><th>target</th><th>rules</th><th>pass</th><th>fail</th><th>timeout or error</th><th>not run</th><th>status</th><th>session</th></tr>\n{rows}    </table>\n",
+                overview.green(),
+                overview.examples.len(),
+            ),
+        ))
+    }
+
+    /// The session, rule outcomes, call traces and iterations of the
+    /// example `name`.
+    pub fn example(&self, name: &str) -> Result<String, DashboardError> {
+        let registry = Registry::scan(&self.examples_dir)?;
+        let example = registry
+            .get(name)
+            .ok_or_else(|| DashboardError::NotFound(name.to_string()))?;
+        let session = Session::find(&example.dir)?;
+        let mut body = format!(
+            "    <p>{} example in <code>{}</code>, {}.</p>\n",
+            example.target,
+            xml_escape(&example.dir.display().to_string()),
+            example.status,
+        );
+        if let Some(source) = &example.source {
+            body.push_str(&format!(
+                "    <p>Drafted from <a href=\"{source}\">{source}</a>.</p>\n",
+                source = xml_escape(source)
+            ));
+        }
+
+        body.push_str("    <h2>Session</h2>\n");
+        match &session {
+            Some(session) => {
+                body.push_str(&format!(
+                    "    <p>{} after {} iterations, {} input and {} output tokens, {} prover jobs.</p>\n",
+                    if session.success { "Green" } else { "Not green" },
+                    session.iterations,
+                    session.usage.input_tokens,
+                    session.usage.output_tokens,
+                    session.prover_jobs,
+                ));
+                if let Some(feedback) = &session.feedback {
+                    body.push_str(&format!(
+                        "    <h3>Last feedback</h3>\n    <pre>{}</pre>\n",
+                        xml_escape(feedback)
+                    ));
+                }
+            }
+            None => body.push_str("    <p>No session recorded.</p>\n"),
+        }
+
+        body.push_str("    <h2>Rules</h2>\n    <table>\n      <tr><th>rule</th><th>outcome</th><th>status</th><th>function</th></tr>\n");
+        let recorded = results::load(&example.dir)?;
+        for rule in &example.rules {
+            let recorded = recorded
+                .iter()
+                .flat_map(|results| &results.rules)
+                .find(|outcome| outcome.rule == *rule);
+            let status = recorded
+                .and_then(|outcome| outcome.status)
+                .or_else(|| session.as_ref()?.rules.get(rule).copied());
+            let outcome = recorded.map_or(Outcome::from(status), |outcome| outcome.outcome);
+            let function = recorded.and_then(|outcome| outcome.function.as_deref());
+            body.push_str(&format!(
+                "      <tr><td>{}</td><td class=\"{class}\">{class}</td><td>{}</td><td>{}</td></tr>\n",
+                xml_escape(rule),
+                status.map_or("-".to_string(), |status| status.to_string()),
+                function.map_or(String::new(), xml_escape),
+                class = outcome_name(outcome),
+            ));
+        }
+        body.push_str("    </table>\n");
+
+        let cache = Cache::load(&example.dir);
+        let traces: Vec<_> = cache
+            .results()
+            .filter(|result| example.rules.contains(&result.rule))
+            .filter_map(|result| Some((&result.rule, result.counterexample.as_ref()?)))
+            .collect();
+        if !traces.is_empty() {
+            body.push_str("    <h2>Call traces</h2>\n");
+            for (rule, counterexample) in traces {
+                body.push_str(&format!(
+                    "    <h3>{}</h3>\n    <pre>{}</pre>\n",
+                    xml_escape(rule),
+                    xml_escape(&counterexample.to_string())
+                ));
+            }
+        }
+
+        if let Some(session) = &session {
+            body.push_str("    <h2>Iterations</h2>\n");
+            for iteration in iterations(session) {
+                body.push_str(&format!("    <h3>Iteration {}</h3>\n", iteration.number));
+                if iteration.changes.is_empty() {
+                    body.push_str("    <p>No files written.</p>\n");
+                }
+                for change in &iteration.changes {
+                    let review = match change.approved {
+                        Some(true) => " (approved)",
+                        Some(false) => " (declined)",
+                        None => "",
+                    };
+                    body.push_str(&format!(
+                        "    <h4><code>{}</code>{review}</h4>\n    {}",
+                        xml_escape(&change.path.display().to_string()),
+                        diff_html(&change.diff),
+                    ));
+                }
+            }
+        }
+        Ok(layout(name, &body))
+    }
+
+    /// The recorded runs, charted and aggregated by model.
+    pub fn metrics(&self) -> Result<String, DashboardError> {
+        let runs = metrics::load(&self.metrics)?;
+        if runs.is_empty() {
+            return Ok(layout(
+                "Metrics",
+                &format!(
+                    "    <p>No runs recorded in <code>{}</code>.</p>\n",
+                    xml_escape(&self.metrics.display().to_string())
+                ),
+            ));
+        }
+        let label = |run: &RunMetrics| {
+            format!(
+                "{} with {} at {}: {}",
+                run.spec,
+                run.model,
+                run.started,
+                if run.success { "green" } else { "not green" }
+            )
+        };
+        let mut body = format!("    <p>{} runs recorded.</p>\n", runs.len());
+        body.push_str("    <h2>Tokens per run</h2>\n");
+        body.push_str(&bar_chart(
+            &runs,
+            |run| (run.input_tokens + run.output_tokens) as f64,
+            label,
+        ));
+        body.push_str("    <h2>Wall time per run</h2>\n");
+        body.push_str(&bar_chart(&runs, |run| run.wall_time_secs, label));
+        body.push_str("    <h2>By model</h2>\n    <table>\n      <tr><th>model</th><th>runs</th><th>green</th><th>iterations to green</th><th>mean tokens</th><th>mean time</th></tr>\n");
+        for (model, group) in Stats::new(&runs, GroupBy::Model).groups {
+            body.push_str(&format!(
+                "      <tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.0}</td><td>{:.1}s</td></tr>\n",
+                xml_escape(&model),
+                group.runs,
+                group.successes,
+                group
+                    .iterations_to_green
+                    .map_or("-".to_string(), |iterations| format!("{iterations:.1}")),
+                group.tokens,
+                group.wall_time_secs,
+            ));
+        }
+        body.push_str("    </table>\n");
+        Ok(layout("Metrics", &body))
+    }
+}
+
+/// Renders a page off the async runtime, as reading the records blocks.
+async fn render(
+    render: impl FnOnce() -> Result<String, DashboardError> + Send + 'static,
+) -> Response {
+    match tokio::task::spawn_blocking(render).await {
+        Ok(Ok(page)) => Html(page).into_response(),
+        Ok(Err(err @ DashboardError::NotFound(_))) => {
+            let page = layout(
+                "Not found",
+                &format!("    <p>{}</p>\n", xml_escape(&err.to_string())),
+            );
+            (StatusCode::NOT_FOUND, Html(page)).into_response()
+        }
+        Ok(Err(err)) => {
+            let page = layout(
+                "Error",
+                &format!("    <pre>{}</pre>\n", xml_escape(&err.to_string())),
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Html(page)).into_response()
+        }
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+/// The routes of the dashboard.
+pub fn router(dashboard: Dashboard) -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(|State(dashboard): State<Arc<Dashboard>>| render(move || dashboard.index())),
+        )
+        .route(
+            "/examples/{name}",
+            get(
+                |State(dashboard): State<Arc<Dashboard>>, UrlPath(name): UrlPath<String>| {
+                    render(move || dashboard.example(&name))
+                },
+            ),
+        )
+        .route(
+            "/metrics",
+            get(|State(dashboard): State<Arc<Dashboard>>| render(move || dashboard.metrics())),
+        )
+        .with_state(Arc::new(dashboard))
+}
+
+/// Serves the dashboard on `listener` until the process is stopped.
+pub fn serve(listener: TcpListener, dashboard: Dashboard) -> std::io::Result<()> {
+    listener.set_nonblocking(true)?;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        axum::serve(listener, router(dashboard)).await
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{Message, Usage};
+    use crate::metrics::Failures;
+    use crate::review::Review;
+    use crate::scaffold;
+    use spec::{Spec, Target};
+
+    fn session(spec: &Spec) -> Session {
+        let mut session = Session::new(spec, None, "implement".to_string()).unwrap();
+        session.messages.push(Message::assistant(
+            "```rust src/processor.rs\nfn add() {}\n```\n```toml Cargo.toml\n[package]\n```\n",
+        ));
+        session
+            .messages
+            .push(Message::user("rule_add_sanity is violated"));
+        session.messages.push(Message::assistant(
+            "```rust src/processor.rs\nfn add() { check(); }\n```\n",
+        ));
+        session.iterations = 2;
+        session.usage = Usage {
+            input_tokens: 1200,
+            output_tokens: 300,
+        };
+        for (iteration, path, approved) in [
+            (1, "src/processor.rs", true),
+            (1, "Cargo.toml", false),
+            (2, "src/processor.rs", true),
+        ] {
+            session.reviews.push(Review {
+                iteration,
+                path: PathBuf::from(path),
+                approved,
+                reviewer: "user".to_string(),
+            });
+        }
+        session
+    }
+
+    #[test]
+    fn test_iterations() {
+        let spec =
+            Spec::from_yaml_str(&scaffold::spec_template("counter", Target::Svm).unwrap()).unwrap();
+        let iterations = iterations(&session(&spec));
+        assert_eq!(iterations.len(), 2);
+        let first = &iterations[0].changes;
+        assert_eq!(first[0].path, PathBuf::from("src/processor.rs"));
+        assert!(first[0].diff.contains("+fn add() {}"));
+        assert_eq!(first[1].approved, Some(false));
+        let second = &iterations[1];
+        assert_eq!(second.number, 2);
+        assert!(second.changes[0]
+            .diff
+            .contains("-fn add() {}\n+fn add() { check(); }"));
+        assert_eq!(second.changes[0].approved, Some(true));
+    }
+
+    #[test]
+    fn test_pages() {
+        let examples = tempfile::tempdir().unwrap();
+        let created =
+            scaffold::new_example("counter", Target::Svm, examples.path(), None, false).unwrap();
+        let spec = Spec::from_path(&created.spec).unwrap();
+        let mut session = session(&spec);
+        session.feedback = Some("rule_add_sanity <violated>".to_string());
+        session.save(&created.dir).unwrap();
+        let dashboard = Dashboard {
+            examples_dir: examples.path().to_path_buf(),
+            metrics: examples.path().join("metrics.jsonl"),
+        };
+
+        let index = dashboard.index().unwrap();
+        assert!(index.contains("<a href=\"/examples/counter\">counter</a>"));
+        assert!(index.contains("2 iterations, not green"));
+
+        let page = dashboard.example("counter").unwrap();
+        assert!(page.contains("Not green after 2 iterations, 1200 input and 300 output tokens"));
+        assert!(page.contains("rule_add_sanity &lt;violated&gt;"));
+        assert!(page.contains("<td>rule_add_sanity</td><td class=\"not run\">not run</td>"));
+        assert!(page.contains("<h3>Iteration 2</h3>"));
+        assert!(page.contains("<h4><code>Cargo.toml</code> (declined)</h4>"));
+        assert!(page.contains("<span class=\"add\">+fn add() { check(); }</span>"));
+        assert!(matches!(
+            dashboard.example("vault"),
+            Err(DashboardError::NotFound(_))
+        ));
+
+        assert!(dashboard.metrics().unwrap().contains("No runs recorded"));
+        let run = RunMetrics {
+            started: 1_760_000_000,
+            spec: "counter".to_string(),
+            format: "yaml".to_string(),
+            target: "svm".to_string(),
+            provider: "anthropic".to_string(),
+            model: "model-a".to_string(),
+            prompts: "builtin".to_string(),
+            success: true,
+            iterations: 2,
+            failures: Failures::default(),
+            input_tokens: 1200,
+            output_tokens: 300,
+            prover_jobs: 4,
+            wall_time_secs: 42.0,
+            exhausted: None,
+        };
+        metrics::record(&dashboard.metrics, &run).unwrap();
+        let page = dashboard.metrics().unwrap();
+        assert!(page.contains("<title>counter with model-a at 1760000000: green</title>"));
+        assert!(page.contains("<td>model-a</td><td>1</td><td>1</td><td>2.0</td><td>1500</td>"));
+    }
+
+    #[test]
+    fn test_serve() {
+        let examples = tempfile::tempdir().unwrap();
+        let dashboard = Dashboard {
+            examples_dir: examples.path().to_path_buf(),
+            metrics: examples.path().join("metrics.jsonl"),
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || serve(listener, dashboard));
+
+        let index = ureq::get(&format!("{url}/")).call().unwrap();
+        assert_eq!(index.status(), 200);
+        assert!(index
+            .into_string()
+            .unwrap()
+            .contains("0 of 0 examples green"));
+        match ureq::get(&format!("{url}/examples/vault")).call() {
+            Err(ureq::Error::Status(status, response)) => {
+                assert_eq!(status, 404);
+                assert!(response
+                    .into_string()
+                    .unwrap()
+                    .contains("no example `vault`"));
+            }
+            other => panic!("expected a 404, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Deployment artifacts of verified Solana programs.
+//!
+//! Once a run succeeds, the crate can be readied for deployment in
+//! `deploy/`: the shared object `cargo build-sbf` builds, the keypair of
+//! the program, and a small crate deploying the program over RPC, so that a
+//! verified example is pushed to devnet with
+//! `cargo run --manifest-path deploy/Cargo.toml`. The ids the examples are
+//! generated with are derived from their names and have no keypair, so the
+//! program gets a keypair of its own the first time, kept in `deploy/` for
+//! later upgrades, and `declare_id!` in `src/lib.rs` is set to its id.
+
+use crate::materialize::svm::{package_name, Framework};
+use crate::materialize::GeneratedFile;
+use crate::sandbox::Sandbox;
+use spec::{Spec, Target};
+use std::path::{Path, PathBuf};
+
+/// The deployment artifacts, relative to the crate root.
+pub const DEPLOY_DIR: &str = "deploy";
+
+const CARGO_TOML: &str = include_str!("../assets/svm/deploy/Cargo.toml.j2");
+const MAIN_RS: &str = include_str!("../assets/svm/deploy/main.rs.j2");
+
+/// The source of the program id in the crate.
+const LIB_RS: &str = "src/lib.rs";
+
+/// An error emitting deployment artifacts.
+#[derive(Debug, thiserror::Error)]
+pub enum DeployError {
+    #[error("{0} programs are not deployed to Solana")]
+    UnsupportedTarget(Target),
+    #[error("`cargo build-sbf` failed:\n{0}")]
+    Build(String),
+    #[error("{} is not a Solana keypair", .0.display())]
+    Keypair(PathBuf),
+    #[error("{} has no `declare_id!`", .0.display())]
+    NoProgramId(PathBuf),
+    #[error("failed to access {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// Where the artifacts of a deployment were written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Artifacts {
+    /// The shared object of the program
+    pub program: PathBuf,
+    /// The keypair whose public key is the program id
+    pub keypair: PathBuf,
+    pub program_id: String,
+    /// The manifest of the crate deploying the program
+    pub helper: PathBuf,
+}
+
+/// Whether programs of `target` can be deployed.
+pub fn supported(target: Target) -> bool {
+    matches!(target, Target::Svm | Target::Anchor | Target::Pinocchio)
+}
+
+/// The public key of the keypair in `json`, as the Solana CLI writes it: the
+/// 32 bytes of the secret key followed by the 32 of the public key.
+pub fn keypair_pubkey(json: &str) -> Option<String> {
+    let bytes: Vec<u8> = serde_json::from_str(json).ok()?;
+    (bytes.len() == 64).then(|| bs58::encode(&bytes[32..]).into_string())
+}
+
+/// `lib` with the id of its `declare_id!` replaced by `id`, or `None` if it
+/// declares no id.
+pub fn set_program_id(lib: &str, id: &str) -> Option<String> {
+    let start = lib.find("declare_id!(\"")? + "declare_id!(\"".len();
+    let end = start + lib[start..].find('"')?;
+    Some(format!("{}{id}{}", &lib[..start], &lib[end..]))
+}
+
+/// The crate deploying the program, relative to the crate root. The
+/// keypair and the shared object are left out of version control.
+pub fn helper_files(spec: &Spec, target: Target) -> Vec<GeneratedFile> {
+    let package = package_name(spec, Framework::of(target));
+    let program = package.replace('-', "_");
+    vec![
+        GeneratedFile::new(
+            &format!("{DEPLOY_DIR}/Cargo.toml"),
+            minijinja::render!(CARGO_TOML, package) + "\n",
+        ),
+        GeneratedFile::new(
+            &format!("{DEPLOY_DIR}/src/main.rs"),
+            minijinja::render!(MAIN_RS, program) + "\n",
+        ),
+        GeneratedFile::new(
+            &format!("{DEPLOY_DIR}/.gitignore"),
+            "target\n*.so\n*-keypair.json\n",
+        ),
+    ]
+}
+
+fn io(path: &Path) -> impl FnOnce(std::io::Error) -> DeployError + '_ {
+    move |source| DeployError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+fn build(sandbox: &Sandbox, dir: &Path) -> Result<(), DeployError> {
+    let output = sandbox.run(dir, &["build-sbf"]).map_err(io(dir))?;
+    if !output.success {
+        return Err(DeployError::Build(output.output));
+    }
+    Ok(())
+}
+
+/// Builds the program of the crate in `dir` with `cargo build-sbf` and
+/// writes the deployment artifacts to its `deploy/`, setting the program
+/// id to the one of the keypair there.
+pub fn emit(
+    spec: &Spec,
+    target: Target,
+    dir: &Path,
+    sandbox: &Sandbox,
+) -> Result<Artifacts, DeployError> {
+    if !supported(target) {
+        return Err(DeployError::UnsupportedTarget(target));
+    }
+    let program = package_name(spec, Framework::of(target)).replace('-', "_");
+    let deploy = dir.join(DEPLOY_DIR);
+    std::fs::create_dir_all(&deploy).map_err(io(&deploy))?;
+    // Where `cargo build-sbf` writes the shared object, and a keypair
+    // unless there is one already
+    let out = sandbox.target_dir(dir).join("deploy");
+    std::fs::create_dir_all(&out).map_err(io(&out))?;
+    let keypair_file = format!("{program}-keypair.json");
+    let keypair = deploy.join(&keypair_file);
+    if keypair.exists() {
+        std::fs::copy(&keypair, out.join(&keypair_file)).map_err(io(&keypair))?;
+    }
+    build(sandbox, dir)?;
+    if !keypair.exists() {
+        std::fs::copy(out.join(&keypair_file), &keypair).map_err(io(&keypair))?;
+    }
+
+    let json = std::fs::read_to_string(&keypair).map_err(io(&keypair))?;
+    let program_id = keypair_pubkey(&json).ok_or_else(|| DeployError::Keypair(keypair.clone()))?;
+    let lib_path = dir.join(LIB_RS);
+    let lib = std::fs::read_to_string(&lib_path).map_err(io(&lib_path))?;
+    let updated = set_program_id(&lib, &program_id)
+        .ok_or_else(|| DeployError::NoProgramId(lib_path.clone()))?;
+    if updated != lib {
+        std::fs::write(&lib_path, updated).map_err(io(&lib_path))?;
+        build(sandbox, dir)?;
+    }
+
+    let so = deploy.join(format!("{program}.so"));
+    std::fs::copy(out.join(format!("{program}.so")), &so).map_err(io(&so))?;
+    for file in helper_files(spec, target) {
+        let path = dir.join(&file.path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io(parent))?;
+        }
+        std::fs::write(&path, &file.contents).map_err(io(&path))?;
+    }
+    Ok(Artifacts {
+        program: so,
+        keypair,
+        program_id,
+        helper: deploy.join("Cargo.toml"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_keypair_pubkey() {
+        let mut bytes = vec![7u8; 32];
+        bytes.extend([0u8; 31]);
+        bytes.push(1);
+        let json = serde_json::to_string(&bytes).unwrap();
+        assert_eq!(
+            keypair_pubkey(&json).as_deref(),
+            Some("11111111111111111111111111111112")
+        );
+        assert_eq!(keypair_pubkey("[1, 2, 3]"), None);
+        assert_eq!(keypair_pubkey("not json"), None);
+    }
+
+    #[test]
+    fn test_set_program_id() {
+        let lib = "use solana_program::declare_id;\n\ndeclare_id!(\"old\");\n\nfn f() {}\n";
+        assert_eq!(
+            set_program_id(lib, "new").unwrap(),
+            "use solana_program::declare_id;\n\ndeclare_id!(\"new\");\n\nfn f() {}\n"
+        );
+        assert_eq!(
+            set_program_id("pinocchio_pubkey::declare_id!(\"old\");\n", "new").unwrap(),
+            "pinocchio_pubkey::declare_id!(\"new\");\n"
+        );
+        assert_eq!(set_program_id("fn f() {}\n", "new"), None);
+    }
+
+    #[test]
+    fn test_helper_files() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let files = helper_files(&spec, Target::Anchor);
+        let paths: Vec<_> = files.iter().map(|f| f.path.to_str().unwrap()).collect();
+        assert_eq!(
+            paths,
+            [
+                "deploy/Cargo.toml",
+                "deploy/src/main.rs",
+                "deploy/.gitignore"
+            ]
+        );
+        assert!(files[0]
+            .contents
+            .contains("name = \"anchor-vault-deploy\"\n"));
+        assert!(files[1]
+            .contents
+            .contains("const PROGRAM: &str = \"anchor_vault\";\n"));
+        assert!(files[2].contents.contains("*-keypair.json"));
+        assert!(!supported(Target::Rust));
+        let err = emit(&spec, Target::Evm, Path::new("."), &Sandbox::None).unwrap_err();
+        assert!(matches!(err, DeployError::UnsupportedTarget(Target::Evm)));
+    }
+}
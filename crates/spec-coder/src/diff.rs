@@ -0,0 +1,689 @@
+//! Semantic differences between two versions of a spec.
+//!
+//! Every clause that changes is classed by what it means for a crate
+//! implementing the old spec, see [`Class`]. Conditions and invariants are
+//! compared conjunct by conjunct, so that tightening `a > 0` to
+//! `a > 0 && a <= cap` is one added conjunct rather than a rewrite;
+//! descriptions are left out, as they do not change the code. The diff also
+//! predicts what regenerating the crate takes: the generated files that
+//! change, the functions [`incremental`](crate::incremental) scaffolds again
+//! and the rules it proves again.
+
+use crate::drift;
+use crate::incremental::Graph;
+use crate::materialize::{self, svm, MaterializeError};
+use spec::{BinaryOp, Expr, Spec, Target};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::PathBuf;
+
+/// What a change means for a crate implementing the old spec, from the
+/// least to the most disruptive.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Class {
+    /// Something new, such as a function or an invariant, that leaves what
+    /// exists as it is
+    Additive,
+    /// The spec asks less: what verified before still verifies
+    Weakening,
+    /// The spec asks more: the implementation may have to change
+    Strengthening,
+    /// The interface or the state layout changes: clients and accounts
+    /// written against the old spec no longer work
+    Breaking,
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Class::Additive => "additive",
+            Class::Weakening => "weakening",
+            Class::Strengthening => "strengthening",
+            Class::Breaking => "breaking",
+        })
+    }
+}
+
+/// A change of a clause.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub class: Class,
+    /// Where the clause is, e.g. `functions.deposit.requires` or
+    /// `state.Vault`
+    pub location: String,
+    pub message: String,
+}
+
+impl Change {
+    fn new(class: Class, location: impl Into<String>, message: impl Into<String>) -> Change {
+        Change {
+            class,
+            location: location.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.class, self.location, self.message)
+    }
+}
+
+/// How a generated file changes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FileChange {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl fmt::Display for FileChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FileChange::Added => "added",
+            FileChange::Removed => "removed",
+            FileChange::Modified => "modified",
+        })
+    }
+}
+
+/// What regenerating a crate from the old spec for the new one takes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Regeneration {
+    /// The generated files that change, relative to the crate root
+    pub files: BTreeMap<PathBuf, FileChange>,
+    /// Functions scaffolded again and implemented anew
+    pub functions: BTreeSet<String>,
+    /// Rules proved again, new ones included
+    pub rules: BTreeSet<String>,
+    /// Rules of the old spec the new one no longer has
+    pub removed_rules: BTreeSet<String>,
+}
+
+/// The differences between two versions of a spec.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpecDiff {
+    /// The target the regeneration is predicted for
+    pub target: Target,
+    pub changes: Vec<Change>,
+    pub regeneration: Regeneration,
+}
+
+impl SpecDiff {
+    /// The most disruptive class of the changes, if anything changed.
+    pub fn class(&self) -> Option<Class> {
+        self.changes.iter().map(|change| change.class).max()
+    }
+}
+
+impl fmt::Display for SpecDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        match self.class() {
+            Some(class) => writeln!(f, "{} changes, {class} overall", self.changes.len())?,
+            None => writeln!(f, "No changes to the clauses")?,
+        }
+        let regeneration = &self.regeneration;
+        if !regeneration.files.is_empty() {
+            writeln!(f, "Generated files for {}:", self.target)?;
+            for (path, change) in &regeneration.files {
+                writeln!(f, "  {change} {}", path.display())?;
+            }
+        }
+        let list = |names: &BTreeSet<String>| names.iter().cloned().collect::<Vec<_>>().join(", ");
+        if !regeneration.functions.is_empty() {
+            writeln!(
+                f,
+                "Functions to implement again: {}",
+                list(&regeneration.functions)
+            )?;
+        }
+        if !regeneration.rules.is_empty() {
+            writeln!(f, "Rules to prove again: {}", list(&regeneration.rules))?;
+        }
+        if !regeneration.removed_rules.is_empty() {
+            writeln!(f, "Rules removed: {}", list(&regeneration.removed_rules))?;
+        }
+        Ok(())
+    }
+}
+
+/// The conjuncts of `expr`, written out.
+fn conjuncts(expr: &Expr, out: &mut Vec<String>) {
+    match expr {
+        Expr::Binary(BinaryOp::And, lhs, rhs) => {
+            conjuncts(lhs, out);
+            conjuncts(rhs, out);
+        }
+        expr => out.push(expr.to_string()),
+    }
+}
+
+/// The conjuncts of `exprs`, without duplicates, in order.
+fn all_conjuncts<'a>(exprs: impl IntoIterator<Item = &'a Expr>) -> Vec<String> {
+    let mut out = Vec::new();
+    for expr in exprs {
+        conjuncts(expr, &mut out);
+    }
+    let mut seen = BTreeSet::new();
+    out.retain(|conjunct| seen.insert(conjunct.clone()));
+    out
+}
+
+/// Pushes a change for each conjunct only one side has: `added` for those
+/// of `new`, `removed` for those of `old`.
+fn diff_conjuncts(
+    changes: &mut Vec<Change>,
+    location: &str,
+    old: &[String],
+    new: &[String],
+    added: (Class, &str),
+    removed: (Class, &str),
+) {
+    for conjunct in new.iter().filter(|conjunct| !old.contains(conjunct)) {
+        changes.push(Change::new(
+            added.0,
+            location,
+            format!("{} `{conjunct}`", added.1),
+        ));
+    }
+    for conjunct in old.iter().filter(|conjunct| !new.contains(conjunct)) {
+        changes.push(Change::new(
+            removed.0,
+            location,
+            format!("{} `{conjunct}`", removed.1),
+        ));
+    }
+}
+
+/// A list of named, typed items, e.g. `amount: u64, shares: u64`.
+fn typed<T>(items: &[T], item: impl Fn(&T) -> String) -> String {
+    items.iter().map(item).collect::<Vec<_>>().join(", ")
+}
+
+/// A limit that is stricter when `lower` and lower or when higher
+/// otherwise: the class of changing it from `old` to `new`.
+fn limit_class(old: Option<u64>, new: Option<u64>, lower: bool) -> Option<Class> {
+    match (old, new) {
+        (None, Some(_)) => Some(Class::Strengthening),
+        (Some(_), None) => Some(Class::Weakening),
+        (Some(old), Some(new)) if old != new => Some(if (new < old) == lower {
+            Class::Strengthening
+        } else {
+            Class::Weakening
+        }),
+        _ => None,
+    }
+}
+
+fn diff_functions(old: &Spec, new: &Spec, changes: &mut Vec<Change>) {
+    for function in &new.functions {
+        let location = format!("functions.{}", function.name);
+        let Some(before) = old.function(&function.name) else {
+            changes.push(Change::new(Class::Additive, location, "new function"));
+            continue;
+        };
+        let accounts = |function: &spec::Function| {
+            typed(&function.accounts, |account| {
+                let mut out = account.name.clone();
+                if let Some(ty) = &account.ty {
+                    out.push_str(&format!(": {ty}"));
+                }
+                if account.signer {
+                    out.push_str(" signer");
+                }
+                if account.writable {
+                    out.push_str(" writable");
+                }
+                out
+            })
+        };
+        if accounts(before) != accounts(function) {
+            changes.push(Change::new(
+                Class::Breaking,
+                format!("{location}.accounts"),
+                format!(
+                    "accounts change from ({}) to ({})",
+                    accounts(before),
+                    accounts(function)
+                ),
+            ));
+        }
+        let args = |function: &spec::Function| {
+            typed(&function.args, |arg| format!("{}: {}", arg.name, arg.ty))
+        };
+        if args(before) != args(function) {
+            changes.push(Change::new(
+                Class::Breaking,
+                format!("{location}.args"),
+                format!(
+                    "arguments change from ({}) to ({})",
+                    args(before),
+                    args(function)
+                ),
+            ));
+        }
+        let exprs = |conditions: &[spec::Condition]| {
+            all_conjuncts(conditions.iter().map(|condition| &condition.expr))
+        };
+        // A function must fail when a precondition does not hold, and it
+        // may always fail where it did before
+        diff_conjuncts(
+            changes,
+            &format!("{location}.requires"),
+            &exprs(&before.requires),
+            &exprs(&function.requires),
+            (Class::Strengthening, "requires"),
+            (Class::Weakening, "no longer requires"),
+        );
+        diff_conjuncts(
+            changes,
+            &format!("{location}.ensures"),
+            &exprs(&before.ensures),
+            &exprs(&function.ensures),
+            (Class::Strengthening, "ensures"),
+            (Class::Weakening, "no longer ensures"),
+        );
+        for example in &function.examples {
+            let location = format!("{location}.examples.{}", example.name);
+            let without_description = |example: &spec::Example| spec::Example {
+                description: None,
+                ..example.clone()
+            };
+            match before.examples.iter().find(|e| e.name == example.name) {
+                None => changes.push(Change::new(Class::Additive, location, "new example")),
+                Some(old) if without_description(old) != without_description(example) => changes
+                    .push(Change::new(
+                        Class::Strengthening,
+                        location,
+                        "the example changes",
+                    )),
+                Some(_) => {}
+            }
+        }
+        for example in &before.examples {
+            if !function.examples.iter().any(|e| e.name == example.name) {
+                changes.push(Change::new(
+                    Class::Weakening,
+                    format!("{location}.examples.{}", example.name),
+                    "the example is removed",
+                ));
+            }
+        }
+        if let Some(class) = limit_class(before.compute_units, function.compute_units, true) {
+            let message = match function.compute_units {
+                Some(units) => format!("limited to {units} compute units"),
+                None => "no longer limited in compute units".to_string(),
+            };
+            changes.push(Change::new(
+                class,
+                format!("{location}.compute_units"),
+                message,
+            ));
+        }
+    }
+    for function in &old.functions {
+        if new.function(&function.name).is_none() {
+            changes.push(Change::new(
+                Class::Breaking,
+                format!("functions.{}", function.name),
+                "the function is removed",
+            ));
+        }
+    }
+}
+
+fn diff_state(old: &Spec, new: &Spec, changes: &mut Vec<Change>) {
+    let layout = |account: &spec::Account| {
+        typed(&account.fields, |field| {
+            format!("{}: {}", field.name, field.ty)
+        })
+    };
+    for account in &new.state {
+        let location = format!("state.{}", account.name);
+        match old.account(&account.name) {
+            None => changes.push(Change::new(Class::Additive, location, "new account type")),
+            Some(before) if layout(before) != layout(account) => changes.push(Change::new(
+                Class::Breaking,
+                location,
+                format!(
+                    "layout changes from ({}) to ({})",
+                    layout(before),
+                    layout(account)
+                ),
+            )),
+            Some(_) => {}
+        }
+    }
+    for account in &old.state {
+        if new.account(&account.name).is_none() {
+            changes.push(Change::new(
+                Class::Breaking,
+                format!("state.{}", account.name),
+                "the account type is removed",
+            ));
+        }
+    }
+}
+
+fn diff_invariants(old: &Spec, new: &Spec, changes: &mut Vec<Change>) {
+    let find = |spec: &'_ Spec, name: &str| {
+        spec.invariants
+            .iter()
+            .find(|invariant| invariant.name == name)
+            .map(|invariant| all_conjuncts([&invariant.expr]))
+    };
+    for invariant in &new.invariants {
+        let location = format!("invariants.{}", invariant.name);
+        match find(old, &invariant.name) {
+            None => changes.push(Change::new(
+                Class::Additive,
+                location,
+                format!("new invariant `{}`", invariant.expr),
+            )),
+            Some(before) => diff_conjuncts(
+                changes,
+                &location,
+                &before,
+                &all_conjuncts([&invariant.expr]),
+                (Class::Strengthening, "also holds"),
+                (Class::Weakening, "no longer holds"),
+            ),
+        }
+    }
+    for invariant in &old.invariants {
+        if find(new, &invariant.name).is_none() {
+            changes.push(Change::new(
+                Class::Weakening,
+                format!("invariants.{}", invariant.name),
+                "the invariant is removed",
+            ));
+        }
+    }
+}
+
+fn diff_helpers(old: &Spec, new: &Spec, changes: &mut Vec<Change>) {
+    let signature = |helper: &spec::Helper| {
+        format!(
+            "({}) -> {}",
+            typed(&helper.args, |arg| format!("{}: {}", arg.name, arg.ty)),
+            helper.returns
+        )
+    };
+    for helper in &new.helpers {
+        let location = format!("helpers.{}", helper.name);
+        let Some(before) = old.helper(&helper.name) else {
+            changes.push(Change::new(Class::Additive, location, "new helper"));
+            continue;
+        };
+        if signature(before) != signature(helper) {
+            changes.push(Change::new(
+                Class::Breaking,
+                location.clone(),
+                format!(
+                    "signature changes from {} to {}",
+                    signature(before),
+                    signature(helper)
+                ),
+            ));
+        }
+        if let Some(class) = limit_class(before.throughput, helper.throughput, false) {
+            let message = match helper.throughput {
+                Some(calls) => format!("has to sustain {calls} calls per second"),
+                None => "no longer has a throughput to sustain".to_string(),
+            };
+            changes.push(Change::new(
+                class,
+                format!("{location}.throughput"),
+                message,
+            ));
+        }
+    }
+    for helper in &old.helpers {
+        if new.helper(&helper.name).is_none() {
+            changes.push(Change::new(
+                Class::Breaking,
+                format!("helpers.{}", helper.name),
+                "the helper is removed",
+            ));
+        }
+    }
+}
+
+/// The changes from `old` to `new`, classed: the program itself first, then
+/// its state, functions, invariants and helpers.
+pub fn changes(old: &Spec, new: &Spec) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if old.name != new.name {
+        changes.push(Change::new(
+            Class::Breaking,
+            "name",
+            format!(
+                "the program is renamed from `{}` to `{}`",
+                old.name, new.name
+            ),
+        ));
+    }
+    if old.target != new.target {
+        changes.push(Change::new(
+            Class::Breaking,
+            "target",
+            format!("the target changes from {} to {}", old.target, new.target),
+        ));
+    }
+    diff_state(old, new, &mut changes);
+    diff_functions(old, new, &mut changes);
+    diff_invariants(old, new, &mut changes);
+    diff_helpers(old, new, &mut changes);
+    changes
+}
+
+/// The rules compiled for `spec`, with the invariants each assumes or
+/// asserts.
+fn rules(spec: &Spec) -> BTreeMap<String, Vec<String>> {
+    spec.functions
+        .iter()
+        .flat_map(|function| svm::function_rules(spec, function))
+        .collect()
+}
+
+/// Predicts what regenerating a `target` crate of `old` for `new` takes.
+pub fn regeneration(
+    old: &Spec,
+    new: &Spec,
+    target: Target,
+) -> Result<Regeneration, MaterializeError> {
+    let before: BTreeMap<PathBuf, String> = materialize::generate(old, target)?
+        .into_iter()
+        .map(|file| (file.path, file.contents))
+        .collect();
+    let mut files = BTreeMap::new();
+    let mut after = BTreeSet::new();
+    for file in materialize::generate(new, target)? {
+        after.insert(file.path.clone());
+        match before.get(&file.path) {
+            None => {
+                files.insert(file.path, FileChange::Added);
+            }
+            Some(contents) if *contents != file.contents => {
+                files.insert(file.path, FileChange::Modified);
+            }
+            Some(_) => {}
+        }
+    }
+    for path in before.keys().filter(|path| !after.contains(*path)) {
+        files.insert(path.clone(), FileChange::Removed);
+    }
+
+    let old_clauses = drift::clause_fingerprints(old);
+    let new_clauses = drift::clause_fingerprints(new);
+    let changed: BTreeSet<String> = new_clauses
+        .iter()
+        .filter(|(clause, fingerprint)| old_clauses.get(*clause) != Some(fingerprint))
+        .map(|(clause, _)| clause.clone())
+        .collect();
+    let graph = Graph::new(new);
+    let functions = graph.functions(&changed);
+    let rules_before = rules(old);
+    let rules_after = rules(new);
+    let mut proved = graph.rules(&functions, &changed);
+    // New rules, and rules whose invariants changed, e.g. by the removal of
+    // one
+    proved.extend(
+        rules_after
+            .iter()
+            .filter(|(rule, invariants)| rules_before.get(*rule) != Some(*invariants))
+            .map(|(rule, _)| rule.clone()),
+    );
+    let removed_rules = rules_before
+        .into_keys()
+        .filter(|rule| !rules_after.contains_key(rule))
+        .collect();
+    Ok(Regeneration {
+        files,
+        functions,
+        rules: proved,
+        removed_rules,
+    })
+}
+
+/// Compares `old` with `new`, predicting the regeneration of a `target`
+/// crate.
+pub fn diff(old: &Spec, new: &Spec, target: Target) -> Result<SpecDiff, MaterializeError> {
+    Ok(SpecDiff {
+        target,
+        changes: changes(old, new),
+        regeneration: regeneration(old, new, target)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    fn edited(edit: impl FnOnce(&mut Spec)) -> (Spec, Spec) {
+        let old = Spec::from_yaml_str(VAULT).unwrap();
+        let mut new = old.clone();
+        edit(&mut new);
+        (old, new)
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let (old, new) = edited(|spec| {
+            spec.functions[0].description = Some("Reworded.".to_string());
+        });
+        let diff = diff(&old, &new, Target::Svm).unwrap();
+        assert_eq!(diff.class(), None);
+        let regeneration = &diff.regeneration;
+        assert!(regeneration.functions.is_empty() && regeneration.rules.is_empty());
+        // Descriptions only reach the documentation of the clients
+        assert_eq!(
+            regeneration.files.get(&PathBuf::from("idl.json")),
+            Some(&FileChange::Modified)
+        );
+        assert!(!regeneration
+            .files
+            .contains_key(&PathBuf::from("src/state.rs")));
+        assert!(diff.to_string().starts_with("No changes to the clauses\n"));
+
+        let same = super::diff(&old, &old, Target::Svm).unwrap();
+        assert_eq!(same.regeneration, Regeneration::default());
+        assert_eq!(same.to_string(), "No changes to the clauses\n");
+    }
+
+    #[test]
+    fn test_conditions() {
+        let (old, new) = edited(|spec| {
+            let withdraw = &mut spec.functions[1];
+            withdraw.requires[0].expr = "shares <= vault.shares_total && shares > 0"
+                .parse()
+                .unwrap();
+            spec.functions[2].ensures.pop();
+            spec.functions[2].compute_units = Some(5_000);
+        });
+        let diff = diff(&old, &new, Target::Svm).unwrap();
+        let changes: Vec<String> = diff.changes.iter().map(Change::to_string).collect();
+        assert_eq!(
+            changes,
+            [
+                "strengthening: functions.withdraw.requires: requires `shares > 0`",
+                "weakening: functions.reward.ensures: no longer ensures \
+                 `vault.shares_total == old(vault.shares_total)`",
+                "strengthening: functions.reward.compute_units: limited to 5000 compute units",
+            ]
+        );
+        assert_eq!(diff.class(), Some(Class::Strengthening));
+        let regeneration = &diff.regeneration;
+        assert_eq!(
+            regeneration.functions,
+            BTreeSet::from(["reward".to_string(), "withdraw".to_string()])
+        );
+        assert!(regeneration.rules.contains("rule_withdraw_sanity"));
+        assert!(regeneration.rules.contains("rule_reward_ensures"));
+        assert!(!regeneration
+            .rules
+            .iter()
+            .any(|rule| rule.contains("deposit")));
+        assert!(regeneration.removed_rules.is_empty());
+        assert_eq!(
+            regeneration.files.get(&PathBuf::from("src/processor.rs")),
+            Some(&FileChange::Modified)
+        );
+        assert!(!regeneration
+            .files
+            .contains_key(&PathBuf::from("src/state.rs")));
+    }
+
+    #[test]
+    fn test_additive_and_breaking() {
+        let (old, new) = edited(|spec| {
+            spec.invariants.push(spec::Invariant {
+                name: "backed".to_string(),
+                description: None,
+                expr: "vault.token_total >= 0".parse().unwrap(),
+            });
+            spec.state[0].fields.push(spec::Field {
+                name: "paused".to_string(),
+                ty: spec::Type::Bool,
+                description: None,
+            });
+            spec.functions.retain(|function| function.name != "reward");
+        });
+        let diff = diff(&old, &new, Target::Svm).unwrap();
+        let classes: Vec<(Class, &str)> = diff
+            .changes
+            .iter()
+            .map(|change| (change.class, change.location.as_str()))
+            .collect();
+        assert_eq!(
+            classes,
+            [
+                (Class::Breaking, "state.Vault"),
+                (Class::Breaking, "functions.reward"),
+                (Class::Additive, "invariants.backed"),
+            ]
+        );
+        assert!(diff.changes[0]
+            .message
+            .ends_with("token_total: u64, paused: bool)"));
+        assert_eq!(diff.class(), Some(Class::Breaking));
+        let regeneration = &diff.regeneration;
+        // Every function takes the vault, whose layout changed
+        assert_eq!(regeneration.functions.len(), 2);
+        assert!(regeneration.rules.contains("rule_deposit_backed"));
+        assert!(regeneration.removed_rules.contains("rule_reward_sanity"));
+        assert_eq!(
+            regeneration.files.get(&PathBuf::from("src/state.rs")),
+            Some(&FileChange::Modified)
+        );
+        let text = diff.to_string();
+        assert!(text.contains("3 changes, breaking overall\n"), "{text}");
+        assert!(text.contains("  modified src/state.rs\n"), "{text}");
+    }
+}
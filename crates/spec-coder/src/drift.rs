@@ -0,0 +1,474 @@
+//! Detection of drift between a spec and the code implementing it.
+//!
+//! Every generated processor function is preceded by a marker naming the
+//! function it implements and a fingerprint of its clauses:
+//!
+//! ```text
+//! // spec-coder: implements functions.deposit clauses=3f2a9c0d1e4b5a67
+//! ```
+//!
+//! When a crate passes verification, the fingerprints of the clauses and of
+//! each function's source are recorded in [`VERIFIED_JSON`]. A function has
+//! drifted when its marker is gone, when the spec no longer matches the
+//! clauses it claims, or when its source differs from the verified one.
+//! Generated files are also stale when the [`provenance`](crate::provenance)
+//! stamp they start with names another spec than the current one.
+
+use crate::provenance::{self, Provenance};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use spec::{Function, Spec};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The record of the last verification, relative to the crate root.
+pub const VERIFIED_JSON: &str = ".spec-coder/verified.json";
+
+/// The source file holding the processor functions.
+pub(crate) const PROCESSOR_RS: &str = "src/processor.rs";
+
+/// The source file holding the state types.
+const STATE_RS: &str = "src/state.rs";
+
+const MARKER: &str = "// spec-coder: implements functions.";
+
+/// An error checking for drift.
+#[derive(Debug, thiserror::Error)]
+pub enum DriftError {
+    #[error("failed to access {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("malformed {}: {source}", .path.display())]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+/// A short hex fingerprint of `bytes`.
+pub(crate) fn fingerprint(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    digest[..8].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// The fingerprint of the clauses of `function`: its accounts, arguments,
+/// pre- and postconditions and examples, but not its description or its
+/// compute-unit budget, which every check measures against anyway.
+pub fn clauses(function: &Function) -> String {
+    let function = Function {
+        description: None,
+        compute_units: None,
+        ..function.clone()
+    };
+    fingerprint(
+        serde_json::to_string(&function)
+            .expect("functions serialize")
+            .as_bytes(),
+    )
+}
+
+/// The fingerprints of every clause of `spec`, keyed by its location, e.g.
+/// `functions.deposit`, `state.Vault` or `invariants.solvency`.
+/// Descriptions are left out, as they do not change the code.
+pub fn clause_fingerprints(spec: &Spec) -> BTreeMap<String, String> {
+    let mut fingerprints = BTreeMap::new();
+    for account in &spec.state {
+        let account = spec::Account {
+            description: None,
+            ..account.clone()
+        };
+        let json = serde_json::to_string(&account).expect("accounts serialize");
+        fingerprints.insert(
+            format!("state.{}", account.name),
+            fingerprint(json.as_bytes()),
+        );
+    }
+    for function in &spec.functions {
+        fingerprints.insert(format!("functions.{}", function.name), clauses(function));
+    }
+    for invariant in &spec.invariants {
+        let invariant = spec::Invariant {
+            description: None,
+            ..invariant.clone()
+        };
+        let json = serde_json::to_string(&invariant).expect("invariants serialize");
+        fingerprints.insert(
+            format!("invariants.{}", invariant.name),
+            fingerprint(json.as_bytes()),
+        );
+    }
+    fingerprints
+}
+
+/// The marker line preceding the processor function implementing `function`.
+pub fn marker(function: &Function) -> String {
+    format!("{MARKER}{} clauses={}", function.name, clauses(function))
+}
+
+/// A marked function found in the source.
+pub(crate) struct Marked<'a> {
+    /// The clauses the function claims to implement
+    pub clauses: &'a str,
+    /// The text from the marker to the end of the function body
+    pub source: &'a str,
+}
+
+/// The length of the text up to and including the brace closing the first
+/// block in `source`, skipping braces in comments and literals.
+pub(crate) fn block_end(source: &str) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i += source[i..].find('\n').unwrap_or(source.len() - i);
+            }
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'\'' if bytes.get(i + 2) == Some(&b'\'') => i += 2,
+            b'{' => depth += 1,
+            b'}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// The marked functions in `source`, by name.
+pub(crate) fn marked(source: &str) -> BTreeMap<&str, Marked<'_>> {
+    let mut functions = BTreeMap::new();
+    let mut rest = source;
+    while let Some(at) = rest.find(MARKER) {
+        let text = &rest[at..];
+        let line = text.lines().next().unwrap_or_default();
+        let (name, clauses) = line[MARKER.len()..]
+            .split_once(" clauses=")
+            .unwrap_or((&line[MARKER.len()..], ""));
+        // Pinocchio processor functions are generic over their accounts
+        let end = ["(", "<"]
+            .iter()
+            .find_map(|open| text.find(&format!("fn process_{name}{open}")))
+            .and_then(|start| Some(start + block_end(&text[start..])?));
+        let Some(end) = end else {
+            rest = &text[line.len()..];
+            continue;
+        };
+        functions.insert(
+            name.trim(),
+            Marked {
+                clauses: clauses.trim(),
+                source: &text[..end],
+            },
+        );
+        rest = &text[end..];
+    }
+    functions
+}
+
+/// The fingerprint of a function's source, insensitive to trailing
+/// whitespace.
+pub(crate) fn source_fingerprint(source: &str) -> String {
+    let normalized: Vec<&str> = source.lines().map(str::trim_end).collect();
+    fingerprint(normalized.join("\n").as_bytes())
+}
+
+/// The fingerprints of a function when it was last verified.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Verified {
+    pub clauses: String,
+    pub source: String,
+}
+
+/// The fingerprint of the code every function relies on: the processor
+/// outside the marked functions, e.g. its helpers, and the state types.
+pub(crate) fn shared_fingerprint(source: &str, state: &str) -> String {
+    // Restamping a file changes nothing it does
+    let mut shared = provenance::strip(source).to_string();
+    for marked in marked(source).values() {
+        shared = shared.replacen(marked.source, "", 1);
+    }
+    source_fingerprint(&format!("{shared}\n{state}"))
+}
+
+/// The last verification of a crate.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Record {
+    /// The fingerprints of the clauses of the spec, by location
+    #[serde(default)]
+    pub clauses: BTreeMap<String, String>,
+    /// The fingerprint of the code the functions share
+    #[serde(default)]
+    pub shared: Option<String>,
+    pub functions: BTreeMap<String, Verified>,
+}
+
+pub(crate) fn read_record(dir: &Path) -> Result<Record, DriftError> {
+    let path = dir.join(VERIFIED_JSON);
+    match std::fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|source| DriftError::Json { path, source }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Record::default()),
+        Err(source) => Err(DriftError::Io { path, source }),
+    }
+}
+
+pub(crate) fn read_processor(dir: &Path) -> Result<String, DriftError> {
+    let path = dir.join(PROCESSOR_RS);
+    std::fs::read_to_string(&path).map_err(|source| DriftError::Io { path, source })
+}
+
+/// The state types of the crate in `dir`, if it has any.
+pub(crate) fn read_state(dir: &Path) -> Result<String, DriftError> {
+    let path = dir.join(STATE_RS);
+    match std::fs::read_to_string(&path) {
+        Ok(state) => Ok(state),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(source) => Err(DriftError::Io { path, source }),
+    }
+}
+
+/// How a function has drifted from its spec.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Drift {
+    /// No processor function is marked as implementing it
+    Unmarked,
+    /// The spec changed since the code was written against it
+    SpecChanged { claimed: String, current: String },
+    /// The code was never verified against the current clauses
+    Unverified,
+    /// The code changed since it was verified
+    SourceChanged,
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drift::Unmarked => f.write_str("no processor function is marked as implementing it"),
+            Drift::SpecChanged { claimed, current } => write!(
+                f,
+                "the code implements clauses {claimed}, but the spec now has {current}"
+            ),
+            Drift::Unverified => f.write_str("the code was never verified"),
+            Drift::SourceChanged => f.write_str("the code changed since it was verified"),
+        }
+    }
+}
+
+/// The drift of one function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionDrift {
+    pub function: String,
+    pub drift: Drift,
+}
+
+/// Compares the crate in `dir` against `spec` and the record of its last
+/// verification, returning the functions that drifted.
+pub fn check(spec: &Spec, dir: &Path) -> Result<Vec<FunctionDrift>, DriftError> {
+    let source = read_processor(dir)?;
+    let marked = marked(&source);
+    let record = read_record(dir)?;
+    let mut drifts = Vec::new();
+    for function in &spec.functions {
+        let current = clauses(function);
+        let drift = match marked.get(function.name.as_str()) {
+            None => Some(Drift::Unmarked),
+            Some(marked) if marked.clauses != current => Some(Drift::SpecChanged {
+                claimed: marked.clauses.to_string(),
+                current,
+            }),
+            Some(marked) => match record.functions.get(&function.name) {
+                Some(verified) if verified.clauses != current => Some(Drift::Unverified),
+                Some(verified) if verified.source != source_fingerprint(marked.source) => {
+                    Some(Drift::SourceChanged)
+                }
+                Some(_) => None,
+                None => Some(Drift::Unverified),
+            },
+        };
+        drifts.extend(drift.map(|drift| FunctionDrift {
+            function: function.name.clone(),
+            drift,
+        }));
+    }
+    Ok(drifts)
+}
+
+/// Entries of a crate that hold no generated files.
+const UNGENERATED: &[&str] = &["target", ".spec-coder", ".git"];
+
+/// The files of the crate in `dir` generated for another spec than `spec`,
+/// according to their stamps, relative to `dir` and sorted.
+pub fn stale(spec: &Spec, dir: &Path) -> Result<Vec<PathBuf>, DriftError> {
+    let current = provenance::fingerprint(spec);
+    let mut stale = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(next) = pending.pop() {
+        let io = |source| DriftError::Io {
+            path: next.clone(),
+            source,
+        };
+        for entry in std::fs::read_dir(&next).map_err(io)? {
+            let path = entry.map_err(io)?.path();
+            let name = path.file_name().expect("entries have names");
+            if UNGENERATED.iter().any(|skipped| name == *skipped) {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            // Files that are not text are not stamped either
+            let Ok(source) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            if Provenance::parse(&source).is_some_and(|stamp| stamp.spec != current) {
+                let relative = path.strip_prefix(dir).expect("below the crate root");
+                stale.push(relative.to_path_buf());
+            }
+        }
+    }
+    stale.sort();
+    Ok(stale)
+}
+
+/// Records the crate in `dir` as verified against `spec`. Functions whose
+/// marker is missing or claims other clauses are left out.
+pub fn record(spec: &Spec, dir: &Path) -> Result<(), DriftError> {
+    let source = read_processor(dir)?;
+    let marked = marked(&source);
+    let mut record = Record {
+        clauses: clause_fingerprints(spec),
+        shared: Some(shared_fingerprint(&source, &read_state(dir)?)),
+        ..Record::default()
+    };
+    for function in &spec.functions {
+        let current = clauses(function);
+        let Some(marked) = marked
+            .get(function.name.as_str())
+            .filter(|marked| marked.clauses == current)
+        else {
+            continue;
+        };
+        record.functions.insert(
+            function.name.clone(),
+            Verified {
+                clauses: current,
+                source: source_fingerprint(marked.source),
+            },
+        );
+    }
+    let path = dir.join(VERIFIED_JSON);
+    let io = |source| DriftError::Io {
+        path: path.clone(),
+        source,
+    };
+    std::fs::create_dir_all(path.parent().expect("has a parent")).map_err(io)?;
+    let json = serde_json::to_string_pretty(&record).expect("records serialize");
+    std::fs::write(&path, json + "\n").map_err(io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_block_end() {
+        let source = "fn f() { let s = \"}\"; let c = '{'; // }\n if x { y } }\nfn g() {}";
+        assert_eq!(
+            &source[..block_end(source).unwrap()],
+            &source[..source.find("\nfn g").unwrap()]
+        );
+        assert_eq!(block_end("fn f() {"), None);
+    }
+
+    #[test]
+    fn test_check_and_record() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let files = crate::materialize::generate(&spec, spec::Target::Svm).unwrap();
+        crate::materialize::write(&files, dir.path(), true).unwrap();
+        let processor = dir.path().join(PROCESSOR_RS);
+        let source = std::fs::read_to_string(&processor).unwrap();
+        assert!(source.contains(&marker(&spec.functions[0])));
+
+        // Nothing was verified yet
+        let drifts = check(&spec, dir.path()).unwrap();
+        assert_eq!(drifts.len(), 3);
+        assert!(drifts.iter().all(|d| d.drift == Drift::Unverified));
+
+        record(&spec, dir.path()).unwrap();
+        assert_eq!(check(&spec, dir.path()).unwrap(), []);
+
+        // Manual edits of the first two functions
+        let edited = source.replacen("    Ok(())\n}", "    let _edited = 1;\n    Ok(())\n}", 2);
+        std::fs::write(&processor, &edited).unwrap();
+        let drifts = check(&spec, dir.path()).unwrap();
+        let names: Vec<_> = drifts.iter().map(|d| d.function.as_str()).collect();
+        assert_eq!(names, ["deposit", "withdraw"]);
+        assert!(drifts.iter().all(|d| d.drift == Drift::SourceChanged));
+
+        // A spec change, and a marker removed by hand
+        let mut changed = spec.clone();
+        changed.functions[2].ensures.pop();
+        let reward = marker(&spec.functions[1]);
+        std::fs::write(&processor, source.replace(&reward, "")).unwrap();
+        let drifts = check(&changed, dir.path()).unwrap();
+        assert_eq!(drifts[0].function, "withdraw");
+        assert_eq!(drifts[0].drift, Drift::Unmarked);
+        assert_eq!(drifts[1].function, "reward");
+        assert!(matches!(drifts[1].drift, Drift::SpecChanged { .. }));
+    }
+
+    #[test]
+    fn test_stale() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut files = crate::materialize::generate(&spec, spec::Target::Svm).unwrap();
+        let provenance = Provenance::new(provenance::fingerprint(&spec), None);
+        provenance::stamp_files(
+            &mut files,
+            &provenance,
+            Some("SPDX-License-Identifier: MIT"),
+        );
+        crate::materialize::write(&files, dir.path(), true).unwrap();
+        assert_eq!(stale(&spec, dir.path()).unwrap(), Vec::<PathBuf>::new());
+        // Restamping leaves the code the functions share as it was
+        record(&spec, dir.path()).unwrap();
+        let processor = read_processor(dir.path()).unwrap();
+        std::fs::write(
+            dir.path().join(PROCESSOR_RS),
+            provenance::stamp(
+                Path::new(PROCESSOR_RS),
+                &processor,
+                &Provenance::new("0000000000000000", Some("gpt-4o")),
+                None,
+            ),
+        )
+        .unwrap();
+        assert_eq!(check(&spec, dir.path()).unwrap(), []);
+
+        let mut changed = spec.clone();
+        changed.functions[2].ensures.pop();
+        let stale = stale(&changed, dir.path()).unwrap();
+        assert!(stale.contains(&PathBuf::from(PROCESSOR_RS)));
+        assert!(stale.contains(&PathBuf::from("Cargo.toml")));
+        // Neither the summaries, which cannot be stamped, nor the record
+        assert!(!stale.iter().any(|path| path.starts_with("certora")));
+        assert!(!stale.iter().any(|path| path.starts_with(".spec-coder")));
+    }
+}
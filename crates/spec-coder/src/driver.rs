@@ -0,0 +1,2510 @@
+//! The verify-and-fix loop.
+//!
+//! Starting from a materialized crate, the driver asks the model for an
+//! implementation, writes the files it returns, and checks them: first the
+//! build and unit tests, then every prover rule, then the compute-unit
+//! budgets of the spec. Compiler errors, failing tests, rule violations and
+//! instructions over budget are fed back to the model until everything
+//! passes or the iteration budget is exhausted. Run incrementally, only the
+//! rules affected by changes since the last verification are proved.
+//! The state of the run is saved in the crate after every step (see
+//! [`crate::session`]), so an interrupted run can be resumed. With roles,
+//! a specifier refines the spec before the first response and a critic
+//! diagnoses rule failures before they are fed back, see [`crate::roles`].
+//! Streamed, each file of a response is written as soon as it arrives, see
+//! [`crate::streaming`]. With a [`Repo`], the crate is committed after every
+//! iteration, see [`crate::git`]. In patch mode, the model sends unified
+//! diffs of the files it changes instead of the whole files, see
+//! [`crate::patch`]. A [`Control`] stops the loop or skips rules while it
+//! runs, e.g. from the [interactive mode](crate::tui).
+
+use crate::compute_units::{self, Measurement};
+use crate::drift::{self, DriftError};
+use crate::git::{self, GitError, Repo};
+use crate::incremental;
+use crate::llm::{
+    ChatRequest, ChatResponse, LlmError, Message, Provider, Role, StopReason, StreamEvent, Usage,
+};
+use crate::materialize;
+use crate::mutation::{self, Mutant};
+use crate::patch;
+use crate::provenance::{self, Provenance};
+use crate::prover::cache::{Cache, Inputs};
+use crate::prover::{self, Backend, ProverError, RuleResult, RuleStatus};
+use crate::regression::{self, REGRESSIONS_RS};
+use crate::review::{self, AutoApprove, Review, Reviewer};
+use crate::roles::{self, Critique, Refinement};
+use crate::sandbox::Sandbox;
+use crate::session::{Session, SessionError};
+use crate::streaming::{self, FileBlocks};
+use crate::templates::{TemplateError, Templates};
+use crate::throughput::{self, Timing};
+use minijinja::context;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use spec::Spec;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Tool output beyond this many bytes is cut before it is sent to the model.
+const MAX_FEEDBACK_BYTES: usize = 16 * 1024;
+
+/// An error that stops the loop.
+#[derive(Debug, thiserror::Error)]
+pub enum DriverError {
+    #[error(transparent)]
+    Llm(#[from] LlmError),
+    #[error(transparent)]
+    Prover(#[from] ProverError),
+    #[error(transparent)]
+    Spec(#[from] spec::SpecError),
+    #[error(transparent)]
+    Template(#[from] TemplateError),
+    #[error(transparent)]
+    Drift(#[from] DriftError),
+    #[error(transparent)]
+    Session(#[from] SessionError),
+    #[error(transparent)]
+    Git(#[from] GitError),
+    #[error("failed to measure compute units:\n{0}")]
+    ComputeUnits(String),
+    #[error("failed to benchmark the helpers:\n{0}")]
+    Bench(String),
+    #[error("failed to {action} {}: {source}", .path.display())]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// The outcome of a check run by a [`Verifier`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Passed,
+    /// Failed, with the output explaining why
+    Failed(String),
+}
+
+/// Checks a crate.
+pub trait Verifier {
+    /// Builds the crate and runs its unit tests.
+    fn build(&self, dir: &Path) -> Result<Verdict, DriverError>;
+
+    /// Proves `rules` of the crate.
+    fn verify(&self, dir: &Path, rules: &[String]) -> Result<Vec<RuleResult>, DriverError>;
+
+    /// Measures the compute units of the calls in the generated
+    /// `tests/compute_units.rs`, see [`compute_units`].
+    fn compute_units(&self, _dir: &Path) -> Result<Vec<Measurement>, DriverError> {
+        Ok(Vec::new())
+    }
+
+    /// Times the calls of the helpers in the generated `benches/helpers.rs`,
+    /// see [`throughput`].
+    fn bench(&self, _dir: &Path) -> Result<Vec<Timing>, DriverError> {
+        Ok(Vec::new())
+    }
+
+    /// Formats the crate once it builds and fixes what lints it can,
+    /// failing with the lints left in the code the model writes.
+    fn tidy(&self, _dir: &Path) -> Result<Verdict, DriverError> {
+        Ok(Verdict::Passed)
+    }
+}
+
+/// Checks a crate with `cargo` and, unless disabled, a prover backend.
+#[derive(Clone, Debug)]
+pub struct CargoVerifier {
+    /// `None` skips formal verification, as in a tests-first workflow
+    pub prover: Option<Backend>,
+    /// How many prover jobs to run at once
+    pub jobs: usize,
+    /// Reuse the results of rules whose inputs did not change, see
+    /// [`prover::cache`]
+    pub cache: bool,
+    /// Where the build and the tests run
+    pub sandbox: Sandbox,
+    /// Run rustfmt and `clippy --fix` on the crate once it builds, and
+    /// deny the lints left, see [`Verifier::tidy`]
+    pub clippy: bool,
+}
+
+impl Default for CargoVerifier {
+    fn default() -> CargoVerifier {
+        CargoVerifier {
+            prover: None,
+            jobs: 1,
+            cache: true,
+            sandbox: Sandbox::default(),
+            clippy: true,
+        }
+    }
+}
+
+impl CargoVerifier {
+    fn cargo(&self, dir: &Path, args: &[&str]) -> Result<Verdict, DriverError> {
+        let output = self
+            .sandbox
+            .cargo(dir, args)
+            .map_err(|source| DriverError::Io {
+                action: "run cargo in",
+                path: dir.to_path_buf(),
+                source,
+            })?;
+        if output.success {
+            return Ok(Verdict::Passed);
+        }
+        // Compiler messages come first, as cargo prints them
+        let mut text = format!("$ cargo {}\n", args.join(" "));
+        for diagnostic in &output.diagnostics {
+            text.push_str(&diagnostic.rendered);
+        }
+        text.push_str(&output.output);
+        Ok(Verdict::Failed(text))
+    }
+}
+
+impl Verifier for CargoVerifier {
+    fn build(&self, dir: &Path) -> Result<Verdict, DriverError> {
+        // The rules only compile with the certora feature
+        for args in [&["test"][..], &["check", "--features", "certora"]] {
+            if let failed @ Verdict::Failed(_) = self.cargo(dir, args)? {
+                return Ok(failed);
+            }
+        }
+        Ok(Verdict::Passed)
+    }
+
+    fn verify(&self, dir: &Path, rules: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+        let Some(prover) = &self.prover else {
+            return Ok(Vec::new());
+        };
+        let (mut cache, keys) = if self.cache {
+            let inputs = Inputs::read(dir)?;
+            let keys: Vec<String> = rules.iter().map(|rule| inputs.key(prover, rule)).collect();
+            (Some(Cache::load(dir)), keys)
+        } else {
+            (None, Vec::new())
+        };
+        let mut results: Vec<Option<Result<RuleResult, ProverError>>> = match &cache {
+            Some(cache) => keys.iter().map(|key| cache.get(key).map(Ok)).collect(),
+            None => std::iter::repeat_with(|| None).take(rules.len()).collect(),
+        };
+        let pending: Vec<usize> = (0..rules.len()).filter(|&i| results[i].is_none()).collect();
+        // Workers take the next rule until none are left
+        let next = AtomicUsize::new(0);
+        let slots = Mutex::new(&mut results);
+        std::thread::scope(|scope| {
+            for _ in 0..self.jobs.clamp(1, pending.len().max(1)) {
+                scope.spawn(|| {
+                    while let Some(&i) = pending.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let result = prover.prove(dir, &rules[i]);
+                        slots.lock().expect("no worker panics")[i] = Some(result);
+                    }
+                });
+            }
+        });
+        let results = results
+            .into_iter()
+            .map(|result| Ok(result.expect("every rule is taken")?))
+            .collect::<Result<Vec<_>, DriverError>>()?;
+        if let Some(cache) = &mut cache {
+            for (key, result) in keys.into_iter().zip(&results) {
+                cache.insert(key, result);
+            }
+            cache.save(dir, &prover::find_rules(dir)?)?;
+        }
+        Ok(results)
+    }
+
+    fn compute_units(&self, dir: &Path) -> Result<Vec<Measurement>, DriverError> {
+        if !dir.join(materialize::COMPUTE_UNITS_RS).exists() {
+            return Ok(Vec::new());
+        }
+        // `cargo test-sbf` builds the program for the SVM before the tests
+        let args = [
+            "test-sbf",
+            "--test",
+            "compute_units",
+            "--",
+            "--nocapture",
+            "--test-threads=1",
+        ];
+        let output = self
+            .sandbox
+            .run(dir, &args)
+            .map_err(|source| DriverError::Io {
+                action: "run cargo in",
+                path: dir.to_path_buf(),
+                source,
+            })?;
+        if !output.success {
+            return Err(DriverError::ComputeUnits(output.output));
+        }
+        Ok(compute_units::parse(&output.output))
+    }
+
+    fn bench(&self, dir: &Path) -> Result<Vec<Timing>, DriverError> {
+        if !dir.join(materialize::BENCHES_RS).exists() {
+            return Ok(Vec::new());
+        }
+        let args = [
+            "bench",
+            "--bench",
+            "helpers",
+            "--",
+            "--output-format",
+            "bencher",
+        ];
+        let output = self
+            .sandbox
+            .run(dir, &args)
+            .map_err(|source| DriverError::Io {
+                action: "run cargo in",
+                path: dir.to_path_buf(),
+                source,
+            })?;
+        if !output.success {
+            return Err(DriverError::Bench(output.output));
+        }
+        Ok(throughput::parse(&output.output))
+    }
+
+    fn tidy(&self, dir: &Path) -> Result<Verdict, DriverError> {
+        if !self.clippy {
+            return Ok(Verdict::Passed);
+        }
+        let io = |source| DriverError::Io {
+            action: "run cargo in",
+            path: dir.to_path_buf(),
+            source,
+        };
+        // Whatever these cannot fix is left to the lints below
+        self.sandbox.fix(dir, &["fmt"]).map_err(io)?;
+        let fix = [
+            "clippy",
+            "--fix",
+            "--allow-dirty",
+            "--allow-no-vcs",
+            "--all-targets",
+        ];
+        self.sandbox.fix(dir, &fix).map_err(io)?;
+        let args = ["clippy", "--all-targets", "--", "-D", "warnings"];
+        let output = self.sandbox.cargo(dir, &args).map_err(io)?;
+        // Lints in the files compiled from the spec are not the model's to
+        // fix, and a toolchain without clippy reports none at all
+        let lints: Vec<_> = output
+            .diagnostics
+            .iter()
+            .filter(|lint| {
+                lint.path
+                    .as_deref()
+                    .is_some_and(|path| rejection(path).is_none())
+            })
+            .collect();
+        if output.success || lints.is_empty() {
+            return Ok(Verdict::Passed);
+        }
+        let mut text = format!("$ cargo {}\n", args.join(" "));
+        for lint in lints {
+            text.push_str(&lint.rendered);
+        }
+        Ok(Verdict::Failed(text))
+    }
+}
+
+/// Limits of a run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DriverOptions {
+    /// How many model responses to check before giving up
+    pub max_iterations: usize,
+    /// How many tokens, input and output, to spend before giving up
+    pub max_tokens: Option<u64>,
+    /// How many rules to prove before giving up
+    pub max_prover_jobs: Option<usize>,
+    /// Only check the functions and prove the rules affected by changes
+    /// since the last verification, see [`incremental::plan`]
+    pub incremental: bool,
+    /// Continue the session saved in the crate
+    pub resume: bool,
+    /// The percentage of the mutants of the processor the tests and rules
+    /// must kill once they pass, see [`mutation`]; `None` skips mutation
+    pub min_mutation_score: Option<u8>,
+    /// Have a specifier and a critic assist the model, see [`roles`]
+    pub roles: bool,
+    /// Stream the responses, writing each file as soon as it is complete
+    /// and stopping at the first that does not parse
+    pub stream: bool,
+    /// Ask for unified diffs of existing files instead of their contents
+    pub patch: bool,
+    /// The tag of the cheap rules, see [`prover::find_tags`]: they are
+    /// proved first, and the other rules only once they all pass
+    pub fast_tag: Option<String>,
+}
+
+impl Default for DriverOptions {
+    fn default() -> DriverOptions {
+        DriverOptions {
+            max_iterations: 8,
+            max_tokens: None,
+            max_prover_jobs: None,
+            incremental: false,
+            resume: false,
+            min_mutation_score: None,
+            roles: false,
+            stream: false,
+            patch: false,
+            fast_tag: None,
+        }
+    }
+}
+
+/// A step of the loop, reported as it happens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Progress {
+    Iteration(usize),
+    /// Text of a model response: each piece as it arrives when streamed,
+    /// else the whole response
+    Output(String),
+    /// Files written from a model response
+    Wrote(Vec<PathBuf>),
+    /// A file of a streamed response that does not parse, ending the
+    /// response
+    SyntaxError {
+        path: PathBuf,
+        error: String,
+    },
+    /// A regression test added for a counterexample
+    Regression(String),
+    Build(Verdict),
+    /// The lints clippy reported once the crate was formatted and fixed
+    Lints(Verdict),
+    Rule(RuleResult),
+    /// The compute units of the most expensive call of a function with a
+    /// budget
+    ComputeUnits {
+        function: String,
+        units: u64,
+        budget: u64,
+    },
+    /// The calls per second of a helper with a throughput target
+    Throughput {
+        helper: String,
+        calls: u64,
+        target: u64,
+    },
+    /// How many of the mutants checked the tests and rules killed
+    Mutants {
+        killed: usize,
+        total: usize,
+    },
+}
+
+/// A budget of a run, see [`DriverOptions`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Iterations,
+    Tokens,
+    ProverJobs,
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Limit::Iterations => "iteration",
+            Limit::Tokens => "token",
+            Limit::ProverJobs => "prover job",
+        })
+    }
+}
+
+/// The outcome of a run, partial if a budget ran out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunReport {
+    /// Whether the build, the tests and every rule passed
+    pub success: bool,
+    pub iterations: usize,
+    pub usage: Usage,
+    /// How many rules were proved
+    pub prover_jobs: usize,
+    /// The verdicts of the last check, by rule
+    pub rules: BTreeMap<String, RuleStatus>,
+    /// Rules the last check left unproved for lack of prover jobs
+    pub unproved: Vec<String>,
+    /// The feedback the last response received, unless it succeeded
+    pub feedback: Option<String>,
+    /// The budget that ran out, unless the run succeeded
+    pub exhausted: Option<Limit>,
+    /// Rules the last check skipped, see [`Control::skip`]
+    pub skipped: Vec<String>,
+    /// Whether the run was stopped by [`Control::abort`]
+    pub aborted: bool,
+}
+
+/// Stops a running loop or has it skip rules, from another thread.
+#[derive(Debug, Default)]
+pub struct Control {
+    aborted: AtomicBool,
+    skipped: Mutex<BTreeSet<String>>,
+}
+
+impl Control {
+    /// Stops the loop at the next step; the session is saved, so the run
+    /// can be resumed.
+    pub fn abort(&self) {
+        self.aborted.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::SeqCst)
+    }
+
+    /// Leaves `rule` out of the checks from now on, or proves it again if
+    /// it was skipped. Returns whether it is skipped now.
+    pub fn skip(&self, rule: &str) -> bool {
+        let mut skipped = self.skipped.lock().expect("not poisoned");
+        if skipped.remove(rule) {
+            return false;
+        }
+        skipped.insert(rule.to_string())
+    }
+
+    pub fn skipped(&self) -> BTreeSet<String> {
+        self.skipped.lock().expect("not poisoned").clone()
+    }
+}
+
+/// A file block of a model response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileEdit {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// Extracts the file blocks of `response`: fenced code blocks whose info
+/// string is a language followed by a path.
+pub fn parse_edits(response: &str) -> Vec<FileEdit> {
+    let mut edits = Vec::new();
+    let mut lines = response.lines();
+    while let Some(line) = lines.next() {
+        let Some(info) = line.trim_start().strip_prefix("```") else {
+            continue;
+        };
+        let mut contents = String::new();
+        for line in lines.by_ref() {
+            if line.trim_start().starts_with("```") {
+                break;
+            }
+            contents.push_str(line);
+            contents.push('\n');
+        }
+        if let [_, path] = info.split_whitespace().collect::<Vec<_>>()[..] {
+            edits.push(FileEdit {
+                path: PathBuf::from(path),
+                contents,
+            });
+        }
+    }
+    edits
+}
+
+/// Why an edit cannot be applied, if it cannot.
+fn rejection(path: &Path) -> Option<&'static str> {
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        Some("paths must be relative to the crate root")
+    } else if path == Path::new(materialize::SPEC_TESTS_RS)
+        || path == Path::new(materialize::PROP_TESTS_RS)
+        || path == Path::new(materialize::SVM_TESTS_RS)
+        || path == Path::new(materialize::COMPUTE_UNITS_RS)
+        || path.starts_with(materialize::FUZZ_DIR)
+    {
+        Some("the tests from the spec cannot be changed")
+    } else if materialize::is_compiled(path) {
+        Some("the rules cannot be changed")
+    } else if path == Path::new(REGRESSIONS_RS) {
+        Some("the regression tests cannot be changed")
+    } else {
+        None
+    }
+}
+
+/// Cuts `output` to [`MAX_FEEDBACK_BYTES`]; compiler errors come first, so
+/// the head is kept.
+fn truncate(output: &str) -> String {
+    if output.len() <= MAX_FEEDBACK_BYTES {
+        return output.to_string();
+    }
+    let mut end = MAX_FEEDBACK_BYTES;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n[output truncated]", &output[..end])
+}
+
+/// A source file, as shown to the model.
+#[derive(Serialize)]
+struct SourceFile {
+    path: String,
+    contents: String,
+}
+
+/// The `src` files of the crate in `dir`, in path order.
+fn source_files(dir: &Path) -> Result<Vec<SourceFile>, DriverError> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::from("src")];
+    while let Some(relative) = pending.pop() {
+        let path = dir.join(&relative);
+        let io = |source| DriverError::Io {
+            action: "read",
+            path: path.clone(),
+            source,
+        };
+        if path.is_dir() {
+            for entry in std::fs::read_dir(&path).map_err(io)? {
+                pending.push(relative.join(entry.map_err(io)?.file_name()));
+            }
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(SourceFile {
+                path: relative.display().to_string(),
+                contents: std::fs::read_to_string(&path).map_err(io)?,
+            });
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Runs the verify-and-fix loop.
+pub struct Driver<'a> {
+    provider: &'a dyn Provider,
+    verifier: &'a dyn Verifier,
+    templates: &'a Templates,
+    reviewer: &'a dyn Reviewer,
+    repo: Option<&'a Repo>,
+    control: Option<&'a Control>,
+    options: DriverOptions,
+}
+
+impl<'a> Driver<'a> {
+    pub fn new(
+        provider: &'a dyn Provider,
+        verifier: &'a dyn Verifier,
+        templates: &'a Templates,
+        options: DriverOptions,
+    ) -> Driver<'a> {
+        Driver {
+            provider,
+            verifier,
+            templates,
+            reviewer: &AutoApprove,
+            repo: None,
+            control: None,
+            options,
+        }
+    }
+
+    /// Has `reviewer` approve every file before it is written, instead of
+    /// writing them all.
+    pub fn with_reviewer(mut self, reviewer: &'a dyn Reviewer) -> Driver<'a> {
+        self.reviewer = reviewer;
+        self
+    }
+
+    /// Commits the crate to `repo` after every iteration.
+    pub fn with_repo(mut self, repo: &'a Repo) -> Driver<'a> {
+        self.repo = Some(repo);
+        self
+    }
+
+    /// Has `control` stop the loop or skip rules.
+    pub fn with_control(mut self, control: &'a Control) -> Driver<'a> {
+        self.control = Some(control);
+        self
+    }
+
+    fn aborted(&self) -> bool {
+        self.control.is_some_and(Control::is_aborted)
+    }
+
+    /// Implements `spec` in the materialized crate in `dir`, saving the
+    /// session after every step. With [`DriverOptions::resume`], the session
+    /// saved in `dir` is continued instead.
+    pub fn run(
+        &self,
+        spec: &Spec,
+        dir: &Path,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<RunReport, DriverError> {
+        // The steps of the iteration, for its commit
+        let steps = RefCell::new(Vec::new());
+        let on_progress = &mut |progress: &Progress| {
+            if self.repo.is_some() {
+                steps.borrow_mut().push(progress.clone());
+            }
+            on_progress(progress);
+        };
+        let mut session = if self.options.resume {
+            Session::load(dir, spec)?
+        } else {
+            match self.start(spec, dir, on_progress)? {
+                Some(session) => session,
+                None => {
+                    return Ok(RunReport {
+                        success: true,
+                        iterations: 0,
+                        usage: Usage::default(),
+                        prover_jobs: 0,
+                        rules: BTreeMap::new(),
+                        unproved: Vec::new(),
+                        feedback: None,
+                        exhausted: None,
+                        skipped: Vec::new(),
+                        aborted: false,
+                    })
+                }
+            }
+        };
+        // A response saved before its check finished is checked again
+        let unchecked = session
+            .messages
+            .last()
+            .filter(|message| message.role == Role::Assistant)
+            .map(|message| parse_edits(&message.text()));
+        if let Some(edits) = unchecked {
+            let feedback = if self.options.stream {
+                self.apply_streamed(dir, &edits, &mut session, on_progress)?
+            } else {
+                self.apply(dir, &edits, &mut session, on_progress)?
+            };
+            self.settle(spec, dir, &mut session, feedback, on_progress)?;
+            self.commit(spec, &session, &steps.take())?;
+        }
+        let mut exhausted = None;
+        while !session.success && !self.aborted() {
+            exhausted = self.exhausted(&session);
+            if exhausted.is_some() {
+                break;
+            }
+            session.iterations += 1;
+            on_progress(&Progress::Iteration(session.iterations));
+            let request = ChatRequest {
+                system: session.system.clone(),
+                messages: session.messages.clone(),
+                ..ChatRequest::default()
+            };
+            let (response, streamed) = if self.options.stream {
+                let (response, feedback) = self.stream(dir, &request, &mut session, on_progress)?;
+                (response, Some(feedback))
+            } else {
+                let response = self.provider.chat(&request)?;
+                on_progress(&Progress::Output(response.text()));
+                (response, None)
+            };
+            session.usage.input_tokens += response.usage.input_tokens;
+            session.usage.output_tokens += response.usage.output_tokens;
+            let edits = parse_edits(&response.text());
+            session.messages.push(response.into_message());
+            session.save(dir)?;
+            // The response is checked when the run is resumed
+            if self.aborted() {
+                break;
+            }
+            let feedback = match streamed {
+                Some(feedback) => feedback,
+                None => self.apply(dir, &edits, &mut session, on_progress)?,
+            };
+            self.settle(spec, dir, &mut session, feedback, on_progress)?;
+            self.commit(spec, &session, &steps.take())?;
+        }
+        Ok(RunReport {
+            success: session.success,
+            iterations: session.iterations,
+            usage: session.usage,
+            prover_jobs: session.prover_jobs,
+            rules: session.rules,
+            unproved: session.unproved,
+            feedback: session.feedback,
+            exhausted,
+            skipped: self.control.map_or(Vec::new(), |control| {
+                control.skipped().into_iter().collect()
+            }),
+            aborted: !session.success && self.aborted(),
+        })
+    }
+
+    /// Commits the crate with a summary of the iteration that made `steps`,
+    /// if there is a repository to commit to.
+    fn commit(
+        &self,
+        spec: &Spec,
+        session: &Session,
+        steps: &[Progress],
+    ) -> Result<(), DriverError> {
+        let Some(repo) = self.repo else {
+            return Ok(());
+        };
+        let summary = git::Summary {
+            spec: &spec.name,
+            iteration: session.iterations,
+            model: format!("{}/{}", self.provider.name(), self.provider.model()),
+            tokens: session.usage.input_tokens + session.usage.output_tokens,
+            steps,
+            rules: &session.rules,
+            success: session.success,
+        };
+        repo.commit(&summary.to_string())?;
+        Ok(())
+    }
+
+    /// The budget `session` has used up, if any.
+    fn exhausted(&self, session: &Session) -> Option<Limit> {
+        let tokens = session.usage.input_tokens + session.usage.output_tokens;
+        if session.iterations >= self.options.max_iterations {
+            Some(Limit::Iterations)
+        } else if self.options.max_tokens.is_some_and(|max| tokens >= max) {
+            Some(Limit::Tokens)
+        } else if self
+            .options
+            .max_prover_jobs
+            .is_some_and(|max| session.prover_jobs >= max)
+        {
+            Some(Limit::ProverJobs)
+        } else {
+            None
+        }
+    }
+
+    /// Starts a session with the first message: the specification and the
+    /// scaffolded sources. Returns `None` if, run incrementally, nothing
+    /// changed and the crate still passes.
+    fn start(
+        &self,
+        spec: &Spec,
+        dir: &Path,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<Option<Session>, DriverError> {
+        let mut changed = None;
+        if self.options.incremental {
+            changed = Some(incremental::plan(spec, dir)?.functions);
+        }
+        let target = spec.target.to_string();
+        let mut system = self.templates.render("system", context! { spec, target })?;
+        if !spec.helpers.is_empty() {
+            system.push_str("\n\n");
+            system.push_str(&self.templates.render("helpers", context! {})?);
+        }
+        if self.options.patch {
+            system.push_str("\n\n");
+            system.push_str(&self.templates.render("patches", context! {})?);
+        }
+        let spec_yaml = spec.to_yaml_string()?;
+        let sources = source_files(dir)?;
+        let mut usage = Usage::default();
+        let mut refinement = None;
+        if self.options.roles {
+            let request = self.templates.render(
+                "initial",
+                context! { spec, target, spec_yaml, sources, changed },
+            )?;
+            refinement = self.consult::<Refinement>("specifier", request, &mut usage)?;
+        }
+        let initial = self.templates.render(
+            "initial",
+            context! { spec, target, spec_yaml, sources, changed, refinement },
+        )?;
+        let mut session = Session::new(spec, Some(system), initial)?;
+        session.usage = usage;
+        if changed.is_some_and(|functions| functions.is_empty())
+            && self.check(spec, dir, &mut session, on_progress)?.is_none()
+        {
+            return Ok(None);
+        }
+        session.save(dir)?;
+        Ok(Some(session))
+    }
+
+    /// Checks the crate once the edits of the last response are applied,
+    /// unless applying them gave `feedback` already, recording the outcome
+    /// and any feedback in `session`.
+    fn settle(
+        &self,
+        spec: &Spec,
+        dir: &Path,
+        session: &mut Session,
+        feedback: Option<String>,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<(), DriverError> {
+        let feedback = match feedback {
+            Some(feedback) => Some(feedback),
+            None => self.check(spec, dir, session, on_progress)?,
+        };
+        // A check cut short by an abort is not finished, so the response is
+        // checked again when the run is resumed
+        if feedback.is_none() && self.aborted() {
+            return Ok(());
+        }
+        session.success = feedback.is_none() && session.unproved.is_empty();
+        session.feedback.clone_from(&feedback);
+        session.messages.extend(feedback.map(Message::user));
+        session.save(dir)?;
+        Ok(())
+    }
+
+    /// Writes the edits, returning feedback if there is nothing to check.
+    fn apply(
+        &self,
+        dir: &Path,
+        edits: &[FileEdit],
+        session: &mut Session,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<Option<String>, DriverError> {
+        let mut rejected = Vec::new();
+        let mut written = Vec::new();
+        for edit in edits {
+            let edit = match self.resolve(dir, edit) {
+                Ok(edit) => edit,
+                Err(conflict) => {
+                    rejected.push(conflict);
+                    continue;
+                }
+            };
+            match self.write(dir, &edit, session)? {
+                Some(reason) => {
+                    rejected.push(context! { path => edit.path.display().to_string(), reason })
+                }
+                None => written.push(edit.path),
+            }
+        }
+        if !written.is_empty() {
+            on_progress(&Progress::Wrote(written));
+        }
+        self.edit_feedback(edits.len(), rejected)
+    }
+
+    /// Writes the edits of a streamed response, one at a time, up to the
+    /// first that does not parse.
+    fn apply_streamed(
+        &self,
+        dir: &Path,
+        edits: &[FileEdit],
+        session: &mut Session,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<Option<String>, DriverError> {
+        let mut rejected = Vec::new();
+        for edit in edits {
+            if !self.apply_block(dir, edit, session, &mut rejected, on_progress)? {
+                break;
+            }
+        }
+        self.edit_feedback(edits.len(), rejected)
+    }
+
+    /// Streams the next response, writing each file as soon as its block is
+    /// complete, and stops reading at the first that does not parse. Returns
+    /// the response, as far as it was read, with the feedback on its files.
+    fn stream(
+        &self,
+        dir: &Path,
+        request: &ChatRequest,
+        session: &mut Session,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<(ChatResponse, Option<String>), DriverError> {
+        let mut blocks = FileBlocks::default();
+        let mut edits = 0;
+        let mut rejected = Vec::new();
+        let mut failure = None;
+        let response = self.provider.chat_stream(request, &mut |event| {
+            let StreamEvent::TextDelta(text) = event else {
+                return true;
+            };
+            on_progress(&Progress::Output(text.clone()));
+            for edit in blocks.push(&text) {
+                edits += 1;
+                match self.apply_block(dir, &edit, session, &mut rejected, on_progress) {
+                    Ok(true) => {}
+                    Ok(false) => return false,
+                    Err(err) => {
+                        failure = Some(err);
+                        return false;
+                    }
+                }
+            }
+            !self.aborted()
+        })?;
+        if let Some(err) = failure {
+            return Err(err);
+        }
+        // A block cut off by the end of the response is written like the
+        // others, as long as it parses
+        if response.stop_reason != StopReason::Aborted {
+            if let Some(edit) = blocks.finish() {
+                edits += 1;
+                self.apply_block(dir, &edit, session, &mut rejected, on_progress)?;
+            }
+        }
+        Ok((response, self.edit_feedback(edits, rejected)?))
+    }
+
+    /// Writes the edit of a streamed file block if it parses, returning
+    /// whether to go on with the rest of the response.
+    fn apply_block(
+        &self,
+        dir: &Path,
+        edit: &FileEdit,
+        session: &mut Session,
+        rejected: &mut Vec<minijinja::Value>,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<bool, DriverError> {
+        let edit = match self.resolve(dir, edit) {
+            Ok(edit) => edit,
+            Err(conflict) => {
+                rejected.push(conflict);
+                return Ok(true);
+            }
+        };
+        let path = edit.path.display().to_string();
+        if let Err(err) = streaming::check_syntax(&edit.path, &edit.contents) {
+            on_progress(&Progress::SyntaxError {
+                path: edit.path.clone(),
+                error: err.to_string(),
+            });
+            let reason =
+                format!("it does not parse ({err}), so the rest of the reply was not read");
+            rejected.push(context! { path, reason });
+            return Ok(false);
+        }
+        match self.write(dir, &edit, session)? {
+            Some(reason) => rejected.push(context! { path, reason }),
+            None => on_progress(&Progress::Wrote(vec![edit.path])),
+        }
+        Ok(true)
+    }
+
+    /// `edit` with the contents the file is to have: in patch mode, a diff
+    /// is applied to the file in `dir`. A diff that does not apply is
+    /// rejected, with the file as it is for the model to diff against.
+    fn resolve(&self, dir: &Path, edit: &FileEdit) -> Result<FileEdit, minijinja::Value> {
+        if !self.options.patch
+            || rejection(&edit.path).is_some()
+            || !patch::is_patch(&edit.contents)
+        {
+            return Ok(edit.clone());
+        }
+        let current = std::fs::read_to_string(dir.join(&edit.path)).unwrap_or_default();
+        match patch::apply(&current, &edit.contents) {
+            Ok(contents) => Ok(FileEdit {
+                path: edit.path.clone(),
+                contents,
+            }),
+            Err(err) => Err(context! {
+                path => edit.path.display().to_string(),
+                reason => format!("the diff does not apply: {err}"),
+                current,
+            }),
+        }
+    }
+
+    /// Writes `edit` unless it is rejected or the reviewer declines it,
+    /// returning why it was not written.
+    fn write(
+        &self,
+        dir: &Path,
+        edit: &FileEdit,
+        session: &mut Session,
+    ) -> Result<Option<&'static str>, DriverError> {
+        if let Some(reason) = rejection(&edit.path) {
+            return Ok(Some(reason));
+        }
+        let path = dir.join(&edit.path);
+        let old = std::fs::read_to_string(&path).ok();
+        let diff = review::unified_diff(&edit.path, old.as_deref(), &edit.contents);
+        let approved = self.reviewer.review(&edit.path, &diff);
+        session.reviews.push(Review {
+            iteration: session.iterations,
+            path: edit.path.clone(),
+            approved,
+            reviewer: self.reviewer.name().to_string(),
+        });
+        if !approved {
+            return Ok(Some("the reviewer declined the change"));
+        }
+        let io = |source| DriverError::Io {
+            action: "write",
+            path: path.clone(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io)?;
+        }
+        // Model output is generated too, and keeps the license of the file
+        let provenance = Provenance::new(session.spec.clone(), Some(self.provider.model()));
+        let license = old.as_deref().and_then(provenance::license);
+        let contents =
+            provenance::stamp(&edit.path, &edit.contents, &provenance, license.as_deref());
+        std::fs::write(&path, contents).map_err(io)?;
+        if !session.written.contains(&edit.path) {
+            session.written.push(edit.path.clone());
+        }
+        Ok(None)
+    }
+
+    /// The feedback on a response with `edits` file blocks, of which those
+    /// in `rejected` were not written, if there is any before checking.
+    fn edit_feedback(
+        &self,
+        edits: usize,
+        rejected: Vec<minijinja::Value>,
+    ) -> Result<Option<String>, DriverError> {
+        if edits == 0 {
+            return Ok(Some(self.templates.render("no_edits", context! {})?));
+        }
+        if rejected.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.templates
+                .render("rejected_edits", context! { rejected })?,
+        ))
+    }
+
+    /// Builds and verifies the crate, returning feedback on any failure.
+    fn check(
+        &self,
+        spec: &Spec,
+        dir: &Path,
+        session: &mut Session,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<Option<String>, DriverError> {
+        let verdict = self.verifier.build(dir)?;
+        on_progress(&Progress::Build(verdict.clone()));
+        if let Verdict::Failed(output) = verdict {
+            let output = truncate(&output);
+            return Ok(Some(
+                self.templates
+                    .render("build_failure", context! { output })?,
+            ));
+        }
+        let verdict = self.verifier.tidy(dir)?;
+        on_progress(&Progress::Lints(verdict.clone()));
+        if let Verdict::Failed(output) = verdict {
+            let output = truncate(&output);
+            return Ok(Some(
+                self.templates.render("lint_failure", context! { output })?,
+            ));
+        }
+        let mut rules = prover::find_rules(dir)?;
+        if let Some(control) = self.control {
+            let skipped = control.skipped();
+            rules.retain(|rule| !skipped.contains(rule));
+        }
+        if self.options.incremental {
+            let stale = incremental::plan(spec, dir)?.rules;
+            rules.retain(|rule| stale.contains(rule));
+        }
+        // Rules beyond the budget are left for a resumed run
+        session.unproved = match self.options.max_prover_jobs {
+            Some(max) => rules.split_off(rules.len().min(max.saturating_sub(session.prover_jobs))),
+            None => Vec::new(),
+        };
+        let mut failures = Vec::new();
+        let mut results = Vec::new();
+        for stage in self.stages(dir, rules)? {
+            let proved = self.verifier.verify(dir, &stage)?;
+            let passed = proved
+                .iter()
+                .all(|result| result.status == RuleStatus::Verified);
+            results.extend(proved);
+            if !passed {
+                break;
+            }
+        }
+        session.prover_jobs += results.iter().filter(|result| !result.cached).count();
+        for result in results {
+            on_progress(&Progress::Rule(result.clone()));
+            session.rules.insert(result.rule.clone(), result.status);
+            if result.status == RuleStatus::Verified {
+                continue;
+            }
+            let test = result.counterexample.as_ref().and_then(|counterexample| {
+                regression::generate(spec, &result.rule, counterexample)
+            });
+            let mut regression_test = None;
+            if let Some(test) = test {
+                let added = regression::add(dir, &test).map_err(|source| DriverError::Io {
+                    action: "add a regression test to",
+                    path: dir.to_path_buf(),
+                    source,
+                })?;
+                if added {
+                    on_progress(&Progress::Regression(test.name.clone()));
+                    regression_test = Some(test.name);
+                }
+            }
+            // A counterexample says more than the log it came with
+            failures.push(context! {
+                rule => result.rule,
+                status => result.status.to_string(),
+                counterexample => result.counterexample.as_ref().map(ToString::to_string),
+                output => truncate(&result.output),
+                regression_test,
+            });
+        }
+        if !failures.is_empty() {
+            let mut feedback = self
+                .templates
+                .render("rule_failure", context! { failures })?;
+            if self.options.roles {
+                let request = self.templates.render(
+                    "critic_request",
+                    context! { failures => feedback, sources => source_files(dir)? },
+                )?;
+                let critique = self.consult::<Critique>("critic", request, &mut session.usage)?;
+                if let Some(Critique { diagnoses }) = critique.filter(|c| !c.diagnoses.is_empty()) {
+                    feedback.push_str("\n\n");
+                    feedback.push_str(&self.templates.render("diagnoses", context! { diagnoses })?);
+                }
+            }
+            return Ok(Some(feedback));
+        }
+        if let Some(feedback) = self.measure(spec, dir, on_progress)? {
+            return Ok(Some(feedback));
+        }
+        if let Some(feedback) = self.bench(spec, dir, on_progress)? {
+            return Ok(Some(feedback));
+        }
+        match self.options.min_mutation_score {
+            Some(min_score) => self.mutate(dir, session, min_score, on_progress),
+            None => Ok(None),
+        }
+    }
+
+    /// Splits `rules` into the rules with the fast tag and the others, in
+    /// the order they are proved, leaving out empty stages.
+    fn stages(&self, dir: &Path, rules: Vec<String>) -> Result<Vec<Vec<String>>, DriverError> {
+        let Some(tag) = &self.options.fast_tag else {
+            return Ok(vec![rules]);
+        };
+        let patterns = prover::find_tags(dir)?.remove(tag).unwrap_or_default();
+        let (fast, slow): (Vec<String>, Vec<String>) = rules.into_iter().partition(|rule| {
+            patterns
+                .iter()
+                .any(|pattern| prover::matches_pattern(pattern, rule))
+        });
+        Ok([fast, slow]
+            .into_iter()
+            .filter(|stage| !stage.is_empty())
+            .collect())
+    }
+
+    /// Asks the role whose prompt is the `role` template about `request`,
+    /// adding what it spent to `usage`. Returns `None` if its answer holds
+    /// no artifact.
+    fn consult<T: DeserializeOwned>(
+        &self,
+        role: &str,
+        request: String,
+        usage: &mut Usage,
+    ) -> Result<Option<T>, DriverError> {
+        let request = ChatRequest {
+            system: Some(self.templates.render(role, context! {})?),
+            messages: vec![Message::user(request)],
+            ..ChatRequest::default()
+        };
+        let response = self.provider.chat(&request)?;
+        usage.input_tokens += response.usage.input_tokens;
+        usage.output_tokens += response.usage.output_tokens;
+        Ok(roles::parse_artifact(&response.text()))
+    }
+
+    /// Checks the compute units of the functions of `spec` with a budget,
+    /// returning feedback on those over it.
+    fn measure(
+        &self,
+        spec: &Spec,
+        dir: &Path,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<Option<String>, DriverError> {
+        if spec.functions.iter().all(|f| f.compute_units.is_none()) {
+            return Ok(None);
+        }
+        let measurements = self.verifier.compute_units(dir)?;
+        let costs = compute_units::costs(spec, &measurements);
+        for cost in &costs {
+            on_progress(&Progress::ComputeUnits {
+                function: cost.function.clone(),
+                units: cost.units,
+                budget: cost.budget,
+            });
+        }
+        let costs: Vec<_> = costs
+            .into_iter()
+            .filter(compute_units::Cost::over_budget)
+            .map(|cost| {
+                context! {
+                    function => cost.function,
+                    example => cost.example,
+                    units => cost.units,
+                    budget => cost.budget,
+                }
+            })
+            .collect();
+        if costs.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.templates.render("over_budget", context! { costs })?,
+        ))
+    }
+
+    /// Benchmarks the helpers of `spec` with a throughput target, returning
+    /// feedback on those below it.
+    fn bench(
+        &self,
+        spec: &Spec,
+        dir: &Path,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<Option<String>, DriverError> {
+        if spec.helpers.iter().all(|h| h.throughput.is_none()) {
+            return Ok(None);
+        }
+        let timings = self.verifier.bench(dir)?;
+        let rates = throughput::rates(spec, &timings);
+        for rate in &rates {
+            on_progress(&Progress::Throughput {
+                helper: rate.helper.clone(),
+                calls: rate.calls,
+                target: rate.target,
+            });
+        }
+        let rates: Vec<_> = rates
+            .into_iter()
+            .filter(throughput::Rate::below_target)
+            .map(|rate| {
+                context! {
+                    helper => rate.helper,
+                    calls => rate.calls,
+                    target => rate.target,
+                }
+            })
+            .collect();
+        if rates.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.templates
+                .render("below_throughput", context! { rates })?,
+        ))
+    }
+
+    /// Checks that the tests and rules kill at least `min_score` percent of
+    /// the mutants of the processor, returning feedback on the survivors
+    /// otherwise. Each mutant is written over the processor in turn, and the
+    /// original written back before returning.
+    fn mutate(
+        &self,
+        dir: &Path,
+        session: &mut Session,
+        min_score: u8,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<Option<String>, DriverError> {
+        let path = dir.join(drift::PROCESSOR_RS);
+        let original = std::fs::read_to_string(&path).map_err(|source| DriverError::Io {
+            action: "read",
+            path: path.clone(),
+            source,
+        })?;
+        let mutants = mutation::sample(mutation::mutants(&original), mutation::MAX_MUTANTS);
+        let restore = Restore {
+            path: &path,
+            original: &original,
+        };
+        let survivors = self.survivors(dir, &path, &mutants, session);
+        restore.finish()?;
+        let Some(survivors) = survivors? else {
+            // Cut short by an abort, so there is no score to report
+            return Ok(None);
+        };
+        let killed = mutants.len() - survivors.len();
+        on_progress(&Progress::Mutants {
+            killed,
+            total: mutants.len(),
+        });
+        let score = mutation::score(killed, mutants.len());
+        if score >= min_score {
+            return Ok(None);
+        }
+        let survivors: Vec<String> = survivors.iter().map(ToString::to_string).collect();
+        Ok(Some(self.templates.render(
+            "surviving_mutants",
+            context! { score, min_score, survivors },
+        )?))
+    }
+
+    /// Writes each of `mutants` to the processor at `path` in turn, returning
+    /// those that pass [`Verifier::build`], which builds the crate and runs
+    /// its tests, and prove every rule, or `None` if the run is aborted first.
+    fn survivors<'m>(
+        &self,
+        dir: &Path,
+        path: &Path,
+        mutants: &'m [Mutant],
+        session: &mut Session,
+    ) -> Result<Option<Vec<&'m Mutant>>, DriverError> {
+        let rules = prover::find_rules(dir)?;
+        let mut survivors = Vec::new();
+        for mutant in mutants {
+            if self.aborted() {
+                return Ok(None);
+            }
+            std::fs::write(path, &mutant.source).map_err(|source| DriverError::Io {
+                action: "write",
+                path: path.to_path_buf(),
+                source,
+            })?;
+            if let Verdict::Failed(_) = self.verifier.build(dir)? {
+                continue;
+            }
+            let results = self.verifier.verify(dir, &rules)?;
+            session.prover_jobs += results.iter().filter(|result| !result.cached).count();
+            if results
+                .iter()
+                .all(|result| result.status == RuleStatus::Verified)
+            {
+                survivors.push(mutant);
+            }
+        }
+        Ok(Some(survivors))
+    }
+}
+
+/// Writes the processor back as it was when dropped, so that no mutant is
+/// left in the crate however mutation testing ends, even by a panic.
+struct Restore<'a> {
+    path: &'a Path,
+    original: &'a str,
+}
+
+impl Restore<'_> {
+    /// Writes the processor back, failing if it cannot be.
+    fn finish(self) -> Result<(), DriverError> {
+        let result = std::fs::write(self.path, self.original).map_err(|source| DriverError::Io {
+            action: "restore",
+            path: self.path.to_path_buf(),
+            source,
+        });
+        std::mem::forget(self);
+        result
+    }
+}
+
+impl Drop for Restore<'_> {
+    fn drop(&mut self) {
+        // Unwinding, with no one to report a failure to
+        let _ = std::fs::write(self.path, self.original);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{ChatResponse, ContentBlock, StopReason};
+    use crate::prover::SolanaProver;
+    use std::cell::RefCell;
+    use std::sync::Mutex;
+
+    /// Replies with the scripted responses in order.
+    struct Scripted(Mutex<Vec<&'static str>>);
+
+    impl Provider for Scripted {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn model(&self) -> &str {
+            "scripted"
+        }
+
+        fn chat(&self, _: &ChatRequest) -> Result<ChatResponse, LlmError> {
+            let text = self.0.lock().unwrap().remove(0);
+            Ok(ChatResponse {
+                content: vec![ContentBlock::Text {
+                    text: text.to_string(),
+                }],
+                stop_reason: StopReason::EndTurn,
+                usage: Usage {
+                    input_tokens: 10,
+                    output_tokens: 1,
+                },
+            })
+        }
+    }
+
+    /// Streams the scripted responses line by line, counting the lines read.
+    struct Trickled {
+        responses: Mutex<Vec<&'static str>>,
+        read: Mutex<usize>,
+    }
+
+    impl Provider for Trickled {
+        fn name(&self) -> &str {
+            "trickled"
+        }
+
+        fn model(&self) -> &str {
+            "trickled"
+        }
+
+        fn chat(&self, _: &ChatRequest) -> Result<ChatResponse, LlmError> {
+            unreachable!("responses are streamed")
+        }
+
+        fn chat_stream(
+            &self,
+            _: &ChatRequest,
+            on_event: &mut dyn FnMut(StreamEvent) -> bool,
+        ) -> Result<ChatResponse, LlmError> {
+            let text = self.responses.lock().unwrap().remove(0);
+            let mut response = ChatResponse {
+                content: vec![ContentBlock::Text {
+                    text: String::new(),
+                }],
+                stop_reason: StopReason::EndTurn,
+                usage: Usage::default(),
+            };
+            for line in text.split_inclusive('\n') {
+                *self.read.lock().unwrap() += 1;
+                if let ContentBlock::Text { text } = &mut response.content[0] {
+                    text.push_str(line);
+                }
+                if !on_event(StreamEvent::TextDelta(line.to_string())) {
+                    response.stop_reason = StopReason::Aborted;
+                    break;
+                }
+            }
+            Ok(response)
+        }
+    }
+
+    /// Builds once `src/processor.rs` contains `compiles` and verifies once it
+    /// contains `fixed`.
+    struct Marker(RefCell<usize>);
+
+    impl Verifier for Marker {
+        fn build(&self, dir: &Path) -> Result<Verdict, DriverError> {
+            *self.0.borrow_mut() += 1;
+            let source = std::fs::read_to_string(dir.join("src/processor.rs")).unwrap();
+            Ok(if source.contains("compiles") {
+                Verdict::Passed
+            } else {
+                Verdict::Failed("error[E0425]: cannot find value".to_string())
+            })
+        }
+
+        fn verify(&self, dir: &Path, _: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+            let source = std::fs::read_to_string(dir.join("src/processor.rs")).unwrap();
+            let status = if source.contains("fixed") {
+                RuleStatus::Verified
+            } else {
+                RuleStatus::Violated
+            };
+            Ok(vec![RuleResult {
+                rule: "rule_deposit".to_string(),
+                status,
+                output: String::new(),
+                counterexample: None,
+                cached: false,
+            }])
+        }
+    }
+
+    fn spec() -> Spec {
+        Spec::from_yaml_str(include_str!("../../../examples/specs/vault.yaml")).unwrap()
+    }
+
+    #[test]
+    fn test_parse_edits() {
+        let response = "Here it is.\n\n```rust src/processor.rs\nfn a() {}\n```\n\n\
+                        ```rust\nnot a file\n```\n```toml Cargo.toml\n[package]\n```\n";
+        assert_eq!(
+            parse_edits(response),
+            [
+                FileEdit {
+                    path: PathBuf::from("src/processor.rs"),
+                    contents: "fn a() {}\n".to_string(),
+                },
+                FileEdit {
+                    path: PathBuf::from("Cargo.toml"),
+                    contents: "[package]\n".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rejection() {
+        assert_eq!(rejection(Path::new("src/processor.rs")), None);
+        assert!(rejection(Path::new("src/certora/spec.rs")).is_some());
+        assert!(rejection(Path::new("src/certora.rs")).is_some());
+        assert!(rejection(Path::new("src/spec_tests.rs")).is_some());
+        assert!(rejection(Path::new("src/regressions.rs")).is_some());
+        assert!(rejection(Path::new("../outside.rs")).is_some());
+        assert!(rejection(Path::new("/etc/passwd")).is_some());
+    }
+
+    #[test]
+    fn test_run_until_verified() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/processor.rs"), "// TODO\n").unwrap();
+        let provider = Scripted(Mutex::new(vec![
+            "I am not sure.",
+            "```rust src/processor.rs\n// broken\n```",
+            "```rust src/processor.rs\n// compiles\n```\n```rust src/certora/spec.rs\n```",
+            "```rust src/processor.rs\n// compiles, fixed\n```",
+        ]));
+        let verifier = Marker(RefCell::new(0));
+        let mut progress = Vec::new();
+        let templates = Templates::builtin("svm");
+        let report = Driver::new(&provider, &verifier, &templates, DriverOptions::default())
+            .run(&spec(), dir.path(), &mut |step| progress.push(step.clone()))
+            .unwrap();
+        assert!(report.success);
+        assert_eq!(report.iterations, 4);
+        assert_eq!(report.usage.input_tokens, 40);
+        // The response without files and the one touching the rules are not
+        // built
+        assert_eq!(*verifier.0.borrow(), 2);
+        assert!(!dir.path().join("src/certora/spec.rs").exists());
+        assert_eq!(
+            progress.last(),
+            Some(&Progress::Rule(RuleResult {
+                rule: "rule_deposit".to_string(),
+                status: RuleStatus::Verified,
+                output: String::new(),
+                counterexample: None,
+                cached: false,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/processor.rs"), "// TODO\n").unwrap();
+        let provider = Trickled {
+            responses: Mutex::new(vec![
+                "```rust src/state.rs\n// compiles\n```\n```rust src/processor.rs\n\
+                 fn a() {\n```\n```rust src/lib.rs\nmod processor;\n```\nThat is all.",
+                "```rust src/processor.rs\n// compiles, fixed\n```\n```toml Cargo.toml\n\
+                 [package]\n",
+            ]),
+            read: Mutex::new(0),
+        };
+        let verifier = Marker(RefCell::new(0));
+        let templates = Templates::builtin("svm");
+        let options = DriverOptions {
+            stream: true,
+            ..DriverOptions::default()
+        };
+        let mut progress = Vec::new();
+        let mut output = String::new();
+        let report = Driver::new(&provider, &verifier, &templates, options)
+            .run(&spec(), dir.path(), &mut |step| match step {
+                Progress::Output(text) => output.push_str(text),
+                step => progress.push(step.clone()),
+            })
+            .unwrap();
+        assert!(report.success);
+        // The output arrives as it is read
+        assert!(output.starts_with("```rust src/state.rs\n// compiles\n```\n```rust"));
+        // The first response is read up to the file that does not parse,
+        // and nothing is built from it
+        assert_eq!(*provider.read.lock().unwrap(), 6 + 5);
+        assert_eq!(*verifier.0.borrow(), 1);
+        assert!(!dir.path().join("src/lib.rs").exists());
+        assert_eq!(
+            progress[..4],
+            [
+                Progress::Iteration(1),
+                Progress::Wrote(vec![PathBuf::from("src/state.rs")]),
+                Progress::SyntaxError {
+                    path: PathBuf::from("src/processor.rs"),
+                    error: "line 1: `{` is never closed".to_string(),
+                },
+                Progress::Iteration(2),
+            ]
+        );
+        // The last file is cut off by the end of the response
+        assert_eq!(
+            progress[4..6],
+            [
+                Progress::Wrote(vec![PathBuf::from("src/processor.rs")]),
+                Progress::Wrote(vec![PathBuf::from("Cargo.toml")]),
+            ]
+        );
+        let session = Session::load(dir.path(), &spec()).unwrap();
+        assert!(session.messages[1].text().ends_with("fn a() {\n```\n"));
+        assert!(session.messages[2].text().contains(
+            "src/processor.rs: it does not parse (line 1: `{` is never closed), so the rest of \
+             the reply was not read"
+        ));
+    }
+
+    #[test]
+    fn test_patch() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("src/processor.rs"),
+            "fn deposit() {\n    // TODO\n}\n",
+        )
+        .unwrap();
+        let provider = Scripted(Mutex::new(vec![
+            "```diff src/processor.rs\n@@ -1,2 +1,2 @@\n fn deposit() {\n-    // missing\n\
+             +    // compiles\n```",
+            "```diff src/processor.rs\n--- a/src/processor.rs\n+++ b/src/processor.rs\n\
+             @@ -1,3 +1,3 @@\n fn deposit() {\n-    // TODO\n+    // compiles, fixed\n }\n```",
+        ]));
+        let verifier = Marker(RefCell::new(0));
+        let templates = Templates::builtin("svm");
+        let options = DriverOptions {
+            patch: true,
+            ..DriverOptions::default()
+        };
+        let report = Driver::new(&provider, &verifier, &templates, options)
+            .run(&spec(), dir.path(), &mut |_| {})
+            .unwrap();
+        assert!(report.success);
+        assert_eq!(*verifier.0.borrow(), 1);
+        let processor = std::fs::read_to_string(dir.path().join("src/processor.rs")).unwrap();
+        assert_eq!(
+            provenance::strip(&processor),
+            "fn deposit() {\n    // compiles, fixed\n}\n"
+        );
+        // Stamped as written by the model
+        let stamp = Provenance::parse(&processor).unwrap();
+        assert_eq!(stamp.model.as_deref(), Some("scripted"));
+        let session = Session::load(dir.path(), &spec()).unwrap();
+        assert!(session
+            .system
+            .unwrap()
+            .contains("reply with a unified diff"));
+        assert_eq!(
+            session.messages[2].text(),
+            "These files were not written:\n\n- src/processor.rs: the diff does not apply: hunk 1 \
+             (`@@ -1,2 +1,2 @@`) does not match the file\n  The file is now:\n\n```\n\
+             fn deposit() {\n    // TODO\n}\n```"
+        );
+    }
+
+    #[test]
+    fn test_git() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .unwrap()
+        };
+        git(&["init", "--quiet"]);
+        git(&["config", "user.name", "Test"]);
+        git(&["config", "user.email", "test@example.com"]);
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/processor.rs"), "// TODO\n").unwrap();
+        let provider = Scripted(Mutex::new(vec![
+            "```rust src/processor.rs\n// broken\n```",
+            "```rust src/processor.rs\n// compiles, fixed\n```",
+        ]));
+        let verifier = Marker(RefCell::new(0));
+        let templates = Templates::builtin("svm");
+        let repo = Repo::open(dir.path()).unwrap();
+        let report = Driver::new(&provider, &verifier, &templates, DriverOptions::default())
+            .with_repo(&repo)
+            .run(&spec(), dir.path(), &mut |_| {})
+            .unwrap();
+        assert!(report.success);
+        let log = String::from_utf8(git(&["log", "--format=%B"]).stdout).unwrap();
+        assert!(log.starts_with(
+            "vault: iteration 2, verified\n\nWrote src/processor.rs.\n\
+             The build and tests passed.\nVERIFIED: rule_deposit\n\n\
+             Iteration: 2\nModel: scripted/scripted\nTokens: 22\n"
+        ));
+        assert!(log.contains("vault: iteration 1, build failed\n"));
+    }
+
+    #[test]
+    fn test_review() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/processor.rs"), "// TODO\n").unwrap();
+        let provider = Scripted(Mutex::new(vec![
+            "```rust src/processor.rs\n// compiles\n```",
+            "```rust src/processor.rs\n// compiles, fixed\n```",
+        ]));
+        let verifier = Marker(RefCell::new(0));
+        let templates = Templates::builtin("svm");
+        let mut shown = Vec::new();
+        let reviewer = crate::review::Prompt::new(&b"n\ny\n"[..], &mut shown);
+        let report = Driver::new(&provider, &verifier, &templates, DriverOptions::default())
+            .with_reviewer(&reviewer)
+            .run(&spec(), dir.path(), &mut |_| {})
+            .unwrap();
+        assert!(report.success);
+        // The declined file is not written, nor built
+        assert_eq!(*verifier.0.borrow(), 1);
+        let shown = String::from_utf8(shown).unwrap();
+        assert!(shown.contains(
+            "--- a/src/processor.rs\n+++ b/src/processor.rs\n@@ -1 +1 @@\n-// TODO\n+// compiles\n"
+        ));
+        let session = Session::load(dir.path(), &spec()).unwrap();
+        assert!(session.messages[2]
+            .text()
+            .contains("src/processor.rs: the reviewer declined the change"));
+        let decisions: Vec<(usize, bool, &str)> = session
+            .reviews
+            .iter()
+            .map(|review| (review.iteration, review.approved, review.reviewer.as_str()))
+            .collect();
+        assert_eq!(decisions, [(1, false, "user"), (2, true, "user")]);
+    }
+
+    #[test]
+    fn test_roles() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/processor.rs"), "// TODO\n").unwrap();
+        let provider = Scripted(Mutex::new(vec![
+            "```json\n{\"functions\": [{\"name\": \"withdraw\", \"notes\": [\"Round down.\"]}]}\n```",
+            "```rust src/processor.rs\n// compiles\n```",
+            "```json\n{\"diagnoses\": [{\"rule\": \"rule_deposit\", \"cause\": \"Nothing is \
+             fixed.\", \"fix\": \"Fix it.\"}]}\n```",
+            "```rust src/processor.rs\n// compiles, fixed\n```",
+        ]));
+        let verifier = Marker(RefCell::new(0));
+        let templates = Templates::builtin("svm");
+        let options = DriverOptions {
+            roles: true,
+            ..DriverOptions::default()
+        };
+        let report = Driver::new(&provider, &verifier, &templates, options)
+            .run(&spec(), dir.path(), &mut |_| {})
+            .unwrap();
+        assert!(report.success);
+        assert_eq!(report.iterations, 2);
+        // The specifier and the critic are paid for too
+        assert_eq!(report.usage.input_tokens, 40);
+        let session = Session::load(dir.path(), &spec()).unwrap();
+        assert!(session.messages[0]
+            .text()
+            .contains("- `withdraw`: Round down."));
+        let feedback = session.messages[2].text();
+        assert!(feedback.contains("`rule_deposit`"));
+        assert!(feedback.contains("- `rule_deposit`: Nothing is fixed. Fix: Fix it."));
+    }
+
+    /// Passes everything, with calls of `withdraw` consuming 2000 compute
+    /// units once `src/processor.rs` says it is cheap and 5000 before.
+    struct Metered;
+
+    impl Verifier for Metered {
+        fn build(&self, _: &Path) -> Result<Verdict, DriverError> {
+            Ok(Verdict::Passed)
+        }
+
+        fn verify(&self, _: &Path, _: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+            Ok(Vec::new())
+        }
+
+        fn compute_units(&self, dir: &Path) -> Result<Vec<Measurement>, DriverError> {
+            let source = std::fs::read_to_string(dir.join("src/processor.rs")).unwrap();
+            let units = if source.contains("cheap") { 2000 } else { 5000 };
+            Ok(vec![Measurement {
+                function: "withdraw".to_string(),
+                example: "everything".to_string(),
+                units,
+            }])
+        }
+    }
+
+    #[test]
+    fn test_run_until_within_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/processor.rs"), "// TODO\n").unwrap();
+        let provider = Scripted(Mutex::new(vec![
+            "```rust src/processor.rs\n// expensive\n```",
+            "```rust src/processor.rs\n// cheap\n```",
+        ]));
+        let mut spec = spec();
+        spec.functions[1].compute_units = Some(3000);
+        let templates = Templates::builtin("svm");
+        let mut progress = Vec::new();
+        let report = Driver::new(&provider, &Metered, &templates, DriverOptions::default())
+            .run(&spec, dir.path(), &mut |step| progress.push(step.clone()))
+            .unwrap();
+        assert!(report.success);
+        assert_eq!(report.iterations, 2);
+        assert_eq!(
+            progress.last(),
+            Some(&Progress::ComputeUnits {
+                function: "withdraw".to_string(),
+                units: 2000,
+                budget: 3000,
+            })
+        );
+        let session = Session::load(dir.path(), &spec).unwrap();
+        assert!(session.messages[2].text().contains(
+            "- `withdraw`: 5000 compute units in the `everything` call, over the budget of 3000"
+        ));
+    }
+
+    /// Passes everything, with calls of `mul_div` taking 10 nanoseconds once
+    /// `src/helpers.rs` says it is fast and 100 before.
+    struct Benched;
+
+    impl Verifier for Benched {
+        fn build(&self, _: &Path) -> Result<Verdict, DriverError> {
+            Ok(Verdict::Passed)
+        }
+
+        fn verify(&self, _: &Path, _: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+            Ok(Vec::new())
+        }
+
+        fn bench(&self, dir: &Path) -> Result<Vec<Timing>, DriverError> {
+            let source = std::fs::read_to_string(dir.join("src/helpers.rs")).unwrap();
+            let nanos = if source.contains("fast") { 10 } else { 100 };
+            Ok(vec![Timing {
+                helper: "mul_div".to_string(),
+                nanos,
+            }])
+        }
+    }
+
+    #[test]
+    fn test_run_until_fast_enough() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/helpers.rs"), "// TODO\n").unwrap();
+        let provider = Scripted(Mutex::new(vec![
+            "```rust src/helpers.rs\n// slow\n```",
+            "```rust src/helpers.rs\n// fast\n```",
+        ]));
+        let mut spec = spec();
+        spec.helpers.push(spec::Helper {
+            name: "mul_div".to_string(),
+            description: None,
+            args: Vec::new(),
+            returns: spec::Type::U64,
+            bench: BTreeMap::new(),
+            throughput: Some(50_000_000),
+        });
+        let templates = Templates::builtin("svm");
+        let mut progress = Vec::new();
+        let report = Driver::new(&provider, &Benched, &templates, DriverOptions::default())
+            .run(&spec, dir.path(), &mut |step| progress.push(step.clone()))
+            .unwrap();
+        assert!(report.success);
+        assert_eq!(report.iterations, 2);
+        assert_eq!(
+            progress.last(),
+            Some(&Progress::Throughput {
+                helper: "mul_div".to_string(),
+                calls: 100_000_000,
+                target: 50_000_000,
+            })
+        );
+        let session = Session::load(dir.path(), &spec).unwrap();
+        assert!(session
+            .system
+            .as_deref()
+            .unwrap()
+            .contains("src/helpers.rs"));
+        assert!(session.messages[2].text().contains(
+            "- `mul_div`: 10000000 calls per second in `cargo bench --bench helpers`, \
+             below the target of 50000000"
+        ));
+    }
+
+    /// Passes everything but clippy, until `src/processor.rs` no longer
+    /// says it is clumsy.
+    struct Linted;
+
+    impl Verifier for Linted {
+        fn build(&self, _: &Path) -> Result<Verdict, DriverError> {
+            Ok(Verdict::Passed)
+        }
+
+        fn verify(&self, _: &Path, _: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+            Ok(Vec::new())
+        }
+
+        fn tidy(&self, dir: &Path) -> Result<Verdict, DriverError> {
+            let source = std::fs::read_to_string(dir.join("src/processor.rs")).unwrap();
+            if source.contains("clumsy") {
+                return Ok(Verdict::Failed(
+                    "error: this `if` has identical blocks\n".to_string(),
+                ));
+            }
+            Ok(Verdict::Passed)
+        }
+    }
+
+    #[test]
+    fn test_run_until_lint_free() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/processor.rs"), "// TODO\n").unwrap();
+        let provider = Scripted(Mutex::new(vec![
+            "```rust src/processor.rs\n// clumsy\n```",
+            "```rust src/processor.rs\n// tidy\n```",
+        ]));
+        let spec = spec();
+        let templates = Templates::builtin("svm");
+        let mut progress = Vec::new();
+        let report = Driver::new(&provider, &Linted, &templates, DriverOptions::default())
+            .run(&spec, dir.path(), &mut |step| progress.push(step.clone()))
+            .unwrap();
+        assert!(report.success);
+        assert_eq!(report.iterations, 2);
+        assert!(progress.contains(&Progress::Lints(Verdict::Failed(
+            "error: this `if` has identical blocks\n".to_string()
+        ))));
+        assert_eq!(progress.last(), Some(&Progress::Lints(Verdict::Passed)));
+        let session = Session::load(dir.path(), &spec).unwrap();
+        let feedback = session.messages[2].text();
+        assert!(feedback.contains("clippy reports lints"), "{feedback}");
+        assert!(feedback.contains("this `if` has identical blocks"));
+    }
+
+    /// Passes everything but, once `src/processor.rs` says it is tested, the
+    /// build of a processor comparing with `<=`.
+    struct Tested;
+
+    impl Verifier for Tested {
+        fn build(&self, dir: &Path) -> Result<Verdict, DriverError> {
+            let source = std::fs::read_to_string(dir.join("src/processor.rs")).unwrap();
+            Ok(if source.contains("// tested") && source.contains(" <= ") {
+                Verdict::Failed("test failed".to_string())
+            } else {
+                Verdict::Passed
+            })
+        }
+
+        fn verify(&self, _: &Path, _: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_run_until_mutants_killed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let provider = Scripted(Mutex::new(vec![
+            "```rust src/processor.rs\nfn lt(a: u8, b: u8) -> bool { a < b }\n```",
+            "```rust src/processor.rs\nfn lt(a: u8, b: u8) -> bool { a < b }\n// tested\n```",
+        ]));
+        let options = DriverOptions {
+            min_mutation_score: Some(100),
+            ..DriverOptions::default()
+        };
+        let mut progress = Vec::new();
+        let templates = Templates::builtin("svm");
+        let report = Driver::new(&provider, &Tested, &templates, options)
+            .run(&spec(), dir.path(), &mut |step| progress.push(step.clone()))
+            .unwrap();
+        assert!(report.success);
+        assert_eq!(report.iterations, 2);
+        let mutants: Vec<&Progress> = progress
+            .iter()
+            .filter(|step| matches!(step, Progress::Mutants { .. }))
+            .collect();
+        assert_eq!(
+            mutants,
+            [
+                &Progress::Mutants {
+                    killed: 0,
+                    total: 1
+                },
+                &Progress::Mutants {
+                    killed: 1,
+                    total: 1
+                },
+            ]
+        );
+        let session = Session::load(dir.path(), &spec()).unwrap();
+        assert!(session.messages[2]
+            .text()
+            .contains("only 0% of the mutants of src/processor.rs"));
+        assert!(session.messages[2]
+            .text()
+            .contains("- line 2: `<` replaced by `<=`"));
+        // The implementation is left as the model wrote it, below its stamp
+        let processor = std::fs::read_to_string(dir.path().join("src/processor.rs")).unwrap();
+        assert_eq!(
+            provenance::strip(&processor),
+            "fn lt(a: u8, b: u8) -> bool { a < b }\n// tested\n"
+        );
+    }
+
+    /// The processor the mutant tests below start from.
+    const MUTATED: &str = "fn f(a: u8, b: u8) -> bool { a < b && a + 1 > b }\n";
+
+    /// Passes everything, handing each build of a mutant of [`MUTATED`] to
+    /// `on_mutant` and counting them.
+    struct Mutated<F> {
+        on_mutant: F,
+        builds: RefCell<usize>,
+    }
+
+    impl<F: Fn()> Verifier for Mutated<F> {
+        fn build(&self, dir: &Path) -> Result<Verdict, DriverError> {
+            let source = std::fs::read_to_string(dir.join("src/processor.rs")).unwrap();
+            if !source.contains(MUTATED) {
+                *self.builds.borrow_mut() += 1;
+                (self.on_mutant)();
+            }
+            Ok(Verdict::Passed)
+        }
+
+        fn verify(&self, _: &Path, _: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_mutants_stop_on_abort() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let response = format!("```rust src/processor.rs\n{MUTATED}```");
+        let provider = Scripted(Mutex::new(vec![response.leak()]));
+        let options = DriverOptions {
+            min_mutation_score: Some(100),
+            ..DriverOptions::default()
+        };
+        let control = Control::default();
+        let verifier = Mutated {
+            on_mutant: || control.abort(),
+            builds: RefCell::new(0),
+        };
+        let templates = Templates::builtin("svm");
+        let report = Driver::new(&provider, &verifier, &templates, options)
+            .with_control(&control)
+            .run(&spec(), dir.path(), &mut |_| {})
+            .unwrap();
+        assert!(report.aborted && !report.success);
+        // No mutant is built once the run is aborted
+        assert_eq!(*verifier.builds.borrow(), 1);
+        let processor = std::fs::read_to_string(dir.path().join("src/processor.rs")).unwrap();
+        assert_eq!(provenance::strip(&processor), MUTATED);
+        // The check did not finish, so the response is checked on resume
+        let session = Session::load(dir.path(), &spec()).unwrap();
+        assert_eq!(session.messages.last().unwrap().role, Role::Assistant);
+    }
+
+    #[test]
+    fn test_mutants_restored_on_panic() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let response = format!("```rust src/processor.rs\n{MUTATED}```");
+        let provider = Scripted(Mutex::new(vec![response.leak()]));
+        let options = DriverOptions {
+            min_mutation_score: Some(100),
+            ..DriverOptions::default()
+        };
+        let verifier = Mutated {
+            on_mutant: || panic!("the build of a mutant panicked"),
+            builds: RefCell::new(0),
+        };
+        let templates = Templates::builtin("svm");
+        let driver = Driver::new(&provider, &verifier, &templates, options);
+        let run = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            driver.run(&spec(), dir.path(), &mut |_| {})
+        }));
+        assert!(run.is_err());
+        let processor = std::fs::read_to_string(dir.path().join("src/processor.rs")).unwrap();
+        assert_eq!(provenance::strip(&processor), MUTATED);
+    }
+
+    #[test]
+    fn test_run_incremental_without_changes() {
+        let spec = spec();
+        let dir = tempfile::tempdir().unwrap();
+        let files = materialize::generate(&spec, spec::Target::Svm).unwrap();
+        materialize::write(&files, dir.path(), true).unwrap();
+        let processor = dir.path().join("src/processor.rs");
+        let source = std::fs::read_to_string(&processor).unwrap();
+        std::fs::write(&processor, format!("// compiles, fixed\n{source}")).unwrap();
+        crate::drift::record(&spec, dir.path()).unwrap();
+
+        // The model is not asked for anything
+        let provider = Scripted(Mutex::new(Vec::new()));
+        let verifier = Marker(RefCell::new(0));
+        let options = DriverOptions {
+            incremental: true,
+            ..DriverOptions::default()
+        };
+        let templates = Templates::builtin("svm");
+        let report = Driver::new(&provider, &verifier, &templates, options)
+            .run(&spec, dir.path(), &mut |_| {})
+            .unwrap();
+        assert!(report.success);
+        assert_eq!(report.iterations, 0);
+        assert_eq!(*verifier.0.borrow(), 1);
+    }
+
+    #[test]
+    fn test_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let templates = Templates::builtin("svm");
+        let verifier = Marker(RefCell::new(0));
+        let provider = Scripted(Mutex::new(vec![
+            "```rust src/processor.rs\n// compiles\n```",
+        ]));
+        let options = DriverOptions {
+            max_iterations: 1,
+            ..DriverOptions::default()
+        };
+        let report = Driver::new(&provider, &verifier, &templates, options)
+            .run(&spec(), dir.path(), &mut |_| {})
+            .unwrap();
+        assert!(!report.success);
+
+        // Only the new response is asked for
+        let provider = Scripted(Mutex::new(vec![
+            "```rust src/processor.rs\n// compiles, fixed\n```",
+        ]));
+        let options = DriverOptions {
+            max_iterations: 2,
+            resume: true,
+            ..DriverOptions::default()
+        };
+        let report = Driver::new(&provider, &verifier, &templates, options)
+            .run(&spec(), dir.path(), &mut |_| {})
+            .unwrap();
+        assert!(report.success);
+        assert_eq!(report.iterations, 2);
+        assert_eq!(report.usage.input_tokens, 20);
+        let session = Session::load(dir.path(), &spec()).unwrap();
+        assert_eq!(session.messages.len(), 4);
+        assert_eq!(session.written, [PathBuf::from("src/processor.rs")]);
+        assert_eq!(session.rules["rule_deposit"], RuleStatus::Verified);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parallel_verify() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        // Proves every rule but `rule_c`, after a while
+        let cli = dir.path().join("prover.sh");
+        std::fs::write(
+            &cli,
+            "#!/bin/sh\nsleep 0.1\nif [ \"$2\" = rule_c ]; then echo \"$2: VIOLATED\"; \
+             else echo \"$2: VERIFIED\"; fi\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&cli, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let verifier = CargoVerifier {
+            prover: Some(Backend::Certora(SolanaProver {
+                cli: cli.display().to_string(),
+                prover_args: Vec::new(),
+            })),
+            jobs: 3,
+            cache: false,
+            sandbox: Sandbox::None,
+            clippy: false,
+        };
+        let rules: Vec<String> = ["rule_a", "rule_b", "rule_c", "rule_d"]
+            .map(String::from)
+            .to_vec();
+        let results = verifier.verify(dir.path(), &rules).unwrap();
+        let verdicts: Vec<_> = results
+            .iter()
+            .map(|r| (r.rule.as_str(), r.status))
+            .collect();
+        assert_eq!(
+            verdicts,
+            [
+                ("rule_a", RuleStatus::Verified),
+                ("rule_b", RuleStatus::Verified),
+                ("rule_c", RuleStatus::Violated),
+                ("rule_d", RuleStatus::Verified),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cached_verify() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let spec_rs = dir.path().join("src/certora/spec.rs");
+        std::fs::create_dir_all(spec_rs.parent().unwrap()).unwrap();
+        std::fs::write(
+            &spec_rs,
+            "#[rule]\npub fn rule_a() {}\n#[rule]\npub fn rule_b() {}\n",
+        )
+        .unwrap();
+        // Logs the rules it proves
+        let cli = dir.path().join("prover.sh");
+        std::fs::write(
+            &cli,
+            "#!/bin/sh\necho \"$2\" >> proved.log\necho \"$2: VERIFIED\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&cli, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let verifier = CargoVerifier {
+            prover: Some(Backend::Certora(SolanaProver {
+                cli: cli.display().to_string(),
+                prover_args: Vec::new(),
+            })),
+            jobs: 1,
+            cache: true,
+            sandbox: Sandbox::None,
+            clippy: false,
+        };
+        let rules: Vec<String> = ["rule_a", "rule_b"].map(String::from).to_vec();
+        let proved = || std::fs::read_to_string(dir.path().join("proved.log")).unwrap();
+
+        let first = verifier.verify(dir.path(), &rules).unwrap();
+        assert!(first.iter().all(|result| !result.cached));
+        let second = verifier.verify(dir.path(), &rules).unwrap();
+        assert!(second.iter().all(|result| result.cached));
+        assert_eq!(second[0].status, RuleStatus::Verified);
+        assert_eq!(proved(), "rule_a\nrule_b\n");
+
+        // Only the changed rule is proved again
+        std::fs::write(
+            &spec_rs,
+            "#[rule]\npub fn rule_a() { assert!(true) }\n#[rule]\npub fn rule_b() {}\n",
+        )
+        .unwrap();
+        let third = verifier.verify(dir.path(), &rules).unwrap();
+        assert!(!third[0].cached && third[1].cached);
+        assert_eq!(proved(), "rule_a\nrule_b\nrule_a\n");
+    }
+
+    #[test]
+    fn test_budgets() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/certora")).unwrap();
+        std::fs::write(
+            dir.path().join("src/certora/spec.rs"),
+            "#[rule]\npub fn rule_a() {}\n#[rule]\npub fn rule_b() {}\n",
+        )
+        .unwrap();
+        let templates = Templates::builtin("svm");
+        let verifier = Marker(RefCell::new(0));
+        let responses = || {
+            Scripted(Mutex::new(vec![
+                "```rust src/processor.rs\n// compiles\n```",
+                "```rust src/processor.rs\n// compiles\n```",
+                "```rust src/processor.rs\n// compiles\n```",
+            ]))
+        };
+
+        let options = DriverOptions {
+            max_tokens: Some(15),
+            ..DriverOptions::default()
+        };
+        let report = Driver::new(&responses(), &verifier, &templates, options)
+            .run(&spec(), dir.path(), &mut |_| {})
+            .unwrap();
+        assert_eq!(report.exhausted, Some(Limit::Tokens));
+        assert_eq!(report.iterations, 2);
+
+        // Only one of the two rules is proved
+        let options = DriverOptions {
+            max_prover_jobs: Some(1),
+            ..DriverOptions::default()
+        };
+        let report = Driver::new(&responses(), &verifier, &templates, options)
+            .run(&spec(), dir.path(), &mut |_| {})
+            .unwrap();
+        assert_eq!(report.exhausted, Some(Limit::ProverJobs));
+        assert_eq!(report.iterations, 1);
+        assert_eq!(report.prover_jobs, 1);
+        assert_eq!(report.unproved, ["rule_b"]);
+        assert_eq!(report.rules["rule_deposit"], RuleStatus::Violated);
+    }
+
+    #[test]
+    fn test_run_exhausted() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let provider = Scripted(Mutex::new(vec![
+            "```rust src/processor.rs\n// compiles\n```",
+            "```rust src/processor.rs\n// compiles\n```",
+        ]));
+        let verifier = Marker(RefCell::new(0));
+        let options = DriverOptions {
+            max_iterations: 2,
+            ..DriverOptions::default()
+        };
+        let templates = Templates::builtin("svm");
+        let report = Driver::new(&provider, &verifier, &templates, options)
+            .run(&spec(), dir.path(), &mut |_| {})
+            .unwrap();
+        assert!(!report.success);
+        assert_eq!(report.iterations, 2);
+        assert_eq!(report.exhausted, Some(Limit::Iterations));
+        assert!(report
+            .feedback
+            .unwrap()
+            .starts_with("Rule `rule_deposit` is VIOLATED"));
+    }
+
+    #[test]
+    fn test_control() {
+        /// Builds anything and proves every rule but `rule_b`.
+        struct AllButB;
+
+        impl Verifier for AllButB {
+            fn build(&self, _: &Path) -> Result<Verdict, DriverError> {
+                Ok(Verdict::Passed)
+            }
+
+            fn verify(&self, _: &Path, rules: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+                Ok(rules
+                    .iter()
+                    .map(|rule| RuleResult {
+                        rule: rule.clone(),
+                        status: if rule == "rule_b" {
+                            RuleStatus::Violated
+                        } else {
+                            RuleStatus::Verified
+                        },
+                        output: String::new(),
+                        counterexample: None,
+                        cached: false,
+                    })
+                    .collect())
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/certora")).unwrap();
+        std::fs::write(
+            dir.path().join("src/certora/spec.rs"),
+            "#[rule]\npub fn rule_a() {}\n#[rule]\npub fn rule_b() {}\n",
+        )
+        .unwrap();
+        let templates = Templates::builtin("svm");
+        let response = || Scripted(Mutex::new(vec!["```rust src/processor.rs\n// done\n```"]));
+
+        let control = Control::default();
+        assert!(control.skip("rule_b"));
+        let mut output = String::new();
+        let report = Driver::new(&response(), &AllButB, &templates, DriverOptions::default())
+            .with_control(&control)
+            .run(&spec(), dir.path(), &mut |progress| {
+                if let Progress::Output(text) = progress {
+                    output.push_str(text);
+                }
+            })
+            .unwrap();
+        assert!(report.success && !report.aborted);
+        assert_eq!(report.skipped, ["rule_b"]);
+        assert_eq!(report.rules.keys().collect::<Vec<_>>(), ["rule_a"]);
+        assert!(output.contains("// done"));
+        // Skipping a skipped rule proves it again
+        assert!(!control.skip("rule_b"));
+        assert!(control.skipped().is_empty());
+
+        // Aborted once the response is in, the crate is left unchecked
+        let control = Control::default();
+        let verifier = Marker(RefCell::new(0));
+        let report = Driver::new(&response(), &verifier, &templates, DriverOptions::default())
+            .with_control(&control)
+            .run(&spec(), dir.path(), &mut |progress| {
+                if let Progress::Iteration(_) = progress {
+                    control.abort();
+                }
+            })
+            .unwrap();
+        assert!(report.aborted && !report.success);
+        assert_eq!(report.iterations, 1);
+        assert_eq!(*verifier.0.borrow(), 0);
+        assert!(report.exhausted.is_none());
+    }
+
+    #[test]
+    fn test_fast_tag() {
+        /// Proves every rule but `rule_b`, recording the batches proved.
+        #[derive(Default)]
+        struct Recording(RefCell<Vec<Vec<String>>>);
+
+        impl Verifier for Recording {
+            fn build(&self, _: &Path) -> Result<Verdict, DriverError> {
+                Ok(Verdict::Passed)
+            }
+
+            fn verify(&self, _: &Path, rules: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+                self.0.borrow_mut().push(rules.to_vec());
+                Ok(rules
+                    .iter()
+                    .map(|rule| RuleResult {
+                        rule: rule.clone(),
+                        status: if rule == "rule_b" {
+                            RuleStatus::Violated
+                        } else {
+                            RuleStatus::Verified
+                        },
+                        output: String::new(),
+                        counterexample: None,
+                        cached: false,
+                    })
+                    .collect())
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/certora")).unwrap();
+        std::fs::write(
+            dir.path().join("src/certora/spec.rs"),
+            "#[rule]\npub fn rule_a() {}\n#[rule]\npub fn rule_b() {}\n#[rule]\npub fn rule_c() {}\n",
+        )
+        .unwrap();
+        let templates = Templates::builtin("svm");
+        let options = || DriverOptions {
+            max_iterations: 1,
+            fast_tag: Some("fast".to_string()),
+            ..DriverOptions::default()
+        };
+        let run = |fast: &str| {
+            std::fs::write(
+                dir.path().join("Cargo.toml"),
+                format!("[package.metadata.certora.tags]\nfast = [\"{fast}\"]\n"),
+            )
+            .unwrap();
+            let verifier = Recording::default();
+            let provider = Scripted(Mutex::new(vec!["```rust src/processor.rs\n// done\n```"]));
+            let report = Driver::new(&provider, &verifier, &templates, options())
+                .run(&spec(), dir.path(), &mut |_| {})
+                .unwrap();
+            (report, verifier.0.into_inner())
+        };
+
+        // The fast rule fails, so the others wait for the next response
+        let (report, batches) = run("rule_b");
+        assert!(!report.success);
+        assert_eq!(batches, [["rule_b"]]);
+        assert_eq!(report.rules.keys().collect::<Vec<_>>(), ["rule_b"]);
+
+        let (_, batches) = run("rule_?");
+        assert_eq!(batches, [vec!["rule_a", "rule_b", "rule_c"]]);
+        let (_, batches) = run("rule_c");
+        assert_eq!(batches, [vec!["rule_c"], vec!["rule_a", "rule_b"]]);
+    }
+}
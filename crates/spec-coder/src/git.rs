@@ -0,0 +1,301 @@
+//! The history of a run in git.
+//!
+//! With git integration, a run starts a branch of its own and the crate is
+//! committed once it is materialized and again after every iteration, with a
+//! message saying which files the model wrote, whether the build passed and
+//! where every rule stands. The log of the branch then shows how a run
+//! converged on verified code, one response at a time, and any step of it
+//! can be checked out. Only the crate is committed, whatever else is staged
+//! or changed in the repository, and never the build output of the sandbox.
+
+use crate::driver::{Progress, Verdict};
+use crate::prover::RuleStatus;
+use crate::sandbox::SANDBOX_TARGET;
+use spec::Target;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// An error running git.
+#[derive(Debug, thiserror::Error)]
+pub enum GitError {
+    #[error("git is not installed")]
+    NotInstalled,
+    #[error("{} is not in a git repository", .0.display())]
+    NotARepository(PathBuf),
+    #[error("`git {command}` failed:\n{output}")]
+    Failed { command: String, output: String },
+    #[error("failed to access {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// The branch of a run of `spec` for `target`, started at `started` seconds
+/// since the epoch.
+pub fn branch_name(spec: &str, target: Target, started: u64) -> String {
+    format!("spec-coder/{spec}-{target}-{started}")
+}
+
+/// A crate in a git repository.
+#[derive(Clone, Debug)]
+pub struct Repo {
+    dir: PathBuf,
+}
+
+impl Repo {
+    /// The crate in `dir`, which must be in a git repository.
+    pub fn open(dir: &Path) -> Result<Repo, GitError> {
+        let repo = Repo {
+            dir: dir.to_path_buf(),
+        };
+        if !repo
+            .git(&["rev-parse", "--is-inside-work-tree"])?
+            .status
+            .success()
+        {
+            return Err(GitError::NotARepository(repo.dir));
+        }
+        Ok(repo)
+    }
+
+    /// Creates the branch `name` from the current commit and switches to it,
+    /// keeping the changes in the working tree.
+    pub fn create_branch(&self, name: &str) -> Result<(), GitError> {
+        self.run(&["checkout", "--quiet", "-b", name])
+    }
+
+    /// Commits the crate as it is, returning false if nothing changed.
+    pub fn commit(&self, message: &str) -> Result<bool, GitError> {
+        let exclude = format!(":(exclude){SANDBOX_TARGET}");
+        self.run(&["add", "--all", "--", ".", &exclude])?;
+        if self
+            .git(&["diff", "--cached", "--quiet", "--", "."])?
+            .status
+            .success()
+        {
+            return Ok(false);
+        }
+        self.run(&["commit", "--quiet", "--message", message, "--", "."])?;
+        Ok(true)
+    }
+
+    fn git(&self, args: &[&str]) -> Result<std::process::Output, GitError> {
+        Command::new("git")
+            .args(args)
+            .current_dir(&self.dir)
+            .output()
+            .map_err(|source| match source.kind() {
+                std::io::ErrorKind::NotFound => GitError::NotInstalled,
+                _ => GitError::Io {
+                    path: self.dir.clone(),
+                    source,
+                },
+            })
+    }
+
+    fn run(&self, args: &[&str]) -> Result<(), GitError> {
+        let output = self.git(args)?;
+        if output.status.success() {
+            return Ok(());
+        }
+        Err(GitError::Failed {
+            command: args.join(" "),
+            output: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+    }
+}
+
+/// What an iteration did, as the message of its commit.
+#[derive(Clone, Debug)]
+pub struct Summary<'a> {
+    pub spec: &'a str,
+    pub iteration: usize,
+    /// The model of the run, as `provider/model`
+    pub model: String,
+    /// The tokens used so far
+    pub tokens: u64,
+    /// The steps of the iteration
+    pub steps: &'a [Progress],
+    /// The latest status of every rule
+    pub rules: &'a BTreeMap<String, RuleStatus>,
+    pub success: bool,
+}
+
+impl fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut written = Vec::new();
+        let mut build = None;
+        let mut checked = false;
+        for step in self.steps {
+            match step {
+                Progress::Wrote(paths) => {
+                    written.extend(paths.iter().map(|path| path.display().to_string()))
+                }
+                Progress::Build(verdict) => build = Some(verdict),
+                Progress::Rule(_) => checked = true,
+                _ => {}
+            }
+        }
+        let verified = self
+            .rules
+            .values()
+            .filter(|status| **status == RuleStatus::Verified)
+            .count();
+        let outcome = match build {
+            _ if self.success => "verified".to_string(),
+            Some(Verdict::Failed(_)) => "build failed".to_string(),
+            _ if checked => format!("{verified} of {} rules verified", self.rules.len()),
+            _ if written.is_empty() => "no files written".to_string(),
+            _ => "not checked".to_string(),
+        };
+        writeln!(f, "{}: iteration {}, {outcome}", self.spec, self.iteration)?;
+        writeln!(f)?;
+        if written.is_empty() {
+            writeln!(f, "No files written.")?;
+        } else {
+            writeln!(f, "Wrote {}.", written.join(", "))?;
+        }
+        match build {
+            Some(Verdict::Passed) => writeln!(f, "The build and tests passed.")?,
+            Some(Verdict::Failed(_)) => writeln!(f, "The build or tests failed.")?,
+            None => {}
+        }
+        let mut by_status: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for (rule, status) in self.rules {
+            by_status
+                .entry(status.to_string())
+                .or_default()
+                .push(rule.as_str());
+        }
+        for (status, rules) in by_status {
+            writeln!(f, "{status}: {}", rules.join(", "))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Iteration: {}", self.iteration)?;
+        writeln!(f, "Model: {}", self.model)?;
+        write!(f, "Tokens: {}", self.tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::RuleResult;
+
+    fn rule(rule: &str, status: RuleStatus) -> Progress {
+        Progress::Rule(RuleResult {
+            rule: rule.to_string(),
+            status,
+            output: String::new(),
+            counterexample: None,
+            cached: false,
+        })
+    }
+
+    #[test]
+    fn test_summary() {
+        let steps = [
+            Progress::Iteration(2),
+            Progress::Wrote(vec![
+                PathBuf::from("src/processor.rs"),
+                PathBuf::from("src/state.rs"),
+            ]),
+            Progress::Build(Verdict::Passed),
+            rule("rule_deposit", RuleStatus::Verified),
+            rule("rule_withdraw", RuleStatus::Violated),
+        ];
+        let rules = BTreeMap::from([
+            ("rule_deposit".to_string(), RuleStatus::Verified),
+            ("rule_reward".to_string(), RuleStatus::Verified),
+            ("rule_withdraw".to_string(), RuleStatus::Violated),
+        ]);
+        let summary = Summary {
+            spec: "vault",
+            iteration: 2,
+            model: "anthropic/claude".to_string(),
+            tokens: 1200,
+            steps: &steps,
+            rules: &rules,
+            success: false,
+        };
+        assert_eq!(
+            summary.to_string(),
+            "vault: iteration 2, 2 of 3 rules verified\n\n\
+             Wrote src/processor.rs, src/state.rs.\n\
+             The build and tests passed.\n\
+             VERIFIED: rule_deposit, rule_reward\n\
+             VIOLATED: rule_withdraw\n\n\
+             Iteration: 2\nModel: anthropic/claude\nTokens: 1200"
+        );
+
+        let steps = [Progress::Build(Verdict::Failed("error".to_string()))];
+        let summary = Summary {
+            steps: &steps,
+            ..summary
+        };
+        assert!(summary
+            .to_string()
+            .starts_with("vault: iteration 2, build failed\n\nNo files written.\n"));
+    }
+
+    #[test]
+    fn test_commit() {
+        let root = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(root.path())
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?}");
+        };
+        git(&["init", "--quiet", "--initial-branch", "main"]);
+        git(&["config", "user.name", "Test"]);
+        git(&["config", "user.email", "test@example.com"]);
+        std::fs::write(root.path().join("README.md"), "readme\n").unwrap();
+        git(&["add", "README.md"]);
+        git(&["commit", "--quiet", "--message", "Initial"]);
+
+        let dir = root.path().join("examples/vault");
+        std::fs::create_dir_all(dir.join(SANDBOX_TARGET)).unwrap();
+        std::fs::write(dir.join("lib.rs"), "// TODO\n").unwrap();
+        std::fs::write(dir.join(SANDBOX_TARGET).join("out"), "").unwrap();
+        // Changes outside the crate are not committed
+        std::fs::write(root.path().join("README.md"), "changed\n").unwrap();
+        git(&["add", "README.md"]);
+
+        let repo = Repo::open(&dir).unwrap();
+        repo.create_branch("spec-coder/vault-svm-1").unwrap();
+        assert!(repo.commit("vault: materialized").unwrap());
+        assert!(!repo.commit("vault: nothing").unwrap());
+        std::fs::write(dir.join("lib.rs"), "// done\n").unwrap();
+        assert!(repo.commit("vault: iteration 1").unwrap());
+
+        let output = Command::new("git")
+            .args([
+                "log",
+                "--format=%s",
+                "--name-only",
+                "spec-coder/vault-svm-1",
+            ])
+            .current_dir(root.path())
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "vault: iteration 1\n\nexamples/vault/lib.rs\n\
+             vault: materialized\n\nexamples/vault/lib.rs\n\
+             Initial\n\nREADME.md\n"
+        );
+
+        let outside = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            Repo::open(outside.path()),
+            Err(GitError::NotARepository(_))
+        ));
+    }
+}
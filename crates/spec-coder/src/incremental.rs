@@ -0,0 +1,311 @@
+//! Incremental regeneration after a spec change.
+//!
+//! Each function is generated from its own clauses and the state types of
+//! its accounts, and each rule is compiled from the clauses of its function
+//! and the invariants it assumes or asserts. Comparing the clauses with the
+//! markers in the processor and with the last verification (see [`drift`])
+//! gives the functions to scaffold again and the rules to prove again;
+//! everything else keeps its implementation and its verdict.
+
+use crate::drift::{self, DriftError};
+use crate::materialize::{self, svm, GeneratedFile, MaterializeError};
+use crate::provenance::{self, Provenance};
+use crate::regression::REGRESSIONS_RS;
+use spec::{Spec, Target};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// An error planning or regenerating a crate.
+#[derive(Debug, thiserror::Error)]
+pub enum IncrementalError {
+    #[error(transparent)]
+    Drift(#[from] DriftError),
+    #[error(transparent)]
+    Materialize(#[from] MaterializeError),
+}
+
+/// The clauses, by location, that functions and rules are generated from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Graph {
+    /// The clauses each function is generated from
+    functions: BTreeMap<String, BTreeSet<String>>,
+    /// The function each rule calls and the clauses it is compiled from
+    rules: BTreeMap<String, (String, BTreeSet<String>)>,
+}
+
+impl Graph {
+    pub fn new(spec: &Spec) -> Graph {
+        let mut graph = Graph::default();
+        for function in &spec.functions {
+            let mut clauses = BTreeSet::from([format!("functions.{}", function.name)]);
+            for account in &function.accounts {
+                clauses.extend(account.ty.as_ref().map(|ty| format!("state.{ty}")));
+            }
+            for (rule, invariants) in svm::function_rules(spec, function) {
+                let mut rule_clauses = clauses.clone();
+                rule_clauses.extend(invariants.iter().map(|name| format!("invariants.{name}")));
+                graph
+                    .rules
+                    .insert(rule, (function.name.clone(), rule_clauses));
+            }
+            graph.functions.insert(function.name.clone(), clauses);
+        }
+        graph
+    }
+
+    /// The functions generated from any of `clauses`.
+    pub fn functions(&self, clauses: &BTreeSet<String>) -> BTreeSet<String> {
+        self.functions
+            .iter()
+            .filter(|(_, from)| !from.is_disjoint(clauses))
+            .map(|(function, _)| function.clone())
+            .collect()
+    }
+
+    /// The rules calling any of `functions` or compiled from any of
+    /// `clauses`.
+    pub fn rules(
+        &self,
+        functions: &BTreeSet<String>,
+        clauses: &BTreeSet<String>,
+    ) -> BTreeSet<String> {
+        self.rules
+            .iter()
+            .filter(|(_, (function, from))| {
+                functions.contains(function) || !from.is_disjoint(clauses)
+            })
+            .map(|(rule, _)| rule.clone())
+            .collect()
+    }
+}
+
+/// What has to be done again for a crate to match its spec.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// Clauses changed since the last verification, by location; all of
+    /// them if the crate was never verified
+    pub changed: BTreeSet<String>,
+    /// Functions whose scaffold no longer matches the spec
+    pub regenerate: BTreeSet<String>,
+    /// Functions whose implementation has to be checked again
+    pub functions: BTreeSet<String>,
+    /// Rules to prove again
+    pub rules: BTreeSet<String>,
+}
+
+/// Plans the work for the crate in `dir` to match `spec`.
+pub fn plan(spec: &Spec, dir: &Path) -> Result<Plan, DriftError> {
+    let graph = Graph::new(spec);
+    let record = drift::read_record(dir)?;
+    let source = drift::read_processor(dir)?;
+    let marked = drift::marked(&source);
+
+    let changed: BTreeSet<String> = drift::clause_fingerprints(spec)
+        .into_iter()
+        .filter(|(clause, fingerprint)| record.clauses.get(clause) != Some(fingerprint))
+        .map(|(clause, _)| clause)
+        .collect();
+    let mut regenerate = BTreeSet::new();
+    for function in &spec.functions {
+        let claimed = marked.get(function.name.as_str()).map(|m| m.clauses);
+        if claimed != Some(drift::clauses(function).as_str()) {
+            regenerate.insert(function.name.clone());
+        }
+    }
+    // Without a record, a state type may or may not have changed; the
+    // markers alone decide then
+    if !record.clauses.is_empty() {
+        let state = changed
+            .iter()
+            .filter(|clause| clause.starts_with("state."))
+            .cloned()
+            .collect();
+        regenerate.extend(graph.functions(&state));
+    }
+
+    let shared = drift::shared_fingerprint(&source, &drift::read_state(dir)?);
+    let mut functions = graph.functions(&changed);
+    functions.extend(regenerate.iter().cloned());
+    for function in &spec.functions {
+        let verified = record.functions.get(&function.name);
+        let current = marked
+            .get(function.name.as_str())
+            .map(|marked| drift::source_fingerprint(marked.source));
+        if record.shared.as_ref() != Some(&shared)
+            || verified.map(|verified| &verified.source) != current.as_ref()
+        {
+            functions.insert(function.name.clone());
+        }
+    }
+    let rules = graph.rules(&functions, &changed);
+    Ok(Plan {
+        changed,
+        regenerate,
+        functions,
+        rules,
+    })
+}
+
+/// Replaces the processor functions in `existing` that are in `regenerate`
+/// by those in `generated`, appending new ones and dropping the functions no
+/// longer in `spec`.
+fn splice(spec: &Spec, existing: &str, generated: &str, regenerate: &BTreeSet<String>) -> String {
+    let old = drift::marked(existing);
+    let new = drift::marked(generated);
+    let mut out = existing.to_string();
+    for (name, marked) in &old {
+        if spec.function(name).is_none() {
+            out = out.replacen(&format!("{}\n", marked.source), "", 1);
+        }
+    }
+    for name in regenerate {
+        let Some(generated) = new.get(name.as_str()) else {
+            continue;
+        };
+        match old.get(name.as_str()) {
+            Some(marked) => out = out.replacen(marked.source, generated.source, 1),
+            None => {
+                out.push('\n');
+                out.push_str(generated.source);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// Brings the crate in `dir` up to date with `spec`, rewriting only what
+/// changed: the scaffold of the functions in [`Plan::regenerate`], the state
+/// types and the dispatch if they changed, and the files compiled from the
+/// spec. Returns the plan the crate was updated by.
+pub fn regenerate(spec: &Spec, target: Target, dir: &Path) -> Result<Plan, IncrementalError> {
+    let plan = plan(spec, dir)?;
+    let files: BTreeMap<_, _> = materialize::generate(spec, target)?
+        .into_iter()
+        .map(|file| (file.path.clone(), file))
+        .collect();
+    let io = |path: &Path| {
+        let path = dir.join(path);
+        move |source| MaterializeError::Io { path, source }
+    };
+
+    let existing = drift::read_processor(dir)?;
+    let mut rewrite = Vec::new();
+    if let Some(processor) = files.get(Path::new(drift::PROCESSOR_RS)) {
+        let spliced = splice(spec, &existing, &processor.contents, &plan.regenerate);
+        if spliced != existing {
+            rewrite.push(GeneratedFile {
+                path: processor.path.clone(),
+                contents: spliced,
+            });
+        }
+        let before: BTreeSet<&str> = drift::marked(&existing).into_keys().collect();
+        let after: BTreeSet<&str> = spec.functions.iter().map(|f| f.name.as_str()).collect();
+        // Anchor handlers also follow the arguments of their functions
+        if before != after || (target == Target::Anchor && !plan.regenerate.is_empty()) {
+            rewrite.extend(files.get(Path::new("src/lib.rs")).cloned());
+        }
+    }
+    if plan
+        .changed
+        .iter()
+        .any(|clause| clause.starts_with("state."))
+    {
+        rewrite.extend(files.get(Path::new("src/state.rs")).cloned());
+    }
+    let provenance = Provenance::new(provenance::fingerprint(spec), None);
+    provenance::restamp_files(&mut rewrite, dir, &provenance);
+    materialize::write(&rewrite, dir, true)?;
+    if dir.join(REGRESSIONS_RS).exists() {
+        let lib_rs = Path::new("src/lib.rs");
+        materialize::declare_module(&dir.join(lib_rs), "test", "regressions")
+            .map_err(io(lib_rs))?;
+    }
+    materialize::write_compiled(spec, target, dir)?;
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    fn materialized(spec: &Spec) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let files = materialize::generate(spec, Target::Svm).unwrap();
+        materialize::write(&files, dir.path(), true).unwrap();
+        dir
+    }
+
+    fn set(items: &[&str]) -> BTreeSet<String> {
+        items.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_graph() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let graph = Graph::new(&spec);
+        assert_eq!(
+            graph.functions(&set(&["state.Vault"])),
+            set(&["deposit", "reward", "withdraw"])
+        );
+        let invariant = format!("invariants.{}", spec.invariants[0].name);
+        let rules = graph.rules(&set(&["reward"]), &set(&[&invariant]));
+        assert!(rules.contains("rule_reward_sanity"));
+        assert!(rules.contains("rule_deposit_ensures"));
+        assert!(!rules.contains("rule_deposit_sanity"));
+    }
+
+    #[test]
+    fn test_plan_after_spec_change() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = materialized(&spec);
+        let unverified = plan(&spec, dir.path()).unwrap();
+        assert!(unverified.regenerate.is_empty());
+        assert_eq!(unverified.functions.len(), 3);
+
+        drift::record(&spec, dir.path()).unwrap();
+        assert_eq!(plan(&spec, dir.path()).unwrap(), Plan::default());
+
+        // Only withdraw and its rules are affected by its postcondition
+        let mut changed = spec.clone();
+        changed.functions[1].ensures.pop();
+        let withdraw = plan(&changed, dir.path()).unwrap();
+        assert_eq!(withdraw.changed, set(&["functions.withdraw"]));
+        assert_eq!(withdraw.regenerate, set(&["withdraw"]));
+        assert_eq!(withdraw.functions, set(&["withdraw"]));
+        assert!(withdraw
+            .rules
+            .iter()
+            .all(|rule| rule.starts_with("rule_withdraw_")));
+        assert!(withdraw.rules.contains("rule_withdraw_ensures"));
+    }
+
+    #[test]
+    fn test_regenerate_keeps_implementations() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = materialized(&spec);
+        let processor = dir.path().join(drift::PROCESSOR_RS);
+        let implemented = std::fs::read_to_string(&processor)
+            .unwrap()
+            .replace("    Ok(())\n}", "    // implemented\n    Ok(())\n}");
+        std::fs::write(&processor, &implemented).unwrap();
+        drift::record(&spec, dir.path()).unwrap();
+
+        let mut changed = spec.clone();
+        changed.functions[1].ensures.pop();
+        changed.functions.remove(2);
+        let plan = regenerate(&changed, Target::Svm, dir.path()).unwrap();
+        assert_eq!(plan.regenerate, set(&["withdraw"]));
+        let source = std::fs::read_to_string(&processor).unwrap();
+        assert!(source.contains(&drift::marker(&changed.functions[1])));
+        assert_eq!(source.matches("// implemented").count(), 1);
+        assert!(!source.contains("fn process_reward"));
+        let lib = std::fs::read_to_string(dir.path().join("src/lib.rs")).unwrap();
+        assert!(!lib.contains("process_reward"));
+        assert!(lib.contains("mod spec_tests;"));
+        let rules = std::fs::read_to_string(dir.path().join("src/certora/spec.rs")).unwrap();
+        assert!(!rules.contains("rule_reward_sanity"));
+    }
+}
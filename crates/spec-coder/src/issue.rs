@@ -0,0 +1,653 @@
+//! GitHub issues and discussions as spec input.
+//!
+//! A feature is often discussed in an issue long before it has a spec.
+//! [`fetch`] reads an issue or discussion and its comments through the
+//! GitHub API, and [`import`] turns it into a new example: the requirements
+//! are split out of the text as [`properties::requirements`] does and listed
+//! in the header of a draft spec, starting from the template of
+//! [`scaffold::new_example`], with the title of the issue as description and
+//! its URL as `source`. The crate materialized from the draft is stamped with
+//! the source, see [`migrate`], so the registry links the example back to the
+//! issue. The requirements are also written next to the spec, one per line,
+//! for `extract-properties` to formalize as invariants.
+//!
+//! Issues are read with the REST API, discussions only with the GraphQL API,
+//! which needs a token.
+
+use crate::properties;
+use crate::scaffold::{self, NewExample, ScaffoldError};
+use serde::Deserialize;
+use spec::Target;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The root of the public GitHub API.
+pub const API_URL: &str = "https://api.github.com";
+
+/// How long a request to the API may take.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The discussion and its comments, by repository and number.
+const DISCUSSION_QUERY: &str = "query($owner: String!, $repo: String!, $number: Int!) {
+  repository(owner: $owner, name: $repo) {
+    discussion(number: $number) {
+      url number title body
+      comments(first: 100) { nodes { body } }
+    }
+  }
+}";
+
+/// An error importing an issue.
+#[derive(Debug, thiserror::Error)]
+pub enum IssueError {
+    #[error("`{0}` is not an issue or discussion; pass its URL, `owner/repo#number`, or a number with a repository")]
+    InvalidReference(String),
+    #[error("`{0}` does not name a repository; pass its URL or a repository as `owner/repo`")]
+    NoRepository(String),
+    #[error("{0} does not exist or is not visible with the token")]
+    NotFound(Reference),
+    #[error("reading discussions needs a token with read access to the repository")]
+    TokenRequired,
+    #[error("GitHub returned HTTP {status} for {url}: {body}")]
+    Http {
+        url: String,
+        status: u16,
+        body: String,
+    },
+    #[error("request to {url} failed: {message}")]
+    Transport { url: String, message: String },
+    #[error("unexpected response from GitHub: {0}")]
+    Decode(String),
+    #[error(transparent)]
+    Scaffold(#[from] ScaffoldError),
+    #[error("failed to write {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// Whether a reference is to an issue or a discussion. Pull requests are
+/// issues to the API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Issue,
+    Discussion,
+}
+
+/// An issue or discussion of a repository.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reference {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+    pub kind: Kind,
+}
+
+/// Whether `owner/repo` names a repository, returning its parts.
+fn repository(repo: &str) -> Option<(&str, &str)> {
+    let (owner, name) = repo.split_once('/')?;
+    let valid = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    (valid(owner) && valid(name)).then_some((owner, name))
+}
+
+impl Reference {
+    /// Parses `input`: the URL of an issue, pull request or discussion,
+    /// `owner/repo#number`, or a number, optionally with a `#`, of an issue
+    /// of `repo`.
+    pub fn parse(input: &str, repo: Option<&str>) -> Result<Reference, IssueError> {
+        let invalid = || IssueError::InvalidReference(input.to_string());
+        let trimmed = input.trim();
+        let path = ["https://", "http://"]
+            .iter()
+            .find_map(|scheme| trimmed.strip_prefix(scheme))
+            .unwrap_or(trimmed);
+        let path = path.strip_prefix("www.").unwrap_or(path);
+        if let Some(path) = path.strip_prefix("github.com/") {
+            let path = path.split(['#', '?']).next().unwrap_or_default();
+            let parts: Vec<&str> = path.split('/').collect();
+            let [owner, name, kind, number, ..] = parts[..] else {
+                return Err(invalid());
+            };
+            let kind = match kind {
+                "issues" | "pull" => Kind::Issue,
+                "discussions" => Kind::Discussion,
+                _ => return Err(invalid()),
+            };
+            return Ok(Reference {
+                owner: owner.to_string(),
+                repo: name.to_string(),
+                number: number.parse().map_err(|_| invalid())?,
+                kind,
+            });
+        }
+        let (repo, number) = match trimmed.split_once('#') {
+            Some((repo, number)) if !repo.is_empty() => (Some(repo), number),
+            Some((_, number)) => (repo, number),
+            None => (repo, trimmed),
+        };
+        let number = number.parse().map_err(|_| invalid())?;
+        let repo = repo.ok_or_else(|| IssueError::NoRepository(input.to_string()))?;
+        let (owner, name) = repository(repo).ok_or_else(invalid)?;
+        Ok(Reference {
+            owner: owner.to_string(),
+            repo: name.to_string(),
+            number,
+            kind: Kind::Issue,
+        })
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            Kind::Issue => "issue",
+            Kind::Discussion => "discussion",
+        };
+        write!(f, "{kind} {}/{}#{}", self.owner, self.repo, self.number)
+    }
+}
+
+/// An issue or discussion as read from the API.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Issue {
+    /// The page of the issue on GitHub
+    pub url: String,
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+    /// The bodies of the comments, oldest first
+    pub comments: Vec<String>,
+}
+
+impl Issue {
+    /// The requirements of the body and then the comments, each once.
+    pub fn requirements(&self) -> Vec<String> {
+        let mut requirements: Vec<String> = Vec::new();
+        for text in std::iter::once(&self.body).chain(&self.comments) {
+            for requirement in properties::requirements(text) {
+                if !requirements.contains(&requirement) {
+                    requirements.push(requirement);
+                }
+            }
+        }
+        requirements
+    }
+
+    /// An example name from the first words of the title, or from the
+    /// number if the title has none to make one of.
+    pub fn slug(&self) -> String {
+        let words: Vec<String> = self
+            .title
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .take(4)
+            .map(str::to_ascii_lowercase)
+            .collect();
+        match words.first() {
+            Some(word) if word.starts_with(|c: char| c.is_ascii_lowercase()) => words.join("_"),
+            _ => format!("issue_{}", self.number),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RestIssue {
+    html_url: String,
+    number: u64,
+    title: String,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Comment {
+    #[serde(default)]
+    body: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GraphQl {
+    #[serde(default)]
+    data: Option<Data>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct Data {
+    repository: Option<Repository>,
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    discussion: Option<Discussion>,
+}
+
+#[derive(Deserialize)]
+struct Discussion {
+    url: String,
+    number: u64,
+    title: String,
+    #[serde(default)]
+    body: Option<String>,
+    comments: Nodes,
+}
+
+#[derive(Deserialize)]
+struct Nodes {
+    nodes: Vec<Comment>,
+}
+
+fn decode<T: serde::de::DeserializeOwned>(json: &str) -> Result<T, IssueError> {
+    serde_json::from_str(json).map_err(|err| IssueError::Decode(err.to_string()))
+}
+
+/// The bodies of `comments` that are not empty.
+fn bodies(comments: Vec<Comment>) -> Vec<String> {
+    comments
+        .into_iter()
+        .filter_map(|comment| comment.body)
+        .filter(|body| !body.trim().is_empty())
+        .collect()
+}
+
+/// The issue of the REST responses for an issue and its comments.
+pub fn from_rest(issue: &str, comments: &str) -> Result<Issue, IssueError> {
+    let issue: RestIssue = decode(issue)?;
+    Ok(Issue {
+        url: issue.html_url,
+        number: issue.number,
+        title: issue.title,
+        body: issue.body.unwrap_or_default(),
+        comments: bodies(decode(comments)?),
+    })
+}
+
+/// The discussion `reference` of the GraphQL response to
+/// [`DISCUSSION_QUERY`].
+pub fn from_graphql(response: &str, reference: &Reference) -> Result<Issue, IssueError> {
+    let response: GraphQl = decode(response)?;
+    let discussion = response
+        .data
+        .and_then(|data| data.repository)
+        .and_then(|repository| repository.discussion);
+    match discussion {
+        Some(discussion) => Ok(Issue {
+            url: discussion.url,
+            number: discussion.number,
+            title: discussion.title,
+            body: discussion.body.unwrap_or_default(),
+            comments: bodies(discussion.comments.nodes),
+        }),
+        None if response.errors.is_empty() => Err(IssueError::NotFound(reference.clone())),
+        None => Err(IssueError::Decode(
+            response
+                .errors
+                .into_iter()
+                .map(|error| error.message)
+                .collect::<Vec<_>>()
+                .join("; "),
+        )),
+    }
+}
+
+/// Sends `request` with `body`, if any, returning the response body.
+fn send(
+    request: ureq::Request,
+    body: Option<&str>,
+    token: Option<&str>,
+    reference: &Reference,
+) -> Result<String, IssueError> {
+    let url = request.url().to_string();
+    let mut request = request.set("accept", "application/vnd.github+json").set(
+        "user-agent",
+        concat!("spec-coder/", env!("CARGO_PKG_VERSION")),
+    );
+    if let Some(token) = token {
+        request = request.set("authorization", &format!("Bearer {token}"));
+    }
+    let response = match body {
+        Some(body) => request
+            .set("content-type", "application/json")
+            .send_string(body),
+        None => request.call(),
+    };
+    let transport = |message: String| IssueError::Transport {
+        url: url.clone(),
+        message,
+    };
+    match response {
+        Ok(response) => response
+            .into_string()
+            .map_err(|err| transport(err.to_string())),
+        Err(ureq::Error::Status(404, _)) => Err(IssueError::NotFound(reference.clone())),
+        Err(ureq::Error::Status(status, response)) => Err(IssueError::Http {
+            url: url.clone(),
+            status,
+            body: response.into_string().unwrap_or_default(),
+        }),
+        Err(err) => Err(transport(err.to_string())),
+    }
+}
+
+/// The GraphQL endpoint of the API at `api_url`; on GitHub Enterprise the
+/// REST API is under `/api/v3` and GraphQL at `/api/graphql`.
+fn graphql_url(api_url: &str) -> String {
+    let api_url = api_url.trim_end_matches('/');
+    format!("{}/graphql", api_url.strip_suffix("/v3").unwrap_or(api_url))
+}
+
+/// Reads `reference` and its first hundred comments from the API at
+/// `api_url`, with `token` if any.
+pub fn fetch(
+    reference: &Reference,
+    api_url: &str,
+    token: Option<&str>,
+) -> Result<Issue, IssueError> {
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+    let Reference {
+        owner,
+        repo,
+        number,
+        kind,
+    } = reference;
+    match kind {
+        Kind::Issue => {
+            let url = format!(
+                "{}/repos/{owner}/{repo}/issues/{number}",
+                api_url.trim_end_matches('/')
+            );
+            let issue = send(agent.get(&url), None, token, reference)?;
+            let comments = send(
+                agent.get(&format!("{url}/comments?per_page=100")),
+                None,
+                token,
+                reference,
+            )?;
+            from_rest(&issue, &comments)
+        }
+        Kind::Discussion => {
+            let token = token.ok_or(IssueError::TokenRequired)?;
+            let body = serde_json::json!({
+                "query": DISCUSSION_QUERY,
+                "variables": { "owner": owner, "repo": repo, "number": number },
+            });
+            let response = send(
+                agent.post(&graphql_url(api_url)),
+                Some(&body.to_string()),
+                Some(token),
+                reference,
+            )?;
+            from_graphql(&response, reference)
+        }
+    }
+}
+
+/// A YAML scalar of `text`; JSON strings are double-quoted YAML strings.
+fn scalar(text: &str) -> String {
+    serde_json::to_string(text).expect("strings serialize")
+}
+
+/// The draft spec of the example `name` for `issue`: the spec template with
+/// the requirements of the issue in its header, the title as description
+/// and the URL as source.
+pub fn draft(issue: &Issue, name: &str, target: Target) -> Result<String, IssueError> {
+    let template = scaffold::spec_template(name, target)?;
+    let mut header = format!("# Drafted from {}: {}\n", issue.url, issue.title);
+    let requirements = issue.requirements();
+    if !requirements.is_empty() {
+        header.push_str("#\n# Requirements to write the clauses from:\n#\n");
+        for requirement in &requirements {
+            header.push_str(&format!("#   - {requirement}\n"));
+        }
+    }
+    header.push_str("#\n");
+    let mut draft = String::new();
+    for line in template.split_inclusive('\n') {
+        if line.starts_with("name: ") {
+            draft.push_str(&header);
+        }
+        if line.starts_with("description: ") {
+            draft.push_str(&format!("description: {}\n", scalar(&issue.title)));
+            draft.push_str(&format!("source: {}\n", scalar(&issue.url)));
+        } else {
+            draft.push_str(line);
+        }
+    }
+    Ok(draft)
+}
+
+/// An issue turned into a new example.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Imported {
+    pub example: NewExample,
+    /// The requirements of the issue, one per line, next to the spec
+    pub requirements: PathBuf,
+    pub count: usize,
+}
+
+/// Creates the example `name` from `issue`: the [`draft`] spec, the crate
+/// materialized from it, whose stamp names the issue, and the requirements
+/// in `<examples-dir>/specs/<name>.requirements.txt`. Neither the spec nor
+/// the crate may exist unless `force` is set.
+pub fn import(
+    issue: &Issue,
+    name: &str,
+    target: Target,
+    examples_dir: &Path,
+    license: Option<&str>,
+    force: bool,
+) -> Result<Imported, IssueError> {
+    let draft = draft(issue, name, target)?;
+    let example = scaffold::example_from(draft, target, examples_dir, license, force)?;
+    let requirements = issue.requirements();
+    let path = examples_dir
+        .join("specs")
+        .join(format!("{name}.requirements.txt"));
+    let text: String = requirements
+        .iter()
+        .map(|requirement| format!("{requirement}\n"))
+        .collect();
+    std::fs::write(&path, text).map_err(|source| IssueError::Io {
+        path: path.clone(),
+        source,
+    })?;
+    Ok(Imported {
+        example,
+        requirements: path,
+        count: requirements.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrate;
+    use spec::Spec;
+
+    fn issue() -> Issue {
+        Issue {
+            url: "https://github.com/acme/programs/issues/42".to_string(),
+            number: 42,
+            title: "Escrow: release \"funds\" after a deadline".to_string(),
+            body: "The depositor locks funds until a deadline.\n\n\
+                   - Only the beneficiary may release the funds.\n\
+                   - The escrow never holds more than was deposited."
+                .to_string(),
+            comments: vec![
+                "Only the beneficiary may release the funds.".to_string(),
+                "After the deadline the depositor may reclaim them.".to_string(),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let reference = Reference::parse(
+            "https://github.com/acme/programs/issues/42#issuecomment-1",
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            reference,
+            Reference {
+                owner: "acme".to_string(),
+                repo: "programs".to_string(),
+                number: 42,
+                kind: Kind::Issue,
+            }
+        );
+        assert_eq!(reference.to_string(), "issue acme/programs#42");
+        let discussion = Reference::parse("github.com/acme/programs/discussions/7", None).unwrap();
+        assert_eq!(discussion.kind, Kind::Discussion);
+        assert_eq!(discussion.number, 7);
+        assert_eq!(
+            Reference::parse("https://github.com/acme/programs/pull/9", None)
+                .unwrap()
+                .kind,
+            Kind::Issue
+        );
+        assert_eq!(
+            Reference::parse("acme/programs#42", None).unwrap(),
+            reference
+        );
+        assert_eq!(
+            Reference::parse("#42", Some("acme/programs")).unwrap(),
+            reference
+        );
+        assert_eq!(
+            Reference::parse("42", Some("acme/programs")).unwrap(),
+            reference
+        );
+        assert!(matches!(
+            Reference::parse("42", None),
+            Err(IssueError::NoRepository(_))
+        ));
+        for input in [
+            "https://github.com/acme/programs",
+            "https://github.com/acme/programs/wiki/42",
+            "acme#42",
+            "forty-two",
+        ] {
+            assert!(matches!(
+                Reference::parse(input, Some("acme/programs")),
+                Err(IssueError::InvalidReference(_))
+            ));
+        }
+        assert_eq!(
+            graphql_url("https://ghe.example.com/api/v3/"),
+            "https://ghe.example.com/api/graphql"
+        );
+        assert_eq!(graphql_url(API_URL), "https://api.github.com/graphql");
+    }
+
+    #[test]
+    fn test_decode() {
+        let issue = from_rest(
+            r#"{"html_url": "https://github.com/acme/programs/issues/42", "number": 42,
+                "title": "Escrow", "body": null, "state": "open"}"#,
+            r#"[{"body": "First."}, {"body": ""}, {"body": "Second."}]"#,
+        )
+        .unwrap();
+        assert_eq!(issue.url, "https://github.com/acme/programs/issues/42");
+        assert_eq!(issue.body, "");
+        assert_eq!(issue.comments, ["First.", "Second."]);
+
+        let reference = Reference::parse("github.com/acme/programs/discussions/7", None).unwrap();
+        let discussion = from_graphql(
+            r#"{"data": {"repository": {"discussion": {
+                "url": "https://github.com/acme/programs/discussions/7", "number": 7,
+                "title": "Vesting", "body": "Tokens vest linearly.",
+                "comments": {"nodes": [{"body": "With a cliff."}]}}}}}"#,
+            &reference,
+        )
+        .unwrap();
+        assert_eq!(discussion.title, "Vesting");
+        assert_eq!(
+            discussion.requirements(),
+            ["Tokens vest linearly.", "With a cliff."]
+        );
+        assert!(matches!(
+            from_graphql(
+                r#"{"data": {"repository": {"discussion": null}}}"#,
+                &reference
+            ),
+            Err(IssueError::NotFound(_))
+        ));
+        assert!(matches!(
+            from_graphql(
+                r#"{"data": null, "errors": [{"message": "Bad credentials"}]}"#,
+                &reference
+            ),
+            Err(IssueError::Decode(message)) if message == "Bad credentials"
+        ));
+        assert!(matches!(from_rest("[]", "[]"), Err(IssueError::Decode(_))));
+    }
+
+    #[test]
+    fn test_import() {
+        let issue = issue();
+        assert_eq!(issue.slug(), "escrow_release_funds_after");
+        let untitled = Issue {
+            title: "#1 bug".to_string(),
+            ..issue.clone()
+        };
+        assert_eq!(untitled.slug(), "issue_42");
+        assert_eq!(
+            issue.requirements(),
+            [
+                "The depositor locks funds until a deadline.",
+                "Only the beneficiary may release the funds.",
+                "The escrow never holds more than was deposited.",
+                "After the deadline the depositor may reclaim them.",
+            ]
+        );
+
+        let draft = draft(&issue, "escrow", Target::Svm).unwrap();
+        assert!(draft.contains("#   - Only the beneficiary may release the funds.\n"));
+        let spec = Spec::from_yaml_str(&draft).unwrap();
+        assert_eq!(spec.name, "escrow");
+        assert_eq!(spec.description.as_deref(), Some(issue.title.as_str()));
+        assert_eq!(spec.source.as_deref(), Some(issue.url.as_str()));
+
+        let examples = tempfile::tempdir().unwrap();
+        let imported = import(&issue, "escrow", Target::Svm, examples.path(), None, false).unwrap();
+        assert_eq!(
+            imported.example.spec,
+            examples.path().join("specs/escrow.yaml")
+        );
+        assert_eq!(
+            std::fs::read_to_string(&imported.requirements)
+                .unwrap()
+                .lines()
+                .collect::<Vec<_>>(),
+            issue.requirements()
+        );
+        assert_eq!(imported.count, 4);
+        assert_eq!(
+            migrate::recorded_source(&imported.example.dir).unwrap(),
+            Some(issue.url.clone())
+        );
+        let registry = crate::registry::Registry::scan(examples.path()).unwrap();
+        assert_eq!(
+            registry.get("escrow").unwrap().source.as_deref(),
+            Some(issue.url.as_str())
+        );
+        assert!(matches!(
+            import(&issue, "escrow", Target::Svm, examples.path(), None, false),
+            Err(IssueError::Scaffold(ScaffoldError::Exists(_)))
+        ));
+    }
+}
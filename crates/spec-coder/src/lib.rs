@@ -0,0 +1,48 @@
+//! Spec-driven generation and verification of example programs.
+//!
+//! Specifications are parsed by the [`spec`] crate; this crate turns them
+//! into example crates under `examples/`, with the [`llm`] module talking to
+//! the models that fill in the implementation.
+
+#[cfg(feature = "api")]
+pub mod api;
+pub mod clean;
+pub mod compute_units;
+pub mod config;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+pub mod deploy;
+pub mod diff;
+pub mod drift;
+pub mod driver;
+pub mod git;
+pub mod incremental;
+pub mod issue;
+pub mod llm;
+pub mod lsp;
+pub mod materialize;
+pub mod mcp;
+pub mod metrics;
+pub mod migrate;
+pub mod mutation;
+pub mod patch;
+pub mod properties;
+pub mod provenance;
+pub mod prover;
+pub mod registry;
+pub mod regression;
+pub mod results;
+pub mod review;
+pub mod roles;
+pub mod sandbox;
+pub mod scaffold;
+pub mod session;
+#[cfg(test)]
+mod snapshots;
+pub mod streaming;
+pub mod summaries;
+pub mod templates;
+pub mod throughput;
+pub mod tui;
+pub mod verify;
+pub mod watch;
@@ -0,0 +1,475 @@
+//! Language-model providers.
+//!
+//! The pipeline talks to models only through the [`Provider`] trait, so the
+//! backend is a matter of configuration: [`from_config`] builds the
+//! [Anthropic](anthropic::Anthropic) or the
+//! [OpenAI-compatible](openai::OpenAi) provider, the latter also covering
+//! local servers such as vLLM, llama.cpp or Ollama. Either is wrapped in
+//! [`retry::Retrying`], so that rate limits and outages do not end a run.
+//! Responses can be recorded and replayed without a model, see [`replay`].
+
+pub mod anthropic;
+mod http;
+pub mod openai;
+pub mod replay;
+pub mod retry;
+mod sse;
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The author of a message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+/// A piece of a message.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    /// A tool invocation requested by the model
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    /// The outcome of a tool invocation, sent back to the model
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        #[serde(default)]
+        is_error: bool,
+    },
+}
+
+/// A message of a conversation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: Vec<ContentBlock>,
+}
+
+impl Message {
+    /// A user message consisting of `text`.
+    pub fn user(text: impl Into<String>) -> Message {
+        Message {
+            role: Role::User,
+            content: vec![ContentBlock::Text { text: text.into() }],
+        }
+    }
+
+    /// An assistant message consisting of `text`.
+    pub fn assistant(text: impl Into<String>) -> Message {
+        Message {
+            role: Role::Assistant,
+            content: vec![ContentBlock::Text { text: text.into() }],
+        }
+    }
+
+    /// The concatenated text of the message.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A tool the model may call.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the tool input
+    pub input_schema: serde_json::Value,
+}
+
+/// A request for the next assistant message.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChatRequest {
+    pub system: Option<String>,
+    pub messages: Vec<Message>,
+    pub tools: Vec<ToolDefinition>,
+    /// Overrides the configured output limit
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+/// Why the model stopped generating.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    EndTurn,
+    MaxTokens,
+    ToolUse,
+    /// The receiver of the stream stopped reading it
+    Aborted,
+    Other(String),
+}
+
+impl StopReason {
+    /// Maps the stop and finish reasons of either API.
+    fn from_wire(reason: &str) -> StopReason {
+        match reason {
+            "end_turn" | "stop" | "stop_sequence" => StopReason::EndTurn,
+            "max_tokens" | "length" => StopReason::MaxTokens,
+            "tool_use" | "tool_calls" => StopReason::ToolUse,
+            other => StopReason::Other(other.to_string()),
+        }
+    }
+}
+
+/// Tokens consumed by a request.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// The assistant message produced for a request.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChatResponse {
+    pub content: Vec<ContentBlock>,
+    pub stop_reason: StopReason,
+    pub usage: Usage,
+}
+
+impl ChatResponse {
+    /// The concatenated text of the response.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The tool invocations the model requested.
+    pub fn tool_uses(&self) -> impl Iterator<Item = &ContentBlock> {
+        self.content
+            .iter()
+            .filter(|block| matches!(block, ContentBlock::ToolUse { .. }))
+    }
+
+    /// The response as a message to append to the conversation.
+    pub fn into_message(self) -> Message {
+        Message {
+            role: Role::Assistant,
+            content: self.content,
+        }
+    }
+}
+
+/// An incremental piece of a streamed response.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamEvent {
+    /// More text of the current text block
+    TextDelta(String),
+    /// A complete tool invocation, reported once its input is known
+    ToolUse(ContentBlock),
+}
+
+/// An error talking to a provider.
+#[derive(Debug, thiserror::Error)]
+pub enum LlmError {
+    #[error("environment variable {0} with the API key is not set")]
+    MissingApiKey(String),
+    #[error("provider returned HTTP {status}: {body}")]
+    Http {
+        status: u16,
+        body: String,
+        /// Seconds to wait before retrying, as the provider asked
+        retry_after: Option<u64>,
+    },
+    #[error("request failed: {0}")]
+    Transport(String),
+    #[error("unexpected response: {0}")]
+    Decode(String),
+    #[error("no response recorded for request {0}")]
+    NotRecorded(String),
+    #[error("failed to access the recorded responses: {0}")]
+    Recording(String),
+}
+
+/// A chat model backend.
+pub trait Provider: Send + Sync {
+    /// Backend name, e.g. `anthropic`.
+    fn name(&self) -> &str;
+
+    /// The model requests are sent to.
+    fn model(&self) -> &str;
+
+    /// Generates the next assistant message.
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError>;
+
+    /// Generates the next assistant message, reporting it piecewise as it is
+    /// produced. Backends without streaming report the whole response at once.
+    /// Once `on_event` returns false, the rest is not read, and the response
+    /// ends with the event it was given, see [`StopReason::Aborted`].
+    fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_event: &mut dyn FnMut(StreamEvent) -> bool,
+    ) -> Result<ChatResponse, LlmError> {
+        let mut response = self.chat(request)?;
+        for (i, block) in response.content.iter().enumerate() {
+            let more = match block {
+                ContentBlock::Text { text } => on_event(StreamEvent::TextDelta(text.clone())),
+                ContentBlock::ToolUse { .. } => on_event(StreamEvent::ToolUse(block.clone())),
+                ContentBlock::ToolResult { .. } => true,
+            };
+            if !more {
+                response.content.truncate(i + 1);
+                response.stop_reason = StopReason::Aborted;
+                break;
+            }
+        }
+        Ok(response)
+    }
+
+    /// Completes a single prompt, returning the response text.
+    fn complete(&self, prompt: &str) -> Result<String, LlmError> {
+        let request = ChatRequest {
+            messages: vec![Message::user(prompt)],
+            ..ChatRequest::default()
+        };
+        Ok(self.chat(&request)?.text())
+    }
+}
+
+impl<P: Provider + ?Sized> Provider for Box<P> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn model(&self) -> &str {
+        (**self).model()
+    }
+
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        (**self).chat(request)
+    }
+
+    fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_event: &mut dyn FnMut(StreamEvent) -> bool,
+    ) -> Result<ChatResponse, LlmError> {
+        (**self).chat_stream(request, on_event)
+    }
+}
+
+/// The supported backends.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    Anthropic,
+    /// Any endpoint implementing the OpenAI chat completions API
+    OpenAi,
+}
+
+impl ProviderKind {
+    /// The model used when none is configured.
+    pub fn default_model(self) -> &'static str {
+        match self {
+            ProviderKind::Anthropic => "claude-sonnet-4-5-20250929",
+            ProviderKind::OpenAi => "gpt-4o",
+        }
+    }
+}
+
+impl std::fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ProviderKind::Anthropic => "anthropic",
+            ProviderKind::OpenAi => "openai",
+        })
+    }
+}
+
+impl std::str::FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ProviderKind, String> {
+        match s {
+            "anthropic" => Ok(ProviderKind::Anthropic),
+            "openai" => Ok(ProviderKind::OpenAi),
+            _ => Err(format!(
+                "unknown provider `{s}`, expected `anthropic` or `openai`"
+            )),
+        }
+    }
+}
+
+/// Provider configuration, e.g. the `[llm]` table of a configuration file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LlmConfig {
+    pub provider: ProviderKind,
+    pub model: String,
+    /// API root, for proxies and local servers
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Environment variable holding the API key; defaults to
+    /// `ANTHROPIC_API_KEY` or `OPENAI_API_KEY`
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    /// Output limit of requests that do not set one
+    #[serde(default = "LlmConfig::default_max_tokens")]
+    pub max_tokens: u32,
+    #[serde(default = "LlmConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Retries of a request failing transiently, see [`retry`]
+    #[serde(default = "LlmConfig::default_max_retries")]
+    pub max_retries: u32,
+    /// Tokens the model reads at most, input and output together; older
+    /// turns of longer conversations are left out of requests
+    #[serde(default)]
+    pub context_tokens: Option<u64>,
+}
+
+impl LlmConfig {
+    /// The defaults for `provider`.
+    pub fn new(provider: ProviderKind) -> LlmConfig {
+        LlmConfig {
+            provider,
+            model: provider.default_model().to_string(),
+            base_url: None,
+            api_key_env: None,
+            max_tokens: LlmConfig::default_max_tokens(),
+            timeout_secs: LlmConfig::default_timeout_secs(),
+            max_retries: LlmConfig::default_max_retries(),
+            context_tokens: None,
+        }
+    }
+
+    fn default_max_tokens() -> u32 {
+        8192
+    }
+
+    fn default_timeout_secs() -> u64 {
+        600
+    }
+
+    fn default_max_retries() -> u32 {
+        5
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    /// The environment variable holding the API key.
+    fn api_key_env<'a>(&'a self, default_env: &'a str) -> &'a str {
+        self.api_key_env.as_deref().unwrap_or(default_env)
+    }
+
+    /// Reads the API key, if set.
+    fn api_key(&self, default_env: &str) -> Option<String> {
+        std::env::var(self.api_key_env(default_env))
+            .ok()
+            .filter(|key| !key.is_empty())
+    }
+}
+
+/// Builds the provider selected by `config`, retrying transient failures.
+pub fn from_config(config: &LlmConfig) -> Result<Box<dyn Provider>, LlmError> {
+    let policy = retry::RetryPolicy::new(config);
+    Ok(match config.provider {
+        ProviderKind::Anthropic => Box::new(retry::Retrying::new(
+            anthropic::Anthropic::new(config)?,
+            policy,
+        )),
+        ProviderKind::OpenAi => {
+            Box::new(retry::Retrying::new(openai::OpenAi::new(config)?, policy))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    impl Provider for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn model(&self) -> &str {
+            "echo"
+        }
+
+        fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+            Ok(ChatResponse {
+                content: request.messages.last().unwrap().content.clone(),
+                stop_reason: StopReason::EndTurn,
+                usage: Usage::default(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_default_stream_and_complete() {
+        assert_eq!(Echo.complete("hi").unwrap(), "hi");
+        let mut events = Vec::new();
+        let request = ChatRequest {
+            messages: vec![Message::user("hello")],
+            ..ChatRequest::default()
+        };
+        let response = Echo
+            .chat_stream(&request, &mut |event| {
+                events.push(event);
+                false
+            })
+            .unwrap();
+        assert_eq!(events, [StreamEvent::TextDelta("hello".to_string())]);
+        assert_eq!(response.stop_reason, StopReason::Aborted);
+    }
+
+    #[test]
+    fn test_config() {
+        let config: LlmConfig = toml::from_str(
+            r#"
+provider = "openai"
+model = "qwen2.5-coder"
+base_url = "http://localhost:8000/v1"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.provider, ProviderKind::OpenAi);
+        assert_eq!(config.max_tokens, 8192);
+        let provider = from_config(&config).unwrap();
+        assert_eq!(
+            (provider.name(), provider.model()),
+            ("openai", "qwen2.5-coder")
+        );
+    }
+
+    #[test]
+    fn test_content_block_wire_format() {
+        let block: ContentBlock = serde_json::from_str(
+            r#"{"type": "tool_use", "id": "t1", "name": "read_file", "input": {"path": "a"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            block,
+            ContentBlock::ToolUse {
+                id: "t1".to_string(),
+                name: "read_file".to_string(),
+                input: serde_json::json!({"path": "a"}),
+            }
+        );
+    }
+}
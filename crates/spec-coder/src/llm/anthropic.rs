@@ -0,0 +1,365 @@
+//! The Anthropic Messages API.
+
+use super::{
+    http, sse, ChatRequest, ChatResponse, ContentBlock, LlmConfig, LlmError, Provider, StopReason,
+    StreamEvent, Usage,
+};
+use serde_json::{json, Value};
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const DEFAULT_API_KEY_ENV: &str = "ANTHROPIC_API_KEY";
+const API_VERSION: &str = "2023-06-01";
+
+/// A provider backed by the Anthropic Messages API.
+pub struct Anthropic {
+    agent: ureq::Agent,
+    url: String,
+    api_key: String,
+    model: String,
+    max_tokens: u32,
+}
+
+impl Anthropic {
+    /// Creates the provider, failing if the API key is not set.
+    pub fn new(config: &LlmConfig) -> Result<Anthropic, LlmError> {
+        let api_key = config.api_key(DEFAULT_API_KEY_ENV).ok_or_else(|| {
+            LlmError::MissingApiKey(config.api_key_env(DEFAULT_API_KEY_ENV).to_string())
+        })?;
+        let base_url = config.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL);
+        Ok(Anthropic {
+            agent: http::agent(config.timeout()),
+            url: format!("{}/v1/messages", base_url.trim_end_matches('/')),
+            api_key,
+            model: config.model.clone(),
+            max_tokens: config.max_tokens,
+        })
+    }
+
+    fn send(&self, request: &ChatRequest, stream: bool) -> Result<ureq::Response, LlmError> {
+        let body = request_body(&self.model, self.max_tokens, request, stream);
+        http::post_json(
+            &self.agent,
+            &self.url,
+            &[
+                ("x-api-key", &self.api_key),
+                ("anthropic-version", API_VERSION),
+            ],
+            &body,
+        )
+    }
+}
+
+impl Provider for Anthropic {
+    fn name(&self) -> &str {
+        "anthropic"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let response = self.send(request, false)?;
+        parse_response(&http::read_json(response)?)
+    }
+
+    fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_event: &mut dyn FnMut(StreamEvent) -> bool,
+    ) -> Result<ChatResponse, LlmError> {
+        let response = self.send(request, true)?;
+        let mut stream = Stream::default();
+        sse::read_events(std::io::BufReader::new(response.into_reader()), |event| {
+            let data: Value = serde_json::from_str(&event.data)
+                .map_err(|err| LlmError::Decode(err.to_string()))?;
+            stream.apply(&data, on_event)
+        })?;
+        stream.finish()
+    }
+}
+
+/// Builds the JSON body of a Messages API request.
+fn request_body(model: &str, max_tokens: u32, request: &ChatRequest, stream: bool) -> Value {
+    let mut body = json!({
+        "model": model,
+        "max_tokens": request.max_tokens.unwrap_or(max_tokens),
+        "messages": request.messages,
+    });
+    if let Some(system) = &request.system {
+        body["system"] = json!(system);
+    }
+    if !request.tools.is_empty() {
+        body["tools"] = json!(request.tools);
+    }
+    if let Some(temperature) = request.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if stream {
+        body["stream"] = json!(true);
+    }
+    body
+}
+
+/// Reads a `message` object.
+fn parse_response(message: &Value) -> Result<ChatResponse, LlmError> {
+    let content = message["content"]
+        .as_array()
+        .ok_or_else(|| LlmError::Decode("message without content".to_string()))?
+        .iter()
+        // Thinking and other unknown blocks are not part of the conversation
+        .filter(|block| matches!(block["type"].as_str(), Some("text" | "tool_use")))
+        .map(|block| {
+            serde_json::from_value(block.clone()).map_err(|err| LlmError::Decode(err.to_string()))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(ChatResponse {
+        content,
+        stop_reason: StopReason::from_wire(message["stop_reason"].as_str().unwrap_or_default()),
+        usage: parse_usage(&message["usage"]),
+    })
+}
+
+fn parse_usage(usage: &Value) -> Usage {
+    Usage {
+        input_tokens: usage["input_tokens"].as_u64().unwrap_or_default(),
+        output_tokens: usage["output_tokens"].as_u64().unwrap_or_default(),
+    }
+}
+
+/// A block being streamed.
+enum Partial {
+    Text(String),
+    ToolUse {
+        id: String,
+        name: String,
+        json: String,
+    },
+    Ignored,
+}
+
+/// The state of a streamed response.
+#[derive(Default)]
+struct Stream {
+    blocks: Vec<ContentBlock>,
+    current: Option<Partial>,
+    stop_reason: Option<String>,
+    usage: Usage,
+    aborted: bool,
+}
+
+impl Stream {
+    /// Applies one event, returning whether more are expected.
+    fn apply(
+        &mut self,
+        event: &Value,
+        on_event: &mut dyn FnMut(StreamEvent) -> bool,
+    ) -> Result<bool, LlmError> {
+        match event["type"].as_str().unwrap_or_default() {
+            "message_start" => {
+                self.usage = parse_usage(&event["message"]["usage"]);
+            }
+            "content_block_start" => {
+                let block = &event["content_block"];
+                self.current = Some(match block["type"].as_str() {
+                    Some("text") => Partial::Text(String::new()),
+                    Some("tool_use") => Partial::ToolUse {
+                        id: block["id"].as_str().unwrap_or_default().to_string(),
+                        name: block["name"].as_str().unwrap_or_default().to_string(),
+                        json: String::new(),
+                    },
+                    _ => Partial::Ignored,
+                });
+            }
+            "content_block_delta" => {
+                let delta = &event["delta"];
+                match (&mut self.current, delta["type"].as_str()) {
+                    (Some(Partial::Text(text)), Some("text_delta")) => {
+                        let piece = delta["text"].as_str().unwrap_or_default();
+                        text.push_str(piece);
+                        if !on_event(StreamEvent::TextDelta(piece.to_string())) {
+                            self.aborted = true;
+                            return Ok(false);
+                        }
+                    }
+                    (Some(Partial::ToolUse { json, .. }), Some("input_json_delta")) => {
+                        json.push_str(delta["partial_json"].as_str().unwrap_or_default());
+                    }
+                    _ => {}
+                }
+            }
+            "content_block_stop" => match self.current.take() {
+                Some(Partial::Text(text)) => self.blocks.push(ContentBlock::Text { text }),
+                Some(Partial::ToolUse { id, name, json }) => {
+                    let input = if json.is_empty() {
+                        json!({})
+                    } else {
+                        serde_json::from_str(&json)
+                            .map_err(|err| LlmError::Decode(err.to_string()))?
+                    };
+                    let block = ContentBlock::ToolUse { id, name, input };
+                    let more = on_event(StreamEvent::ToolUse(block.clone()));
+                    self.blocks.push(block);
+                    if !more {
+                        self.aborted = true;
+                        return Ok(false);
+                    }
+                }
+                Some(Partial::Ignored) | None => {}
+            },
+            "message_delta" => {
+                if let Some(reason) = event["delta"]["stop_reason"].as_str() {
+                    self.stop_reason = Some(reason.to_string());
+                }
+                if let Some(output_tokens) = event["usage"]["output_tokens"].as_u64() {
+                    self.usage.output_tokens = output_tokens;
+                }
+            }
+            "message_stop" => return Ok(false),
+            "error" => return Err(LlmError::Decode(event["error"].to_string())),
+            // Pings
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn finish(mut self) -> Result<ChatResponse, LlmError> {
+        if self.aborted {
+            if let Some(Partial::Text(text)) = self.current.take() {
+                self.blocks.push(ContentBlock::Text { text });
+            }
+            return Ok(ChatResponse {
+                content: self.blocks,
+                stop_reason: StopReason::Aborted,
+                usage: self.usage,
+            });
+        }
+        let stop_reason = self
+            .stop_reason
+            .ok_or_else(|| LlmError::Decode("stream ended before the message".to_string()))?;
+        Ok(ChatResponse {
+            content: self.blocks,
+            stop_reason: StopReason::from_wire(&stop_reason),
+            usage: self.usage,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{Message, ToolDefinition};
+
+    #[test]
+    fn test_request_body() {
+        let request = ChatRequest {
+            system: Some("Be terse.".to_string()),
+            messages: vec![Message::user("hi")],
+            tools: vec![ToolDefinition {
+                name: "read_file".to_string(),
+                description: "Reads a file".to_string(),
+                input_schema: json!({"type": "object"}),
+            }],
+            ..ChatRequest::default()
+        };
+        assert_eq!(
+            request_body("claude", 1024, &request, false),
+            json!({
+                "model": "claude",
+                "max_tokens": 1024,
+                "system": "Be terse.",
+                "messages": [{"role": "user", "content": [{"type": "text", "text": "hi"}]}],
+                "tools": [{
+                    "name": "read_file",
+                    "description": "Reads a file",
+                    "input_schema": {"type": "object"},
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let response = parse_response(&json!({
+            "content": [
+                {"type": "thinking", "thinking": "..."},
+                {"type": "text", "text": "Reading."},
+                {"type": "tool_use", "id": "t1", "name": "read_file", "input": {"path": "a"}},
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 10, "output_tokens": 5},
+        }))
+        .unwrap();
+        assert_eq!(response.text(), "Reading.");
+        assert_eq!(response.tool_uses().count(), 1);
+        assert_eq!(response.stop_reason, StopReason::ToolUse);
+        assert_eq!(
+            response.usage,
+            Usage {
+                input_tokens: 10,
+                output_tokens: 5,
+            }
+        );
+    }
+
+    #[test]
+    fn test_stream() {
+        let events = [
+            json!({"type": "message_start", "message": {"usage": {"input_tokens": 7}}}),
+            json!({"type": "content_block_start", "content_block": {"type": "text"}}),
+            json!({"type": "content_block_delta", "delta": {"type": "text_delta", "text": "He"}}),
+            json!({"type": "content_block_delta", "delta": {"type": "text_delta", "text": "y"}}),
+            json!({"type": "content_block_stop"}),
+            json!({"type": "content_block_start",
+                   "content_block": {"type": "tool_use", "id": "t1", "name": "ls"}}),
+            json!({"type": "content_block_delta",
+                   "delta": {"type": "input_json_delta", "partial_json": "{\"dir\":"}}),
+            json!({"type": "content_block_delta",
+                   "delta": {"type": "input_json_delta", "partial_json": " \"src\"}"}}),
+            json!({"type": "content_block_stop"}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"},
+                   "usage": {"output_tokens": 3}}),
+            json!({"type": "message_stop"}),
+        ];
+        let mut stream = Stream::default();
+        let mut seen = Vec::new();
+        for event in &events {
+            stream
+                .apply(event, &mut |event| {
+                    seen.push(event);
+                    true
+                })
+                .unwrap();
+        }
+        let tool_use = ContentBlock::ToolUse {
+            id: "t1".to_string(),
+            name: "ls".to_string(),
+            input: json!({"dir": "src"}),
+        };
+        assert_eq!(
+            seen,
+            [
+                StreamEvent::TextDelta("He".to_string()),
+                StreamEvent::TextDelta("y".to_string()),
+                StreamEvent::ToolUse(tool_use.clone()),
+            ]
+        );
+        let response = stream.finish().unwrap();
+        assert_eq!(
+            response.content,
+            [
+                ContentBlock::Text {
+                    text: "Hey".to_string(),
+                },
+                tool_use,
+            ]
+        );
+        assert_eq!(
+            response.usage,
+            Usage {
+                input_tokens: 7,
+                output_tokens: 3,
+            }
+        );
+    }
+}
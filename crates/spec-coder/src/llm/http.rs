@@ -0,0 +1,41 @@
+//! JSON-over-HTTP transport shared by the providers.
+
+use super::LlmError;
+use std::time::Duration;
+
+/// Builds the HTTP agent of a provider.
+pub fn agent(timeout: Duration) -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout(timeout).build()
+}
+
+/// POSTs `body` to `url`, turning non-success statuses into errors.
+pub fn post_json(
+    agent: &ureq::Agent,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &serde_json::Value,
+) -> Result<ureq::Response, LlmError> {
+    let mut request = agent.post(url).set("content-type", "application/json");
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    match request.send_string(&body.to_string()) {
+        Ok(response) => Ok(response),
+        Err(ureq::Error::Status(status, response)) => Err(LlmError::Http {
+            status,
+            retry_after: response
+                .header("retry-after")
+                .and_then(|secs| secs.trim().parse().ok()),
+            body: response.into_string().unwrap_or_default(),
+        }),
+        Err(err) => Err(LlmError::Transport(err.to_string())),
+    }
+}
+
+/// Decodes a JSON response body.
+pub fn read_json<T: serde::de::DeserializeOwned>(response: ureq::Response) -> Result<T, LlmError> {
+    let body = response
+        .into_string()
+        .map_err(|err| LlmError::Transport(err.to_string()))?;
+    serde_json::from_str(&body).map_err(|err| LlmError::Decode(err.to_string()))
+}
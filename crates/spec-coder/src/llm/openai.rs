@@ -0,0 +1,441 @@
+//! The OpenAI chat completions API, as also served by vLLM, llama.cpp,
+//! Ollama and most proxies.
+
+use super::{
+    http, sse, ChatRequest, ChatResponse, ContentBlock, LlmConfig, LlmError, Provider, Role,
+    StopReason, StreamEvent, Usage,
+};
+use serde_json::{json, Value};
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_API_KEY_ENV: &str = "OPENAI_API_KEY";
+
+/// A provider backed by an OpenAI-compatible endpoint.
+pub struct OpenAi {
+    agent: ureq::Agent,
+    url: String,
+    /// Optional, since local servers usually do not check it
+    authorization: Option<String>,
+    model: String,
+    max_tokens: u32,
+}
+
+impl OpenAi {
+    /// Creates the provider; the API key is sent only if set.
+    pub fn new(config: &LlmConfig) -> Result<OpenAi, LlmError> {
+        let base_url = config.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL);
+        Ok(OpenAi {
+            agent: http::agent(config.timeout()),
+            url: format!("{}/chat/completions", base_url.trim_end_matches('/')),
+            authorization: config
+                .api_key(DEFAULT_API_KEY_ENV)
+                .map(|key| format!("Bearer {key}")),
+            model: config.model.clone(),
+            max_tokens: config.max_tokens,
+        })
+    }
+
+    fn send(&self, request: &ChatRequest, stream: bool) -> Result<ureq::Response, LlmError> {
+        let body = request_body(&self.model, self.max_tokens, request, stream);
+        let headers: Vec<_> = self
+            .authorization
+            .iter()
+            .map(|value| ("authorization", value.as_str()))
+            .collect();
+        http::post_json(&self.agent, &self.url, &headers, &body)
+    }
+}
+
+impl Provider for OpenAi {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let response = self.send(request, false)?;
+        parse_response(&http::read_json(response)?)
+    }
+
+    fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_event: &mut dyn FnMut(StreamEvent) -> bool,
+    ) -> Result<ChatResponse, LlmError> {
+        let response = self.send(request, true)?;
+        let mut stream = Stream::default();
+        sse::read_events(std::io::BufReader::new(response.into_reader()), |event| {
+            if event.data == "[DONE]" {
+                return Ok(false);
+            }
+            let chunk: Value = serde_json::from_str(&event.data)
+                .map_err(|err| LlmError::Decode(err.to_string()))?;
+            Ok(stream.apply(&chunk, on_event))
+        })?;
+        stream.finish(on_event)
+    }
+}
+
+/// Builds the JSON body of a chat completions request.
+fn request_body(model: &str, max_tokens: u32, request: &ChatRequest, stream: bool) -> Value {
+    let mut messages = Vec::new();
+    if let Some(system) = &request.system {
+        messages.push(json!({"role": "system", "content": system}));
+    }
+    for message in &request.messages {
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+        for block in &message.content {
+            match block {
+                ContentBlock::Text { text: piece } => text.push_str(piece),
+                ContentBlock::ToolUse { id, name, input } => tool_calls.push(json!({
+                    "id": id,
+                    "type": "function",
+                    "function": {"name": name, "arguments": input.to_string()},
+                })),
+                // Tool results are messages of their own
+                ContentBlock::ToolResult {
+                    tool_use_id,
+                    content,
+                    ..
+                } => messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": tool_use_id,
+                    "content": content,
+                })),
+            }
+        }
+        if text.is_empty() && tool_calls.is_empty() {
+            continue;
+        }
+        let mut wire = match message.role {
+            Role::User => json!({"role": "user", "content": text}),
+            Role::Assistant => json!({"role": "assistant", "content": text}),
+        };
+        if !tool_calls.is_empty() {
+            wire["tool_calls"] = json!(tool_calls);
+        }
+        messages.push(wire);
+    }
+    let mut body = json!({
+        "model": model,
+        "max_tokens": request.max_tokens.unwrap_or(max_tokens),
+        "messages": messages,
+    });
+    if !request.tools.is_empty() {
+        let tools: Vec<_> = request
+            .tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.input_schema,
+                    },
+                })
+            })
+            .collect();
+        body["tools"] = json!(tools);
+    }
+    if let Some(temperature) = request.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if stream {
+        body["stream"] = json!(true);
+        body["stream_options"] = json!({"include_usage": true});
+    }
+    body
+}
+
+/// Reads a tool call, whose arguments are a JSON string.
+fn tool_use(id: &str, name: &str, arguments: &str) -> Result<ContentBlock, LlmError> {
+    let input = if arguments.trim().is_empty() {
+        json!({})
+    } else {
+        serde_json::from_str(arguments).map_err(|err| LlmError::Decode(err.to_string()))?
+    };
+    Ok(ContentBlock::ToolUse {
+        id: id.to_string(),
+        name: name.to_string(),
+        input,
+    })
+}
+
+/// Reads a chat completion object.
+fn parse_response(completion: &Value) -> Result<ChatResponse, LlmError> {
+    let choice = &completion["choices"][0];
+    let message = &choice["message"];
+    if !message.is_object() {
+        return Err(LlmError::Decode("completion without a message".to_string()));
+    }
+    let mut content = Vec::new();
+    if let Some(text) = message["content"].as_str().filter(|text| !text.is_empty()) {
+        content.push(ContentBlock::Text {
+            text: text.to_string(),
+        });
+    }
+    for call in message["tool_calls"].as_array().into_iter().flatten() {
+        content.push(tool_use(
+            call["id"].as_str().unwrap_or_default(),
+            call["function"]["name"].as_str().unwrap_or_default(),
+            call["function"]["arguments"].as_str().unwrap_or_default(),
+        )?);
+    }
+    Ok(ChatResponse {
+        content,
+        stop_reason: StopReason::from_wire(choice["finish_reason"].as_str().unwrap_or_default()),
+        usage: parse_usage(&completion["usage"]),
+    })
+}
+
+fn parse_usage(usage: &Value) -> Usage {
+    Usage {
+        input_tokens: usage["prompt_tokens"].as_u64().unwrap_or_default(),
+        output_tokens: usage["completion_tokens"].as_u64().unwrap_or_default(),
+    }
+}
+
+/// A tool call being streamed.
+#[derive(Default)]
+struct PartialCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// The state of a streamed response.
+#[derive(Default)]
+struct Stream {
+    text: String,
+    /// Calls by their index in the choice
+    calls: Vec<PartialCall>,
+    finish_reason: Option<String>,
+    usage: Usage,
+    aborted: bool,
+}
+
+impl Stream {
+    /// Applies one chunk, returning whether more are wanted.
+    fn apply(&mut self, chunk: &Value, on_event: &mut dyn FnMut(StreamEvent) -> bool) -> bool {
+        if chunk["usage"].is_object() {
+            self.usage = parse_usage(&chunk["usage"]);
+        }
+        let choice = &chunk["choices"][0];
+        let delta = &choice["delta"];
+        if let Some(piece) = delta["content"].as_str().filter(|piece| !piece.is_empty()) {
+            self.text.push_str(piece);
+            if !on_event(StreamEvent::TextDelta(piece.to_string())) {
+                self.aborted = true;
+                return false;
+            }
+        }
+        for call in delta["tool_calls"].as_array().into_iter().flatten() {
+            let index = call["index"].as_u64().unwrap_or_default() as usize;
+            if self.calls.len() <= index {
+                self.calls.resize_with(index + 1, PartialCall::default);
+            }
+            let partial = &mut self.calls[index];
+            if let Some(id) = call["id"].as_str() {
+                partial.id.push_str(id);
+            }
+            if let Some(name) = call["function"]["name"].as_str() {
+                partial.name.push_str(name);
+            }
+            if let Some(arguments) = call["function"]["arguments"].as_str() {
+                partial.arguments.push_str(arguments);
+            }
+        }
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.finish_reason = Some(reason.to_string());
+        }
+        true
+    }
+
+    fn finish(
+        self,
+        on_event: &mut dyn FnMut(StreamEvent) -> bool,
+    ) -> Result<ChatResponse, LlmError> {
+        // The calls are left out, their arguments being incomplete
+        if self.aborted {
+            return Ok(ChatResponse {
+                content: vec![ContentBlock::Text { text: self.text }],
+                stop_reason: StopReason::Aborted,
+                usage: self.usage,
+            });
+        }
+        let finish_reason = self
+            .finish_reason
+            .ok_or_else(|| LlmError::Decode("stream ended before the completion".to_string()))?;
+        let mut content = Vec::new();
+        if !self.text.is_empty() {
+            content.push(ContentBlock::Text { text: self.text });
+        }
+        // Arguments are only complete once the stream is
+        for call in &self.calls {
+            let block = tool_use(&call.id, &call.name, &call.arguments)?;
+            let more = on_event(StreamEvent::ToolUse(block.clone()));
+            content.push(block);
+            if !more {
+                return Ok(ChatResponse {
+                    content,
+                    stop_reason: StopReason::Aborted,
+                    usage: self.usage,
+                });
+            }
+        }
+        Ok(ChatResponse {
+            content,
+            stop_reason: StopReason::from_wire(&finish_reason),
+            usage: self.usage,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{Message, ToolDefinition};
+
+    #[test]
+    fn test_request_body() {
+        let request = ChatRequest {
+            system: Some("Be terse.".to_string()),
+            messages: vec![
+                Message::user("list src"),
+                Message {
+                    role: Role::Assistant,
+                    content: vec![ContentBlock::ToolUse {
+                        id: "c1".to_string(),
+                        name: "ls".to_string(),
+                        input: json!({"dir": "src"}),
+                    }],
+                },
+                Message {
+                    role: Role::User,
+                    content: vec![ContentBlock::ToolResult {
+                        tool_use_id: "c1".to_string(),
+                        content: "lib.rs".to_string(),
+                        is_error: false,
+                    }],
+                },
+            ],
+            tools: vec![ToolDefinition {
+                name: "ls".to_string(),
+                description: "Lists a directory".to_string(),
+                input_schema: json!({"type": "object"}),
+            }],
+            temperature: Some(0.0),
+            ..ChatRequest::default()
+        };
+        assert_eq!(
+            request_body("qwen", 512, &request, false),
+            json!({
+                "model": "qwen",
+                "max_tokens": 512,
+                "temperature": 0.0,
+                "messages": [
+                    {"role": "system", "content": "Be terse."},
+                    {"role": "user", "content": "list src"},
+                    {"role": "assistant", "content": "", "tool_calls": [{
+                        "id": "c1",
+                        "type": "function",
+                        "function": {"name": "ls", "arguments": "{\"dir\":\"src\"}"},
+                    }]},
+                    {"role": "tool", "tool_call_id": "c1", "content": "lib.rs"},
+                ],
+                "tools": [{
+                    "type": "function",
+                    "function": {
+                        "name": "ls",
+                        "description": "Lists a directory",
+                        "parameters": {"type": "object"},
+                    },
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let response = parse_response(&json!({
+            "choices": [{
+                "message": {
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "c1",
+                        "type": "function",
+                        "function": {"name": "ls", "arguments": "{\"dir\": \"src\"}"},
+                    }],
+                },
+                "finish_reason": "tool_calls",
+            }],
+            "usage": {"prompt_tokens": 12, "completion_tokens": 4},
+        }))
+        .unwrap();
+        assert_eq!(
+            response.content,
+            [ContentBlock::ToolUse {
+                id: "c1".to_string(),
+                name: "ls".to_string(),
+                input: json!({"dir": "src"}),
+            }]
+        );
+        assert_eq!(response.stop_reason, StopReason::ToolUse);
+        assert_eq!(response.usage.input_tokens, 12);
+    }
+
+    #[test]
+    fn test_stream() {
+        let chunks = [
+            json!({"choices": [{"delta": {"role": "assistant", "content": "Ok"}}]}),
+            json!({"choices": [{"delta": {"tool_calls": [{
+                "index": 0, "id": "c1", "function": {"name": "ls", "arguments": "{\"dir\""}
+            }]}}]}),
+            json!({"choices": [{"delta": {"tool_calls": [{
+                "index": 0, "function": {"arguments": ": \"src\"}"}
+            }]}}]}),
+            json!({"choices": [{"delta": {}, "finish_reason": "tool_calls"}]}),
+            json!({"choices": [], "usage": {"prompt_tokens": 3, "completion_tokens": 2}}),
+        ];
+        let mut stream = Stream::default();
+        let mut seen = Vec::new();
+        for chunk in &chunks {
+            assert!(stream.apply(chunk, &mut |event| {
+                seen.push(event);
+                true
+            }));
+        }
+        let response = stream
+            .finish(&mut |event| {
+                seen.push(event);
+                true
+            })
+            .unwrap();
+        let tool_use = ContentBlock::ToolUse {
+            id: "c1".to_string(),
+            name: "ls".to_string(),
+            input: json!({"dir": "src"}),
+        };
+        assert_eq!(
+            seen,
+            [
+                StreamEvent::TextDelta("Ok".to_string()),
+                StreamEvent::ToolUse(tool_use.clone()),
+            ]
+        );
+        assert_eq!(response.text(), "Ok");
+        assert_eq!(response.content[1], tool_use);
+        assert_eq!(
+            response.usage,
+            Usage {
+                input_tokens: 3,
+                output_tokens: 2,
+            }
+        );
+    }
+}
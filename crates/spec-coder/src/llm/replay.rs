@@ -0,0 +1,279 @@
+//! Recording and replay of model responses.
+//!
+//! [`Recording`] wraps a provider and stores every response it gets in a
+//! directory, keyed by a hash of the request: the model, the system prompt,
+//! the messages, the tools and the sampling parameters. [`Replay`] answers
+//! the same requests from that directory without a network or an API key,
+//! so a run recorded once is replayed byte for byte, in CI or to debug it.
+//! A request sent more than once, e.g. by a run retrying a role, is
+//! answered with the responses in the order they were recorded. A request
+//! that was never recorded fails the replay instead of reaching a model.
+
+use super::{
+    ChatRequest, ChatResponse, ContentBlock, LlmConfig, LlmError, Message, Provider, StopReason,
+    StreamEvent, ToolDefinition, Usage,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// What a request is keyed by.
+#[derive(Serialize)]
+struct Key<'a> {
+    model: &'a str,
+    system: Option<&'a str>,
+    messages: &'a [Message],
+    tools: &'a [ToolDefinition],
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+}
+
+/// The key of `request` to a model: the hex SHA-256 of its JSON.
+pub fn key(model: &str, request: &ChatRequest) -> String {
+    let key = Key {
+        model,
+        system: request.system.as_deref(),
+        messages: &request.messages,
+        tools: &request.tools,
+        max_tokens: request.max_tokens,
+        temperature: request.temperature,
+    };
+    let json = serde_json::to_vec(&key).expect("requests serialize");
+    Sha256::digest(json)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// A response as it is stored.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Recorded {
+    content: Vec<ContentBlock>,
+    stop_reason: String,
+    usage: Usage,
+}
+
+impl From<&ChatResponse> for Recorded {
+    fn from(response: &ChatResponse) -> Recorded {
+        let stop_reason = match &response.stop_reason {
+            StopReason::EndTurn => "end_turn",
+            StopReason::MaxTokens => "max_tokens",
+            StopReason::ToolUse => "tool_use",
+            StopReason::Aborted => "aborted",
+            StopReason::Other(reason) => reason,
+        };
+        Recorded {
+            content: response.content.clone(),
+            stop_reason: stop_reason.to_string(),
+            usage: response.usage,
+        }
+    }
+}
+
+impl From<Recorded> for ChatResponse {
+    fn from(recorded: Recorded) -> ChatResponse {
+        ChatResponse {
+            content: recorded.content,
+            stop_reason: match recorded.stop_reason.as_str() {
+                "aborted" => StopReason::Aborted,
+                reason => StopReason::from_wire(reason),
+            },
+            usage: recorded.usage,
+        }
+    }
+}
+
+/// The file of the responses to the requests with `key`.
+fn path(dir: &Path, key: &str) -> PathBuf {
+    dir.join(format!("{key}.json"))
+}
+
+/// A provider recording the responses of another in a directory.
+pub struct Recording<P> {
+    inner: P,
+    dir: PathBuf,
+    /// The responses of this run by key, as their files hold them
+    responses: Mutex<HashMap<String, Vec<Recorded>>>,
+}
+
+impl<P: Provider> Recording<P> {
+    /// Records the responses of `inner` in `dir`, replacing those recorded
+    /// there before for the same requests.
+    pub fn new(inner: P, dir: impl Into<PathBuf>) -> Recording<P> {
+        Recording {
+            inner,
+            dir: dir.into(),
+            responses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn record(&self, request: &ChatRequest, response: &ChatResponse) -> Result<(), LlmError> {
+        let key = key(self.inner.model(), request);
+        let mut responses = self.responses.lock().expect("no recording panics");
+        let recorded = responses.entry(key.clone()).or_default();
+        recorded.push(Recorded::from(response));
+        let json = serde_json::to_string_pretty(recorded).expect("responses serialize");
+        let path = path(&self.dir, &key);
+        std::fs::create_dir_all(&self.dir)
+            .and_then(|()| std::fs::write(&path, json + "\n"))
+            .map_err(|err| LlmError::Recording(format!("{}: {err}", path.display())))
+    }
+}
+
+impl<P: Provider> Provider for Recording<P> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let response = self.inner.chat(request)?;
+        self.record(request, &response)?;
+        Ok(response)
+    }
+
+    /// Records the response as it was read, so that one cut short by
+    /// `on_event` is replayed as short.
+    fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_event: &mut dyn FnMut(StreamEvent) -> bool,
+    ) -> Result<ChatResponse, LlmError> {
+        let response = self.inner.chat_stream(request, on_event)?;
+        self.record(request, &response)?;
+        Ok(response)
+    }
+}
+
+/// A provider answering requests with the responses recorded in a
+/// directory.
+pub struct Replay {
+    name: String,
+    model: String,
+    dir: PathBuf,
+    /// How many responses of each key were replayed
+    served: Mutex<HashMap<String, usize>>,
+}
+
+impl Replay {
+    /// Replays the responses recorded in `dir` for the model of `config`.
+    pub fn new(config: &LlmConfig, dir: impl Into<PathBuf>) -> Replay {
+        Replay {
+            name: config.provider.to_string(),
+            model: config.model.clone(),
+            dir: dir.into(),
+            served: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Provider for Replay {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let key = key(&self.model, request);
+        let path = path(&self.dir, &key);
+        let json = match std::fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(LlmError::NotRecorded(key));
+            }
+            Err(err) => {
+                return Err(LlmError::Recording(format!("{}: {err}", path.display())));
+            }
+        };
+        let mut recorded: Vec<Recorded> = serde_json::from_str(&json)
+            .map_err(|err| LlmError::Recording(format!("{}: {err}", path.display())))?;
+        let mut served = self.served.lock().expect("no replay panics");
+        let count = served.entry(key.clone()).or_default();
+        if *count >= recorded.len() {
+            return Err(LlmError::NotRecorded(key));
+        }
+        *count += 1;
+        Ok(recorded.swap_remove(*count - 1).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ProviderKind;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Numbers its responses.
+    struct Counter(AtomicUsize);
+
+    impl Provider for Counter {
+        fn name(&self) -> &str {
+            "anthropic"
+        }
+
+        fn model(&self) -> &str {
+            "claude-sonnet-4-5-20250929"
+        }
+
+        fn chat(&self, _: &ChatRequest) -> Result<ChatResponse, LlmError> {
+            let n = self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(ChatResponse {
+                content: vec![ContentBlock::Text {
+                    text: format!("response {n}"),
+                }],
+                stop_reason: StopReason::EndTurn,
+                usage: Usage {
+                    input_tokens: 10,
+                    output_tokens: 2,
+                },
+            })
+        }
+    }
+
+    fn request(text: &str) -> ChatRequest {
+        ChatRequest {
+            system: Some("You write Rust.".to_string()),
+            messages: vec![Message::user(text)],
+            ..ChatRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let recording = Recording::new(Counter(AtomicUsize::new(0)), dir.path());
+        let recorded: Vec<ChatResponse> = [request("a"), request("b"), request("a")]
+            .iter()
+            .map(|request| recording.chat(request).unwrap())
+            .collect();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        let replay = Replay::new(&LlmConfig::new(ProviderKind::Anthropic), dir.path());
+        assert_eq!(replay.chat(&request("a")).unwrap(), recorded[0]);
+        assert_eq!(replay.chat(&request("b")).unwrap(), recorded[1]);
+        assert_eq!(replay.chat(&request("a")).unwrap().text(), "response 2");
+        // Every response to `a` was replayed, and `c` never was recorded
+        assert!(matches!(
+            replay.chat(&request("a")),
+            Err(LlmError::NotRecorded(_))
+        ));
+        assert!(matches!(
+            replay.chat(&request("c")),
+            Err(LlmError::NotRecorded(_))
+        ));
+
+        // The key depends on the model as well as on the request
+        assert_ne!(
+            key("gpt-4o", &request("a")),
+            key(replay.model(), &request("a"))
+        );
+    }
+}
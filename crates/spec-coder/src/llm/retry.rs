@@ -0,0 +1,374 @@
+//! Retries of transient provider failures.
+//!
+//! [`Retrying`] wraps a provider and sends a request again when it fails in
+//! a way that may pass on its own: rate limiting (HTTP 429), a request
+//! timeout (408), an overloaded or failing server (5xx), or the connection
+//! failing or timing out. It waits between attempts with full-jitter
+//! exponential backoff, or as long as the provider asks with
+//! `retry-after`. A request too long for the context window of the model is
+//! sent again without its oldest turns, see [`trim`], and with
+//! [`LlmConfig::context_tokens`] set such requests are trimmed before they
+//! are sent at all.
+
+use super::{ChatRequest, ChatResponse, ContentBlock, LlmConfig, LlmError, Provider, StreamEvent};
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// What the providers answer a request exceeding the context window with.
+const CONTEXT_ERRORS: &[&str] = &[
+    "prompt is too long",
+    "context_length_exceeded",
+    "maximum context length",
+    "context window",
+];
+
+/// Characters per token, to estimate the size of a request.
+const CHARS_PER_TOKEN: u64 = 4;
+
+/// How often and how long to wait before retrying.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// The longest first wait; it doubles with every retry
+    pub base_delay: Duration,
+    /// The longest wait
+    pub max_delay: Duration,
+    pub context_tokens: Option<u64>,
+}
+
+impl RetryPolicy {
+    pub fn new(config: &LlmConfig) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: config.max_retries,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            context_tokens: config
+                .context_tokens
+                .map(|tokens| tokens.saturating_sub(u64::from(config.max_tokens))),
+        }
+    }
+
+    /// The wait before retry `attempt`, counting from 0: a random duration
+    /// up to the capped exponential delay.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let cap = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(attempt);
+        let millis = cap.as_millis() as u64;
+        Duration::from_millis(hasher.finish() % (millis + 1))
+    }
+}
+
+/// Whether `err` may pass if the request is sent again.
+pub fn is_transient(err: &LlmError) -> bool {
+    match err {
+        LlmError::Http { status, .. } => matches!(status, 408 | 429 | 500..=599),
+        LlmError::Transport(_) => true,
+        LlmError::MissingApiKey(_)
+        | LlmError::Decode(_)
+        | LlmError::NotRecorded(_)
+        | LlmError::Recording(_) => false,
+    }
+}
+
+/// Whether `err` says the request exceeds the context window.
+pub fn is_context_overflow(err: &LlmError) -> bool {
+    let LlmError::Http { status, body, .. } = err else {
+        return false;
+    };
+    let body = body.to_lowercase();
+    matches!(status, 400 | 413) && CONTEXT_ERRORS.iter().any(|error| body.contains(error))
+}
+
+/// Estimates the input tokens of `request`.
+pub fn estimate_tokens(request: &ChatRequest) -> u64 {
+    let mut chars = request.system.as_deref().map_or(0, str::len);
+    for message in &request.messages {
+        for block in &message.content {
+            chars += match block {
+                ContentBlock::Text { text } => text.len(),
+                ContentBlock::ToolUse { input, .. } => input.to_string().len(),
+                ContentBlock::ToolResult { content, .. } => content.len(),
+            };
+        }
+    }
+    chars as u64 / CHARS_PER_TOKEN
+}
+
+/// Leaves the oldest exchange after the first message out of `request`:
+/// the reply to it and the message after the reply, so that the roles still
+/// alternate and tool results stay with their invocations. The first
+/// message, with the task, and the latest are kept. Returns false if there
+/// is nothing left to leave out.
+pub fn trim(request: &mut ChatRequest) -> bool {
+    if request.messages.len() <= 3 {
+        return false;
+    }
+    request.messages.drain(1..3);
+    true
+}
+
+/// A provider retrying the transient failures of another.
+pub struct Retrying<P> {
+    inner: P,
+    policy: RetryPolicy,
+    sleep: Box<dyn Fn(Duration) + Send + Sync>,
+}
+
+impl<P: Provider> Retrying<P> {
+    pub fn new(inner: P, policy: RetryPolicy) -> Retrying<P> {
+        Retrying {
+            inner,
+            policy,
+            sleep: Box::new(std::thread::sleep),
+        }
+    }
+
+    /// Waits with `sleep` instead of blocking the thread, e.g. in tests.
+    pub fn with_sleep(mut self, sleep: impl Fn(Duration) + Send + Sync + 'static) -> Retrying<P> {
+        self.sleep = Box::new(sleep);
+        self
+    }
+
+    /// Sends `request` with `send` until it succeeds, fails for good, the
+    /// retries run out or `retryable` says it may not be sent again.
+    fn send(
+        &self,
+        request: &ChatRequest,
+        retryable: impl Fn() -> bool,
+        mut send: impl FnMut(&ChatRequest) -> Result<ChatResponse, LlmError>,
+    ) -> Result<ChatResponse, LlmError> {
+        let mut request = request.clone();
+        if let Some(limit) = self.policy.context_tokens {
+            while estimate_tokens(&request) > limit && trim(&mut request) {}
+        }
+        let mut attempt = 0;
+        loop {
+            let err = match send(&request) {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            if !retryable() {
+                return Err(err);
+            }
+            if is_context_overflow(&err) && trim(&mut request) {
+                continue;
+            }
+            if !is_transient(&err) || attempt >= self.policy.max_retries {
+                return Err(err);
+            }
+            let delay = match err {
+                LlmError::Http {
+                    retry_after: Some(secs),
+                    ..
+                } => Duration::from_secs(secs).min(self.policy.max_delay),
+                _ => self.policy.delay(attempt),
+            };
+            (self.sleep)(delay);
+            attempt += 1;
+        }
+    }
+}
+
+impl<P: Provider> Provider for Retrying<P> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        self.send(request, || true, |request| self.inner.chat(request))
+    }
+
+    /// Retries only while nothing of the response has been reported, since
+    /// reported events cannot be taken back.
+    fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_event: &mut dyn FnMut(StreamEvent) -> bool,
+    ) -> Result<ChatResponse, LlmError> {
+        let reported = Cell::new(false);
+        self.send(
+            request,
+            || !reported.get(),
+            |request| {
+                self.inner.chat_stream(request, &mut |event| {
+                    reported.set(true);
+                    on_event(event)
+                })
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{Message, StopReason, Usage};
+    use std::sync::{Arc, Mutex};
+
+    /// Fails with the scripted errors, then succeeds, recording how many
+    /// messages each request had.
+    struct Flaky {
+        errors: Mutex<Vec<LlmError>>,
+        lengths: Mutex<Vec<usize>>,
+    }
+
+    impl Flaky {
+        fn new(errors: Vec<LlmError>) -> Flaky {
+            Flaky {
+                errors: Mutex::new(errors),
+                lengths: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl Provider for Flaky {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn model(&self) -> &str {
+            "flaky"
+        }
+
+        fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+            self.lengths.lock().unwrap().push(request.messages.len());
+            let mut errors = self.errors.lock().unwrap();
+            if !errors.is_empty() {
+                return Err(errors.remove(0));
+            }
+            Ok(ChatResponse {
+                content: vec![ContentBlock::Text {
+                    text: "done".to_string(),
+                }],
+                stop_reason: StopReason::EndTurn,
+                usage: Usage::default(),
+            })
+        }
+    }
+
+    fn http(status: u16, body: &str, retry_after: Option<u64>) -> LlmError {
+        LlmError::Http {
+            status,
+            body: body.to_string(),
+            retry_after,
+        }
+    }
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            context_tokens: None,
+        }
+    }
+
+    fn conversation(turns: usize) -> ChatRequest {
+        let mut messages = vec![Message::user("task")];
+        for i in 0..turns {
+            messages.push(Message::assistant(format!("reply {i}")));
+            messages.push(Message::user(format!("feedback {i}")));
+        }
+        ChatRequest {
+            messages,
+            ..ChatRequest::default()
+        }
+    }
+
+    fn retrying(flaky: Flaky, policy: RetryPolicy) -> (Retrying<Flaky>, Arc<Mutex<Vec<Duration>>>) {
+        let waits = Arc::new(Mutex::new(Vec::new()));
+        let recorded = waits.clone();
+        let provider = Retrying::new(flaky, policy)
+            .with_sleep(move |delay| recorded.lock().unwrap().push(delay));
+        (provider, waits)
+    }
+
+    #[test]
+    fn test_retries_transient_failures() {
+        let flaky = Flaky::new(vec![
+            http(429, "rate limited", Some(3)),
+            http(529, "overloaded", None),
+            LlmError::Transport("timed out".to_string()),
+        ]);
+        let (provider, waits) = retrying(flaky, policy(3));
+        assert_eq!(provider.complete("hi").unwrap(), "done");
+        let waits = waits.lock().unwrap();
+        assert_eq!(waits.len(), 3);
+        assert_eq!(waits[0], Duration::from_secs(3));
+        assert!(waits[1] <= Duration::from_millis(200));
+        assert!(waits[2] <= Duration::from_millis(400));
+    }
+
+    #[test]
+    fn test_gives_up() {
+        let flaky = Flaky::new(vec![http(503, "down", None), http(503, "down", None)]);
+        let (provider, waits) = retrying(flaky, policy(1));
+        let err = provider.complete("hi").unwrap_err();
+        assert!(matches!(err, LlmError::Http { status: 503, .. }));
+        assert_eq!(waits.lock().unwrap().len(), 1);
+
+        // Errors that cannot pass on their own are not retried
+        let flaky = Flaky::new(vec![http(401, "invalid x-api-key", None)]);
+        let (provider, waits) = retrying(flaky, policy(3));
+        assert!(provider.complete("hi").is_err());
+        assert!(waits.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trims_context() {
+        let flaky = Flaky::new(vec![
+            http(
+                400,
+                "prompt is too long: 210000 tokens > 200000 maximum",
+                None,
+            ),
+            http(
+                400,
+                "This model's maximum context length is 128000 tokens",
+                None,
+            ),
+        ]);
+        let (provider, waits) = retrying(flaky, policy(0));
+        provider.chat(&conversation(3)).unwrap();
+        assert_eq!(*provider.inner.lengths.lock().unwrap(), [7, 5, 3]);
+        assert!(waits.lock().unwrap().is_empty());
+
+        // Nothing left to leave out
+        let flaky = Flaky::new(vec![http(400, "prompt is too long", None)]);
+        let (provider, _) = retrying(flaky, policy(0));
+        assert!(provider.chat(&conversation(1)).is_err());
+
+        // Trimmed before sending
+        let mut request = conversation(4);
+        request.messages[1] = Message::assistant("x".repeat(400));
+        let (provider, _) = retrying(
+            Flaky::new(Vec::new()),
+            RetryPolicy {
+                context_tokens: Some(50),
+                ..policy(0)
+            },
+        );
+        provider.chat(&request).unwrap();
+        assert_eq!(*provider.inner.lengths.lock().unwrap(), [7]);
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = policy(10);
+        for attempt in 0..10 {
+            let cap = Duration::from_millis(100 * 2u64.pow(attempt)).min(policy.max_delay);
+            assert!(policy.delay(attempt) <= cap);
+        }
+        assert!(policy.delay(40) <= policy.max_delay);
+    }
+}
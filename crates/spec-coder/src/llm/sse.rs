@@ -0,0 +1,81 @@
+//! Server-sent events, the framing both APIs use for streaming.
+
+use super::LlmError;
+use std::io::BufRead;
+
+/// One dispatched event.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    /// The `event:` field, if any
+    pub event: Option<String>,
+    /// The `data:` lines, joined with newlines
+    pub data: String,
+}
+
+/// Reads events from `reader`, calling `on_event` for each until it returns
+/// `false` or the stream ends.
+pub fn read_events(
+    reader: impl BufRead,
+    mut on_event: impl FnMut(Event) -> Result<bool, LlmError>,
+) -> Result<(), LlmError> {
+    let mut event = Event::default();
+    let mut has_data = false;
+    for line in reader.lines() {
+        let line = line.map_err(|err| LlmError::Transport(err.to_string()))?;
+        if line.is_empty() {
+            if has_data && !on_event(std::mem::take(&mut event))? {
+                return Ok(());
+            }
+            event = Event::default();
+            has_data = false;
+            continue;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((&line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => event.event = Some(value.to_string()),
+            "data" => {
+                if has_data {
+                    event.data.push('\n');
+                }
+                event.data.push_str(value);
+                has_data = true;
+            }
+            // Comments, ids and retry hints are irrelevant here
+            _ => {}
+        }
+    }
+    if has_data {
+        on_event(event)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_events() {
+        let stream = ": keep-alive\n\nevent: a\ndata: 1\ndata:2\n\ndata: last";
+        let mut events = Vec::new();
+        read_events(stream.as_bytes(), |event| {
+            events.push(event);
+            Ok(true)
+        })
+        .unwrap();
+        assert_eq!(
+            events,
+            [
+                Event {
+                    event: Some("a".to_string()),
+                    data: "1\n2".to_string(),
+                },
+                Event {
+                    event: None,
+                    data: "last".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,1101 @@
+//! A language server for spec files.
+//!
+//! `spec-coder lsp` speaks the Language Server Protocol over stdio, so that
+//! editors check a spec while it is written instead of when it is
+//! materialized. For every open `.yaml`, `.toml` or `.md` spec it publishes
+//! the problems [`spec::lint`] finds, from invariants naming undeclared state
+//! fields to functions no invariant constrains, along with parse errors. It
+//! also answers:
+//!
+//! - hovers, with the declaration of the state account, field, function,
+//!   argument, invariant or helper under the cursor;
+//! - go-to-definition, from a name to its declaration, from an invariant to
+//!   the functions that must preserve it, and from the declaration of a
+//!   function to the invariants it must preserve.
+//!
+//! Positions are found by scanning the source for the declarations of the
+//! parsed spec, see [`Outline`], so they work the same in every format.
+
+use serde_json::{json, Value};
+use spec::{Format, Function, Invariant, Severity, Spec, SpecError};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// What a declaration in a spec declares.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Symbol {
+    State(String),
+    /// A field of a state account: the account, then the field
+    Field(String, String),
+    Function(String),
+    /// An account or argument of a function: the function, then the name
+    Input(String, String),
+    /// An example of a function: the function, then the example
+    Example(String, String),
+    Helper(String),
+    Invariant(String),
+}
+
+/// Where a name is declared: its line, and its columns in bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The declarations of a spec, found in its source.
+#[derive(Debug, Default)]
+pub struct Outline {
+    pub declarations: Vec<(Symbol, Span)>,
+    /// The lines the `state`, `functions`, `helpers` and `invariants`
+    /// sections start on
+    sections: BTreeMap<&'static str, usize>,
+}
+
+/// The column range of `name` in `line` if the line declares it, as a
+/// `name:` key in YAML, a `name = ".."` key in TOML, or a heading or list
+/// item in Markdown.
+fn declares(line: &str, name: &str, format: Format) -> Option<(usize, usize)> {
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+    let candidates: Vec<String> = match format {
+        Format::Yaml => vec![format!("name: {name}"), format!("name: \"{name}\"")],
+        Format::Toml => vec![format!("name = \"{name}\"")],
+        Format::Markdown => {
+            let heading = trimmed.trim_start_matches('#');
+            if heading.len() < trimmed.len() && heading.trim() == name {
+                let start = indent + line[indent..].find(name)?;
+                return Some((start, start + name.len()));
+            }
+            vec![format!("- `{name}`"), format!("- **{name}**")]
+        }
+    };
+    for candidate in candidates {
+        let Some(at) = line.find(&candidate) else {
+            continue;
+        };
+        // The key must not end in the middle of a longer name
+        let after = line[at + candidate.len()..].chars().next();
+        if after.is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') {
+            continue;
+        }
+        if format == Format::Markdown && !line[..at].trim().is_empty() {
+            continue;
+        }
+        let start = at + candidate.find(name).expect("the candidate holds the name");
+        return Some((start, start + name.len()));
+    }
+    None
+}
+
+/// The line the section `key` of a spec starts on.
+fn section(lines: &[&str], keys: &[&str], format: Format) -> Option<usize> {
+    lines.iter().position(|line| {
+        keys.iter().any(|key| match format {
+            Format::Yaml => line
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.starts_with(':')),
+            Format::Toml => {
+                line.starts_with(&format!("[[{key}]]"))
+                    || line.starts_with(&format!("[[{key}."))
+                    || line
+                        .strip_prefix(key)
+                        .is_some_and(|rest| rest.trim_start().starts_with('='))
+            }
+            Format::Markdown => line
+                .strip_prefix("## ")
+                .is_some_and(|title| title.trim().eq_ignore_ascii_case(key)),
+        })
+    })
+}
+
+impl Outline {
+    /// Finds the declarations of `spec` in its source `src`. Declarations
+    /// are looked for in the order the spec lists them, each after the
+    /// previous one of its section, and those not found are left out.
+    pub fn new(spec: &Spec, src: &str, format: Format) -> Outline {
+        let lines: Vec<&str> = src.lines().collect();
+        let mut outline = Outline::default();
+        let find = |from: usize, name: &str| {
+            (from..lines.len()).find_map(|line| {
+                let (start, end) = declares(lines[line], name, format)?;
+                Some(Span { line, start, end })
+            })
+        };
+        let keys: [(&str, &[&str]); 4] = [
+            ("state", &["state"]),
+            ("functions", &["functions", "instructions"]),
+            ("helpers", &["helpers"]),
+            ("invariants", &["invariants"]),
+        ];
+        for (name, keys) in keys {
+            if let Some(line) = section(&lines, keys, format) {
+                outline.sections.insert(name, line);
+            }
+        }
+        let start = |outline: &Outline, section| outline.sections.get(section).copied();
+
+        let mut from = start(&outline, "state").unwrap_or(0);
+        for account in &spec.state {
+            let Some(span) = find(from, &account.name) else {
+                continue;
+            };
+            from = span.line + 1;
+            let mut field_from = from;
+            outline
+                .declarations
+                .push((Symbol::State(account.name.clone()), span));
+            for field in &account.fields {
+                if let Some(span) = find(field_from, &field.name) {
+                    field_from = span.line + 1;
+                    let symbol = Symbol::Field(account.name.clone(), field.name.clone());
+                    outline.declarations.push((symbol, span));
+                }
+            }
+        }
+
+        let mut from = start(&outline, "functions").unwrap_or(0);
+        for function in &spec.functions {
+            let Some(span) = find(from, &function.name) else {
+                continue;
+            };
+            from = span.line + 1;
+            outline
+                .declarations
+                .push((Symbol::Function(function.name.clone()), span));
+            let inputs = function
+                .accounts
+                .iter()
+                .map(|account| &account.name)
+                .chain(function.args.iter().map(|arg| &arg.name));
+            for name in inputs {
+                if let Some(span) = find(from, name) {
+                    let symbol = Symbol::Input(function.name.clone(), name.clone());
+                    outline.declarations.push((symbol, span));
+                }
+            }
+            for example in &function.examples {
+                if let Some(span) = find(from, &example.name) {
+                    let symbol = Symbol::Example(function.name.clone(), example.name.clone());
+                    outline.declarations.push((symbol, span));
+                }
+            }
+        }
+
+        let helpers = spec.helpers.iter().map(|h| Symbol::Helper(h.name.clone()));
+        let invariants = spec
+            .invariants
+            .iter()
+            .map(|i| Symbol::Invariant(i.name.clone()));
+        for (section, symbols) in [
+            ("helpers", helpers.collect::<Vec<_>>()),
+            ("invariants", invariants.collect()),
+        ] {
+            let mut from = start(&outline, section).unwrap_or(0);
+            for symbol in symbols {
+                let (Symbol::Helper(name) | Symbol::Invariant(name)) = &symbol else {
+                    continue;
+                };
+                if let Some(span) = find(from, name) {
+                    from = span.line + 1;
+                    outline.declarations.push((symbol, span));
+                }
+            }
+        }
+        outline
+    }
+
+    /// Where `symbol` is declared.
+    pub fn span(&self, symbol: &Symbol) -> Option<&Span> {
+        self.declarations
+            .iter()
+            .find(|(declared, _)| declared == symbol)
+            .map(|(_, span)| span)
+    }
+
+    /// The function whose declaration is the last one before `line`, if
+    /// `line` is in the functions section.
+    fn function_at(&self, line: usize) -> Option<&str> {
+        let mut function = None;
+        for (symbol, span) in &self.declarations {
+            if span.line > line {
+                continue;
+            }
+            match symbol {
+                Symbol::Function(name) => function = Some((span.line, name.as_str())),
+                Symbol::State(_) | Symbol::Helper(_) | Symbol::Invariant(_)
+                    if function.is_some_and(|(at, _)| span.line > at) =>
+                {
+                    function = None;
+                }
+                _ => {}
+            }
+        }
+        function.map(|(_, name)| name)
+    }
+
+    /// The span of a [`spec::Lint`] location, e.g. `functions.deposit` or
+    /// `functions.deposit.ensures[0]`: the line of the condition in YAML and
+    /// Markdown, and otherwise the declaration it is under.
+    pub fn locate(&self, location: &str, src: &str, format: Format) -> Option<Span> {
+        let lines: Vec<&str> = src.lines().collect();
+        let mut parts = location.splitn(3, '.');
+        let section = parts.next()?;
+        let Some(name) = parts.next() else {
+            let line = *self.sections.get(section)?;
+            return Some(Span {
+                line,
+                start: 0,
+                end: lines[line].len(),
+            });
+        };
+        let symbol = match section {
+            "state" => Symbol::State(name.to_string()),
+            "functions" => Symbol::Function(name.to_string()),
+            "helpers" => Symbol::Helper(name.to_string()),
+            "invariants" => Symbol::Invariant(name.to_string()),
+            _ => return None,
+        };
+        let span = self.span(&symbol)?.clone();
+        let Some(rest) = parts.next() else {
+            return Some(span);
+        };
+        if let Some(example) = rest.strip_prefix("examples.") {
+            let symbol = Symbol::Example(name.to_string(), example.to_string());
+            return Some(self.span(&symbol).unwrap_or(&span).clone());
+        }
+        let Some((kind, index)) = rest.split_once('[') else {
+            return Some(span);
+        };
+        let index: usize = index.trim_end_matches(']').parse().ok()?;
+        let key = (span.line..lines.len()).find(|&line| {
+            let line = lines[line].trim_start().to_lowercase();
+            line.strip_prefix(kind)
+                .is_some_and(|rest| rest.trim_start().starts_with([':', '=']))
+        });
+        let Some(key) = key else {
+            return Some(span);
+        };
+        if format == Format::Toml {
+            return Some(Span {
+                line: key,
+                start: 0,
+                end: lines[key].len(),
+            });
+        }
+        let item = (key + 1..lines.len())
+            .filter(|&line| lines[line].trim_start().starts_with("- "))
+            .nth(index)
+            .unwrap_or(key);
+        let indent = lines[item].len() - lines[item].trim_start().len();
+        Some(Span {
+            line: item,
+            start: indent,
+            end: lines[item].len(),
+        })
+    }
+}
+
+/// The invariants `function` must preserve: those referring to the state it
+/// writes.
+pub fn preserves<'a>(spec: &'a Spec, function: &Function) -> Vec<&'a Invariant> {
+    spec.invariants
+        .iter()
+        .filter(|invariant| constrains(spec, invariant, function))
+        .collect()
+}
+
+/// The functions that must preserve `invariant`.
+pub fn checked_by<'a>(spec: &'a Spec, invariant: &Invariant) -> Vec<&'a Function> {
+    spec.functions
+        .iter()
+        .filter(|function| constrains(spec, invariant, function))
+        .collect()
+}
+
+fn constrains(spec: &Spec, invariant: &Invariant, function: &Function) -> bool {
+    let mut roots = Vec::new();
+    invariant
+        .expr
+        .visit_paths(&mut |path, _| roots.push(path[0].clone()));
+    function
+        .accounts
+        .iter()
+        .filter(|account| account.writable)
+        .filter_map(|account| spec.account(account.ty.as_deref()?))
+        .any(|account| roots.contains(&account.binding()))
+}
+
+/// The byte column of the UTF-16 column `character` in `line`, which is how
+/// LSP counts.
+fn byte_column(line: &str, character: usize) -> usize {
+    let mut units = 0;
+    for (at, c) in line.char_indices() {
+        if units >= character {
+            return at;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+/// The UTF-16 column of the byte column `at` in `line`.
+fn utf16_column(line: &str, at: usize) -> usize {
+    line[..at.min(line.len())].encode_utf16().count()
+}
+
+/// The identifier at byte column `at` of `line`, with the one before it
+/// if they are joined by a dot, e.g. `vault` for `shares_total` in
+/// `vault.shares_total`.
+fn word_at(line: &str, at: usize) -> Option<(Option<&str>, &str)> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let start = line[..at.min(line.len())]
+        .rfind(|c| !is_ident(c))
+        .map_or(0, |i| i + 1);
+    let end = line[start..]
+        .find(|c| !is_ident(c))
+        .map_or(line.len(), |i| start + i);
+    if start == end {
+        return None;
+    }
+    let qualifier = line[..start].strip_suffix('.').and_then(|before| {
+        let from = before.rfind(|c| !is_ident(c)).map_or(0, |i| i + 1);
+        (from < before.len()).then(|| &before[from..])
+    });
+    Some((qualifier, &line[start..end]))
+}
+
+/// An open spec file.
+struct Document {
+    text: String,
+    format: Format,
+    /// The spec, or why its source does not parse
+    spec: Result<Spec, SpecError>,
+    outline: Outline,
+}
+
+impl Document {
+    fn new(uri: &str, text: String) -> Document {
+        let format = Format::from_path(Path::new(uri)).unwrap_or(Format::Yaml);
+        let spec = Spec::parse_unvalidated(&text, format);
+        let outline = spec
+            .as_ref()
+            .map(|spec| Outline::new(spec, &text, format))
+            .unwrap_or_default();
+        Document {
+            text,
+            format,
+            spec,
+            outline,
+        }
+    }
+
+    fn range(&self, span: &Span) -> Value {
+        let line = self.text.lines().nth(span.line).unwrap_or("");
+        json!({
+            "start": { "line": span.line, "character": utf16_column(line, span.start) },
+            "end": { "line": span.line, "character": utf16_column(line, span.end) },
+        })
+    }
+
+    /// A range covering the whole of line `line`.
+    fn line_range(&self, line: usize) -> Value {
+        let length = self.text.lines().nth(line).unwrap_or("").len();
+        self.range(&Span {
+            line,
+            start: 0,
+            end: length,
+        })
+    }
+
+    /// The diagnostics of a source that does not parse.
+    fn parse_errors(&self, err: &SpecError) -> Vec<Value> {
+        match err {
+            SpecError::Markdown(diagnostics) => diagnostics
+                .0
+                .iter()
+                .map(|d| {
+                    let range = self.line_range(d.line.saturating_sub(1));
+                    diagnostic(range, d.severity, None, &d.message)
+                })
+                .collect(),
+            SpecError::Yaml(yaml) => {
+                let line = yaml.location().map_or(0, |at| at.line().saturating_sub(1));
+                let range = self.line_range(line);
+                vec![diagnostic(range, Severity::Error, None, &err.to_string())]
+            }
+            SpecError::Toml(toml) => {
+                let at = toml
+                    .span()
+                    .map_or(0, |span| span.start.min(self.text.len()));
+                let range = self.line_range(self.text[..at].matches('\n').count());
+                vec![diagnostic(range, Severity::Error, None, toml.message())]
+            }
+            err => {
+                let range = self.line_range(0);
+                vec![diagnostic(range, Severity::Error, None, &err.to_string())]
+            }
+        }
+    }
+
+    /// The LSP diagnostics of the document.
+    fn diagnostics(&self) -> Vec<Value> {
+        let spec = match &self.spec {
+            Ok(spec) => spec,
+            Err(err) => return self.parse_errors(err),
+        };
+        let mut diagnostics = Vec::new();
+        if self.format == Format::Markdown {
+            let (_, warnings) = spec::markdown::lower(&self.text);
+            for warning in warnings {
+                let range = self.line_range(warning.line.saturating_sub(1));
+                diagnostics.push(diagnostic(range, warning.severity, None, &warning.message));
+            }
+        }
+        for lint in spec::lint(spec) {
+            let range = match self.outline.locate(&lint.location, &self.text, self.format) {
+                Some(span) => self.range(&span),
+                None => self.line_range(0),
+            };
+            diagnostics.push(diagnostic(
+                range,
+                lint.severity,
+                Some(lint.rule),
+                &lint.message,
+            ));
+        }
+        diagnostics
+    }
+
+    /// The symbol at `position`, with the span of the word it was read from.
+    fn symbol_at(&self, position: &Value) -> Option<(Symbol, Span)> {
+        let spec = self.spec.as_ref().ok()?;
+        let line_number = position.get("line")?.as_u64()? as usize;
+        let line = self.text.lines().nth(line_number)?;
+        let at = byte_column(line, position.get("character")?.as_u64()? as usize);
+        let (qualifier, word) = word_at(line, at)?;
+        let start = word.as_ptr() as usize - line.as_ptr() as usize;
+        let span = Span {
+            line: line_number,
+            start,
+            end: start + word.len(),
+        };
+        // A declaration is what it declares
+        if let Some((symbol, _)) = self
+            .outline
+            .declarations
+            .iter()
+            .find(|(_, declared)| *declared == span)
+        {
+            return Some((symbol.clone(), span));
+        }
+        let function = self
+            .outline
+            .function_at(line_number)
+            .and_then(|name| spec.function(name));
+        // The account type of a name: an account of the function around
+        // the position, or a state account by its binding
+        let account_type = |name: &str| {
+            function
+                .and_then(|function| function.account(name)?.ty.as_deref())
+                .and_then(|ty| spec.account(ty))
+                .or_else(|| spec.state.iter().find(|account| account.binding() == name))
+        };
+        let symbol = if let Some(qualifier) = qualifier {
+            let account = account_type(qualifier)?;
+            account.field(word)?;
+            Symbol::Field(account.name.clone(), word.to_string())
+        } else if let Some(function) = function
+            .filter(|function| function.arg(word).is_some() || function.account(word).is_some())
+        {
+            Symbol::Input(function.name.clone(), word.to_string())
+        } else if spec.function(word).is_some() {
+            Symbol::Function(word.to_string())
+        } else if spec
+            .invariants
+            .iter()
+            .any(|invariant| invariant.name == word)
+        {
+            Symbol::Invariant(word.to_string())
+        } else if spec.helper(word).is_some() {
+            Symbol::Helper(word.to_string())
+        } else if let Some(account) = spec.account(word).or_else(|| account_type(word)) {
+            Symbol::State(account.name.clone())
+        } else {
+            return None;
+        };
+        Some((symbol, span))
+    }
+
+    /// The Markdown shown when hovering `symbol`.
+    fn hover(&self, symbol: &Symbol) -> Option<String> {
+        let spec = self.spec.as_ref().ok()?;
+        let described = |text: String, description: &Option<String>| match description {
+            Some(description) => format!("{text}\n\n{description}"),
+            None => text,
+        };
+        Some(match symbol {
+            Symbol::State(name) => {
+                let account = spec.account(name)?;
+                let mut text = format!(
+                    "**{name}**: state account, `{}` in invariants",
+                    account.binding()
+                );
+                if let Some(description) = &account.description {
+                    text.push_str(&format!("\n\n{description}"));
+                }
+                text.push('\n');
+                for field in &account.fields {
+                    text.push_str(&format!("\n- `{}`: {}", field.name, field.ty));
+                }
+                text
+            }
+            Symbol::Field(account, name) => {
+                let field = spec.account(account)?.field(name)?;
+                described(
+                    format!("`{account}.{name}`: {}", field.ty),
+                    &field.description,
+                )
+            }
+            Symbol::Function(name) => {
+                let function = spec.function(name)?;
+                let args: Vec<String> = function
+                    .args
+                    .iter()
+                    .map(|arg| format!("{}: {}", arg.name, arg.ty))
+                    .collect();
+                let mut text = described(
+                    format!("**{name}**({})", args.join(", ")),
+                    &function.description,
+                );
+                for (kind, conditions) in [
+                    ("Requires", &function.requires),
+                    ("Ensures", &function.ensures),
+                ] {
+                    if !conditions.is_empty() {
+                        text.push_str(&format!("\n\n{kind}:"));
+                        for condition in conditions {
+                            text.push_str(&format!("\n- `{}`", condition.expr));
+                        }
+                    }
+                }
+                let invariants: Vec<String> = preserves(spec, function)
+                    .iter()
+                    .map(|invariant| format!("`{}`", invariant.name))
+                    .collect();
+                if !invariants.is_empty() {
+                    text.push_str(&format!("\n\nPreserves {}", invariants.join(", ")));
+                }
+                text
+            }
+            Symbol::Input(function, name) => {
+                let function = spec.function(function)?;
+                if let Some(arg) = function.arg(name) {
+                    format!("`{name}`: {}, argument of `{}`", arg.ty, function.name)
+                } else {
+                    let account = function.account(name)?;
+                    let mut flags = Vec::new();
+                    if account.signer {
+                        flags.push("signer");
+                    }
+                    if account.writable {
+                        flags.push("writable");
+                    }
+                    let ty = account.ty.as_deref().unwrap_or("untyped");
+                    let flags = flags
+                        .iter()
+                        .map(|flag| format!(", {flag}"))
+                        .collect::<String>();
+                    format!("`{name}`: {ty}{flags}, account of `{}`", function.name)
+                }
+            }
+            Symbol::Example(function, name) => {
+                let example = spec
+                    .function(function)?
+                    .examples
+                    .iter()
+                    .find(|example| &example.name == name)?;
+                described(
+                    format!("**{name}**: example of `{function}`"),
+                    &example.description,
+                )
+            }
+            Symbol::Helper(name) => {
+                let helper = spec.helper(name)?;
+                let args: Vec<String> = helper
+                    .args
+                    .iter()
+                    .map(|arg| format!("{}: {}", arg.name, arg.ty))
+                    .collect();
+                described(
+                    format!("**{name}**({}) -> {}", args.join(", "), helper.returns),
+                    &helper.description,
+                )
+            }
+            Symbol::Invariant(name) => {
+                let invariant = spec.invariants.iter().find(|i| &i.name == name)?;
+                let mut text = described(
+                    format!("**{name}**: `{}`", invariant.expr),
+                    &invariant.description,
+                );
+                let functions: Vec<String> = checked_by(spec, invariant)
+                    .iter()
+                    .map(|function| format!("`{}`", function.name))
+                    .collect();
+                if !functions.is_empty() {
+                    text.push_str(&format!("\n\nPreserved by {}", functions.join(", ")));
+                }
+                text
+            }
+        })
+    }
+
+    /// Where `symbol`, read from `at`, leads: the declaration of a name, the
+    /// functions of an invariant, or the invariants of a function declaration.
+    fn definitions(&self, symbol: &Symbol, at: &Span) -> Vec<Span> {
+        let Ok(spec) = &self.spec else {
+            return Vec::new();
+        };
+        let declared = self.outline.span(symbol);
+        let related: Vec<Symbol> = match symbol {
+            Symbol::Invariant(name) => spec
+                .invariants
+                .iter()
+                .filter(|invariant| &invariant.name == name)
+                .flat_map(|invariant| checked_by(spec, invariant))
+                .map(|function| Symbol::Function(function.name.clone()))
+                .collect(),
+            Symbol::Function(name) if declared == Some(at) => spec
+                .function(name)
+                .map(|function| preserves(spec, function))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|invariant| Symbol::Invariant(invariant.name.clone()))
+                .collect(),
+            _ => return declared.into_iter().cloned().collect(),
+        };
+        related
+            .iter()
+            .filter_map(|symbol| self.outline.span(symbol))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Answers the requests of one editor.
+#[derive(Default)]
+pub struct Server {
+    documents: BTreeMap<String, Document>,
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server::default()
+    }
+
+    /// Answers the messages of `input`, framed by `Content-Length` headers,
+    /// on `output` until the editor exits or the input ends.
+    pub fn serve(
+        &mut self,
+        mut input: impl BufRead,
+        mut output: impl Write,
+    ) -> std::io::Result<()> {
+        loop {
+            let mut length = None;
+            loop {
+                let mut header = String::new();
+                if input.read_line(&mut header)? == 0 {
+                    return Ok(());
+                }
+                let header = header.trim_end();
+                if header.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse::<usize>().ok();
+                    }
+                }
+            }
+            let Some(length) = length else {
+                continue;
+            };
+            let mut body = vec![0; length];
+            input.read_exact(&mut body)?;
+            let Ok(message) = serde_json::from_slice::<Value>(&body) else {
+                continue;
+            };
+            if message.get("method").and_then(Value::as_str) == Some("exit") {
+                return Ok(());
+            }
+            for reply in self.handle(&message) {
+                let reply = reply.to_string();
+                write!(output, "Content-Length: {}\r\n\r\n{reply}", reply.len())?;
+            }
+            output.flush()?;
+        }
+    }
+
+    /// The messages answering `message`: the response to a request, and
+    /// the diagnostics of the documents it changed.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let uri = params["textDocument"]["uri"].as_str().map(str::to_string);
+        let mut replies = Vec::new();
+        let result = match (method, uri) {
+            ("initialize", _) => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": { "openClose": true, "change": 1 },
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                },
+                "serverInfo": { "name": "spec-coder", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            ("shutdown", _) => Ok(Value::Null),
+            ("textDocument/didOpen", Some(uri)) => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                replies.push(self.open(uri, text.to_string()));
+                Ok(Value::Null)
+            }
+            ("textDocument/didChange", Some(uri)) => {
+                // Changes are whole documents, as initialize asks
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    replies.push(self.open(uri, text.to_string()));
+                }
+                Ok(Value::Null)
+            }
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.remove(&uri);
+                replies.push(publish(&uri, Vec::new()));
+                Ok(Value::Null)
+            }
+            ("textDocument/hover", Some(uri)) => Ok(self
+                .documents
+                .get(&uri)
+                .and_then(|document| {
+                    let (symbol, span) = document.symbol_at(&params["position"])?;
+                    let text = document.hover(&symbol)?;
+                    Some(json!({
+                        "contents": { "kind": "markdown", "value": text },
+                        "range": document.range(&span),
+                    }))
+                })
+                .unwrap_or(Value::Null)),
+            ("textDocument/definition", Some(uri)) => Ok(self
+                .documents
+                .get(&uri)
+                .and_then(|document| {
+                    let (symbol, span) = document.symbol_at(&params["position"])?;
+                    let locations: Vec<Value> = document
+                        .definitions(&symbol, &span)
+                        .iter()
+                        .map(|span| json!({ "uri": uri, "range": document.range(span) }))
+                        .collect();
+                    (!locations.is_empty()).then_some(Value::Array(locations))
+                })
+                .unwrap_or(Value::Null)),
+            (
+                "textDocument/didOpen"
+                | "textDocument/didChange"
+                | "textDocument/didClose"
+                | "textDocument/hover"
+                | "textDocument/definition",
+                None,
+            ) => Err((INVALID_PARAMS, format!("`{method}` needs a text document"))),
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method `{method}`"))),
+        };
+        // Notifications get no response
+        if let Some(id) = message.get("id") {
+            replies.insert(
+                0,
+                match result {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": code, "message": message },
+                    }),
+                },
+            );
+        }
+        replies
+    }
+
+    /// Opens `uri` with `text`, or replaces its text, returning its
+    /// diagnostics.
+    fn open(&mut self, uri: String, text: String) -> Value {
+        let document = Document::new(&uri, text);
+        let diagnostics = document.diagnostics();
+        self.documents.insert(uri.clone(), document);
+        publish(&uri, diagnostics)
+    }
+}
+
+/// An LSP diagnostic.
+fn diagnostic(range: Value, severity: Severity, code: Option<&str>, message: &str) -> Value {
+    json!({
+        "range": range,
+        "severity": match severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+        },
+        "code": code,
+        "source": "spec-coder",
+        "message": message,
+    })
+}
+
+/// The notification publishing `diagnostics` for `uri`.
+fn publish(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+    const VAULT_MD: &str = include_str!("../../../examples/specs/vault.md");
+
+    /// The text of `span` in `src`.
+    fn text<'a>(src: &'a str, span: &Span) -> &'a str {
+        &src.lines().nth(span.line).unwrap()[span.start..span.end]
+    }
+
+    #[test]
+    fn test_outline() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let toml = toml::to_string(&spec).unwrap();
+        for (src, format) in [
+            (VAULT, Format::Yaml),
+            (VAULT_MD, Format::Markdown),
+            (toml.as_str(), Format::Toml),
+        ] {
+            let outline = Outline::new(&spec, src, format);
+            let symbols = [
+                Symbol::State("Vault".to_string()),
+                Symbol::Field("Vault".to_string(), "token_total".to_string()),
+                Symbol::Function("withdraw".to_string()),
+                Symbol::Input("withdraw".to_string(), "shares".to_string()),
+                Symbol::Example("withdraw".to_string(), "everything".to_string()),
+                Symbol::Invariant("solvency".to_string()),
+            ];
+            let mut last = 0;
+            for symbol in &symbols {
+                let span = outline.span(symbol).unwrap_or_else(|| panic!("{symbol:?}"));
+                let (Symbol::State(name)
+                | Symbol::Field(_, name)
+                | Symbol::Function(name)
+                | Symbol::Input(_, name)
+                | Symbol::Example(_, name)
+                | Symbol::Invariant(name)
+                | Symbol::Helper(name)) = symbol;
+                assert_eq!(text(src, span), name, "{format:?}");
+                assert!(span.line > last, "{symbol:?} in {format:?}");
+                last = span.line;
+            }
+            // `deposit` is the function around its examples
+            let example = Symbol::Example("deposit".to_string(), "overflow".to_string());
+            let line = outline.span(&example).unwrap().line;
+            assert_eq!(outline.function_at(line), Some("deposit"));
+            assert_eq!(outline.function_at(last), None);
+
+            let condition = outline
+                .locate("functions.deposit.ensures[1]", src, format)
+                .unwrap();
+            let line = src.lines().nth(condition.line).unwrap();
+            if format == Format::Toml {
+                assert!(line.starts_with("ensures"), "{line}");
+            } else {
+                assert!(line.contains("vault.shares_total >= old"), "{line}");
+            }
+        }
+    }
+
+    /// Frames `messages` as an editor sends them.
+    fn frame(messages: &[Value]) -> Vec<u8> {
+        let mut input = Vec::new();
+        for message in messages {
+            let message = message.to_string();
+            write!(input, "Content-Length: {}\r\n\r\n{message}", message.len()).unwrap();
+        }
+        input
+    }
+
+    /// Splits the framed `output` into messages.
+    fn unframe(output: &[u8]) -> Vec<Value> {
+        let mut output = std::str::from_utf8(output).unwrap();
+        let mut messages = Vec::new();
+        while let Some(rest) = output.strip_prefix("Content-Length: ") {
+            let (length, rest) = rest.split_once("\r\n\r\n").unwrap();
+            let length: usize = length.parse().unwrap();
+            messages.push(serde_json::from_str(&rest[..length]).unwrap());
+            output = &rest[length..];
+        }
+        messages
+    }
+
+    /// The position of `word` in the first occurrence of `context` in `src`.
+    fn position(src: &str, context: &str, word: &str) -> Value {
+        let (line, text) = src
+            .lines()
+            .enumerate()
+            .find(|(_, line)| line.contains(context))
+            .unwrap();
+        let column = text.find(context).unwrap() + context.find(word).unwrap();
+        json!({ "line": line, "character": column + 1 })
+    }
+
+    fn request(id: u64, method: &str, uri: &str, position: Value) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": { "textDocument": { "uri": uri }, "position": position },
+        })
+    }
+
+    #[test]
+    fn test_server() {
+        let uri = "file:///specs/vault.yaml";
+        let src = VAULT.replace(
+            "expr: vault.shares_total <= vault.token_total",
+            "expr: vault.shares_total <= vault.tokens",
+        );
+        let input = frame(&[
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didOpen",
+                "params": { "textDocument": { "uri": uri, "languageId": "yaml", "version": 1, "text": src } },
+            }),
+            request(
+                2,
+                "textDocument/hover",
+                uri,
+                position(&src, "shares_total <=", "shares_total"),
+            ),
+            request(
+                3,
+                "textDocument/definition",
+                uri,
+                position(&src, "name: solvency", "solvency"),
+            ),
+            request(
+                4,
+                "textDocument/definition",
+                uri,
+                position(&src, "name: deposit", "deposit"),
+            ),
+            request(
+                5,
+                "textDocument/definition",
+                uri,
+                position(&src, "amount > 0", "amount"),
+            ),
+            request(
+                6,
+                "textDocument/hover",
+                uri,
+                position(&src, "name: withdraw", "withdraw"),
+            ),
+            json!({ "jsonrpc": "2.0", "id": 7, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+            json!({ "jsonrpc": "2.0", "id": 8, "method": "shutdown" }),
+        ]);
+        let mut output = Vec::new();
+        Server::new().serve(&input[..], &mut output).unwrap();
+        let messages = unframe(&output);
+        // The request after exit is not answered
+        assert_eq!(messages.len(), 8);
+        assert_eq!(messages[0]["result"]["capabilities"]["hoverProvider"], true);
+
+        let diagnostics = messages[1]["params"]["diagnostics"].as_array().unwrap();
+        let undeclared = diagnostics.iter().find(|d| d["code"] == "invalid").unwrap();
+        assert_eq!(undeclared["message"], "`Vault` has no field `tokens`");
+        assert_eq!(undeclared["severity"], 1);
+        let solvency = src
+            .lines()
+            .position(|l| l.contains("name: solvency"))
+            .unwrap();
+        assert_eq!(undeclared["range"]["start"]["line"], solvency);
+        assert!(diagnostics.iter().any(|d| d["code"] == "authorization"));
+
+        let hover = messages[2]["result"]["contents"]["value"].as_str().unwrap();
+        assert!(hover.starts_with("`Vault.shares_total`: u64"), "{hover}");
+
+        // The invariant leads to the functions writing the vault
+        let lines = |message: &Value| -> Vec<&str> {
+            message["result"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|location| {
+                    assert_eq!(location["uri"], uri);
+                    let line = location["range"]["start"]["line"].as_u64().unwrap();
+                    src.lines().nth(line as usize).unwrap().trim()
+                })
+                .collect()
+        };
+        assert_eq!(
+            lines(&messages[3]),
+            ["- name: deposit", "- name: withdraw", "- name: reward"]
+        );
+        assert_eq!(lines(&messages[4]), ["- name: solvency"]);
+        assert_eq!(lines(&messages[5]), ["- { name: amount, type: u64 }"]);
+
+        let hover = messages[6]["result"]["contents"]["value"].as_str().unwrap();
+        assert!(hover.starts_with("**withdraw**(shares: u64)"), "{hover}");
+        assert!(hover.contains("Preserves `solvency`"), "{hover}");
+        assert_eq!(messages[7]["id"], 7);
+        assert_eq!(messages[7]["result"], Value::Null);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let mut server = Server::new();
+        let open = |uri: &str, text: &str| {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didOpen",
+                "params": { "textDocument": { "uri": uri, "text": text } },
+            })
+        };
+        let replies = server.handle(&open("file:///a.yaml", "name: a\nstate: [\n"));
+        assert_eq!(replies.len(), 1);
+        let diagnostic = &replies[0]["params"]["diagnostics"][0];
+        assert_eq!(diagnostic["severity"], 1);
+        assert!(diagnostic["message"].as_str().unwrap().contains("YAML"));
+
+        let replies = server.handle(&open("file:///a.toml", "name = \"a\"\n\nstate = 1\n"));
+        assert_eq!(
+            replies[0]["params"]["diagnostics"][0]["range"]["start"]["line"],
+            2
+        );
+
+        let replies = server.handle(&open("file:///a.md", "# A\n\n## Bogus\n"));
+        assert!(!replies[0]["params"]["diagnostics"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+
+        let unknown =
+            server.handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": "workspace/symbol" }));
+        assert_eq!(unknown[0]["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
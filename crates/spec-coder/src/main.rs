@@ -0,0 +1,1684 @@
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use spec::{Severity, Spec, Target};
+#[cfg(feature = "api")]
+use spec_coder::api::{self, Api, Engine, EngineError, RunRequest, SpecFormat};
+use spec_coder::clean;
+use spec_coder::config::Config;
+#[cfg(feature = "dashboard")]
+use spec_coder::dashboard::{self, Dashboard};
+use spec_coder::deploy;
+use spec_coder::diff;
+use spec_coder::drift;
+#[cfg(feature = "api")]
+use spec_coder::driver::RunReport;
+use spec_coder::driver::{CargoVerifier, Control, Driver, DriverOptions, Limit, Progress, Verdict};
+use spec_coder::git::{self, Repo};
+use spec_coder::incremental;
+use spec_coder::issue;
+use spec_coder::llm::replay::{Recording, Replay};
+use spec_coder::llm::{self, LlmConfig, Provider, ProviderKind};
+use spec_coder::lsp;
+use spec_coder::materialize::{self, MaterializeError};
+use spec_coder::mcp;
+use spec_coder::metrics::{self, Failures, GroupBy, RunContext, RunMetrics, Stats};
+use spec_coder::migrate;
+use spec_coder::properties;
+use spec_coder::provenance::{self, Provenance};
+use spec_coder::prover::cloud::CloudProver;
+use spec_coder::prover::{self, Backend, RuleStatus};
+use spec_coder::registry::{self, Registry};
+use spec_coder::results::overview::{self, Overview};
+use spec_coder::results::{self, Outcome};
+use spec_coder::review::{AutoApprove, Prompt, Reviewer};
+use spec_coder::sandbox::Sandbox;
+use spec_coder::scaffold::{self, ScaffoldError};
+use spec_coder::session::Session;
+use spec_coder::summaries;
+use spec_coder::templates::Templates;
+use spec_coder::tui;
+use spec_coder::verify::{self, VerifyEvent};
+use spec_coder::watch::{self, WatchEvent, WatchOptions};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Parser)]
+#[command(name = "spec-coder", version, about = "Spec-driven program generation")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check a specification for behavior it leaves unspecified, such as
+    /// rounding, authorization or state no postcondition constrains
+    Lint {
+        /// Specification file (.yaml, .yml, .toml or .md)
+        spec: PathBuf,
+        /// Fail on warnings as well as errors
+        #[arg(long)]
+        deny_warnings: bool,
+    },
+    /// Serve the lints of spec files, hovers and go-to-definition to an
+    /// editor over the Language Server Protocol, on stdin and stdout
+    Lsp,
+    /// Have a model formalize requirements written in prose as invariants
+    /// of a specification, and confirm each before it is added
+    ExtractProperties {
+        /// Specification file (.yaml, .yml, .toml or .md)
+        spec: PathBuf,
+        /// Text file of requirements, one sentence or list item each
+        #[arg(long)]
+        requirements: PathBuf,
+        /// Model backend: `anthropic` or `openai`; defaults to
+        /// `model.provider` in spec-coder.toml, or `anthropic`
+        #[arg(long)]
+        provider: Option<ProviderKind>,
+        /// Model name; defaults to `model.name` in spec-coder.toml, or one
+        /// suited to the provider
+        #[arg(long)]
+        model: Option<String>,
+        /// API root, e.g. of a local OpenAI-compatible server; defaults to
+        /// `model.base_url` in spec-coder.toml
+        #[arg(long)]
+        base_url: Option<String>,
+        /// Directory of prompt templates overriding the built-in ones, e.g.
+        /// `properties.j2`; defaults to `templates.dir` in spec-coder.toml
+        #[arg(long)]
+        templates: Option<PathBuf>,
+        /// Add every proposed invariant without asking
+        #[arg(long)]
+        auto_approve: bool,
+        /// Write the specification with the confirmed invariants, as YAML, to
+        /// this file
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Generate an example crate from a specification
+    Materialize {
+        /// Specification file (.yaml, .yml, .toml or .md)
+        spec: PathBuf,
+        /// Target platform; defaults to `target` in spec-coder.toml, or the
+        /// one the specification declares
+        #[arg(long)]
+        target: Option<Target>,
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+        /// Output directory, instead of
+        /// `<examples-dir>/<target>/materialized_<name>`
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Overwrite an existing crate
+        #[arg(long)]
+        force: bool,
+        /// Also generate the CPI interface crate of a Solana program in
+        /// `cpi/`
+        #[arg(long)]
+        cpi: bool,
+    },
+    /// Materialize a specification and have a model implement it, iterating
+    /// until the build, the tests and the rules pass
+    Implement(ImplementArgs),
+    /// Bring a crate to the version of its specification, regenerating and
+    /// verifying again only what changed since the version it was
+    /// materialized from
+    Migrate(ImplementArgs),
+    /// Serve an HTTP API taking specs to materialize and implement, with
+    /// the status and files of each run; requests must carry
+    /// `SPEC_CODER_API_TOKEN` as a bearer token if it is set, which it must
+    /// be to listen on other than a loopback address
+    #[cfg(feature = "api")]
+    Api {
+        /// Address to listen on
+        #[arg(long, default_value = api::DEFAULT_ADDR)]
+        addr: String,
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+        /// Prover backend: `certora`, `cloud` to run the rules as jobs of
+        /// the Certora cloud, `auto` to choose per rule, or `kani` to model
+        /// check the rules locally
+        #[arg(long, default_value = "certora")]
+        backend: Backend,
+    },
+    /// Serve the `materialize_spec`, `run_verification` and
+    /// `get_counterexample` tools to an IDE agent or assistant over the Model
+    /// Context Protocol, on stdin and stdout
+    Mcp {
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+        /// Rules to prove at once; defaults to `prover.jobs` in
+        /// spec-coder.toml, or 1
+        #[arg(long)]
+        jobs: Option<usize>,
+        /// Prover backend: `certora`, `cloud` to run the rules as jobs of
+        /// the Certora cloud, `auto` to choose per rule, or `kani` to model
+        /// check the rules locally
+        #[arg(long, default_value = "certora")]
+        backend: Backend,
+    },
+    /// Serve a web dashboard of the sessions, results and metrics the other
+    /// commands record
+    #[cfg(feature = "dashboard")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = dashboard::DEFAULT_ADDR)]
+        addr: String,
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+        /// Metrics store; defaults to `output.metrics` in spec-coder.toml, or
+        /// `.spec-coder/metrics.jsonl`
+        #[arg(long)]
+        metrics: Option<PathBuf>,
+    },
+    /// Aggregate the metrics `implement` records of every run
+    Stats {
+        /// What to group the runs by: `model`, `prompts`, `spec`, `format`
+        /// or `target`
+        #[arg(long, default_value = "model")]
+        by: GroupBy,
+        /// Metrics store; defaults to `output.metrics` in spec-coder.toml, or
+        /// `.spec-coder/metrics.jsonl`
+        #[arg(long)]
+        metrics: Option<PathBuf>,
+    },
+    /// Create a new example: a spec template and the crate materialized from
+    /// it
+    NewExample {
+        /// Example name, e.g. `bonding_curve`
+        name: String,
+        /// Target platform; defaults to `target` in spec-coder.toml, or `svm`
+        #[arg(long)]
+        target: Option<Target>,
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+        /// Overwrite an existing spec and crate
+        #[arg(long)]
+        force: bool,
+    },
+    /// Create a new example from a GitHub issue or discussion: a draft spec
+    /// listing its requirements, linked back to it, and the crate
+    /// materialized from it
+    ImportIssue {
+        /// The URL of the issue or discussion, `owner/repo#number`, or the
+        /// number of an issue of --repo
+        issue: String,
+        /// Repository of an issue given by number, as `owner/repo`
+        #[arg(long)]
+        repo: Option<String>,
+        /// Example name; defaults to one made from the title of the issue
+        #[arg(long)]
+        name: Option<String>,
+        /// Target platform; defaults to `target` in spec-coder.toml, or `svm`
+        #[arg(long)]
+        target: Option<Target>,
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+        /// Root of the GitHub API; defaults to `GITHUB_API_URL`, or the
+        /// public API
+        #[arg(long)]
+        api_url: Option<String>,
+        /// Overwrite an existing spec and crate
+        #[arg(long)]
+        force: bool,
+    },
+    /// Report functions whose code or spec changed since the crate was last
+    /// verified
+    CheckDrift {
+        /// Specification file (.yaml, .yml, .toml or .md)
+        spec: PathBuf,
+        /// Target platform; defaults to `target` in spec-coder.toml, or the
+        /// one the specification declares
+        #[arg(long)]
+        target: Option<Target>,
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+        /// Crate directory, instead of
+        /// `<examples-dir>/<target>/materialized_<name>`
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Watch a crate and its spec, building and testing after every change
+    /// and proving the rules once the files are left alone
+    Watch {
+        /// Specification file (.yaml, .yml, .toml or .md)
+        spec: PathBuf,
+        /// Target platform; defaults to `target` in spec-coder.toml, or the
+        /// one the specification declares
+        #[arg(long)]
+        target: Option<Target>,
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+        /// Crate directory, instead of
+        /// `<examples-dir>/<target>/materialized_<name>`
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Seconds the files must be left alone before the rules are proved
+        #[arg(long, default_value_t = 5)]
+        debounce: u64,
+        /// Rules to prove at once; defaults to `prover.jobs` in
+        /// spec-coder.toml, or 1
+        #[arg(long)]
+        jobs: Option<usize>,
+        /// Only build and test, without the prover
+        #[arg(long)]
+        no_fv: bool,
+        /// Prover backend: `certora`, `cloud` to run the rules as jobs of
+        /// the Certora cloud, `auto` to choose per rule, or `kani` to model
+        /// check the rules locally
+        #[arg(long, default_value = "certora")]
+        backend: Backend,
+    },
+    /// Build an example with the certora feature and prove its rules,
+    /// reporting each verdict as it comes and a summary at the end
+    Verify {
+        /// The example: its name under the examples directory, or its crate
+        /// directory; defaults to the crate in the current directory
+        example: Option<String>,
+        /// Prove only the rules this name or pattern matches, e.g.
+        /// `rule_*_solvency`; may be given more than once
+        #[arg(long = "rule", visible_alias = "rules", value_name = "PATTERN")]
+        rules: Vec<String>,
+        /// Prove only the rules with this tag, from
+        /// `[package.metadata.certora.tags]` in the crate's Cargo.toml; may
+        /// be given more than once
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+        /// Rules to prove at once; defaults to `prover.jobs` in
+        /// spec-coder.toml, or 1
+        #[arg(long)]
+        jobs: Option<usize>,
+        /// Prover backend: `certora`, `cloud` to run the rules as jobs of
+        /// the Certora cloud, `auto` to choose per rule, or `kani` to model
+        /// check the rules locally
+        #[arg(long, default_value = "certora")]
+        backend: Backend,
+        /// Prove every rule again instead of reusing the results of rules
+        /// whose inputs did not change
+        #[arg(long)]
+        no_cache: bool,
+    },
+    /// Write the rule verdicts of the last run as JSON and SARIF, located at
+    /// the rules and the processor functions they check
+    Report {
+        /// Specification file (.yaml, .yml, .toml or .md)
+        #[arg(required_unless_present = "all")]
+        spec: Option<PathBuf>,
+        /// Summarize the results last recorded in every example instead, as
+        /// a table and an HTML page
+        #[arg(long, conflicts_with_all = ["spec", "target", "out", "json", "sarif", "junit"])]
+        all: bool,
+        /// HTML output of `--all`, instead of `.spec-coder/report.html`
+        #[arg(long, requires = "all")]
+        html: Option<PathBuf>,
+        /// Target platform; defaults to `target` in spec-coder.toml, or the
+        /// one the specification declares
+        #[arg(long)]
+        target: Option<Target>,
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+        /// Crate directory, instead of
+        /// `<examples-dir>/<target>/materialized_<name>`
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// JSON output, instead of `.spec-coder/results.json` in the crate
+        #[arg(long)]
+        json: Option<PathBuf>,
+        /// SARIF output, instead of `.spec-coder/results.sarif` in the crate
+        #[arg(long)]
+        sarif: Option<PathBuf>,
+        /// JUnit XML output, instead of `.spec-coder/results.xml` in the
+        /// crate
+        #[arg(long)]
+        junit: Option<PathBuf>,
+    },
+    /// List the materialized examples, their specs, rules and verification
+    /// status in a registry
+    Registry {
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+        /// Output file
+        #[arg(long, default_value = registry::EXAMPLES_TOML)]
+        out: PathBuf,
+    },
+    /// Compare two versions of a specification, classing each change as
+    /// additive, weakening, strengthening or breaking, and predict the
+    /// generated files, functions and rules regenerating a crate touches
+    Diff {
+        /// The old specification file (.yaml, .yml, .toml or .md)
+        old: PathBuf,
+        /// The new specification file
+        new: PathBuf,
+        /// Target platform; defaults to `target` in spec-coder.toml, or the
+        /// one the new specification declares
+        #[arg(long)]
+        target: Option<Target>,
+    },
+    /// Remove the sessions, prover outputs, caches and sandbox builds runs
+    /// leave in the examples, keeping what the retention policy allows
+    Clean {
+        /// The example: its name under the examples directory, or its crate
+        /// directory; defaults to every example
+        example: Option<String>,
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+        /// Remove only this kind of artifact: `session`, `prover-output`,
+        /// `cache` or `sandbox`; may be given more than once
+        #[arg(long = "kind", value_name = "KIND")]
+        kinds: Vec<clean::Kind>,
+        /// Remove artifacts last written longer ago than this, e.g. `7d`;
+        /// defaults to `clean.max_age` in spec-coder.toml
+        #[arg(long)]
+        older_than: Option<clean::Age>,
+        /// Remove the oldest artifacts until the rest take up at most this,
+        /// e.g. `2G`; defaults to `clean.max_size` in spec-coder.toml
+        #[arg(long)]
+        max_size: Option<clean::Size>,
+        /// List what would be removed without removing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List the vetted CPI and syscall summaries the Solana examples share,
+    /// or the calls an example makes directly instead of through them
+    Summaries {
+        /// The example to audit: its name under the examples directory, or
+        /// its crate directory
+        example: Option<String>,
+        /// Directory the examples live in; defaults to `output.examples_dir`
+        /// in spec-coder.toml, or `examples`
+        #[arg(long)]
+        examples_dir: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Args)]
+struct ImplementArgs {
+    /// Specification file (.yaml, .yml, .toml or .md)
+    spec: PathBuf,
+    /// Target platform; defaults to `target` in spec-coder.toml, or the
+    /// one the specification declares
+    #[arg(long)]
+    target: Option<Target>,
+    /// Directory the examples live in; defaults to `output.examples_dir`
+    /// in spec-coder.toml, or `examples`
+    #[arg(long)]
+    examples_dir: Option<PathBuf>,
+    /// Crate directory, instead of
+    /// `<examples-dir>/<target>/materialized_<name>`; an existing crate is
+    /// continued from
+    #[arg(long)]
+    out: Option<PathBuf>,
+    /// Materialize the crate again even if it exists
+    #[arg(long)]
+    force: bool,
+    /// Model backend: `anthropic` or `openai`; defaults to
+    /// `model.provider` in spec-coder.toml, or `anthropic`
+    #[arg(long)]
+    provider: Option<ProviderKind>,
+    /// Model name; defaults to `model.name` in spec-coder.toml, or one
+    /// suited to the provider
+    #[arg(long)]
+    model: Option<String>,
+    /// API root, e.g. of a local OpenAI-compatible server; defaults to
+    /// `model.base_url` in spec-coder.toml
+    #[arg(long)]
+    base_url: Option<String>,
+    /// Model responses to check before giving up; defaults to
+    /// `budget.max_iterations` in spec-coder.toml, or 8
+    #[arg(long)]
+    max_iterations: Option<usize>,
+    /// Input and output tokens to spend before giving up
+    #[arg(long)]
+    max_tokens: Option<u64>,
+    /// Rules to prove before giving up
+    #[arg(long)]
+    max_prover_jobs: Option<usize>,
+    /// Rules to prove at once; defaults to `prover.jobs` in
+    /// spec-coder.toml, or 1
+    #[arg(long)]
+    jobs: Option<usize>,
+    /// Prove the rules with this tag first, and the others only once they
+    /// pass; defaults to `prover.fast_tag` in spec-coder.toml
+    #[arg(long, value_name = "TAG")]
+    fast_tag: Option<String>,
+    /// Skip formal verification and stop once the build and tests pass
+    #[arg(long)]
+    no_fv: bool,
+    /// Prover backend: `certora`, `cloud` to run the rules as jobs of the
+    /// Certora cloud, `auto` to choose per rule, or `kani` to model check
+    /// the rules locally
+    #[arg(long, default_value = "certora")]
+    backend: Backend,
+    /// Prove every rule again instead of reusing the results of rules
+    /// whose inputs did not change
+    #[arg(long)]
+    no_cache: bool,
+    /// Directory of prompt templates overriding the built-in ones, e.g.
+    /// `svm/system.j2`; defaults to `templates.dir` in spec-coder.toml
+    #[arg(long)]
+    templates: Option<PathBuf>,
+    /// Continue the interrupted run saved in the crate instead of
+    /// starting over
+    #[arg(long, conflicts_with = "force")]
+    resume: bool,
+    /// Percentage of the mutants of the processor the tests and rules
+    /// must kill once they pass; defaults to `mutation.min_score` in
+    /// spec-coder.toml, or no mutation
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    min_mutation_score: Option<u8>,
+    /// Where to build and test the model's code: `tempdir`, a copy of
+    /// the crate with a restricted environment, `docker[:<image>]` or
+    /// `podman[:<image>]`, or `none`; defaults to `build.sandbox` in
+    /// spec-coder.toml, or `tempdir`
+    #[arg(long)]
+    sandbox: Option<Sandbox>,
+    /// Leave the model's code as it is once it builds instead of running
+    /// rustfmt and `clippy --fix` on it and feeding back the lints left;
+    /// defaults to `build.clippy` in spec-coder.toml
+    #[arg(long)]
+    no_clippy: bool,
+    /// Write the model's files without showing their diffs for approval
+    #[arg(long)]
+    auto_approve: bool,
+    /// Follow the run in an interactive interface instead of a log: the
+    /// model's output as it arrives, the build and every rule, with keys
+    /// to approve diffs, skip rules or abort
+    #[arg(long)]
+    tui: bool,
+    /// Have a specifier refine the spec before the first response and a
+    /// critic diagnose rule failures before they are fed back
+    #[arg(long)]
+    roles: bool,
+    /// Stream the model's responses, writing each file as soon as it is
+    /// complete and cutting a response short at the first file that does
+    /// not parse
+    #[arg(long)]
+    stream: bool,
+    /// Ask the model for unified diffs of the files it changes instead
+    /// of their whole contents; a diff that does not match the file is
+    /// not applied
+    #[arg(long)]
+    patch: bool,
+    /// Commit the crate to a new branch once it is materialized and
+    /// after every iteration; a resumed run commits to the current
+    /// branch
+    #[arg(long)]
+    git: bool,
+    /// Once the run passes, build the program and write what deploying
+    /// it takes to `deploy/` in the crate: the shared object, the program
+    /// keypair and a crate deploying it over RPC
+    #[arg(long)]
+    deploy_artifacts: bool,
+    /// Record every response of the model in this directory, keyed by a
+    /// hash of its request, for `--replay`
+    #[arg(long, value_name = "DIR", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+    /// Answer the model's requests with the responses recorded in this
+    /// directory by `--record`, without a network or an API key; a
+    /// request that was not recorded fails the run
+    #[arg(long, value_name = "DIR")]
+    replay: Option<PathBuf>,
+}
+
+fn load(path: &PathBuf) -> anyhow::Result<Spec> {
+    Spec::from_path(path).with_context(|| format!("failed to load {}", path.display()))
+}
+
+/// Prints what the linter finds in a loaded spec, which are only warnings,
+/// so that they are settled before a model guesses.
+fn warn_lints(spec: &Spec) {
+    for lint in spec::lint(spec) {
+        eprintln!("{lint}");
+    }
+}
+
+fn report(progress: &Progress) {
+    match progress {
+        Progress::Iteration(iteration) => println!("Iteration {iteration}"),
+        // The log shows what the model's text leads to, not the text
+        Progress::Output(_) => {}
+        Progress::Wrote(paths) => {
+            for path in paths {
+                println!("  wrote {}", path.display());
+            }
+        }
+        Progress::SyntaxError { path, error } => {
+            println!("  {} does not parse: {error}", path.display())
+        }
+        Progress::Regression(test) => println!("  added regression test {test}"),
+        Progress::Build(Verdict::Passed) => println!("  build and tests passed"),
+        Progress::Build(Verdict::Failed(_)) => println!("  build or tests failed"),
+        Progress::Lints(Verdict::Passed) => println!("  clippy passed"),
+        Progress::Lints(Verdict::Failed(_)) => println!("  clippy reported lints"),
+        Progress::Rule(result) if result.cached => {
+            println!("  {}: {} (cached)", result.rule, result.status)
+        }
+        Progress::Rule(result) => println!("  {}: {}", result.rule, result.status),
+        Progress::ComputeUnits {
+            function,
+            units,
+            budget,
+        } => println!("  {function}: {units} of {budget} compute units"),
+        Progress::Throughput {
+            helper,
+            calls,
+            target,
+        } => println!("  {helper}: {calls} of {target} calls per second"),
+        Progress::Mutants { killed, total } => {
+            println!("  killed {killed} of {total} mutants")
+        }
+    }
+}
+
+/// The model of a run: the flags given, then the configuration, then the
+/// defaults of the provider.
+fn llm_config(
+    project: &Config,
+    provider: Option<ProviderKind>,
+    model: Option<String>,
+    base_url: Option<String>,
+) -> LlmConfig {
+    let provider = provider
+        .or(project.model.provider)
+        .unwrap_or(ProviderKind::Anthropic);
+    let mut config = LlmConfig::new(provider);
+    if let Some(model) = model.or_else(|| project.model.name.clone()) {
+        config.model = model;
+    }
+    config.base_url = base_url.or_else(|| project.model.base_url.clone());
+    if let Some(timeout_secs) = project.model.timeout_secs {
+        config.timeout_secs = timeout_secs;
+    }
+    if let Some(max_retries) = project.model.max_retries {
+        config.max_retries = max_retries;
+    }
+    config.context_tokens = project.model.context_tokens;
+    config
+}
+
+/// `backend` with the cloud settings of the project. Cloud jobs are
+/// cancelled on the first Ctrl-C, and the process exits on the second.
+fn prover_backend(backend: Backend, project: &Config) -> Backend {
+    let cloud = |prover: &mut CloudProver| {
+        if let Some(secs) = project.prover.poll_interval_secs {
+            prover.polling.interval = Duration::from_secs(secs);
+        }
+        if let Some(secs) = project.prover.job_timeout_secs {
+            prover.polling.timeout = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        prover.key.clone_from(&project.prover.cloud_key);
+    };
+    let backend = match backend {
+        Backend::Cloud(mut prover) => {
+            cloud(&mut prover);
+            Backend::Cloud(prover)
+        }
+        Backend::Auto(mut router) => {
+            cloud(&mut router.cloud);
+            router.cloud_rules.clone_from(&project.prover.cloud_rules);
+            Backend::Auto(router)
+        }
+        backend => return backend,
+    };
+    match signal_hook::iterator::Signals::new([signal_hook::consts::SIGINT]) {
+        Ok(mut signals) => {
+            std::thread::spawn(move || {
+                for _ in signals.forever() {
+                    if prover::cloud::cancelled() {
+                        std::process::exit(130);
+                    }
+                    eprintln!("Cancelling the prover jobs; press Ctrl-C again to exit");
+                    prover::cloud::cancel();
+                }
+            });
+        }
+        Err(err) => eprintln!("warning: Ctrl-C will not cancel the prover jobs: {err}"),
+    }
+    backend
+}
+
+/// Fails for targets whose programs are not built and proved by the driver.
+fn driven(target: Target) -> anyhow::Result<()> {
+    if target == Target::Evm {
+        anyhow::bail!(
+            "{target} programs are not implemented by spec-coder; materialize them and run \
+             `certoraRun certora/conf/default.conf` in the materialized directory"
+        );
+    }
+    Ok(())
+}
+
+/// Implements a spec, or with `migrating` brings an existing crate to the
+/// version of its spec first.
+fn implement(project: &Config, args: ImplementArgs, migrating: bool) -> anyhow::Result<()> {
+    let ImplementArgs {
+        spec,
+        target,
+        examples_dir,
+        out,
+        force,
+        provider,
+        model,
+        base_url,
+        max_iterations,
+        max_tokens,
+        max_prover_jobs,
+        jobs,
+        fast_tag,
+        no_fv,
+        backend,
+        no_cache,
+        templates,
+        resume,
+        min_mutation_score,
+        sandbox,
+        no_clippy,
+        auto_approve,
+        tui,
+        roles,
+        stream,
+        patch,
+        git,
+        deploy_artifacts,
+        record,
+        replay,
+    } = args;
+    if tui && !std::io::stdout().is_terminal() {
+        anyhow::bail!("--tui needs a terminal");
+    }
+    let budget = project.budget.clone();
+    let format = spec
+        .extension()
+        .map_or(String::new(), |ext| ext.to_string_lossy().into_owned());
+    let mut spec = load(&spec)?;
+    warn_lints(&spec);
+    let target = target.or(project.target).unwrap_or(spec.target);
+    driven(target)?;
+    if deploy_artifacts && !deploy::supported(target) {
+        anyhow::bail!("{target} programs have no deployment artifacts");
+    }
+    // Regression tests are written for the target being implemented
+    spec.target = target;
+    let dir = out.unwrap_or_else(|| {
+        materialize::crate_dir(
+            &examples_dir.unwrap_or_else(|| project.examples_dir()),
+            &spec,
+            target,
+        )
+    });
+    if migrating && force {
+        anyhow::bail!("a migration keeps the crate; implement it with --force to start over");
+    }
+    // An existing crate is only brought up to date with the spec
+    let incremental = !force && dir.exists();
+    // A resumed run continues with the crate as it was left
+    if !resume && migrating {
+        let migration = migrate::migrate(&spec, target, &dir)?;
+        println!("Migrating `{}` from {migration}", spec.name);
+        for function in &migration.plan.regenerate {
+            println!("Regenerated `{function}`");
+        }
+    } else if !resume && incremental {
+        let plan = incremental::regenerate(&spec, target, &dir)?;
+        migrate::record(&spec, &dir)?;
+        for function in &plan.regenerate {
+            println!("Regenerated `{function}`");
+        }
+    } else if !resume {
+        let mut files = materialize::generate(&spec, target)?;
+        let provenance = Provenance::new(provenance::fingerprint(&spec), None);
+        provenance::stamp_files(&mut files, &provenance, project.output.license.as_deref());
+        materialize::write(&files, &dir, force)?;
+        migrate::record(&spec, &dir)?;
+        println!("Materialized `{}` into {}", spec.name, dir.display());
+    }
+    let llm_config = llm_config(project, provider, model, base_url);
+    let provider: Box<dyn Provider> = match (record, replay) {
+        (_, Some(dir)) => Box::new(Replay::new(&llm_config, dir)),
+        (Some(dir), None) => Box::new(Recording::new(llm::from_config(&llm_config)?, dir)),
+        (None, None) => llm::from_config(&llm_config)?,
+    };
+    let sandbox = sandbox
+        .or(project.build.sandbox.clone())
+        .unwrap_or_default();
+    let verifier = CargoVerifier {
+        prover: (!no_fv).then(|| prover_backend(backend, project)),
+        jobs: jobs.or(project.prover.jobs).unwrap_or(1),
+        cache: !no_cache && project.prover.cache.unwrap_or(true),
+        sandbox: sandbox.clone(),
+        clippy: !no_clippy && project.build.clippy.unwrap_or(true),
+    };
+    let templates = templates.or_else(|| project.templates.dir.clone());
+    let prompts = templates
+        .as_ref()
+        .map_or("builtin".to_string(), |dir| dir.display().to_string());
+    let templates = match templates {
+        Some(dir) => Templates::with_overrides(&target.to_string(), &dir),
+        None => Templates::builtin(&target.to_string()),
+    };
+    let options = DriverOptions {
+        max_iterations: max_iterations
+            .or(budget.max_iterations)
+            .unwrap_or(DriverOptions::default().max_iterations),
+        max_tokens: max_tokens.or(budget.max_tokens),
+        max_prover_jobs: max_prover_jobs.or(budget.max_prover_jobs),
+        incremental,
+        resume,
+        min_mutation_score: min_mutation_score.or(project.mutation.min_score),
+        roles,
+        stream,
+        patch,
+        fast_tag: fast_tag.or_else(|| project.prover.fast_tag.clone()),
+    };
+    let control = Control::default();
+    let (events, received) = mpsc::channel();
+    let approval = tui::Approval::new(events.clone());
+    let prompt;
+    let reviewer: &dyn Reviewer = if auto_approve {
+        &AutoApprove
+    } else if tui {
+        &approval
+    } else if std::io::stdin().is_terminal() {
+        prompt = Prompt::new(std::io::stdin().lock(), std::io::stderr());
+        &prompt
+    } else {
+        anyhow::bail!(
+            "the model's files need approval but stdin is not a terminal; \
+             pass --auto-approve to write them without review"
+        );
+    };
+    let context = RunContext {
+        started: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs()),
+        spec: spec.name.clone(),
+        format,
+        target: target.to_string(),
+        provider: provider.name().to_string(),
+        model: provider.model().to_string(),
+        prompts,
+    };
+    let repo = if git {
+        let repo = Repo::open(&dir)?;
+        if !resume {
+            let branch = git::branch_name(&spec.name, target, context.started);
+            repo.create_branch(&branch)?;
+            let message = match spec.version.filter(|_| migrating) {
+                Some(version) => format!("{}: migrated to {version}", spec.name),
+                None => format!("{}: materialized for {target}", spec.name),
+            };
+            repo.commit(&message)?;
+            println!("Committing to branch {branch}");
+        }
+        Some(repo)
+    } else {
+        None
+    };
+    let started = Instant::now();
+    let mut failures = Failures::default();
+    let max_iterations = options.max_iterations;
+    let mut driver =
+        Driver::new(provider.as_ref(), &verifier, &templates, options).with_reviewer(reviewer);
+    if let Some(repo) = &repo {
+        driver = driver.with_repo(repo);
+    }
+    let outcome = if tui {
+        let title = format!("{} ({target})", spec.name);
+        let rules = prover::find_rules(&dir).unwrap_or_default();
+        let app = tui::App::new(title, max_iterations, &rules);
+        let driver = driver.with_control(&control);
+        std::thread::scope(|scope| {
+            let ui = scope.spawn(|| tui::run(app, received, &control));
+            let outcome = driver.run(&spec, &dir, &mut |progress| {
+                let _ = events.send(tui::Event::Progress(progress.clone()));
+                failures.observe(progress);
+            });
+            let message = match &outcome {
+                Ok(outcome) if outcome.success => {
+                    format!("passed after {} iterations", outcome.iterations)
+                }
+                Ok(outcome) if outcome.aborted => {
+                    format!("aborted after {} iterations", outcome.iterations)
+                }
+                Ok(outcome) => format!("did not pass after {} iterations", outcome.iterations),
+                Err(err) => format!("failed: {err}"),
+            };
+            let _ = events.send(tui::Event::Finished(message));
+            ui.join().expect("the interface does not panic")?;
+            anyhow::Ok(outcome?)
+        })?
+    } else {
+        driver.run(&spec, &dir, &mut |progress| {
+            report(progress);
+            failures.observe(progress);
+        })?
+    };
+    let run = RunMetrics::new(context, &outcome, failures, started.elapsed());
+    metrics::record(&project.metrics(), &run)?;
+    println!(
+        "{} tokens in, {} tokens out, {} prover jobs",
+        outcome.usage.input_tokens, outcome.usage.output_tokens, outcome.prover_jobs
+    );
+    if outcome.aborted {
+        anyhow::bail!(
+            "`{}` was aborted after {} iterations; pass --resume to continue",
+            spec.name,
+            outcome.iterations
+        );
+    }
+    for rule in &outcome.skipped {
+        println!("  {rule}: skipped");
+    }
+    if !outcome.success {
+        if outcome
+            .exhausted
+            .is_some_and(|limit| limit != Limit::Iterations)
+        {
+            for (rule, status) in &outcome.rules {
+                println!("  {rule}: {status}");
+            }
+            for rule in &outcome.unproved {
+                println!("  {rule}: not proved");
+            }
+        }
+        let limit = outcome.exhausted.unwrap_or(Limit::Iterations);
+        anyhow::bail!(
+            "`{}` did not pass within the {limit} budget, after {} iterations; \
+             pass --resume with a larger budget to continue:\n{}",
+            spec.name,
+            outcome.iterations,
+            outcome.feedback.unwrap_or_default()
+        );
+    }
+    // A crate with skipped rules is not verified
+    if !no_fv && outcome.skipped.is_empty() {
+        drift::record(&spec, &dir)?;
+    }
+    println!(
+        "`{}` passed after {} iterations",
+        spec.name, outcome.iterations
+    );
+    if deploy_artifacts {
+        let artifacts = deploy::emit(&spec, target, &dir, &sandbox)?;
+        println!(
+            "Wrote {} for program {}; deploy it with `cargo run --manifest-path {}`",
+            artifacts.program.display(),
+            artifacts.program_id,
+            artifacts.helper.display()
+        );
+    }
+    Ok(())
+}
+
+/// The engine of `api`: materializes and implements a spec as `implement`
+/// does, with the model, prover and budget of spec-coder.toml, writing the
+/// model's files without review.
+#[cfg(feature = "api")]
+struct Pipeline {
+    project: Config,
+    examples_dir: PathBuf,
+    backend: Backend,
+}
+
+#[cfg(feature = "api")]
+impl Pipeline {
+    fn implement(
+        &self,
+        spec: &Spec,
+        target: Target,
+        dir: &Path,
+        request: &RunRequest,
+        control: &Control,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> anyhow::Result<RunReport> {
+        let project = &self.project;
+        driven(target)?;
+        let mut spec = spec.clone();
+        spec.target = target;
+        let incremental = !request.force && dir.exists();
+        if incremental {
+            incremental::regenerate(&spec, target, dir)?;
+        } else {
+            let mut files = materialize::generate(&spec, target)?;
+            let provenance = Provenance::new(provenance::fingerprint(&spec), None);
+            provenance::stamp_files(&mut files, &provenance, project.output.license.as_deref());
+            materialize::write(&files, dir, request.force)?;
+        }
+        migrate::record(&spec, dir)?;
+        let provider = llm::from_config(&llm_config(project, None, None, None))?;
+        let verifier = CargoVerifier {
+            prover: (!request.no_fv).then(|| self.backend.clone()),
+            jobs: project.prover.jobs.unwrap_or(1),
+            cache: project.prover.cache.unwrap_or(true),
+            sandbox: project.build.sandbox.clone().unwrap_or_default(),
+            clippy: project.build.clippy.unwrap_or(true),
+        };
+        let templates = match &project.templates.dir {
+            Some(dir) => Templates::with_overrides(&target.to_string(), dir),
+            None => Templates::builtin(&target.to_string()),
+        };
+        let budget = &project.budget;
+        let options = DriverOptions {
+            max_iterations: request
+                .max_iterations
+                .or(budget.max_iterations)
+                .unwrap_or(DriverOptions::default().max_iterations),
+            max_tokens: request.max_tokens.or(budget.max_tokens),
+            max_prover_jobs: request.max_prover_jobs.or(budget.max_prover_jobs),
+            incremental,
+            min_mutation_score: project.mutation.min_score,
+            fast_tag: self.project.prover.fast_tag.clone(),
+            ..DriverOptions::default()
+        };
+        let context = RunContext {
+            started: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+            spec: spec.name.clone(),
+            format: match request.format {
+                SpecFormat::Yaml => "yaml",
+                SpecFormat::Toml => "toml",
+                SpecFormat::Markdown => "md",
+            }
+            .to_string(),
+            target: target.to_string(),
+            provider: provider.name().to_string(),
+            model: provider.model().to_string(),
+            prompts: project
+                .templates
+                .dir
+                .as_ref()
+                .map_or("builtin".to_string(), |dir| dir.display().to_string()),
+        };
+        let started = Instant::now();
+        let mut failures = Failures::default();
+        let outcome = Driver::new(provider.as_ref(), &verifier, &templates, options)
+            .with_reviewer(&AutoApprove)
+            .with_control(control)
+            .run(&spec, dir, &mut |progress| {
+                failures.observe(progress);
+                on_progress(progress);
+            })?;
+        let run = RunMetrics::new(context, &outcome, failures, started.elapsed());
+        metrics::record(&project.metrics(), &run)?;
+        if outcome.success && !request.no_fv && outcome.skipped.is_empty() {
+            drift::record(&spec, dir)?;
+        }
+        Ok(outcome)
+    }
+}
+
+#[cfg(feature = "api")]
+impl Engine for Pipeline {
+    fn examples_dir(&self) -> &Path {
+        &self.examples_dir
+    }
+
+    fn crate_dir(&self, spec: &Spec, target: Target) -> PathBuf {
+        materialize::crate_dir(&self.examples_dir, spec, target)
+    }
+
+    fn run(
+        &self,
+        spec: &Spec,
+        target: Target,
+        dir: &Path,
+        request: &RunRequest,
+        control: &Control,
+        on_progress: &mut dyn FnMut(&Progress),
+    ) -> Result<RunReport, EngineError> {
+        self.implement(spec, target, dir, request, control, on_progress)
+            .map_err(Into::into)
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let command = Cli::parse().command;
+    let project = Config::load(Path::new("."))?;
+    match command {
+        Command::Lint {
+            spec: path,
+            deny_warnings,
+        } => {
+            let spec = Spec::from_path_unvalidated(&path)
+                .with_context(|| format!("failed to load {}", path.display()))?;
+            let lints = spec::lint(&spec);
+            for lint in &lints {
+                println!("{lint}");
+            }
+            let errors = lints
+                .iter()
+                .filter(|lint| lint.severity == Severity::Error)
+                .count();
+            let warnings = lints.len() - errors;
+            if errors > 0 || (deny_warnings && warnings > 0) {
+                anyhow::bail!(
+                    "`{}` has {errors} errors and {warnings} warnings",
+                    spec.name
+                );
+            }
+            println!("`{}`: {warnings} warnings", spec.name);
+        }
+        Command::Lsp => {
+            lsp::Server::new().serve(std::io::stdin().lock(), std::io::stdout().lock())?;
+        }
+        Command::ExtractProperties {
+            spec: spec_path,
+            requirements,
+            provider,
+            model,
+            base_url,
+            templates,
+            auto_approve,
+            out,
+        } => {
+            let mut spec = load(&spec_path)?;
+            let text = std::fs::read_to_string(&requirements)
+                .with_context(|| format!("failed to read {}", requirements.display()))?;
+            let requirements = properties::requirements(&text);
+            let provider = llm::from_config(&llm_config(&project, provider, model, base_url))?;
+            let target = spec.target.to_string();
+            let templates = match templates.or_else(|| project.templates.dir.clone()) {
+                Some(dir) => Templates::with_overrides(&target, &dir),
+                None => Templates::builtin(&target),
+            };
+            let extraction =
+                properties::extract(provider.as_ref(), &templates, &spec, &requirements)?;
+            for rejection in &extraction.rejections {
+                eprintln!(
+                    "rejected `{}` for \"{}\": {}",
+                    rejection.candidate.name, rejection.candidate.requirement, rejection.reason
+                );
+            }
+            let prompt;
+            let reviewer: &dyn Reviewer = if auto_approve {
+                &AutoApprove
+            } else if std::io::stdin().is_terminal() {
+                prompt = Prompt::new(std::io::stdin().lock(), std::io::stderr());
+                &prompt
+            } else {
+                anyhow::bail!(
+                    "the proposed invariants need confirmation but stdin is not a terminal; \
+                     pass --auto-approve to add them without review"
+                );
+            };
+            let confirmed =
+                properties::confirm(&mut spec, &spec_path, extraction.proposals, reviewer)?;
+            for invariant in spec
+                .invariants
+                .iter()
+                .filter(|i| confirmed.contains(&i.name))
+            {
+                println!("{}: {}", invariant.name, invariant.expr);
+            }
+            println!(
+                "Confirmed {} invariants for {} requirements",
+                confirmed.len(),
+                requirements.len()
+            );
+            if let Some(out) = out {
+                std::fs::write(&out, spec.to_yaml_string()?)
+                    .with_context(|| format!("failed to write {}", out.display()))?;
+                println!("Wrote {}", out.display());
+            }
+        }
+        Command::Materialize {
+            spec,
+            target,
+            examples_dir,
+            out,
+            force,
+            cpi,
+        } => {
+            let spec = load(&spec)?;
+            warn_lints(&spec);
+            let target = target.or(project.target).unwrap_or(spec.target);
+            let mut files = materialize::generate(&spec, target)?;
+            if cpi {
+                files.extend(materialize::cpi(&spec, target)?);
+            }
+            let provenance = Provenance::new(provenance::fingerprint(&spec), None);
+            provenance::stamp_files(&mut files, &provenance, project.output.license.as_deref());
+            let dir = out.unwrap_or_else(|| {
+                materialize::crate_dir(
+                    &examples_dir.unwrap_or_else(|| project.examples_dir()),
+                    &spec,
+                    target,
+                )
+            });
+            match materialize::write(&files, &dir, force) {
+                Err(err @ MaterializeError::Exists(_)) => {
+                    return Err(err).context("pass --force to overwrite it");
+                }
+                result => result?,
+            }
+            migrate::record(&spec, &dir)?;
+            println!("Materialized `{}` into {}", spec.name, dir.display());
+        }
+        Command::Implement(args) => implement(&project, args, false)?,
+        Command::Migrate(args) => implement(&project, args, true)?,
+        #[cfg(feature = "api")]
+        Command::Api {
+            addr,
+            examples_dir,
+            backend,
+        } => {
+            let listener = std::net::TcpListener::bind(&addr)
+                .with_context(|| format!("failed to listen on {addr}"))?;
+            let token = std::env::var("SPEC_CODER_API_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty());
+            let target = project.target;
+            let pipeline = Pipeline {
+                examples_dir: examples_dir.unwrap_or_else(|| project.examples_dir()),
+                backend: prover_backend(backend, &project),
+                project,
+            };
+            println!(
+                "Serving the API on http://{}{}",
+                listener.local_addr()?,
+                if token.is_some() {
+                    ", with a token"
+                } else {
+                    ""
+                }
+            );
+            api::serve(listener, Api::new(pipeline, target), token)?;
+        }
+        #[cfg(feature = "dashboard")]
+        Command::Serve {
+            addr,
+            examples_dir,
+            metrics,
+        } => {
+            let listener = std::net::TcpListener::bind(&addr)
+                .with_context(|| format!("failed to listen on {addr}"))?;
+            println!("Serving the dashboard on http://{}", listener.local_addr()?);
+            dashboard::serve(
+                listener,
+                Dashboard {
+                    examples_dir: examples_dir.unwrap_or_else(|| project.examples_dir()),
+                    metrics: metrics.unwrap_or_else(|| project.metrics()),
+                },
+            )?;
+        }
+        Command::Stats { by, metrics: path } => {
+            let path = path.unwrap_or_else(|| project.metrics());
+            let runs = metrics::load(&path)?;
+            if runs.is_empty() {
+                anyhow::bail!("no runs recorded in {}", path.display());
+            }
+            print!("{}", Stats::new(&runs, by));
+        }
+        Command::NewExample {
+            name,
+            target,
+            examples_dir,
+            force,
+        } => {
+            let created = match scaffold::new_example(
+                &name,
+                target.or(project.target).unwrap_or(Target::Svm),
+                &examples_dir.unwrap_or_else(|| project.examples_dir()),
+                project.output.license.as_deref(),
+                force,
+            ) {
+                Err(err @ ScaffoldError::Exists(_)) => {
+                    return Err(err).context("pass --force to overwrite it");
+                }
+                result => result?,
+            };
+            println!("Wrote the spec template to {}", created.spec.display());
+            println!("Materialized `{name}` into {}", created.dir.display());
+        }
+        Command::ImportIssue {
+            issue: input,
+            repo,
+            name,
+            target,
+            examples_dir,
+            api_url,
+            force,
+        } => {
+            let reference = issue::Reference::parse(&input, repo.as_deref())?;
+            let api_url = api_url
+                .or_else(|| std::env::var("GITHUB_API_URL").ok())
+                .unwrap_or_else(|| issue::API_URL.to_string());
+            let token = ["GITHUB_TOKEN", "GH_TOKEN"]
+                .iter()
+                .find_map(|var| std::env::var(var).ok().filter(|token| !token.is_empty()));
+            let issue = issue::fetch(&reference, &api_url, token.as_deref())
+                .with_context(|| format!("failed to read {reference}"))?;
+            let name = name.unwrap_or_else(|| issue.slug());
+            let imported = match issue::import(
+                &issue,
+                &name,
+                target.or(project.target).unwrap_or(Target::Svm),
+                &examples_dir.unwrap_or_else(|| project.examples_dir()),
+                project.output.license.as_deref(),
+                force,
+            ) {
+                Err(err @ issue::IssueError::Scaffold(ScaffoldError::Exists(_))) => {
+                    return Err(err).context("pass --force to overwrite it");
+                }
+                result => result?,
+            };
+            println!(
+                "Wrote the draft spec for \"{}\" to {}",
+                issue.title,
+                imported.example.spec.display()
+            );
+            println!(
+                "Wrote {} requirements to {}",
+                imported.count,
+                imported.requirements.display()
+            );
+            println!(
+                "Materialized `{name}` into {}, linked to {}",
+                imported.example.dir.display(),
+                issue.url
+            );
+        }
+        Command::CheckDrift {
+            spec,
+            target,
+            examples_dir,
+            out,
+        } => {
+            let spec = load(&spec)?;
+            let target = target.or(project.target).unwrap_or(spec.target);
+            let dir = out.unwrap_or_else(|| {
+                materialize::crate_dir(
+                    &examples_dir.unwrap_or_else(|| project.examples_dir()),
+                    &spec,
+                    target,
+                )
+            });
+            let drifts = drift::check(&spec, &dir)?;
+            for drift in &drifts {
+                println!("{}: {}", drift.function, drift.drift);
+            }
+            let stale = drift::stale(&spec, &dir)?;
+            for path in &stale {
+                println!(
+                    "{}: generated for another version of the spec",
+                    path.display()
+                );
+            }
+            if !stale.is_empty() {
+                anyhow::bail!(
+                    "{} generated files of `{}` are stale; run `implement` to regenerate them",
+                    stale.len(),
+                    spec.name
+                );
+            }
+            if !drifts.is_empty() {
+                anyhow::bail!(
+                    "{} of {} functions drifted from `{}`",
+                    drifts.len(),
+                    spec.functions.len(),
+                    spec.name
+                );
+            }
+            println!("`{}` matches its last verification", spec.name);
+        }
+        Command::Watch {
+            spec: spec_path,
+            target,
+            examples_dir,
+            out,
+            debounce,
+            jobs,
+            no_fv,
+            backend,
+        } => {
+            let spec = load(&spec_path)?;
+            let target = target.or(project.target).unwrap_or(spec.target);
+            driven(target)?;
+            let dir = out.unwrap_or_else(|| {
+                materialize::crate_dir(
+                    &examples_dir.unwrap_or_else(|| project.examples_dir()),
+                    &spec,
+                    target,
+                )
+            });
+            if !dir.exists() {
+                anyhow::bail!("{} does not exist; run `materialize` first", dir.display());
+            }
+            let verifier = CargoVerifier {
+                prover: (!no_fv).then(|| prover_backend(backend, &project)),
+                jobs: jobs.or(project.prover.jobs).unwrap_or(1),
+                cache: project.prover.cache.unwrap_or(true),
+                sandbox: project.build.sandbox.unwrap_or_default(),
+                // The crate is being edited, so it is not rewritten under
+                // the editor
+                clippy: false,
+            };
+            let options = WatchOptions {
+                debounce: Duration::from_secs(debounce),
+                no_fv,
+                ..WatchOptions::default()
+            };
+            println!("Watching {} and {}", spec_path.display(), dir.display());
+            watch::watch(
+                &spec_path,
+                target,
+                &dir,
+                &verifier,
+                options,
+                &mut |event| match event {
+                    WatchEvent::Changed(paths) => {
+                        for path in paths {
+                            println!("changed {}", path.display());
+                        }
+                    }
+                    WatchEvent::Regenerated(plan) => {
+                        for function in &plan.regenerate {
+                            println!("  regenerated `{function}`");
+                        }
+                    }
+                    WatchEvent::Build(verdict) => report(&Progress::Build(verdict.clone())),
+                    WatchEvent::Rule(result) => report(&Progress::Rule(result.clone())),
+                    WatchEvent::Error(err) => eprintln!("  error: {err}"),
+                },
+                &mut || false,
+            )?;
+        }
+        Command::Mcp {
+            examples_dir,
+            jobs,
+            backend,
+        } => {
+            let examples_dir = examples_dir.unwrap_or_else(|| project.examples_dir());
+            let jobs = jobs.or(project.prover.jobs).unwrap_or(1);
+            let verifier = CargoVerifier {
+                prover: Some(prover_backend(backend, &project)),
+                jobs,
+                cache: project.prover.cache.unwrap_or(true),
+                sandbox: project.build.sandbox.unwrap_or_default(),
+                clippy: false,
+            };
+            let mut server = mcp::Server::new(
+                examples_dir,
+                project.target,
+                project.output.license.clone(),
+                &verifier,
+                jobs,
+            );
+            // Stdout carries the protocol, so progress goes nowhere
+            server.serve(std::io::stdin().lock(), std::io::stdout().lock())?;
+        }
+        Command::Verify {
+            example,
+            rules,
+            tags,
+            examples_dir,
+            jobs,
+            backend,
+            no_cache,
+        } => {
+            let example = example.unwrap_or_else(|| ".".to_string());
+            let (dir, spec) = verify::locate(
+                &examples_dir.unwrap_or_else(|| project.examples_dir()),
+                &example,
+            )?;
+            let jobs = jobs.or(project.prover.jobs).unwrap_or(1);
+            let verifier = CargoVerifier {
+                prover: Some(prover_backend(backend, &project)),
+                jobs,
+                cache: !no_cache && project.prover.cache.unwrap_or(true),
+                sandbox: project.build.sandbox.unwrap_or_default(),
+                // Verifying leaves the crate as it is
+                clippy: false,
+            };
+            println!("Verifying {}", dir.display());
+            let summary =
+                verify::verify(
+                    &dir,
+                    &rules,
+                    &tags,
+                    &verifier,
+                    jobs,
+                    &mut |event| match event {
+                        VerifyEvent::ConfWritten(paths) => {
+                            for path in paths {
+                                println!("  wrote {} for the prover configuration", path.display());
+                            }
+                        }
+                        VerifyEvent::Build(verdict) => report(&Progress::Build(verdict.clone())),
+                        VerifyEvent::Proving(rules) => println!("  proving {} rules", rules.len()),
+                        VerifyEvent::Rule(result) => report(&Progress::Rule(result.clone())),
+                    },
+                )?;
+            if let Some(spec) = &spec {
+                let verdicts = summary
+                    .results
+                    .iter()
+                    .map(|result| (result.rule.clone(), result.status))
+                    .collect();
+                results::record(&load(spec)?, &dir, &verdicts)?;
+            }
+            if let Verdict::Failed(output) = &summary.build {
+                anyhow::bail!(
+                    "{} does not build with the certora feature:\n{output}",
+                    dir.display()
+                );
+            }
+            let counts: Vec<String> = summary
+                .counts()
+                .iter()
+                .map(|(status, count)| format!("{count} {status}"))
+                .collect();
+            println!("{} rules: {}", summary.results.len(), counts.join(", "));
+            let failed: Vec<&str> = summary
+                .results
+                .iter()
+                .filter(|result| result.status != RuleStatus::Verified)
+                .map(|result| result.rule.as_str())
+                .collect();
+            if !failed.is_empty() {
+                anyhow::bail!(
+                    "{} of {} rules failed: {}",
+                    failed.len(),
+                    summary.results.len(),
+                    failed.join(", ")
+                );
+            }
+        }
+        Command::Report {
+            all: true,
+            examples_dir,
+            html,
+            ..
+        } => {
+            let registry = Registry::scan(&examples_dir.unwrap_or_else(|| project.examples_dir()))?;
+            let overview = Overview::collect(&registry)?;
+            print!("{overview}");
+            let html = html.unwrap_or_else(|| PathBuf::from(overview::REPORT_HTML));
+            if let Some(parent) = html.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&html, overview.to_html())
+                .with_context(|| format!("failed to write {}", html.display()))?;
+            println!(
+                "{} of {} examples green",
+                overview.green(),
+                overview.examples.len()
+            );
+            println!("Wrote {}", html.display());
+        }
+        Command::Report {
+            spec,
+            target,
+            examples_dir,
+            out,
+            json,
+            sarif,
+            junit,
+            ..
+        } => {
+            let spec = load(&spec.expect("required without --all"))?;
+            let target = target.or(project.target).unwrap_or(spec.target);
+            let dir = out.unwrap_or_else(|| {
+                materialize::crate_dir(
+                    &examples_dir.unwrap_or_else(|| project.examples_dir()),
+                    &spec,
+                    target,
+                )
+            });
+            let session = Session::load(&dir, &spec).context("run `implement` first")?;
+            let results = results::collect(&spec, &dir, &session.rules)?;
+            let json = json.unwrap_or_else(|| dir.join(results::RESULTS_JSON));
+            let sarif = sarif.unwrap_or_else(|| dir.join(results::RESULTS_SARIF));
+            let junit = junit.unwrap_or_else(|| dir.join(results::RESULTS_JUNIT));
+            results.write(&json, &sarif, &junit)?;
+            let passed = results
+                .rules
+                .iter()
+                .filter(|rule| rule.outcome == Outcome::Pass)
+                .count();
+            println!("{passed} of {} rules passed", results.rules.len());
+            println!(
+                "Wrote {}, {} and {}",
+                json.display(),
+                sarif.display(),
+                junit.display()
+            );
+        }
+        Command::Registry { examples_dir, out } => {
+            let registry = Registry::scan(&examples_dir.unwrap_or_else(|| project.examples_dir()))?;
+            registry.save(&out)?;
+            for example in &registry.examples {
+                print!(
+                    "{}/{}: {} rules, {}",
+                    example.target,
+                    example.name,
+                    example.rules.len(),
+                    example.status
+                );
+                match &example.source {
+                    Some(source) => println!(", from {source}"),
+                    None => println!(),
+                }
+            }
+            println!("Wrote {}", out.display());
+        }
+        Command::Diff { old, new, target } => {
+            let (old, new) = (load(&old)?, load(&new)?);
+            let target = target.or(project.target).unwrap_or(new.target);
+            print!("{}", diff::diff(&old, &new, target)?);
+        }
+        Command::Summaries {
+            example,
+            examples_dir,
+        } => {
+            let Some(example) = example else {
+                for summary in summaries::SUMMARIES {
+                    println!("svm_fv_support::cpi::{}: {}", summary.name, summary.model);
+                }
+                return Ok(());
+            };
+            let examples_dir = examples_dir.unwrap_or_else(|| project.examples_dir());
+            let (dir, _) = verify::locate(&examples_dir, &example)?;
+            let findings = summaries::audit(&dir)?;
+            for finding in &findings {
+                println!("{finding}");
+            }
+            if !findings.is_empty() {
+                anyhow::bail!(
+                    "{} call(s) in {} bypass the shared summaries",
+                    findings.len(),
+                    dir.display()
+                );
+            }
+            println!(
+                "{} makes no direct call with a shared summary",
+                dir.display()
+            );
+        }
+        Command::Clean {
+            example,
+            examples_dir,
+            kinds,
+            older_than,
+            max_size,
+            dry_run,
+        } => {
+            let examples_dir = examples_dir.unwrap_or_else(|| project.examples_dir());
+            let dirs = match example {
+                Some(example) => vec![verify::locate(&examples_dir, &example)?.0],
+                None => Registry::scan(&examples_dir)?
+                    .examples
+                    .into_iter()
+                    .map(|example| example.dir)
+                    .collect(),
+            };
+            let kinds = if kinds.is_empty() {
+                clean::Kind::ALL.to_vec()
+            } else {
+                kinds
+            };
+            let retention = clean::Retention {
+                max_age: older_than.or(project.clean.max_age),
+                max_size: max_size.or(project.clean.max_size),
+            };
+            let mut artifacts = Vec::new();
+            for dir in &dirs {
+                artifacts.extend(clean::artifacts(dir, &kinds)?);
+            }
+            let expired = clean::expired(&artifacts, &retention, SystemTime::now());
+            let mut freed = 0;
+            for artifact in &expired {
+                if !dry_run {
+                    clean::remove(artifact)?;
+                }
+                freed += artifact.size;
+                println!(
+                    "{} {} ({}, {})",
+                    if dry_run { "Would remove" } else { "Removed" },
+                    artifact.path.display(),
+                    artifact.kind,
+                    clean::Size(artifact.size)
+                );
+            }
+            let kept: u64 = artifacts.iter().map(|artifact| artifact.size).sum::<u64>() - freed;
+            println!(
+                "{} {} in {} of {} artifacts, keeping {}",
+                if dry_run { "Would free" } else { "Freed" },
+                clean::Size(freed),
+                expired.len(),
+                artifacts.len(),
+                clean::Size(kept)
+            );
+        }
+    }
+    Ok(())
+}
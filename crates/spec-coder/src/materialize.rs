@@ -0,0 +1,466 @@
+//! Scaffolding of example crates from a specification.
+//!
+//! The generated crate has the layout of the hand-written examples: state
+//! types, one processor function per specified function with its
+//! preconditions checked, a certora module whose rules are compiled from
+//! the invariants and postconditions, and unit tests compiled from the
+//! examples. Postconditions are left as comments in the processor for the
+//! implementation step. EVM programs are generated as a Solidity contract
+//! with a CVL spec instead, see [`evm`], and plain Rust libraries as crates
+//! of ordinary structs and functions, see [`rust`].
+
+mod benches;
+mod evm;
+mod rust;
+pub(crate) mod svm;
+
+pub use svm::program_id;
+
+use crate::provenance::{self, Provenance};
+use crate::prover::settings;
+use spec::{Spec, Target};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A file of a generated crate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratedFile {
+    /// Path relative to the crate root
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+impl GeneratedFile {
+    pub(crate) fn new(path: &str, contents: impl Into<String>) -> GeneratedFile {
+        GeneratedFile {
+            path: PathBuf::from(path),
+            contents: contents.into(),
+        }
+    }
+}
+
+/// An error materializing a specification.
+#[derive(Debug, thiserror::Error)]
+pub enum MaterializeError {
+    #[error("{location}: {message}")]
+    Unsupported { location: String, message: String },
+    #[error("{} already exists", .0.display())]
+    Exists(PathBuf),
+    #[error("failed to write {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// Generates the files of the example crate for `spec`.
+pub fn generate(spec: &Spec, target: Target) -> Result<Vec<GeneratedFile>, MaterializeError> {
+    let mut files = match target {
+        Target::Svm => svm::generate(spec, svm::Framework::Native)?,
+        Target::Anchor => svm::generate(spec, svm::Framework::Anchor)?,
+        Target::Pinocchio => svm::generate(spec, svm::Framework::Pinocchio)?,
+        Target::Evm => return evm::generate(spec),
+        Target::Rust => rust::generate(spec)?,
+    };
+    benches::add(spec, &mut files);
+    if !spec.prover.is_empty() {
+        files.push(GeneratedFile::new(
+            settings::RULES_TOML,
+            settings::rules_toml(&spec.prover),
+        ));
+    }
+    Ok(files)
+}
+
+/// Generates the files of the CPI interface crate of the program for `spec`,
+/// relative to the crate root, for Solana targets.
+pub fn cpi(spec: &Spec, target: Target) -> Result<Vec<GeneratedFile>, MaterializeError> {
+    let framework = match target {
+        Target::Svm => svm::Framework::Native,
+        Target::Anchor => svm::Framework::Anchor,
+        Target::Pinocchio => svm::Framework::Pinocchio,
+        Target::Evm | Target::Rust => {
+            return Err(MaterializeError::Unsupported {
+                location: "target".to_string(),
+                message: format!("{target} crates are not called through CPI"),
+            })
+        }
+    };
+    Ok(svm::cpi_files(spec, framework))
+}
+
+/// Where the example crate for `spec` lives, e.g.
+/// `examples/svm/materialized_vault`.
+pub fn crate_dir(examples_dir: &Path, spec: &Spec, target: Target) -> PathBuf {
+    examples_dir
+        .join(target.to_string())
+        .join(format!("materialized_{}", spec.name))
+}
+
+/// The unit tests compiled from the examples of the spec, relative to the
+/// crate root.
+pub const SPEC_TESTS_RS: &str = "src/spec_tests.rs";
+
+/// The property tests compiled from the invariants of the spec, relative to
+/// the crate root.
+pub const PROP_TESTS_RS: &str = "src/prop_tests.rs";
+
+/// The declaration of the module of [`PROP_TESTS_RS`] in `src/lib.rs`.
+const PROP_TESTS_MOD: &str = "\n#[cfg(test)]\nmod prop_tests;\n";
+
+/// The end-to-end tests of the program built for the SVM, relative to the
+/// crate root.
+pub const SVM_TESTS_RS: &str = "tests/svm_tests.rs";
+
+/// The measurements of the compute units of the instructions with a budget,
+/// relative to the crate root.
+pub const COMPUTE_UNITS_RS: &str = "tests/compute_units.rs";
+
+/// The instruction builders and state decoders of Solana programs for
+/// off-chain clients, relative to the crate root.
+pub const CLIENT_RS: &str = "src/client.rs";
+
+/// The TypeScript client package of Solana programs, relative to the crate
+/// root.
+pub const TS_CLIENT_DIR: &str = "clients/ts";
+
+/// The Python client package of Solana programs, relative to the crate
+/// root.
+pub const PY_CLIENT_DIR: &str = "clients/py";
+
+/// The CPI interface crate of Solana programs, relative to the crate root.
+pub const CPI_DIR: &str = "cpi";
+
+/// The IDL of Solana programs, relative to the crate root.
+pub const IDL_JSON: &str = "idl.json";
+
+/// The pure helpers of the spec, relative to the crate root.
+pub const HELPERS_RS: &str = "src/helpers.rs";
+
+/// The criterion benchmarks of the helpers of the spec, relative to the
+/// crate root.
+pub const BENCHES_RS: &str = "benches/helpers.rs";
+
+/// The cargo-fuzz crate with a target per instruction handler, relative to
+/// the crate root.
+pub const FUZZ_DIR: &str = "fuzz";
+
+/// The Kani proof harnesses of the rules of Rust crates, relative to the
+/// crate root.
+pub const PROOFS_RS: &str = "src/proofs.rs";
+
+/// The prover configuration in the manifest of the crates the Certora
+/// Solana Prover verifies, naming the [`certora_summaries`].
+pub const CERTORA_METADATA: &str = r#"[package.metadata.certora]
+sources = [
+    "Cargo.toml",
+    "src/**/*.rs"
+]
+solana_inlining = ["certora/summaries/cvlr_inlining_core.txt"]
+solana_summaries = ["certora/summaries/cvlr_summaries_core.txt"]
+"#;
+
+/// The inlining and summaries files [`CERTORA_METADATA`] names.
+pub fn certora_summaries() -> Vec<GeneratedFile> {
+    vec![
+        GeneratedFile::new(
+            "certora/summaries/cvlr_inlining_core.txt",
+            svm::CVLR_INLINING,
+        ),
+        GeneratedFile::new(
+            "certora/summaries/cvlr_summaries_core.txt",
+            svm::CVLR_SUMMARIES,
+        ),
+    ]
+}
+
+/// The declaration of the module of [`PROOFS_RS`] in `src/lib.rs`.
+const PROOFS_MOD: &str = "\n#[cfg(kani)]\nmod proofs;\n";
+
+/// Returns true if `path`, relative to the crate root, is compiled from the
+/// spec: the certora module, the Kani proof harnesses, the unit tests from
+/// its examples, the property tests from its invariants, the end-to-end
+/// tests, the compute-unit measurements, the benchmarks, the fuzz targets,
+/// the IDL and the clients.
+pub fn is_compiled(path: &Path) -> bool {
+    path == Path::new("src/certora.rs")
+        || path.starts_with("src/certora")
+        || path == Path::new(PROOFS_RS)
+        || path == Path::new(SPEC_TESTS_RS)
+        || path == Path::new(PROP_TESTS_RS)
+        || path == Path::new(SVM_TESTS_RS)
+        || path == Path::new(COMPUTE_UNITS_RS)
+        || path == Path::new(BENCHES_RS)
+        || path.starts_with(FUZZ_DIR)
+        || path == Path::new(IDL_JSON)
+        || path == Path::new(CLIENT_RS)
+        || path.starts_with(TS_CLIENT_DIR)
+        || path.starts_with(PY_CLIENT_DIR)
+}
+
+/// Regenerates the files of the existing crate in `dir` that are compiled
+/// from the spec, so that its rules and tests follow the current spec.
+pub fn write_compiled(spec: &Spec, target: Target, dir: &Path) -> Result<(), MaterializeError> {
+    let mut files: Vec<GeneratedFile> = generate(spec, target)?
+        .into_iter()
+        .filter(|file| is_compiled(&file.path))
+        .collect();
+    let provenance = Provenance::new(provenance::fingerprint(spec), None);
+    provenance::restamp_files(&mut files, dir, &provenance);
+    write(&files, dir, true)?;
+    let lib_rs = dir.join("src/lib.rs");
+    // Each is declared right after the state module, so in reverse order
+    for (path, cfg, name) in [
+        (PROOFS_RS, "kani", "proofs"),
+        (PROP_TESTS_RS, "test", "prop_tests"),
+        (SPEC_TESTS_RS, "test", "spec_tests"),
+    ] {
+        if files.iter().any(|file| file.path == Path::new(path)) {
+            declare_module(&lib_rs, cfg, name).map_err(|source| MaterializeError::Io {
+                path: lib_rs.clone(),
+                source,
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Declares the module `name`, compiled under `#[cfg({cfg})]`, in the
+/// `lib.rs` at `path` unless it is declared already.
+pub(crate) fn declare_module(path: &Path, cfg: &str, name: &str) -> std::io::Result<()> {
+    let lib = std::fs::read_to_string(path)?;
+    if lib.contains(&format!("mod {name};")) {
+        return Ok(());
+    }
+    let declaration = format!("\n#[cfg({cfg})]\nmod {name};\n");
+    let lib = match lib.find("pub mod state;\n") {
+        Some(at) => {
+            let at = at + "pub mod state;\n".len();
+            format!("{}{declaration}{}", &lib[..at], &lib[at..])
+        }
+        None => format!("{lib}{declaration}"),
+    };
+    std::fs::write(path, lib)
+}
+
+/// Writes `files` under `dir`, which must not exist unless `force` is set.
+/// Rust sources are formatted with `rustfmt` when it is installed.
+pub fn write(files: &[GeneratedFile], dir: &Path, force: bool) -> Result<(), MaterializeError> {
+    if dir.exists() && !force {
+        return Err(MaterializeError::Exists(dir.to_path_buf()));
+    }
+    let mut sources = Vec::new();
+    for file in files {
+        let path = dir.join(&file.path);
+        let io = |source| MaterializeError::Io {
+            path: path.clone(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io)?;
+        }
+        std::fs::write(&path, &file.contents).map_err(io)?;
+        if path.extension().is_some_and(|ext| ext == "rs") {
+            sources.push(path);
+        }
+    }
+    // Formatting is cosmetic, so a missing or failing rustfmt is not an error
+    let _ = Command::new("rustfmt")
+        .args(["--edition", "2021"])
+        .args(&sources)
+        .status();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    fn file<'a>(files: &'a [GeneratedFile], path: &str) -> &'a str {
+        &files
+            .iter()
+            .find(|file| file.path == Path::new(path))
+            .unwrap()
+            .contents
+    }
+
+    #[test]
+    fn test_generate_vault() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let files = generate(&spec, Target::Svm).unwrap();
+        let paths: Vec<_> = files.iter().map(|f| f.path.to_str().unwrap()).collect();
+        assert_eq!(
+            paths,
+            [
+                "Cargo.toml",
+                "src/lib.rs",
+                "src/state.rs",
+                "src/processor.rs",
+                "src/certora.rs",
+                "src/certora/spec.rs",
+                "src/proofs.rs",
+                "src/client.rs",
+                "certora/summaries/cvlr_inlining_core.txt",
+                "certora/summaries/cvlr_summaries_core.txt",
+                "idl.json",
+                "src/spec_tests.rs",
+                "src/prop_tests.rs",
+                "tests/svm_tests.rs",
+                "fuzz/Cargo.toml",
+                "fuzz/.gitignore",
+                "fuzz/fuzz_targets/deposit.rs",
+                "fuzz/fuzz_targets/withdraw.rs",
+                "fuzz/fuzz_targets/reward.rs",
+                "clients/ts/package.json",
+                "clients/ts/tsconfig.json",
+                "clients/ts/.gitignore",
+                "clients/ts/src/index.ts",
+                "clients/py/pyproject.toml",
+                "clients/py/vault_client/__init__.py",
+            ]
+        );
+        assert!(file(&files, "Cargo.toml").contains("name = \"solana-vault\""));
+        assert!(file(&files, "src/lib.rs").contains(&program_id("vault")));
+        assert!(file(&files, "src/state.rs").contains("pub token_total: PodU64,"));
+
+        let processor = file(&files, "src/processor.rs");
+        // Preconditions become negated checks, postconditions comments
+        assert!(processor.contains("if amount == 0 {"));
+        assert!(processor.contains("if shares > u64::from(vault.shares_total) {"));
+        assert!(processor.contains("// - vault.token_total == old(vault.token_total) + amount"));
+        // Accounts the preconditions do not read are left unused
+        assert!(processor.contains("let _vault: &mut Vault"));
+        let rules = file(&files, "src/certora/spec.rs");
+        assert!(rules.contains("pub fn rule_withdraw_sanity()"));
+        assert!(rules.contains("pub fn rule_withdraw_ensures()"));
+        assert!(file(&files, "src/certora.rs").contains("macro_rules! assert_solvency"));
+    }
+
+    #[test]
+    fn test_generate_anchor() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let files = generate(&spec, Target::Anchor).unwrap();
+        assert!(file(&files, "Cargo.toml").contains("anchor-lang = \"0.30\""));
+        let lib = file(&files, "src/lib.rs");
+        assert!(lib.contains(&format!("declare_id!(\"{}\");", program_id("vault"))));
+        assert!(lib.contains("#[program]\npub mod vault {"));
+        assert!(lib.contains(
+            "pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {\n        \
+             processor::process_deposit(&ctx.accounts.to_account_infos(), &amount.to_le_bytes())?;"
+        ));
+        assert!(lib.contains("    #[account(mut)]\n    pub vault: AccountLoader<'info, Vault>,"));
+        assert!(file(&files, "src/state.rs").contains("#[account(zero_copy)]\n"));
+        // The state follows the discriminator of the account data
+        assert!(file(&files, "src/processor.rs")
+            .contains("bytemuck::from_bytes_mut(&mut vault_data[8..])"));
+        assert!(file(&files, "src/certora/spec.rs")
+            .contains("*bytemuck::from_bytes(&vault_account.data.borrow()[8..])"));
+        assert!(
+            file(&files, SPEC_TESTS_RS).contains("[[0; 8].as_slice(), bytemuck::bytes_of(&vault)]")
+        );
+
+        let spec = Spec::from_yaml_str(
+            "name: pair\ntarget: anchor\n\
+             state: [{ name: Pool, fields: [{ name: total, type: u64 }] }]\n\
+             functions:\n  - name: swap\n    accounts:\n\
+             \x20     - { name: authority, signer: true }\n\
+             \x20     - { name: from, type: Pool, writable: true }\n\
+             \x20     - { name: to, type: Pool, writable: true }\n\
+             \x20     - { name: oracle }\n",
+        )
+        .unwrap();
+        let lib = file(&generate(&spec, spec.target).unwrap(), "src/lib.rs").to_string();
+        assert!(lib.contains("pub authority: Signer<'info>,"));
+        assert!(lib.contains(
+            "#[account(mut, constraint = to.key() != from.key())]\n    pub to: AccountLoader<'info, Pool>,"
+        ));
+        assert!(lib.contains("/// CHECK: only its key is read by the processor\n    pub oracle: UncheckedAccount<'info>,"));
+    }
+
+    #[test]
+    fn test_generate_pinocchio() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let files = generate(&spec, Target::Pinocchio).unwrap();
+        assert!(file(&files, "Cargo.toml").contains("pinocchio = \"0.8\""));
+        assert!(file(&files, "src/account.rs").contains("pub trait Account {"));
+        let lib = file(&files, "src/lib.rs");
+        assert!(lib.contains(&format!(
+            "pinocchio_pubkey::declare_id!(\"{}\");",
+            program_id("vault")
+        )));
+        assert!(lib.contains("pub mod account;"));
+        assert!(file(&files, "src/state.rs").contains("use pinocchio::pubkey::Pubkey;"));
+        let processor = file(&files, "src/processor.rs");
+        assert!(processor.contains(
+            "pub fn process_deposit<A: Account>(accounts: &[A], instruction_data: &[u8]) -> ProgramResult {"
+        ));
+        assert!(processor.contains("let mut vault_data = vault_account.data_mut()?;"));
+        assert_eq!(crate::drift::marked(processor).len(), 3);
+        // The rules and tests run against solana-program's accounts
+        assert!(file(&files, "src/certora/spec.rs")
+            .contains("*bytemuck::from_bytes(&vault_account.data.borrow())"));
+        assert!(file(&files, SPEC_TESTS_RS)
+            .contains("let program_id = Pubkey::new_from_array(crate::id());"));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let spec = Spec::from_yaml_str(
+            "name: wide\nstate: [{ name: Wide, fields: [{ name: x, type: u128 }] }]",
+        )
+        .unwrap();
+        let err = generate(&spec, Target::Svm).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "state.Wide.x: u128 has no zero-copy representation on SVM"
+        );
+    }
+
+    #[test]
+    fn test_write_compiled() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/processor.rs"), "// implemented\n").unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "pub mod state;\n").unwrap();
+        write_compiled(&spec, Target::Svm, dir.path()).unwrap();
+        let rules = std::fs::read_to_string(dir.path().join("src/certora/spec.rs")).unwrap();
+        assert!(rules.contains("fn rule_deposit_solvency()"));
+        assert!(dir.path().join("src/certora.rs").exists());
+        assert!(dir.path().join(SPEC_TESTS_RS).exists());
+        let lib = std::fs::read_to_string(dir.path().join("src/lib.rs")).unwrap();
+        assert_eq!(
+            lib,
+            "pub mod state;\n\n#[cfg(test)]\nmod spec_tests;\n\n#[cfg(test)]\nmod prop_tests;\n\n\
+             #[cfg(kani)]\nmod proofs;\n"
+        );
+        // The implementation is left alone
+        let processor = std::fs::read_to_string(dir.path().join("src/processor.rs")).unwrap();
+        assert_eq!(processor, "// implemented\n");
+        assert!(!dir.path().join("Cargo.toml").exists());
+
+        // Declaring the module again leaves lib.rs unchanged
+        write_compiled(&spec, Target::Svm, dir.path()).unwrap();
+        let again = std::fs::read_to_string(dir.path().join("src/lib.rs")).unwrap();
+        assert_eq!(again, lib);
+        assert!(is_compiled(Path::new("src/certora/spec.rs")));
+        assert!(is_compiled(Path::new(PROOFS_RS)));
+        assert!(is_compiled(Path::new(PROP_TESTS_RS)));
+        assert!(!is_compiled(Path::new("src/certora_helpers.rs")));
+    }
+
+    #[test]
+    fn test_write_refuses_existing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [GeneratedFile::new("src/lib.rs", "pub fn f() {}\n")];
+        assert!(matches!(
+            write(&files, dir.path(), false),
+            Err(MaterializeError::Exists(_))
+        ));
+        write(&files, dir.path(), true).unwrap();
+        let written = std::fs::read_to_string(dir.path().join("src/lib.rs")).unwrap();
+        assert_eq!(written, "pub fn f() {}\n");
+    }
+}
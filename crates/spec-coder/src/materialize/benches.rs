@@ -0,0 +1,230 @@
+//! Criterion benchmarks of the helpers of the spec.
+//!
+//! A spec may declare pure helpers, such as share conversions and
+//! fixed-point operations, that the functions are implemented with. A crate
+//! with helpers gets them scaffolded in `src/helpers.rs`, for the model to
+//! implement like the processor, and `benches/helpers.rs`, compiled from
+//! the spec, benchmarks each with criterion on the arguments its `bench`
+//! gives. Repair iterations that make the math slower then show up in
+//! `cargo bench`, and [`throughput`](crate::throughput) checks the helpers
+//! with a target against it. Helpers are the same for every target, so the
+//! files are added to the crate of any target after it is generated.
+
+use super::{GeneratedFile, BENCHES_RS, HELPERS_RS};
+use spec::{Helper, Spec, Type, Value};
+
+/// The declaration of the helpers module in `src/lib.rs`.
+const HELPERS_MOD: &str = "pub mod helpers;\n";
+
+/// The Rust type of a helper argument or result.
+fn rust_type(ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".to_string(),
+        ty => ty.to_string(),
+    }
+}
+
+/// The literal of `value` for an argument of type `ty`.
+fn literal(value: Value, ty: &Type) -> String {
+    match (value, ty) {
+        (Value::Bool(value), _) => value.to_string(),
+        (Value::Int(value), ty) => format!("{value}{ty}"),
+        (Value::Max, ty) => format!("{ty}::MAX"),
+        (Value::Min, ty) => format!("{ty}::MIN"),
+    }
+}
+
+fn signature(helper: &Helper) -> String {
+    let args: Vec<String> = helper
+        .args
+        .iter()
+        .map(|arg| format!("{}: {}", arg.name, rust_type(&arg.ty)))
+        .collect();
+    format!(
+        "pub fn {}({}) -> {}",
+        helper.name,
+        args.join(", "),
+        rust_type(&helper.returns)
+    )
+}
+
+/// The scaffold of `src/helpers.rs`, with every helper left to implement.
+fn helpers_rs(spec: &Spec) -> String {
+    let mut out = String::from(
+        "//! Pure helpers of the implementation, benchmarked by `benches/helpers.rs`.\n",
+    );
+    for helper in &spec.helpers {
+        out.push('\n');
+        if let Some(description) = &helper.description {
+            for line in description.trim().lines() {
+                out.push_str(&format!("/// {line}\n").replace("/// \n", "///\n"));
+            }
+        }
+        if let Some(throughput) = helper.throughput {
+            if helper.description.is_some() {
+                out.push_str("///\n");
+            }
+            out.push_str(&format!(
+                "/// Must sustain at least {throughput} calls per second.\n"
+            ));
+        }
+        out.push_str(&signature(helper));
+        out.push_str(" {\n");
+        if !helper.args.is_empty() {
+            let names: Vec<&str> = helper.args.iter().map(|arg| arg.name.as_str()).collect();
+            let names = match names[..] {
+                [name] => name.to_string(),
+                _ => format!("({})", names.join(", ")),
+            };
+            out.push_str(&format!("    let _ = {names};\n"));
+        }
+        out.push_str(&format!("    todo!(\"implement `{}`\")\n}}\n", helper.name));
+    }
+    out
+}
+
+/// `benches/helpers.rs`, calling every helper of `spec` from the library
+/// `krate`.
+fn benches_rs(spec: &Spec, krate: &str) -> String {
+    let mut out = String::from(
+        "//! Benchmarks of the helpers of the spec.\n//!\n\
+         //! Run with `cargo bench --bench helpers`.\n\n\
+         use criterion::{black_box, criterion_group, criterion_main, Criterion};\n",
+    );
+    out.push_str(&format!(
+        "use {krate}::helpers;\n\nfn helpers(c: &mut Criterion) {{\n"
+    ));
+    for helper in &spec.helpers {
+        let args: Vec<String> = helper
+            .args
+            .iter()
+            .map(|arg| {
+                let value = helper.bench.get(&arg.name).copied();
+                let value = match (value, &arg.ty) {
+                    (Some(value), ty) => literal(value, ty),
+                    (None, Type::Bool) => "false".to_string(),
+                    (None, ty) => literal(Value::Int(0), ty),
+                };
+                format!("black_box({value})")
+            })
+            .collect();
+        out.push_str(&format!(
+            "    c.bench_function(\"{0}\", |b| b.iter(|| helpers::{0}({1})));\n",
+            helper.name,
+            args.join(", ")
+        ));
+    }
+    out.push_str("}\n\ncriterion_group!(benches, helpers);\ncriterion_main!(benches);\n");
+    out
+}
+
+/// Adds the helpers of `spec`, if it has any, to the generated `files`:
+/// their scaffold and benchmarks, the module declaration and the bench
+/// target with its dependency on criterion.
+pub(super) fn add(spec: &Spec, files: &mut Vec<GeneratedFile>) {
+    if spec.helpers.is_empty() {
+        return;
+    }
+    let mut krate = None;
+    for file in files.iter_mut() {
+        match file.path.to_str() {
+            Some("Cargo.toml") => {
+                krate = file
+                    .contents
+                    .lines()
+                    .find_map(|line| line.strip_prefix("name = \"")?.strip_suffix('"'))
+                    .map(|name| name.replace('-', "_"));
+                file.contents = file.contents.replacen(
+                    "[dev-dependencies]\n",
+                    "[dev-dependencies]\ncriterion = \"0.5\"\n",
+                    1,
+                );
+                file.contents
+                    .push_str("\n[[bench]]\nname = \"helpers\"\nharness = false\n");
+            }
+            Some("src/lib.rs") => {
+                file.contents = file.contents.replacen(
+                    "pub mod processor;\n",
+                    &format!("{HELPERS_MOD}pub mod processor;\n"),
+                    1,
+                );
+            }
+            _ => {}
+        }
+    }
+    let krate = krate.unwrap_or_else(|| spec.name.clone());
+    files.push(GeneratedFile::new(HELPERS_RS, helpers_rs(spec)));
+    files.push(GeneratedFile::new(BENCHES_RS, benches_rs(spec, &krate)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materialize;
+    use spec::Target;
+
+    const VAULT: &str = include_str!("../../../../examples/specs/vault.yaml");
+
+    const HELPERS: &str = "
+helpers:
+- name: shares_for_deposit
+  description: The shares minted for a deposit of `amount`.
+  args:
+    - { name: amount, type: u64 }
+    - { name: shares_total, type: u64 }
+    - { name: token_total, type: u64 }
+  returns: u64
+  bench: { amount: 1000, shares_total: max }
+  throughput: 50000000
+- name: is_dust
+  args: [{ name: amount, type: u64 }]
+  returns: bool
+";
+
+    fn file<'a>(files: &'a [GeneratedFile], path: &str) -> &'a str {
+        &files
+            .iter()
+            .find(|file| file.path == std::path::Path::new(path))
+            .unwrap()
+            .contents
+    }
+
+    #[test]
+    fn test_helpers() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let files = materialize::generate(&spec, Target::Svm).unwrap();
+        assert!(!files
+            .iter()
+            .any(|file| file.path == std::path::Path::new(BENCHES_RS)));
+
+        let spec = Spec::from_yaml_str(&format!("{VAULT}{HELPERS}")).unwrap();
+        for target in [Target::Svm, Target::Anchor, Target::Rust] {
+            let files = materialize::generate(&spec, target).unwrap();
+            let cargo = file(&files, "Cargo.toml");
+            assert!(cargo.contains("[dev-dependencies]\ncriterion = \"0.5\"\n"));
+            assert!(cargo.ends_with("[[bench]]\nname = \"helpers\"\nharness = false\n"));
+            assert!(file(&files, "src/lib.rs").contains("pub mod helpers;\npub mod processor;\n"));
+        }
+
+        let files = materialize::generate(&spec, Target::Svm).unwrap();
+        assert_eq!(
+            file(&files, HELPERS_RS),
+            "//! Pure helpers of the implementation, benchmarked by `benches/helpers.rs`.\n\n\
+             /// The shares minted for a deposit of `amount`.\n///\n\
+             /// Must sustain at least 50000000 calls per second.\n\
+             pub fn shares_for_deposit(amount: u64, shares_total: u64, token_total: u64) -> u64 {\n    \
+             let _ = (amount, shares_total, token_total);\n    \
+             todo!(\"implement `shares_for_deposit`\")\n}\n\n\
+             pub fn is_dust(amount: u64) -> bool {\n    let _ = amount;\n    \
+             todo!(\"implement `is_dust`\")\n}\n"
+        );
+        let benches = file(&files, BENCHES_RS);
+        assert!(benches.contains("use solana_vault::helpers;\n"));
+        assert!(benches.contains(
+            "helpers::shares_for_deposit(black_box(1000u64), black_box(u64::MAX), black_box(0u64))"
+        ));
+        assert!(benches.contains("helpers::is_dust(black_box(0u64))"));
+        assert!(materialize::is_compiled(std::path::Path::new(BENCHES_RS)));
+        assert!(!materialize::is_compiled(std::path::Path::new(HELPERS_RS)));
+    }
+}
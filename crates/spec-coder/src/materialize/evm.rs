@@ -0,0 +1,705 @@
+//! Generator for EVM programs: a Solidity contract and its CVL spec.
+//!
+//! Every state type becomes a struct held once in contract storage, under
+//! the name invariants use for it, and every function an external function
+//! that reverts with `InvalidArgument` when a precondition does not hold,
+//! its postconditions left as comments. The signer of a function is the
+//! caller, other untyped accounts are passed as addresses.
+//!
+//! The CVL spec has, per function, a sanity rule and a rule asserting its
+//! postconditions, reading storage through `currentContract`, and a CVL
+//! invariant per invariant, which the prover checks against every function.
+//! `certora/conf/default.conf` verifies the contract against it with
+//! `certoraRun`.
+
+use super::svm::{doc, negate, pascal_case, with_article};
+use super::{GeneratedFile, MaterializeError};
+use spec::{AccountInput, Arithmetic, BinaryOp, Expr, Function, Spec, Type, UnaryOp};
+
+const PRAGMA: &str = "pragma solidity ^0.8.20;";
+
+/// Generates every file of the example project for `spec`.
+pub fn generate(spec: &Spec) -> Result<Vec<GeneratedFile>, MaterializeError> {
+    for function in &spec.functions {
+        check_function(spec, function)?;
+    }
+    let contract = contract_name(spec);
+    Ok(vec![
+        GeneratedFile::new("foundry.toml", FOUNDRY_TOML),
+        GeneratedFile::new(&format!("src/{contract}.sol"), contract_sol(spec)?),
+        GeneratedFile::new(&spec_path(spec), spec_cvl(spec)?),
+        GeneratedFile::new("certora/conf/default.conf", conf(spec)),
+    ])
+}
+
+const FOUNDRY_TOML: &str = r#"[profile.default]
+src = "src"
+out = "out"
+"#;
+
+/// The contract name, suffixed when it would clash with a state type.
+fn contract_name(spec: &Spec) -> String {
+    let name = pascal_case(&spec.name);
+    if spec.account(&name).is_some() {
+        format!("{name}Program")
+    } else {
+        name
+    }
+}
+
+/// The CVL spec, relative to the project root.
+fn spec_path(spec: &Spec) -> String {
+    format!("certora/specs/{}.spec", spec.name)
+}
+
+/// Rejects functions whose accounts have no EVM counterpart: contract
+/// storage holds one value of each state type and a call has one sender.
+fn check_function(spec: &Spec, function: &Function) -> Result<(), MaterializeError> {
+    let unsupported = |location: String, message: String| MaterializeError::Unsupported {
+        location: format!("functions.{}.{location}", function.name),
+        message,
+    };
+    for arg in &function.args {
+        if int_type(&arg.ty).is_none() && !matches!(arg.ty, Type::Bool | Type::Pubkey) {
+            return Err(unsupported(
+                arg.name.clone(),
+                format!("`{}` arguments are not supported", arg.ty),
+            ));
+        }
+    }
+    for (i, account) in function.accounts.iter().enumerate() {
+        let earlier = &function.accounts[..i];
+        if let Some(ty) = &account.ty {
+            if spec.account(ty).is_none() {
+                return Err(unsupported(
+                    account.name.clone(),
+                    format!("unknown state type `{ty}`"),
+                ));
+            }
+            if earlier.iter().any(|other| other.ty == account.ty) {
+                return Err(unsupported(
+                    account.name.clone(),
+                    format!("EVM contracts hold a single `{ty}`"),
+                ));
+            }
+        } else if account.signer && earlier.iter().any(|a| a.ty.is_none() && a.signer) {
+            return Err(unsupported(
+                account.name.clone(),
+                "EVM calls have a single signer, the caller".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The Solidity integer type of `ty`.
+fn int_type(ty: &Type) -> Option<&'static str> {
+    Some(match ty {
+        Type::U8 => "uint8",
+        Type::U16 => "uint16",
+        Type::U32 => "uint32",
+        Type::U64 => "uint64",
+        Type::I64 => "int64",
+        Type::U128 => "uint128",
+        _ => return None,
+    })
+}
+
+/// The Solidity type of `ty`.
+fn solidity_type(ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".to_string(),
+        Type::Pubkey => "address".to_string(),
+        Type::Array(inner, len) => format!("{}[{len}]", solidity_type(inner)),
+        ty => int_type(ty).unwrap_or("uint256").to_string(),
+    }
+}
+
+/// The language an expression is translated into.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Dialect {
+    Solidity,
+    Cvl,
+}
+
+/// The type of a translated expression.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Ty {
+    Bool,
+    Address,
+    /// An integer of the given Solidity type
+    Int(&'static str),
+}
+
+/// A translated expression.
+struct Code {
+    code: String,
+    ty: Ty,
+    /// Whether the code is a leaf that needs no parentheses
+    atomic: bool,
+}
+
+impl Code {
+    fn leaf(code: String, ty: Ty) -> Code {
+        Code {
+            code,
+            ty,
+            atomic: true,
+        }
+    }
+
+    fn parenthesized(&self) -> String {
+        if self.atomic {
+            self.code.clone()
+        } else {
+            format!("({})", self.code)
+        }
+    }
+
+    /// The code widened to `int256`, wide enough for arithmetic over any
+    /// two supported integers.
+    fn wide(&self) -> String {
+        match self.ty {
+            Ty::Int("int256" | "literal") => self.parenthesized(),
+            Ty::Int(ty) if ty.starts_with('u') => format!("int256(uint256({}))", self.code),
+            _ => format!("int256({})", self.code),
+        }
+    }
+}
+
+/// Translates conditions into Solidity over the parameters and storage of
+/// the contract, or into CVL over the rule parameters and `currentContract`.
+#[derive(Copy, Clone)]
+struct Translator<'a> {
+    spec: &'a Spec,
+    function: &'a Function,
+    dialect: Dialect,
+    /// Whether `old(..)` is allowed, storage read inside it being bound
+    /// before the call with an `old_` prefix
+    postcondition: bool,
+    /// Whether the expression is inside `old(..)`
+    old: bool,
+}
+
+impl Translator<'_> {
+    fn path(&self, path: &[String]) -> Result<Code, String> {
+        let root = &path[0];
+        if let Some(arg) = self.function.arg(root) {
+            return Ok(Code::leaf(root.clone(), leaf_type(&arg.ty)?));
+        }
+        let account = self.function.account(root).ok_or("unknown name")?;
+        let Some(field) = path.get(1) else {
+            return match (&account.ty, account.signer) {
+                (None, true) => Ok(Code::leaf(self.sender().to_string(), Ty::Address)),
+                (None, false) => Ok(Code::leaf(root.clone(), Ty::Address)),
+                (Some(_), _) => Err("state accounts have no address on EVM".to_string()),
+            };
+        };
+        let ty = account.ty.as_deref().ok_or("untyped account")?;
+        let state = self.spec.account(ty).ok_or("unknown field")?;
+        let field = state.field(field).ok_or("unknown field")?;
+        let storage = state.binding();
+        let code = match (self.dialect, self.old) {
+            (Dialect::Solidity, _) => format!("{storage}.{}", field.name),
+            (Dialect::Cvl, false) => format!("currentContract.{storage}.{}", field.name),
+            (Dialect::Cvl, true) => format!("old_{storage}_{}", field.name),
+        };
+        Ok(Code::leaf(code, leaf_type(&field.ty)?))
+    }
+
+    fn sender(&self) -> &'static str {
+        match self.dialect {
+            Dialect::Solidity => "msg.sender",
+            Dialect::Cvl => "e.msg.sender",
+        }
+    }
+
+    fn expr(&self, expr: &Expr) -> Result<Code, String> {
+        Ok(match expr {
+            Expr::Int(value) => Code::leaf(value.to_string(), Ty::Int("literal")),
+            Expr::Bool(value) => Code::leaf(value.to_string(), Ty::Bool),
+            Expr::Path(path) => self.path(path)?,
+            Expr::Old(inner) if self.postcondition => {
+                Translator { old: true, ..*self }.expr(inner)?
+            }
+            Expr::Old(_) => return Err("`old(..)` is only allowed in postconditions".into()),
+            Expr::Unary(UnaryOp::Not, inner) => {
+                let inner = self.expr(inner)?;
+                Code {
+                    code: format!("!{}", inner.parenthesized()),
+                    ty: Ty::Bool,
+                    atomic: true,
+                }
+            }
+            Expr::Binary(BinaryOp::Implies, lhs, rhs) if self.dialect == Dialect::Solidity => {
+                let expr = Expr::binary(BinaryOp::Or, negate(lhs), (**rhs).clone());
+                self.expr(&expr)?
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+                if let Some(code) = unsigned_zero_comparison(*op, &lhs, &rhs) {
+                    return Ok(code);
+                }
+                let symbol = match op {
+                    BinaryOp::Implies => "=>",
+                    op => op.symbol(),
+                };
+                // CVL computes over unbounded integers, so only Solidity
+                // needs operands of one type
+                let (lhs, rhs, ty) = match op {
+                    _ if self.dialect == Dialect::Cvl => {
+                        let ty = if op.is_boolean() {
+                            Ty::Bool
+                        } else {
+                            Ty::Int("int256")
+                        };
+                        (lhs.parenthesized(), rhs.parenthesized(), ty)
+                    }
+                    BinaryOp::And | BinaryOp::Or => {
+                        (lhs.parenthesized(), rhs.parenthesized(), Ty::Bool)
+                    }
+                    _ if op.is_boolean() => match (lhs.ty, rhs.ty) {
+                        (l, r) if l == r || l == Ty::Int("literal") || r == Ty::Int("literal") => {
+                            (lhs.parenthesized(), rhs.parenthesized(), Ty::Bool)
+                        }
+                        (Ty::Int(_), Ty::Int(_)) => (lhs.wide(), rhs.wide(), Ty::Bool),
+                        _ => return Err(format!("mismatched operands of `{}`", op.symbol())),
+                    },
+                    _ => (lhs.wide(), rhs.wide(), Ty::Int("int256")),
+                };
+                Code {
+                    code: format!("{lhs} {symbol} {rhs}"),
+                    ty,
+                    atomic: false,
+                }
+            }
+        })
+    }
+}
+
+/// Rewrites `value <= 0` over an unsigned value, which only holds at zero,
+/// as `value == 0`.
+fn unsigned_zero_comparison(op: BinaryOp, lhs: &Code, rhs: &Code) -> Option<Code> {
+    let unsigned = |code: &Code| matches!(code.ty, Ty::Int(ty) if ty.starts_with('u'));
+    let value = match op {
+        BinaryOp::Le if unsigned(lhs) && rhs.code == "0" => lhs,
+        BinaryOp::Ge if lhs.code == "0" && unsigned(rhs) => rhs,
+        _ => return None,
+    };
+    Some(Code {
+        code: format!("{} == 0", value.parenthesized()),
+        ty: Ty::Bool,
+        atomic: false,
+    })
+}
+
+/// The type of an argument or field read in an expression.
+fn leaf_type(ty: &Type) -> Result<Ty, String> {
+    match ty {
+        Type::Bool => Ok(Ty::Bool),
+        Type::Pubkey => Ok(Ty::Address),
+        ty => int_type(ty)
+            .map(Ty::Int)
+            .ok_or_else(|| format!("`{ty}` fields cannot be used in expressions")),
+    }
+}
+
+/// The parameters of `function` in Solidity: the addresses of its untyped
+/// accounts other than the caller, then its arguments.
+fn parameters(function: &Function) -> Vec<(String, String)> {
+    let accounts = function
+        .accounts
+        .iter()
+        .filter(|account| account.ty.is_none() && !account.signer)
+        .map(|account| ("address".to_string(), account.name.clone()));
+    let args = function
+        .args
+        .iter()
+        .map(|arg| (solidity_type(&arg.ty), arg.name.clone()));
+    accounts.chain(args).collect()
+}
+
+fn unsupported(location: String) -> impl FnOnce(String) -> MaterializeError {
+    move |message| MaterializeError::Unsupported { location, message }
+}
+
+fn contract_sol(spec: &Spec) -> Result<String, MaterializeError> {
+    let mut out = format!("// SPDX-License-Identifier: UNLICENSED\n{PRAGMA}\n\n");
+    let title = format!("The {} program.", spec.name.replace('_', " "));
+    doc(
+        &mut out,
+        "",
+        &format!(
+            "@title {}",
+            spec.description.as_deref().unwrap_or(&title).trim()
+        ),
+    );
+    out.push_str(&format!("contract {} {{\n", contract_name(spec)));
+    for account in &spec.state {
+        let description = account
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("The {} account data structure.", account.name));
+        doc(&mut out, "    ", &description);
+        out.push_str(&format!("    struct {} {{\n", account.name));
+        for field in &account.fields {
+            if let Some(description) = &field.description {
+                doc(&mut out, "        ", description);
+            }
+            out.push_str(&format!(
+                "        {} {};\n",
+                solidity_type(&field.ty),
+                field.name
+            ));
+        }
+        out.push_str("    }\n\n");
+    }
+    out.push_str("    /// A precondition of the called function does not hold.\n");
+    out.push_str("    error InvalidArgument();\n");
+    for account in &spec.state {
+        out.push_str(&format!(
+            "\n    {} public {};\n",
+            account.name,
+            account.binding()
+        ));
+    }
+
+    for function in &spec.functions {
+        let translator = Translator {
+            spec,
+            function,
+            dialect: Dialect::Solidity,
+            postcondition: false,
+            old: false,
+        };
+        out.push('\n');
+        out.push_str(&format!("    {}\n", crate::drift::marker(function)));
+        let description = function
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("Process {} instruction.", with_article(&function.name)));
+        doc(&mut out, "    ", &description);
+        let params: Vec<String> = parameters(function)
+            .into_iter()
+            .map(|(ty, name)| format!("{ty} {name}"))
+            .collect();
+        let writes = function
+            .accounts
+            .iter()
+            .any(|account| account.ty.is_some() && account.writable);
+        out.push_str(&format!(
+            "    function {}({}) external{} {{\n",
+            function.name,
+            params.join(", "),
+            if writes { "" } else { " view" }
+        ));
+        for (i, condition) in function.requires.iter().enumerate() {
+            let check = translator
+                .expr(&negate(&condition.expr))
+                .map_err(unsupported(format!(
+                    "functions.{}.requires[{i}]",
+                    function.name
+                )))?;
+            out.push_str(&format!(
+                "        if ({}) revert InvalidArgument();\n",
+                check.code
+            ));
+        }
+        if !function.ensures.is_empty() {
+            if !function.requires.is_empty() {
+                out.push('\n');
+            }
+            out.push_str("        // Establish the postconditions:\n");
+            for condition in &function.ensures {
+                out.push_str(&format!("        // - {}\n", condition.expr));
+                if let Some(description) = &condition.description {
+                    out.push_str(&format!("        //   {description}\n"));
+                }
+            }
+        }
+        out.push_str("    }\n");
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// Declares the storage that `exprs` read inside `old(..)`, as it is before
+/// the call.
+fn bind_old(out: &mut String, spec: &Spec, function: &Function, exprs: &[&Expr]) {
+    let mut bound = Vec::new();
+    for expr in exprs {
+        expr.visit_paths(&mut |path, in_old| {
+            let [root, field] = path else { return };
+            let Some(state) = function
+                .account(root)
+                .and_then(|account| spec.account(account.ty.as_deref()?))
+            else {
+                return;
+            };
+            let Some(field) = state.field(field) else {
+                return;
+            };
+            let storage = state.binding();
+            let name = format!("old_{storage}_{}", field.name);
+            if !in_old || bound.contains(&name) {
+                return;
+            }
+            let ty = match field.ty {
+                Type::Bool => "bool",
+                Type::Pubkey => "address",
+                _ => "mathint",
+            };
+            out.push_str(&format!(
+                "    {ty} {name} = currentContract.{storage}.{};\n",
+                field.name
+            ));
+            bound.push(name);
+        });
+    }
+}
+
+/// The head of a rule calling `function`, with the environment and the
+/// parameters of the call as rule parameters.
+fn rule_head(out: &mut String, doc: &str, name: &str, function: &Function) {
+    let mut params = vec!["env e".to_string()];
+    params.extend(
+        parameters(function)
+            .into_iter()
+            .map(|(ty, name)| format!("{ty} {name}")),
+    );
+    out.push_str(&format!(
+        "\n/// {doc}\nrule {name}({}) {{\n",
+        params.join(", ")
+    ));
+}
+
+/// The call of `function` with the rule parameters, which only continues if
+/// it does not revert.
+fn call(function: &Function) -> String {
+    let mut args = vec!["e".to_string()];
+    args.extend(parameters(function).into_iter().map(|(_, name)| name));
+    format!("    {}({});\n", function.name, args.join(", "))
+}
+
+fn spec_cvl(spec: &Spec) -> Result<String, MaterializeError> {
+    let mut out = format!(
+        "// Rules for the {} program, compiled from its spec.\n",
+        spec.name.replace('_', " ")
+    );
+    for function in &spec.functions {
+        rule_head(
+            &mut out,
+            &format!(
+                "Verifies that {} can succeed.",
+                with_article(&function.name)
+            ),
+            &format!("rule_{}_sanity", function.name),
+            function,
+        );
+        out.push_str(&call(function));
+        out.push_str("    satisfy true;\n}\n");
+
+        if function.ensures.is_empty() {
+            continue;
+        }
+        let translator = Translator {
+            spec,
+            function,
+            dialect: Dialect::Cvl,
+            postcondition: true,
+            old: false,
+        };
+        let mut asserts = String::new();
+        for (i, condition) in function.ensures.iter().enumerate() {
+            let code = translator
+                .expr(&condition.expr)
+                .map_err(unsupported(format!(
+                    "functions.{}.ensures[{i}]",
+                    function.name
+                )))?;
+            asserts.push_str(&format!(
+                "    assert {}, \"postcondition `{}` violated\";\n",
+                code.code, condition.expr
+            ));
+        }
+        rule_head(
+            &mut out,
+            &format!(
+                "Verifies the postconditions of {}.",
+                with_article(&function.name)
+            ),
+            &format!("rule_{}_ensures", function.name),
+            function,
+        );
+        let exprs: Vec<&Expr> = function.ensures.iter().map(|c| &c.expr).collect();
+        bind_old(&mut out, spec, function, &exprs);
+        out.push('\n');
+        out.push_str(&call(function));
+        out.push('\n');
+        out.push_str(&asserts);
+        out.push_str("}\n");
+    }
+
+    for invariant in &spec.invariants {
+        // Translated as a condition over accounts named by their bindings
+        let function = Function {
+            name: invariant.name.clone(),
+            description: None,
+            accounts: spec
+                .state
+                .iter()
+                .map(|account| AccountInput {
+                    name: account.binding(),
+                    ty: Some(account.name.clone()),
+                    signer: false,
+                    writable: false,
+                })
+                .collect(),
+            args: Vec::new(),
+            requires: Vec::new(),
+            ensures: Vec::new(),
+            examples: Vec::new(),
+            compute_units: None,
+        };
+        let translator = Translator {
+            spec,
+            function: &function,
+            dialect: Dialect::Cvl,
+            postcondition: false,
+            old: false,
+        };
+        let code = translator
+            .expr(&invariant.expr)
+            .map_err(unsupported(format!("invariants.{}", invariant.name)))?;
+        out.push('\n');
+        match &invariant.description {
+            Some(description) => doc(&mut out, "", description.trim()),
+            None => out.push_str(&format!("/// The {} invariant.\n", invariant.name)),
+        }
+        out.push_str(&format!(
+            "invariant {}()\n    {};\n",
+            invariant.name, code.code
+        ));
+    }
+    Ok(out)
+}
+
+/// The prover configuration verifying the contract against its spec, with
+/// the `prover` settings of every rule, `*`: a single run proves all rules,
+/// so the settings of some cannot be told apart.
+fn conf(spec: &Spec) -> String {
+    let contract = contract_name(spec);
+    let mut conf = serde_json::json!({
+        "files": [format!("src/{contract}.sol")],
+        "verify": format!("{contract}:{}", spec_path(spec)),
+        "msg": spec.name,
+        "rule_sanity": "basic",
+        "optimistic_loop": true,
+    });
+    if let Some(settings) = spec.prover.get("*") {
+        if let Some(loop_iter) = settings.loop_iter {
+            conf["loop_iter"] = loop_iter.to_string().into();
+        }
+        if settings.arithmetic == Some(Arithmetic::Bitvector) {
+            conf["precise_bitwise_ops"] = true.into();
+        }
+        if let Some(smt_timeout) = settings.smt_timeout {
+            conf["smt_timeout"] = smt_timeout.to_string().into();
+        }
+    }
+    serde_json::to_string_pretty(&conf).expect("configurations serialize") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_vault() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let contract = contract_sol(&spec).unwrap();
+        assert!(contract.contains("contract VaultProgram {"));
+        assert!(contract.contains(
+            "    struct Vault {\n        /// The vault owner (authority)\n        address owner;"
+        ));
+        assert!(contract.contains("    Vault public vault;"));
+        assert!(contract.contains(
+            "    function withdraw(uint64 shares) external {\n        \
+             if (shares > vault.shares_total) revert InvalidArgument();\n"
+        ));
+        assert!(contract
+            .contains("        // - vault.token_total == old(vault.token_total) + amount\n"));
+
+        let rules = spec_cvl(&spec).unwrap();
+        assert!(rules.contains(
+            "rule rule_deposit_ensures(env e, uint64 amount) {\n    \
+             mathint old_vault_token_total = currentContract.vault.token_total;\n    \
+             mathint old_vault_shares_total = currentContract.vault.shares_total;\n\n    \
+             deposit(e, amount);\n\n    \
+             assert currentContract.vault.token_total == (old_vault_token_total + amount), \
+             \"postcondition `vault.token_total == old(vault.token_total) + amount` violated\";\n"
+        ));
+        assert!(rules.contains("rule rule_withdraw_sanity(env e, uint64 shares) {"));
+        assert!(rules.contains(
+            "invariant solvency()\n    currentContract.vault.shares_total <= currentContract.vault.token_total;\n"
+        ));
+
+        let conf: serde_json::Value = serde_json::from_str(&conf(&spec)).unwrap();
+        assert_eq!(conf["verify"], "VaultProgram:certora/specs/vault.spec");
+        assert_eq!(conf["files"][0], "src/VaultProgram.sol");
+        assert_eq!(conf.get("loop_iter"), None);
+
+        let mut tuned = spec.clone();
+        tuned.prover.insert(
+            "*".to_string(),
+            spec::ProverSettings {
+                loop_iter: Some(3),
+                arithmetic: Some(Arithmetic::Bitvector),
+                smt_timeout: None,
+            },
+        );
+        let conf: serde_json::Value = serde_json::from_str(&super::conf(&tuned)).unwrap();
+        assert_eq!(conf["loop_iter"], "3");
+        assert_eq!(conf["precise_bitwise_ops"], true);
+        assert_eq!(conf.get("smt_timeout"), None);
+    }
+
+    #[test]
+    fn test_callers_and_widening() {
+        let spec = Spec::from_yaml_str(
+            "name: registry\n\
+             state: [{ name: Entry, fields: [{ name: admin, type: pubkey }, { name: count, type: u8 }] }]\n\
+             functions:\n  - name: bump\n    accounts:\n\
+             \x20     - { name: admin, signer: true }\n\
+             \x20     - { name: entry, type: Entry, writable: true }\n\
+             \x20     - { name: peer }\n\
+             \x20   args: [{ name: by, type: u64 }]\n\
+             \x20   requires: [entry.admin == admin, entry.count + by <= 255]\n",
+        )
+        .unwrap();
+        let contract = contract_sol(&spec).unwrap();
+        assert!(contract.contains("contract Registry {"));
+        assert!(contract.contains("    function bump(address peer, uint64 by) external {\n"));
+        assert!(
+            contract.contains("        if (entry.admin != msg.sender) revert InvalidArgument();\n")
+        );
+        assert!(contract.contains(
+            "        if ((int256(uint256(entry.count)) + int256(uint256(by))) > 255) revert InvalidArgument();\n"
+        ));
+
+        let twice = Spec::from_yaml_str(
+            "name: pair\nstate: [{ name: Pool, fields: [{ name: total, type: u64 }] }]\n\
+             functions:\n  - name: swap\n    accounts:\n\
+             \x20     - { name: from, type: Pool, writable: true }\n\
+             \x20     - { name: to, type: Pool, writable: true }\n",
+        )
+        .unwrap();
+        assert_eq!(
+            generate(&twice).unwrap_err().to_string(),
+            "functions.swap.to: EVM contracts hold a single `Pool`"
+        );
+    }
+}
@@ -0,0 +1,1195 @@
+//! Generator for plain Rust libraries, e.g. parsers, codecs and state
+//! machines.
+//!
+//! The crate has the layout of the SVM examples, so that it is implemented
+//! and verified the same way, but nothing in it runs on a chain: state types
+//! are ordinary structs, and each processor function takes the state it
+//! reads by reference and its arguments by value, returning
+//! `Err(Error::InvalidArgument)` when a precondition does not hold. Accounts
+//! without a type are passed as 32-byte `Key`s. The rules call the
+//! functions on nondeterministic state, and invariants become predicates in
+//! `src/certora.rs`. The functions also carry their clauses as [contracts]
+//! for deductive verifiers, and the invariants are also checked by
+//! [property tests](prop_tests).
+
+mod contracts;
+mod prop_tests;
+
+use super::svm::{
+    doc, invariant_accounts, invariants, native_int, negate, strategy_parameter,
+    unsigned_zero_comparison, with_article, Code, Ty,
+};
+use super::{
+    certora_summaries, GeneratedFile, MaterializeError, CERTORA_METADATA, PROOFS_MOD, PROOFS_RS,
+    PROP_TESTS_MOD, PROP_TESTS_RS, SPEC_TESTS_RS,
+};
+use spec::{
+    AccountInput, BinaryOp, Example, Expr, Function, Invariant, Outcome, Spec, Type, UnaryOp, Value,
+};
+use std::collections::{BTreeSet, HashSet};
+
+/// Generates every file of the library crate for `spec`.
+pub fn generate(spec: &Spec) -> Result<Vec<GeneratedFile>, MaterializeError> {
+    let functions = spec
+        .functions
+        .iter()
+        .map(|function| FunctionGen::new(spec, function))
+        .collect::<Result<Vec<_>, _>>()?;
+    let spec_tests = spec_tests_rs(spec);
+    let prop_tests = prop_tests::prop_tests_rs(spec)?;
+    let mut files = vec![
+        GeneratedFile::new("Cargo.toml", cargo_toml(spec)),
+        GeneratedFile::new(
+            "src/lib.rs",
+            lib_rs(spec, spec_tests.is_some(), prop_tests.is_some()),
+        ),
+        GeneratedFile::new("src/state.rs", state_rs(spec)),
+        GeneratedFile::new("src/processor.rs", processor_rs(spec, &functions)),
+        GeneratedFile::new("src/certora.rs", certora_rs(spec)?),
+        GeneratedFile::new("src/certora/spec.rs", spec_rs(spec, &functions)?),
+        GeneratedFile::new(PROOFS_RS, proofs_rs(spec, &functions)?),
+    ];
+    files.extend(certora_summaries());
+    if let Some(spec_tests) = spec_tests {
+        files.push(GeneratedFile::new(SPEC_TESTS_RS, spec_tests));
+    }
+    if let Some(prop_tests) = prop_tests {
+        files.push(GeneratedFile::new(PROP_TESTS_RS, prop_tests));
+    }
+    Ok(files)
+}
+
+fn cargo_toml(spec: &Spec) -> String {
+    format!(
+        r#"[package]
+name = "{}"
+version = "0.1.0"
+edition = "2021"
+
+[features]
+default = []
+certora = ["cvlr"]
+prusti = ["prusti-contracts"]
+creusot = ["creusot-contracts"]
+
+[dependencies]
+# Optional dependencies for formal verification
+cvlr = {{ version = "0.4", optional = true }}
+prusti-contracts = {{ version = "0.2", optional = true }}
+creusot-contracts = {{ version = "0.2", optional = true }}
+
+[dev-dependencies]
+proptest = "1"
+
+[lints.rust]
+unexpected_cfgs = {{ level = "warn", check-cfg = ['cfg(kani)'] }}
+
+{CERTORA_METADATA}"#,
+        spec.name.replace('_', "-")
+    )
+}
+
+fn lib_rs(spec: &Spec, spec_tests: bool, prop_tests: bool) -> String {
+    let mut out = String::new();
+    let description = spec
+        .description
+        .clone()
+        .unwrap_or_else(|| format!("The {} library.", spec.name.replace('_', " ")));
+    for line in description.trim().lines() {
+        out.push_str(&format!("//! {line}\n").replace("//! \n", "//!\n"));
+    }
+    out.push_str("\npub mod processor;\npub mod state;\n");
+    if spec_tests {
+        out.push_str("\n#[cfg(test)]\nmod spec_tests;\n");
+    }
+    if prop_tests {
+        out.push_str(PROP_TESTS_MOD);
+    }
+    out.push_str(PROOFS_MOD);
+    out.push_str(
+        r#"
+// Include formal verification module only if certora feature is enabled.
+#[cfg(feature = "certora")]
+pub mod certora;
+
+/// The error of a call whose preconditions do not hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    InvalidArgument,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid argument")
+    }
+}
+
+impl std::error::Error for Error {}
+"#,
+    );
+    out
+}
+
+/// Returns true if `ty` holds keys.
+fn has_key(ty: &Type) -> bool {
+    match ty {
+        Type::Pubkey => true,
+        Type::Array(inner, _) => has_key(inner),
+        _ => false,
+    }
+}
+
+/// Returns true if any state field, argument or untyped account of `spec`
+/// is a key.
+fn uses_key(spec: &Spec) -> bool {
+    spec.state
+        .iter()
+        .flat_map(|account| &account.fields)
+        .any(|field| has_key(&field.ty))
+        || spec.functions.iter().any(|function| {
+            function.args.iter().any(|arg| has_key(&arg.ty))
+                || function.accounts.iter().any(|account| account.ty.is_none())
+        })
+}
+
+/// The Rust type of a field or argument.
+fn rust_type(ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".to_string(),
+        Type::U128 => "u128".to_string(),
+        Type::Pubkey => "Key".to_string(),
+        Type::Array(inner, len) => format!("[{}; {len}]", rust_type(inner)),
+        ty => native_int(ty).expect("integer").to_string(),
+    }
+}
+
+/// The zero value of `ty`.
+fn zero(ty: &Type) -> String {
+    match ty {
+        Type::Bool => "false".to_string(),
+        Type::Pubkey => "[0; 32]".to_string(),
+        Type::Array(inner, len) => format!("[{}; {len}]", zero(inner)),
+        _ => "0".to_string(),
+    }
+}
+
+/// Returns true if `ty` implements `Default`, which arrays of more than 32
+/// elements do not.
+fn derives_default(ty: &Type) -> bool {
+    match ty {
+        Type::Array(inner, len) => *len <= 32 && derives_default(inner),
+        _ => true,
+    }
+}
+
+/// A nondeterministic value of `ty`; cvlr only implements `Nondet` for
+/// scalars.
+fn nondet(ty: &Type) -> String {
+    match ty {
+        Type::Pubkey => "core::array::from_fn(|_| nondet())".to_string(),
+        Type::Array(inner, _) => format!("core::array::from_fn(|_| {})", nondet(inner)),
+        _ => "nondet()".to_string(),
+    }
+}
+
+fn state_rs(spec: &Spec) -> String {
+    let mut out = String::new();
+    if uses_key(spec) {
+        out.push_str(
+            "/// A 32-byte key, e.g. a public key or a digest.\npub type Key = [u8; 32];\n\n",
+        );
+    }
+    for account in &spec.state {
+        let description = account
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("The {} state.", account.name));
+        doc(&mut out, "", &description);
+        let default = account
+            .fields
+            .iter()
+            .all(|field| derives_default(&field.ty));
+        out.push_str(&format!(
+            "#[derive(Clone, Copy, Debug, {}PartialEq, Eq)]\n\
+             #[cfg_attr(kani, derive(kani::Arbitrary))]\npub struct {} {{\n",
+            if default { "Default, " } else { "" },
+            account.name
+        ));
+        for field in &account.fields {
+            if let Some(description) = &field.description {
+                doc(&mut out, "    ", description);
+            }
+            out.push_str(&format!(
+                "    pub {}: {},\n",
+                field.name,
+                rust_type(&field.ty)
+            ));
+        }
+        out.push_str("}\n\n");
+        if !default {
+            out.push_str(&format!(
+                "impl Default for {0} {{\n    fn default() -> {0} {{\n        {0} {{\n",
+                account.name
+            ));
+            for field in &account.fields {
+                out.push_str(&format!(
+                    "            {}: {},\n",
+                    field.name,
+                    zero(&field.ty)
+                ));
+            }
+            out.push_str("        }\n    }\n}\n\n");
+        }
+    }
+    if !spec.state.is_empty() {
+        // The rules start from any state
+        out.push_str(
+            "#[cfg(feature = \"certora\")]\nmod nondet {\n    use super::*;\n    \
+             use cvlr::nondet::{nondet, Nondet};\n",
+        );
+        for account in &spec.state {
+            out.push_str(&format!(
+                "\n    impl Nondet for {0} {{\n        fn nondet() -> {0} {{\n            {0} {{\n",
+                account.name
+            ));
+            for field in &account.fields {
+                out.push_str(&format!(
+                    "                {}: {},\n",
+                    field.name,
+                    nondet(&field.ty)
+                ));
+            }
+            out.push_str("            }\n        }\n    }\n");
+        }
+        out.push_str("}\n");
+    }
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
+
+/// The generated type of an expression leaf of type `ty`.
+fn leaf(code: String, ty: &Type) -> Result<Code, String> {
+    Ok(match ty {
+        Type::Bool => Code::leaf(code, Ty::Bool),
+        Type::Pubkey => Code::leaf(code, Ty::Key),
+        ty => match native_int(ty) {
+            Some(native) => Code::leaf(code, Ty::Int(native)),
+            None => return Err(format!("`{ty}` values cannot be used in expressions")),
+        },
+    })
+}
+
+/// Translates conditions into Rust over the parameters of the generated
+/// processor function.
+#[derive(Copy, Clone)]
+struct Translator<'a> {
+    spec: &'a Spec,
+    function: &'a Function,
+    /// Whether `old(..)` is allowed, the state before the call being bound
+    /// with an `old_` prefix
+    postcondition: bool,
+    /// Whether the expression is inside `old(..)`
+    old: bool,
+    /// The contract language the expression is written in, if not plain
+    /// Rust
+    dialect: Option<contracts::Dialect>,
+}
+
+impl Translator<'_> {
+    fn leaf(&self, code: String, ty: &Type) -> Result<Code, String> {
+        match self.dialect {
+            Some(dialect) => dialect.leaf(code, ty),
+            None => leaf(code, ty),
+        }
+    }
+
+    fn path(&self, path: &[String]) -> Result<Code, String> {
+        let root = &path[0];
+        if let Some(arg) = self.function.arg(root) {
+            return self.leaf(root.clone(), &arg.ty);
+        }
+        let account = self.function.account(root).ok_or("unknown name")?;
+        let (Some(ty), Some(field)) = (account.ty.as_deref(), path.get(1)) else {
+            return match account.ty {
+                None => Ok(Code::leaf(root.clone(), Ty::Key)),
+                Some(_) => Err("state values have no key".to_string()),
+            };
+        };
+        let field = self
+            .spec
+            .account(ty)
+            .and_then(|account| account.field(field))
+            .ok_or("unknown field")?;
+        let code = match self.dialect {
+            Some(dialect) => dialect.field(root, &field.name, account.writable, self.old),
+            None if self.old => format!("old_{root}.{}", field.name),
+            None => format!("{root}.{}", field.name),
+        };
+        self.leaf(code, &field.ty)
+    }
+
+    fn expr(&self, expr: &Expr) -> Result<Code, String> {
+        Ok(match expr {
+            Expr::Int(value) => Code::leaf(value.to_string(), Ty::Int("literal")),
+            Expr::Bool(value) => Code::leaf(value.to_string(), Ty::Bool),
+            Expr::Path(path) => self.path(path)?,
+            Expr::Old(inner) if self.postcondition => {
+                Translator { old: true, ..*self }.expr(inner)?
+            }
+            Expr::Old(_) => return Err("`old(..)` is only allowed in postconditions".into()),
+            Expr::Unary(UnaryOp::Not, inner) => {
+                let inner = self.expr(inner)?;
+                Code {
+                    code: format!("!{}", inner.parenthesized()),
+                    ty: Ty::Bool,
+                    atomic: true,
+                }
+            }
+            Expr::Binary(BinaryOp::Implies, lhs, rhs) => {
+                let expr = Expr::binary(BinaryOp::Or, negate(lhs), (**rhs).clone());
+                self.expr(&expr)?
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+                if let Some(code) = unsigned_zero_comparison(*op, &lhs, &rhs) {
+                    return Ok(code);
+                }
+                let (lhs, rhs, ty) = match op {
+                    BinaryOp::And | BinaryOp::Or => {
+                        (lhs.parenthesized(), rhs.parenthesized(), Ty::Bool)
+                    }
+                    _ if op.is_boolean() => match (lhs.ty, rhs.ty) {
+                        (l, r) if l == r || l == Ty::Int("literal") || r == Ty::Int("literal") => {
+                            (lhs.parenthesized(), rhs.parenthesized(), Ty::Bool)
+                        }
+                        (Ty::Int(_), Ty::Int(_)) => (lhs.wide(), rhs.wide(), Ty::Bool),
+                        _ => return Err(format!("mismatched operands of `{}`", op.symbol())),
+                    },
+                    _ => (lhs.wide(), rhs.wide(), Ty::Int("i128")),
+                };
+                Code {
+                    code: format!("{lhs} {} {rhs}", op.symbol()),
+                    ty,
+                    atomic: false,
+                }
+            }
+        })
+    }
+}
+
+/// Translates a postcondition of `function` into Rust over its parameters
+/// after the call, with the state from before the call bound with an `old_`
+/// prefix.
+fn postcondition(spec: &Spec, function: &Function, expr: &Expr) -> Result<String, String> {
+    let translator = Translator {
+        spec,
+        function,
+        postcondition: true,
+        old: false,
+        dialect: None,
+    };
+    Ok(translator.expr(expr)?.code)
+}
+
+/// The type of the parameter `account` is passed as.
+fn account_type(account: &AccountInput) -> String {
+    match (&account.ty, account.writable) {
+        (Some(ty), true) => format!("&mut {ty}"),
+        (Some(ty), false) => format!("&{ty}"),
+        (None, _) => "Key".to_string(),
+    }
+}
+
+/// Everything needed to emit one processor function and its rules.
+struct FunctionGen<'a> {
+    function: &'a Function,
+    /// Preconditions translated to Rust, negated: the function fails when
+    /// one holds
+    checks: Vec<String>,
+    /// Names referenced by the clauses, which the contracts name
+    used: HashSet<String>,
+    /// The `cfg_attr`s of the contracts of the function
+    contracts: String,
+}
+
+impl<'a> FunctionGen<'a> {
+    fn new(spec: &'a Spec, function: &'a Function) -> Result<FunctionGen<'a>, MaterializeError> {
+        let translator = Translator {
+            spec,
+            function,
+            postcondition: false,
+            old: false,
+            dialect: None,
+        };
+        let mut checks = Vec::new();
+        let mut used = HashSet::new();
+        for (i, condition) in function.requires.iter().enumerate() {
+            let code = translator
+                .expr(&negate(&condition.expr))
+                .map_err(|message| MaterializeError::Unsupported {
+                    location: format!("functions.{}.requires[{i}]", function.name),
+                    message,
+                })?;
+            checks.push(code.code);
+        }
+        for condition in function.requires.iter().chain(&function.ensures) {
+            condition.expr.visit_paths(&mut |path, _| {
+                used.insert(path[0].clone());
+            });
+        }
+        Ok(FunctionGen {
+            function,
+            checks,
+            used,
+            contracts: contracts::contracts(spec, function)?,
+        })
+    }
+
+    /// The parameter name for `name`, prefixed with `_` when unused.
+    fn binding(&self, name: &str) -> String {
+        if self.used.contains(name) {
+            name.to_string()
+        } else {
+            format!("_{name}")
+        }
+    }
+
+    fn emit(&self, out: &mut String) {
+        let function = self.function;
+        out.push_str(&crate::drift::marker(function));
+        out.push('\n');
+        out.push_str(&format!("/// Process {}.\n", with_article(&function.name)));
+        if let Some(description) = &function.description {
+            doc(out, "", description);
+        }
+        out.push_str(&self.contracts);
+        let params: Vec<String> = function
+            .accounts
+            .iter()
+            .map(|account| format!("{}: {}", self.binding(&account.name), account_type(account)))
+            .chain(
+                function
+                    .args
+                    .iter()
+                    .map(|arg| format!("{}: {}", self.binding(&arg.name), rust_type(&arg.ty))),
+            )
+            .collect();
+        out.push_str(&format!(
+            "pub fn process_{}({}) -> Result<(), Error> {{\n",
+            function.name,
+            params.join(", ")
+        ));
+        for check in &self.checks {
+            out.push_str(&format!(
+                "    if {check} {{\n        return Err(Error::InvalidArgument);\n    }}\n"
+            ));
+        }
+        if !function.ensures.is_empty() {
+            if !self.checks.is_empty() {
+                out.push('\n');
+            }
+            out.push_str("    // Establish the postconditions:\n");
+            for condition in &function.ensures {
+                out.push_str(&format!("    // - {}\n", condition.expr));
+                if let Some(description) = &condition.description {
+                    out.push_str(&format!("    //   {description}\n"));
+                }
+            }
+        }
+        if !self.checks.is_empty() || !function.ensures.is_empty() {
+            out.push('\n');
+        }
+        out.push_str("    Ok(())\n}\n");
+    }
+}
+
+/// The `use` of the state types in `types`, e.g. `use crate::state::{Key,
+/// Vault};`, or nothing.
+fn use_state(types: &BTreeSet<String>) -> String {
+    match types.len() {
+        0 => String::new(),
+        1 => format!("use crate::state::{};\n", types.first().unwrap()),
+        _ => format!(
+            "use crate::state::{{{}}};\n",
+            types.iter().cloned().collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// The state types and keys the parameters of `function` use.
+fn parameter_types(function: &Function, types: &mut BTreeSet<String>) {
+    for account in &function.accounts {
+        types.insert(account.ty.clone().unwrap_or_else(|| "Key".to_string()));
+    }
+    if function.args.iter().any(|arg| has_key(&arg.ty)) {
+        types.insert("Key".to_string());
+    }
+}
+
+fn processor_rs(spec: &Spec, functions: &[FunctionGen]) -> String {
+    let mut types = BTreeSet::new();
+    for function in &spec.functions {
+        parameter_types(function, &mut types);
+    }
+    let mut out = use_state(&types);
+    out.push_str("use crate::Error;\n");
+    for function in functions {
+        out.push('\n');
+        function.emit(&mut out);
+    }
+    out
+}
+
+/// The predicates of `invariants`, each over the state it names, whose
+/// types are added to `types`.
+fn predicates<'a>(
+    spec: &Spec,
+    invariants: impl IntoIterator<Item = &'a Invariant>,
+    types: &mut BTreeSet<String>,
+) -> Result<String, MaterializeError> {
+    let mut predicates = String::new();
+    for invariant in invariants {
+        let accounts = invariant_accounts(spec, invariant);
+        // Translated as a condition over parameters named by their bindings
+        let function = Function {
+            name: invariant.name.clone(),
+            description: None,
+            accounts: accounts
+                .iter()
+                .map(|account| AccountInput {
+                    name: account.binding(),
+                    ty: Some(account.name.clone()),
+                    signer: false,
+                    writable: false,
+                })
+                .collect(),
+            args: Vec::new(),
+            requires: Vec::new(),
+            ensures: Vec::new(),
+            examples: Vec::new(),
+            compute_units: None,
+        };
+        let translator = Translator {
+            spec,
+            function: &function,
+            postcondition: false,
+            old: false,
+            dialect: None,
+        };
+        let code =
+            translator
+                .expr(&invariant.expr)
+                .map_err(|message| MaterializeError::Unsupported {
+                    location: format!("invariants.{}", invariant.name),
+                    message,
+                })?;
+        predicates.push_str(&format!(
+            "\n/// Whether the {} invariant holds",
+            invariant.name
+        ));
+        match &invariant.description {
+            Some(description) => predicates.push_str(&format!(": {}\n", description.trim())),
+            None => predicates.push_str(".\n"),
+        }
+        let params: Vec<String> = function
+            .accounts
+            .iter()
+            .map(|input| format!("{}: {}", input.name, account_type(input)))
+            .collect();
+        predicates.push_str(&format!(
+            "pub fn {}({}) -> bool {{\n    {}\n}}\n",
+            invariant.name,
+            params.join(", "),
+            code.code
+        ));
+        types.extend(accounts.iter().map(|account| account.name.clone()));
+    }
+    Ok(predicates)
+}
+
+/// Generates `src/certora.rs`, with a predicate per invariant.
+fn certora_rs(spec: &Spec) -> Result<String, MaterializeError> {
+    let mut types = BTreeSet::new();
+    let predicates = predicates(spec, &spec.invariants, &mut types)?;
+    let mut out = format!(
+        "//! Formal verification module for the {}.\n\npub mod spec;\n",
+        spec.name.replace('_', " ")
+    );
+    if !types.is_empty() {
+        out.push('\n');
+        out.push_str(&use_state(&types));
+    }
+    out.push_str(&predicates);
+    Ok(out)
+}
+
+/// The verifier rules are written for: the Certora Prover, whose rules are
+/// in `src/certora/spec.rs`, or Kani, whose proof harnesses of the same
+/// names are in `src/proofs.rs`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Checker {
+    Certora,
+    Kani,
+}
+
+impl Checker {
+    /// Writes the head of a rule.
+    fn head(self, out: &mut String, doc: &str, name: &str) {
+        let attribute = match self {
+            Checker::Certora => "#[rule]\npub fn",
+            Checker::Kani => "#[kani::proof]\nfn",
+        };
+        out.push_str(&format!("\n/// {doc}\n{attribute} {name}() {{\n"));
+    }
+
+    /// A nondeterministic value of `ty`, or of a state type.
+    fn any(self, ty: Option<&Type>) -> String {
+        match self {
+            Checker::Certora => ty.map_or_else(|| "nondet()".to_string(), nondet),
+            Checker::Kani => "kani::any()".to_string(),
+        }
+    }
+
+    fn assume(self, condition: &str) -> String {
+        match self {
+            Checker::Certora => format!("cvlr_assume!({condition});"),
+            Checker::Kani => format!("kani::assume({condition});"),
+        }
+    }
+
+    fn assert(self, condition: &str) -> String {
+        match self {
+            Checker::Certora => format!("cvlr_assert!({condition});"),
+            Checker::Kani => format!("assert!({condition});"),
+        }
+    }
+
+    /// The statement calling `call`, which only continues if it succeeds.
+    fn call(self, call: &str) -> String {
+        match self {
+            Checker::Certora => format!("{call}.unwrap();"),
+            Checker::Kani => format!("kani::assume({call}.is_ok());"),
+        }
+    }
+
+    /// The statement checking that a rule can reach its end.
+    fn satisfy(self) -> &'static str {
+        match self {
+            Checker::Certora => "cvlr_satisfy!(true);",
+            Checker::Kani => "kani::cover!();",
+        }
+    }
+}
+
+/// Writes nondeterministic parameters of `function` and returns the
+/// arguments of its call.
+fn nondet_params(out: &mut String, function: &Function, checker: Checker) -> Vec<String> {
+    let mut call = Vec::new();
+    for account in &function.accounts {
+        let name = &account.name;
+        match (&account.ty, account.writable) {
+            (Some(ty), true) => {
+                out.push_str(&format!(
+                    "    let mut {name}: {ty} = {};\n",
+                    checker.any(None)
+                ));
+                call.push(format!("&mut {name}"));
+            }
+            (Some(ty), false) => {
+                out.push_str(&format!("    let {name}: {ty} = {};\n", checker.any(None)));
+                call.push(format!("&{name}"));
+            }
+            (None, _) => {
+                out.push_str(&format!(
+                    "    let {name}: Key = {};\n",
+                    checker.any(Some(&Type::Pubkey))
+                ));
+                call.push(name.clone());
+            }
+        }
+    }
+    for arg in &function.args {
+        out.push_str(&format!(
+            "    let {}: {} = {};\n",
+            arg.name,
+            rust_type(&arg.ty),
+            checker.any(Some(&arg.ty))
+        ));
+        call.push(arg.name.clone());
+    }
+    call
+}
+
+/// The call of the invariant predicate on `inputs`.
+fn predicate(invariant: &Invariant, inputs: &[&AccountInput]) -> String {
+    let args: Vec<String> = inputs
+        .iter()
+        .map(|input| format!("&{}", input.name))
+        .collect();
+    format!("{}({})", invariant.name, args.join(", "))
+}
+
+/// Writes the rules of `function`: a sanity rule, a rule asserting its
+/// postconditions and a rule per invariant it could break.
+fn function_rules(
+    out: &mut String,
+    spec: &Spec,
+    function: &Function,
+    checker: Checker,
+) -> Result<(), MaterializeError> {
+    let call = |out: &mut String, args: &[String]| {
+        let call = format!("process_{}({})", function.name, args.join(", "));
+        out.push_str(&format!("\n    {}\n", checker.call(&call)));
+    };
+
+    checker.head(
+        out,
+        &format!(
+            "Verifies that {} can succeed.",
+            with_article(&function.name)
+        ),
+        &format!("rule_{}_sanity", function.name),
+    );
+    let args = nondet_params(out, function, checker);
+    call(out, &args);
+    out.push_str(&format!("    {}\n}}\n", checker.satisfy()));
+
+    let invariants = invariants(spec, function);
+    if !function.ensures.is_empty() {
+        let mut asserts = Vec::new();
+        let mut before = BTreeSet::new();
+        for (i, condition) in function.ensures.iter().enumerate() {
+            let code = postcondition(spec, function, &condition.expr).map_err(|message| {
+                MaterializeError::Unsupported {
+                    location: format!("functions.{}.ensures[{i}]", function.name),
+                    message,
+                }
+            })?;
+            asserts.push(code);
+            condition.expr.visit_paths(&mut |path, in_old| {
+                if in_old && path.len() > 1 {
+                    before.insert(path[0].clone());
+                }
+            });
+        }
+        checker.head(
+            out,
+            &format!(
+                "Verifies the postconditions of {}.",
+                with_article(&function.name)
+            ),
+            &format!("rule_{}_ensures", function.name),
+        );
+        let args = nondet_params(out, function, checker);
+        for name in &before {
+            out.push_str(&format!("    let old_{name} = {name};\n"));
+        }
+        for (invariant, inputs) in &invariants {
+            out.push_str(&format!(
+                "    {}\n",
+                checker.assume(&predicate(invariant, inputs))
+            ));
+        }
+        call(out, &args);
+        out.push('\n');
+        for code in asserts {
+            out.push_str(&format!("    {}\n", checker.assert(&code)));
+        }
+        out.push_str("}\n");
+    }
+
+    for (invariant, inputs) in &invariants {
+        checker.head(
+            out,
+            &format!(
+                "Verifies that {} preserves the {} invariant.",
+                with_article(&function.name),
+                invariant.name
+            ),
+            &format!("rule_{}_{}", function.name, invariant.name),
+        );
+        let args = nondet_params(out, function, checker);
+        let predicate = predicate(invariant, inputs);
+        out.push_str(&format!("    {}\n", checker.assume(&predicate)));
+        call(out, &args);
+        out.push_str(&format!("\n    {}\n}}\n", checker.assert(&predicate)));
+    }
+    Ok(())
+}
+
+/// Generates `src/certora/spec.rs`.
+fn spec_rs(spec: &Spec, functions: &[FunctionGen]) -> Result<String, MaterializeError> {
+    let mut rules = String::new();
+    let mut types = BTreeSet::new();
+    for generated in functions {
+        function_rules(&mut rules, spec, generated.function, Checker::Certora)?;
+        parameter_types(generated.function, &mut types);
+    }
+    let mut out = format!(
+        "//! This module contains the specification for the {} library.\n\n",
+        spec.name.replace('_', " ")
+    );
+    if !spec.invariants.is_empty() {
+        out.push_str("use crate::certora::*;\n");
+    }
+    if !functions.is_empty() {
+        out.push_str("use crate::processor::*;\n");
+    }
+    out.push_str(&use_state(&types));
+    out.push_str("use cvlr::prelude::*;\n");
+    out.push_str(&rules);
+    Ok(out)
+}
+
+/// Generates `src/proofs.rs`, with a Kani proof harness per rule and the
+/// predicates of the invariants they check.
+fn proofs_rs(spec: &Spec, functions: &[FunctionGen]) -> Result<String, MaterializeError> {
+    let mut harnesses = String::new();
+    let mut types = BTreeSet::new();
+    let mut checked = BTreeSet::new();
+    for generated in functions {
+        function_rules(&mut harnesses, spec, generated.function, Checker::Kani)?;
+        parameter_types(generated.function, &mut types);
+        for (invariant, _) in invariants(spec, generated.function) {
+            checked.insert(invariant.name.as_str());
+        }
+    }
+    let predicates = predicates(
+        spec,
+        spec.invariants
+            .iter()
+            .filter(|invariant| checked.contains(invariant.name.as_str())),
+        &mut types,
+    )?;
+    let mut out = format!(
+        "//! Kani proof harnesses for the {} library, checking the rules of the\n\
+         //! certora module with `cargo kani`.\n\n",
+        spec.name.replace('_', " ")
+    );
+    if !functions.is_empty() {
+        out.push_str("use crate::processor::*;\n");
+    }
+    out.push_str(&use_state(&types));
+    out.push_str(&predicates);
+    out.push_str(&harnesses);
+    Ok(out)
+}
+
+/// The literal of `value` as a `ty`.
+fn literal(value: Value, ty: &Type) -> Option<String> {
+    let native = match ty {
+        Type::Bool => {
+            return Some(match value {
+                Value::Bool(value) => value.to_string(),
+                Value::Int(value) => (value != 0).to_string(),
+                Value::Max => "true".to_string(),
+                Value::Min => "false".to_string(),
+            })
+        }
+        Type::U128 => "u128",
+        ty => native_int(ty)?,
+    };
+    Some(match value {
+        Value::Int(value) => format!("{value}{native}"),
+        Value::Bool(value) => format!("{}{native}", u8::from(value)),
+        Value::Max => format!("{native}::MAX"),
+        Value::Min => format!("{native}::MIN"),
+    })
+}
+
+/// Key assignments implied by the preconditions: a key field required to
+/// equal an untyped account or a key argument is set to it, since examples
+/// only name keys symbolically.
+fn key_bindings(spec: &Spec, function: &Function) -> Vec<(String, String)> {
+    let field_of = |path: &[String]| -> Option<String> {
+        let [root, field] = path else { return None };
+        let ty = function.account(root)?.ty.as_deref()?;
+        let field = spec.account(ty)?.field(field)?;
+        (field.ty == Type::Pubkey).then(|| format!("{root}.{}", field.name))
+    };
+    let key_of = |path: &[String]| -> Option<String> {
+        let [root] = path else { return None };
+        let key = function.arg(root).is_some_and(|arg| arg.ty == Type::Pubkey)
+            || function
+                .account(root)
+                .is_some_and(|account| account.ty.is_none());
+        key.then(|| root.clone())
+    };
+    let mut bindings = Vec::new();
+    for condition in &function.requires {
+        let Expr::Binary(BinaryOp::Eq, lhs, rhs) = &condition.expr else {
+            continue;
+        };
+        let (Expr::Path(lhs), Expr::Path(rhs)) = (&**lhs, &**rhs) else {
+            continue;
+        };
+        let binding = match (field_of(lhs), field_of(rhs)) {
+            (Some(field), None) => key_of(rhs).map(|key| (field, key)),
+            (None, Some(field)) => key_of(lhs).map(|key| (field, key)),
+            _ => None,
+        };
+        bindings.extend(binding);
+    }
+    bindings
+}
+
+fn test(out: &mut String, spec: &Spec, function: &Function, example: &Example) {
+    out.push('\n');
+    match &example.description {
+        Some(description) => doc(out, "", description),
+        None => out.push_str(&format!(
+            "/// The `{}` example of {}.\n",
+            example.name,
+            with_article(&function.name)
+        )),
+    }
+    out.push_str(&format!(
+        "#[test]\nfn test_{}_{}() {{\n",
+        function.name, example.name
+    ));
+
+    // Keys are distinct and only named symbolically
+    let mut next_key = 0u8;
+    let mut key = || {
+        next_key += 1;
+        format!("[{next_key}; 32]")
+    };
+    let mut args = Vec::new();
+    for account in &function.accounts {
+        if account.ty.is_none() {
+            out.push_str(&format!("    let {}: Key = {};\n", account.name, key()));
+            args.push(account.name.clone());
+        }
+    }
+    for arg in &function.args {
+        let value = match &arg.ty {
+            Type::Pubkey => key(),
+            ty => example
+                .args
+                .get(&arg.name)
+                .and_then(|&value| literal(value, ty))
+                .unwrap_or_else(|| zero(ty)),
+        };
+        out.push_str(&format!("    let {} = {value};\n", arg.name));
+    }
+
+    let checks: Vec<(String, &Expr)> = function
+        .ensures
+        .iter()
+        .filter_map(|condition| {
+            let code = postcondition(spec, function, &condition.expr).ok()?;
+            Some((code, &condition.expr))
+        })
+        .collect();
+    let mut before = BTreeSet::new();
+    if example.expect == Outcome::Ok {
+        for condition in &function.ensures {
+            condition.expr.visit_paths(&mut |path, in_old| {
+                if in_old && path.len() > 1 {
+                    before.insert(path[0].clone());
+                }
+            });
+        }
+    }
+    let keys = key_bindings(spec, function);
+    let mut params = Vec::new();
+    for account in &function.accounts {
+        let Some(ty) = account.ty.as_deref() else {
+            params.push(account.name.clone());
+            continue;
+        };
+        let name = &account.name;
+        let mut assignments = String::new();
+        for field in spec.account(ty).map_or(&[][..], |account| &account.fields) {
+            let access = format!("{name}.{}", field.name);
+            if let Some((_, key)) = keys.iter().find(|(field, _)| *field == access) {
+                assignments.push_str(&format!("    {access} = {key};\n"));
+            } else if let Some(value) = example
+                .state
+                .get(name)
+                .and_then(|fields| fields.get(&field.name))
+                .and_then(|&value| literal(value, &field.ty))
+            {
+                assignments.push_str(&format!("    {access} = {value};\n"));
+            }
+        }
+        let mutable = if account.writable || !assignments.is_empty() {
+            "mut "
+        } else {
+            ""
+        };
+        out.push_str(&format!(
+            "\n    let {mutable}{name} = crate::state::{ty}::default();\n{assignments}"
+        ));
+        if before.contains(name) {
+            out.push_str(&format!("    let old_{name} = {name};\n"));
+        }
+        params.push(if account.writable {
+            format!("&mut {name}")
+        } else {
+            format!("&{name}")
+        });
+    }
+    params.extend(function.args.iter().map(|arg| arg.name.clone()));
+    out.push_str(&format!(
+        "\n    let result = crate::processor::process_{}({});\n",
+        function.name,
+        params.join(", ")
+    ));
+
+    if example.expect == Outcome::Error {
+        out.push_str("\n    assert!(result.is_err());\n}\n");
+        return;
+    }
+    out.push_str("\n    result.unwrap();\n");
+    for (account, fields) in &example.after {
+        let Some(ty) = function
+            .account(account)
+            .and_then(|account| spec.account(account.ty.as_deref()?))
+        else {
+            continue;
+        };
+        for (field, &value) in fields {
+            let Some((field, literal)) = ty
+                .field(field)
+                .and_then(|field| Some((field, literal(value, &field.ty)?)))
+            else {
+                continue;
+            };
+            out.push_str(&format!(
+                "    assert_eq!({account}.{}, {literal});\n",
+                field.name
+            ));
+        }
+    }
+    for (check, expr) in &checks {
+        out.push_str(&format!(
+            "    assert!({check}, \"postcondition `{expr}` violated\");\n"
+        ));
+    }
+    out.push_str("}\n");
+}
+
+/// Generates `src/spec_tests.rs`, or `None` if the spec has no examples.
+fn spec_tests_rs(spec: &Spec) -> Option<String> {
+    let functions: Vec<&Function> = spec
+        .functions
+        .iter()
+        .filter(|function| !function.examples.is_empty())
+        .collect();
+    if functions.is_empty() {
+        return None;
+    }
+    let mut out = String::from("//! Unit tests from the examples of the spec.\n");
+    if functions
+        .iter()
+        .any(|function| function.accounts.iter().any(|account| account.ty.is_none()))
+    {
+        out.push_str("\nuse crate::state::Key;\n");
+    }
+    for function in functions {
+        for example in &function.examples {
+            test(&mut out, spec, function, example);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../examples/specs/vault.yaml");
+
+    fn file<'a>(files: &'a [GeneratedFile], path: &str) -> &'a str {
+        &files
+            .iter()
+            .find(|file| file.path == std::path::Path::new(path))
+            .unwrap()
+            .contents
+    }
+
+    #[test]
+    fn test_vault() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let files = generate(&spec).unwrap();
+        assert!(file(&files, "Cargo.toml").contains("name = \"vault\""));
+        let state = file(&files, "src/state.rs");
+        assert!(state.contains("pub type Key = [u8; 32];"));
+        assert!(state.contains("    pub owner: Key,\n"));
+        assert!(state.contains("                token_total: nondet(),\n"));
+
+        let processor = file(&files, "src/processor.rs");
+        assert!(processor.contains("use crate::state::Vault;\nuse crate::Error;\n"));
+        assert!(processor.contains(
+            "pub fn process_deposit(vault: &mut Vault, amount: u64) -> Result<(), Error> {\n    \
+             if amount == 0 {\n        return Err(Error::InvalidArgument);\n    }\n"
+        ));
+        assert_eq!(crate::drift::marked(processor).len(), 3);
+
+        assert!(file(&files, "src/certora.rs").contains(
+            "pub fn solvency(vault: &Vault) -> bool {\n    vault.shares_total <= vault.token_total\n}"
+        ));
+        let rules = file(&files, "src/certora/spec.rs");
+        for line in [
+            "pub fn rule_deposit_sanity() {\n    let mut vault: Vault = nondet();\n    \
+             let amount: u64 = nondet();\n\n    process_deposit(&mut vault, amount).unwrap();",
+            "    let old_vault = vault;\n    cvlr_assume!(solvency(&vault));\n",
+            "    cvlr_assert!(vault.shares_total >= old_vault.shares_total);",
+            "pub fn rule_withdraw_solvency() {",
+        ] {
+            assert!(rules.contains(line), "missing `{line}` in\n{rules}");
+        }
+
+        let tests = file(&files, SPEC_TESTS_RS);
+        for line in [
+            "    let amount = 100u64;\n\n    let mut vault = crate::state::Vault::default();\n    \
+             let old_vault = vault;\n",
+            "    vault.token_total = u64::MAX;\n",
+            "crate::processor::process_withdraw(&mut vault, shares);",
+            "    assert_eq!(vault.token_total, 100u64);",
+        ] {
+            assert!(tests.contains(line), "missing `{line}` in\n{tests}");
+        }
+    }
+
+    #[test]
+    fn test_keys_and_wide_state() {
+        let spec = Spec::from_yaml_str(
+            "name: registry\ntarget: rust\n\
+             state: [{ name: Registry, fields: [{ name: admin, type: pubkey }, \
+             { name: slots, type: '[u8; 40]' }, { name: total, type: u128 }] }]\n\
+             functions:\n  - name: claim\n    accounts:\n\
+             \x20     - { name: registry, type: Registry, writable: true }\n\
+             \x20     - { name: admin, signer: true }\n\
+             \x20   requires: [registry.admin == admin]\n\
+             \x20   examples: [{ name: by_admin }]\n",
+        )
+        .unwrap();
+        let files = generate(&spec).unwrap();
+        let state = file(&files, "src/state.rs");
+        // Arrays of more than 32 elements have no derived `Default`
+        assert!(state.contains("#[derive(Clone, Copy, Debug, PartialEq, Eq)]"));
+        assert!(state.contains("            slots: [0; 40],\n"));
+        assert!(state.contains("admin: core::array::from_fn(|_| nondet()),"));
+        assert!(file(&files, "src/processor.rs")
+            .contains("pub fn process_claim(registry: &mut Registry, admin: Key)"));
+        let tests = file(&files, SPEC_TESTS_RS);
+        assert!(tests.contains("    let admin: Key = [1; 32];\n"));
+        assert!(tests.contains("    registry.admin = admin;\n"));
+        assert!(tests.contains("process_claim(&mut registry, admin);"));
+
+        let wide = Spec::from_yaml_str(
+            "name: wide\nstate: [{ name: Wide, fields: [{ name: x, type: u128 }] }]\n\
+             invariants: [{ name: small, expr: wide.x < 10 }]",
+        )
+        .unwrap();
+        let err = generate(&wide).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invariants.small: `u128` values cannot be used in expressions"
+        );
+    }
+}
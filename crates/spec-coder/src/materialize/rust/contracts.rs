@@ -0,0 +1,150 @@
+//! Function contracts for deductive verifiers.
+//!
+//! Every processor function of a library carries its clauses as
+//! `#[ensures]` contracts for Prusti and for Creusot, each behind the
+//! feature of its name, so `cargo prusti --features prusti` or
+//! `cargo creusot --features creusot` proves them at compile time for every
+//! input. Preconditions are checked by the function rather than assumed, so
+//! both they and the postconditions are contracts on the calls that
+//! succeed.
+
+use super::{Code, MaterializeError, Translator, Ty};
+use spec::{Function, Spec, Type};
+
+/// The specification language of a deductive verifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Dialect {
+    Prusti,
+    /// Pearlite, whose integers are compared and computed on as
+    /// mathematical integers
+    Creusot,
+}
+
+impl Dialect {
+    const ALL: [Dialect; 2] = [Dialect::Prusti, Dialect::Creusot];
+
+    /// The feature and the crate of the verifier.
+    fn name(self) -> &'static str {
+        match self {
+            Dialect::Prusti => "prusti",
+            Dialect::Creusot => "creusot",
+        }
+    }
+
+    /// The condition that the call succeeded.
+    fn success(self) -> &'static str {
+        match self {
+            Dialect::Prusti => "result.is_ok()",
+            Dialect::Creusot => "result == Ok(())",
+        }
+    }
+
+    /// The field `field` of the state `root`, as it was before the call if
+    /// `old` or after it otherwise.
+    pub(super) fn field(self, root: &str, field: &str, writable: bool, old: bool) -> String {
+        match self {
+            Dialect::Prusti if old => format!("old({root}.{field})"),
+            Dialect::Creusot if writable && !old => format!("(^{root}).{field}"),
+            _ => format!("{root}.{field}"),
+        }
+    }
+
+    /// The leaf `code` of type `ty`; Pearlite integers are viewed as
+    /// mathematical ones, so they need no widening.
+    pub(super) fn leaf(self, code: String, ty: &Type) -> Result<Code, String> {
+        match self {
+            Dialect::Creusot if super::native_int(ty).is_some() => {
+                Ok(Code::leaf(format!("{code}@"), Ty::Int("i128")))
+            }
+            _ => super::leaf(code, ty),
+        }
+    }
+}
+
+/// The contracts of `function`, one `cfg_attr` per verifier, or nothing if
+/// it has no clauses.
+pub(super) fn contracts(spec: &Spec, function: &Function) -> Result<String, MaterializeError> {
+    let mut out = String::new();
+    for dialect in Dialect::ALL {
+        // Preconditions are read from the state before the call
+        let clauses = function
+            .requires
+            .iter()
+            .enumerate()
+            .map(|(i, condition)| (format!("requires[{i}]"), &condition.expr, false))
+            .chain(
+                function
+                    .ensures
+                    .iter()
+                    .enumerate()
+                    .map(|(i, condition)| (format!("ensures[{i}]"), &condition.expr, true)),
+            );
+        let mut attributes = Vec::new();
+        for (location, expr, postcondition) in clauses {
+            let translator = Translator {
+                spec,
+                function,
+                postcondition,
+                old: !postcondition,
+                dialect: Some(dialect),
+            };
+            let code = translator
+                .expr(expr)
+                .map_err(|message| MaterializeError::Unsupported {
+                    location: format!("functions.{}.{location}", function.name),
+                    message,
+                })?;
+            attributes.push(format!(
+                "    {}_contracts::ensures({} ==> {})",
+                dialect.name(),
+                dialect.success(),
+                code.parenthesized()
+            ));
+        }
+        if !attributes.is_empty() {
+            out.push_str(&format!(
+                "#[cfg_attr(\n    feature = \"{}\",\n{}\n)]\n",
+                dialect.name(),
+                attributes.join(",\n")
+            ));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_contracts() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let deposit = spec.function("deposit").unwrap();
+        assert_eq!(
+            contracts(&spec, deposit).unwrap(),
+            "#[cfg_attr(\n    feature = \"prusti\",\n    \
+             prusti_contracts::ensures(result.is_ok() ==> (amount > 0)),\n    \
+             prusti_contracts::ensures(result.is_ok() ==> ((vault.token_total as i128) == \
+             ((old(vault.token_total) as i128) + (amount as i128)))),\n    \
+             prusti_contracts::ensures(result.is_ok() ==> (vault.shares_total >= \
+             old(vault.shares_total)))\n)]\n\
+             #[cfg_attr(\n    feature = \"creusot\",\n    \
+             creusot_contracts::ensures(result == Ok(()) ==> (amount@ > 0)),\n    \
+             creusot_contracts::ensures(result == Ok(()) ==> ((^vault).token_total@ == \
+             (vault.token_total@ + amount@))),\n    \
+             creusot_contracts::ensures(result == Ok(()) ==> ((^vault).shares_total@ >= \
+             vault.shares_total@))\n)]\n"
+        );
+        // Preconditions only see the state before the call
+        let withdraw = spec.function("withdraw").unwrap();
+        let contracts = contracts(&spec, withdraw).unwrap();
+        assert!(contracts.contains(
+            "prusti_contracts::ensures(result.is_ok() ==> (shares <= old(vault.shares_total))),"
+        ));
+        assert!(contracts.contains(
+            "creusot_contracts::ensures(result == Ok(()) ==> (shares@ <= vault.shares_total@)),"
+        ));
+    }
+}
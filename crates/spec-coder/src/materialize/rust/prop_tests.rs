@@ -0,0 +1,188 @@
+//! Property tests compiled from the invariants of the spec.
+//!
+//! As for Solana programs, `src/prop_tests.rs` holds a `proptest!` test per
+//! invariant rule that draws the state and the arguments at random, keeps
+//! the draws satisfying the invariant and checks that every call that
+//! succeeds preserves it. The invariants are the predicates of the certora
+//! module, repeated in the file since that module is only compiled for the
+//! prover.
+
+use super::{
+    invariants, key_bindings, native_int, predicate, predicates, strategy_parameter, use_state,
+    with_article, MaterializeError,
+};
+use spec::{Function, Spec, Type};
+use std::collections::BTreeSet;
+
+/// The strategy of a value of `ty`; keys and arrays are not drawn.
+fn strategy(ty: &Type) -> Option<String> {
+    let native = match ty {
+        Type::Bool => "bool",
+        Type::U128 => "u128",
+        ty => native_int(ty)?,
+    };
+    Some(format!("any::<{native}>()"))
+}
+
+/// Writes the property tests of `function`, returning whether they name
+/// keys.
+fn function_tests(out: &mut String, spec: &Spec, function: &Function) -> bool {
+    let invariants = invariants(spec, function);
+    if invariants.is_empty() {
+        return false;
+    }
+    // Arguments are drawn as one parameter, and the fields of each account
+    let mut params = Vec::new();
+    let mut args = Vec::new();
+    let mut body = String::new();
+    // Keys are distinct, as in the unit tests
+    let mut next_key = 0u8;
+    let mut key = || {
+        next_key += 1;
+        format!("[{next_key}; 32]")
+    };
+    for account in &function.accounts {
+        if account.ty.is_none() {
+            body.push_str(&format!("        let {}: Key = {};\n", account.name, key()));
+        }
+    }
+    for arg in &function.args {
+        match strategy(&arg.ty) {
+            Some(strategy) => args.push((arg.name.clone(), strategy)),
+            None if arg.ty == Type::Pubkey => {
+                body.push_str(&format!("        let {}: Key = {};\n", arg.name, key()));
+            }
+            None => body.push_str(&format!(
+                "        let {} = {};\n",
+                arg.name,
+                super::zero(&arg.ty)
+            )),
+        }
+    }
+    if !args.is_empty() {
+        params.push(strategy_parameter(&args));
+    }
+    let keys = key_bindings(spec, function);
+    let mut call = Vec::new();
+    for account in &function.accounts {
+        let Some(ty) = account.ty.as_deref() else {
+            call.push(account.name.clone());
+            continue;
+        };
+        let name = &account.name;
+        let mut assignments = String::new();
+        let mut fields = Vec::new();
+        for field in spec.account(ty).map_or(&[][..], |account| &account.fields) {
+            let access = format!("{name}.{}", field.name);
+            if let Some((_, key)) = keys.iter().find(|(field, _)| *field == access) {
+                assignments.push_str(&format!("        {access} = {key};\n"));
+            } else if let Some(strategy) = strategy(&field.ty) {
+                let drawn = format!("{name}_{}", field.name);
+                fields.push((drawn.clone(), strategy));
+                assignments.push_str(&format!("        {access} = {drawn};\n"));
+            }
+        }
+        if !fields.is_empty() {
+            params.push(strategy_parameter(&fields));
+        }
+        let mutable = if account.writable || !assignments.is_empty() {
+            "mut "
+        } else {
+            ""
+        };
+        body.push_str(&format!(
+            "\n        let {mutable}{name} = crate::state::{ty}::default();\n{assignments}"
+        ));
+        call.push(if account.writable {
+            format!("&mut {name}")
+        } else {
+            format!("&{name}")
+        });
+    }
+    call.extend(function.args.iter().map(|arg| arg.name.clone()));
+
+    for (invariant, inputs) in invariants {
+        let predicate = predicate(invariant, &inputs);
+        out.push_str(&format!(
+            "\n    /// Checks that {} preserves the {} invariant.\n    #[test]\n    \
+             fn prop_{}_{}(\n",
+            with_article(&function.name),
+            invariant.name,
+            function.name,
+            invariant.name
+        ));
+        for (pattern, strategy) in &params {
+            out.push_str(&format!("        {pattern} in {strategy},\n"));
+        }
+        out.push_str("    ) {\n");
+        out.push_str(body.trim_start_matches('\n'));
+        out.push_str(&format!(
+            "        prop_assume!({predicate});\n\n        \
+             let result = process_{}({});\n        if result.is_ok() {{\n            \
+             prop_assert!({predicate}, \"invariant `{}` violated\");\n        }}\n    }}\n",
+            function.name,
+            call.join(", "),
+            invariant.name
+        ));
+    }
+    next_key > 0
+}
+
+/// Generates `src/prop_tests.rs`, or `None` if no function can break an
+/// invariant.
+pub(super) fn prop_tests_rs(spec: &Spec) -> Result<Option<String>, MaterializeError> {
+    let mut tests = String::new();
+    let mut types = BTreeSet::new();
+    let mut checked = BTreeSet::new();
+    for function in &spec.functions {
+        if function_tests(&mut tests, spec, function) {
+            types.insert("Key".to_string());
+        }
+        for (invariant, _) in invariants(spec, function) {
+            checked.insert(invariant.name.as_str());
+        }
+    }
+    if tests.is_empty() {
+        return Ok(None);
+    }
+    let predicates = predicates(
+        spec,
+        spec.invariants
+            .iter()
+            .filter(|invariant| checked.contains(invariant.name.as_str())),
+        &mut types,
+    )?;
+    Ok(Some(format!(
+        "//! Property tests from the invariants of the spec.\n\n\
+         use crate::processor::*;\n{}use proptest::prelude::*;\n{predicates}\n\
+         proptest! {{{tests}}}\n",
+        use_state(&types)
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_prop_tests_rs() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let tests = prop_tests_rs(&spec).unwrap().unwrap();
+        for line in [
+            "use crate::processor::*;\nuse crate::state::Vault;\nuse proptest::prelude::*;\n",
+            "pub fn solvency(vault: &Vault) -> bool {",
+            "    fn prop_deposit_solvency(\n        amount in any::<u64>(),\n        \
+             (vault_shares_total, vault_token_total) in (any::<u64>(), any::<u64>()),\n    ) {\n        \
+             let mut vault = crate::state::Vault::default();\n        \
+             vault.shares_total = vault_shares_total;\n",
+            "        prop_assume!(solvency(&vault));\n\n        \
+             let result = process_deposit(&mut vault, amount);\n        if result.is_ok() {\n            \
+             prop_assert!(solvency(&vault), \"invariant `solvency` violated\");\n        }\n",
+            "fn prop_withdraw_solvency(",
+        ] {
+            assert!(tests.contains(line), "missing `{line}` in\n{tests}");
+        }
+    }
+}
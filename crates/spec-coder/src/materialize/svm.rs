@@ -0,0 +1,1283 @@
+//! Generator for Solana programs laid out like `examples/svm/materialized_vault`.
+//!
+//! Anchor programs share the layout, the processor functions and the certora
+//! module. Their state types are zero-copy Anchor accounts, whose data starts
+//! with an 8-byte discriminator, and `src/lib.rs` holds the `#[program]`
+//! module whose handlers call the processor functions on the accounts
+//! declared, with their constraints, in an `Accounts` struct per instruction.
+
+mod client;
+mod compute_units;
+mod cpi;
+mod fuzz;
+pub(crate) mod harness;
+mod idl;
+mod layout;
+mod litesvm;
+mod proofs;
+mod prop_tests;
+mod py_client;
+mod rules;
+mod spec_tests;
+mod svm_tests;
+mod ts_client;
+
+pub(crate) use prop_tests::strategy_parameter;
+pub(crate) use rules::{function_rules, invariant_accounts, invariants};
+
+use super::{
+    certora_summaries, GeneratedFile, MaterializeError, CERTORA_METADATA, CLIENT_RS,
+    COMPUTE_UNITS_RS, IDL_JSON, PROOFS_MOD, PROOFS_RS, PROP_TESTS_MOD, PROP_TESTS_RS,
+    PY_CLIENT_DIR, SPEC_TESTS_RS, SVM_TESTS_RS, TS_CLIENT_DIR,
+};
+use sha2::{Digest, Sha256};
+use spec::{AccountInput, BinaryOp, Expr, Function, Spec, Target, Type, UnaryOp};
+use std::collections::{BTreeSet, HashSet};
+
+pub(super) const CVLR_INLINING: &str = include_str!("../../assets/svm/cvlr_inlining_core.txt");
+pub(super) const CVLR_SUMMARIES: &str = include_str!("../../assets/svm/cvlr_summaries_core.txt");
+const ACCOUNT_RS: &str = include_str!("../../assets/svm/pinocchio/account.rs");
+
+/// The framework a Solana program is written with.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Framework {
+    /// Plain `solana-program` processor functions over `AccountInfo`s
+    #[default]
+    Native,
+    /// An Anchor `#[program]` calling the same processor functions
+    Anchor,
+    /// Pinocchio, with processor functions generic over the accounts they
+    /// take: pinocchio's on chain, solana-program's in rules and tests
+    Pinocchio,
+}
+
+impl Framework {
+    /// The range of the account data holding the state, past the
+    /// discriminator of Anchor accounts.
+    pub(crate) fn state_data(self) -> &'static str {
+        match self {
+            Framework::Native | Framework::Pinocchio => "",
+            Framework::Anchor => "[8..]",
+        }
+    }
+
+    pub(crate) fn of(target: Target) -> Framework {
+        match target {
+            Target::Anchor => Framework::Anchor,
+            Target::Pinocchio => Framework::Pinocchio,
+            Target::Svm | Target::Evm | Target::Rust => Framework::Native,
+        }
+    }
+}
+
+/// Generates every file of the example crate for `spec`.
+pub fn generate(spec: &Spec, framework: Framework) -> Result<Vec<GeneratedFile>, MaterializeError> {
+    let functions = spec
+        .functions
+        .iter()
+        .map(|function| FunctionGen::new(spec, function, framework))
+        .collect::<Result<Vec<_>, _>>()?;
+    let spec_tests = spec_tests::spec_tests_rs(spec, framework);
+    let prop_tests = prop_tests::prop_tests_rs(spec, framework)?;
+    let tests = (spec_tests.is_some(), prop_tests.is_some());
+    let lib_rs = match framework {
+        Framework::Native | Framework::Pinocchio => lib_rs(spec, framework, tests),
+        Framework::Anchor => anchor_lib_rs(spec, tests),
+    };
+    let mut files = vec![
+        GeneratedFile::new("Cargo.toml", cargo_toml(spec, framework)),
+        GeneratedFile::new("src/lib.rs", lib_rs),
+        GeneratedFile::new("src/state.rs", state_rs(spec, framework)?),
+        GeneratedFile::new(
+            "src/processor.rs",
+            processor_rs(spec, framework, &functions),
+        ),
+        GeneratedFile::new("src/certora.rs", rules::certora_rs(spec, framework)?),
+        GeneratedFile::new("src/certora/spec.rs", rules::spec_rs(spec, &functions)?),
+        GeneratedFile::new(PROOFS_RS, proofs::proofs_rs(spec, framework)?),
+        GeneratedFile::new(CLIENT_RS, client::client_rs(spec, framework)),
+    ];
+    files.extend(certora_summaries());
+    files.push(GeneratedFile::new(IDL_JSON, idl::idl_json(spec, framework)));
+    if framework == Framework::Pinocchio {
+        files.insert(2, GeneratedFile::new("src/account.rs", ACCOUNT_RS));
+    }
+    if let Some(spec_tests) = spec_tests {
+        files.push(GeneratedFile::new(SPEC_TESTS_RS, spec_tests));
+    }
+    if let Some(prop_tests) = prop_tests {
+        files.push(GeneratedFile::new(PROP_TESTS_RS, prop_tests));
+    }
+    if let Some(svm_tests) = svm_tests::svm_tests_rs(spec, framework)? {
+        files.push(GeneratedFile::new(SVM_TESTS_RS, svm_tests));
+    }
+    if let Some(compute_units) = compute_units::compute_units_rs(spec, framework) {
+        files.push(GeneratedFile::new(COMPUTE_UNITS_RS, compute_units));
+    }
+    for (path, contents) in fuzz::fuzz_files(spec, framework)? {
+        files.push(GeneratedFile::new(&path, contents));
+    }
+    for (path, contents) in ts_client::ts_client_files(spec, framework) {
+        files.push(GeneratedFile::new(&path, contents));
+    }
+    for (path, contents) in py_client::py_client_files(spec, framework) {
+        files.push(GeneratedFile::new(&path, contents));
+    }
+    Ok(files)
+}
+
+/// Generates the files of the CPI interface crate of the program, relative
+/// to the crate root.
+pub fn cpi_files(spec: &Spec, framework: Framework) -> Vec<GeneratedFile> {
+    cpi::cpi_files(spec, framework)
+        .into_iter()
+        .map(|(path, contents)| GeneratedFile::new(&path, contents))
+        .collect()
+}
+
+/// A program id derived from the program name, so that regenerating an
+/// example does not churn its `declare_id!`.
+pub fn program_id(name: &str) -> String {
+    let digest = Sha256::digest(format!("spec-coder:{name}").as_bytes());
+    bs58::encode(digest).into_string()
+}
+
+/// A snake case name in PascalCase, e.g. `BondingCurve`.
+pub(crate) fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// The Anchor `Accounts` struct of `function`, suffixed when its name would
+/// clash with a state type.
+pub(crate) fn accounts_struct(spec: &Spec, function: &Function) -> String {
+    let name = pascal_case(&function.name);
+    if spec.account(&name).is_some() {
+        format!("{name}Accounts")
+    } else {
+        name
+    }
+}
+
+/// Ordinal used in the account-array docs, e.g. "second".
+fn ordinal(index: usize) -> String {
+    const ORDINALS: [&str; 10] = [
+        "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+        "tenth",
+    ];
+    ORDINALS
+        .get(index)
+        .map_or_else(|| format!("{}th", index + 1), |s| s.to_string())
+}
+
+/// Writes `text` as `///` doc lines indented by `indent`.
+pub(crate) fn doc(out: &mut String, indent: &str, text: &str) {
+    for line in text.lines() {
+        if line.is_empty() {
+            out.push_str(&format!("{indent}///\n"));
+        } else {
+            out.push_str(&format!("{indent}/// {line}\n"));
+        }
+    }
+}
+
+/// The name of the package of the program, e.g. `solana-vault`.
+pub(crate) fn package_name(spec: &Spec, framework: Framework) -> String {
+    let name = spec.name.replace('_', "-");
+    match framework {
+        Framework::Native => format!("solana-{name}"),
+        Framework::Anchor => format!("anchor-{name}"),
+        Framework::Pinocchio => format!("pinocchio-{name}"),
+    }
+}
+
+fn cargo_toml(spec: &Spec, framework: Framework) -> String {
+    let package = package_name(spec, framework);
+    let mut certora = r#""cvlr", "cvlr-solana""#;
+    let mut dev_dependencies = "\n[dev-dependencies]\nproptest = \"1\"\n";
+    let (crate_type, features, dependencies) = match framework {
+        // Also a library for the fuzz crate to link
+        Framework::Native => (
+            r#""cdylib", "lib""#,
+            "client = []\nrt = []\n",
+            r#"solana-program = "1.18""#,
+        ),
+        // Rules and tests call the processor with solana-program accounts
+        Framework::Pinocchio => {
+            certora = r#""cvlr", "cvlr-solana", "solana-program""#;
+            dev_dependencies =
+                "\n[dev-dependencies]\nproptest = \"1\"\nsolana-program = \"1.18\"\n";
+            (
+                r#""cdylib", "lib""#,
+                "client = [\"solana-program\"]\n",
+                "pinocchio = \"0.8\"\npinocchio-pubkey = \"0.2\"\n\
+                 solana-program = { version = \"1.18\", optional = true }",
+            )
+        }
+        Framework::Anchor => (
+            r#""cdylib", "lib""#,
+            "client = []\nno-idl = []\nno-log-ix-name = []\ncpi = [\"no-entrypoint\"]\n\
+             idl-build = [\"anchor-lang/idl-build\"]\nanchor-debug = []\n",
+            "anchor-lang = \"0.30\"\nsolana-program = \"1.18\"",
+        ),
+    };
+    // The tests under `tests/` run the program built for the SVM, under
+    // `cargo test-sbf`
+    let dev_dependencies = format!("{dev_dependencies}litesvm = \"0.1\"\nsolana-sdk = \"1.18\"\n");
+    format!(
+        r#"[package]
+name = "{package}"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = [{crate_type}]
+
+[features]
+default = []
+certora = [{certora}]
+no-entrypoint = []
+{features}test-sbf = []
+custom-heap = []
+custom-panic = []
+
+[dependencies]
+{dependencies}
+bytemuck = {{ version = "1.14", features = ["derive"] }}
+spl-pod = "0.1"
+
+# Optional dependencies for formal verification
+cvlr = {{ version = "0.4", optional = true }}
+cvlr-solana = {{ version = "0.4", optional = true }}
+{dev_dependencies}
+[lints.rust]
+unexpected_cfgs = {{ level = "warn", check-cfg = ['cfg(target_os, values("solana"))', 'cfg(kani)'] }}
+
+{CERTORA_METADATA}"#,
+    )
+}
+
+/// The `src/lib.rs` of a native or Pinocchio program, declaring the unit and
+/// property test modules if `tests` says they exist.
+fn lib_rs(spec: &Spec, framework: Framework, tests: (bool, bool)) -> String {
+    let mut arms = String::new();
+    for (i, function) in spec.functions.iter().enumerate() {
+        arms.push_str(&format!(
+            "        {i} => {{\n            msg!(\"Instruction: {}\");\n            \
+             processor::process_{}(accounts, instruction_data)?;\n        }}\n",
+            function.name.replace('_', " "),
+            function.name,
+        ));
+    }
+    let (sdk, uses, declare_id) = match framework {
+        Framework::Pinocchio => (
+            "pinocchio",
+            "use pinocchio::{\n    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, \
+             ProgramResult,\n};\npub mod account;\n",
+            "pinocchio_pubkey::declare_id!",
+        ),
+        _ => (
+            "solana_program",
+            "use solana_program::{\n    account_info::AccountInfo, declare_id, entrypoint::ProgramResult, \
+             program_error::ProgramError,\n    pubkey::Pubkey,\n};\n",
+            "declare_id!",
+        ),
+    };
+    format!(
+        r#"{uses}pub mod processor;
+pub mod state;
+{spec_tests}{prop_tests}{proofs}
+// Include formal verification module only if certora feature is enabled.
+#[cfg(feature = "certora")]
+pub mod certora;
+
+// Include the off-chain client module only if client feature is enabled.
+#[cfg(feature = "client")]
+pub mod client;
+
+#[cfg(not(feature = "certora"))]
+use {sdk}::msg;
+// If certora feature is enabled, msg should be substituted with `clog!`.
+#[cfg(feature = "certora")]
+use cvlr::clog as msg;
+
+{declare_id}("{id}");
+
+#[cfg(not(feature = "no-entrypoint"))]
+use {sdk}::entrypoint;
+
+#[cfg(not(feature = "no-entrypoint"))]
+entrypoint!(process_instruction);
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {{
+    let (instruction_discriminant, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    match instruction_discriminant {{
+{arms}        _ => {{
+            msg!("Error: unknown instruction");
+            return Err(ProgramError::InvalidInstructionData);
+        }}
+    }}
+    Ok(())
+}}
+"#,
+        id = program_id(&spec.name),
+        spec_tests = if tests.0 {
+            "\n#[cfg(test)]\nmod spec_tests;\n"
+        } else {
+            ""
+        },
+        prop_tests = if tests.1 { PROP_TESTS_MOD } else { "" },
+        proofs = PROOFS_MOD,
+    )
+}
+
+/// The field declaring `account` in an Anchor `Accounts` struct, with the
+/// constraints that the account is distinct from the typed accounts in
+/// `before`.
+fn anchor_account_field(account: &AccountInput, before: &[&AccountInput]) -> String {
+    let mut constraints = Vec::new();
+    if account.writable {
+        constraints.push("mut".to_string());
+    }
+    if account.signer && account.ty.is_some() {
+        constraints.push("signer".to_string());
+    }
+    if account.ty.is_some() {
+        for other in before {
+            constraints.push(format!(
+                "constraint = {}.key() != {}.key()",
+                account.name, other.name
+            ));
+        }
+    }
+    let mut out = String::new();
+    if !constraints.is_empty() {
+        out.push_str(&format!("    #[account({})]\n", constraints.join(", ")));
+    }
+    let ty = match (&account.ty, account.signer) {
+        (Some(ty), _) => format!("AccountLoader<'info, {ty}>"),
+        (None, true) => "Signer<'info>".to_string(),
+        (None, false) => {
+            out.push_str("    /// CHECK: only its key is read by the processor\n");
+            "UncheckedAccount<'info>".to_string()
+        }
+    };
+    out.push_str(&format!("    pub {}: {ty},\n", account.name));
+    out
+}
+
+/// Writes the `#[program]` handler of `function`, which encodes its
+/// arguments as the processor function reads them and calls it.
+fn anchor_handler(out: &mut String, spec: &Spec, function: &Function) {
+    let description = function
+        .description
+        .clone()
+        .unwrap_or_else(|| format!("Process {} instruction.", with_article(&function.name)));
+    doc(out, "    ", &description);
+    let mut params = vec![format!("ctx: Context<{}>", accounts_struct(spec, function))];
+    params.extend(
+        function
+            .args
+            .iter()
+            .map(|arg| format!("{}: {}", arg.name, anchor_arg_type(&arg.ty))),
+    );
+    out.push_str(&format!(
+        "    pub fn {}({}) -> Result<()> {{\n",
+        function.name,
+        params.join(", ")
+    ));
+    let parts: Vec<String> = function
+        .args
+        .iter()
+        .map(|arg| match &arg.ty {
+            Type::Pubkey => format!("{}.as_ref()", arg.name),
+            Type::Bool => format!("&[u8::from({})]", arg.name),
+            _ => format!("&{}.to_le_bytes()", arg.name),
+        })
+        .collect();
+    let data = match parts.as_slice() {
+        [] => "&[]".to_string(),
+        [single] => single.clone(),
+        parts => {
+            out.push_str("        let mut instruction_data = Vec::new();\n");
+            for part in parts {
+                let part = part.strip_prefix('&').unwrap_or(part);
+                let part = if part.ends_with(".as_ref()") {
+                    part.to_string()
+                } else {
+                    format!("&{part}")
+                };
+                out.push_str(&format!(
+                    "        instruction_data.extend_from_slice({part});\n"
+                ));
+            }
+            "&instruction_data".to_string()
+        }
+    };
+    out.push_str(&format!(
+        "        processor::process_{}(&ctx.accounts.to_account_infos(), {data})?;\n        Ok(())\n    }}\n",
+        function.name
+    ));
+}
+
+/// The Rust type of an instruction argument of an Anchor program.
+fn anchor_arg_type(ty: &Type) -> &'static str {
+    match ty {
+        Type::Bool => "bool",
+        Type::Pubkey => "Pubkey",
+        ty => native_int(ty).unwrap_or("u64"),
+    }
+}
+
+fn anchor_lib_rs(spec: &Spec, tests: (bool, bool)) -> String {
+    let state_types: BTreeSet<&str> = spec
+        .functions
+        .iter()
+        .flat_map(|f| f.accounts.iter().filter_map(|a| a.ty.as_deref()))
+        .collect();
+    let mut handlers = String::new();
+    let mut structs = String::new();
+    for (i, function) in spec.functions.iter().enumerate() {
+        if i > 0 {
+            handlers.push('\n');
+        }
+        anchor_handler(&mut handlers, spec, function);
+        structs.push_str(&format!(
+            "\n/// The accounts of {} instruction.\n#[derive(Accounts)]\npub struct {}<'info> {{\n",
+            with_article(&function.name),
+            accounts_struct(spec, function)
+        ));
+        let mut typed = Vec::new();
+        for account in &function.accounts {
+            structs.push_str(&anchor_account_field(account, &typed));
+            if account.ty.is_some() {
+                typed.push(account);
+            }
+        }
+        structs.push_str("}\n");
+    }
+    let uses = match state_types.len() {
+        0 => String::new(),
+        1 => format!("use state::{};\n", state_types.first().unwrap()),
+        _ => format!(
+            "use state::{{{}}};\n",
+            state_types.into_iter().collect::<Vec<_>>().join(", ")
+        ),
+    };
+    format!(
+        r#"use anchor_lang::prelude::*;
+{uses}
+pub mod processor;
+pub mod state;
+{spec_tests}{prop_tests}{proofs}
+// Include formal verification module only if certora feature is enabled.
+#[cfg(feature = "certora")]
+pub mod certora;
+
+// Include the off-chain client module only if client feature is enabled.
+#[cfg(feature = "client")]
+pub mod client;
+
+declare_id!("{id}");
+
+#[program]
+pub mod {name} {{
+    use super::*;
+
+{handlers}}}
+{structs}"#,
+        id = program_id(&spec.name),
+        name = spec.name,
+        spec_tests = if tests.0 {
+            "\n#[cfg(test)]\nmod spec_tests;\n"
+        } else {
+            ""
+        },
+        prop_tests = if tests.1 { PROP_TESTS_MOD } else { "" },
+        proofs = PROOFS_MOD,
+    )
+}
+
+/// The Rust type of a field in the zero-copy layout.
+fn field_type(ty: &Type, pods: &mut BTreeSet<&'static str>) -> Result<String, String> {
+    Ok(match ty {
+        Type::Bool | Type::U8 => "u8".to_string(),
+        Type::U16 => pod(pods, "PodU16"),
+        Type::U32 => pod(pods, "PodU32"),
+        Type::U64 => pod(pods, "PodU64"),
+        Type::I64 => pod(pods, "PodI64"),
+        Type::Pubkey => "Pubkey".to_string(),
+        Type::U128 => return Err("u128 has no zero-copy representation on SVM".to_string()),
+        Type::Array(inner, len) => format!("[{}; {len}]", field_type(inner, pods)?),
+    })
+}
+
+fn pod(pods: &mut BTreeSet<&'static str>, name: &'static str) -> String {
+    pods.insert(name);
+    name.to_string()
+}
+
+fn state_rs(spec: &Spec, framework: Framework) -> Result<String, MaterializeError> {
+    let mut pods = BTreeSet::new();
+    let mut uses_pubkey = false;
+    let mut structs = String::new();
+    for account in &spec.state {
+        let description = account
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("The {} account data structure.", account.name));
+        doc(&mut structs, "", &description);
+        // `Default` is only implemented for arrays of up to 32 elements
+        let default = account
+            .fields
+            .iter()
+            .all(|field| !matches!(field.ty, Type::Array(_, len) if len > 32));
+        match framework {
+            Framework::Native | Framework::Pinocchio => {
+                structs.push_str("#[repr(C)]\n");
+                structs.push_str(&format!(
+                    "#[derive(Copy, Clone, Debug, {}Pod, Zeroable)]\n",
+                    if default { "Default, " } else { "" }
+                ));
+            }
+            // `zero_copy` derives the layout, `Pod` and `Zeroable` itself
+            Framework::Anchor => structs.push_str(&format!(
+                "#[account(zero_copy)]\n#[derive(Debug{})]\n",
+                if default { ", Default" } else { "" }
+            )),
+        }
+        structs.push_str(&format!("pub struct {} {{\n", account.name));
+        for field in &account.fields {
+            let ty = field_type(&field.ty, &mut pods).map_err(|message| {
+                MaterializeError::Unsupported {
+                    location: format!("state.{}.{}", account.name, field.name),
+                    message,
+                }
+            })?;
+            uses_pubkey |= ty.contains("Pubkey");
+            match (&field.description, &field.ty) {
+                (Some(description), _) => doc(&mut structs, "    ", description),
+                (None, Type::Bool) => doc(&mut structs, "    ", "Non-zero when set"),
+                (None, _) => {}
+            }
+            structs.push_str(&format!("    pub {}: {ty},\n", field.name));
+        }
+        structs.push_str("}\n\n");
+    }
+
+    let mut out = match framework {
+        Framework::Native | Framework::Pinocchio => {
+            String::from("use bytemuck::{Pod, Zeroable};\n")
+        }
+        Framework::Anchor => String::from("use anchor_lang::prelude::*;\n"),
+    };
+    match framework {
+        Framework::Native if uses_pubkey => out.push_str("use solana_program::pubkey::Pubkey;\n"),
+        Framework::Pinocchio if uses_pubkey => out.push_str("use pinocchio::pubkey::Pubkey;\n"),
+        _ => {}
+    }
+    match pods.len() {
+        0 => {}
+        1 => out.push_str(&format!(
+            "use spl_pod::primitives::{};\n",
+            pods.first().unwrap()
+        )),
+        _ => out.push_str(&format!(
+            "use spl_pod::primitives::{{{}}};\n",
+            pods.into_iter().collect::<Vec<_>>().join(", ")
+        )),
+    }
+    out.push('\n');
+    out.push_str(&structs);
+
+    // Pin the layout so that accidental changes show up in review
+    out.push_str("#[cfg(test)]\nmod tests {\n    use super::*;\n\n");
+    out.push_str("    #[test]\n    fn test_account_sizes() {\n");
+    for account in &spec.state {
+        let size: usize = account.fields.iter().map(|field| field.ty.size()).sum();
+        out.push_str(&format!(
+            "        assert_eq!(std::mem::size_of::<{}>(), {size});\n",
+            account.name
+        ));
+    }
+    out.push_str("    }\n}\n");
+    Ok(out)
+}
+
+/// The generated Rust type of an expression leaf.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum Ty {
+    Bool,
+    Key,
+    /// An integer of the given native type
+    Int(&'static str),
+}
+
+/// A translated expression.
+pub(super) struct Code {
+    pub(super) code: String,
+    pub(super) ty: Ty,
+    /// Whether the code is a leaf that needs no parentheses
+    pub(super) atomic: bool,
+}
+
+impl Code {
+    pub(super) fn leaf(code: String, ty: Ty) -> Code {
+        Code {
+            code,
+            ty,
+            atomic: true,
+        }
+    }
+
+    pub(super) fn parenthesized(&self) -> String {
+        if self.atomic {
+            self.code.clone()
+        } else {
+            format!("({})", self.code)
+        }
+    }
+
+    /// The code widened to `i128`, wide enough for arithmetic over any two
+    /// supported integers.
+    pub(super) fn wide(&self) -> String {
+        match self.ty {
+            Ty::Int("i128") => self.parenthesized(),
+            _ => format!("({} as i128)", self.code),
+        }
+    }
+}
+
+pub(crate) fn native_int(ty: &Type) -> Option<&'static str> {
+    Some(match ty {
+        Type::U8 => "u8",
+        Type::U16 => "u16",
+        Type::U32 => "u32",
+        Type::U64 => "u64",
+        Type::I64 => "i64",
+        _ => return None,
+    })
+}
+
+/// Translates conditions into Rust over the bindings of the generated
+/// processor function.
+#[derive(Copy, Clone)]
+struct Translator<'a> {
+    spec: &'a Spec,
+    function: &'a Function,
+    framework: Framework,
+    /// Whether `old(..)` is allowed, its account data being bound with an
+    /// `old_` prefix
+    postcondition: bool,
+    /// Whether the expression is inside `old(..)`
+    old: bool,
+}
+
+impl Translator<'_> {
+    fn path(&self, path: &[String]) -> Result<Code, String> {
+        let function = self.function;
+        let root = &path[0];
+        // Postconditions are checked by rules and tests, which hold
+        // solana-program keys even for pinocchio programs
+        let bytes = self.framework == Framework::Pinocchio && self.postcondition;
+        if let Some(arg) = function.arg(root) {
+            return Ok(match &arg.ty {
+                Type::Bool => Code::leaf(root.clone(), Ty::Bool),
+                Type::Pubkey if bytes => Code::leaf(format!("{root}.to_bytes()"), Ty::Key),
+                Type::Pubkey => Code::leaf(root.clone(), Ty::Key),
+                ty => Code::leaf(root.clone(), Ty::Int(native_int(ty).unwrap_or("u64"))),
+            });
+        }
+        let account = function.account(root).ok_or("unknown name")?;
+        let Some(field) = path.get(1) else {
+            let binding = account_binding(account);
+            let key = match self.framework {
+                Framework::Pinocchio if bytes => format!("{binding}.key.to_bytes()"),
+                Framework::Pinocchio => format!("*{binding}.key()"),
+                _ => format!("*{binding}.key"),
+            };
+            return Ok(Code::leaf(key, Ty::Key));
+        };
+        let ty = account.ty.as_deref().ok_or("untyped account")?;
+        let field = self
+            .spec
+            .account(ty)
+            .and_then(|account| account.field(field))
+            .ok_or("unknown field")?;
+        let prefix = if self.old { "old_" } else { "" };
+        let access = format!("{prefix}{root}.{}", field.name);
+        Ok(match &field.ty {
+            Type::Bool => Code {
+                code: format!("{access} != 0"),
+                ty: Ty::Bool,
+                atomic: false,
+            },
+            Type::U8 => Code::leaf(access, Ty::Int("u8")),
+            Type::Pubkey => Code::leaf(access, Ty::Key),
+            ty => match native_int(ty) {
+                Some(native) => Code::leaf(format!("{native}::from({access})"), Ty::Int(native)),
+                None => return Err(format!("`{ty}` fields cannot be used in expressions")),
+            },
+        })
+    }
+
+    fn expr(&self, expr: &Expr) -> Result<Code, String> {
+        Ok(match expr {
+            Expr::Int(value) => Code::leaf(value.to_string(), Ty::Int("literal")),
+            Expr::Bool(value) => Code::leaf(value.to_string(), Ty::Bool),
+            Expr::Path(path) => self.path(path)?,
+            Expr::Old(inner) if self.postcondition => {
+                Translator { old: true, ..*self }.expr(inner)?
+            }
+            Expr::Old(_) => return Err("`old(..)` is only allowed in postconditions".into()),
+            Expr::Unary(UnaryOp::Not, inner) => {
+                let inner = self.expr(inner)?;
+                Code {
+                    code: format!("!{}", inner.parenthesized()),
+                    ty: Ty::Bool,
+                    atomic: true,
+                }
+            }
+            Expr::Binary(BinaryOp::Implies, lhs, rhs) => {
+                let expr = Expr::binary(BinaryOp::Or, negate(lhs), (**rhs).clone());
+                self.expr(&expr)?
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+                if let Some(code) = unsigned_zero_comparison(*op, &lhs, &rhs) {
+                    return Ok(code);
+                }
+                let (lhs, rhs, ty) = match op {
+                    BinaryOp::And | BinaryOp::Or => {
+                        (lhs.parenthesized(), rhs.parenthesized(), Ty::Bool)
+                    }
+                    _ if op.is_boolean() => match (lhs.ty, rhs.ty) {
+                        // Operands of one type compare natively, others widen
+                        (l, r) if l == r || l == Ty::Int("literal") || r == Ty::Int("literal") => {
+                            (lhs.parenthesized(), rhs.parenthesized(), Ty::Bool)
+                        }
+                        (Ty::Int(_), Ty::Int(_)) => (lhs.wide(), rhs.wide(), Ty::Bool),
+                        _ => return Err(format!("mismatched operands of `{}`", op.symbol())),
+                    },
+                    // Arithmetic is done in i128, which cannot overflow for
+                    // a sum or product of two supported integers
+                    _ => (lhs.wide(), rhs.wide(), Ty::Int("i128")),
+                };
+                Code {
+                    code: format!("{lhs} {} {rhs}", op.symbol()),
+                    ty,
+                    atomic: false,
+                }
+            }
+        })
+    }
+}
+
+/// Rewrites comparisons of an unsigned value against zero that are trivially
+/// true or false, or only hold at zero, which clippy rejects as absurd.
+pub(super) fn unsigned_zero_comparison(op: BinaryOp, lhs: &Code, rhs: &Code) -> Option<Code> {
+    let unsigned = |code: &Code| matches!(code.ty, Ty::Int("u8" | "u16" | "u32" | "u64"));
+    let zero = |code: &Code| code.code == "0";
+    // Normalize to `value op 0`
+    let (value, op) = if unsigned(lhs) && zero(rhs) {
+        (lhs, op)
+    } else if zero(lhs) && unsigned(rhs) {
+        let flipped = match op {
+            BinaryOp::Lt => BinaryOp::Gt,
+            BinaryOp::Le => BinaryOp::Ge,
+            BinaryOp::Gt => BinaryOp::Lt,
+            BinaryOp::Ge => BinaryOp::Le,
+            op => op,
+        };
+        (rhs, flipped)
+    } else {
+        return None;
+    };
+    let code = match op {
+        BinaryOp::Le => format!("{} == 0", value.parenthesized()),
+        BinaryOp::Lt => "false".to_string(),
+        BinaryOp::Ge => "true".to_string(),
+        _ => return None,
+    };
+    Some(Code {
+        atomic: !code.contains(' '),
+        code,
+        ty: Ty::Bool,
+    })
+}
+
+/// The negation of a boolean expression, pushed down to the comparisons so
+/// that the generated checks read naturally.
+pub(crate) fn negate(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Bool(value) => Expr::Bool(!value),
+        Expr::Unary(UnaryOp::Not, inner) => (**inner).clone(),
+        Expr::Binary(op, lhs, rhs) => {
+            let (lhs, rhs) = ((**lhs).clone(), (**rhs).clone());
+            match op {
+                BinaryOp::Eq => Expr::binary(BinaryOp::Ne, lhs, rhs),
+                BinaryOp::Ne => Expr::binary(BinaryOp::Eq, lhs, rhs),
+                BinaryOp::Lt => Expr::binary(BinaryOp::Ge, lhs, rhs),
+                BinaryOp::Le => Expr::binary(BinaryOp::Gt, lhs, rhs),
+                BinaryOp::Gt => Expr::binary(BinaryOp::Le, lhs, rhs),
+                BinaryOp::Ge => Expr::binary(BinaryOp::Lt, lhs, rhs),
+                BinaryOp::And => Expr::binary(BinaryOp::Or, negate(&lhs), negate(&rhs)),
+                BinaryOp::Or => Expr::binary(BinaryOp::And, negate(&lhs), negate(&rhs)),
+                BinaryOp::Implies => Expr::binary(BinaryOp::And, lhs, negate(&rhs)),
+                _ => Expr::Unary(UnaryOp::Not, Box::new(expr.clone())),
+            }
+        }
+        _ => Expr::Unary(UnaryOp::Not, Box::new(expr.clone())),
+    }
+}
+
+/// Translates a postcondition of `function` into Rust over the bindings of
+/// its processor after the call, with the account data from before the call
+/// bound with an `old_` prefix.
+pub(crate) fn postcondition(
+    spec: &Spec,
+    function: &Function,
+    framework: Framework,
+    expr: &Expr,
+) -> Result<String, String> {
+    let translator = Translator {
+        spec,
+        function,
+        framework,
+        postcondition: true,
+        old: false,
+    };
+    Ok(translator.expr(expr)?.code)
+}
+
+/// The variable bound to an account's `AccountInfo`.
+pub(crate) fn account_binding(account: &AccountInput) -> String {
+    match account.ty {
+        Some(_) => format!("{}_account", account.name),
+        None => account.name.clone(),
+    }
+}
+
+/// An argument's size in the instruction data and the reader decoding it.
+pub(crate) fn arg_reader(ty: &Type) -> Option<(usize, &'static str)> {
+    Some(match ty {
+        Type::Bool | Type::U8 => (1, "read_u8"),
+        Type::U16 => (2, "read_u16"),
+        Type::U32 => (4, "read_u32"),
+        Type::U64 => (8, "read_u64"),
+        Type::I64 => (8, "read_i64"),
+        Type::Pubkey => (32, "read_pubkey"),
+        Type::U128 | Type::Array(..) => return None,
+    })
+}
+
+/// Everything needed to emit one processor function and its rule.
+struct FunctionGen<'a> {
+    function: &'a Function,
+    framework: Framework,
+    /// Preconditions translated to Rust, negated: the function fails when
+    /// one holds
+    checks: Vec<String>,
+    /// Names referenced by the preconditions
+    used: HashSet<String>,
+}
+
+impl<'a> FunctionGen<'a> {
+    fn new(
+        spec: &'a Spec,
+        function: &'a Function,
+        framework: Framework,
+    ) -> Result<FunctionGen<'a>, MaterializeError> {
+        for arg in &function.args {
+            if arg_reader(&arg.ty).is_none() {
+                return Err(MaterializeError::Unsupported {
+                    location: format!("functions.{}.{}", function.name, arg.name),
+                    message: format!("`{}` arguments are not supported", arg.ty),
+                });
+            }
+        }
+        let translator = Translator {
+            spec,
+            function,
+            framework,
+            postcondition: false,
+            old: false,
+        };
+        let mut checks = Vec::new();
+        let mut used = HashSet::new();
+        for (i, condition) in function.requires.iter().enumerate() {
+            let code = translator
+                .expr(&negate(&condition.expr))
+                .map_err(|message| MaterializeError::Unsupported {
+                    location: format!("functions.{}.requires[{i}]", function.name),
+                    message,
+                })?;
+            checks.push(code.code);
+            condition.expr.visit_paths(&mut |path, _| {
+                used.insert(path[0].clone());
+            });
+        }
+        Ok(FunctionGen {
+            function,
+            framework,
+            checks,
+            used,
+        })
+    }
+
+    /// The variable name for `name`, prefixed with `_` when unused.
+    fn binding(&self, name: &str, used: bool) -> String {
+        if used || self.used.contains(name) {
+            name.to_string()
+        } else {
+            format!("_{name}")
+        }
+    }
+
+    fn instruction_data_doc(&self) -> String {
+        let parts: Vec<String> = self
+            .function
+            .args
+            .iter()
+            .map(|arg| {
+                let (size, _) = arg_reader(&arg.ty).expect("checked in new");
+                let bytes = if size == 1 { "byte" } else { "bytes" };
+                format!("{size} {bytes} representing `{}`", arg.name)
+            })
+            .collect();
+        match parts.as_slice() {
+            [] => "unused".to_string(),
+            [first, rest @ ..] => rest.iter().fold(first.clone(), |doc, part| {
+                format!("{doc}, followed by {part}")
+            }),
+        }
+    }
+
+    fn accounts_doc(&self) -> String {
+        let accounts: Vec<String> = self
+            .function
+            .accounts
+            .iter()
+            .enumerate()
+            .map(|(i, account)| {
+                let signing = if account.signer { "signing " } else { "" };
+                let kind = if account.ty.is_some() { " account" } else { "" };
+                format!("{signing}{}{kind} {}", account.name, ordinal(i))
+            })
+            .collect();
+        if accounts.is_empty() {
+            "unused".to_string()
+        } else {
+            format!("Account array ({})", accounts.join(", "))
+        }
+    }
+
+    fn emit(&self, out: &mut String) {
+        let function = self.function;
+        out.push_str(&crate::drift::marker(function));
+        out.push('\n');
+        out.push_str(&format!(
+            "/// Process {} instruction.\n",
+            with_article(&function.name)
+        ));
+        if let Some(description) = &function.description {
+            doc(out, "", description);
+        }
+        out.push_str("///\n/// # Arguments\n");
+        bullet(out, &format!("`accounts`: {}", self.accounts_doc()));
+        bullet(
+            out,
+            &format!("`instruction_data`: {}", self.instruction_data_doc()),
+        );
+        let accounts = if function.accounts.is_empty() {
+            "_accounts"
+        } else {
+            "accounts"
+        };
+        let data = if function.args.is_empty() {
+            "_instruction_data"
+        } else {
+            "instruction_data"
+        };
+        let pinocchio = self.framework == Framework::Pinocchio;
+        if pinocchio {
+            out.push_str(&format!(
+                "pub fn process_{}<A: Account>({accounts}: &[A], {data}: &[u8]) -> ProgramResult {{\n",
+                function.name
+            ));
+        } else {
+            out.push_str(&format!(
+                "pub fn process_{}({accounts}: &[AccountInfo], {data}: &[u8]) -> ProgramResult {{\n",
+                function.name
+            ));
+        }
+        // Pinocchio accounts are read through methods of `Account`
+        let call = if pinocchio { "()" } else { "" };
+
+        let mut offset = 0;
+        for arg in &function.args {
+            let (size, reader) = arg_reader(&arg.ty).expect("checked in new");
+            let read = format!("{reader}(instruction_data, {offset})?");
+            let value = if arg.ty == Type::Bool {
+                format!("{read} != 0")
+            } else {
+                read
+            };
+            out.push_str(&format!(
+                "    let {} = {value};\n",
+                self.binding(&arg.name, false)
+            ));
+            offset += size;
+        }
+        if !function.args.is_empty() {
+            out.push('\n');
+        }
+
+        for (i, account) in function.accounts.iter().enumerate() {
+            let binding = match account.ty {
+                Some(_) => account_binding(account),
+                None => self.binding(&account.name, account.signer),
+            };
+            let get = if i == 0 {
+                "first()".to_string()
+            } else {
+                format!("get({i})")
+            };
+            out.push_str(&format!(
+                "    let {binding} = accounts.{get}.ok_or(ProgramError::NotEnoughAccountKeys)?;\n"
+            ));
+        }
+        for account in function.accounts.iter().filter(|a| a.signer) {
+            out.push_str(&format!(
+                "    if !{}.is_signer{call} {{\n        return Err(ProgramError::MissingRequiredSignature);\n    }}\n",
+                account_binding(account)
+            ));
+        }
+        let typed: Vec<&AccountInput> = function
+            .accounts
+            .iter()
+            .filter(|a| a.ty.is_some())
+            .collect();
+        let mut aliases = Vec::new();
+        for (i, a) in typed.iter().enumerate() {
+            for b in &typed[i + 1..] {
+                aliases.push(format!(
+                    "{}.key{call} == {}.key{call}",
+                    account_binding(a),
+                    account_binding(b)
+                ));
+            }
+        }
+        if !aliases.is_empty() {
+            out.push_str(&format!(
+                "    if {} {{\n        return Err(ProgramError::InvalidArgument);\n    }}\n",
+                aliases.join(" || ")
+            ));
+        }
+
+        if !typed.is_empty() {
+            let names: Vec<&str> = typed.iter().map(|a| a.name.as_str()).collect();
+            out.push_str(&format!(
+                "\n    // Borrow and read {} data\n",
+                join_names(&names)
+            ));
+        }
+        let state = self.framework.state_data();
+        for account in &typed {
+            let ty = account.ty.as_deref().unwrap();
+            let binding = self.binding(&account.name, false);
+            let info = account_binding(account);
+            let (borrow, borrow_mut) = if pinocchio {
+                ("data()?", "data_mut()?")
+            } else {
+                ("data.borrow()", "data.borrow_mut()")
+            };
+            if account.writable {
+                out.push_str(&format!(
+                    "    let mut {name}_data = {info}.{borrow_mut};\n    \
+                     let {binding}: &mut {ty} = bytemuck::from_bytes_mut(&mut {name}_data{state});\n",
+                    name = account.name
+                ));
+            } else {
+                out.push_str(&format!(
+                    "    let {name}_data = {info}.{borrow};\n    \
+                     let {binding}: &{ty} = bytemuck::from_bytes(&{name}_data{state});\n",
+                    name = account.name
+                ));
+            }
+        }
+
+        if !self.checks.is_empty() {
+            out.push('\n');
+        }
+        for check in &self.checks {
+            out.push_str(&format!(
+                "    if {check} {{\n        return Err(ProgramError::InvalidArgument);\n    }}\n"
+            ));
+        }
+
+        if !function.ensures.is_empty() {
+            out.push_str("\n    // Establish the postconditions:\n");
+            for condition in &function.ensures {
+                out.push_str(&format!("    // - {}\n", condition.expr));
+                if let Some(description) = &condition.description {
+                    out.push_str(&format!("    //   {description}\n"));
+                }
+            }
+        }
+        out.push_str("\n    Ok(())\n}\n");
+    }
+}
+
+/// Writes a `/// - ` doc bullet, wrapped at 80 columns with continuation
+/// lines indented under the text.
+fn bullet(out: &mut String, text: &str) {
+    const WIDTH: usize = 80;
+    let mut line = String::from("/// -");
+    for word in text.split(' ') {
+        if line.len() + 1 + word.len() > WIDTH && line.len() > "/// -".len() {
+            out.push_str(&line);
+            out.push('\n');
+            line = String::from("///  ");
+        }
+        line.push(' ');
+        line.push_str(word);
+    }
+    out.push_str(&line);
+    out.push('\n');
+}
+
+/// A function name as prose with its indefinite article, e.g. "an open
+/// ticket".
+pub(crate) fn with_article(name: &str) -> String {
+    let name = name.replace('_', " ");
+    let article = if name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        "an"
+    } else {
+        "a"
+    };
+    format!("{article} {name}")
+}
+
+/// Joins names as prose, e.g. "market, pool and position".
+fn join_names(names: &[&str]) -> String {
+    match names {
+        [] => String::new(),
+        [name] => name.to_string(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
+
+fn processor_rs(spec: &Spec, framework: Framework, functions: &[FunctionGen]) -> String {
+    let state_types: BTreeSet<&str> = spec
+        .functions
+        .iter()
+        .flat_map(|f| f.accounts.iter().filter_map(|a| a.ty.as_deref()))
+        .collect();
+    let readers: BTreeSet<&str> = spec
+        .functions
+        .iter()
+        .flat_map(|f| f.args.iter().filter_map(|a| arg_reader(&a.ty)))
+        .map(|(_, reader)| reader)
+        .collect();
+
+    let mut out = String::new();
+    if framework == Framework::Pinocchio {
+        out.push_str("use crate::account::Account;\n");
+    }
+    match state_types.len() {
+        0 => {}
+        1 => out.push_str(&format!(
+            "use crate::state::{};\n",
+            state_types.first().unwrap()
+        )),
+        _ => out.push_str(&format!(
+            "use crate::state::{{{}}};\n",
+            state_types.iter().copied().collect::<Vec<_>>().join(", ")
+        )),
+    }
+    if framework == Framework::Pinocchio {
+        if readers.contains("read_pubkey") {
+            out.push_str(
+                "use pinocchio::{program_error::ProgramError, pubkey::Pubkey, ProgramResult};\n\n",
+            );
+        } else {
+            out.push_str("use pinocchio::{program_error::ProgramError, ProgramResult};\n\n");
+        }
+    } else {
+        out.push_str("use solana_program::{\n    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,\n");
+        if readers.contains("read_pubkey") {
+            out.push_str("    pubkey::Pubkey,\n");
+        }
+        out.push_str("};\n\n");
+    }
+
+    for reader in &readers {
+        out.push_str(&reader_fn(reader));
+        out.push('\n');
+    }
+    for (i, function) in functions.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        function.emit(&mut out);
+    }
+    out
+}
+
+fn reader_fn(reader: &str) -> String {
+    match reader {
+        "read_u8" => "/// Reads the byte at `offset` in the instruction data.
+fn read_u8(instruction_data: &[u8], offset: usize) -> Result<u8, ProgramError> {
+    instruction_data
+        .get(offset)
+        .copied()
+        .ok_or(ProgramError::InvalidInstructionData)
+}
+"
+        .to_string(),
+        "read_pubkey" => "/// Reads a `Pubkey` starting at `offset` in the instruction data.
+fn read_pubkey(instruction_data: &[u8], offset: usize) -> Result<Pubkey, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 32)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    Pubkey::try_from(bytes).map_err(|_| ProgramError::InvalidInstructionData)
+}
+"
+        .to_string(),
+        _ => {
+            let ty = reader.trim_start_matches("read_");
+            let size = match ty {
+                "u16" => 2,
+                "u32" => 4,
+                _ => 8,
+            };
+            format!(
+                "/// Reads a little-endian `{ty}` starting at `offset` in the instruction data.
+fn {reader}(instruction_data: &[u8], offset: usize) -> Result<{ty}, ProgramError> {{
+    let bytes = instruction_data
+        .get(offset..offset + {size})
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; {size}];
+    buf.copy_from_slice(bytes);
+    Ok({ty}::from_le_bytes(buf))
+}}
+"
+            )
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! The Rust client module of the program.
+//!
+//! `src/client.rs`, behind the `client` feature, has per function an
+//! `<function>_ix` builder taking the keys of its accounts and then its
+//! arguments and returning the `Instruction`, encoded as the entrypoint
+//! decodes it, and per state type a `decode_<type>` function reading the
+//! account data, past the discriminator of Anchor accounts, so bots,
+//! indexers and tests off chain need not encode instruction data by hand.
+
+use super::litesvm::discriminator;
+use super::{doc, with_article, Framework};
+use spec::{Account, Function, Spec, Type};
+
+/// The Rust type of an argument of a builder.
+pub(super) fn arg_type(ty: &Type) -> &'static str {
+    match ty {
+        Type::Bool => "bool",
+        Type::U8 => "u8",
+        Type::U16 => "u16",
+        Type::U32 => "u32",
+        Type::U64 => "u64",
+        Type::I64 => "i64",
+        Type::Pubkey => "Pubkey",
+        Type::U128 | Type::Array(..) => unreachable!("rejected as arguments"),
+    }
+}
+
+/// A PascalCase name in snake case, e.g. `bonding_curve`.
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    out
+}
+
+/// Writes the `<function>_ix` builder of `function`, the function at
+/// `index`.
+pub(super) fn builder(out: &mut String, index: usize, function: &Function, framework: Framework) {
+    let mut params: Vec<String> = function
+        .accounts
+        .iter()
+        .map(|account| format!("{}: Pubkey", account.name))
+        .collect();
+    params.extend(
+        function
+            .args
+            .iter()
+            .map(|arg| format!("{}: {}", arg.name, arg_type(&arg.ty))),
+    );
+    out.push('\n');
+    match &function.description {
+        Some(description) => doc(out, "", description),
+        None => doc(
+            out,
+            "",
+            &format!("Builds {} instruction.", with_article(&function.name)),
+        ),
+    }
+    out.push_str(&format!(
+        "pub fn {}_ix({}) -> Instruction {{\n",
+        function.name,
+        params.join(", ")
+    ));
+    let data = match framework {
+        Framework::Native | Framework::Pinocchio => format!("vec![{index}u8]"),
+        Framework::Anchor => {
+            let bytes: Vec<String> = discriminator(&format!("global:{}", function.name))
+                .iter()
+                .map(u8::to_string)
+                .collect();
+            format!("vec![{}]", bytes.join(", "))
+        }
+    };
+    let mutable = if function.args.is_empty() { "" } else { "mut " };
+    out.push_str(&format!("    let {mutable}data = {data};\n"));
+    for arg in &function.args {
+        let bytes = match &arg.ty {
+            Type::Bool => format!("&[u8::from({})]", arg.name),
+            Type::Pubkey => format!("{}.as_ref()", arg.name),
+            _ => format!("&{}.to_le_bytes()", arg.name),
+        };
+        out.push_str(&format!("    data.extend_from_slice({bytes});\n"));
+    }
+    out.push_str("    Instruction {\n        program_id: program_id(),\n        accounts: vec![\n");
+    for account in &function.accounts {
+        let meta = if account.writable {
+            "new"
+        } else {
+            "new_readonly"
+        };
+        out.push_str(&format!(
+            "            AccountMeta::{meta}({}, {}),\n",
+            account.name, account.signer
+        ));
+    }
+    out.push_str("        ],\n        data,\n    }\n}\n");
+}
+
+fn decoder(out: &mut String, account: &Account, framework: Framework) {
+    let name = &account.name;
+    out.push_str(&format!(
+        "\n/// Decodes the data of {} account.\n\
+         pub fn decode_{}(data: &[u8]) -> Result<{name}, ProgramError> {{\n",
+        with_article(name),
+        snake_case(name)
+    ));
+    if framework == Framework::Anchor {
+        let bytes: Vec<String> = discriminator(&format!("account:{name}"))
+            .iter()
+            .map(u8::to_string)
+            .collect();
+        out.push_str(&format!(
+            "    let data = data\n        .strip_prefix(&[{}])\n        \
+             .ok_or(ProgramError::InvalidAccountData)?;\n",
+            bytes.join(", ")
+        ));
+    }
+    out.push_str(&format!(
+        "    data\n        .get(..core::mem::size_of::<{name}>())\n        \
+         .map(bytemuck::pod_read_unaligned)\n        \
+         .ok_or(ProgramError::InvalidAccountData)\n}}\n"
+    ));
+}
+
+/// Generates `src/client.rs`.
+pub(super) fn client_rs(spec: &Spec, framework: Framework) -> String {
+    let mut out = String::from(
+        "//! Instruction builders and state decoders for off-chain clients.\n\n\
+         use solana_program::instruction::{AccountMeta, Instruction};\n\
+         use solana_program::program_error::ProgramError;\n\
+         use solana_program::pubkey::Pubkey;\n",
+    );
+    let states: Vec<&str> = spec.state.iter().map(|a| a.name.as_str()).collect();
+    match states.as_slice() {
+        [] => {}
+        [state] => out.push_str(&format!("\nuse crate::state::{state};\n")),
+        states => out.push_str(&format!("\nuse crate::state::{{{}}};\n", states.join(", "))),
+    }
+    let program_id = match framework {
+        Framework::Native | Framework::Anchor => "crate::id()",
+        Framework::Pinocchio => "Pubkey::new_from_array(crate::id())",
+    };
+    out.push_str(&format!(
+        "\n/// The id of the program.\npub fn program_id() -> Pubkey {{\n    {program_id}\n}}\n"
+    ));
+    for (index, function) in spec.functions.iter().enumerate() {
+        builder(&mut out, index, function, framework);
+    }
+    for account in &spec.state {
+        decoder(&mut out, account, framework);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_client_rs() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let out = client_rs(&spec, Framework::Native);
+        for line in [
+            "use crate::state::Vault;\n",
+            "pub fn program_id() -> Pubkey {\n    crate::id()\n}\n",
+            "/// Burns shares and returns the corresponding tokens.\n\
+             pub fn withdraw_ix(vault: Pubkey, shares: u64) -> Instruction {\n    \
+             let mut data = vec![1u8];\n    \
+             data.extend_from_slice(&shares.to_le_bytes());\n",
+            "            AccountMeta::new(vault, false),\n",
+            "pub fn decode_vault(data: &[u8]) -> Result<Vault, ProgramError> {\n    \
+             data\n        .get(..core::mem::size_of::<Vault>())\n",
+        ] {
+            assert!(out.contains(line), "missing `{line}` in\n{out}");
+        }
+
+        let out = client_rs(&spec, Framework::Anchor);
+        // sha256("global:withdraw")[..8]
+        assert!(out.contains("    let mut data = vec![183, 18, 70, 156, 148, 109, 161, 34];\n"));
+        assert!(out.contains("    let data = data\n        .strip_prefix(&["));
+        let out = client_rs(&spec, Framework::Pinocchio);
+        assert!(out.contains("    Pubkey::new_from_array(crate::id())\n"));
+        assert_eq!(snake_case("BondingCurve"), "bonding_curve");
+    }
+}
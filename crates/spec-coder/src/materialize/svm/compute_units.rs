@@ -0,0 +1,134 @@
+//! Compute-unit measurements of the instruction handlers.
+//!
+//! `tests/compute_units.rs` sends every instruction with a compute-unit
+//! budget to the program built for the SVM and loaded in LiteSVM, once per
+//! example expected to succeed, or once with zeroed accounts and arguments
+//! if there is none, and prints the compute units each transaction
+//! consumed. Building the program takes the Solana toolchain, so the tests
+//! only run under `cargo test-sbf`, which builds it first.
+
+use super::litesvm::{self, instruction, instruction_data, keys, states};
+use super::spec_tests::{literal, lookup};
+use super::Framework;
+use spec::{Example, Function, Outcome, Spec, Type};
+
+/// Writes the test measuring `function` called as in `example`, or with
+/// zeroed accounts and arguments if it is `None`.
+fn test(
+    out: &mut String,
+    spec: &Spec,
+    framework: Framework,
+    function: &Function,
+    example: Option<&Example>,
+) {
+    let name = example.map_or("zeroed", |example| example.name.as_str());
+    let value = |name: &str, ty: &Type| literal(lookup(example?, name)?, ty);
+    out.push_str(&format!(
+        "\n#[test]\nfn {}_{name}() {{\n    let mut svm = svm();\n",
+        function.name
+    ));
+    instruction_data(out, spec, framework, function, &value);
+    let signers = keys(out, function);
+    states(out, spec, framework, function, &value);
+    instruction(out, function);
+    out.push_str(&format!(
+        "    measure(&mut svm, \"{}\", \"{name}\", instruction, &[{}]);\n}}\n",
+        function.name,
+        signers.join(", ")
+    ));
+}
+
+/// Generates `tests/compute_units.rs`, or `None` if no function of the spec
+/// has a compute-unit budget.
+pub(super) fn compute_units_rs(spec: &Spec, framework: Framework) -> Option<String> {
+    let functions: Vec<&Function> = spec
+        .functions
+        .iter()
+        .filter(|function| function.compute_units.is_some())
+        .collect();
+    if functions.is_empty() {
+        return None;
+    }
+    let mut out = litesvm::preamble(
+        spec,
+        framework,
+        "//! Compute units consumed by the instructions with a budget in the spec.\n//!\n\
+         //! Run with `cargo test-sbf --test compute_units -- --nocapture`, which\n\
+         //! builds the program first.\n",
+    );
+    out.push_str(
+        r#"
+/// Sends `instruction`, signed by `signers`, and prints the compute units
+/// it consumed, whether it succeeded or not.
+fn measure(
+    svm: &mut LiteSVM,
+    function: &str,
+    example: &str,
+    instruction: Instruction,
+    signers: &[&Keypair],
+) {
+    let units = match send(svm, instruction, signers) {
+        Ok(meta) => meta.compute_units_consumed,
+        Err(failed) => failed.meta.compute_units_consumed,
+    };
+    println!("compute units: {function} {example} {units}");
+}
+"#,
+    );
+    for function in functions {
+        let examples: Vec<&Example> = function
+            .examples
+            .iter()
+            .filter(|example| example.expect == Outcome::Ok)
+            .collect();
+        if examples.is_empty() {
+            test(&mut out, spec, framework, function, None);
+        }
+        for example in examples {
+            test(&mut out, spec, framework, function, Some(example));
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_compute_units_rs() {
+        let mut spec = Spec::from_yaml_str(VAULT).unwrap();
+        assert_eq!(compute_units_rs(&spec, Framework::Native), None);
+        spec.functions[1].compute_units = Some(5_000);
+        spec.functions[2].compute_units = Some(5_000);
+        spec.functions[2].examples.clear();
+        let out = compute_units_rs(&spec, Framework::Native).unwrap();
+        for line in [
+            "#![cfg(feature = \"test-sbf\")]\n",
+            "    svm.add_program_from_file(program_id(), format!(\"{dir}/solana_vault.so\"))\n",
+            "fn withdraw_everything() {\n    let mut svm = svm();\n    \
+             let mut instruction_data = vec![1u8];\n    let shares = 50u64;\n    \
+             instruction_data.extend_from_slice(&shares.to_le_bytes());\n",
+            "    let mut vault: solana_vault::state::Vault = bytemuck::Zeroable::zeroed();\n    \
+             vault.shares_total = 50u64.into();\n    vault.token_total = 80u64.into();\n    \
+             set_state(&mut svm, vault_key, bytemuck::bytes_of(&vault).to_vec());\n",
+            "        vec![AccountMeta::new(vault_key, false)],\n",
+            "    measure(&mut svm, \"withdraw\", \"everything\", instruction, &[]);\n",
+            "fn reward_zeroed() {\n    let mut svm = svm();\n    \
+             let mut instruction_data = vec![2u8];\n    let amount = 0u64;\n",
+        ] {
+            assert!(out.contains(line), "missing `{line}` in\n{out}");
+        }
+        // Failing examples are not measured, nor functions without a budget
+        assert!(!out.contains("fn withdraw_more_than_outstanding()"));
+        assert!(!out.contains("fn deposit_"));
+
+        let out = compute_units_rs(&spec, Framework::Anchor).unwrap();
+        // sha256("global:withdraw")[..8]
+        assert!(out.contains(
+            "    let mut instruction_data = [183, 18, 70, 156, 148, 109, 161, 34].to_vec();\n"
+        ));
+    }
+}
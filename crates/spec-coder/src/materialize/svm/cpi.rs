@@ -0,0 +1,135 @@
+//! The CPI interface crate of the program.
+//!
+//! `cpi/` is a crate depending on solana-program alone, so other programs
+//! can call the program through CPI without depending on its source. It
+//! declares the program id and has, per function, the `<function>_ix`
+//! builder of the client module and a `<function>` function invoking the
+//! instruction on the `AccountInfo`s of its accounts, signing with the
+//! seeds given for the accounts the caller derives.
+
+use super::client::{arg_type, builder};
+use super::{package_name, program_id, Framework};
+use crate::materialize::CPI_DIR;
+use spec::{Function, Spec};
+
+fn cargo_toml(spec: &Spec, framework: Framework) -> String {
+    format!(
+        r#"[package]
+name = "{}-cpi"
+version = "0.1.0"
+edition = "2021"
+description = "CPI interface of the {} program"
+
+[dependencies]
+solana-program = "1.18"
+
+# Kept out of any workspace above the crate
+[workspace]
+members = ["."]
+"#,
+        package_name(spec, framework),
+        spec.name
+    )
+}
+
+/// Writes the function invoking `function` through CPI.
+fn invoke(out: &mut String, function: &Function) {
+    let mut params = vec!["program: &AccountInfo<'info>".to_string()];
+    params.extend(
+        function
+            .accounts
+            .iter()
+            .map(|account| format!("{}: &AccountInfo<'info>", account.name)),
+    );
+    params.extend(
+        function
+            .args
+            .iter()
+            .map(|arg| format!("{}: {}", arg.name, arg_type(&arg.ty))),
+    );
+    params.push("signer_seeds: &[&[&[u8]]]".to_string());
+    let mut keys: Vec<String> = function
+        .accounts
+        .iter()
+        .map(|account| format!("*{}.key", account.name))
+        .collect();
+    keys.extend(function.args.iter().map(|arg| arg.name.clone()));
+    let mut infos: Vec<String> = function
+        .accounts
+        .iter()
+        .map(|account| format!("{}.clone()", account.name))
+        .collect();
+    infos.push("program.clone()".to_string());
+    out.push_str(&format!(
+        "\n/// Calls `{name}` through CPI, signing with `signer_seeds` for the\n\
+         /// accounts the caller derives.\n\
+         pub fn {name}<'info>({}) -> ProgramResult {{\n    \
+         invoke_signed(&{name}_ix({}), &[{}], signer_seeds)\n}}\n",
+        params.join(", "),
+        keys.join(", "),
+        infos.join(", "),
+        name = function.name
+    ));
+}
+
+fn lib_rs(spec: &Spec, framework: Framework) -> String {
+    let mut out = format!(
+        "//! CPI interface of the {name} program: instruction builders and the\n\
+         //! functions invoking them.\n\n\
+         use solana_program::account_info::AccountInfo;\n\
+         use solana_program::entrypoint::ProgramResult;\n\
+         use solana_program::instruction::{{AccountMeta, Instruction}};\n\
+         use solana_program::program::invoke_signed;\n\
+         use solana_program::pubkey::Pubkey;\n\n\
+         solana_program::declare_id!(\"{id}\");\n\n\
+         /// The id of the {name} program.\n\
+         pub fn program_id() -> Pubkey {{\n    ID\n}}\n",
+        name = spec.name,
+        id = program_id(&spec.name)
+    );
+    for (index, function) in spec.functions.iter().enumerate() {
+        builder(&mut out, index, function, framework);
+    }
+    for function in &spec.functions {
+        invoke(&mut out, function);
+    }
+    out
+}
+
+/// Generates the files of the CPI crate, relative to the crate root.
+pub(super) fn cpi_files(spec: &Spec, framework: Framework) -> Vec<(String, String)> {
+    vec![
+        (format!("{CPI_DIR}/Cargo.toml"), cargo_toml(spec, framework)),
+        (format!("{CPI_DIR}/src/lib.rs"), lib_rs(spec, framework)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_cpi_files() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let files = cpi_files(&spec, Framework::Anchor);
+        assert!(files[0].1.contains("name = \"anchor-vault-cpi\"\n"));
+        let lib = &files[1].1;
+        for line in [
+            &format!(
+                "solana_program::declare_id!(\"{}\");\n",
+                program_id("vault")
+            ),
+            "pub fn withdraw_ix(vault: Pubkey, shares: u64) -> Instruction {\n",
+            // sha256("global:withdraw")[..8]
+            "    let mut data = vec![183, 18, 70, 156, 148, 109, 161, 34];\n",
+            "pub fn withdraw<'info>(program: &AccountInfo<'info>, vault: &AccountInfo<'info>, \
+             shares: u64, signer_seeds: &[&[&[u8]]]) -> ProgramResult {\n    \
+             invoke_signed(&withdraw_ix(*vault.key, shares), &[vault.clone(), program.clone()], \
+             signer_seeds)\n}\n",
+        ] {
+            assert!(lib.contains(line), "missing `{line}` in\n{lib}");
+        }
+    }
+}
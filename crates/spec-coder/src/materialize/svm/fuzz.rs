@@ -0,0 +1,320 @@
+//! Fuzz targets over the instruction handlers.
+//!
+//! `fuzz/` is a cargo-fuzz crate with a target per processor function,
+//! named after it. A target splits the input into the data of each typed
+//! account and the instruction data, calls the function on them and, when
+//! the call succeeds, checks that it preserved every invariant that held on
+//! the accounts before. The instruction data is never padded, so short or
+//! malformed inputs reach the parsing code that the rules, which draw
+//! well-formed arguments, never exercise.
+
+use super::harness::key_bindings;
+use super::rules::{holds, invariant_code, invariants};
+use super::{account_binding, package_name, Framework};
+use crate::materialize::{MaterializeError, FUZZ_DIR};
+use spec::{Function, Spec};
+
+/// Binds the state of the typed accounts in `names` from the data of their
+/// `AccountInfo`s, indented by `indent`.
+fn bind_states(
+    code: &mut String,
+    program: &str,
+    function: &Function,
+    framework: Framework,
+    names: &[String],
+    indent: &str,
+) {
+    for account in &function.accounts {
+        let (Some(ty), true) = (account.ty.as_deref(), names.contains(&account.name)) else {
+            continue;
+        };
+        code.push_str(&format!(
+            "{indent}let {name}_data = {binding}.data.borrow();\n{indent}\
+             let {name}: &{program}::state::{ty} = bytemuck::from_bytes(&{name}_data{state});\n",
+            name = account.name,
+            binding = account_binding(account),
+            state = framework.state_data()
+        ));
+    }
+}
+
+/// Generates the fuzz target of `function`.
+fn target_rs(
+    spec: &Spec,
+    framework: Framework,
+    function: &Function,
+) -> Result<String, MaterializeError> {
+    let program = package_name(spec, framework).replace('-', "_");
+    let discriminator = match framework {
+        Framework::Anchor => "8 + ",
+        Framework::Native | Framework::Pinocchio => "",
+    };
+    let mut out = format!(
+        "//! Fuzzes `process_{}` with arbitrary account and instruction data.\n\n\
+         #![no_main]\n\n\
+         use libfuzzer_sys::fuzz_target;\n\
+         use solana_program::{{account_info::AccountInfo, pubkey::Pubkey}};\n",
+        function.name
+    );
+    let typed: Vec<(&str, &str)> = function
+        .accounts
+        .iter()
+        .filter_map(|account| Some((account.name.as_str(), account.ty.as_deref()?)))
+        .collect();
+    if !typed.is_empty() {
+        out.push('\n');
+    }
+    for (name, ty) in &typed {
+        out.push_str(&format!(
+            "const {}_LEN: usize = {discriminator}core::mem::size_of::<{program}::state::{ty}>();\n",
+            name.to_uppercase()
+        ));
+    }
+
+    let mut body = format!(
+        "    let program_id = {};\n",
+        match framework {
+            Framework::Pinocchio => format!("Pubkey::new_from_array({program}::id())"),
+            Framework::Native | Framework::Anchor => format!("{program}::id()"),
+        }
+    );
+    // The account data comes first, each as long as its state
+    let mut rest = "data".to_string();
+    for (name, _) in &typed {
+        body.push_str(&format!(
+            "    if {rest}.len() < {len} {{\n        return;\n    }}\n    \
+             let ({name}_state, {name}_rest) = {rest}.split_at({len});\n",
+            len = format!("{}_LEN", name.to_uppercase())
+        ));
+        rest = format!("{name}_rest");
+    }
+    body.push_str(&format!("    let instruction_data = {rest};\n"));
+    for account in &function.accounts {
+        body.push_str(&format!(
+            "    let {}_key = Pubkey::new_unique();\n",
+            account.name
+        ));
+    }
+
+    // Keys the preconditions require are set, so that the checks past them
+    // are reached
+    let keys: Vec<(String, String)> = key_bindings(spec, function)
+        .into_iter()
+        .filter(|(_, key)| key.ends_with("_key"))
+        .collect();
+    for account in &function.accounts {
+        let name = &account.name;
+        let data = match account.ty.as_deref() {
+            Some(ty) => {
+                body.push_str(&format!(
+                    "\n    let mut {name}_data = {name}_state.to_vec();\n"
+                ));
+                let assignments: Vec<String> = keys
+                    .iter()
+                    .filter_map(|(field, key)| {
+                        let field = field.strip_prefix(&format!("{name}."))?;
+                        Some(match framework {
+                            Framework::Pinocchio => format!("{name}.{field} = {key}.to_bytes();"),
+                            _ => format!("{name}.{field} = {key};"),
+                        })
+                    })
+                    .collect();
+                if !assignments.is_empty() {
+                    body.push_str(&format!(
+                        "    {{\n        let {name}: &mut {program}::state::{ty} =\n            \
+                         bytemuck::from_bytes_mut(&mut {name}_data{state});\n",
+                        state = framework.state_data()
+                    ));
+                    for assignment in assignments {
+                        body.push_str(&format!("        {assignment}\n"));
+                    }
+                    body.push_str("    }\n");
+                }
+                format!("&mut {name}_data")
+            }
+            None => {
+                body.push('\n');
+                "&mut []".to_string()
+            }
+        };
+        body.push_str(&format!(
+            "    let mut {name}_lamports = 0;\n    let {binding} = AccountInfo::new(\n        \
+             &{name}_key,\n        {},\n        {},\n        &mut {name}_lamports,\n        \
+             {data},\n        &program_id,\n        false,\n        0,\n    );\n",
+            account.signer,
+            account.writable,
+            binding = account_binding(account)
+        ));
+    }
+    let accounts: Vec<String> = function
+        .accounts
+        .iter()
+        .map(|account| format!("{}.clone()", account_binding(account)))
+        .collect();
+    if accounts.is_empty() && framework == Framework::Pinocchio {
+        // Processor functions are generic over the accounts they take
+        body.push_str("\n    let accounts: [AccountInfo; 0] = [];\n");
+    } else {
+        body.push_str(&format!(
+            "\n    let accounts = [{}];\n",
+            accounts.join(", ")
+        ));
+    }
+
+    // Invariants are only checked on the states they held in
+    let invariants = invariants(spec, function);
+    let mut checks = Vec::new();
+    for (invariant, inputs) in &invariants {
+        let code = invariant_code(spec, framework, invariant)?;
+        let holds = holds(spec, invariant, &code, inputs);
+        let names: Vec<String> = inputs.iter().map(|input| input.name.clone()).collect();
+        body.push_str(&format!("    let {}_held = {{\n", invariant.name));
+        bind_states(&mut body, &program, function, framework, &names, "        ");
+        body.push_str(&format!("        {holds}\n    }};\n"));
+        checks.push((invariant.name.clone(), holds, names));
+    }
+    body.push_str(&format!(
+        "    let result = {program}::processor::process_{}(&accounts, instruction_data);\n",
+        function.name
+    ));
+    if !checks.is_empty() {
+        body.push_str("\n    if result.is_ok() {\n");
+        let mut names: Vec<String> = Vec::new();
+        for (_, _, inputs) in &checks {
+            for name in inputs {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        bind_states(&mut body, &program, function, framework, &names, "        ");
+        for (name, holds, _) in &checks {
+            body.push_str(&format!(
+                "        assert!(!{name}_held || {holds}, \"invariant `{name}` violated\");\n"
+            ));
+        }
+        body.push_str("    }\n");
+    } else {
+        body.push_str("    let _ = result;\n");
+    }
+    out.push_str(&format!("\nfuzz_target!(|data: &[u8]| {{\n{body}}});\n"));
+    Ok(out)
+}
+
+/// The manifest of the fuzz crate, with a binary per target.
+fn cargo_toml(spec: &Spec, framework: Framework) -> String {
+    let package = package_name(spec, framework);
+    // Pinocchio programs implement their accounts for solana-program's with
+    // the optional dependency enabled
+    let features = match framework {
+        Framework::Pinocchio => r#"["no-entrypoint", "solana-program"]"#,
+        Framework::Native | Framework::Anchor => r#"["no-entrypoint"]"#,
+    };
+    let mut out = format!(
+        r#"[package]
+name = "{package}-fuzz"
+version = "0.0.0"
+publish = false
+edition = "2021"
+
+[package.metadata]
+cargo-fuzz = true
+
+[dependencies]
+libfuzzer-sys = "0.4"
+bytemuck = "1.14"
+solana-program = "1.18"
+
+[dependencies.{package}]
+path = ".."
+features = {features}
+
+# Kept out of any workspace above the crate
+[workspace]
+members = ["."]
+"#
+    );
+    for function in &spec.functions {
+        out.push_str(&format!(
+            "\n[[bin]]\nname = \"{name}\"\npath = \"fuzz_targets/{name}.rs\"\n\
+             test = false\ndoc = false\nbench = false\n",
+            name = function.name
+        ));
+    }
+    out
+}
+
+/// Generates the files of the fuzz crate, relative to the crate root.
+pub(super) fn fuzz_files(
+    spec: &Spec,
+    framework: Framework,
+) -> Result<Vec<(String, String)>, MaterializeError> {
+    // What `cargo fuzz` writes while running is left out of the crate
+    let mut files = vec![
+        (
+            format!("{FUZZ_DIR}/Cargo.toml"),
+            cargo_toml(spec, framework),
+        ),
+        (
+            format!("{FUZZ_DIR}/.gitignore"),
+            "target\ncorpus\nartifacts\ncoverage\n".to_string(),
+        ),
+    ];
+    for function in &spec.functions {
+        files.push((
+            format!("{FUZZ_DIR}/fuzz_targets/{}.rs", function.name),
+            target_rs(spec, framework, function)?,
+        ));
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_fuzz_files() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let files = fuzz_files(&spec, Framework::Native).unwrap();
+        let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "fuzz/Cargo.toml",
+                "fuzz/.gitignore",
+                "fuzz/fuzz_targets/deposit.rs",
+                "fuzz/fuzz_targets/withdraw.rs",
+                "fuzz/fuzz_targets/reward.rs",
+            ]
+        );
+        let manifest = &files[0].1;
+        assert!(manifest.contains("name = \"solana-vault-fuzz\"\n"));
+        assert!(manifest.contains("[dependencies.solana-vault]\npath = \"..\"\n"));
+        assert!(manifest
+            .contains("[[bin]]\nname = \"withdraw\"\npath = \"fuzz_targets/withdraw.rs\"\n"));
+
+        let withdraw = &files[3].1;
+        for line in [
+            "#![no_main]\n",
+            "const VAULT_LEN: usize = core::mem::size_of::<solana_vault::state::Vault>();\n",
+            "    if data.len() < VAULT_LEN {\n        return;\n    }\n    \
+             let (vault_state, vault_rest) = data.split_at(VAULT_LEN);\n    \
+             let instruction_data = vault_rest;\n",
+            "    let mut vault_data = vault_state.to_vec();\n",
+            "    let solvency_held = {\n        let vault_data = vault_account.data.borrow();\n",
+            "    let result = solana_vault::processor::process_withdraw(&accounts, instruction_data);\n",
+            "        assert!(!solvency_held || { let vault = &vault; u64::from(vault.shares_total) <= \
+             u64::from(vault.token_total) }, \"invariant `solvency` violated\");\n",
+        ] {
+            assert!(withdraw.contains(line), "missing `{line}` in\n{withdraw}");
+        }
+
+        let files = fuzz_files(&spec, Framework::Anchor).unwrap();
+        assert!(files[2].1.contains(
+            "const VAULT_LEN: usize = 8 + core::mem::size_of::<anchor_vault::state::Vault>();\n"
+        ));
+    }
+}
@@ -0,0 +1,272 @@
+//! Native calls of a processor function in generated unit tests.
+//!
+//! A test sets up every account with zeroed data overwritten by the values
+//! it is given, calls the processor function with `AccountInfo`s over that
+//! data and checks the result, e.g. against the postconditions.
+
+use super::{account_binding, arg_reader, native_int, postcondition, Framework};
+use spec::{BinaryOp, Expr, Function, Spec, Type};
+use std::collections::BTreeSet;
+
+/// Key assignments implied by the preconditions: a `Pubkey` field required
+/// to equal an account or argument is set to it, since tests only name keys
+/// symbolically.
+pub(crate) fn key_bindings(spec: &Spec, function: &Function) -> Vec<(String, String)> {
+    let field_of = |path: &[String]| -> Option<String> {
+        let [root, field] = path else { return None };
+        let ty = function.account(root)?.ty.as_deref()?;
+        let field = spec.account(ty)?.field(field)?;
+        (field.ty == Type::Pubkey).then(|| format!("{root}.{field}", field = field.name))
+    };
+    let key_of = |path: &[String]| -> Option<String> {
+        let [root] = path else { return None };
+        if function.arg(root).is_some_and(|arg| arg.ty == Type::Pubkey) {
+            return Some(root.clone());
+        }
+        function.account(root).map(|_| format!("{root}_key"))
+    };
+    let mut bindings = Vec::new();
+    for condition in &function.requires {
+        let Expr::Binary(BinaryOp::Eq, lhs, rhs) = &condition.expr else {
+            continue;
+        };
+        let (Expr::Path(lhs), Expr::Path(rhs)) = (&**lhs, &**rhs) else {
+            continue;
+        };
+        let binding = match (field_of(lhs), field_of(rhs)) {
+            (Some(field), None) => key_of(rhs).map(|key| (field, key)),
+            (None, Some(field)) => key_of(lhs).map(|key| (field, key)),
+            _ => None,
+        };
+        bindings.extend(binding);
+    }
+    bindings
+}
+
+/// The postconditions of `function` that translate to Rust, with the
+/// accounts they read after the call and those they read from before it.
+pub(crate) struct Postconditions<'a> {
+    pub checks: Vec<(String, &'a Expr)>,
+    pub after: BTreeSet<String>,
+    pub before: BTreeSet<String>,
+}
+
+impl<'a> Postconditions<'a> {
+    pub(crate) fn new(
+        spec: &Spec,
+        function: &'a Function,
+        framework: Framework,
+    ) -> Postconditions<'a> {
+        let mut after = BTreeSet::new();
+        let mut before = BTreeSet::new();
+        for condition in &function.ensures {
+            condition.expr.visit_paths(&mut |path, in_old| {
+                if path.len() > 1 {
+                    if in_old {
+                        before.insert(path[0].clone());
+                    } else {
+                        after.insert(path[0].clone());
+                    }
+                }
+            });
+        }
+        let checks = function
+            .ensures
+            .iter()
+            .filter_map(|condition| {
+                let code = postcondition(spec, function, framework, &condition.expr).ok()?;
+                Some((code, &condition.expr))
+            })
+            .collect();
+        Postconditions {
+            checks,
+            after,
+            before,
+        }
+    }
+
+    /// Writes the assertions, indented by `indent`, after the accounts they
+    /// read are bound.
+    pub(crate) fn assert(
+        &self,
+        code: &mut String,
+        function: &Function,
+        framework: Framework,
+        indent: &str,
+    ) {
+        bind_after(code, function, framework, &self.after, indent);
+        for (check, expr) in &self.checks {
+            code.push_str(&format!(
+                "{indent}assert!({check}, \"postcondition `{expr}` violated\");\n"
+            ));
+        }
+    }
+}
+
+/// Writes the set-up and the call of `function`, binding its result to
+/// `result`. `value` gives the literal of an argument or of an account field,
+/// named `account.field`, of the given type; others are zero. Accounts in
+/// `before` are also bound with an `old_` prefix before the call, and `setup`
+/// is written just before it. The data of Anchor accounts starts with a
+/// zeroed discriminator.
+pub(crate) fn call(
+    code: &mut String,
+    spec: &Spec,
+    function: &Function,
+    framework: Framework,
+    value: impl Fn(&str, &Type) -> Option<String>,
+    before: &BTreeSet<String>,
+    setup: &str,
+) {
+    let mut data = Vec::new();
+    for arg in &function.args {
+        let value = match &arg.ty {
+            Type::Pubkey => "Pubkey::new_unique()".to_string(),
+            ty => value(&arg.name, ty).unwrap_or_else(|| {
+                let native = native_int(ty).unwrap_or("u8");
+                format!("0{native}")
+            }),
+        };
+        code.push_str(&format!("    let {} = {value};\n", arg.name));
+        data.push(match (&arg.ty, arg_reader(&arg.ty)) {
+            (Type::Pubkey, _) => format!("{}.as_ref()", arg.name),
+            (_, Some((1, _))) => format!("&[{}]", arg.name),
+            _ => format!("&{}.to_le_bytes()", arg.name),
+        });
+    }
+    for account in &function.accounts {
+        code.push_str(&format!(
+            "    let {}_key = Pubkey::new_unique();\n",
+            account.name
+        ));
+    }
+
+    let keys = key_bindings(spec, function);
+    for account in &function.accounts {
+        let name = &account.name;
+        let binding = account_binding(account);
+        let data = match account.ty.as_deref() {
+            Some(ty) => {
+                let mut assignments = String::new();
+                let fields = spec.account(ty).map_or(&[][..], |state| &state.fields);
+                for field in fields {
+                    let access = format!("{name}.{}", field.name);
+                    if let Some((_, key)) = keys.iter().find(|(field, _)| *field == access) {
+                        // Pinocchio state holds keys as bytes
+                        let key = match framework {
+                            Framework::Pinocchio => format!("{key}.to_bytes()"),
+                            _ => key.clone(),
+                        };
+                        assignments.push_str(&format!("    {access} = {key};\n"));
+                        continue;
+                    }
+                    let Some(value) = value(&access, &field.ty) else {
+                        continue;
+                    };
+                    let value = match field.ty {
+                        Type::Bool | Type::U8 => value,
+                        _ => format!("{value}.into()"),
+                    };
+                    assignments.push_str(&format!("    {access} = {value};\n"));
+                }
+                let mutable = if assignments.is_empty() { "" } else { "mut " };
+                code.push_str(&format!(
+                    "\n    let {mutable}{name}: crate::state::{ty} = bytemuck::Zeroable::zeroed();\n\
+                     {assignments}"
+                ));
+                if before.contains(name) {
+                    code.push_str(&format!("    let old_{name} = {name};\n"));
+                }
+                code.push_str(&match framework {
+                    Framework::Native | Framework::Pinocchio => format!(
+                        "    let mut {name}_data = bytemuck::bytes_of(&{name}).to_vec();\n"
+                    ),
+                    Framework::Anchor => format!(
+                        "    let mut {name}_data = [[0; 8].as_slice(), bytemuck::bytes_of(&{name})].concat();\n"
+                    ),
+                });
+                format!("&mut {name}_data")
+            }
+            None => {
+                code.push('\n');
+                "&mut []".to_string()
+            }
+        };
+        code.push_str(&format!(
+            "    let mut {name}_lamports = 0;\n    let {binding} = AccountInfo::new(\n        \
+             &{name}_key,\n        {},\n        {},\n        &mut {name}_lamports,\n        \
+             {data},\n        &program_id,\n        false,\n        0,\n    );\n",
+            account.signer, account.writable
+        ));
+    }
+
+    let accounts: Vec<String> = function
+        .accounts
+        .iter()
+        .map(|account| format!("{}.clone()", account_binding(account)))
+        .collect();
+    if accounts.is_empty() && framework == Framework::Pinocchio {
+        // Processor functions are generic over the accounts they take
+        code.push_str("\n    let accounts: [solana_program::account_info::AccountInfo; 0] = [];\n");
+    } else {
+        code.push_str(&format!(
+            "\n    let accounts = [{}];\n",
+            accounts.join(", ")
+        ));
+    }
+    let data = match data.as_slice() {
+        [] => "&[]".to_string(),
+        [single] => single.clone(),
+        parts => {
+            code.push_str("    let mut instruction_data = Vec::new();\n");
+            for part in parts {
+                let part = part.strip_prefix('&').unwrap_or(part);
+                let part = if part.ends_with(".as_ref()") {
+                    part.to_string()
+                } else {
+                    format!("&{part}")
+                };
+                code.push_str(&format!(
+                    "    instruction_data.extend_from_slice({part});\n"
+                ));
+            }
+            "&instruction_data".to_string()
+        }
+    };
+    code.push_str(setup);
+    code.push_str(&format!(
+        "    let result = crate::processor::process_{}(&accounts, {data});\n",
+        function.name
+    ));
+}
+
+/// The id of the program as the owner of test accounts.
+pub(crate) fn program_id(framework: Framework) -> &'static str {
+    match framework {
+        Framework::Pinocchio => "Pubkey::new_from_array(crate::id())",
+        _ => "crate::id()",
+    }
+}
+
+/// Binds the data of the typed accounts in `names` after the call, indented
+/// by `indent`.
+pub(crate) fn bind_after(
+    code: &mut String,
+    function: &Function,
+    framework: Framework,
+    names: &BTreeSet<String>,
+    indent: &str,
+) {
+    for account in &function.accounts {
+        let (Some(ty), true) = (account.ty.as_deref(), names.contains(&account.name)) else {
+            continue;
+        };
+        code.push_str(&format!(
+            "{indent}let {name}_data = {binding}.data.borrow();\n{indent}\
+             let {name}: &crate::state::{ty} = bytemuck::from_bytes(&{name}_data{state});\n",
+            name = account.name,
+            binding = account_binding(account),
+            state = framework.state_data()
+        ));
+    }
+}
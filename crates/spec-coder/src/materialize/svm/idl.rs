@@ -0,0 +1,207 @@
+//! The IDL of the program, for wallets, explorers and client generators.
+//!
+//! `idl.json` follows the Anchor 0.30 IDL format, which Codama reads with
+//! `@codama/nodes-from-anchor`, for every framework: it is the layout the
+//! entrypoint decodes, not one Anchor would derive. Instructions are
+//! discriminated by the byte indexing the function for native and
+//! Pinocchio programs and by the 8-byte discriminator of the handler for
+//! Anchor ones, and their arguments follow little-endian, as Borsh lays
+//! them out. State accounts are `repr(C)` structs of alignment-1 Pod
+//! fields, so packed, behind the discriminator of Anchor accounts; their
+//! `bool` fields are bytes. Programs fail with the builtin `ProgramError`s
+//! rather than custom codes, so `errors` is empty and the docs of each
+//! instruction name the preconditions it fails with `InvalidArgument` on.
+
+use super::litesvm::discriminator;
+use super::{program_id, Framework};
+use serde_json::{json, Map, Value};
+use spec::{Account, Function, Spec, Type};
+
+/// The IDL type of `ty`.
+fn idl_type(ty: &Type) -> Value {
+    match ty {
+        Type::Array(inner, len) => json!({ "array": [idl_type(inner), len] }),
+        ty => json!(ty.to_string()),
+    }
+}
+
+/// The IDL type of `ty` as a field of a state account: `bool` is a byte.
+fn field_type(ty: &Type) -> Value {
+    match ty {
+        Type::Bool => json!("u8"),
+        Type::Array(inner, len) => json!({ "array": [field_type(inner), len] }),
+        ty => idl_type(ty),
+    }
+}
+
+/// Sets the `docs` of `object` to `lines`, unless there are none.
+fn docs(object: &mut Map<String, Value>, lines: Vec<String>) {
+    if !lines.is_empty() {
+        object.insert("docs".to_string(), json!(lines));
+    }
+}
+
+fn instruction(index: usize, function: &Function, framework: Framework) -> Value {
+    let discriminator = match framework {
+        Framework::Native | Framework::Pinocchio => vec![index as u8],
+        Framework::Anchor => discriminator(&format!("global:{}", function.name)).to_vec(),
+    };
+    let accounts: Vec<Value> = function
+        .accounts
+        .iter()
+        .map(|account| {
+            let mut object = Map::new();
+            object.insert("name".to_string(), json!(account.name));
+            if account.writable {
+                object.insert("writable".to_string(), json!(true));
+            }
+            if account.signer {
+                object.insert("signer".to_string(), json!(true));
+            }
+            Value::Object(object)
+        })
+        .collect();
+    let args: Vec<Value> = function
+        .args
+        .iter()
+        .map(|arg| json!({ "name": arg.name, "type": idl_type(&arg.ty) }))
+        .collect();
+    let mut object = Map::new();
+    object.insert("name".to_string(), json!(function.name));
+    let mut lines: Vec<String> = function.description.iter().cloned().collect();
+    lines.extend(
+        function
+            .requires
+            .iter()
+            .map(|condition| format!("Fails with `InvalidArgument` unless `{}`", condition.expr)),
+    );
+    docs(&mut object, lines);
+    object.insert("discriminator".to_string(), json!(discriminator));
+    object.insert("accounts".to_string(), json!(accounts));
+    object.insert("args".to_string(), json!(args));
+    Value::Object(object)
+}
+
+fn state_type(account: &Account) -> Value {
+    let fields: Vec<Value> = account
+        .fields
+        .iter()
+        .map(|field| {
+            let mut object = Map::new();
+            object.insert("name".to_string(), json!(field.name));
+            let lines = match (&field.description, &field.ty) {
+                (Some(description), _) => vec![description.clone()],
+                (None, Type::Bool) => vec!["Non-zero when set".to_string()],
+                (None, _) => Vec::new(),
+            };
+            docs(&mut object, lines);
+            object.insert("type".to_string(), field_type(&field.ty));
+            Value::Object(object)
+        })
+        .collect();
+    let mut object = Map::new();
+    object.insert("name".to_string(), json!(account.name));
+    docs(&mut object, account.description.iter().cloned().collect());
+    object.insert("serialization".to_string(), json!("bytemuck"));
+    object.insert("repr".to_string(), json!({ "kind": "c", "packed": true }));
+    object.insert(
+        "type".to_string(),
+        json!({ "kind": "struct", "fields": fields }),
+    );
+    Value::Object(object)
+}
+
+/// Generates `idl.json`.
+pub(super) fn idl_json(spec: &Spec, framework: Framework) -> String {
+    let instructions: Vec<Value> = spec
+        .functions
+        .iter()
+        .enumerate()
+        .map(|(index, function)| instruction(index, function, framework))
+        .collect();
+    let accounts: Vec<Value> = spec
+        .state
+        .iter()
+        .map(|account| {
+            let discriminator = match framework {
+                Framework::Native | Framework::Pinocchio => Vec::new(),
+                Framework::Anchor => discriminator(&format!("account:{}", account.name)).to_vec(),
+            };
+            json!({ "name": account.name, "discriminator": discriminator })
+        })
+        .collect();
+    let types: Vec<Value> = spec.state.iter().map(state_type).collect();
+    let mut metadata = Map::new();
+    metadata.insert("name".to_string(), json!(spec.name));
+    metadata.insert("version".to_string(), json!("0.1.0"));
+    metadata.insert("spec".to_string(), json!("0.1.0"));
+    if let Some(description) = &spec.description {
+        metadata.insert("description".to_string(), json!(description));
+    }
+    let idl = json!({
+        "address": program_id(&spec.name),
+        "metadata": metadata,
+        "instructions": instructions,
+        "accounts": accounts,
+        "errors": [],
+        "types": types,
+    });
+    serde_json::to_string_pretty(&idl).expect("IDLs serialize") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_idl_json() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let idl: Value = serde_json::from_str(&idl_json(&spec, Framework::Native)).unwrap();
+        assert_eq!(idl["address"], json!(program_id("vault")));
+        assert_eq!(idl["metadata"]["name"], json!("vault"));
+        assert_eq!(
+            idl["instructions"][0],
+            json!({
+                "name": "deposit",
+                "docs": [
+                    "Deposits tokens and mints shares at the current exchange rate.",
+                    "Fails with `InvalidArgument` unless `amount > 0`"
+                ],
+                "discriminator": [0],
+                "accounts": [{ "name": "vault", "writable": true }],
+                "args": [{ "name": "amount", "type": "u64" }]
+            })
+        );
+        assert_eq!(idl["instructions"][2]["discriminator"], json!([2]));
+        assert_eq!(
+            idl["accounts"],
+            json!([{ "name": "Vault", "discriminator": [] }])
+        );
+        assert_eq!(
+            idl["types"][0]["type"]["fields"][0],
+            json!({ "name": "owner", "docs": ["The vault owner (authority)"], "type": "pubkey" })
+        );
+        assert_eq!(idl["errors"], json!([]));
+
+        let idl: Value = serde_json::from_str(&idl_json(&spec, Framework::Anchor)).unwrap();
+        // sha256("global:withdraw")[..8]
+        assert_eq!(
+            idl["instructions"][1]["discriminator"],
+            json!([183, 18, 70, 156, 148, 109, 161, 34])
+        );
+        assert_eq!(
+            idl["accounts"][0]["discriminator"]
+                .as_array()
+                .unwrap()
+                .len(),
+            8
+        );
+
+        assert_eq!(
+            field_type(&Type::Array(Box::new(Type::Bool), 4)),
+            json!({ "array": ["u8", 4] })
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! The byte layouts of the instructions and state accounts of a program,
+//! as the client generators encode and decode them.
+//!
+//! An instruction is the byte indexing its function for native and
+//! Pinocchio programs, or the 8-byte discriminator of its handler for
+//! Anchor ones, followed by its arguments, little-endian. A state account is
+//! its fields in order, with no padding since every Pod field has an
+//! alignment of 1, behind the 8-byte discriminator of Anchor accounts.
+
+use super::litesvm::discriminator;
+use super::Framework;
+use spec::{Account, AccountInput, Function, Spec, Type};
+
+/// A value at a fixed offset: an argument or a state field.
+pub(super) struct Slot<'a> {
+    pub(super) name: &'a str,
+    pub(super) ty: &'a Type,
+    pub(super) offset: usize,
+    pub(super) description: Option<&'a str>,
+}
+
+/// The layout of the data of a state account.
+pub(super) struct State<'a> {
+    pub(super) account: &'a Account,
+    /// The discriminator the data starts with, for Anchor accounts
+    pub(super) discriminator: Option<[u8; 8]>,
+    pub(super) fields: Vec<Slot<'a>>,
+    /// The size of the data in bytes, discriminator included
+    pub(super) size: usize,
+}
+
+/// The layout of an instruction.
+pub(super) struct Instruction<'a> {
+    pub(super) function: &'a Function,
+    /// The bytes the data starts with, selecting the function
+    pub(super) prefix: Vec<u8>,
+    pub(super) accounts: &'a [AccountInput],
+    pub(super) args: Vec<Slot<'a>>,
+    /// The size of the data in bytes
+    pub(super) size: usize,
+}
+
+/// The slots of `values`, given as name, type and description, laid out
+/// from `start`, and the offset they end at.
+fn slots<'a>(
+    values: impl Iterator<Item = (&'a str, &'a Type, Option<&'a str>)>,
+    start: usize,
+) -> (Vec<Slot<'a>>, usize) {
+    let mut offset = start;
+    let slots = values
+        .map(|(name, ty, description)| {
+            let slot = Slot {
+                name,
+                ty,
+                offset,
+                description,
+            };
+            offset += ty.size();
+            slot
+        })
+        .collect();
+    (slots, offset)
+}
+
+/// The layouts of the state accounts of `spec`.
+pub(super) fn states(spec: &Spec, framework: Framework) -> Vec<State<'_>> {
+    spec.state
+        .iter()
+        .map(|account| {
+            let discriminator = match framework {
+                Framework::Native | Framework::Pinocchio => None,
+                Framework::Anchor => Some(discriminator(&format!("account:{}", account.name))),
+            };
+            let start = discriminator.map_or(0, |bytes| bytes.len());
+            let fields = account
+                .fields
+                .iter()
+                .map(|field| (field.name.as_str(), &field.ty, field.description.as_deref()));
+            let (fields, size) = slots(fields, start);
+            State {
+                account,
+                discriminator,
+                fields,
+                size,
+            }
+        })
+        .collect()
+}
+
+/// The layouts of the instructions of `spec`.
+pub(super) fn instructions(spec: &Spec, framework: Framework) -> Vec<Instruction<'_>> {
+    spec.functions
+        .iter()
+        .enumerate()
+        .map(|(index, function)| {
+            let prefix = match framework {
+                Framework::Native | Framework::Pinocchio => vec![index as u8],
+                Framework::Anchor => discriminator(&format!("global:{}", function.name)).to_vec(),
+            };
+            let args = function
+                .args
+                .iter()
+                .map(|arg| (arg.name.as_str(), &arg.ty, None));
+            let (args, size) = slots(args, prefix.len());
+            Instruction {
+                function,
+                prefix,
+                accounts: &function.accounts,
+                args,
+                size,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_layouts() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let states = states(&spec, Framework::Anchor);
+        let offsets: Vec<usize> = states[0].fields.iter().map(|f| f.offset).collect();
+        assert_eq!(offsets, [8, 40, 48]);
+        assert_eq!(states[0].size, 56);
+
+        let instructions = instructions(&spec, Framework::Native);
+        assert_eq!(instructions[1].prefix, [1]);
+        assert_eq!(instructions[1].args[0].offset, 1);
+        assert_eq!(instructions[1].size, 9);
+        // sha256("global:withdraw")[..8]
+        let instructions = super::instructions(&spec, Framework::Anchor);
+        assert_eq!(
+            instructions[1].prefix,
+            [183, 18, 70, 156, 148, 109, 161, 34]
+        );
+        assert_eq!(instructions[1].size, 16);
+    }
+}
@@ -0,0 +1,247 @@
+//! Transactions against the program built for the SVM, in generated tests.
+//!
+//! Tests under `tests/` load the program that `cargo test-sbf` builds into
+//! LiteSVM, create the accounts of a call owned by the program, and send the
+//! instruction encoded as its entrypoint decodes it: a byte indexing the
+//! function for native and Pinocchio programs, the 8-byte discriminator of
+//! the handler for Anchor ones, followed by the arguments.
+
+use super::harness::key_bindings;
+use super::{native_int, package_name, Framework};
+use sha2::{Digest, Sha256};
+use spec::{Function, Spec, Type};
+
+/// The first 8 bytes of the SHA-256 of `preimage`, as Anchor derives its
+/// instruction and account discriminators.
+pub(super) fn discriminator(preimage: &str) -> [u8; 8] {
+    let digest = Sha256::digest(preimage.as_bytes());
+    digest[..8].try_into().expect("digests are 32 bytes")
+}
+
+/// [`discriminator`] as an array literal.
+pub(super) fn anchor_discriminator(preimage: &str) -> String {
+    let bytes: Vec<String> = discriminator(preimage).iter().map(u8::to_string).collect();
+    format!("[{}]", bytes.join(", "))
+}
+
+/// The crate name of the program, as tests refer to it.
+pub(super) fn program(spec: &Spec, framework: Framework) -> String {
+    package_name(spec, framework).replace('-', "_")
+}
+
+/// The start of a test file with the doc comment `doc`: the imports and the
+/// helpers setting up the simulator, creating accounts and sending
+/// transactions.
+pub(super) fn preamble(spec: &Spec, framework: Framework, doc: &str) -> String {
+    let program = program(spec, framework);
+    let program_id = match framework {
+        Framework::Pinocchio => format!("Pubkey::new_from_array({program}::id())"),
+        Framework::Native | Framework::Anchor => format!("{program}::id()"),
+    };
+    format!(
+        r#"{doc}
+#![cfg(feature = "test-sbf")]
+
+use litesvm::types::TransactionResult;
+use litesvm::LiteSVM;
+use solana_sdk::{{
+    account::Account,
+    instruction::{{AccountMeta, Instruction}},
+    pubkey::Pubkey,
+    signature::{{Keypair, Signer}},
+    transaction::Transaction,
+}};
+
+fn program_id() -> Pubkey {{
+    {program_id}
+}}
+
+/// A simulator with the program loaded from where `cargo test-sbf` put it.
+fn svm() -> LiteSVM {{
+    let dir = std::env::var("SBF_OUT_DIR").unwrap_or_else(|_| "target/deploy".to_string());
+    let mut svm = LiteSVM::new();
+    svm.add_program_from_file(program_id(), format!("{{dir}}/{program}.so"))
+        .expect("the program is built by `cargo test-sbf`");
+    svm
+}}
+
+/// Creates an account of the program holding `data`.
+fn set_state(svm: &mut LiteSVM, key: Pubkey, data: Vec<u8>) {{
+    let account = Account {{
+        lamports: 1_000_000_000,
+        data,
+        owner: program_id(),
+        executable: false,
+        rent_epoch: 0,
+    }};
+    svm.set_account(key, account).unwrap();
+}}
+
+/// Sends `instruction`, signed by `signers` and paid for by a new account.
+fn send(svm: &mut LiteSVM, instruction: Instruction, signers: &[&Keypair]) -> TransactionResult {{
+    let payer = Keypair::new();
+    svm.airdrop(&payer.pubkey(), 1_000_000_000).unwrap();
+    let mut keypairs = vec![&payer];
+    keypairs.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &keypairs,
+        svm.latest_blockhash(),
+    );
+    svm.send_transaction(transaction)
+}}
+"#
+    )
+}
+
+/// Writes the instruction data of a call of `function`, from the index of
+/// the function or its discriminator and then its arguments. `value` gives
+/// the literal of an argument; others are zero.
+pub(super) fn instruction_data(
+    out: &mut String,
+    spec: &Spec,
+    framework: Framework,
+    function: &Function,
+    value: &dyn Fn(&str, &Type) -> Option<String>,
+) {
+    let index = spec
+        .functions
+        .iter()
+        .position(|other| other.name == function.name)
+        .expect("functions of the spec");
+    out.push_str(&match framework {
+        Framework::Native | Framework::Pinocchio => {
+            format!("    let mut instruction_data = vec![{index}u8];\n")
+        }
+        Framework::Anchor => format!(
+            "    let mut instruction_data = {}.to_vec();\n",
+            anchor_discriminator(&format!("global:{}", function.name))
+        ),
+    });
+    for arg in &function.args {
+        let value = match &arg.ty {
+            Type::Pubkey => "Pubkey::new_unique()".to_string(),
+            ty => value(&arg.name, ty).unwrap_or_else(|| {
+                let native = native_int(ty).unwrap_or("u8");
+                format!("0{native}")
+            }),
+        };
+        let bytes = match &arg.ty {
+            Type::Pubkey => format!("{}.as_ref()", arg.name),
+            Type::Bool | Type::U8 => format!("&[{}]", arg.name),
+            _ => format!("&{}.to_le_bytes()", arg.name),
+        };
+        out.push_str(&format!(
+            "    let {} = {value};\n    instruction_data.extend_from_slice({bytes});\n",
+            arg.name
+        ));
+    }
+}
+
+/// Writes the key of every account of `function`, from a keypair for
+/// signers, and returns the keypairs.
+pub(super) fn keys(out: &mut String, function: &Function) -> Vec<String> {
+    let mut signers = Vec::new();
+    for account in &function.accounts {
+        if account.signer {
+            out.push_str(&format!(
+                "    let {name}_signer = Keypair::new();\n    let {name}_key = {name}_signer.pubkey();\n",
+                name = account.name
+            ));
+            signers.push(format!("&{}_signer", account.name));
+        } else {
+            out.push_str(&format!(
+                "    let {}_key = Pubkey::new_unique();\n",
+                account.name
+            ));
+        }
+    }
+    signers
+}
+
+/// The data of the account holding the state `name` of type `ty`, past the
+/// discriminator of Anchor accounts.
+pub(super) fn account_data(framework: Framework, name: &str, ty: &str) -> String {
+    match framework {
+        Framework::Native | Framework::Pinocchio => {
+            format!("bytemuck::bytes_of(&{name}).to_vec()")
+        }
+        Framework::Anchor => format!(
+            "[{}.as_slice(), bytemuck::bytes_of(&{name})].concat()",
+            anchor_discriminator(&format!("account:{ty}"))
+        ),
+    }
+}
+
+/// Writes the state of every typed account of `function`, zeroed and
+/// overwritten by the values `value` gives for `account.field`, and creates
+/// its account. Keys the preconditions require are set.
+pub(super) fn states(
+    out: &mut String,
+    spec: &Spec,
+    framework: Framework,
+    function: &Function,
+    value: &dyn Fn(&str, &Type) -> Option<String>,
+) {
+    let program = program(spec, framework);
+    let keys = key_bindings(spec, function);
+    for account in &function.accounts {
+        let Some(ty) = account.ty.as_deref() else {
+            continue;
+        };
+        let name = &account.name;
+        let mut assignments = String::new();
+        let fields = spec.account(ty).map_or(&[][..], |state| &state.fields);
+        for field in fields {
+            let access = format!("{name}.{}", field.name);
+            if let Some((_, key)) = keys.iter().find(|(field, _)| *field == access) {
+                let key = match framework {
+                    Framework::Pinocchio => format!("{key}.to_bytes()"),
+                    _ => key.clone(),
+                };
+                assignments.push_str(&format!("    {access} = {key};\n"));
+                continue;
+            }
+            let Some(value) = value(&access, &field.ty) else {
+                continue;
+            };
+            let value = match field.ty {
+                Type::Bool | Type::U8 => value,
+                _ => format!("{value}.into()"),
+            };
+            assignments.push_str(&format!("    {access} = {value};\n"));
+        }
+        let mutable = if assignments.is_empty() { "" } else { "mut " };
+        out.push_str(&format!(
+            "\n    let {mutable}{name}: {program}::state::{ty} = bytemuck::Zeroable::zeroed();\n\
+             {assignments}    set_state(&mut svm, {name}_key, {});\n",
+            account_data(framework, name, ty)
+        ));
+    }
+}
+
+/// Writes the instruction calling `function` on the keys of its accounts
+/// with the instruction data.
+pub(super) fn instruction(out: &mut String, function: &Function) {
+    let metas: Vec<String> = function
+        .accounts
+        .iter()
+        .map(|account| {
+            let constructor = if account.writable {
+                "new"
+            } else {
+                "new_readonly"
+            };
+            format!(
+                "AccountMeta::{constructor}({}_key, {})",
+                account.name, account.signer
+            )
+        })
+        .collect();
+    out.push_str(&format!(
+        "\n    let instruction = Instruction::new_with_bytes(\n        program_id(),\n        \
+         &instruction_data,\n        vec![{}],\n    );\n",
+        metas.join(", ")
+    ));
+}
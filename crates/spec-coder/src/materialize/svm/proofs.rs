@@ -0,0 +1,176 @@
+//! Kani proof harnesses checking the rules without the Certora Prover.
+//!
+//! `src/proofs.rs` holds a `#[kani::proof]` harness per rule of the certora
+//! module, named after it, for `cargo kani`. A harness sets up the accounts
+//! as the unit tests do, but with nondeterministic field values and
+//! arguments; keys and arrays stay concrete. Like the rules, harnesses only
+//! consider calls that succeed, from a state satisfying the invariants.
+
+use super::harness::{self, Postconditions};
+use super::rules::{holds, invariant_code, invariants};
+use super::{native_int, with_article, Framework};
+use crate::materialize::MaterializeError;
+use spec::{Function, Spec, Type};
+use std::collections::BTreeSet;
+
+/// A nondeterministic value of `ty`, as the state stores it; booleans are
+/// stored as bytes.
+fn any(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Bool => Some("u8::from(kani::any::<bool>())".to_string()),
+        ty => native_int(ty).map(|native| format!("kani::any::<{native}>()")),
+    }
+}
+
+/// Writes the head of the harness `name` of `function`.
+fn head(out: &mut String, framework: Framework, function: &Function, doc: &str, name: &str) {
+    out.push_str(&format!("\n/// {doc}\n#[kani::proof]\nfn {name}() {{\n"));
+    if !function.accounts.is_empty() {
+        out.push_str(&format!(
+            "    let program_id = {};\n",
+            harness::program_id(framework)
+        ));
+    }
+}
+
+/// Writes the harnesses of `function`, mirroring its rules.
+fn function_proofs(
+    out: &mut String,
+    spec: &Spec,
+    framework: Framework,
+    function: &Function,
+) -> Result<(), MaterializeError> {
+    let call = |out: &mut String, before: &BTreeSet<String>, setup: &str| {
+        harness::call(
+            out,
+            spec,
+            function,
+            framework,
+            |_, ty| any(ty),
+            before,
+            setup,
+        );
+    };
+    head(
+        out,
+        framework,
+        function,
+        &format!(
+            "Verifies that {} can succeed.",
+            with_article(&function.name)
+        ),
+        &format!("rule_{}_sanity", function.name),
+    );
+    call(out, &BTreeSet::new(), "");
+    out.push_str("\n    kani::cover!(result.is_ok());\n}\n");
+
+    let mut checked = Vec::new();
+    for (invariant, inputs) in invariants(spec, function) {
+        let code = invariant_code(spec, framework, invariant)?;
+        checked.push((invariant, holds(spec, invariant, &code, &inputs), inputs));
+    }
+    let assume = |holds: &str| format!("    kani::assume({holds});\n");
+
+    if !function.ensures.is_empty() {
+        let postconditions = Postconditions::new(spec, function, framework);
+        let setup: String = checked.iter().map(|(_, holds, _)| assume(holds)).collect();
+        head(
+            out,
+            framework,
+            function,
+            &format!(
+                "Verifies the postconditions of {}.",
+                with_article(&function.name)
+            ),
+            &format!("rule_{}_ensures", function.name),
+        );
+        call(out, &postconditions.before, &setup);
+        out.push_str("\n    kani::assume(result.is_ok());\n");
+        postconditions.assert(out, function, framework, "    ");
+        out.push_str("}\n");
+    }
+
+    for (invariant, holds, inputs) in &checked {
+        head(
+            out,
+            framework,
+            function,
+            &format!(
+                "Verifies that {} preserves the {} invariant.",
+                with_article(&function.name),
+                invariant.name
+            ),
+            &format!("rule_{}_{}", function.name, invariant.name),
+        );
+        call(out, &BTreeSet::new(), &assume(holds));
+        out.push_str("\n    kani::assume(result.is_ok());\n");
+        let names = inputs.iter().map(|input| input.name.clone()).collect();
+        harness::bind_after(out, function, framework, &names, "    ");
+        out.push_str(&format!(
+            "    assert!({holds}, \"invariant `{}` violated\");\n}}\n",
+            invariant.name
+        ));
+    }
+    Ok(())
+}
+
+/// Generates `src/proofs.rs`.
+pub(super) fn proofs_rs(spec: &Spec, framework: Framework) -> Result<String, MaterializeError> {
+    let mut out = format!(
+        "//! Kani proof harnesses for the {} program, checking the rules of the\n\
+         //! certora module with `cargo kani`.\n\n",
+        spec.name.replace('_', " ")
+    );
+    let accounts = spec.functions.iter().any(|f| !f.accounts.is_empty());
+    let keys = accounts
+        || spec
+            .functions
+            .iter()
+            .any(|f| f.args.iter().any(|a| a.ty == Type::Pubkey));
+    match (accounts, keys) {
+        (true, _) => {
+            out.push_str("use solana_program::{account_info::AccountInfo, pubkey::Pubkey};\n")
+        }
+        (false, true) => out.push_str("use solana_program::pubkey::Pubkey;\n"),
+        (false, false) => {}
+    }
+    for function in &spec.functions {
+        function_proofs(&mut out, spec, framework, function)?;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_proofs_rs() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let proofs = proofs_rs(&spec, Framework::Native).unwrap();
+        for line in [
+            "#[kani::proof]\nfn rule_deposit_sanity() {\n    let program_id = crate::id();\n    \
+             let amount = kani::any::<u64>();",
+            "    vault.token_total = kani::any::<u64>().into();",
+            "    kani::cover!(result.is_ok());",
+            "    kani::assume({ let vault = &vault; u64::from(vault.shares_total) <= u64::from(vault.token_total) });\n    \
+             let result = crate::processor::process_deposit(",
+            "fn rule_withdraw_solvency() {",
+            "    assert!({ let vault = &vault; u64::from(vault.shares_total) <= u64::from(vault.token_total) }, \
+             \"invariant `solvency` violated\");",
+        ] {
+            assert!(proofs.contains(line), "missing `{line}` in\n{proofs}");
+        }
+        // A harness per rule, of the same name
+        for function in &spec.functions {
+            for (rule, _) in super::super::function_rules(&spec, function) {
+                assert!(
+                    proofs.contains(&format!("fn {rule}() {{")),
+                    "missing {rule}"
+                );
+            }
+        }
+    }
+}
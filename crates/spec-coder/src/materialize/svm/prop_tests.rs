@@ -0,0 +1,200 @@
+//! Property tests compiled from the invariants of the spec.
+//!
+//! `src/prop_tests.rs` holds a `proptest!` test per invariant rule, named
+//! after it with a `prop_` prefix. It draws the fields of the state and the
+//! instruction data at random, keeps the draws satisfying the invariant and
+//! checks that every call that succeeds preserves it. Keys and arrays stay
+//! as the unit tests set them. This is a cheap probabilistic check that runs
+//! with `cargo test`, before any prover is invoked.
+
+use super::harness;
+use super::rules::{holds, invariant_code, invariants};
+use super::{native_int, with_article, Framework};
+use crate::materialize::MaterializeError;
+use spec::{Function, Spec, Type};
+use std::collections::BTreeSet;
+
+/// Proptest implements `Strategy` for tuples of up to this many strategies.
+const MAX_TUPLE: usize = 12;
+
+/// The strategy of a value of `ty` and its literal as the state stores it,
+/// from the drawn value `name`; booleans are stored as bytes.
+fn strategy(ty: &Type, name: &str) -> Option<(String, String)> {
+    match ty {
+        Type::Bool => Some(("any::<bool>()".to_string(), format!("u8::from({name})"))),
+        ty => native_int(ty).map(|native| (format!("any::<{native}>()"), name.to_string())),
+    }
+}
+
+/// The pattern and the strategy of a parameter drawing `values`, as
+/// `(name, strategy)` pairs, nested in tuples of at most [`MAX_TUPLE`].
+pub(crate) fn strategy_parameter(values: &[(String, String)]) -> (String, String) {
+    if let [(name, strategy)] = values {
+        return (name.clone(), strategy.clone());
+    }
+    let parts: Vec<(String, String)> = if values.len() <= MAX_TUPLE {
+        values.to_vec()
+    } else {
+        values
+            .chunks(values.len().div_ceil(MAX_TUPLE))
+            .map(strategy_parameter)
+            .collect()
+    };
+    let (patterns, strategies): (Vec<String>, Vec<String>) = parts.into_iter().unzip();
+    (
+        format!("({})", patterns.join(", ")),
+        format!("({})", strategies.join(", ")),
+    )
+}
+
+/// Writes the property tests of `function`.
+fn function_tests(
+    out: &mut String,
+    spec: &Spec,
+    framework: Framework,
+    function: &Function,
+) -> Result<(), MaterializeError> {
+    // Drawn values are named after the paths they are written to
+    let mut params = Vec::new();
+    let args: Vec<(String, String)> = function
+        .args
+        .iter()
+        .filter_map(|arg| {
+            let (strategy, _) = strategy(&arg.ty, "")?;
+            Some((format!("arg_{}", arg.name), strategy))
+        })
+        .collect();
+    if !args.is_empty() {
+        params.push(strategy_parameter(&args));
+    }
+    for account in &function.accounts {
+        let Some(state) = account.ty.as_deref().and_then(|ty| spec.account(ty)) else {
+            continue;
+        };
+        let fields: Vec<(String, String)> = state
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let (strategy, _) = strategy(&field.ty, "")?;
+                Some((format!("{}_{}", account.name, field.name), strategy))
+            })
+            .collect();
+        if !fields.is_empty() {
+            params.push(strategy_parameter(&fields));
+        }
+    }
+    let value = |name: &str, ty: &Type| {
+        let drawn = match name.split_once('.') {
+            Some((account, field)) => format!("{account}_{field}"),
+            None => format!("arg_{name}"),
+        };
+        strategy(ty, &drawn).map(|(_, value)| value)
+    };
+
+    for (invariant, inputs) in invariants(spec, function) {
+        let code = invariant_code(spec, framework, invariant)?;
+        let holds = holds(spec, invariant, &code, &inputs);
+        let mut body = String::new();
+        if !function.accounts.is_empty() {
+            body.push_str(&format!(
+                "    let program_id = {};\n",
+                harness::program_id(framework)
+            ));
+        }
+        harness::call(
+            &mut body,
+            spec,
+            function,
+            framework,
+            value,
+            &BTreeSet::new(),
+            &format!("    prop_assume!({holds});\n"),
+        );
+        body.push_str("\n    if result.is_ok() {\n");
+        let names = inputs.iter().map(|input| input.name.clone()).collect();
+        harness::bind_after(&mut body, function, framework, &names, "        ");
+        body.push_str(&format!(
+            "        prop_assert!({holds}, \"invariant `{}` violated\");\n    }}\n",
+            invariant.name
+        ));
+
+        out.push_str(&format!(
+            "\n    /// Checks that {} preserves the {} invariant.\n    #[test]\n    \
+             fn prop_{}_{}(\n",
+            with_article(&function.name),
+            invariant.name,
+            function.name,
+            invariant.name
+        ));
+        for (pattern, strategy) in &params {
+            out.push_str(&format!("        {pattern} in {strategy},\n"));
+        }
+        out.push_str("    ) {\n");
+        for line in body.lines() {
+            if line.is_empty() {
+                out.push('\n');
+            } else {
+                out.push_str(&format!("    {line}\n"));
+            }
+        }
+        out.push_str("    }\n");
+    }
+    Ok(())
+}
+
+/// Generates `src/prop_tests.rs`, or `None` if no function can break an
+/// invariant.
+pub(super) fn prop_tests_rs(
+    spec: &Spec,
+    framework: Framework,
+) -> Result<Option<String>, MaterializeError> {
+    let mut tests = String::new();
+    for function in &spec.functions {
+        function_tests(&mut tests, spec, framework, function)?;
+    }
+    if tests.is_empty() {
+        return Ok(None);
+    }
+    // Invariants are only checked on accounts
+    Ok(Some(format!(
+        "//! Property tests from the invariants of the spec.\n\n\
+         use proptest::prelude::*;\n\
+         use solana_program::{{account_info::AccountInfo, pubkey::Pubkey}};\n\n\
+         proptest! {{{tests}}}\n"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_prop_tests_rs() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let tests = prop_tests_rs(&spec, Framework::Native).unwrap().unwrap();
+        for line in [
+            "use proptest::prelude::*;\n",
+            "    /// Checks that a deposit preserves the solvency invariant.\n    #[test]\n    \
+             fn prop_deposit_solvency(\n        arg_amount in any::<u64>(),\n        \
+             (vault_shares_total, vault_token_total) in (any::<u64>(), any::<u64>()),\n    ) {\n",
+            "        let amount = arg_amount;\n",
+            "        vault.token_total = vault_token_total.into();\n",
+            "        prop_assume!({ let vault = &vault; u64::from(vault.shares_total) <= \
+             u64::from(vault.token_total) });\n",
+            "            prop_assert!({ let vault = &vault; u64::from(vault.shares_total) <= \
+             u64::from(vault.token_total) }, \"invariant `solvency` violated\");\n",
+            "fn prop_withdraw_solvency(",
+        ] {
+            assert!(tests.contains(line), "missing `{line}` in\n{tests}");
+        }
+
+        let values: Vec<(String, String)> = (0..14)
+            .map(|i| (format!("v{i}"), "any::<u8>()".to_string()))
+            .collect();
+        let (pattern, strategy) = strategy_parameter(&values);
+        assert!(pattern.starts_with("((v0, v1), (v2, v3), "), "{pattern}");
+        assert_eq!(strategy.matches("any::<u8>()").count(), 14);
+    }
+}
@@ -0,0 +1,324 @@
+//! The Python client of the program.
+//!
+//! `clients/py` is a package over `solders` laid out from the same
+//! [`layout`] as the TypeScript client: per state type a frozen dataclass
+//! with a `decode` class method reading the account data, and per function
+//! dataclasses of its accounts and arguments and a builder of its
+//! `Instruction`. Integers are `int`s, keys `Pubkey`s.
+
+use super::layout::{self, Instruction, State};
+use super::ts_client::screaming_case;
+use super::{pascal_case, program_id, Framework, PY_CLIENT_DIR};
+use spec::{Spec, Type};
+
+/// The name of the Python package, e.g. `vault_client`.
+fn package(spec: &Spec) -> String {
+    format!("{}_client", spec.name)
+}
+
+fn pyproject_toml(spec: &Spec) -> String {
+    format!(
+        r#"[project]
+name = "{}-client"
+version = "0.1.0"
+description = "Python client of the {} program"
+requires-python = ">=3.9"
+dependencies = ["solders>=0.21"]
+
+[build-system]
+requires = ["setuptools>=61"]
+build-backend = "setuptools.build_meta"
+
+[tool.setuptools]
+packages = ["{}"]
+"#,
+        spec.name.replace('_', "-"),
+        spec.name,
+        package(spec)
+    )
+}
+
+/// The Python type of a value of type `ty`.
+fn py_type(ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".to_string(),
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 | Type::U128 | Type::I64 => "int".to_string(),
+        Type::Pubkey => "Pubkey".to_string(),
+        Type::Array(inner, _) => format!("list[{}]", py_type(inner)),
+    }
+}
+
+/// The `struct` format of an integer of type `ty`, if it has one.
+fn struct_format(ty: &Type) -> Option<&'static str> {
+    Some(match ty {
+        Type::U16 => "<H",
+        Type::U32 => "<I",
+        Type::U64 => "<Q",
+        Type::I64 => "<q",
+        _ => return None,
+    })
+}
+
+/// The expression reading a value of type `ty` at `offset` of `data`;
+/// `depth` numbers the indices of nested arrays.
+fn read(ty: &Type, offset: &str, depth: usize) -> String {
+    let end = |size: usize| match offset.parse::<usize>() {
+        Ok(offset) => (offset + size).to_string(),
+        Err(_) => format!("{offset} + {size}"),
+    };
+    match ty {
+        Type::Bool => format!("data[{offset}] != 0"),
+        Type::U8 => format!("data[{offset}]"),
+        Type::U128 => format!("int.from_bytes(data[{offset}:{}], \"little\")", end(16)),
+        Type::Pubkey => format!("Pubkey.from_bytes(bytes(data[{offset}:{}]))", end(32)),
+        Type::Array(inner, len) => {
+            let index = format!("i{depth}");
+            let element = read(
+                inner,
+                &format!("{offset} + {index} * {}", inner.size()),
+                depth + 1,
+            );
+            format!("[{element} for {index} in range({len})]")
+        }
+        ty => format!(
+            "struct.unpack_from(\"{}\", data, {offset})[0]",
+            struct_format(ty).expect("integers")
+        ),
+    }
+}
+
+/// The statement writing `value` of type `ty` at `offset` of `data`.
+/// Arguments are never arrays.
+fn write(ty: &Type, value: &str, offset: usize) -> String {
+    match ty {
+        Type::Bool => format!("data[{offset}] = 1 if {value} else 0"),
+        Type::U8 => format!("data[{offset}] = {value}"),
+        Type::Pubkey => format!("data[{offset}:{}] = bytes({value})", offset + 32),
+        Type::U128 | Type::Array(..) => unreachable!("rejected as arguments"),
+        ty => format!(
+            "struct.pack_into(\"{}\", data, {offset}, {value})",
+            struct_format(ty).expect("integers")
+        ),
+    }
+}
+
+/// Writes a docstring.
+fn docstring(out: &mut String, indent: &str, text: &str) {
+    out.push_str(&format!("{indent}\"\"\"{text}\"\"\"\n"));
+}
+
+/// Writes the size of the state `layout` and its dataclass.
+fn account(out: &mut String, layout: &State) {
+    let name = &layout.account.name;
+    let constant = screaming_case(name);
+    out.push_str(&format!(
+        "\n\n# The size of the data of a `{name}` account in bytes\n{constant}_SIZE = {}\n",
+        layout.size
+    ));
+    if let Some(discriminator) = layout.discriminator {
+        let bytes: Vec<String> = discriminator.iter().map(u8::to_string).collect();
+        out.push_str(&format!(
+            "# The discriminator the data of a `{name}` account starts with\n\
+             {constant}_DISCRIMINATOR = bytes([{}])\n",
+            bytes.join(", ")
+        ));
+    }
+    out.push_str(&format!("\n\n@dataclass(frozen=True)\nclass {name}:\n"));
+    docstring(
+        out,
+        "    ",
+        layout
+            .account
+            .description
+            .as_deref()
+            .unwrap_or(&format!("The {name} account data.")),
+    );
+    out.push('\n');
+    for field in &layout.fields {
+        if let Some(description) = field.description {
+            out.push_str(&format!("    # {description}\n"));
+        }
+        out.push_str(&format!("    {}: {}\n", field.name, py_type(field.ty)));
+    }
+    out.push_str(&format!(
+        "\n    @classmethod\n    def decode(cls, data: bytes) -> \"{name}\":\n"
+    ));
+    docstring(
+        out,
+        "        ",
+        &format!("Decodes the data of a `{name}` account."),
+    );
+    out.push_str(&format!(
+        "        if len(data) < {constant}_SIZE:\n            \
+         raise ValueError(\"{name} account data is too short\")\n"
+    ));
+    if layout.discriminator.is_some() {
+        out.push_str(&format!(
+            "        if data[:8] != {constant}_DISCRIMINATOR:\n            \
+             raise ValueError(\"not a {name} account\")\n"
+        ));
+    }
+    out.push_str("        return cls(\n");
+    for field in &layout.fields {
+        out.push_str(&format!(
+            "            {}={},\n",
+            field.name,
+            read(field.ty, &field.offset.to_string(), 0)
+        ));
+    }
+    out.push_str("        )\n");
+}
+
+/// Writes the dataclasses of the accounts and arguments of the instruction
+/// `layout` and the builder of the instruction.
+fn instruction(out: &mut String, layout: &Instruction) {
+    let function = &layout.function.name;
+    let pascal = pascal_case(function);
+    let mut params = Vec::new();
+    if !layout.accounts.is_empty() {
+        out.push_str(&format!(
+            "\n\n@dataclass(frozen=True)\nclass {pascal}Accounts:\n"
+        ));
+        docstring(
+            out,
+            "    ",
+            &format!("The accounts of a `{function}` instruction."),
+        );
+        out.push('\n');
+        for account in layout.accounts {
+            out.push_str(&format!("    {}: Pubkey\n", account.name));
+        }
+        params.push(format!("accounts: {pascal}Accounts"));
+    }
+    if !layout.args.is_empty() {
+        out.push_str(&format!(
+            "\n\n@dataclass(frozen=True)\nclass {pascal}Args:\n"
+        ));
+        docstring(
+            out,
+            "    ",
+            &format!("The arguments of a `{function}` instruction."),
+        );
+        out.push('\n');
+        for arg in &layout.args {
+            out.push_str(&format!("    {}: {}\n", arg.name, py_type(arg.ty)));
+        }
+        params.push(format!("args: {pascal}Args"));
+    }
+    params.push("program_id: Pubkey = PROGRAM_ID".to_string());
+
+    out.push_str(&format!(
+        "\n\ndef create_{function}_instruction(\n    {},\n) -> Instruction:\n",
+        params.join(",\n    ")
+    ));
+    docstring(
+        out,
+        "    ",
+        layout
+            .function
+            .description
+            .as_deref()
+            .unwrap_or(&format!("Builds a `{function}` instruction.")),
+    );
+    let bytes: Vec<String> = layout.prefix.iter().map(u8::to_string).collect();
+    out.push_str(&format!(
+        "    data = bytearray({})\n    data[0:{}] = bytes([{}])\n",
+        layout.size,
+        layout.prefix.len(),
+        bytes.join(", ")
+    ));
+    for arg in &layout.args {
+        let value = format!("args.{}", arg.name);
+        out.push_str(&format!("    {}\n", write(arg.ty, &value, arg.offset)));
+    }
+    out.push_str("    return Instruction(\n        program_id,\n        bytes(data),\n        [\n");
+    for account in layout.accounts {
+        out.push_str(&format!(
+            "            AccountMeta(accounts.{}, is_signer={}, is_writable={}),\n",
+            account.name,
+            if account.signer { "True" } else { "False" },
+            if account.writable { "True" } else { "False" },
+        ));
+    }
+    out.push_str("        ],\n    )\n");
+}
+
+fn init_py(spec: &Spec, framework: Framework) -> String {
+    let mut out = format!(
+        "\"\"\"Python client of the {} program: account decoders and instruction\nbuilders.\"\"\"\n\n\
+         from __future__ import annotations\n\n\
+         import struct\n\
+         from dataclasses import dataclass\n\n\
+         from solders.instruction import AccountMeta, Instruction\n\
+         from solders.pubkey import Pubkey\n\n\
+         # The id of the {} program\n\
+         PROGRAM_ID = Pubkey.from_string(\"{}\")\n",
+        spec.name,
+        spec.name,
+        program_id(&spec.name)
+    );
+    for state in layout::states(spec, framework) {
+        account(&mut out, &state);
+    }
+    for layout in layout::instructions(spec, framework) {
+        instruction(&mut out, &layout);
+    }
+    out
+}
+
+/// Generates the files of the client package, relative to the crate root.
+pub(super) fn py_client_files(spec: &Spec, framework: Framework) -> Vec<(String, String)> {
+    vec![
+        (
+            format!("{PY_CLIENT_DIR}/pyproject.toml"),
+            pyproject_toml(spec),
+        ),
+        (
+            format!("{PY_CLIENT_DIR}/{}/__init__.py", package(spec)),
+            init_py(spec, framework),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_init_py() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let out = init_py(&spec, Framework::Native);
+        for line in [
+            &format!(
+                "PROGRAM_ID = Pubkey.from_string(\"{}\")\n",
+                program_id("vault")
+            ),
+            "VAULT_SIZE = 48\n",
+            "@dataclass(frozen=True)\nclass Vault:\n    \"\"\"The vault account.\"\"\"\n\n    \
+             # The vault owner (authority)\n    owner: Pubkey\n",
+            "        return cls(\n            \
+             owner=Pubkey.from_bytes(bytes(data[0:32])),\n            \
+             shares_total=struct.unpack_from(\"<Q\", data, 32)[0],\n",
+            "def create_withdraw_instruction(\n    accounts: WithdrawAccounts,\n    \
+             args: WithdrawArgs,\n    program_id: Pubkey = PROGRAM_ID,\n) -> Instruction:\n",
+            "    data = bytearray(9)\n    data[0:1] = bytes([1])\n    \
+             struct.pack_into(\"<Q\", data, 1, args.shares)\n",
+            "            AccountMeta(accounts.vault, is_signer=False, is_writable=True),\n",
+        ] {
+            assert!(out.contains(line), "missing `{line}` in\n{out}");
+        }
+
+        let out = init_py(&spec, Framework::Anchor);
+        assert!(out.contains("VAULT_SIZE = 56\n"));
+        assert!(out.contains("        if data[:8] != VAULT_DISCRIMINATOR:\n"));
+        // sha256("global:withdraw")[..8]
+        assert!(out.contains("    data[0:8] = bytes([183, 18, 70, 156, 148, 109, 161, 34])\n"));
+
+        assert_eq!(
+            read(&Type::Array(Box::new(Type::U16), 3), "8", 0),
+            "[struct.unpack_from(\"<H\", data, 8 + i0 * 2)[0] for i0 in range(3)]"
+        );
+    }
+}
@@ -0,0 +1,586 @@
+//! Compilation of the spec into the certora module.
+//!
+//! Each invariant becomes a pair of `assume_<name>!`/`assert_<name>!` macros
+//! in `src/certora.rs` over the state it names. `src/certora/spec.rs` then
+//! holds, per function, a sanity rule, a rule asserting its postconditions
+//! and a rule per invariant it could break, i.e. one over state the function
+//! writes. Rules only consider calls that succeed, so the preconditions the
+//! processor checks are assumed.
+
+use super::{account_binding, native_int, with_article, Framework, FunctionGen, Translator};
+use crate::materialize::MaterializeError;
+use spec::{Account, AccountInput, Function, Invariant, Spec, Type};
+use std::collections::BTreeSet;
+
+/// The state accounts `invariant` ranges over, in declaration order.
+pub(crate) fn invariant_accounts<'a>(spec: &'a Spec, invariant: &Invariant) -> Vec<&'a Account> {
+    let mut bindings = BTreeSet::new();
+    invariant.expr.visit_paths(&mut |path, _| {
+        bindings.insert(path[0].clone());
+    });
+    spec.state
+        .iter()
+        .filter(|account| bindings.contains(&account.binding()))
+        .collect()
+}
+
+/// The accounts of `function` an invariant over `accounts` is checked on:
+/// the first of each type, provided the function writes one of them.
+fn invariant_inputs<'a>(
+    function: &'a Function,
+    accounts: &[&Account],
+) -> Option<Vec<&'a AccountInput>> {
+    let inputs = accounts
+        .iter()
+        .map(|account| {
+            function
+                .accounts
+                .iter()
+                .find(|input| input.ty.as_deref() == Some(account.name.as_str()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    inputs.iter().any(|input| input.writable).then_some(inputs)
+}
+
+/// Writes the `assume_`/`assert_` macro for `invariant`.
+fn invariant_macro(
+    out: &mut String,
+    kind: &str,
+    invariant: &Invariant,
+    accounts: &[&Account],
+    code: &str,
+) {
+    let verb = if kind == "assume" {
+        "Assumes"
+    } else {
+        "Asserts"
+    };
+    out.push_str(&format!("\n/// {verb} the {} invariant", invariant.name));
+    match &invariant.description {
+        Some(description) => out.push_str(&format!(": {}\n", description.trim())),
+        None => out.push_str(".\n"),
+    }
+    out.push_str(&format!(
+        "#[macro_export]\nmacro_rules! {kind}_{} {{\n",
+        invariant.name
+    ));
+    let bindings: Vec<String> = accounts.iter().map(|account| account.binding()).collect();
+    let params: Vec<String> = bindings.iter().map(|b| format!("${b}:expr")).collect();
+    out.push_str(&format!("    ({}) => {{{{\n", params.join(", ")));
+    for binding in &bindings {
+        out.push_str(&format!("        let {binding} = &${binding};\n"));
+    }
+    out.push_str(&format!(
+        "        cvlr::cvlr_{kind}!({code});\n    }}}};\n}}\n"
+    ));
+}
+
+/// The translation of `invariant`, over references to the state accounts it
+/// ranges over named by their bindings.
+pub(super) fn invariant_code(
+    spec: &Spec,
+    framework: Framework,
+    invariant: &Invariant,
+) -> Result<String, MaterializeError> {
+    let accounts = invariant_accounts(spec, invariant);
+    // Translated as a condition over accounts named by their bindings
+    let function = Function {
+        name: invariant.name.clone(),
+        description: None,
+        accounts: accounts
+            .iter()
+            .map(|account| AccountInput {
+                name: account.binding(),
+                ty: Some(account.name.clone()),
+                signer: false,
+                writable: false,
+            })
+            .collect(),
+        args: Vec::new(),
+        requires: Vec::new(),
+        ensures: Vec::new(),
+        examples: Vec::new(),
+        compute_units: None,
+    };
+    let translator = Translator {
+        spec,
+        function: &function,
+        framework,
+        postcondition: false,
+        old: false,
+    };
+    let mut keys = false;
+    invariant
+        .expr
+        .visit_paths(&mut |path, _| keys |= path.len() < 2);
+    if keys {
+        Err("invariants can only read account fields".to_string())
+    } else {
+        translator.expr(&invariant.expr).map(|code| code.code)
+    }
+    .map_err(|message| MaterializeError::Unsupported {
+        location: format!("invariants.{}", invariant.name),
+        message,
+    })
+}
+
+/// The expression checking `invariant`, whose translation is `code`, on the
+/// state of `inputs`.
+pub(super) fn holds(
+    spec: &Spec,
+    invariant: &Invariant,
+    code: &str,
+    inputs: &[&AccountInput],
+) -> String {
+    let bindings: Vec<String> = invariant_accounts(spec, invariant)
+        .iter()
+        .zip(inputs)
+        .map(|(account, input)| format!("let {} = &{};", account.binding(), input.name))
+        .collect();
+    format!("{{ {} {code} }}", bindings.join(" "))
+}
+
+/// Generates `src/certora.rs`.
+pub(super) fn certora_rs(spec: &Spec, framework: Framework) -> Result<String, MaterializeError> {
+    let mut out = format!(
+        "//! Formal verification module for the {}.\n\npub mod spec;\n",
+        spec.name.replace('_', " ")
+    );
+    for invariant in &spec.invariants {
+        let accounts = invariant_accounts(spec, invariant);
+        let code = invariant_code(spec, framework, invariant)?;
+        invariant_macro(&mut out, "assume", invariant, &accounts, &code);
+        invariant_macro(&mut out, "assert", invariant, &accounts, &code);
+    }
+    Ok(out)
+}
+
+/// Writes the nondeterministic arguments of `function` and returns the
+/// instruction data expression encoding them.
+fn nondet_args(out: &mut String, function: &Function) -> String {
+    let mut data = Vec::new();
+    for arg in &function.args {
+        let (ty, bytes) = match &arg.ty {
+            Type::Pubkey => {
+                out.push_str(&format!("    let {} = cvlr_nondet_pubkey();\n", arg.name));
+                data.push(format!("{}.as_ref()", arg.name));
+                continue;
+            }
+            Type::Bool => ("bool", format!("&[u8::from({})]", arg.name)),
+            ty => {
+                let native = native_int(ty).unwrap_or("u64");
+                (native, format!("&{}.to_le_bytes()", arg.name))
+            }
+        };
+        out.push_str(&format!("    let {}: {ty} = nondet();\n", arg.name));
+        data.push(bytes);
+    }
+    match data.as_slice() {
+        [] => "&[]".to_string(),
+        [single] if single.starts_with('&') => single.clone(),
+        parts => {
+            out.push_str("    let mut instruction_data = Vec::new();\n");
+            for part in parts {
+                let part = part.strip_prefix('&').unwrap_or(part);
+                out.push_str(&format!(
+                    "    instruction_data.extend_from_slice({});\n",
+                    if part.ends_with(".as_ref()") {
+                        part.to_string()
+                    } else {
+                        format!("&{part}")
+                    }
+                ));
+            }
+            "&instruction_data".to_string()
+        }
+    }
+}
+
+/// The state of the accounts a rule reads, before and after the call.
+#[derive(Default)]
+struct Reads<'a> {
+    /// Accounts whose `AccountInfo` is needed
+    infos: BTreeSet<&'a str>,
+    before: BTreeSet<&'a str>,
+    after: BTreeSet<&'a str>,
+    framework: Framework,
+}
+
+impl<'a> Reads<'a> {
+    /// Records the accounts read by a postcondition of `function`.
+    fn condition(&mut self, function: &'a Function, expr: &'a spec::Expr) {
+        expr.visit_paths(&mut |path, in_old| {
+            let Some(account) = function.account(&path[0]) else {
+                return;
+            };
+            self.infos.insert(&account.name);
+            if path.len() > 1 {
+                if in_old {
+                    self.before.insert(&account.name);
+                } else {
+                    self.after.insert(&account.name);
+                }
+            }
+        });
+    }
+
+    /// Writes the bindings of the `AccountInfo`s and of the account data
+    /// before the call.
+    fn bind_before(&self, out: &mut String, function: &Function, types: &mut BTreeSet<String>) {
+        for (i, account) in function.accounts.iter().enumerate() {
+            if self.infos.contains(account.name.as_str()) {
+                out.push_str(&format!(
+                    "    let {} = &account_infos[{i}];\n",
+                    account_binding(account)
+                ));
+            }
+        }
+        self.bind_data(out, function, &self.before, "old_", types);
+    }
+
+    /// Writes the bindings of the account data after the call.
+    fn bind_after(&self, out: &mut String, function: &Function, types: &mut BTreeSet<String>) {
+        self.bind_data(out, function, &self.after, "", types);
+    }
+
+    fn bind_data(
+        &self,
+        out: &mut String,
+        function: &Function,
+        names: &BTreeSet<&str>,
+        prefix: &str,
+        types: &mut BTreeSet<String>,
+    ) {
+        for account in &function.accounts {
+            let (Some(ty), true) = (&account.ty, names.contains(account.name.as_str())) else {
+                continue;
+            };
+            types.insert(ty.clone());
+            out.push_str(&format!(
+                "    let {prefix}{}: {ty} = *bytemuck::from_bytes(&{}.data.borrow(){});\n",
+                account.name,
+                account_binding(account),
+                self.framework.state_data()
+            ));
+        }
+    }
+}
+
+/// Writes the head of a rule up to the nondeterministic accounts.
+fn rule_head(out: &mut String, doc: &str, name: &str) {
+    out.push_str(&format!(
+        "\n/// {doc}\n#[rule]\npub fn {name}() {{\n    let account_infos = cvlr_deserialize_nondet_accounts();\n"
+    ));
+}
+
+/// Writes the call of `function` with nondeterministic arguments, which only
+/// continues if it succeeds.
+fn call(out: &mut String, function: &Function) {
+    out.push('\n');
+    let data = nondet_args(out, function);
+    out.push_str(&format!(
+        "    process_{}(&account_infos, {data}).unwrap();\n\n",
+        function.name
+    ));
+}
+
+/// The invariants `function` could break, with the accounts each is checked
+/// on.
+pub(crate) fn invariants<'a>(
+    spec: &'a Spec,
+    function: &'a Function,
+) -> Vec<(&'a Invariant, Vec<&'a AccountInput>)> {
+    spec.invariants
+        .iter()
+        .filter_map(|invariant| {
+            let accounts = invariant_accounts(spec, invariant);
+            Some((invariant, invariant_inputs(function, &accounts)?))
+        })
+        .collect()
+}
+
+/// The rules compiled for `function`, each with the invariants it assumes
+/// or asserts.
+pub(crate) fn function_rules(spec: &Spec, function: &Function) -> Vec<(String, Vec<String>)> {
+    let invariants: Vec<String> = invariants(spec, function)
+        .into_iter()
+        .map(|(invariant, _)| invariant.name.clone())
+        .collect();
+    let mut rules = vec![(format!("rule_{}_sanity", function.name), Vec::new())];
+    if !function.ensures.is_empty() {
+        rules.push((
+            format!("rule_{}_ensures", function.name),
+            invariants.clone(),
+        ));
+    }
+    for invariant in invariants {
+        rules.push((
+            format!("rule_{}_{invariant}", function.name),
+            vec![invariant],
+        ));
+    }
+    rules
+}
+
+/// The arguments of an invariant macro over `inputs`.
+fn macro_args(inputs: &[&AccountInput], prefix: &str) -> String {
+    inputs
+        .iter()
+        .map(|input| format!("{prefix}{}", input.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Writes the rule asserting the postconditions of `function` after a call
+/// from a state satisfying the invariants.
+fn ensures_rule(
+    out: &mut String,
+    spec: &Spec,
+    generated: &FunctionGen,
+    types: &mut BTreeSet<String>,
+) -> Result<(), MaterializeError> {
+    let function = generated.function;
+    let translator = Translator {
+        spec,
+        function,
+        framework: generated.framework,
+        postcondition: true,
+        old: false,
+    };
+    let mut asserts = Vec::new();
+    let mut reads = Reads {
+        framework: generated.framework,
+        ..Reads::default()
+    };
+    for (i, condition) in function.ensures.iter().enumerate() {
+        let code =
+            translator
+                .expr(&condition.expr)
+                .map_err(|message| MaterializeError::Unsupported {
+                    location: format!("functions.{}.ensures[{i}]", function.name),
+                    message,
+                })?;
+        asserts.push(code.code);
+        reads.condition(function, &condition.expr);
+    }
+    let invariants = invariants(spec, function);
+    for (_, inputs) in &invariants {
+        for input in inputs {
+            reads.infos.insert(&input.name);
+            reads.before.insert(&input.name);
+        }
+    }
+
+    rule_head(
+        out,
+        &format!(
+            "Verifies the postconditions of {}.",
+            with_article(&function.name)
+        ),
+        &format!("rule_{}_ensures", function.name),
+    );
+    reads.bind_before(out, function, types);
+    for (invariant, inputs) in &invariants {
+        out.push_str(&format!(
+            "    assume_{}!({});\n",
+            invariant.name,
+            macro_args(inputs, "old_")
+        ));
+    }
+    call(out, function);
+    reads.bind_after(out, function, types);
+    for code in asserts {
+        out.push_str(&format!("    cvlr_assert!({code});\n"));
+    }
+    out.push_str("}\n");
+    Ok(())
+}
+
+/// Writes the rule checking that `function` preserves `invariant`.
+fn invariant_rule(
+    out: &mut String,
+    generated: &FunctionGen,
+    invariant: &Invariant,
+    inputs: &[&AccountInput],
+    types: &mut BTreeSet<String>,
+) {
+    let function = generated.function;
+    let mut reads = Reads {
+        framework: generated.framework,
+        ..Reads::default()
+    };
+    for input in inputs {
+        reads.infos.insert(&input.name);
+        reads.before.insert(&input.name);
+        reads.after.insert(&input.name);
+    }
+    rule_head(
+        out,
+        &format!(
+            "Verifies that {} preserves the {} invariant.",
+            with_article(&function.name),
+            invariant.name
+        ),
+        &format!("rule_{}_{}", function.name, invariant.name),
+    );
+    reads.bind_before(out, function, types);
+    out.push_str(&format!(
+        "    assume_{}!({});\n",
+        invariant.name,
+        macro_args(inputs, "old_")
+    ));
+    call(out, function);
+    reads.bind_after(out, function, types);
+    out.push_str(&format!(
+        "    assert_{}!({});\n}}\n",
+        invariant.name,
+        macro_args(inputs, "")
+    ));
+}
+
+/// Generates `src/certora/spec.rs`.
+pub(super) fn spec_rs(spec: &Spec, functions: &[FunctionGen]) -> Result<String, MaterializeError> {
+    let uses_pubkey = spec
+        .functions
+        .iter()
+        .any(|f| f.args.iter().any(|a| a.ty == Type::Pubkey));
+    let mut rules = String::new();
+    let mut macros = BTreeSet::new();
+    let mut state_types = BTreeSet::new();
+    for generated in functions {
+        let function = generated.function;
+        rule_head(
+            &mut rules,
+            &format!(
+                "Verifies that {} can succeed.",
+                with_article(&function.name)
+            ),
+            &format!("rule_{}_sanity", function.name),
+        );
+        let data = nondet_args(&mut rules, function);
+        rules.push_str(&format!(
+            "\n    process_{}(&account_infos, {data}).unwrap();\n    cvlr_satisfy!(true);\n}}\n",
+            function.name
+        ));
+
+        if !function.ensures.is_empty() {
+            ensures_rule(&mut rules, spec, generated, &mut state_types)?;
+        }
+        for (invariant, inputs) in invariants(spec, function) {
+            invariant_rule(&mut rules, generated, invariant, &inputs, &mut state_types);
+            macros.insert(format!("assert_{}", invariant.name));
+            macros.insert(format!("assume_{}", invariant.name));
+        }
+    }
+
+    let mut out = format!(
+        "//! This module contains the specification for the {} application.\n\n",
+        spec.name.replace('_', " ")
+    );
+    let mut uses: Vec<String> = macros.into_iter().collect();
+    uses.push("processor::*".to_string());
+    match state_types.len() {
+        0 => {}
+        1 => uses.push(format!("state::{}", state_types.first().unwrap())),
+        _ => uses.push(format!(
+            "state::{{{}}}",
+            state_types.into_iter().collect::<Vec<_>>().join(", ")
+        )),
+    }
+    match uses.as_slice() {
+        [single] => out.push_str(&format!("use crate::{single};\n")),
+        _ => out.push_str(&format!("use crate::{{{}}};\n", uses.join(", "))),
+    }
+    out.push_str("use cvlr::prelude::*;\n");
+    if uses_pubkey {
+        out.push_str("use cvlr_solana::{cvlr_deserialize_nondet_accounts, cvlr_nondet_pubkey};\n");
+    } else {
+        out.push_str("use cvlr_solana::cvlr_deserialize_nondet_accounts;\n");
+    }
+    out.push_str(&rules);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_invariant_macros() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let certora = certora_rs(&spec, Framework::Native).unwrap();
+        assert!(certora.contains(
+            "macro_rules! assume_solvency {\n    ($vault:expr) => {{\n        let vault = &$vault;\n        \
+             cvlr::cvlr_assume!(u64::from(vault.shares_total) <= u64::from(vault.token_total));\n"
+        ));
+        assert!(certora.contains("macro_rules! assert_solvency {"));
+    }
+
+    #[test]
+    fn test_rules() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let functions = spec
+            .functions
+            .iter()
+            .map(|function| FunctionGen::new(&spec, function, Framework::Native))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let rules = spec_rs(&spec, &functions).unwrap();
+        assert!(rules.starts_with(
+            "//! This module contains the specification for the vault application.\n\n\
+             use crate::{assert_solvency, assume_solvency, processor::*, state::Vault};\n"
+        ));
+        let deposit = "
+/// Verifies the postconditions of a deposit.
+#[rule]
+pub fn rule_deposit_ensures() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let vault_account = &account_infos[0];
+    let old_vault: Vault = *bytemuck::from_bytes(&vault_account.data.borrow());
+    assume_solvency!(old_vault);
+
+    let amount: u64 = nondet();
+    process_deposit(&account_infos, &amount.to_le_bytes()).unwrap();
+
+    let vault: Vault = *bytemuck::from_bytes(&vault_account.data.borrow());
+    cvlr_assert!((u64::from(vault.token_total) as i128) == ((u64::from(old_vault.token_total) as i128) + (amount as i128)));
+    cvlr_assert!(u64::from(vault.shares_total) >= u64::from(old_vault.shares_total));
+}
+";
+        assert!(rules.contains(deposit), "{rules}");
+        assert!(rules.contains(
+            "    process_withdraw(&account_infos, &shares.to_le_bytes()).unwrap();\n\n    \
+             let vault: Vault = *bytemuck::from_bytes(&vault_account.data.borrow());\n    \
+             assert_solvency!(vault);\n}\n"
+        ));
+        for rule in [
+            "rule_deposit_solvency",
+            "rule_withdraw_solvency",
+            "rule_reward_ensures",
+        ] {
+            assert!(rules.contains(&format!("pub fn {rule}()")), "{rule}");
+        }
+    }
+
+    #[test]
+    fn test_read_only_functions_keep_invariants() {
+        let spec = Spec::from_yaml_str(
+            "\
+name: counter
+state: [{ name: Counter, fields: [{ name: count, type: u64 }] }]
+functions:
+  - name: peek
+    accounts: [{ name: counter, type: Counter }]
+invariants:
+  - { name: bounded, expr: counter.count <= 1000 }
+",
+        )
+        .unwrap();
+        let functions = [FunctionGen::new(&spec, &spec.functions[0], Framework::Native).unwrap()];
+        let rules = spec_rs(&spec, &functions).unwrap();
+        assert!(rules.contains("pub fn rule_peek_sanity()"));
+        assert!(!rules.contains("bounded"), "{rules}");
+    }
+}
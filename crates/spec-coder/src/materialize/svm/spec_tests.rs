@@ -0,0 +1,185 @@
+//! Unit tests compiled from the examples of the spec.
+//!
+//! Every example becomes a `#[test]` in `src/spec_tests.rs` that calls the
+//! function natively from the given state and checks the outcome: a call
+//! expected to fail must return an error, while one expected to succeed must
+//! leave the given field values and establish the postconditions. These run
+//! with `cargo test`, long before the prover would report the same problem.
+
+use super::harness::{self, Postconditions};
+use super::{doc, native_int, with_article, Framework};
+use spec::{Example, Function, Outcome, Spec, Type, Value};
+use std::collections::BTreeSet;
+
+/// The literal of `value` as a `ty`; booleans are stored as bytes.
+pub(super) fn literal(value: Value, ty: &Type) -> Option<String> {
+    let native = match ty {
+        Type::Bool => "u8",
+        ty => native_int(ty)?,
+    };
+    Some(match value {
+        Value::Int(value) => format!("{value}{native}"),
+        Value::Bool(value) => format!("{}{native}", u8::from(value)),
+        Value::Max => format!("{native}::MAX"),
+        Value::Min => format!("{native}::MIN"),
+    })
+}
+
+/// The value of an argument or an `account.field` in the state of `example`.
+pub(super) fn lookup(example: &Example, name: &str) -> Option<Value> {
+    match name.split_once('.') {
+        Some((account, field)) => example.state.get(account)?.get(field).copied(),
+        None => example.args.get(name).copied(),
+    }
+}
+
+fn test(
+    out: &mut String,
+    spec: &Spec,
+    framework: Framework,
+    function: &Function,
+    example: &Example,
+) {
+    out.push('\n');
+    match &example.description {
+        Some(description) => doc(out, "", description),
+        None => out.push_str(&format!(
+            "/// The `{}` example of {}.\n",
+            example.name,
+            with_article(&function.name)
+        )),
+    }
+    out.push_str(&format!(
+        "#[test]\nfn test_{}_{}() {{\n",
+        function.name, example.name
+    ));
+    if !function.accounts.is_empty() {
+        out.push_str(&format!(
+            "    let program_id = {};\n",
+            harness::program_id(framework)
+        ));
+    }
+    let postconditions = Postconditions::new(spec, function, framework);
+    let before = match example.expect {
+        Outcome::Ok => postconditions.before.clone(),
+        Outcome::Error => BTreeSet::new(),
+    };
+    harness::call(
+        out,
+        spec,
+        function,
+        framework,
+        |name, ty| literal(lookup(example, name)?, ty),
+        &before,
+        "",
+    );
+
+    if example.expect == Outcome::Error {
+        out.push_str("\n    assert!(result.is_err());\n}\n");
+        return;
+    }
+    out.push_str("\n    result.unwrap();\n");
+    let mut names = postconditions.after.clone();
+    let mut expected = Vec::new();
+    for (account, fields) in &example.after {
+        let Some(ty) = function
+            .account(account)
+            .and_then(|account| spec.account(account.ty.as_deref()?))
+        else {
+            continue;
+        };
+        for (field, &value) in fields {
+            let Some((field, literal)) = ty
+                .field(field)
+                .and_then(|field| Some((field, literal(value, &field.ty)?)))
+            else {
+                continue;
+            };
+            let access = format!("{account}.{}", field.name);
+            let actual = match native_int(&field.ty) {
+                Some(native) if field.ty != Type::U8 => format!("{native}::from({access})"),
+                _ => access,
+            };
+            names.insert(account.clone());
+            expected.push(format!("    assert_eq!({actual}, {literal});\n"));
+        }
+    }
+    harness::bind_after(out, function, framework, &names, "    ");
+    out.push_str(&expected.concat());
+    for (check, expr) in &postconditions.checks {
+        out.push_str(&format!(
+            "    assert!({check}, \"postcondition `{expr}` violated\");\n"
+        ));
+    }
+    out.push_str("}\n");
+}
+
+/// Generates `src/spec_tests.rs`, or `None` if the spec has no examples.
+pub(super) fn spec_tests_rs(spec: &Spec, framework: Framework) -> Option<String> {
+    let functions: Vec<&Function> = spec
+        .functions
+        .iter()
+        .filter(|function| !function.examples.is_empty())
+        .collect();
+    if functions.is_empty() {
+        return None;
+    }
+    let mut out = String::from("//! Unit tests from the examples of the spec.\n\n");
+    let accounts = functions.iter().any(|f| !f.accounts.is_empty());
+    let keys = accounts
+        || functions
+            .iter()
+            .any(|f| f.args.iter().any(|a| a.ty == Type::Pubkey));
+    match (accounts, keys) {
+        (true, _) => {
+            out.push_str("use solana_program::{account_info::AccountInfo, pubkey::Pubkey};\n")
+        }
+        (false, true) => out.push_str("use solana_program::pubkey::Pubkey;\n"),
+        (false, false) => {}
+    }
+    for function in functions {
+        for example in &function.examples {
+            test(&mut out, spec, framework, function, example);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_spec_tests_rs() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let tests = spec_tests_rs(&spec, Framework::Native).unwrap();
+        for line in [
+            "/// The first deposit is taken in full.\n#[test]\nfn test_deposit_into_empty_vault() {",
+            "/// The `zero_amount` example of a deposit.",
+            "    let amount = 0u64;",
+            "    vault.token_total = u64::MAX.into();",
+            "    let old_vault = vault;",
+            "    result.unwrap();\n    let vault_data = vault_account.data.borrow();",
+            "    assert_eq!(u64::from(vault.token_total), 100u64);",
+            "    assert!(result.is_err());\n}\n",
+        ] {
+            assert!(tests.contains(line), "missing `{line}` in\n{tests}");
+        }
+
+        let mut spec = spec;
+        for function in &mut spec.functions {
+            function.examples.clear();
+        }
+        assert!(spec_tests_rs(&spec, Framework::Native).is_none());
+    }
+
+    #[test]
+    fn test_literal() {
+        assert_eq!(literal(Value::Int(-3), &Type::I64).unwrap(), "-3i64");
+        assert_eq!(literal(Value::Bool(true), &Type::Bool).unwrap(), "1u8");
+        assert_eq!(literal(Value::Min, &Type::U16).unwrap(), "u16::MIN");
+        assert!(literal(Value::Max, &Type::Pubkey).is_none());
+    }
+}
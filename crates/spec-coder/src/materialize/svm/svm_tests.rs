@@ -0,0 +1,319 @@
+//! End-to-end tests of the program built for the SVM.
+//!
+//! The unit tests from the examples call the processor functions natively;
+//! `tests/svm_tests.rs` sends the same calls as transactions to the program
+//! loaded in LiteSVM, so the entrypoint, the instruction encoding and the
+//! account checks of the runtime are exercised too. Every example becomes a
+//! test asserting the outcome, the given field values, the postconditions
+//! and the invariants that held before the call. A last test sends the calls
+//! of every example in turn to the same accounts, starting from zeroed
+//! state, and checks after each one that it preserved the invariants.
+
+use super::harness::{key_bindings, Postconditions};
+use super::litesvm::{self, account_data, instruction, instruction_data, keys, program, states};
+use super::rules::{holds, invariant_code, invariants};
+use super::spec_tests::{literal, lookup};
+use super::{native_int, Framework};
+use crate::materialize::MaterializeError;
+use spec::{Example, Function, Outcome, Spec, Type};
+use std::collections::BTreeSet;
+
+/// Binds the state of the typed accounts of `function` in `names` as read
+/// from the simulator.
+fn bind_states(
+    out: &mut String,
+    spec: &Spec,
+    framework: Framework,
+    function: &Function,
+    names: &BTreeSet<String>,
+) {
+    let program = program(spec, framework);
+    for account in &function.accounts {
+        let (Some(ty), true) = (account.ty.as_deref(), names.contains(&account.name)) else {
+            continue;
+        };
+        out.push_str(&format!(
+            "    let {name}: {program}::state::{ty} = state(&svm, &{name}_key);\n",
+            name = account.name
+        ));
+    }
+}
+
+/// The invariants `function` could break, each with the expression checking
+/// it on the bound states and the accounts it reads.
+fn invariant_checks(
+    spec: &Spec,
+    framework: Framework,
+    function: &Function,
+) -> Result<Vec<(String, String, BTreeSet<String>)>, MaterializeError> {
+    invariants(spec, function)
+        .into_iter()
+        .map(|(invariant, inputs)| {
+            let code = invariant_code(spec, framework, invariant)?;
+            let names = inputs.iter().map(|input| input.name.clone()).collect();
+            Ok((
+                invariant.name.clone(),
+                holds(spec, invariant, &code, &inputs),
+                names,
+            ))
+        })
+        .collect()
+}
+
+/// Writes the test sending the call of `example`.
+fn test(
+    out: &mut String,
+    spec: &Spec,
+    framework: Framework,
+    function: &Function,
+    example: &Example,
+) -> Result<(), MaterializeError> {
+    let value = |name: &str, ty: &Type| literal(lookup(example, name)?, ty);
+    out.push_str(&format!(
+        "\n#[test]\nfn {}_{}() {{\n    let mut svm = svm();\n",
+        function.name, example.name
+    ));
+    instruction_data(out, spec, framework, function, &value);
+    let signers = keys(out, function);
+    states(out, spec, framework, function, &value);
+    instruction(out, function);
+    let send = format!("send(&mut svm, instruction, &[{}])", signers.join(", "));
+    if example.expect == Outcome::Error {
+        out.push_str(&format!("    assert!({send}.is_err());\n}}\n"));
+        return Ok(());
+    }
+
+    let postconditions = Postconditions::new(spec, function, framework);
+    let checks = invariant_checks(spec, framework, function)?;
+    for name in &postconditions.before {
+        if function.account(name).is_some_and(|a| a.ty.is_some()) {
+            out.push_str(&format!("    let old_{name} = {name};\n"));
+        }
+    }
+    for (name, holds, _) in &checks {
+        out.push_str(&format!("    let {name}_held = {holds};\n"));
+    }
+    out.push_str(&format!("    {send}.unwrap();\n\n"));
+
+    let mut names = postconditions.after.clone();
+    for (_, _, inputs) in &checks {
+        names.extend(inputs.iter().cloned());
+    }
+    let mut expected = Vec::new();
+    for (account, fields) in &example.after {
+        let Some(ty) = function
+            .account(account)
+            .and_then(|account| spec.account(account.ty.as_deref()?))
+        else {
+            continue;
+        };
+        for (field, &value) in fields {
+            let Some((field, literal)) = ty
+                .field(field)
+                .and_then(|field| Some((field, literal(value, &field.ty)?)))
+            else {
+                continue;
+            };
+            let access = format!("{account}.{}", field.name);
+            let actual = match native_int(&field.ty) {
+                Some(native) if field.ty != Type::U8 => format!("{native}::from({access})"),
+                _ => access,
+            };
+            names.insert(account.clone());
+            expected.push(format!("    assert_eq!({actual}, {literal});\n"));
+        }
+    }
+    bind_states(out, spec, framework, function, &names);
+    out.push_str(&expected.concat());
+    for (check, expr) in &postconditions.checks {
+        out.push_str(&format!(
+            "    assert!({check}, \"postcondition `{expr}` violated\");\n"
+        ));
+    }
+    for (name, holds, _) in &checks {
+        out.push_str(&format!(
+            "    assert!(!{name}_held || {holds}, \"invariant `{name}` violated\");\n"
+        ));
+    }
+    out.push_str("}\n");
+    Ok(())
+}
+
+/// Writes the test sending the calls of every example in turn to the same
+/// accounts, named after the accounts of the functions.
+fn sequence(out: &mut String, spec: &Spec, framework: Framework) -> Result<(), MaterializeError> {
+    let program = program(spec, framework);
+    out.push_str(
+        "\n/// The calls of every example in turn, on the same accounts.\n\
+         #[test]\nfn sequence() {\n    let mut svm = svm();\n",
+    );
+    // An account is shared by the functions naming it the same
+    let mut accounts = Vec::new();
+    let mut bindings = Vec::new();
+    for function in &spec.functions {
+        for account in &function.accounts {
+            if !accounts.iter().any(|(name, _, _)| *name == account.name) {
+                accounts.push((account.name.clone(), account.ty.clone(), account.signer));
+            }
+        }
+        bindings.extend(key_bindings(spec, function));
+    }
+    for (name, _, signer) in &accounts {
+        out.push_str(&match signer {
+            true => format!(
+                "    let {name}_signer = Keypair::new();\n    let {name}_key = {name}_signer.pubkey();\n"
+            ),
+            false => format!("    let {name}_key = Pubkey::new_unique();\n"),
+        });
+    }
+    for (name, ty, _) in &accounts {
+        let Some(ty) = ty else { continue };
+        let keys: Vec<&(String, String)> = bindings
+            .iter()
+            .filter(|(field, key)| field.starts_with(&format!("{name}.")) && key.ends_with("_key"))
+            .collect();
+        let mutable = if keys.is_empty() { "" } else { "mut " };
+        out.push_str(&format!(
+            "\n    let {mutable}{name}: {program}::state::{ty} = bytemuck::Zeroable::zeroed();\n"
+        ));
+        let mut assigned = BTreeSet::new();
+        for (field, key) in keys {
+            if assigned.insert(field) {
+                let key = match framework {
+                    Framework::Pinocchio => format!("{key}.to_bytes()"),
+                    _ => key.clone(),
+                };
+                out.push_str(&format!("    {field} = {key};\n"));
+            }
+        }
+        out.push_str(&format!(
+            "    set_state(&mut svm, {name}_key, {});\n",
+            account_data(framework, name, ty)
+        ));
+    }
+
+    for function in &spec.functions {
+        let checks = invariant_checks(spec, framework, function)?;
+        let names: BTreeSet<String> = checks
+            .iter()
+            .flat_map(|(_, _, inputs)| inputs.iter().cloned())
+            .collect();
+        let signers: Vec<String> = function
+            .accounts
+            .iter()
+            .filter(|account| account.signer)
+            .map(|account| format!("&{}_signer", account.name))
+            .collect();
+        for example in &function.examples {
+            let value = |name: &str, ty: &Type| literal(lookup(example, name)?, ty);
+            out.push_str(&format!(
+                "\n    // {} {}\n    {{\n",
+                function.name, example.name
+            ));
+            let mut body = String::new();
+            instruction_data(&mut body, spec, framework, function, &value);
+            bind_states(&mut body, spec, framework, function, &names);
+            for (name, holds, _) in &checks {
+                body.push_str(&format!("    let {name}_held = {holds};\n"));
+            }
+            instruction(&mut body, function);
+            // A failing call leaves the accounts as they were
+            body.push_str(&format!(
+                "    let _ = send(&mut svm, instruction, &[{}]);\n",
+                signers.join(", ")
+            ));
+            if !checks.is_empty() {
+                body.push('\n');
+                bind_states(&mut body, spec, framework, function, &names);
+            }
+            for (name, holds, _) in &checks {
+                body.push_str(&format!(
+                    "    assert!(!{name}_held || {holds}, \"invariant `{name}` violated\");\n"
+                ));
+            }
+            for line in body.lines() {
+                match line {
+                    "" => out.push('\n'),
+                    line => out.push_str(&format!("    {line}\n")),
+                }
+            }
+            out.push_str("    }\n");
+        }
+    }
+    out.push_str("}\n");
+    Ok(())
+}
+
+/// Generates `tests/svm_tests.rs`, or `None` if the spec has no functions.
+pub(super) fn svm_tests_rs(
+    spec: &Spec,
+    framework: Framework,
+) -> Result<Option<String>, MaterializeError> {
+    if spec.functions.is_empty() {
+        return Ok(None);
+    }
+    let mut out = litesvm::preamble(
+        spec,
+        framework,
+        "//! End-to-end tests of the program from the examples and invariants of\n\
+         //! the spec.\n//!\n\
+         //! Run with `cargo test-sbf --test svm_tests`, which builds the program\n\
+         //! first.\n",
+    );
+    let offset = match framework {
+        Framework::Anchor => "8",
+        Framework::Native | Framework::Pinocchio => "0",
+    };
+    out.push_str(&format!(
+        r#"
+/// The state held by the account at `key`.
+fn state<T: bytemuck::Pod>(svm: &LiteSVM, key: &Pubkey) -> T {{
+    let account = svm.get_account(key).expect("the account exists");
+    bytemuck::pod_read_unaligned(&account.data[{offset}..])
+}}
+"#
+    ));
+    for function in &spec.functions {
+        for example in &function.examples {
+            test(&mut out, spec, framework, function, example)?;
+        }
+    }
+    sequence(&mut out, spec, framework)?;
+    Ok(Some(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_svm_tests_rs() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let out = svm_tests_rs(&spec, Framework::Native).unwrap().unwrap();
+        for line in [
+            "#![cfg(feature = \"test-sbf\")]\n",
+            "    bytemuck::pod_read_unaligned(&account.data[0..])\n",
+            "fn withdraw_everything() {\n    let mut svm = svm();\n    \
+             let mut instruction_data = vec![1u8];\n",
+            "    let old_vault = vault;\n    \
+             let solvency_held = { let vault = &vault; u64::from(vault.shares_total) <= \
+             u64::from(vault.token_total) };\n    \
+             send(&mut svm, instruction, &[]).unwrap();\n\n    \
+             let vault: solana_vault::state::Vault = state(&svm, &vault_key);\n    \
+             assert_eq!(u64::from(vault.shares_total), 0u64);\n",
+            "    assert!(send(&mut svm, instruction, &[]).is_err());\n",
+            "fn sequence() {\n    let mut svm = svm();\n    let vault_key = Pubkey::new_unique();\n\n    \
+             let vault: solana_vault::state::Vault = bytemuck::Zeroable::zeroed();\n    \
+             set_state(&mut svm, vault_key, bytemuck::bytes_of(&vault).to_vec());\n",
+            "\n    // withdraw everything\n    {\n        let mut instruction_data = vec![1u8];\n",
+            "        let _ = send(&mut svm, instruction, &[]);\n",
+        ] {
+            assert!(out.contains(line), "missing `{line}` in\n{out}");
+        }
+
+        let out = svm_tests_rs(&spec, Framework::Anchor).unwrap().unwrap();
+        assert!(out.contains("    bytemuck::pod_read_unaligned(&account.data[8..])\n"));
+    }
+}
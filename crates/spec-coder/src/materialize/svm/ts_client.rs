@@ -0,0 +1,375 @@
+//! The TypeScript client of the program.
+//!
+//! `clients/ts` is an npm package over `@solana/web3.js` with, per state
+//! type, an interface and a decoder reading the account data at the offsets
+//! of its Pod layout, and per function a builder of its
+//! `TransactionInstruction`, encoding the arguments as the entrypoint
+//! decodes them. Integers of 64 bits are `bigint`s, keys `PublicKey`s.
+
+use super::layout::{self, Instruction, State};
+use super::{pascal_case, program_id, Framework, TS_CLIENT_DIR};
+use spec::{Spec, Type};
+
+/// The package manifest, written as is.
+fn package_json(spec: &Spec) -> String {
+    format!(
+        r#"{{
+  "name": "{}-client",
+  "version": "0.1.0",
+  "description": "TypeScript client of the {} program",
+  "main": "dist/index.js",
+  "types": "dist/index.d.ts",
+  "scripts": {{
+    "build": "tsc"
+  }},
+  "dependencies": {{
+    "@solana/web3.js": "^1.95"
+  }},
+  "devDependencies": {{
+    "typescript": "^5.4"
+  }}
+}}
+"#,
+        spec.name.replace('_', "-"),
+        spec.name
+    )
+}
+
+const TSCONFIG_JSON: &str = r#"{
+  "compilerOptions": {
+    "target": "ES2020",
+    "module": "commonjs",
+    "declaration": true,
+    "strict": true,
+    "outDir": "dist"
+  },
+  "include": ["src"]
+}
+"#;
+
+/// A snake case name in camelCase, e.g. `sharesTotal`.
+fn camel_case(name: &str) -> String {
+    let pascal = pascal_case(name);
+    let mut chars = pascal.chars();
+    chars
+        .next()
+        .map(|first| first.to_ascii_lowercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+/// A PascalCase name in SCREAMING_SNAKE_CASE, e.g. `BONDING_CURVE`.
+pub(super) fn screaming_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_uppercase());
+    }
+    out
+}
+
+/// The TypeScript type of a value of type `ty`.
+fn ts_type(ty: &Type) -> String {
+    match ty {
+        Type::Bool => "boolean".to_string(),
+        Type::U8 | Type::U16 | Type::U32 => "number".to_string(),
+        Type::U64 | Type::U128 | Type::I64 => "bigint".to_string(),
+        Type::Pubkey => "PublicKey".to_string(),
+        Type::Array(inner, _) => format!("{}[]", ts_type(inner)),
+    }
+}
+
+/// The expression reading a value of type `ty` at `offset` of `data`, whose
+/// `DataView` is `view`; `depth` numbers the indices of nested arrays.
+fn read(ty: &Type, offset: &str, depth: usize) -> String {
+    match ty {
+        Type::Bool => format!("data[{offset}] !== 0"),
+        Type::U8 => format!("data[{offset}]"),
+        Type::U16 => format!("view.getUint16({offset}, true)"),
+        Type::U32 => format!("view.getUint32({offset}, true)"),
+        Type::U64 => format!("view.getBigUint64({offset}, true)"),
+        Type::I64 => format!("view.getBigInt64({offset}, true)"),
+        Type::U128 => format!(
+            "view.getBigUint64({offset}, true) | (view.getBigUint64({offset} + 8, true) << 64n)"
+        ),
+        Type::Pubkey => {
+            let end = match offset.parse::<usize>() {
+                Ok(offset) => (offset + 32).to_string(),
+                Err(_) => format!("{offset} + 32"),
+            };
+            format!("new PublicKey(data.subarray({offset}, {end}))")
+        }
+        Type::Array(inner, len) => {
+            let index = format!("i{depth}");
+            let element = read(
+                inner,
+                &format!("{offset} + {index} * {}", inner.size()),
+                depth + 1,
+            );
+            format!("Array.from({{ length: {len} }}, (_, {index}) => {element})")
+        }
+    }
+}
+
+/// The statement writing `value` of type `ty` at `offset` of `data`, whose
+/// `DataView` is `view`. Arguments are never arrays.
+fn write(ty: &Type, value: &str, offset: usize) -> String {
+    match ty {
+        Type::Bool => format!("data[{offset}] = {value} ? 1 : 0;"),
+        Type::U8 => format!("data[{offset}] = {value};"),
+        Type::U16 => format!("view.setUint16({offset}, {value}, true);"),
+        Type::U32 => format!("view.setUint32({offset}, {value}, true);"),
+        Type::U64 => format!("view.setBigUint64({offset}, {value}, true);"),
+        Type::I64 => format!("view.setBigInt64({offset}, {value}, true);"),
+        Type::Pubkey => format!("data.set({value}.toBytes(), {offset});"),
+        Type::U128 | Type::Array(..) => unreachable!("rejected as arguments"),
+    }
+}
+
+/// Writes a doc comment.
+fn doc(out: &mut String, indent: &str, text: &str) {
+    out.push_str(&format!("{indent}/** {text} */\n"));
+}
+
+/// Writes the interface, size and decoder of the state `layout`.
+fn account(out: &mut String, layout: &State) {
+    let name = &layout.account.name;
+    let constant = screaming_case(name);
+    out.push('\n');
+    doc(
+        out,
+        "",
+        layout
+            .account
+            .description
+            .as_deref()
+            .unwrap_or(&format!("The {name} account data.")),
+    );
+    out.push_str(&format!("export interface {name} {{\n"));
+    for field in &layout.fields {
+        if let Some(description) = field.description {
+            doc(out, "  ", description);
+        }
+        out.push_str(&format!(
+            "  {}: {};\n",
+            camel_case(field.name),
+            ts_type(field.ty)
+        ));
+    }
+    out.push_str("}\n\n");
+    doc(
+        out,
+        "",
+        &format!("The size of the data of a `{name}` account in bytes."),
+    );
+    out.push_str(&format!(
+        "export const {constant}_SIZE = {};\n",
+        layout.size
+    ));
+    if let Some(discriminator) = layout.discriminator {
+        let bytes: Vec<String> = discriminator.iter().map(u8::to_string).collect();
+        out.push('\n');
+        doc(
+            out,
+            "",
+            &format!("The discriminator the data of a `{name}` account starts with."),
+        );
+        out.push_str(&format!(
+            "export const {constant}_DISCRIMINATOR = Uint8Array.from([{}]);\n",
+            bytes.join(", ")
+        ));
+    }
+    out.push('\n');
+    doc(out, "", &format!("Decodes the data of a `{name}` account."));
+    out.push_str(&format!(
+        "export function decode{name}(data: Uint8Array): {name} {{\n  \
+         if (data.length < {constant}_SIZE) {{\n    \
+         throw new Error(\"{name} account data is too short\");\n  }}\n"
+    ));
+    if layout.discriminator.is_some() {
+        out.push_str(&format!(
+            "  if (!{constant}_DISCRIMINATOR.every((byte, i) => data[i] === byte)) {{\n    \
+             throw new Error(\"not a {name} account\");\n  }}\n"
+        ));
+    }
+    out.push_str(
+        "  const view = new DataView(data.buffer, data.byteOffset, data.byteLength);\n  return {\n",
+    );
+    for field in &layout.fields {
+        out.push_str(&format!(
+            "    {}: {},\n",
+            camel_case(field.name),
+            read(field.ty, &field.offset.to_string(), 0)
+        ));
+    }
+    out.push_str("  };\n}\n");
+}
+
+/// Writes the interfaces of the accounts and arguments of the instruction
+/// `layout` and the builder of the instruction.
+fn instruction(out: &mut String, layout: &Instruction) {
+    let function = &layout.function.name;
+    let pascal = pascal_case(function);
+    let mut params = Vec::new();
+    if !layout.accounts.is_empty() {
+        out.push('\n');
+        doc(
+            out,
+            "",
+            &format!("The accounts of a `{function}` instruction."),
+        );
+        out.push_str(&format!("export interface {pascal}Accounts {{\n"));
+        for account in layout.accounts {
+            out.push_str(&format!("  {}: PublicKey;\n", camel_case(&account.name)));
+        }
+        out.push_str("}\n");
+        params.push(format!("accounts: {pascal}Accounts"));
+    }
+    if !layout.args.is_empty() {
+        out.push('\n');
+        doc(
+            out,
+            "",
+            &format!("The arguments of a `{function}` instruction."),
+        );
+        out.push_str(&format!("export interface {pascal}Args {{\n"));
+        for arg in &layout.args {
+            out.push_str(&format!(
+                "  {}: {};\n",
+                camel_case(arg.name),
+                ts_type(arg.ty)
+            ));
+        }
+        out.push_str("}\n");
+        params.push(format!("args: {pascal}Args"));
+    }
+    params.push("programId: PublicKey = PROGRAM_ID".to_string());
+
+    out.push('\n');
+    doc(
+        out,
+        "",
+        layout
+            .function
+            .description
+            .as_deref()
+            .unwrap_or(&format!("Builds a `{function}` instruction.")),
+    );
+    out.push_str(&format!(
+        "export function create{pascal}Instruction(\n  {},\n): TransactionInstruction {{\n",
+        params.join(",\n  ")
+    ));
+    out.push_str(&format!(
+        "  const data = new Uint8Array({});\n",
+        layout.size
+    ));
+    if layout.args.iter().any(|arg| arg.ty.size() > 1) {
+        out.push_str("  const view = new DataView(data.buffer);\n");
+    }
+    let bytes: Vec<String> = layout.prefix.iter().map(u8::to_string).collect();
+    out.push_str(&format!("  data.set([{}], 0);\n", bytes.join(", ")));
+    for arg in &layout.args {
+        let value = format!("args.{}", camel_case(arg.name));
+        out.push_str(&format!("  {}\n", write(arg.ty, &value, arg.offset)));
+    }
+    out.push_str("  return new TransactionInstruction({\n    programId,\n    keys: [\n");
+    for account in layout.accounts {
+        out.push_str(&format!(
+            "      {{ pubkey: accounts.{}, isSigner: {}, isWritable: {} }},\n",
+            camel_case(&account.name),
+            account.signer,
+            account.writable
+        ));
+    }
+    out.push_str("    ],\n    data: Buffer.from(data),\n  });\n}\n");
+}
+
+fn index_ts(spec: &Spec, framework: Framework) -> String {
+    let mut out = format!(
+        "/**\n * TypeScript client of the {} program: account decoders and\n * instruction builders.\n */\n\n\
+         import {{ PublicKey, TransactionInstruction }} from \"@solana/web3.js\";\n\n",
+        spec.name
+    );
+    doc(
+        &mut out,
+        "",
+        &format!("The id of the {} program.", spec.name),
+    );
+    out.push_str(&format!(
+        "export const PROGRAM_ID = new PublicKey(\"{}\");\n",
+        program_id(&spec.name)
+    ));
+    for state in layout::states(spec, framework) {
+        account(&mut out, &state);
+    }
+    for layout in layout::instructions(spec, framework) {
+        instruction(&mut out, &layout);
+    }
+    out
+}
+
+/// Generates the files of the client package, relative to the crate root.
+pub(super) fn ts_client_files(spec: &Spec, framework: Framework) -> Vec<(String, String)> {
+    vec![
+        (format!("{TS_CLIENT_DIR}/package.json"), package_json(spec)),
+        (
+            format!("{TS_CLIENT_DIR}/tsconfig.json"),
+            TSCONFIG_JSON.to_string(),
+        ),
+        (
+            format!("{TS_CLIENT_DIR}/.gitignore"),
+            "node_modules\ndist\n".to_string(),
+        ),
+        (
+            format!("{TS_CLIENT_DIR}/src/index.ts"),
+            index_ts(spec, framework),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_index_ts() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let out = index_ts(&spec, Framework::Native);
+        for line in [
+            "import { PublicKey, TransactionInstruction } from \"@solana/web3.js\";\n",
+            &format!(
+                "export const PROGRAM_ID = new PublicKey(\"{}\");\n",
+                program_id("vault")
+            ),
+            "export interface Vault {\n  /** The vault owner (authority) */\n  owner: PublicKey;\n",
+            "export const VAULT_SIZE = 48;\n",
+            "    owner: new PublicKey(data.subarray(0, 32)),\n    \
+             sharesTotal: view.getBigUint64(32, true),\n    \
+             tokenTotal: view.getBigUint64(40, true),\n",
+            "export interface WithdrawArgs {\n  shares: bigint;\n}\n",
+            "export function createWithdrawInstruction(\n  accounts: WithdrawAccounts,\n  \
+             args: WithdrawArgs,\n  programId: PublicKey = PROGRAM_ID,\n): TransactionInstruction {\n  \
+             const data = new Uint8Array(9);\n  const view = new DataView(data.buffer);\n  \
+             data.set([1], 0);\n  view.setBigUint64(1, args.shares, true);\n",
+            "      { pubkey: accounts.vault, isSigner: false, isWritable: true },\n",
+        ] {
+            assert!(out.contains(line), "missing `{line}` in\n{out}");
+        }
+
+        let out = index_ts(&spec, Framework::Anchor);
+        assert!(out.contains("export const VAULT_SIZE = 56;\n"));
+        assert!(out.contains("export const VAULT_DISCRIMINATOR = Uint8Array.from(["));
+        assert!(out.contains("    sharesTotal: view.getBigUint64(40, true),\n"));
+        // sha256("global:withdraw")[..8]
+        assert!(out.contains("  data.set([183, 18, 70, 156, 148, 109, 161, 34], 0);\n"));
+
+        assert_eq!(
+            read(&Type::Array(Box::new(Type::U16), 3), "8", 0),
+            "Array.from({ length: 3 }, (_, i0) => view.getUint16(8 + i0 * 2, true))"
+        );
+        assert_eq!(screaming_case("BondingCurve"), "BONDING_CURVE");
+    }
+}
@@ -0,0 +1,662 @@
+//! A Model Context Protocol server exposing the pipeline as tools.
+//!
+//! `spec-coder mcp` speaks MCP over stdio, so that IDE agents and other
+//! assistants drive materialization and verification themselves instead of
+//! running the CLI: every line read is a JSON-RPC 2.0 message, and every
+//! response is written as a line. Three tools are listed:
+//!
+//! - `materialize_spec` materializes a spec file into its crate, as
+//!   `materialize` does;
+//! - `run_verification` builds an example and proves its rules, or the ones
+//!   asked for, as `verify` does, recording the verdicts with its results;
+//! - `get_counterexample` returns the call trace and the values of the
+//!   counterexample to a violated rule, from the last verification of this
+//!   server or else from the [prover cache](crate::prover::cache).
+//!
+//! A tool that fails answers with the error as text and `isError`, so the
+//! agent can act on it; only malformed requests are JSON-RPC errors.
+
+use crate::driver::{Verdict, Verifier};
+use crate::materialize::{self, MaterializeError};
+use crate::migrate;
+use crate::provenance::{self, Provenance};
+use crate::prover::cache::Cache;
+use crate::prover::{RuleResult, RuleStatus};
+use crate::results;
+use crate::verify;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use spec::{Spec, Target};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+/// The latest protocol version the server speaks.
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// The versions a client may ask for, oldest first.
+const PROTOCOL_VERSIONS: &[&str] = &["2024-11-05", "2025-03-26", PROTOCOL_VERSION];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// A JSON-RPC error.
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn invalid_params(message: impl ToString) -> RpcError {
+        RpcError {
+            code: INVALID_PARAMS,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterializeArgs {
+    spec: PathBuf,
+    #[serde(default)]
+    target: Option<Target>,
+    #[serde(default)]
+    out: Option<PathBuf>,
+    #[serde(default)]
+    force: bool,
+    #[serde(default)]
+    cpi: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VerificationArgs {
+    example: String,
+    #[serde(default)]
+    rules: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CounterexampleArgs {
+    example: String,
+    rule: String,
+}
+
+/// The tools, as `tools/list` describes them.
+fn tools() -> Value {
+    let example = json!({
+        "type": "string",
+        "description": "The name of a materialized example, or the directory of a crate",
+    });
+    json!([
+        {
+            "name": "materialize_spec",
+            "description": "Materialize a spec into its crate: the program skeleton, the unit \
+                tests from the spec examples and the formal verification rules, whose \
+                function bodies the caller then implements",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "spec": {
+                        "type": "string",
+                        "description": "The spec file (.yaml, .yml, .toml or .md)",
+                    },
+                    "target": {
+                        "type": "string",
+                        "enum": ["svm", "anchor", "pinocchio", "rust", "evm"],
+                        "description": "The target platform; defaults to the one the spec declares",
+                    },
+                    "out": {
+                        "type": "string",
+                        "description": "The crate directory, instead of the one of the example",
+                    },
+                    "force": {
+                        "type": "boolean",
+                        "description": "Overwrite an existing crate",
+                    },
+                    "cpi": {
+                        "type": "boolean",
+                        "description": "Also generate the CPI interface crate of a Solana program in cpi/",
+                    },
+                },
+                "required": ["spec"],
+            },
+        },
+        {
+            "name": "run_verification",
+            "description": "Build an example with the certora feature and prove its rules, \
+                returning the build output if it fails and the verdict of every rule",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "example": example,
+                    "rules": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "The rules to prove, by name or pattern such as \
+                            `rule_*_sanity`; all of them if empty",
+                    },
+                    "tags": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Only prove the rules with one of these tags, from \
+                            `[package.metadata.certora.tags]` in the crate's Cargo.toml",
+                    },
+                },
+                "required": ["example"],
+            },
+        },
+        {
+            "name": "get_counterexample",
+            "description": "The call trace and variable values of the counterexample to a \
+                rule that run_verification found violated",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "example": example,
+                    "rule": { "type": "string", "description": "The violated rule" },
+                },
+                "required": ["example", "rule"],
+            },
+        },
+    ])
+}
+
+/// The arguments of a call to the tool `name`.
+fn args<T: DeserializeOwned>(name: &str, arguments: Value) -> Result<T, RpcError> {
+    serde_json::from_value(arguments)
+        .map_err(|err| RpcError::invalid_params(format!("{name}: {err}")))
+}
+
+/// The result of a tool call: its text, and whether it is an error.
+fn tool_result(outcome: Result<String, String>) -> Value {
+    let (text, is_error) = match outcome {
+        Ok(text) => (text, false),
+        Err(text) => (text, true),
+    };
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+/// Answers the tool calls of one client.
+pub struct Server<'a> {
+    pub examples_dir: PathBuf,
+    /// The target of specs that do not name one
+    pub target: Option<Target>,
+    /// The header of generated files, see [`provenance`]
+    pub license: Option<String>,
+    pub verifier: &'a dyn Verifier,
+    /// Rules to prove at once
+    pub jobs: usize,
+    /// The verdicts of the last verification, by crate and rule
+    verified: BTreeMap<(PathBuf, String), RuleResult>,
+}
+
+impl<'a> Server<'a> {
+    pub fn new(
+        examples_dir: PathBuf,
+        target: Option<Target>,
+        license: Option<String>,
+        verifier: &'a dyn Verifier,
+        jobs: usize,
+    ) -> Server<'a> {
+        Server {
+            examples_dir,
+            target,
+            license,
+            verifier,
+            jobs,
+            verified: BTreeMap::new(),
+        }
+    }
+
+    /// Answers the messages of `input`, one per line, on `output` until
+    /// the input ends.
+    pub fn serve(&mut self, input: impl BufRead, mut output: impl Write) -> std::io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                writeln!(output, "{response}")?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The response to the message `line`, unless it is a notification.
+    pub fn handle(&mut self, line: &str) -> Option<Value> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(err) => {
+                return Some(json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": PARSE_ERROR, "message": err.to_string() },
+                }))
+            }
+        };
+        // Notifications, such as `notifications/initialized`, and responses
+        // have nothing to answer
+        let id = message.get("id").cloned()?;
+        let result = match message.get("method").and_then(Value::as_str) {
+            Some(method) => {
+                let params = message.get("params").cloned().unwrap_or(Value::Null);
+                self.call(method, params)
+            }
+            None => Err(RpcError {
+                code: INVALID_REQUEST,
+                message: "a request needs a method".to_string(),
+            }),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": err.code, "message": err.message },
+            }),
+        })
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => {
+                let requested = params.get("protocolVersion").and_then(Value::as_str);
+                let version = requested
+                    .filter(|version| PROTOCOL_VERSIONS.contains(version))
+                    .unwrap_or(PROTOCOL_VERSION);
+                Ok(json!({
+                    "protocolVersion": version,
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "spec-coder", "version": env!("CARGO_PKG_VERSION") },
+                }))
+            }
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools() })),
+            "tools/call" => {
+                let name = params
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| RpcError::invalid_params("a tool call needs a name"))?;
+                let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+                let outcome = match name {
+                    "materialize_spec" => self.materialize_spec(args(name, arguments)?),
+                    "run_verification" => self.run_verification(args(name, arguments)?),
+                    "get_counterexample" => self.get_counterexample(args(name, arguments)?),
+                    _ => return Err(RpcError::invalid_params(format!("unknown tool `{name}`"))),
+                };
+                Ok(tool_result(outcome))
+            }
+            _ => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("unknown method `{method}`"),
+            }),
+        }
+    }
+
+    fn materialize_spec(&self, args: MaterializeArgs) -> Result<String, String> {
+        let spec = Spec::from_path(&args.spec)
+            .map_err(|err| format!("failed to load {}: {err}", args.spec.display()))?;
+        let target = args.target.or(self.target).unwrap_or(spec.target);
+        let mut files = materialize::generate(&spec, target).map_err(|err| err.to_string())?;
+        if args.cpi {
+            files.extend(materialize::cpi(&spec, target).map_err(|err| err.to_string())?);
+        }
+        let provenance = Provenance::new(provenance::fingerprint(&spec), None);
+        provenance::stamp_files(&mut files, &provenance, self.license.as_deref());
+        let dir = args
+            .out
+            .unwrap_or_else(|| materialize::crate_dir(&self.examples_dir, &spec, target));
+        materialize::write(&files, &dir, args.force).map_err(|err| match err {
+            MaterializeError::Exists(_) => format!("{err}; pass force to overwrite it"),
+            err => err.to_string(),
+        })?;
+        migrate::record(&spec, &dir).map_err(|err| err.to_string())?;
+        let mut text = format!(
+            "Materialized `{}` for {target} into {}:\n",
+            spec.name,
+            dir.display()
+        );
+        for file in &files {
+            writeln!(text, "  {}", file.path.display()).expect("strings are written to");
+        }
+        Ok(text)
+    }
+
+    fn run_verification(&mut self, args: VerificationArgs) -> Result<String, String> {
+        let (dir, spec) =
+            verify::locate(&self.examples_dir, &args.example).map_err(|err| err.to_string())?;
+        let summary = verify::verify(
+            &dir,
+            &args.rules,
+            &args.tags,
+            self.verifier,
+            self.jobs,
+            &mut |_| {},
+        )
+        .map_err(|err| err.to_string())?;
+        if let Some(spec) = &spec {
+            let spec = Spec::from_path(spec).map_err(|err| err.to_string())?;
+            let verdicts = summary
+                .results
+                .iter()
+                .map(|result| (result.rule.clone(), result.status))
+                .collect();
+            results::record(&spec, &dir, &verdicts).map_err(|err| err.to_string())?;
+        }
+        if let Verdict::Failed(output) = &summary.build {
+            return Ok(format!(
+                "{} does not build with the certora feature:\n{output}",
+                dir.display()
+            ));
+        }
+        let mut text = String::new();
+        for result in &summary.results {
+            writeln!(text, "{}: {}", result.rule, result.status).expect("strings are written to");
+            self.verified
+                .insert((dir.clone(), result.rule.clone()), result.clone());
+        }
+        let counts: Vec<String> = summary
+            .counts()
+            .iter()
+            .map(|(status, count)| format!("{count} {status}"))
+            .collect();
+        writeln!(
+            text,
+            "{} rules: {}",
+            summary.results.len(),
+            counts.join(", ")
+        )
+        .expect("strings are written to");
+        if summary
+            .results
+            .iter()
+            .any(|result| result.status == RuleStatus::Violated)
+        {
+            text.push_str("Call get_counterexample for the trace of a violated rule.\n");
+        }
+        Ok(text)
+    }
+
+    fn get_counterexample(&self, args: CounterexampleArgs) -> Result<String, String> {
+        let (dir, _) =
+            verify::locate(&self.examples_dir, &args.example).map_err(|err| err.to_string())?;
+        let cache = Cache::load(&dir);
+        let result = self
+            .verified
+            .get(&(dir.clone(), args.rule.clone()))
+            .filter(|result| result.counterexample.is_some())
+            .or_else(|| {
+                cache
+                    .results()
+                    .find(|result| result.rule == args.rule && result.counterexample.is_some())
+            })
+            .ok_or_else(|| {
+                format!(
+                    "no counterexample to `{}` is recorded for {}; call run_verification first",
+                    args.rule,
+                    dir.display()
+                )
+            })?;
+        let counterexample = result.counterexample.as_ref().expect("filtered");
+        let mut text = format!(
+            "{}: {}\n\nCall trace:\n{counterexample}",
+            result.rule, result.status
+        );
+        if !counterexample.assignments.is_empty() {
+            text.push_str("\nValues:\n");
+            for assignment in &counterexample.assignments {
+                writeln!(text, "  {} = {}", assignment.name, assignment.value)
+                    .expect("strings are written to");
+            }
+        }
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::DriverError;
+    use crate::prover::report::{Assignment, Counterexample, TraceNode};
+    use std::path::Path;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    /// Passes every build, violating the sanity rule of `withdraw` with a
+    /// counterexample and verifying the other rules.
+    struct Fake;
+
+    fn counterexample() -> Counterexample {
+        Counterexample {
+            call_trace: TraceNode {
+                message: "process_withdraw".to_string(),
+                children: vec![TraceNode {
+                    message: "amount = 7".to_string(),
+                    children: Vec::new(),
+                }],
+            },
+            assignments: vec![Assignment {
+                name: "amount".to_string(),
+                value: "7".to_string(),
+            }],
+        }
+    }
+
+    impl Verifier for Fake {
+        fn build(&self, _: &Path) -> Result<Verdict, DriverError> {
+            Ok(Verdict::Passed)
+        }
+
+        fn verify(&self, _: &Path, rules: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+            Ok(rules
+                .iter()
+                .map(|rule| {
+                    let violated = rule == "rule_withdraw_sanity";
+                    RuleResult {
+                        rule: rule.clone(),
+                        status: if violated {
+                            RuleStatus::Violated
+                        } else {
+                            RuleStatus::Verified
+                        },
+                        output: String::new(),
+                        counterexample: violated.then(counterexample),
+                        cached: false,
+                    }
+                })
+                .collect())
+        }
+    }
+
+    /// Sends `requests` to a fresh server, returning its responses.
+    fn exchange(examples: &Path, requests: &[Value]) -> Vec<Value> {
+        let mut server = Server::new(examples.to_path_buf(), None, None, &Fake, 2);
+        let input: String = requests
+            .iter()
+            .map(|request| format!("{request}\n"))
+            .collect();
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn call(id: u64, name: &str, arguments: Value) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": arguments },
+        })
+    }
+
+    fn text(response: &Value) -> &str {
+        response["result"]["content"][0]["text"].as_str().unwrap()
+    }
+
+    #[test]
+    fn test_protocol() {
+        let examples = tempfile::tempdir().unwrap();
+        let responses = exchange(
+            examples.path(),
+            &[
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "initialize",
+                    "params": { "protocolVersion": "2025-03-26", "capabilities": {} },
+                }),
+                json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+                json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }),
+                json!({ "jsonrpc": "2.0", "id": 3, "method": "resources/list" }),
+                call(4, "deploy", json!({})),
+                call(5, "run_verification", json!({ "rule": "x" })),
+            ],
+        );
+        // The notification is not answered
+        assert_eq!(responses.len(), 5);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"]["protocolVersion"], "2025-03-26");
+        assert_eq!(responses[0]["result"]["serverInfo"]["name"], "spec-coder");
+        let tools: Vec<&str> = responses[1]["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            tools,
+            ["materialize_spec", "run_verification", "get_counterexample"]
+        );
+        assert_eq!(responses[2]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[3]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(responses[4]["error"]["code"], INVALID_PARAMS);
+
+        let mut server = Server::new(examples.path().to_path_buf(), None, None, &Fake, 1);
+        assert_eq!(server.handle("{").unwrap()["error"]["code"], PARSE_ERROR);
+        // Unknown protocol versions get the latest
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "protocolVersion": "1999-01-01" },
+        });
+        assert_eq!(
+            server.handle(&initialize.to_string()).unwrap()["result"]["protocolVersion"],
+            PROTOCOL_VERSION
+        );
+    }
+
+    #[test]
+    fn test_tools() {
+        let examples = tempfile::tempdir().unwrap();
+        let spec = examples.path().join("specs/vault.yaml");
+        std::fs::create_dir_all(spec.parent().unwrap()).unwrap();
+        std::fs::write(&spec, VAULT).unwrap();
+        let responses = exchange(
+            examples.path(),
+            &[
+                call(
+                    1,
+                    "get_counterexample",
+                    json!({ "example": "vault", "rule": "x" }),
+                ),
+                call(2, "materialize_spec", json!({ "spec": spec })),
+                call(3, "materialize_spec", json!({ "spec": spec })),
+                call(
+                    4,
+                    "get_counterexample",
+                    json!({
+                        "example": "vault",
+                        "rule": "rule_withdraw_sanity",
+                    }),
+                ),
+                call(5, "run_verification", json!({ "example": "vault" })),
+                call(
+                    6,
+                    "get_counterexample",
+                    json!({
+                        "example": "vault",
+                        "rule": "rule_withdraw_sanity",
+                    }),
+                ),
+                call(
+                    7,
+                    "get_counterexample",
+                    json!({
+                        "example": "vault",
+                        "rule": "rule_deposit_sanity",
+                    }),
+                ),
+            ],
+        );
+        let is_error = |response: &Value| response["result"]["isError"].as_bool().unwrap();
+        // The example does not exist yet
+        assert!(is_error(&responses[0]));
+        assert!(!is_error(&responses[1]), "{}", text(&responses[1]));
+        let dir = materialize::crate_dir(
+            examples.path(),
+            &Spec::from_path(&spec).unwrap(),
+            Target::Svm,
+        );
+        assert!(text(&responses[1]).contains(&dir.display().to_string()));
+        assert!(dir.join("Cargo.toml").exists());
+        assert!(is_error(&responses[2]));
+        assert!(text(&responses[2]).contains("pass force"));
+        // Nothing was verified yet
+        assert!(is_error(&responses[3]));
+        assert!(text(&responses[3]).contains("call run_verification first"));
+
+        let verification = text(&responses[4]);
+        assert!(!is_error(&responses[4]), "{verification}");
+        assert!(verification.contains("rule_withdraw_sanity: VIOLATED"));
+        assert!(verification.contains("get_counterexample"));
+        assert!(results::load(&dir).unwrap().is_some());
+
+        let trace = text(&responses[5]);
+        assert!(!is_error(&responses[5]), "{trace}");
+        assert!(trace.contains("process_withdraw"));
+        assert!(trace.contains("amount = 7"));
+        assert!(is_error(&responses[6]));
+
+        // A new server finds the counterexample in the prover cache
+        let mut cache = Cache::load(&dir);
+        let mut result = Fake
+            .verify(&dir, &["rule_withdraw_sanity".to_string()])
+            .unwrap();
+        cache.insert("key".to_string(), &result.remove(0));
+        cache
+            .save(&dir, &["rule_withdraw_sanity".to_string()])
+            .unwrap();
+        let responses = exchange(
+            examples.path(),
+            &[call(
+                1,
+                "get_counterexample",
+                json!({
+                    "example": dir,
+                    "rule": "rule_withdraw_sanity",
+                }),
+            )],
+        );
+        assert!(text(&responses[0]).contains("process_withdraw"));
+    }
+}
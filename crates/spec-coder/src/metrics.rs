@@ -0,0 +1,451 @@
+//! Metrics of runs, kept across runs.
+//!
+//! Every `implement` run that ends, whether it passed or ran out of budget,
+//! appends a [`RunMetrics`] to [`METRICS_JSONL`] in the project root: how
+//! many iterations it took, what the checks failed on, the tokens it spent
+//! and how long it took, with the model, the prompts and the spec it ran
+//! with. [`Stats`] aggregates them by any of those, so that models, prompt
+//! overrides and ways of writing specs can be compared on the same footing.
+//!
+//! The failures are counted by kind as the run reports them: a build or a
+//! test failing, a rule violated with a counterexample, a rule timing out,
+//! a rule passing vacuously, and the prover failing without a verdict.
+
+use crate::driver::{Progress, RunReport, Verdict};
+use crate::prover::RuleStatus;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The metrics store, relative to the project root.
+pub const METRICS_JSONL: &str = ".spec-coder/metrics.jsonl";
+
+/// An error reading or writing the metrics store.
+#[derive(Debug, thiserror::Error)]
+pub enum MetricsError {
+    #[error("failed to access {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("malformed {} at line {line}: {source}", .path.display())]
+    Json {
+        path: PathBuf,
+        line: usize,
+        source: serde_json::Error,
+    },
+}
+
+/// How often each kind of check failed during a run.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Failures {
+    /// Builds or unit tests that failed
+    pub build: usize,
+    /// Rules violated with a counterexample
+    pub counterexample: usize,
+    pub timeout: usize,
+    /// Rules that held vacuously
+    pub vacuous: usize,
+    /// Rules the prover gave no verdict on
+    pub prover_error: usize,
+}
+
+impl Failures {
+    /// Counts the failure `progress` reports, if any.
+    pub fn observe(&mut self, progress: &Progress) {
+        match progress {
+            Progress::Build(Verdict::Failed(_)) => self.build += 1,
+            Progress::Rule(result) => match result.status {
+                RuleStatus::Verified => {}
+                RuleStatus::Violated => self.counterexample += 1,
+                RuleStatus::Timeout => self.timeout += 1,
+                RuleStatus::SanityFailed => self.vacuous += 1,
+                RuleStatus::Error => self.prover_error += 1,
+            },
+            _ => {}
+        }
+    }
+}
+
+/// The metrics of one run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunMetrics {
+    /// Seconds since the Unix epoch when the run started
+    pub started: u64,
+    /// The name of the spec
+    pub spec: String,
+    /// The format of the spec file, e.g. `yaml` or `md`
+    pub format: String,
+    pub target: String,
+    pub provider: String,
+    pub model: String,
+    /// The directory of prompt overrides, or `builtin`
+    pub prompts: String,
+    pub success: bool,
+    pub iterations: usize,
+    pub failures: Failures,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub prover_jobs: usize,
+    pub wall_time_secs: f64,
+    /// The budget that ran out, e.g. `token`, unless the run succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exhausted: Option<String>,
+}
+
+/// What a run was started with, recorded with its metrics.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunContext {
+    pub started: u64,
+    pub spec: String,
+    pub format: String,
+    pub target: String,
+    pub provider: String,
+    pub model: String,
+    pub prompts: String,
+}
+
+impl RunMetrics {
+    /// The metrics of the run started in `context` that ended with `report`
+    /// after `wall_time`.
+    pub fn new(
+        context: RunContext,
+        report: &RunReport,
+        failures: Failures,
+        wall_time: Duration,
+    ) -> RunMetrics {
+        RunMetrics {
+            started: context.started,
+            spec: context.spec,
+            format: context.format,
+            target: context.target,
+            provider: context.provider,
+            model: context.model,
+            prompts: context.prompts,
+            success: report.success,
+            iterations: report.iterations,
+            failures,
+            input_tokens: report.usage.input_tokens,
+            output_tokens: report.usage.output_tokens,
+            prover_jobs: report.prover_jobs,
+            wall_time_secs: wall_time.as_secs_f64(),
+            exhausted: report.exhausted.map(|limit| limit.to_string()),
+        }
+    }
+
+    /// The value of the key runs are grouped by.
+    fn key(&self, by: GroupBy) -> &str {
+        match by {
+            GroupBy::Model => &self.model,
+            GroupBy::Prompts => &self.prompts,
+            GroupBy::Spec => &self.spec,
+            GroupBy::Format => &self.format,
+            GroupBy::Target => &self.target,
+        }
+    }
+}
+
+fn io(path: &Path) -> impl FnOnce(std::io::Error) -> MetricsError + '_ {
+    move |source| MetricsError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Appends `metrics` to the store at `path`.
+pub fn record(path: &Path, metrics: &RunMetrics) -> Result<(), MetricsError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(io(parent))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(io(path))?;
+    let line = serde_json::to_string(metrics).expect("metrics serialize");
+    writeln!(file, "{line}").map_err(io(path))
+}
+
+/// The runs in the store at `path`, oldest first; none if there is no store.
+pub fn load(path: &Path) -> Result<Vec<RunMetrics>, MetricsError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(io(path)(err)),
+    };
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|source| MetricsError::Json {
+                path: path.to_path_buf(),
+                line: i + 1,
+                source,
+            })
+        })
+        .collect()
+}
+
+/// What runs are grouped by in [`Stats`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GroupBy {
+    Model,
+    Prompts,
+    Spec,
+    Format,
+    Target,
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<GroupBy, String> {
+        match s {
+            "model" => Ok(GroupBy::Model),
+            "prompts" => Ok(GroupBy::Prompts),
+            "spec" => Ok(GroupBy::Spec),
+            "format" => Ok(GroupBy::Format),
+            "target" => Ok(GroupBy::Target),
+            _ => Err(format!(
+                "unknown grouping `{s}`; expected model, prompts, spec, format or target"
+            )),
+        }
+    }
+}
+
+/// The aggregate of a group of runs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Group {
+    pub runs: usize,
+    pub successes: usize,
+    /// The mean iterations of the runs that passed
+    pub iterations_to_green: Option<f64>,
+    /// The failures of all runs together
+    pub failures: Failures,
+    /// The mean tokens of a run, input and output together
+    pub tokens: f64,
+    /// The mean wall time of a run
+    pub wall_time_secs: f64,
+}
+
+/// Runs aggregated by a key, e.g. by model.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stats {
+    pub by: GroupBy,
+    pub groups: BTreeMap<String, Group>,
+}
+
+impl Stats {
+    pub fn new(runs: &[RunMetrics], by: GroupBy) -> Stats {
+        let mut grouped: BTreeMap<String, Vec<&RunMetrics>> = BTreeMap::new();
+        for run in runs {
+            grouped
+                .entry(run.key(by).to_string())
+                .or_default()
+                .push(run);
+        }
+        let groups = grouped
+            .into_iter()
+            .map(|(key, runs)| {
+                let n = runs.len() as f64;
+                let green: Vec<_> = runs.iter().filter(|run| run.success).collect();
+                let mut failures = Failures::default();
+                for run in &runs {
+                    failures.build += run.failures.build;
+                    failures.counterexample += run.failures.counterexample;
+                    failures.timeout += run.failures.timeout;
+                    failures.vacuous += run.failures.vacuous;
+                    failures.prover_error += run.failures.prover_error;
+                }
+                let group = Group {
+                    runs: runs.len(),
+                    successes: green.len(),
+                    iterations_to_green: (!green.is_empty()).then(|| {
+                        green.iter().map(|run| run.iterations as f64).sum::<f64>()
+                            / green.len() as f64
+                    }),
+                    failures,
+                    tokens: runs
+                        .iter()
+                        .map(|run| (run.input_tokens + run.output_tokens) as f64)
+                        .sum::<f64>()
+                        / n,
+                    wall_time_secs: runs.iter().map(|run| run.wall_time_secs).sum::<f64>() / n,
+                };
+                (key, group)
+            })
+            .collect();
+        Stats { by, groups }
+    }
+}
+
+impl fmt::Display for Stats {
+    /// A table with a row per group.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .groups
+            .keys()
+            .map(String::len)
+            .chain([5])
+            .max()
+            .unwrap_or(5);
+        writeln!(
+            f,
+            "{:width$}  {:>4}  {:>6}  {:>8}  {:>5}  {:>5}  {:>7}  {:>7}  {:>5}  {:>9}  {:>8}",
+            "group",
+            "runs",
+            "passed",
+            "iters",
+            "build",
+            "cex",
+            "timeout",
+            "vacuous",
+            "error",
+            "tokens",
+            "time",
+        )?;
+        for (key, group) in &self.groups {
+            let iterations = group
+                .iterations_to_green
+                .map_or("-".to_string(), |iterations| format!("{iterations:.1}"));
+            writeln!(
+                f,
+                "{key:width$}  {:>4}  {:>6}  {iterations:>8}  {:>5}  {:>5}  {:>7}  {:>7}  {:>5}  {:>9.0}  {:>7.1}s",
+                group.runs,
+                group.successes,
+                group.failures.build,
+                group.failures.counterexample,
+                group.failures.timeout,
+                group.failures.vacuous,
+                group.failures.prover_error,
+                group.tokens,
+                group.wall_time_secs,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::Limit;
+    use crate::llm::Usage;
+    use crate::prover::RuleResult;
+
+    fn run(model: &str, report: &RunReport, failures: Failures, secs: u64) -> RunMetrics {
+        let context = RunContext {
+            started: 1_700_000_000,
+            spec: "vault".to_string(),
+            format: "yaml".to_string(),
+            target: "svm".to_string(),
+            provider: "anthropic".to_string(),
+            model: model.to_string(),
+            prompts: "builtin".to_string(),
+        };
+        RunMetrics::new(context, report, failures, Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn test_failures() {
+        let mut failures = Failures::default();
+        let rule = |status| {
+            Progress::Rule(RuleResult {
+                rule: "rule_a".to_string(),
+                status,
+                output: String::new(),
+                counterexample: None,
+                cached: false,
+            })
+        };
+        for progress in [
+            Progress::Iteration(1),
+            Progress::Build(Verdict::Failed("error[E0308]".to_string())),
+            Progress::Build(Verdict::Passed),
+            rule(RuleStatus::Violated),
+            rule(RuleStatus::Violated),
+            rule(RuleStatus::Timeout),
+            rule(RuleStatus::Verified),
+            rule(RuleStatus::Error),
+        ] {
+            failures.observe(&progress);
+        }
+        assert_eq!(
+            failures,
+            Failures {
+                build: 1,
+                counterexample: 2,
+                timeout: 1,
+                vacuous: 0,
+                prover_error: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_record_and_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(METRICS_JSONL);
+        assert_eq!(load(&path).unwrap(), []);
+
+        let passed = RunReport {
+            success: true,
+            iterations: 3,
+            usage: Usage {
+                input_tokens: 900,
+                output_tokens: 100,
+            },
+            prover_jobs: 4,
+            rules: BTreeMap::new(),
+            unproved: Vec::new(),
+            feedback: None,
+            exhausted: None,
+            skipped: Vec::new(),
+            aborted: false,
+        };
+        let failed = RunReport {
+            success: false,
+            iterations: 8,
+            exhausted: Some(Limit::Iterations),
+            skipped: Vec::new(),
+            aborted: false,
+            ..passed.clone()
+        };
+        let failures = Failures {
+            build: 2,
+            counterexample: 1,
+            ..Failures::default()
+        };
+        let runs = [
+            run("large", &passed, failures, 60),
+            run("large", &failed, failures, 120),
+            run("small", &passed, Failures::default(), 30),
+        ];
+        for metrics in &runs {
+            record(&path, metrics).unwrap();
+        }
+        let loaded = load(&path).unwrap();
+        assert_eq!(loaded, runs);
+        assert_eq!(loaded[1].exhausted.as_deref(), Some("iteration"));
+
+        let stats = Stats::new(&loaded, GroupBy::Model);
+        let large = &stats.groups["large"];
+        assert_eq!(large.runs, 2);
+        assert_eq!(large.successes, 1);
+        assert_eq!(large.iterations_to_green, Some(3.0));
+        assert_eq!(large.failures.build, 4);
+        assert_eq!(large.tokens, 1000.0);
+        assert_eq!(large.wall_time_secs, 90.0);
+        let table = stats.to_string();
+        assert!(table.starts_with("group  runs  passed"));
+        assert!(table.contains(
+            "large     2       1       3.0      4      2        0        0      0       1000     90.0s\n"
+        ));
+        assert_eq!(Stats::new(&loaded, GroupBy::Spec).groups.len(), 1);
+        assert!("style".parse::<GroupBy>().is_err());
+    }
+}
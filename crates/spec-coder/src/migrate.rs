@@ -0,0 +1,184 @@
+//! Migration of materialized crates to new versions of their specs.
+//!
+//! A spec may declare a semantic `version`. Whenever a crate is materialized
+//! or regenerated, the version of its spec is stamped in
+//! [`MATERIALIZED_JSON`], along with its `source` if it names one, and the
+//! registry lists them with the example. A
+//! migration takes a crate from the version it was materialized from to the
+//! version of the spec: the deltas between the two are the clauses that
+//! changed, so only the functions they touch are scaffolded again and only
+//! their rules are proved again, as [`incremental`] plans it. A spec is never
+//! migrated back to an older version; migrating to the version already
+//! stamped continues a migration that was interrupted.
+
+use crate::incremental::{self, IncrementalError, Plan};
+use serde::{Deserialize, Serialize};
+use spec::{Spec, Target, Version};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The version stamp of a crate, relative to its root.
+pub const MATERIALIZED_JSON: &str = ".spec-coder/materialized.json";
+
+/// An error stamping or migrating a crate.
+#[derive(Debug, thiserror::Error)]
+pub enum MigrateError {
+    #[error("failed to access {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("malformed {}: {source}", .path.display())]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("{} is not a materialized crate", .0.display())]
+    NotMaterialized(PathBuf),
+    #[error("the spec of `{0}` has no version to migrate to")]
+    Unversioned(String),
+    #[error("the crate is materialized from version {from}, newer than the spec's {to}")]
+    Downgrade { from: Version, to: Version },
+    #[error(transparent)]
+    Incremental(#[from] IncrementalError),
+}
+
+/// What a crate was materialized from.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spec_version: Option<Version>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+}
+
+/// Stamps the crate in `dir` as materialized from `spec`.
+pub fn record(spec: &Spec, dir: &Path) -> Result<(), MigrateError> {
+    let path = dir.join(MATERIALIZED_JSON);
+    let io = |source| MigrateError::Io {
+        path: path.clone(),
+        source,
+    };
+    std::fs::create_dir_all(path.parent().expect("has a parent")).map_err(io)?;
+    let stamp = Stamp {
+        spec_version: spec.version,
+        source: spec.source.clone(),
+    };
+    let json = serde_json::to_string_pretty(&stamp).expect("stamps serialize");
+    std::fs::write(&path, json + "\n").map_err(io)
+}
+
+/// The stamp of the crate in `dir`, empty if it has none.
+fn stamp(dir: &Path) -> Result<Stamp, MigrateError> {
+    let path = dir.join(MATERIALIZED_JSON);
+    match std::fs::read_to_string(&path) {
+        Ok(json) => {
+            serde_json::from_str(&json).map_err(|source| MigrateError::Json { path, source })
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Stamp::default()),
+        Err(source) => Err(MigrateError::Io { path, source }),
+    }
+}
+
+/// The version of the spec the crate in `dir` was materialized from, if it
+/// is stamped with one.
+pub fn recorded(dir: &Path) -> Result<Option<Version>, MigrateError> {
+    Ok(stamp(dir)?.spec_version)
+}
+
+/// The source of the spec the crate in `dir` was materialized from, e.g.
+/// the issue it was drafted from, if it is stamped with one.
+pub fn recorded_source(dir: &Path) -> Result<Option<String>, MigrateError> {
+    Ok(stamp(dir)?.source)
+}
+
+/// A crate brought to a new version of its spec.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Migration {
+    /// The version the crate was materialized from, if it was stamped
+    pub from: Option<Version>,
+    pub to: Version,
+    /// What changed, and what has to be implemented and proved again
+    pub plan: Plan,
+}
+
+impl fmt::Display for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.from {
+            Some(from) => write!(f, "{from} to {}", self.to),
+            None => write!(f, "an unversioned spec to {}", self.to),
+        }
+    }
+}
+
+/// Migrates the crate in `dir` to the version of `spec`, regenerating what
+/// changed and stamping it with the new version.
+pub fn migrate(spec: &Spec, target: Target, dir: &Path) -> Result<Migration, MigrateError> {
+    if !dir.join("Cargo.toml").is_file() {
+        return Err(MigrateError::NotMaterialized(dir.to_path_buf()));
+    }
+    let to = spec
+        .version
+        .ok_or_else(|| MigrateError::Unversioned(spec.name.clone()))?;
+    let from = recorded(dir)?;
+    if let Some(from) = from.filter(|from| *from > to) {
+        return Err(MigrateError::Downgrade { from, to });
+    }
+    let plan = incremental::regenerate(spec, target, dir)?;
+    record(spec, dir)?;
+    Ok(Migration { from, to, plan })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{drift, materialize};
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_migrate() {
+        let mut spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            migrate(&spec, Target::Svm, dir.path()),
+            Err(MigrateError::NotMaterialized(_))
+        ));
+        let files = materialize::generate(&spec, Target::Svm).unwrap();
+        materialize::write(&files, dir.path(), true).unwrap();
+        assert_eq!(recorded(dir.path()).unwrap(), None);
+        assert!(matches!(
+            migrate(&spec, Target::Svm, dir.path()),
+            Err(MigrateError::Unversioned(_))
+        ));
+
+        spec.version = Some(Version::new(1, 0, 0));
+        record(&spec, dir.path()).unwrap();
+        assert_eq!(recorded(dir.path()).unwrap(), spec.version);
+        drift::record(&spec, dir.path()).unwrap();
+
+        // Only withdraw changed in 1.1.0
+        let mut next = spec.clone();
+        next.version = Some(Version::new(1, 1, 0));
+        next.functions[1].ensures.pop();
+        let migration = migrate(&next, Target::Svm, dir.path()).unwrap();
+        assert_eq!(migration.to_string(), "1.0.0 to 1.1.0");
+        assert_eq!(
+            migration.plan.regenerate.into_iter().collect::<Vec<_>>(),
+            ["withdraw"]
+        );
+        assert!(migration
+            .plan
+            .rules
+            .iter()
+            .all(|rule| rule.starts_with("rule_withdraw_")));
+        assert_eq!(recorded(dir.path()).unwrap(), next.version);
+
+        // Again, as after an interruption, but never back
+        assert!(migrate(&next, Target::Svm, dir.path()).is_ok());
+        assert!(matches!(
+            migrate(&spec, Target::Svm, dir.path()),
+            Err(MigrateError::Downgrade { .. })
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+//! Mutants of an implementation.
+//!
+//! Passing every test and rule only means something if they would fail on a
+//! wrong implementation. A mutant is `src/processor.rs` with one operator
+//! swapped, e.g. a `+` for a `-` or a `<` for a `<=`; the driver builds,
+//! tests and proves each one, and the mutants nothing fails on survive. The
+//! share of mutants killed is the mutation score, which a run must reach
+//! before it succeeds.
+
+use std::fmt;
+
+/// Operators and the ones they are swapped for, surrounded by spaces so
+/// that generics, paths and arrows are left alone.
+const SWAPS: &[(&str, &str)] = &[
+    (" + ", " - "),
+    (" - ", " + "),
+    (" * ", " / "),
+    (" / ", " * "),
+    (" % ", " * "),
+    (" += ", " -= "),
+    (" -= ", " += "),
+    (" < ", " <= "),
+    (" <= ", " < "),
+    (" > ", " >= "),
+    (" >= ", " > "),
+    (" == ", " != "),
+    (" != ", " == "),
+    (" && ", " || "),
+    (" || ", " && "),
+    (".checked_add(", ".checked_sub("),
+    (".checked_sub(", ".checked_add("),
+    (".checked_mul(", ".checked_div("),
+    (".checked_div(", ".checked_mul("),
+    (".saturating_add(", ".saturating_sub("),
+    (".saturating_sub(", ".saturating_add("),
+];
+
+/// At most this many mutants are checked, spread over the source, since
+/// each is built and proved.
+pub const MAX_MUTANTS: usize = 24;
+
+/// A source with one operator swapped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mutant {
+    /// The line of the swap, from 1
+    pub line: usize,
+    pub original: String,
+    pub replacement: String,
+    /// The whole mutated source
+    pub source: String,
+}
+
+impl fmt::Display for Mutant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: `{}` replaced by `{}`",
+            self.line,
+            self.original.trim(),
+            self.replacement.trim()
+        )
+    }
+}
+
+/// The part of `line` that is code: up to a line comment, with the
+/// contents of string literals blanked out.
+fn code(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_string = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                let escaped = chars.next().map_or(0, char::len_utf8);
+                out.push_str(&" ".repeat(1 + escaped));
+            }
+            '"' => {
+                in_string = !in_string;
+                out.push('"');
+            }
+            '/' if !in_string && chars.peek() == Some(&'/') => break,
+            c if in_string => out.push_str(&" ".repeat(c.len_utf8())),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Every mutant of `source`, in source order. Attributes, comments, string
+/// literals and the tests are not mutated.
+pub fn mutants(source: &str) -> Vec<Mutant> {
+    let mut mutants = Vec::new();
+    let mut offset = 0;
+    // Brackets left open by an attribute, which may span lines
+    let mut attribute = 0usize;
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        let start = offset;
+        offset += line.len();
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("#[cfg(test)]") {
+            break;
+        }
+        let code = code(line);
+        if attribute > 0 || trimmed.starts_with('#') {
+            attribute =
+                (attribute + code.matches('[').count()).saturating_sub(code.matches(']').count());
+            continue;
+        }
+        for (original, replacement) in SWAPS {
+            for (at, _) in code.match_indices(original) {
+                let at = start + at;
+                mutants.push(Mutant {
+                    line: index + 1,
+                    original: original.to_string(),
+                    replacement: replacement.to_string(),
+                    source: format!(
+                        "{}{replacement}{}",
+                        &source[..at],
+                        &source[at + original.len()..]
+                    ),
+                });
+            }
+        }
+    }
+    mutants
+}
+
+/// At most `max` of `mutants`, evenly spread over them.
+pub fn sample(mutants: Vec<Mutant>, max: usize) -> Vec<Mutant> {
+    if mutants.len() <= max {
+        return mutants;
+    }
+    let step = mutants.len().div_ceil(max);
+    mutants.into_iter().step_by(step).collect()
+}
+
+/// The percentage of `total` mutants that were `killed`; a source without
+/// mutants scores 100.
+pub fn score(killed: usize, total: usize) -> u8 {
+    if total == 0 {
+        return 100;
+    }
+    (killed * 100 / total) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mutants() {
+        let source = "\
+use std::collections::Vec<u8>;
+
+#[cfg_attr(
+    feature = \"prusti\",
+    prusti_contracts::ensures(result.is_ok() ==> (a > 0))
+)]
+fn f(a: u64, b: u64) -> Result<u64, E> {
+    // a + b
+    if a < b && b != 0 {
+        msg!(\"a + b\");
+    }
+    a.checked_add(b).ok_or(E) // b - a
+}
+
+#[cfg(test)]
+mod tests {
+    fn t() { assert!(1 + 1 == 2); }
+}
+";
+        let mutants = mutants(source);
+        let found: Vec<String> = mutants.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            found,
+            [
+                "line 9: `<` replaced by `<=`",
+                "line 9: `!=` replaced by `==`",
+                "line 9: `&&` replaced by `||`",
+                "line 12: `.checked_add(` replaced by `.checked_sub(`",
+            ]
+        );
+        assert!(mutants[0].source.contains("    if a <= b && b != 0 {\n"));
+        assert!(mutants[3]
+            .source
+            .contains("    a.checked_sub(b).ok_or(E) // b - a\n"));
+        assert_eq!(mutants[0].source.len(), source.len() + 1);
+
+        assert_eq!(sample(mutants.clone(), 2).len(), 2);
+        assert_eq!(sample(mutants, 8).len(), 4);
+        assert_eq!(score(3, 4), 75);
+        assert_eq!(score(0, 0), 100);
+    }
+}
@@ -0,0 +1,303 @@
+//! Unified diffs from the model.
+//!
+//! Sending only what changed instead of whole files saves tokens on every
+//! response, and, unlike a whole file, a diff cannot silently undo changes
+//! made to the file since the model last saw it. A diff is applied hunk by
+//! hunk, each where its context and removed lines are found in the file,
+//! near the line the hunk header names. Models are sloppy with diffs, so the
+//! line counts of headers are ignored and the lines are matched exactly
+//! first, then ignoring trailing and then all whitespace, and at last
+//! without up to [`MAX_FUZZ`] lines of context at either end, as `patch`
+//! does. A hunk that still matches nowhere is a conflict, and the file is
+//! left as it is.
+
+use std::fmt;
+
+/// How many lines of context at either end of a hunk may be left out to
+/// place it.
+pub const MAX_FUZZ: usize = 2;
+
+/// Why a diff does not apply.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum PatchError {
+    #[error("the diff has no hunks")]
+    Empty,
+    #[error("line {line} of the diff is not part of a hunk: `{text}`")]
+    Malformed { line: usize, text: String },
+    #[error("hunk {hunk} (`{header}`) does not match the file")]
+    Conflict { hunk: usize, header: String },
+}
+
+/// A line of a hunk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Line {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+impl Line {
+    /// The line as it is before the change, if it is there then.
+    fn old(&self) -> Option<&str> {
+        match self {
+            Line::Context(text) | Line::Removed(text) => Some(text),
+            Line::Added(_) => None,
+        }
+    }
+}
+
+/// A hunk of a diff.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Hunk {
+    header: String,
+    /// The line the hunk starts on before the change, counting from 1
+    old_start: usize,
+    lines: Vec<Line>,
+}
+
+impl fmt::Display for Hunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.header)
+    }
+}
+
+/// Whether the contents of a file block are a unified diff rather than the
+/// file: they start with a hunk or with the `---` and `+++` headers.
+pub fn is_patch(contents: &str) -> bool {
+    let mut lines = contents.lines().skip_while(|line| line.trim().is_empty());
+    match lines.next() {
+        Some(line) if line.starts_with("@@ ") => true,
+        Some(line) if line.starts_with("--- ") => {
+            lines.next().is_some_and(|line| line.starts_with("+++ "))
+        }
+        _ => false,
+    }
+}
+
+fn parse(patch: &str) -> Result<Vec<Hunk>, PatchError> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for (i, text) in patch.lines().enumerate() {
+        if let Some(range) = text.strip_prefix("@@ -") {
+            let start = range
+                .split([',', ' '])
+                .next()
+                .and_then(|start| start.parse().ok())
+                .ok_or_else(|| PatchError::Malformed {
+                    line: i + 1,
+                    text: text.to_string(),
+                })?;
+            hunks.push(Hunk {
+                header: text.to_string(),
+                old_start: start,
+                lines: Vec::new(),
+            });
+            continue;
+        }
+        let Some(hunk) = hunks.last_mut() else {
+            // The file headers, and any `diff` or `index` line before them
+            if ["--- ", "+++ ", "diff ", "index "]
+                .iter()
+                .any(|prefix| text.starts_with(prefix))
+                || text.trim().is_empty()
+            {
+                continue;
+            }
+            return Err(PatchError::Malformed {
+                line: i + 1,
+                text: text.to_string(),
+            });
+        };
+        let line = match text.chars().next() {
+            Some('+') => Line::Added(text[1..].to_string()),
+            Some('-') => Line::Removed(text[1..].to_string()),
+            Some(' ') => Line::Context(text[1..].to_string()),
+            Some('\\') => continue,
+            // An empty context line that lost its space
+            None => Line::Context(String::new()),
+            Some(_) => {
+                return Err(PatchError::Malformed {
+                    line: i + 1,
+                    text: text.to_string(),
+                })
+            }
+        };
+        hunk.lines.push(line);
+    }
+    if hunks.is_empty() {
+        return Err(PatchError::Empty);
+    }
+    Ok(hunks)
+}
+
+/// How strictly lines are compared, from the strictest.
+const MATCHES: [fn(&str, &str) -> bool; 3] = [
+    |a, b| a == b,
+    |a, b| a.trim_end() == b.trim_end(),
+    |a, b| a.split_whitespace().eq(b.split_whitespace()),
+];
+
+/// Where `old` is in `lines`, at or after `from`, searching outward from
+/// `near`.
+fn find(lines: &[&str], old: &[&str], from: usize, near: usize) -> Option<usize> {
+    if lines.len() < old.len() + from {
+        return None;
+    }
+    let last = lines.len() - old.len();
+    let near = near.clamp(from, last);
+    for matches in MATCHES {
+        let at = |start: usize| {
+            lines[start..start + old.len()]
+                .iter()
+                .zip(old)
+                .all(|(line, old)| matches(line, old))
+        };
+        for distance in 0..=last - from {
+            let candidates = [near.checked_sub(distance), Some(near + distance)];
+            for start in candidates.into_iter().flatten() {
+                if start >= from && start <= last && at(start) {
+                    return Some(start);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Applies the unified diff `patch` to `original`.
+pub fn apply(original: &str, patch: &str) -> Result<String, PatchError> {
+    let hunks = parse(patch)?;
+    let lines: Vec<&str> = original.lines().collect();
+    let mut patched: Vec<String> = Vec::new();
+    // The lines before `next` are in `patched` already
+    let mut next = 0;
+    // How far the hunks so far were from their headers
+    let mut offset: isize = 0;
+    for (i, hunk) in hunks.iter().enumerate() {
+        let leading = hunk
+            .lines
+            .iter()
+            .take_while(|line| matches!(line, Line::Context(_)))
+            .count();
+        let trailing = hunk.lines[leading..]
+            .iter()
+            .rev()
+            .take_while(|line| matches!(line, Line::Context(_)))
+            .count();
+        let placed = (0..=MAX_FUZZ).find_map(|fuzz| {
+            let (skip, keep) = (leading.min(fuzz), hunk.lines.len() - trailing.min(fuzz));
+            if fuzz > 0 && skip + (hunk.lines.len() - keep) == 0 {
+                return None;
+            }
+            let body = &hunk.lines[skip..keep];
+            let old: Vec<&str> = body.iter().filter_map(Line::old).collect();
+            let near = (hunk.old_start.saturating_sub(1) + skip) as isize + offset;
+            let start = find(&lines, &old, next, near.max(0) as usize)?;
+            Some((start, body, old.len(), near))
+        });
+        let Some((start, body, len, near)) = placed else {
+            return Err(PatchError::Conflict {
+                hunk: i + 1,
+                header: hunk.to_string(),
+            });
+        };
+        offset += start as isize - near;
+        patched.extend(lines[next..start].iter().map(|line| line.to_string()));
+        let mut current = start;
+        for line in body {
+            match line {
+                // The file keeps its own version of the context
+                Line::Context(_) => {
+                    patched.push(lines[current].to_string());
+                    current += 1;
+                }
+                Line::Removed(_) => current += 1,
+                Line::Added(text) => patched.push(text.clone()),
+            }
+        }
+        next = start + len;
+    }
+    patched.extend(lines[next..].iter().map(|line| line.to_string()));
+    let mut contents = patched.join("\n");
+    if !patched.is_empty() {
+        contents.push('\n');
+    }
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str = "fn deposit() {\n    // TODO\n}\n\nfn withdraw() {\n    \
+                            // TODO\n}\n\nfn reward() {\n    // TODO\n}\n";
+
+    #[test]
+    fn test_is_patch() {
+        assert!(is_patch("@@ -1 +1 @@\n-a\n+b\n"));
+        assert!(is_patch(
+            "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n"
+        ));
+        assert!(!is_patch("---\ntitle: readme\n---\n"));
+        assert!(!is_patch("fn main() {}\n"));
+    }
+
+    #[test]
+    fn test_apply() {
+        // The second hunk is two lines off and its header miscounted, and
+        // the third has a context line with other indentation
+        let patch = "--- a/src/processor.rs\n+++ b/src/processor.rs\n\
+                     @@ -1,3 +1,3 @@\n fn deposit() {\n-    // TODO\n+    vault.total += amount;\n }\n\
+                     @@ -3,3 +3,4 @@\n fn withdraw() {\n-    // TODO\n+    check()?;\n+    pay();\n }\n\
+                     @@ -9,3 +10,3 @@\n fn reward() {\n-  // TODO\n+    mint();\n";
+        assert_eq!(
+            apply(ORIGINAL, patch).unwrap(),
+            "fn deposit() {\n    vault.total += amount;\n}\n\nfn withdraw() {\n    check()?;\n    \
+             pay();\n}\n\nfn reward() {\n    mint();\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_fuzz() {
+        // The context around the change was edited since the model saw it
+        let original = ORIGINAL.replace("fn withdraw() {", "pub fn withdraw() {");
+        let patch = "@@ -4,4 +4,4 @@\n \n fn withdraw() {\n-    // TODO\n+    pay();\n }\n";
+        assert_eq!(
+            apply(&original, patch).unwrap(),
+            original.replacen(
+                "fn withdraw() {\n    // TODO",
+                "fn withdraw() {\n    pay();",
+                1
+            )
+        );
+
+        // The line to remove was edited
+        let patch =
+            "@@ -1 +1 @@\n fn deposit() {\n@@ -6 +6 @@\n-    // TODO: withdraw\n+    pay();\n";
+        assert_eq!(
+            apply(ORIGINAL, patch),
+            Err(PatchError::Conflict {
+                hunk: 2,
+                header: "@@ -6 +6 @@".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_new_file_and_malformed() {
+        assert_eq!(
+            apply(
+                "",
+                "--- /dev/null\n+++ b/src/math.rs\n@@ -0,0 +1,2 @@\n+mod a;\n+mod b;\n"
+            ),
+            Ok("mod a;\nmod b;\n".to_string())
+        );
+        assert_eq!(
+            apply(ORIGINAL, "@@ -1 +1 @@\n-fn deposit() {\nfn\n"),
+            Err(PatchError::Malformed {
+                line: 3,
+                text: "fn".to_string(),
+            })
+        );
+        assert_eq!(apply(ORIGINAL, "--- a\n+++ b\n"), Err(PatchError::Empty));
+    }
+}
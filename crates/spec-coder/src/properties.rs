@@ -0,0 +1,297 @@
+//! Candidate invariants from requirements in prose.
+//!
+//! Requirements are often written as sentences before any spec exists, e.g.
+//! "the vault must always be able to honor all shares". `extract` asks a
+//! model to formalize each of them as an invariant over the state of a spec
+//! and checks what it proposes: a candidate whose expression does not parse
+//! or does not validate against the spec is rejected with the reason.
+//! Nothing is added to the spec unconfirmed; `confirm` shows every proposal
+//! to a [`Reviewer`] as the change it makes to the spec, and only those it
+//! approves are added, where the rule compiler picks them up like any other
+//! invariant.
+
+use crate::llm::{ChatRequest, LlmError, Message, Provider, Usage};
+use crate::review::{unified_diff, Reviewer};
+use crate::roles::parse_artifact;
+use crate::templates::{TemplateError, Templates};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use spec::{validate, Expr, Invariant, Spec, SpecError};
+use std::path::Path;
+
+/// An error extracting invariants.
+#[derive(Debug, thiserror::Error)]
+pub enum PropertiesError {
+    #[error(transparent)]
+    Llm(#[from] LlmError),
+    #[error(transparent)]
+    Template(#[from] TemplateError),
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+}
+
+/// An invariant as the model proposes it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Candidate {
+    /// The requirement it formalizes, as written
+    pub requirement: String,
+    pub name: String,
+    pub expr: String,
+}
+
+/// The answer of the model.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Candidates {
+    pub invariants: Vec<Candidate>,
+}
+
+/// A candidate that does not fit the spec, and why.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rejection {
+    pub candidate: Candidate,
+    pub reason: String,
+}
+
+/// What the model proposed for a list of requirements.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Extraction {
+    /// The invariants that fit the spec, described by their requirement
+    pub proposals: Vec<Invariant>,
+    pub rejections: Vec<Rejection>,
+    pub usage: Usage,
+}
+
+/// Splits prose into requirements: the sentences of its paragraphs and list
+/// items, without list markers.
+pub fn requirements(text: &str) -> Vec<String> {
+    let mut units: Vec<String> = Vec::new();
+    let mut open = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            open = false;
+            continue;
+        }
+        let item = ["- ", "* "]
+            .iter()
+            .find_map(|marker| line.strip_prefix(marker))
+            .or_else(|| {
+                let (number, rest) = line.split_once(". ")?;
+                number.chars().all(|c| c.is_ascii_digit()).then_some(rest)
+            });
+        match (item, open) {
+            (None, true) => {
+                let unit = units.last_mut().expect("an open paragraph");
+                unit.push(' ');
+                unit.push_str(line);
+            }
+            (item, _) => units.push(item.unwrap_or(line).trim().to_string()),
+        }
+        open = true;
+    }
+    let mut sentences = Vec::new();
+    for unit in units {
+        let mut start = 0;
+        for (i, c) in unit.char_indices() {
+            let end = i + c.len_utf8();
+            let boundary = unit[end..].is_empty() || unit[end..].starts_with(' ');
+            if matches!(c, '.' | '!' | '?') && boundary {
+                sentences.push(unit[start..end].trim().to_string());
+                start = end;
+            }
+        }
+        if !unit[start..].trim().is_empty() {
+            sentences.push(unit[start..].trim().to_string());
+        }
+    }
+    sentences.retain(|sentence| !sentence.is_empty());
+    sentences
+}
+
+/// The invariant `candidate` stands for, if it parses and validates as an
+/// invariant of `spec`.
+pub fn check(spec: &Spec, candidate: &Candidate) -> Result<Invariant, String> {
+    let expr: Expr = candidate
+        .expr
+        .parse()
+        .map_err(|err| format!("`{}` does not parse: {err}", candidate.expr))?;
+    let invariant = Invariant {
+        name: candidate.name.clone(),
+        description: Some(candidate.requirement.clone()),
+        expr,
+    };
+    let mut extended = spec.clone();
+    extended.invariants.push(invariant.clone());
+    match validate(&extended) {
+        Ok(()) => Ok(invariant),
+        Err(errors) => Err(errors.to_string()),
+    }
+}
+
+/// Asks the model for invariants of `spec` formalizing `requirements` and
+/// checks them against it. Later candidates are checked with the earlier
+/// proposals in the spec, so two never share a name.
+pub fn extract(
+    provider: &dyn Provider,
+    templates: &Templates,
+    spec: &Spec,
+    requirements: &[String],
+) -> Result<Extraction, PropertiesError> {
+    let spec_yaml = spec.to_yaml_string()?;
+    let request = ChatRequest {
+        system: Some(templates.render("properties", context! {})?),
+        messages: vec![Message::user(
+            templates.render("properties_request", context! { spec_yaml, requirements })?,
+        )],
+        ..ChatRequest::default()
+    };
+    let response = provider.chat(&request)?;
+    let candidates: Candidates = parse_artifact(&response.text()).unwrap_or_default();
+    let mut extraction = Extraction {
+        usage: response.usage,
+        ..Extraction::default()
+    };
+    let mut extended = spec.clone();
+    for candidate in candidates.invariants {
+        match check(&extended, &candidate) {
+            Ok(invariant) => {
+                extended.invariants.push(invariant.clone());
+                extraction.proposals.push(invariant);
+            }
+            Err(reason) => extraction.rejections.push(Rejection { candidate, reason }),
+        }
+    }
+    Ok(extraction)
+}
+
+/// Shows each proposal to `reviewer` as the change it makes to the spec at
+/// `path` and adds those it approves to `spec`, returning their names.
+pub fn confirm(
+    spec: &mut Spec,
+    path: &Path,
+    proposals: Vec<Invariant>,
+    reviewer: &dyn Reviewer,
+) -> Result<Vec<String>, PropertiesError> {
+    let mut confirmed = Vec::new();
+    for invariant in proposals {
+        let before = spec.to_yaml_string()?;
+        spec.invariants.push(invariant);
+        let diff = unified_diff(path, Some(&before), &spec.to_yaml_string()?);
+        if reviewer.review(path, &diff) {
+            confirmed.push(spec.invariants.last().expect("just added").name.clone());
+        } else {
+            spec.invariants.pop();
+        }
+    }
+    Ok(confirmed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{ChatResponse, ContentBlock, StopReason};
+    use crate::review::Prompt;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    /// Replies with the same text to every request.
+    struct Canned(&'static str);
+
+    impl Provider for Canned {
+        fn name(&self) -> &str {
+            "canned"
+        }
+
+        fn model(&self) -> &str {
+            "canned"
+        }
+
+        fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+            assert!(request.messages[0]
+                .text()
+                .contains("requirements:\n- The vault must always be able to honor all shares."));
+            Ok(ChatResponse {
+                content: vec![ContentBlock::Text {
+                    text: self.0.to_string(),
+                }],
+                stop_reason: StopReason::EndTurn,
+                usage: Usage {
+                    input_tokens: 10,
+                    output_tokens: 1,
+                },
+            })
+        }
+    }
+
+    #[test]
+    fn test_requirements() {
+        let text = "# Vault\n\nThe vault must always be able to honor all shares. Nobody\n\
+                    loses tokens on a deposit!\n\n- Shares never exceed\n  tokens\n\
+                    2. Rewards mint no shares\n";
+        assert_eq!(
+            requirements(text),
+            [
+                "The vault must always be able to honor all shares.",
+                "Nobody loses tokens on a deposit!",
+                "Shares never exceed tokens",
+                "Rewards mint no shares",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_and_confirm() {
+        let mut spec = Spec::from_yaml_str(VAULT).unwrap();
+        spec.invariants.clear();
+        let provider = Canned(
+            "```json\n{\"invariants\": [\
+             {\"requirement\": \"The vault must always be able to honor all shares.\", \
+              \"name\": \"honor_shares\", \"expr\": \"vault.shares_total <= vault.token_total\"}, \
+             {\"requirement\": \"Shares are bounded.\", \"name\": \"bounded\", \
+              \"expr\": \"vault.shares <= 10\"}, \
+             {\"requirement\": \"Tokens grow.\", \"name\": \"grows\", \
+              \"expr\": \"vault.token_total >=\"}, \
+             {\"requirement\": \"Again.\", \"name\": \"honor_shares\", \"expr\": \"true\"}]}\n```",
+        );
+        let requirements = vec!["The vault must always be able to honor all shares.".to_string()];
+        let extraction =
+            extract(&provider, &Templates::builtin("svm"), &spec, &requirements).unwrap();
+        assert_eq!(extraction.usage.input_tokens, 10);
+        assert_eq!(extraction.proposals.len(), 1);
+        assert_eq!(
+            extraction.proposals[0].expr.to_string(),
+            "vault.shares_total <= vault.token_total"
+        );
+        let reasons: Vec<_> = extraction
+            .rejections
+            .iter()
+            .map(|rejection| rejection.reason.as_str())
+            .collect();
+        assert_eq!(reasons.len(), 3);
+        assert_eq!(
+            reasons[0],
+            "invariants.bounded: `Vault` has no field `shares`"
+        );
+        assert!(reasons[1].starts_with("`vault.token_total >=` does not parse"));
+        assert_eq!(reasons[2], "invariants: duplicate invariant `honor_shares`");
+
+        // The first proposal is declined, the copy of it approved
+        let mut proposals = extraction.proposals.clone();
+        proposals.push(Invariant {
+            name: "solvency".to_string(),
+            ..extraction.proposals[0].clone()
+        });
+        let mut output = Vec::new();
+        let prompt = Prompt::new("n\ny\n".as_bytes(), &mut output);
+        let confirmed = confirm(&mut spec, Path::new("vault.yaml"), proposals, &prompt).unwrap();
+        assert_eq!(confirmed, ["solvency"]);
+        assert_eq!(spec.invariants.len(), 1);
+        assert_eq!(
+            spec.invariants[0].description.as_deref(),
+            Some("The vault must always be able to honor all shares.")
+        );
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("+- name: honor_shares\n"));
+        assert!(output.contains("Write vault.yaml? [y/n/a] "));
+    }
+}
@@ -0,0 +1,262 @@
+//! Provenance stamps of generated files.
+//!
+//! Every file spec-coder writes, whether compiled from the spec or written
+//! by a model, starts with a stamp in the comment syntax of the file:
+//!
+//! ```text
+//! // spec-coder: generated spec=3f2a9c0d1e4b5a67 generator=0.1.0 model=gpt-4o at=1760000000
+//! ```
+//!
+//! naming the fingerprint of the spec, the version of spec-coder, the model
+//! if one wrote the file, and when it was written, in seconds since the
+//! Unix epoch; `SOURCE_DATE_EPOCH` fixes the time for reproducible output.
+//! A configured license header goes above the stamp. Files without a stamp
+//! are hand-written, and [`crate::drift`] reads the stamps back to find
+//! generated files that an older spec produced.
+
+use crate::materialize::GeneratedFile;
+use spec::Spec;
+use std::fmt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What a stamp starts with, after the comment delimiter.
+const PREFIX: &str = "spec-coder: generated ";
+
+/// Where a generated file came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    /// The fingerprint of the spec, see [`fingerprint`]
+    pub spec: String,
+    /// The version of spec-coder
+    pub generator: String,
+    /// The model that wrote the file, if it was not compiled from the spec
+    pub model: Option<String>,
+    /// Seconds since the Unix epoch
+    pub at: u64,
+}
+
+impl Provenance {
+    /// The provenance of a file generated now for the spec with the
+    /// fingerprint `spec`.
+    pub fn new(spec: impl Into<String>, model: Option<&str>) -> Provenance {
+        let at = std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|epoch| epoch.parse().ok())
+            .unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs())
+            });
+        Provenance {
+            spec: spec.into(),
+            generator: env!("CARGO_PKG_VERSION").to_string(),
+            model: model.map(str::to_string),
+            at,
+        }
+    }
+
+    /// Reads the stamp of `source`, if it has one.
+    pub fn parse(source: &str) -> Option<Provenance> {
+        let fields = source.lines().find_map(stamp_fields)?;
+        let mut provenance = Provenance {
+            spec: String::new(),
+            generator: String::new(),
+            model: None,
+            at: 0,
+        };
+        for field in fields.split_whitespace() {
+            match field.split_once('=')? {
+                ("spec", spec) => provenance.spec = spec.to_string(),
+                ("generator", generator) => provenance.generator = generator.to_string(),
+                ("model", model) => provenance.model = Some(model.to_string()),
+                ("at", at) => provenance.at = at.parse().ok()?,
+                _ => {}
+            }
+        }
+        Some(provenance).filter(|provenance| !provenance.spec.is_empty())
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{PREFIX}spec={} generator={}", self.spec, self.generator)?;
+        if let Some(model) = &self.model {
+            write!(f, " model={model}")?;
+        }
+        write!(f, " at={}", self.at)
+    }
+}
+
+/// The fingerprint of `spec` a stamp names, the one a session is keyed by.
+pub fn fingerprint(spec: &Spec) -> String {
+    let yaml = spec.to_yaml_string().expect("specs serialize");
+    crate::drift::fingerprint(yaml.as_bytes())
+}
+
+/// The line comment delimiter of the file at `path`, if it has one a stamp
+/// can go in.
+fn comment(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("rs" | "sol" | "spec" | "ts" | "js") => Some("//"),
+        Some("toml" | "yaml" | "yml" | "py") => Some("#"),
+        _ if name == ".gitignore" => Some("#"),
+        _ => None,
+    }
+}
+
+/// The fields of `line` if it is a stamp.
+fn stamp_fields(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let rest = line.strip_prefix("//").or_else(|| line.strip_prefix('#'))?;
+    rest.trim_start().strip_prefix(PREFIX)
+}
+
+/// Whether `source` was generated, i.e. has a stamp.
+pub fn is_generated(source: &str) -> bool {
+    Provenance::parse(source).is_some()
+}
+
+/// `source` without its header: the license above the stamp, and the stamp.
+pub fn strip(source: &str) -> &str {
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        offset += line.len();
+        if stamp_fields(line).is_some() {
+            return &source[offset..];
+        }
+        let trimmed = line.trim_start();
+        if !(trimmed.starts_with("//") || trimmed.starts_with('#')) {
+            break;
+        }
+    }
+    source
+}
+
+/// The license header of `source`, uncommented, if it has a stamp with
+/// comment lines above it.
+pub fn license(source: &str) -> Option<String> {
+    let body = strip(source);
+    if body.len() == source.len() {
+        return None;
+    }
+    let header = &source[..source.len() - body.len()];
+    let lines: Vec<&str> = header
+        .lines()
+        .take_while(|line| stamp_fields(line).is_none())
+        .map(|line| {
+            let line = line.trim_start();
+            let line = line
+                .strip_prefix("//")
+                .or_else(|| line.strip_prefix('#'))
+                .unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line)
+        })
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// `contents` of the file at `path` with `license`, if any, and the stamp
+/// of `provenance` in place of the header it had. Files whose syntax has no
+/// line comments are returned as they are.
+pub fn stamp(
+    path: &Path,
+    contents: &str,
+    provenance: &Provenance,
+    license: Option<&str>,
+) -> String {
+    let Some(comment) = comment(path) else {
+        return contents.to_string();
+    };
+    let mut out = String::new();
+    for line in license.into_iter().flat_map(str::lines) {
+        match line {
+            "" => out.push_str(comment),
+            line => out.push_str(&format!("{comment} {line}")),
+        }
+        out.push('\n');
+    }
+    out.push_str(&format!("{comment} {provenance}\n"));
+    out.push_str(strip(contents));
+    out
+}
+
+/// Stamps every file of `files` with `provenance` and `license`.
+pub fn stamp_files(files: &mut [GeneratedFile], provenance: &Provenance, license: Option<&str>) {
+    for file in files {
+        file.contents = stamp(&file.path, &file.contents, provenance, license);
+    }
+}
+
+/// Stamps every file of `files`, about to replace those of the crate in
+/// `dir`, with `provenance`, keeping the license header each had.
+pub fn restamp_files(files: &mut [GeneratedFile], dir: &Path, provenance: &Provenance) {
+    for file in files {
+        let existing = std::fs::read_to_string(dir.join(&file.path)).ok();
+        let license = existing.as_deref().and_then(license);
+        file.contents = stamp(&file.path, &file.contents, provenance, license.as_deref());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stamp_and_parse() {
+        let provenance = Provenance {
+            spec: "3f2a9c0d1e4b5a67".to_string(),
+            generator: "0.1.0".to_string(),
+            model: Some("gpt-4o".to_string()),
+            at: 1_760_000_000,
+        };
+        let source = "//! The processor.\n\npub fn f() {}\n";
+        let header = "SPDX-License-Identifier: MIT\n\nCopyright Example";
+        let stamped = stamp(
+            Path::new("src/processor.rs"),
+            source,
+            &provenance,
+            Some(header),
+        );
+        assert_eq!(
+            stamped,
+            "// SPDX-License-Identifier: MIT\n//\n// Copyright Example\n\
+             // spec-coder: generated spec=3f2a9c0d1e4b5a67 generator=0.1.0 model=gpt-4o \
+             at=1760000000\n//! The processor.\n\npub fn f() {}\n"
+        );
+        assert_eq!(Provenance::parse(&stamped), Some(provenance.clone()));
+        assert_eq!(strip(&stamped), source);
+        assert_eq!(license(&stamped).as_deref(), Some(header));
+        assert!(is_generated(&stamped));
+        assert!(!is_generated(source));
+        assert_eq!(license(source), None);
+
+        // Stamping again replaces the header rather than stacking another
+        let compiled = Provenance {
+            model: None,
+            ..provenance
+        };
+        let restamped = stamp(Path::new("src/processor.rs"), &stamped, &compiled, None);
+        assert_eq!(
+            restamped,
+            "// spec-coder: generated spec=3f2a9c0d1e4b5a67 generator=0.1.0 at=1760000000\n\
+             //! The processor.\n\npub fn f() {}\n"
+        );
+        assert_eq!(Provenance::parse(&restamped), Some(compiled.clone()));
+
+        let toml = stamp(Path::new("Cargo.toml"), "[package]\n", &compiled, None);
+        assert!(toml.starts_with("# spec-coder: generated spec="));
+        assert!(is_generated(&toml));
+        let summaries = "cvlr_summaries\n";
+        assert_eq!(
+            stamp(
+                Path::new("certora/summaries/core.txt"),
+                summaries,
+                &compiled,
+                None
+            ),
+            summaries
+        );
+    }
+}
@@ -0,0 +1,507 @@
+//! Running the Certora Solana Prover on an example crate.
+//!
+//! The prover is invoked once per rule from the crate directory; it builds
+//! the SBF target itself and reads `[package.metadata.certora]` from the
+//! manifest. Each rule is an independent job, so several may run at once.
+//! Results are read from the [report] the run leaves behind, falling back to
+//! the console output when there is none, and kept in the [cache] until the
+//! inputs of their rule change. Rules may also be proved as jobs of the
+//! Certora [cloud], each [route]d there or to the local prover. Rules may
+//! have [settings] of their own, e.g. loop bounds. Without the prover, the same rules can be model checked
+//! with [kani], as selected by the [`Backend`].
+
+pub mod cache;
+pub mod cloud;
+pub mod kani;
+pub mod report;
+pub mod route;
+pub mod settings;
+
+use cloud::CloudProver;
+use kani::KaniProver;
+use report::{Counterexample, Report};
+use route::Router;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+/// Flags passed to the prover through `--prover_args`, as the composer does.
+pub const DEFAULT_PROVER_ARGS: &[&str] = &[
+    "-solanaOptimisticJoin true",
+    "-solanaOptimisticOverlaps true",
+    "-solanaOptimisticMemcpyPromotion true",
+    "-solanaOptimisticMemcmp true",
+    "-solanaOptimisticNoMemmove true",
+    "-unsatCoresForAllAsserts true",
+    "-solanaAggressiveGlobalDetection true",
+    "-solanaTACOptimize 0",
+];
+
+/// The verdict of the prover on a rule.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RuleStatus {
+    Verified,
+    Violated,
+    Timeout,
+    /// The rule holds vacuously
+    SanityFailed,
+    /// The prover failed without a verdict, e.g. on a build error
+    Error,
+}
+
+impl std::fmt::Display for RuleStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RuleStatus::Verified => "VERIFIED",
+            RuleStatus::Violated => "VIOLATED",
+            RuleStatus::Timeout => "TIMEOUT",
+            RuleStatus::SanityFailed => "SANITY_FAILED",
+            RuleStatus::Error => "ERROR",
+        })
+    }
+}
+
+/// The outcome of proving one rule.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleResult {
+    pub rule: String,
+    pub status: RuleStatus,
+    /// Combined stdout and stderr of the prover
+    pub output: String,
+    pub counterexample: Option<Counterexample>,
+    /// Whether the result was taken from the [cache] rather than proved
+    #[serde(skip)]
+    pub cached: bool,
+}
+
+/// An error running the prover.
+#[derive(Debug, thiserror::Error)]
+pub enum ProverError {
+    #[error("{0} was not found on PATH; install it to prove the rules")]
+    NotInstalled(String),
+    #[error("failed to read {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid rule tags in {}: {message}", .path.display())]
+    Tags { path: PathBuf, message: String },
+    #[error("invalid rule settings in {}: {message}", .path.display())]
+    Settings { path: PathBuf, message: String },
+    #[error("prover cloud: {0}")]
+    Cloud(String),
+    #[error("the job of rule {0} was cancelled")]
+    Cancelled(String),
+}
+
+/// The names of the `#[rule]` functions in `source`.
+pub fn rules(source: &str) -> Vec<String> {
+    let mut rules = Vec::new();
+    let mut lines = source.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if line != "#[rule]" {
+            continue;
+        }
+        // Doc comments and other attributes may follow the rule attribute
+        let Some(signature) = lines.find(|line| !line.starts_with("#[") && !line.starts_with("//"))
+        else {
+            break;
+        };
+        let name = signature
+            .trim_start_matches("pub ")
+            .strip_prefix("fn ")
+            .and_then(|rest| rest.split(['(', '<']).next());
+        if let Some(name) = name {
+            rules.push(name.trim().to_string());
+        }
+    }
+    rules
+}
+
+/// The rules of the crate in `dir`, from the sources under `src/certora`.
+pub fn find_rules(dir: &Path) -> Result<Vec<String>, ProverError> {
+    let mut rules = Vec::new();
+    let mut pending = vec![dir.join("src").join("certora")];
+    while let Some(path) = pending.pop() {
+        let io = |source| ProverError::Io {
+            path: path.clone(),
+            source,
+        };
+        if path.is_dir() {
+            let mut entries = std::fs::read_dir(&path)
+                .map_err(io)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(io)?;
+            // Popped in reverse, so files are visited in name order
+            entries.sort_by(|a, b| b.cmp(a));
+            pending.extend(entries);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            rules.extend(self::rules(&std::fs::read_to_string(&path).map_err(io)?));
+        }
+    }
+    Ok(rules)
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for any one.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+    // The last `*` seen and where in `name` it started matching, to retry
+    // with one more character when the rest does not match
+    let (mut p, mut n, mut star) = (0, 0, None);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((at, from)) => {
+                    p = at + 1;
+                    n = from + 1;
+                    star = Some((at, from + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The tags of the rules of the crate in `dir`, from the `tags` table of
+/// `[package.metadata.certora]` in its manifest, which lists the rules of
+/// each tag by name or pattern:
+///
+/// ```toml
+/// [package.metadata.certora.tags]
+/// fast = ["rule_*_sanity"]
+/// solvency = ["rule_*_solvency"]
+/// ```
+pub fn find_tags(dir: &Path) -> Result<BTreeMap<String, Vec<String>>, ProverError> {
+    let path = dir.join("Cargo.toml");
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(source) => return Err(ProverError::Io { path, source }),
+    };
+    let invalid = |message: String| ProverError::Tags {
+        path: path.clone(),
+        message,
+    };
+    let manifest: toml::Value = toml::from_str(&text).map_err(|err| invalid(err.to_string()))?;
+    let tags = ["package", "metadata", "certora", "tags"]
+        .iter()
+        .try_fold(&manifest, |value, key| value.get(key));
+    let Some(tags) = tags else {
+        return Ok(BTreeMap::new());
+    };
+    let tags = tags
+        .as_table()
+        .ok_or_else(|| invalid("`tags` is not a table".to_string()))?;
+    tags.iter()
+        .map(|(tag, rules)| {
+            let rules = rules
+                .as_array()
+                .and_then(|rules| {
+                    rules
+                        .iter()
+                        .map(|rule| rule.as_str().map(str::to_string))
+                        .collect()
+                })
+                .ok_or_else(|| invalid(format!("tag `{tag}` is not a list of rules")))?;
+            Ok((tag.clone(), rules))
+        })
+        .collect()
+}
+
+/// The rules of `rules` matching any of `patterns`, in order.
+pub fn tagged<'a>(rules: &'a [String], patterns: &[String]) -> Vec<&'a String> {
+    rules
+        .iter()
+        .filter(|rule| {
+            patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern, rule))
+        })
+        .collect()
+}
+
+/// The verifier proving the rules of a crate.
+#[derive(Clone, Debug)]
+pub enum Backend {
+    /// The Certora Solana Prover, proving the rules of `src/certora`
+    Certora(SolanaProver),
+    /// The same prover, running the rules as jobs of the Certora cloud
+    Cloud(CloudProver),
+    /// Either of the two, chosen per rule
+    Auto(Router),
+    /// Kani, checking the harnesses of the same names in `src/proofs.rs`
+    Kani(KaniProver),
+}
+
+impl Backend {
+    /// Proves `rule` of the crate in `dir`.
+    pub fn prove(&self, dir: &Path, rule: &str) -> Result<RuleResult, ProverError> {
+        match self {
+            Backend::Certora(prover) => prover.prove(dir, rule),
+            Backend::Cloud(prover) => {
+                let key = cache::Inputs::read(dir)?.key(self, rule);
+                prover.prove(dir, rule, &key)
+            }
+            Backend::Auto(router) => {
+                let key = cache::Inputs::read(dir)?.key(self, rule);
+                router.prove(dir, rule, &key)
+            }
+            Backend::Kani(prover) => prover.prove(dir, rule),
+        }
+    }
+
+    /// The command line of a run, whose results depend on it.
+    pub fn command_line(&self) -> String {
+        match self {
+            Backend::Certora(prover) => {
+                format!("{}\n{}", prover.cli, prover.prover_args.join(" "))
+            }
+            Backend::Cloud(prover) => {
+                format!("{} cloud\n{}", prover.cli, prover.prover_args.join(" "))
+            }
+            Backend::Auto(router) => format!(
+                "{} auto\n{}",
+                router.local.cli,
+                router.local.prover_args.join(" ")
+            ),
+            Backend::Kani(prover) => format!("{} kani\n{}", prover.cargo, prover.args.join(" ")),
+        }
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    /// The backend named `s`, with its defaults.
+    fn from_str(s: &str) -> Result<Backend, String> {
+        match s {
+            "certora" => Ok(Backend::Certora(SolanaProver::default())),
+            "auto" => Ok(Backend::Auto(Router::default())),
+            "cloud" => Ok(Backend::Cloud(CloudProver::default())),
+            "kani" => Ok(Backend::Kani(KaniProver::default())),
+            _ => Err(format!(
+                "unknown backend `{s}`, expected `certora`, `cloud`, `auto` or `kani`"
+            )),
+        }
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Backend {
+        Backend::Certora(SolanaProver::default())
+    }
+}
+
+/// Reads the verdict on `rule` from the prover output.
+fn parse_status(output: &str, rule: &str, success: bool) -> RuleStatus {
+    for line in output.lines() {
+        let Some(rest) = line.find(rule).map(|at| &line[at + rule.len()..]) else {
+            continue;
+        };
+        let verdict = rest
+            .trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ':' | '-'))
+            .to_ascii_uppercase();
+        if verdict.starts_with("VERIFIED") || verdict.starts_with("PASSED") {
+            return RuleStatus::Verified;
+        }
+        if verdict.starts_with("VIOLATED") || verdict.starts_with("FAILED") {
+            return RuleStatus::Violated;
+        }
+        if verdict.starts_with("TIMEOUT") {
+            return RuleStatus::Timeout;
+        }
+        if verdict.starts_with("SANITY_FAILED") {
+            return RuleStatus::SanityFailed;
+        }
+    }
+    let upper = output.to_ascii_uppercase();
+    if upper.contains("VIOLATED") {
+        RuleStatus::Violated
+    } else if !success {
+        RuleStatus::Error
+    } else {
+        RuleStatus::Verified
+    }
+}
+
+/// The `certoraSolanaProver` command line.
+#[derive(Clone, Debug)]
+pub struct SolanaProver {
+    pub cli: String,
+    pub prover_args: Vec<String>,
+}
+
+impl Default for SolanaProver {
+    fn default() -> SolanaProver {
+        SolanaProver {
+            cli: "certoraSolanaProver".to_string(),
+            prover_args: DEFAULT_PROVER_ARGS
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl SolanaProver {
+    /// Proves `rule` of the crate in `dir`.
+    pub fn prove(&self, dir: &Path, rule: &str) -> Result<RuleResult, ProverError> {
+        let started = SystemTime::now();
+        let mut command = Command::new(&self.cli);
+        command.current_dir(dir).args(["--rule", rule]);
+        if !self.prover_args.is_empty() {
+            command.arg("--prover_args").args(&self.prover_args);
+        }
+        command.args(["--rule_sanity", "basic"]);
+        command.args(settings::certora_args(
+            &settings::Settings::read(dir)?.for_rule(rule),
+        ));
+        let output = command.output().map_err(|source| match source.kind() {
+            std::io::ErrorKind::NotFound => ProverError::NotInstalled(self.cli.clone()),
+            _ => ProverError::Io {
+                path: dir.to_path_buf(),
+                source,
+            },
+        })?;
+        let text = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let report = output_report(dir, started, rule);
+        let status = report.as_ref().and_then(|report| report.status(rule));
+        Ok(RuleResult {
+            rule: rule.to_string(),
+            status: status.unwrap_or_else(|| parse_status(&text, rule, output.status.success())),
+            output: text,
+            counterexample: report.and_then(|report| {
+                report
+                    .rule(rule)
+                    .find_map(|result| result.counterexample.clone())
+            }),
+            cached: false,
+        })
+    }
+}
+
+/// The report on `rule` in the newest `emv-*` output directory a local run
+/// created in `dir` since `started`. Runs of other rules may have created
+/// directories at the same time, so only reports on `rule` are considered.
+fn output_report(dir: &Path, started: SystemTime, rule: &str) -> Option<Report> {
+    let mut runs: Vec<(SystemTime, PathBuf)> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("emv-"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .filter(|(modified, _)| *modified >= started)
+        .collect();
+    runs.sort_by(|a, b| b.cmp(a));
+    runs.into_iter()
+        .filter_map(|(_, run)| Report::from_dir(&run).ok())
+        .find(|report| report.status(rule).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let source = "\
+/// Verifies a deposit.
+#[rule]
+pub fn rule_deposit() {}
+
+#[rule]
+/// Verifies a withdrawal.
+fn rule_withdraw<T>() {}
+
+pub fn helper() {}
+";
+        assert_eq!(rules(source), ["rule_deposit", "rule_withdraw"]);
+    }
+
+    #[test]
+    fn test_find_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let certora = dir.path().join("src/certora");
+        std::fs::create_dir_all(certora.join("spec")).unwrap();
+        std::fs::write(certora.join("spec.rs"), "#[rule]\npub fn rule_b() {}\n").unwrap();
+        std::fs::write(certora.join("a.rs"), "#[rule]\npub fn rule_a() {}\n").unwrap();
+        assert_eq!(find_rules(dir.path()).unwrap(), ["rule_a", "rule_b"]);
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("rule_*_sanity", "rule_deposit_sanity"));
+        assert!(matches_pattern("*", "rule_a"));
+        assert!(matches_pattern("rule_?", "rule_a"));
+        assert!(matches_pattern("rule_*a*b", "rule_xaxxab"));
+        assert!(!matches_pattern("rule_?", "rule_ab"));
+        assert!(!matches_pattern("rule_*_sanity", "rule_deposit_ensures"));
+        assert!(!matches_pattern("rule_a", "rule_ab"));
+    }
+
+    #[test]
+    fn test_find_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(find_tags(dir.path()).unwrap().is_empty());
+        let manifest = dir.path().join("Cargo.toml");
+        std::fs::write(
+            &manifest,
+            "[package]\nname = \"a\"\n\n[package.metadata.certora.tags]\n\
+             fast = [\"rule_*_sanity\"]\nsolvency = [\"rule_a\", \"rule_b\"]\n",
+        )
+        .unwrap();
+        let tags = find_tags(dir.path()).unwrap();
+        assert_eq!(tags["fast"], ["rule_*_sanity"]);
+        let rules = ["rule_a", "rule_a_sanity", "rule_c"].map(String::from);
+        assert_eq!(tagged(&rules, &tags["fast"]), ["rule_a_sanity"]);
+        assert_eq!(tagged(&rules, &tags["solvency"]), ["rule_a"]);
+
+        std::fs::write(&manifest, "[package.metadata.certora.tags]\nfast = 1\n").unwrap();
+        assert!(matches!(
+            find_tags(dir.path()),
+            Err(ProverError::Tags { message, .. }) if message == "tag `fast` is not a list of rules"
+        ));
+    }
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(
+            parse_status("rule_deposit: VERIFIED\n", "rule_deposit", true),
+            RuleStatus::Verified
+        );
+        assert_eq!(
+            parse_status("Rule rule_deposit - Violated\n", "rule_deposit", false),
+            RuleStatus::Violated
+        );
+        assert_eq!(
+            parse_status("rule_deposit: TIMEOUT", "rule_deposit", false),
+            RuleStatus::Timeout
+        );
+        assert_eq!(
+            parse_status("error: could not compile", "rule_deposit", false),
+            RuleStatus::Error
+        );
+        assert_eq!(
+            parse_status("Done", "rule_deposit", true),
+            RuleStatus::Verified
+        );
+    }
+}
@@ -0,0 +1,391 @@
+//! Caching of prover results across checks.
+//!
+//! A result is keyed by the fingerprint of everything the prover sees for a
+//! rule: the command line, the source of the rule and the helpers it may
+//! call, and the program it is compiled against. Other rules and the
+//! processor functions the rule does not call are left out of its key, so a
+//! patch to one function only invalidates the rules of that function and of
+//! the functions calling it.
+
+use super::settings::{Settings, RULES_TOML};
+use super::{Backend, ProverError, RuleResult, RuleStatus};
+use crate::drift;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The cached results, relative to the crate root.
+pub const CACHE_JSON: &str = ".spec-coder/prover-cache.json";
+
+/// Files that are only compiled for unit tests, which the prover never sees.
+const TEST_ONLY: &[&str] = &[
+    "src/spec_tests.rs",
+    "src/prop_tests.rs",
+    "src/regressions.rs",
+];
+
+/// The sources of a crate the prover reads, by path relative to its root.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Inputs {
+    /// `src/certora.rs` and the sources under `src/certora`
+    rules: BTreeMap<PathBuf, String>,
+    processor: String,
+    /// Everything else: the manifest, the other sources and the summaries
+    program: BTreeMap<PathBuf, Vec<u8>>,
+    /// Only the settings of a rule are part of its key
+    settings: Settings,
+}
+
+/// `source` without the functions in `names`, from their signature to the
+/// end of their body.
+fn without(source: &str, names: impl IntoIterator<Item = impl AsRef<str>>) -> String {
+    let mut out = source.to_string();
+    for name in names {
+        for signature in [
+            format!("fn {}(", name.as_ref()),
+            format!("fn {}<", name.as_ref()),
+        ] {
+            let Some(start) = out.find(&signature) else {
+                continue;
+            };
+            if let Some(end) = drift::block_end(&out[start..]) {
+                out.replace_range(start..start + end, "");
+            }
+        }
+    }
+    out
+}
+
+/// Whether `text` mentions the identifier `ident`, e.g. by calling it.
+fn mentions(text: &str, ident: &str) -> bool {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    text.match_indices(ident).any(|(at, _)| {
+        !text[..at].ends_with(is_ident) && !text[at + ident.len()..].starts_with(is_ident)
+    })
+}
+
+/// The files under `path`, recursively, in name order.
+pub(crate) fn files(path: &Path) -> Result<Vec<PathBuf>, ProverError> {
+    let io = |source| ProverError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    if !path.is_dir() {
+        return Ok(if path.is_file() {
+            vec![path.to_path_buf()]
+        } else {
+            Vec::new()
+        });
+    }
+    let mut entries = std::fs::read_dir(path)
+        .map_err(io)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io)?;
+    entries.sort();
+    let mut files = Vec::new();
+    for entry in entries {
+        files.extend(self::files(&entry)?);
+    }
+    Ok(files)
+}
+
+impl Inputs {
+    /// Reads the inputs of the crate in `dir`.
+    pub fn read(dir: &Path) -> Result<Inputs, ProverError> {
+        let mut inputs = Inputs {
+            settings: Settings::read(dir)?,
+            ..Inputs::default()
+        };
+        let mut paths = vec![dir.join("Cargo.toml"), dir.join("Cargo.lock")];
+        paths.extend(files(&dir.join("src"))?);
+        paths.extend(files(&dir.join("certora"))?);
+        for path in paths {
+            let Ok(relative) = path.strip_prefix(dir).map(Path::to_path_buf) else {
+                continue;
+            };
+            let bytes = match std::fs::read(&path) {
+                Ok(bytes) => bytes,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(source) => return Err(ProverError::Io { path, source }),
+            };
+            let is_rust = relative.extension().is_some_and(|ext| ext == "rs");
+            if (relative.starts_with("src/certora") && is_rust)
+                || relative == Path::new("src/certora.rs")
+            {
+                inputs
+                    .rules
+                    .insert(relative, String::from_utf8_lossy(&bytes).into_owned());
+            } else if relative == Path::new(drift::PROCESSOR_RS) {
+                inputs.processor = String::from_utf8_lossy(&bytes).into_owned();
+            } else if !TEST_ONLY.iter().any(|test| relative == Path::new(test))
+                && relative != Path::new(RULES_TOML)
+            {
+                inputs.program.insert(relative, bytes);
+            }
+        }
+        Ok(inputs)
+    }
+
+    /// The key of the result of proving `rule` with `backend`.
+    pub fn key(&self, backend: &Backend, rule: &str) -> String {
+        let settings = serde_json::to_string(&self.settings.for_rule(rule)).expect("serializes");
+        let mut text = format!("{}\n{rule}\n{settings}\n", backend.command_line());
+        for (path, source) in &self.rules {
+            let others = super::rules(source)
+                .into_iter()
+                .filter(|other| other != rule);
+            text.push_str(&format!(
+                "{}\n{}\n",
+                path.display(),
+                without(source, others)
+            ));
+        }
+        // The processor function with the longest name the rule is named
+        // after, e.g. `withdraw` rather than `with` for `rule_withdraw_sanity`
+        let marked = drift::marked(&self.processor);
+        let function = marked
+            .keys()
+            .filter(|name| {
+                rule.strip_prefix("rule_")
+                    .and_then(|rest| rest.strip_prefix(**name))
+                    .is_some_and(|suffix| suffix.is_empty() || suffix.starts_with('_'))
+            })
+            .max_by_key(|name| name.len());
+        let mut processor = self.processor.clone();
+        if let Some(function) = function {
+            // The other marked functions the rule may reach: those called by
+            // its function or the code around the marked functions, directly
+            // or through one another
+            let mut reached = marked
+                .values()
+                .fold(self.processor.clone(), |rest, marked| {
+                    rest.replacen(marked.source, "", 1)
+                });
+            reached.push_str(marked[function].source);
+            let mut others: Vec<_> = marked
+                .iter()
+                .filter(|(name, _)| *name != function)
+                .collect();
+            loop {
+                let (called, uncalled): (Vec<_>, Vec<_>) = others
+                    .into_iter()
+                    .partition(|(name, _)| mentions(&reached, &format!("process_{name}")));
+                others = uncalled;
+                if called.is_empty() {
+                    break;
+                }
+                for (_, marked) in called {
+                    reached.push_str(marked.source);
+                }
+            }
+            for (_, marked) in others {
+                processor = processor.replacen(marked.source, "", 1);
+            }
+        }
+        text.push_str(&processor);
+        let mut bytes = text.into_bytes();
+        for (path, contents) in &self.program {
+            bytes.extend(path.display().to_string().bytes());
+            bytes.push(b'\n');
+            bytes.extend(contents);
+        }
+        drift::fingerprint(&bytes)
+    }
+}
+
+/// Prover results by the key of their inputs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cache {
+    results: BTreeMap<String, RuleResult>,
+}
+
+impl Cache {
+    /// Loads the cache of the crate in `dir`; a missing or malformed cache
+    /// is empty.
+    pub fn load(dir: &Path) -> Cache {
+        let results = std::fs::read_to_string(dir.join(CACHE_JSON))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Cache { results }
+    }
+
+    /// The cached result for `key`.
+    pub fn get(&self, key: &str) -> Option<RuleResult> {
+        let mut result = self.results.get(key)?.clone();
+        result.cached = true;
+        Some(result)
+    }
+
+    /// The cached results, in key order.
+    pub fn results(&self) -> impl Iterator<Item = &RuleResult> {
+        self.results.values()
+    }
+
+    /// Caches `result` under `key`. Errors and timeouts may not recur, so
+    /// they are proved again.
+    pub fn insert(&mut self, key: String, result: &RuleResult) {
+        if !matches!(result.status, RuleStatus::Error | RuleStatus::Timeout) {
+            self.results.insert(key, result.clone());
+        }
+    }
+
+    /// Saves the results of the rules in `rules` to the crate in `dir`,
+    /// dropping those of rules that no longer exist.
+    pub fn save(&mut self, dir: &Path, rules: &[String]) -> Result<(), ProverError> {
+        self.results
+            .retain(|_, result| rules.contains(&result.rule));
+        let path = dir.join(CACHE_JSON);
+        let io = |source| ProverError::Io {
+            path: path.clone(),
+            source,
+        };
+        std::fs::create_dir_all(path.parent().expect("has a parent")).map_err(io)?;
+        let json = serde_json::to_string_pretty(&self.results).expect("results serialize");
+        std::fs::write(&path, json + "\n").map_err(io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materialize;
+    use spec::{Spec, Target};
+
+    const VAULT: &str = include_str!("../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_key_follows_the_rule_inputs() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let files = materialize::generate(&spec, Target::Svm).unwrap();
+        materialize::write(&files, dir.path(), true).unwrap();
+        let prover = Backend::default();
+        let keys = |rules: &[&str]| {
+            let inputs = Inputs::read(dir.path()).unwrap();
+            rules
+                .iter()
+                .map(|rule| inputs.key(&prover, rule))
+                .collect::<Vec<_>>()
+        };
+        let rules = ["rule_deposit_sanity", "rule_withdraw_sanity"];
+        let before = keys(&rules);
+        assert_eq!(keys(&rules), before);
+        assert_ne!(before[0], before[1]);
+        let kani: Backend = "kani".parse().unwrap();
+        assert_ne!(
+            Inputs::read(dir.path()).unwrap().key(&kani, rules[0]),
+            before[0]
+        );
+
+        // A patch to withdraw leaves the rules of deposit alone
+        let processor = dir.path().join(drift::PROCESSOR_RS);
+        let source = std::fs::read_to_string(&processor).unwrap();
+        let start = source.find("fn process_withdraw(").unwrap();
+        let mut patched = source.clone();
+        patched.insert_str(
+            start + source[start..].find('{').unwrap() + 1,
+            "\n    // patched",
+        );
+        std::fs::write(&processor, patched).unwrap();
+        let after = keys(&rules);
+        assert_eq!(after[0], before[0]);
+        assert_ne!(after[1], before[1]);
+
+        // Tests are not compiled for the prover
+        std::fs::write(dir.path().join("src/spec_tests.rs"), "// changed\n").unwrap();
+        assert_eq!(keys(&rules), after);
+
+        // Settings of one rule leave the other alone
+        std::fs::write(
+            dir.path().join(RULES_TOML),
+            "[rule_withdraw_sanity]\nloop_iter = 4\n",
+        )
+        .unwrap();
+        let tuned = keys(&rules);
+        assert_eq!(tuned[0], after[0]);
+        assert_ne!(tuned[1], after[1]);
+
+        std::fs::write(dir.path().join("src/state.rs"), "// changed\n").unwrap();
+        assert_ne!(keys(&rules)[0], after[0]);
+    }
+
+    #[test]
+    fn test_key_follows_callees() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let files = materialize::generate(&spec, Target::Svm).unwrap();
+        materialize::write(&files, dir.path(), true).unwrap();
+        let prover = Backend::default();
+        let key = |rule: &str| Inputs::read(dir.path()).unwrap().key(&prover, rule);
+        let processor = dir.path().join(drift::PROCESSOR_RS);
+        let patch = |function: &str, line: &str| {
+            let source = std::fs::read_to_string(&processor).unwrap();
+            let start = source.find(&format!("fn {function}(")).unwrap();
+            let mut patched = source.clone();
+            patched.insert_str(start + source[start..].find('{').unwrap() + 1, line);
+            std::fs::write(&processor, patched).unwrap();
+        };
+
+        // Once deposit calls withdraw, a patch to withdraw invalidates the
+        // rules of deposit
+        patch(
+            "process_deposit",
+            "\n    let _ = process_withdraw(accounts, instruction_data);",
+        );
+        let before = key("rule_deposit_sanity");
+        patch("process_withdraw", "\n    // patched");
+        assert_ne!(key("rule_deposit_sanity"), before);
+
+        // And so does a patch to a function withdraw calls in turn
+        let before = key("rule_deposit_sanity");
+        patch(
+            "process_withdraw",
+            "\n    let _ = process_reward(accounts, instruction_data);",
+        );
+        assert_ne!(key("rule_deposit_sanity"), before);
+        let before = key("rule_deposit_sanity");
+        patch("process_reward", "\n    // patched");
+        assert_ne!(key("rule_deposit_sanity"), before);
+    }
+
+    #[test]
+    fn test_mentions() {
+        assert!(mentions(
+            "let _ = process_withdraw(accounts);",
+            "process_withdraw"
+        ));
+        assert!(!mentions(
+            "process_withdraw_all(accounts);",
+            "process_withdraw"
+        ));
+        assert!(!mentions(
+            "preprocess_withdraw(accounts);",
+            "process_withdraw"
+        ));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = |rule: &str, status| RuleResult {
+            rule: rule.to_string(),
+            status,
+            output: String::new(),
+            counterexample: None,
+            cached: false,
+        };
+        let mut cache = Cache::load(dir.path());
+        cache.insert("a".to_string(), &result("rule_a", RuleStatus::Violated));
+        cache.insert("b".to_string(), &result("rule_b", RuleStatus::Verified));
+        cache.insert("c".to_string(), &result("rule_c", RuleStatus::Timeout));
+        cache.save(dir.path(), &["rule_a".to_string()]).unwrap();
+
+        let cache = Cache::load(dir.path());
+        let cached = cache.get("a").unwrap();
+        assert!(cached.cached);
+        assert_eq!(cached.status, RuleStatus::Violated);
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_none());
+    }
+}
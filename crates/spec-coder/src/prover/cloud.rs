@@ -0,0 +1,549 @@
+//! Running rules as jobs of the Certora cloud.
+//!
+//! A cloud run does not block on the prover: the CLI submits the job and
+//! prints the URL of its results, and the job is then polled until it
+//! finishes, fails or overruns its timeout, in which case it is cancelled
+//! and the rule reported as a [`RuleStatus::Timeout`]. Submitted jobs are
+//! recorded in [`JOBS_JSON`] until they are done, so a run restarted after
+//! a crash polls the jobs it left instead of submitting them again, as long
+//! as the inputs of their rules did not change. Ctrl-C cancels every job
+//! being polled, see [`cancel`].
+
+use super::report::Report;
+use super::settings::{certora_args, Settings};
+use super::{ProverError, RuleResult, RuleStatus, DEFAULT_PROVER_ARGS};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The pending jobs of a crate, relative to its root.
+pub const JOBS_JSON: &str = ".spec-coder/prover-jobs.json";
+
+/// The environment variable holding the key of the cloud service.
+pub const KEY_ENV: &str = "CERTORAKEY";
+
+/// Set once the jobs being polled are to be cancelled.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Serializes the updates of [`JOBS_JSON`] by the workers proving rules.
+static JOBS: Mutex<()> = Mutex::new(());
+
+/// Cancels every job being polled, as on Ctrl-C: each is cancelled on the
+/// service and its rule fails with [`ProverError::Cancelled`].
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+
+/// Whether [`cancel`] was called.
+pub fn cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// A job submitted to the cloud.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    pub rule: String,
+    /// The results page the CLI printed
+    pub url: String,
+    /// The cache key of the inputs of the rule when it was submitted, see
+    /// [`super::cache::Inputs::key`]
+    pub key: String,
+    /// Seconds since the Unix epoch
+    pub submitted: u64,
+}
+
+impl Job {
+    /// The job whose results page is `url`, e.g.
+    /// `https://prover.certora.com/output/<user>/<job>?anonymousKey=<key>`.
+    fn from_url(url: &str, rule: &str, key: &str) -> Option<Job> {
+        let (_, path) = url.split_once("/output/")?;
+        let path = path.split(['?', '#']).next()?;
+        let id = path.trim_end_matches('/').rsplit('/').next()?;
+        if id.is_empty() {
+            return None;
+        }
+        Some(Job {
+            id: id.to_string(),
+            rule: rule.to_string(),
+            url: url.to_string(),
+            key: key.to_string(),
+            submitted: now(),
+        })
+    }
+
+    /// The URL of `page` of the job on the service, e.g. `jobStatus`, with
+    /// the same path and query as its results page.
+    fn page(&self, page: &str) -> String {
+        self.url.replacen("/output/", &format!("/{page}/"), 1)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// Where a job is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JobState {
+    Queued,
+    Running,
+    Finished,
+    /// The job failed on the service, with its reason
+    Failed(String),
+    Cancelled,
+}
+
+impl JobState {
+    /// The state the service reports as `status`.
+    fn parse(status: &str) -> JobState {
+        match status.to_ascii_uppercase().as_str() {
+            "QUEUED" | "PENDING" | "SUBMITTED" => JobState::Queued,
+            "SUCCEEDED" | "SUCCESS" | "FINISHED" | "COMPLETED" => JobState::Finished,
+            "FAILED" | "ERROR" => JobState::Failed(format!("the job {status}")),
+            "CANCELED" | "CANCELLED" | "HALTED" | "KILLED" => JobState::Cancelled,
+            _ => JobState::Running,
+        }
+    }
+}
+
+/// What jobs are submitted to and polled on.
+pub trait JobClient: Sync {
+    /// Submits `rule` of the crate in `dir`, whose inputs have `key`.
+    fn submit(&self, dir: &Path, rule: &str, key: &str) -> Result<Job, ProverError>;
+
+    fn state(&self, job: &Job) -> Result<JobState, ProverError>;
+
+    /// The report of a finished job.
+    fn report(&self, job: &Job) -> Result<Report, ProverError>;
+
+    fn cancel(&self, job: &Job) -> Result<(), ProverError>;
+}
+
+/// How jobs are polled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polling {
+    /// The wait between two polls
+    pub interval: Duration,
+    /// How long a job may take from its submission before it is
+    /// cancelled; `None` waits for it however long it takes
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Polling {
+    fn default() -> Polling {
+        Polling {
+            interval: Duration::from_secs(10),
+            timeout: Some(Duration::from_secs(2 * 3600)),
+        }
+    }
+}
+
+/// The jobs recorded in the crate in `dir`, by rule.
+pub fn pending(dir: &Path) -> BTreeMap<String, Job> {
+    std::fs::read_to_string(dir.join(JOBS_JSON))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Records `job` in the crate in `dir`, or forgets the job of `rule` if
+/// `job` is `None`.
+fn record(dir: &Path, rule: &str, job: Option<&Job>) -> Result<(), ProverError> {
+    let _guard = JOBS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut jobs = pending(dir);
+    match job {
+        Some(job) => jobs.insert(rule.to_string(), job.clone()),
+        None => jobs.remove(rule),
+    };
+    let path = dir.join(JOBS_JSON);
+    let io = |source| ProverError::Io {
+        path: path.clone(),
+        source,
+    };
+    if jobs.is_empty() {
+        return match std::fs::remove_file(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(io(err)),
+            _ => Ok(()),
+        };
+    }
+    std::fs::create_dir_all(path.parent().expect("has a parent")).map_err(io)?;
+    let json = serde_json::to_string_pretty(&jobs).expect("jobs serialize");
+    std::fs::write(&path, json + "\n").map_err(io)
+}
+
+/// Proves `rule` of the crate in `dir`, whose inputs have `key`, as a job
+/// of `client`: the job recorded for the same inputs if there is one, else
+/// a new one. Polls it until it is done, `polling` overruns or `cancelled`
+/// returns true.
+pub fn run(
+    client: &dyn JobClient,
+    dir: &Path,
+    rule: &str,
+    key: &str,
+    polling: &Polling,
+    cancelled: &dyn Fn() -> bool,
+) -> Result<RuleResult, ProverError> {
+    let job = match pending(dir).remove(rule).filter(|job| job.key == key) {
+        Some(job) => job,
+        None => {
+            let job = client.submit(dir, rule, key)?;
+            record(dir, rule, Some(&job))?;
+            job
+        }
+    };
+    let result = |status, output: String, counterexample| RuleResult {
+        rule: rule.to_string(),
+        status,
+        output,
+        counterexample,
+        cached: false,
+    };
+    loop {
+        if cancelled() {
+            client.cancel(&job)?;
+            record(dir, rule, None)?;
+            return Err(ProverError::Cancelled(rule.to_string()));
+        }
+        match client.state(&job)? {
+            JobState::Finished => {
+                let report = client.report(&job)?;
+                record(dir, rule, None)?;
+                let status = report.status(rule).unwrap_or(RuleStatus::Error);
+                let counterexample = report
+                    .rule(rule)
+                    .find_map(|result| result.counterexample.clone());
+                return Ok(result(status, job.url.clone(), counterexample));
+            }
+            JobState::Failed(reason) => {
+                record(dir, rule, None)?;
+                return Ok(result(
+                    RuleStatus::Error,
+                    format!("{reason}: {}", job.url),
+                    None,
+                ));
+            }
+            JobState::Cancelled => {
+                record(dir, rule, None)?;
+                return Err(ProverError::Cancelled(rule.to_string()));
+            }
+            JobState::Queued | JobState::Running => {}
+        }
+        let elapsed = Duration::from_secs(now().saturating_sub(job.submitted));
+        if let Some(timeout) = polling.timeout.filter(|&timeout| elapsed >= timeout) {
+            client.cancel(&job)?;
+            record(dir, rule, None)?;
+            return Ok(result(
+                RuleStatus::Timeout,
+                format!(
+                    "the job did not finish within {}s and was cancelled: {}",
+                    timeout.as_secs(),
+                    job.url
+                ),
+                None,
+            ));
+        }
+        std::thread::sleep(polling.interval);
+    }
+}
+
+/// The `certoraSolanaProver` command line submitting jobs to the cloud,
+/// and the service they are polled on.
+#[derive(Clone, Debug)]
+pub struct CloudProver {
+    pub cli: String,
+    pub prover_args: Vec<String>,
+    pub polling: Polling,
+    /// The key of the service, from `cloud_key` under `[prover]`; read from
+    /// [`KEY_ENV`] when `None`
+    pub key: Option<String>,
+}
+
+impl Default for CloudProver {
+    fn default() -> CloudProver {
+        CloudProver {
+            cli: "certoraSolanaProver".to_string(),
+            prover_args: DEFAULT_PROVER_ARGS
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect(),
+            polling: Polling::default(),
+            key: None,
+        }
+    }
+}
+
+impl CloudProver {
+    /// Proves `rule` of the crate in `dir`, whose inputs have `key`, until
+    /// [`cancel`] is called.
+    pub fn prove(&self, dir: &Path, rule: &str, key: &str) -> Result<RuleResult, ProverError> {
+        run(self, dir, rule, key, &self.polling, &cancelled)
+    }
+
+    /// The key of the service, if there is one.
+    pub fn key(&self) -> Option<String> {
+        self.key
+            .clone()
+            .or_else(|| std::env::var(KEY_ENV).ok())
+            .filter(|key| !key.is_empty())
+    }
+
+    fn get(&self, url: &str) -> Result<ureq::Response, ProverError> {
+        let mut request = ureq::get(url).timeout(Duration::from_secs(60));
+        if let Some(key) = self.key() {
+            request = request.set("authorization", &format!("Bearer {key}"));
+        }
+        request
+            .call()
+            .map_err(|err| ProverError::Cloud(format!("GET {url}: {err}")))
+    }
+}
+
+impl JobClient for CloudProver {
+    fn submit(&self, dir: &Path, rule: &str, key: &str) -> Result<Job, ProverError> {
+        let mut command = Command::new(&self.cli);
+        command.current_dir(dir).args(["--rule", rule]);
+        if !self.prover_args.is_empty() {
+            command.arg("--prover_args").args(&self.prover_args);
+        }
+        command.args(["--rule_sanity", "basic", "--wait_for_results", "none"]);
+        command.args(certora_args(&Settings::read(dir)?.for_rule(rule)));
+        if let Some(key) = self.key() {
+            command.env(KEY_ENV, key);
+        }
+        let output = command.output().map_err(|source| match source.kind() {
+            std::io::ErrorKind::NotFound => ProverError::NotInstalled(self.cli.clone()),
+            _ => ProverError::Io {
+                path: dir.to_path_buf(),
+                source,
+            },
+        })?;
+        let text = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        text.split_whitespace()
+            .filter(|word| word.starts_with("https://") && word.contains("/output/"))
+            .find_map(|url| Job::from_url(url, rule, key))
+            .ok_or_else(|| ProverError::Cloud(format!("the prover printed no job URL:\n{text}")))
+    }
+
+    fn state(&self, job: &Job) -> Result<JobState, ProverError> {
+        let url = job.page("jobStatus");
+        let text = self
+            .get(&url)?
+            .into_string()
+            .map_err(|err| ProverError::Cloud(format!("GET {url}: {err}")))?;
+        let status: serde_json::Value = serde_json::from_str(&text)
+            .map_err(|err| ProverError::Cloud(format!("GET {url}: {err}")))?;
+        let status = status
+            .get("jobStatus")
+            .and_then(|status| status.as_str())
+            .ok_or_else(|| ProverError::Cloud(format!("GET {url}: no jobStatus in {text}")))?;
+        Ok(JobState::parse(status))
+    }
+
+    fn report(&self, job: &Job) -> Result<Report, ProverError> {
+        let url = job.page("zipOutput");
+        let mut bytes = Vec::new();
+        self.get(&url)?
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .map_err(|err| ProverError::Cloud(format!("GET {url}: {err}")))?;
+        let mut file = tempfile::NamedTempFile::new().map_err(|source| ProverError::Io {
+            path: std::env::temp_dir(),
+            source,
+        })?;
+        std::io::Write::write_all(&mut file, &bytes).map_err(|source| ProverError::Io {
+            path: file.path().to_path_buf(),
+            source,
+        })?;
+        Report::from_zip(file.path()).map_err(|err| ProverError::Cloud(err.to_string()))
+    }
+
+    fn cancel(&self, job: &Job) -> Result<(), ProverError> {
+        let url = job.page("cancel");
+        let mut request = ureq::post(&url).timeout(Duration::from_secs(60));
+        if let Some(key) = self.key() {
+            request = request.set("authorization", &format!("Bearer {key}"));
+        }
+        request
+            .call()
+            .map_err(|err| ProverError::Cloud(format!("POST {url}: {err}")))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::report::RuleReport;
+    use std::cell::Cell;
+
+    /// Finishes jobs after `polls` polls, with a report of the rule
+    /// verified, and records the calls made.
+    struct Fake {
+        polls: usize,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl Fake {
+        fn new(polls: usize) -> Fake {
+            Fake {
+                polls,
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl JobClient for Fake {
+        fn submit(&self, _: &Path, rule: &str, key: &str) -> Result<Job, ProverError> {
+            self.calls.lock().unwrap().push(format!("submit {rule}"));
+            let url = format!("https://prover.example/output/1/job-{rule}?anonymousKey=k");
+            Ok(Job::from_url(&url, rule, key).unwrap())
+        }
+
+        fn state(&self, job: &Job) -> Result<JobState, ProverError> {
+            let mut calls = self.calls.lock().unwrap();
+            calls.push(format!("state {}", job.id));
+            let polled = calls
+                .iter()
+                .filter(|call| call.starts_with("state"))
+                .count();
+            Ok(if polled > self.polls {
+                JobState::Finished
+            } else {
+                JobState::Running
+            })
+        }
+
+        fn report(&self, job: &Job) -> Result<Report, ProverError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("report {}", job.id));
+            Ok(Report {
+                rules: vec![RuleReport {
+                    rule: job.rule.clone(),
+                    contract: None,
+                    method: None,
+                    status: RuleStatus::Verified,
+                    counterexample: None,
+                }],
+            })
+        }
+
+        fn cancel(&self, job: &Job) -> Result<(), ProverError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("cancel {}", job.id));
+            Ok(())
+        }
+    }
+
+    const POLL: Polling = Polling {
+        interval: Duration::ZERO,
+        timeout: None,
+    };
+
+    #[test]
+    fn test_job_urls() {
+        let job = Job::from_url(
+            "https://prover.certora.com/output/123/abc-def/?anonymousKey=xyz",
+            "rule_a",
+            "key",
+        )
+        .unwrap();
+        assert_eq!(job.id, "abc-def");
+        assert_eq!(
+            job.page("jobStatus"),
+            "https://prover.certora.com/jobStatus/123/abc-def/?anonymousKey=xyz"
+        );
+        assert!(Job::from_url("https://prover.certora.com/", "rule_a", "key").is_none());
+        assert_eq!(JobState::parse("succeeded"), JobState::Finished);
+        assert_eq!(JobState::parse("RUNNING"), JobState::Running);
+        assert_eq!(JobState::parse("HALTED"), JobState::Cancelled);
+    }
+
+    #[test]
+    fn test_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Fake::new(2);
+        let result = run(&client, dir.path(), "rule_a", "k1", &POLL, &|| false).unwrap();
+        assert_eq!(result.status, RuleStatus::Verified);
+        assert!(result.output.contains("/output/1/job-rule_a"));
+        assert_eq!(
+            client.calls(),
+            [
+                "submit rule_a",
+                "state job-rule_a",
+                "state job-rule_a",
+                "state job-rule_a",
+                "report job-rule_a"
+            ]
+        );
+        // Done jobs are forgotten
+        assert!(!dir.path().join(JOBS_JSON).exists());
+    }
+
+    #[test]
+    fn test_cancel_and_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Fake::new(usize::MAX);
+        let polls = Cell::new(0);
+        let cancelled = || {
+            polls.set(polls.get() + 1);
+            polls.get() > 2
+        };
+        let err = run(&client, dir.path(), "rule_a", "k1", &POLL, &cancelled).unwrap_err();
+        assert!(matches!(err, ProverError::Cancelled(rule) if rule == "rule_a"));
+        assert_eq!(client.calls().last().unwrap(), "cancel job-rule_a");
+        assert!(pending(dir.path()).is_empty());
+
+        // A job left by an interrupted run is polled again, unless the
+        // inputs of its rule changed
+        let job = client.submit(dir.path(), "rule_b", "k1").unwrap();
+        record(dir.path(), "rule_b", Some(&job)).unwrap();
+        assert_eq!(pending(dir.path())["rule_b"], job);
+        let client = Fake::new(0);
+        run(&client, dir.path(), "rule_b", "k1", &POLL, &|| false).unwrap();
+        assert_eq!(client.calls(), ["state job-rule_b", "report job-rule_b"]);
+        record(dir.path(), "rule_b", Some(&job)).unwrap();
+        let client = Fake::new(0);
+        run(&client, dir.path(), "rule_b", "k2", &POLL, &|| false).unwrap();
+        assert_eq!(client.calls()[0], "submit rule_b");
+    }
+
+    #[test]
+    fn test_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Fake::new(usize::MAX);
+        let polling = Polling {
+            interval: Duration::ZERO,
+            timeout: Some(Duration::ZERO),
+        };
+        let result = run(&client, dir.path(), "rule_a", "k1", &polling, &|| false).unwrap();
+        assert_eq!(result.status, RuleStatus::Timeout);
+        assert!(result
+            .output
+            .starts_with("the job did not finish within 0s"));
+        assert_eq!(
+            client.calls(),
+            ["submit rule_a", "state job-rule_a", "cancel job-rule_a"]
+        );
+        assert!(pending(dir.path()).is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+//! Running Kani on the proof harnesses of an example crate.
+//!
+//! Every rule has a `#[kani::proof]` harness of the same name in
+//! `src/proofs.rs`, so the rules of the certora module can be model checked
+//! locally, without the Certora Prover. Each harness is checked by its own
+//! `cargo kani` run; Kani reports no counterexamples the driver can turn into
+//! regression tests, only the failed checks in its output.
+
+use super::settings::{self, Settings};
+use super::{ProverError, RuleResult, RuleStatus};
+use std::path::Path;
+use std::process::Command;
+
+/// The `cargo kani` command line.
+#[derive(Clone, Debug)]
+pub struct KaniProver {
+    /// The cargo binary Kani is installed as a subcommand of
+    pub cargo: String,
+    /// Flags passed to `cargo kani` after the harness
+    pub args: Vec<String>,
+}
+
+impl Default for KaniProver {
+    fn default() -> KaniProver {
+        KaniProver {
+            cargo: "cargo".to_string(),
+            args: Vec::new(),
+        }
+    }
+}
+
+/// Reads the verdict on the harness from the output of its run. A harness
+/// whose cover is never satisfied holds vacuously, as a failed sanity check
+/// does for the prover.
+fn parse_status(output: &str, success: bool) -> RuleStatus {
+    let mut status = None;
+    for line in output.lines().map(str::trim) {
+        if let Some(verdict) = line.strip_prefix("VERIFICATION:-") {
+            status = Some(if verdict.trim() == "SUCCESSFUL" {
+                RuleStatus::Verified
+            } else {
+                RuleStatus::Violated
+            });
+        }
+        // `** 0 of 1 cover properties satisfied`
+        let covers = line
+            .strip_prefix("** ")
+            .and_then(|rest| rest.strip_suffix(" cover properties satisfied"))
+            .and_then(|rest| rest.split_once(" of "));
+        if let Some((satisfied, total)) = covers {
+            if satisfied != total {
+                return RuleStatus::SanityFailed;
+            }
+        }
+    }
+    match status {
+        Some(status) => status,
+        None if success => RuleStatus::Verified,
+        None => RuleStatus::Error,
+    }
+}
+
+impl KaniProver {
+    /// Checks the harness of `rule` of the crate in `dir`.
+    pub fn prove(&self, dir: &Path, rule: &str) -> Result<RuleResult, ProverError> {
+        let output = Command::new(&self.cargo)
+            .current_dir(dir)
+            .args(["kani", "--harness", rule, "--exact"])
+            .args(&self.args)
+            .args(settings::kani_args(&Settings::read(dir)?.for_rule(rule)))
+            .output()
+            .map_err(|source| match source.kind() {
+                std::io::ErrorKind::NotFound => ProverError::NotInstalled(self.cargo.clone()),
+                _ => ProverError::Io {
+                    path: dir.to_path_buf(),
+                    source,
+                },
+            })?;
+        let text = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        if text.contains("no such command: `kani`") {
+            return Err(ProverError::NotInstalled(format!("{} kani", self.cargo)));
+        }
+        Ok(RuleResult {
+            rule: rule.to_string(),
+            status: parse_status(&text, output.status.success()),
+            output: text,
+            counterexample: None,
+            cached: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let run = |checks: &str, verdict: &str| {
+            format!(
+                "Checking harness rule_deposit_sanity...\n\n\
+                 SUMMARY:\n ** 0 of 12 failed\n{checks}\nVERIFICATION:- {verdict}\n\
+                 Verification Time: 0.5s\n"
+            )
+        };
+        assert_eq!(
+            parse_status(&run("", "SUCCESSFUL"), true),
+            RuleStatus::Verified
+        );
+        assert_eq!(
+            parse_status(&run("", "FAILED"), false),
+            RuleStatus::Violated
+        );
+        assert_eq!(
+            parse_status(
+                &run(" ** 0 of 1 cover properties satisfied", "SUCCESSFUL"),
+                true
+            ),
+            RuleStatus::SanityFailed
+        );
+        assert_eq!(
+            parse_status(
+                &run(" ** 1 of 1 cover properties satisfied", "SUCCESSFUL"),
+                true
+            ),
+            RuleStatus::Verified
+        );
+        assert_eq!(
+            parse_status("error: could not compile", false),
+            RuleStatus::Error
+        );
+    }
+}
@@ -0,0 +1,536 @@
+//! Structured results from the output of a prover run.
+//!
+//! A run leaves its results in `Reports/treeView`: the last
+//! `treeViewStatus_<n>.json` holds the tree of rules with their statuses, and
+//! each violated assertion points to a JSON file with the call trace of its
+//! counterexample. The same tree is found in the output directory of a local
+//! run and in the ZIP archive of a cloud run.
+
+use super::RuleStatus;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const TREE_VIEW_DIR: &str = "Reports/treeView";
+
+/// Call trace nodes that only add noise to a counterexample.
+const SKIPPED_TRACE_NODES: &[&str] = &["Setup", "Global State", "Evaluate branch condition"];
+
+/// An error reading a report.
+#[derive(Debug, thiserror::Error)]
+pub enum ReportError {
+    #[error("no tree view results in {}", .0.display())]
+    NoResults(PathBuf),
+    #[error("failed to read {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to read {}: {source}", .path.display())]
+    Zip {
+        path: PathBuf,
+        source: zip::result::ZipError,
+    },
+    #[error("malformed {file}: {source}")]
+    Malformed {
+        file: String,
+        source: serde_json::Error,
+    },
+}
+
+/// One line of a call trace, with the lines it is made of.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceNode {
+    /// The message with its arguments filled in
+    pub message: String,
+    pub children: Vec<TraceNode>,
+}
+
+impl TraceNode {
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        writeln!(f, "{:width$}{}", "", self.message, width = depth * 2)?;
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn visit<'a>(&'a self, visit: &mut impl FnMut(&'a TraceNode)) {
+        visit(self);
+        for child in &self.children {
+            child.visit(visit);
+        }
+    }
+}
+
+/// A value the prover chose for a variable.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub name: String,
+    pub value: String,
+}
+
+/// The execution violating a rule.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counterexample {
+    pub call_trace: TraceNode,
+    /// The `name = value` lines of the call trace, in trace order
+    pub assignments: Vec<Assignment>,
+}
+
+impl Counterexample {
+    fn new(call_trace: TraceNode) -> Counterexample {
+        let mut assignments = Vec::new();
+        call_trace.visit(&mut |node| {
+            if let Some(assignment) = assignment(&node.message) {
+                assignments.push(assignment);
+            }
+        });
+        Counterexample {
+            call_trace,
+            assignments,
+        }
+    }
+
+    /// The value assigned last to `name`.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.assignments
+            .iter()
+            .rev()
+            .find(|assignment| assignment.name == name)
+            .map(|assignment| assignment.value.as_str())
+    }
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.call_trace.fmt_indented(f, 0)
+    }
+}
+
+/// Reads `name = value` out of a trace message such as
+/// `CVT_nondet_u64: amount = 42`.
+fn assignment(message: &str) -> Option<Assignment> {
+    let (lhs, value) = message.split_once(" = ")?;
+    let name = lhs.rsplit([':', ' ']).next()?;
+    let is_identifier = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.'));
+    is_identifier.then(|| Assignment {
+        name: name.to_string(),
+        value: value.trim().to_string(),
+    })
+}
+
+/// The result of one rule, or of one instantiation of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleReport {
+    pub rule: String,
+    pub contract: Option<String>,
+    pub method: Option<String>,
+    pub status: RuleStatus,
+    /// Present when an assertion was violated
+    pub counterexample: Option<Counterexample>,
+}
+
+impl RuleReport {
+    /// The rule with the contract or method it was checked for.
+    pub fn name(&self) -> String {
+        match (&self.method, &self.contract) {
+            (Some(method), _) => format!("{} for {method}", self.rule),
+            (None, Some(contract)) => format!("{} in contract {contract}", self.rule),
+            (None, None) => self.rule.clone(),
+        }
+    }
+}
+
+/// The results of a prover run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub rules: Vec<RuleReport>,
+}
+
+impl Report {
+    /// The results of `rule`, one per instantiation.
+    pub fn rule<'a>(&'a self, rule: &'a str) -> impl Iterator<Item = &'a RuleReport> {
+        self.rules.iter().filter(move |report| report.rule == rule)
+    }
+
+    /// The overall status of `rule`: its worst instantiation.
+    pub fn status(&self, rule: &str) -> Option<RuleStatus> {
+        self.rule(rule)
+            .map(|report| report.status)
+            .max_by_key(|status| match status {
+                RuleStatus::Verified => 0,
+                RuleStatus::Timeout => 1,
+                RuleStatus::SanityFailed => 2,
+                RuleStatus::Violated => 3,
+                RuleStatus::Error => 4,
+            })
+    }
+
+    /// Whether every rule was verified.
+    pub fn all_verified(&self) -> bool {
+        self.rules
+            .iter()
+            .all(|report| report.status == RuleStatus::Verified)
+    }
+
+    /// Reads the report in the output directory of a local run.
+    pub fn from_dir(dir: &Path) -> Result<Report, ReportError> {
+        let tree_view = dir.join(TREE_VIEW_DIR);
+        let entries =
+            std::fs::read_dir(&tree_view).map_err(|_| ReportError::NoResults(dir.into()))?;
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|source| ReportError::Io {
+                path: tree_view.clone(),
+                source,
+            })?;
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        Report::read(dir, &names, |name| {
+            let path = tree_view.join(name);
+            std::fs::read_to_string(&path).map_err(|source| ReportError::Io { path, source })
+        })
+    }
+
+    /// Reads the report in the ZIP archive of a run, e.g. as downloaded from
+    /// the prover dashboard.
+    pub fn from_zip(path: &Path) -> Result<Report, ReportError> {
+        let zip_error = |source| ReportError::Zip {
+            path: path.to_path_buf(),
+            source,
+        };
+        let file = std::fs::File::open(path).map_err(|source| ReportError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut archive = zip::ZipArchive::new(file).map_err(zip_error)?;
+        // The tree view may be nested below a top-level directory
+        let Some(prefix) = archive.file_names().find_map(|name| {
+            name.find(TREE_VIEW_DIR)
+                .map(|at| name[..at + TREE_VIEW_DIR.len()].to_string())
+        }) else {
+            return Err(ReportError::NoResults(path.to_path_buf()));
+        };
+        let names: Vec<String> = archive
+            .file_names()
+            .filter_map(|name| name.strip_prefix(&prefix)?.strip_prefix('/'))
+            .map(str::to_string)
+            .collect();
+        Report::read(path, &names, |name| {
+            let mut contents = String::new();
+            archive
+                .by_name(&format!("{prefix}/{name}"))
+                .map_err(zip_error)?
+                .read_to_string(&mut contents)
+                .map_err(|source| ReportError::Io {
+                    path: path.join(name),
+                    source,
+                })?;
+            Ok(contents)
+        })
+    }
+
+    /// Reads the report from the files of a tree view directory.
+    fn read(
+        origin: &Path,
+        names: &[String],
+        mut read: impl FnMut(&str) -> Result<String, ReportError>,
+    ) -> Result<Report, ReportError> {
+        // Statuses are written as the run progresses; the last one is final
+        let last = names
+            .iter()
+            .filter_map(|name| {
+                let index = name
+                    .strip_prefix("treeViewStatus_")?
+                    .strip_suffix(".json")?;
+                Some((index.parse::<u64>().ok()?, name))
+            })
+            .max();
+        let Some((_, status_file)) = last else {
+            return Err(ReportError::NoResults(origin.to_path_buf()));
+        };
+        let status: TreeViewStatus = parse(status_file, &read(status_file)?)?;
+        let mut report = Report::default();
+        for root in &status.rules {
+            let path = RuleReport {
+                rule: root.name.clone(),
+                contract: None,
+                method: None,
+                status: RuleStatus::Error,
+                counterexample: None,
+            };
+            flatten(root, path, &mut read, &mut report.rules)?;
+        }
+        Ok(report)
+    }
+}
+
+fn parse<'de, T: Deserialize<'de>>(file: &str, contents: &'de str) -> Result<T, ReportError> {
+    serde_json::from_str(contents).map_err(|source| ReportError::Malformed {
+        file: file.to_string(),
+        source,
+    })
+}
+
+#[derive(Deserialize)]
+struct TreeViewStatus {
+    rules: Vec<RuleNode>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleNode {
+    name: String,
+    #[serde(default)]
+    output: Vec<String>,
+    #[serde(default)]
+    children: Vec<RuleNode>,
+    status: Option<String>,
+    node_type: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CallTraceNode {
+    message: TraceMessage,
+    #[serde(default)]
+    children_list: Vec<CallTraceNode>,
+}
+
+#[derive(Deserialize)]
+struct TraceMessage {
+    text: String,
+    #[serde(default)]
+    arguments: Vec<TraceArgument>,
+}
+
+#[derive(Deserialize)]
+struct TraceArgument {
+    value: String,
+}
+
+impl From<CallTraceNode> for TraceNode {
+    fn from(node: CallTraceNode) -> TraceNode {
+        let mut message = node.message.text;
+        for (index, argument) in node.message.arguments.iter().enumerate() {
+            message = message.replace(&format!("{{{index}}}"), &argument.value);
+        }
+        TraceNode {
+            message,
+            children: node
+                .children_list
+                .into_iter()
+                .filter(|child| !SKIPPED_TRACE_NODES.contains(&child.message.text.as_str()))
+                .map(TraceNode::from)
+                .collect(),
+        }
+    }
+}
+
+fn status(status: Option<&str>) -> RuleStatus {
+    match status {
+        Some("VERIFIED") => RuleStatus::Verified,
+        Some("VIOLATED") => RuleStatus::Violated,
+        Some("TIMEOUT") => RuleStatus::Timeout,
+        Some("SANITY_FAILED") => RuleStatus::SanityFailed,
+        _ => RuleStatus::Error,
+    }
+}
+
+/// Collects the results below `node`, the way the prover dashboard shows
+/// them: one per method or contract instantiation, with the counterexample
+/// of the first violated assertion.
+fn flatten(
+    node: &RuleNode,
+    mut path: RuleReport,
+    read: &mut impl FnMut(&str) -> Result<String, ReportError>,
+    out: &mut Vec<RuleReport>,
+) -> Result<(), ReportError> {
+    match node.node_type.as_str() {
+        "METHOD_INSTANTIATION" => path.method = Some(node.name.clone()),
+        "CONTRACT" => path.contract = Some(node.name.clone()),
+        "INVARIANT_SUBCHECK" if node.name.contains("constructor") => {
+            path.method = Some("constructor".to_string());
+        }
+        _ => {}
+    }
+    path.status = status(node.status.as_deref());
+    let leaf = match path.status {
+        RuleStatus::Error | RuleStatus::SanityFailed => true,
+        RuleStatus::Verified => node
+            .children
+            .iter()
+            .all(|child| child.node_type == "SANITY"),
+        RuleStatus::Timeout | RuleStatus::Violated => node.children.is_empty(),
+    };
+    if leaf {
+        out.push(path);
+        return Ok(());
+    }
+    let violated_assert = node
+        .children
+        .iter()
+        .any(|child| child.node_type == "VIOLATED_ASSERT");
+    if violated_assert && path.status == RuleStatus::Violated {
+        if let Some(file) = node.output.first() {
+            path.counterexample = counterexample(file, &read(file)?)?;
+        }
+        out.push(path);
+        return Ok(());
+    }
+    for child in &node.children {
+        flatten(child, path.clone(), read, out)?;
+    }
+    Ok(())
+}
+
+/// Reads the counterexample from the output file of a violated rule.
+fn counterexample(file: &str, contents: &str) -> Result<Option<Counterexample>, ReportError> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Output {
+        call_trace: Option<CallTraceNode>,
+    }
+    let output: Output = parse(file, contents)?;
+    Ok(output
+        .call_trace
+        .map(|trace| Counterexample::new(trace.into())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const STATUS: &str = r#"{"rules": [
+        {"name": "rule_deposit", "nodeType": "ROOT", "status": "VERIFIED", "output": [],
+         "children": [{"name": "sanity", "nodeType": "SANITY", "status": "VERIFIED",
+                       "output": [], "children": []}]},
+        {"name": "rule_withdraw", "nodeType": "ROOT", "status": "VIOLATED",
+         "output": ["rule_withdraw.json"],
+         "children": [{"name": "assert", "nodeType": "VIOLATED_ASSERT", "status": "VIOLATED",
+                       "output": [], "children": []}]},
+        {"name": "rule_reward", "nodeType": "ROOT", "status": "TIMEOUT", "output": [],
+         "children": []}
+    ]}"#;
+
+    const OUTPUT: &str = r#"{"callTrace": {
+        "message": {"text": "rule_withdraw", "arguments": []},
+        "childrenList": [
+            {"message": {"text": "Setup", "arguments": []}, "childrenList": []},
+            {"message": {"text": "CVT_nondet_u64: {0} = {1}",
+                         "arguments": [{"value": "amount"}, {"value": "7"}]},
+             "childrenList": []},
+            {"message": {"text": "assert shares_total >= amount", "arguments": []},
+             "childrenList": []}
+        ]
+    }}"#;
+
+    fn write_tree_view(dir: &Path) {
+        let tree_view = dir.join(TREE_VIEW_DIR);
+        std::fs::create_dir_all(&tree_view).unwrap();
+        std::fs::write(tree_view.join("treeViewStatus_0.json"), r#"{"rules": []}"#).unwrap();
+        std::fs::write(tree_view.join("treeViewStatus_12.json"), STATUS).unwrap();
+        std::fs::write(tree_view.join("treeViewStatus_9.json"), r#"{"rules": []}"#).unwrap();
+        std::fs::write(tree_view.join("rule_withdraw.json"), OUTPUT).unwrap();
+    }
+
+    fn check(report: &Report) {
+        assert_eq!(report.status("rule_deposit"), Some(RuleStatus::Verified));
+        assert_eq!(report.status("rule_withdraw"), Some(RuleStatus::Violated));
+        assert_eq!(report.status("rule_reward"), Some(RuleStatus::Timeout));
+        assert!(!report.all_verified());
+        let counterexample = report
+            .rule("rule_withdraw")
+            .next()
+            .unwrap()
+            .counterexample
+            .as_ref()
+            .unwrap();
+        assert_eq!(counterexample.value("amount"), Some("7"));
+        assert_eq!(
+            counterexample.to_string(),
+            "rule_withdraw\n  CVT_nondet_u64: amount = 7\n  assert shares_total >= amount\n"
+        );
+    }
+
+    #[test]
+    fn test_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        write_tree_view(dir.path());
+        check(&Report::from_dir(dir.path()).unwrap());
+        assert!(matches!(
+            Report::from_dir(&dir.path().join("missing")),
+            Err(ReportError::NoResults(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, contents) in [
+            ("treeViewStatus_3.json", STATUS),
+            ("rule_withdraw.json", OUTPUT),
+        ] {
+            zip.start_file(format!("emv-1/{TREE_VIEW_DIR}/{name}"), options)
+                .unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        check(&Report::from_zip(&path).unwrap());
+    }
+
+    #[test]
+    fn test_instantiations() {
+        let status = r#"{"rules": [{"name": "inv", "nodeType": "ROOT", "status": "VIOLATED",
+            "output": [], "children": [
+                {"name": "deposit()", "nodeType": "METHOD_INSTANTIATION", "status": "VERIFIED",
+                 "output": [], "children": []},
+                {"name": "withdraw()", "nodeType": "METHOD_INSTANTIATION", "status": "VIOLATED",
+                 "output": ["w.json"], "children": [
+                    {"name": "a", "nodeType": "VIOLATED_ASSERT", "status": "VIOLATED",
+                     "output": [], "children": []}]}
+            ]}]}"#;
+        let names = ["treeViewStatus_1.json".to_string(), "w.json".to_string()];
+        let report = Report::read(Path::new("run"), &names, |name| {
+            Ok(match name {
+                "w.json" => "{}".to_string(),
+                _ => status.to_string(),
+            })
+        })
+        .unwrap();
+        let names: Vec<_> = report
+            .rules
+            .iter()
+            .map(|rule| (rule.name(), rule.status))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("inv for deposit()".to_string(), RuleStatus::Verified),
+                ("inv for withdraw()".to_string(), RuleStatus::Violated),
+            ]
+        );
+        assert_eq!(report.status("inv"), Some(RuleStatus::Violated));
+    }
+
+    #[test]
+    fn test_assignment() {
+        assert_eq!(
+            assignment("vault.shares_total = 100"),
+            Some(Assignment {
+                name: "vault.shares_total".to_string(),
+                value: "100".to_string(),
+            })
+        );
+        assert_eq!(assignment("assert a == b"), None);
+    }
+}
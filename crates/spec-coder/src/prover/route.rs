@@ -0,0 +1,229 @@
+//! Choosing between the local prover and the cloud, per rule.
+//!
+//! The [`Router`] sends the rules matching its cloud patterns, e.g. the
+//! large ones, to the Certora [cloud](super::cloud) and proves the others
+//! with the local prover. Which of the two can run is detected once per
+//! run: the local prover needs its CLI on `PATH`, the cloud a key as well.
+//! A rule whose prover cannot run goes to the other one, as does a rule
+//! whose cloud job could not be submitted or polled.
+
+use super::cloud::CloudProver;
+use super::{find_tags, matches_pattern, ProverError, RuleResult, SolanaProver};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+/// Where a rule is proved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Route {
+    Local,
+    Cloud,
+}
+
+impl std::fmt::Display for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Route::Local => "the local prover",
+            Route::Cloud => "the cloud",
+        })
+    }
+}
+
+/// Which provers can run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    pub local: bool,
+    pub cloud: bool,
+}
+
+impl Capabilities {
+    /// Whether the CLI of `local` is installed, and that of `cloud` with a
+    /// key.
+    pub fn detect(local: &SolanaProver, cloud: &CloudProver) -> Capabilities {
+        Capabilities {
+            local: on_path(&local.cli),
+            cloud: on_path(&cloud.cli) && cloud.key().is_some(),
+        }
+    }
+}
+
+/// Whether `program` can be run: a path to a file, or a file in a
+/// directory of `PATH`.
+pub fn on_path(program: &str) -> bool {
+    if program.contains(std::path::MAIN_SEPARATOR) {
+        return Path::new(program).is_file();
+    }
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// Proves each rule locally or in the cloud.
+#[derive(Clone, Debug, Default)]
+pub struct Router {
+    pub local: SolanaProver,
+    pub cloud: CloudProver,
+    /// The rules to prove in the cloud, as patterns of their names or tags
+    /// of the crate, see [`find_tags`]
+    pub cloud_rules: Vec<String>,
+    /// Detected on the first rule, and shared with the clones of the router
+    /// proving the others
+    capabilities: Arc<OnceLock<Capabilities>>,
+}
+
+impl Router {
+    /// Where `rule` of the crate in `dir` would be proved, were both provers
+    /// able to run.
+    pub fn preferred(&self, dir: &Path, rule: &str) -> Result<Route, ProverError> {
+        let tags = find_tags(dir)?;
+        let cloud = self.cloud_rules.iter().any(|pattern| {
+            matches_pattern(pattern, rule)
+                || tags.get(pattern).is_some_and(|patterns| {
+                    patterns
+                        .iter()
+                        .any(|pattern| matches_pattern(pattern, rule))
+                })
+        });
+        Ok(if cloud { Route::Cloud } else { Route::Local })
+    }
+
+    /// Where `rule` of the crate in `dir` is proved given `capabilities`:
+    /// its preferred prover if it can run, else the other one.
+    pub fn route(
+        &self,
+        dir: &Path,
+        rule: &str,
+        capabilities: Capabilities,
+    ) -> Result<Route, ProverError> {
+        match (self.preferred(dir, rule)?, capabilities) {
+            (Route::Local, Capabilities { local: true, .. }) => Ok(Route::Local),
+            (Route::Cloud, Capabilities { cloud: true, .. }) => Ok(Route::Cloud),
+            (_, Capabilities { local: true, .. }) => Ok(Route::Local),
+            (_, Capabilities { cloud: true, .. }) => Ok(Route::Cloud),
+            _ if on_path(&self.cloud.cli) => Err(ProverError::Cloud(format!(
+                "{} is installed but has no key to reach the cloud; set `cloud_key` under \
+                 [prover] or {}",
+                self.cloud.cli,
+                super::cloud::KEY_ENV
+            ))),
+            _ => Err(ProverError::NotInstalled(self.local.cli.clone())),
+        }
+    }
+
+    /// Proves `rule` of the crate in `dir`, whose inputs have `key`.
+    pub fn prove(&self, dir: &Path, rule: &str, key: &str) -> Result<RuleResult, ProverError> {
+        let capabilities = *self
+            .capabilities
+            .get_or_init(|| Capabilities::detect(&self.local, &self.cloud));
+        self.prove_with(dir, rule, key, capabilities)
+    }
+
+    fn prove_with(
+        &self,
+        dir: &Path,
+        rule: &str,
+        key: &str,
+        capabilities: Capabilities,
+    ) -> Result<RuleResult, ProverError> {
+        match self.route(dir, rule, capabilities)? {
+            Route::Local => self.local.prove(dir, rule),
+            Route::Cloud => match self.cloud.prove(dir, rule, key) {
+                Err(ProverError::Cancelled(rule)) => Err(ProverError::Cancelled(rule)),
+                Err(err) if capabilities.local => {
+                    let mut result = self.local.prove(dir, rule)?;
+                    result.output = format!(
+                        "{} failed, proved by {} instead: {err}\n{}",
+                        Route::Cloud,
+                        Route::Local,
+                        result.output
+                    );
+                    Ok(result)
+                }
+                result => result,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::RuleStatus;
+    use std::os::unix::fs::PermissionsExt;
+
+    const BOTH: Capabilities = Capabilities {
+        local: true,
+        cloud: true,
+    };
+
+    #[test]
+    fn test_route() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"vault\"\n\n[package.metadata.certora.tags]\n\
+             slow = [\"rule_*_solvency\"]\n",
+        )
+        .unwrap();
+        let router = Router {
+            cloud: CloudProver {
+                cli: dir.path().join("missing").display().to_string(),
+                ..CloudProver::default()
+            },
+            cloud_rules: vec!["slow".to_string(), "rule_big".to_string()],
+            ..Router::default()
+        };
+        let route = |rule, capabilities| router.route(dir.path(), rule, capabilities).unwrap();
+        assert_eq!(route("rule_big", BOTH), Route::Cloud);
+        assert_eq!(route("rule_vault_solvency", BOTH), Route::Cloud);
+        assert_eq!(route("rule_deposit", BOTH), Route::Local);
+
+        // Rules go to the other prover when theirs cannot run
+        let local = Capabilities {
+            local: true,
+            cloud: false,
+        };
+        assert_eq!(route("rule_big", local), Route::Local);
+        let cloud = Capabilities {
+            local: false,
+            cloud: true,
+        };
+        assert_eq!(route("rule_deposit", cloud), Route::Cloud);
+        let none = Capabilities {
+            local: false,
+            cloud: false,
+        };
+        assert!(matches!(
+            router.route(dir.path(), "rule_deposit", none),
+            Err(ProverError::NotInstalled(cli)) if cli == "certoraSolanaProver"
+        ));
+    }
+
+    #[test]
+    fn test_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let cli = dir.path().join("prover.sh");
+        std::fs::write(&cli, "#!/bin/sh\necho \"$2: VERIFIED\"\n").unwrap();
+        std::fs::set_permissions(&cli, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let router = Router {
+            local: SolanaProver {
+                cli: cli.display().to_string(),
+                prover_args: Vec::new(),
+            },
+            cloud: CloudProver {
+                cli: dir.path().join("missing").display().to_string(),
+                ..CloudProver::default()
+            },
+            cloud_rules: vec!["*".to_string()],
+            ..Router::default()
+        };
+        assert!(on_path(&router.local.cli));
+        assert!(!on_path(&router.cloud.cli));
+        assert!(on_path("sh"));
+
+        // The cloud job cannot be submitted, so the rule is proved locally
+        let result = router.prove_with(dir.path(), "rule_a", "k", BOTH).unwrap();
+        assert_eq!(result.status, RuleStatus::Verified);
+        assert!(result
+            .output
+            .starts_with("the cloud failed, proved by the local prover instead"));
+    }
+}
@@ -0,0 +1,174 @@
+//! Prover settings per rule.
+//!
+//! One global setting either times out complex rules or wastes time on
+//! simple ones, so loop bounds, the arithmetic and the solver timeout may be
+//! set per rule in [`RULES_TOML`], keyed by rule name or pattern. The file is
+//! written from the `prover` section of the spec when the crate is
+//! materialized, or by hand in crates without one. The settings of every
+//! key matching a rule are merged, its exact name last, and added to the
+//! command line proving it.
+
+use super::{matches_pattern, ProverError};
+use spec::{Arithmetic, ProverSettings};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The per-rule settings of a crate, relative to its root.
+pub const RULES_TOML: &str = "certora/rules.toml";
+
+/// The settings of a crate, by rule name or pattern.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Settings(pub BTreeMap<String, ProverSettings>);
+
+impl Settings {
+    /// Reads the settings of the crate in `dir`; none if it has no
+    /// [`RULES_TOML`].
+    pub fn read(dir: &Path) -> Result<Settings, ProverError> {
+        let path = dir.join(RULES_TOML);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Settings::default())
+            }
+            Err(source) => return Err(ProverError::Io { path, source }),
+        };
+        toml::from_str(&text)
+            .map(Settings)
+            .map_err(|err| ProverError::Settings {
+                path,
+                message: err.to_string(),
+            })
+    }
+
+    /// The settings of `rule`: those of the patterns matching it, in name
+    /// order, then those of its name.
+    pub fn for_rule(&self, rule: &str) -> ProverSettings {
+        let mut settings = ProverSettings::default();
+        for (pattern, other) in &self.0 {
+            if pattern != rule && matches_pattern(pattern, rule) {
+                settings.merge(other);
+            }
+        }
+        if let Some(other) = self.0.get(rule) {
+            settings.merge(other);
+        }
+        settings
+    }
+}
+
+/// The contents of [`RULES_TOML`] for the `prover` section of a spec.
+pub fn rules_toml(settings: &BTreeMap<String, ProverSettings>) -> String {
+    let mut out = String::new();
+    for (pattern, settings) in settings {
+        let table = toml::to_string(settings).expect("settings serialize");
+        out.push_str(&format!("[{}]\n{table}\n", toml_key(pattern)));
+    }
+    out
+}
+
+/// `key` quoted unless it is a bare TOML key.
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        key.to_string()
+    } else {
+        format!("{key:?}")
+    }
+}
+
+/// The `certoraSolanaProver` flags of `settings`.
+pub fn certora_args(settings: &ProverSettings) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(loop_iter) = settings.loop_iter {
+        args.extend(["--loop_iter".to_string(), loop_iter.to_string()]);
+    }
+    if settings.arithmetic == Some(Arithmetic::Bitvector) {
+        args.push("--precise_bitwise_ops".to_string());
+    }
+    if let Some(smt_timeout) = settings.smt_timeout {
+        args.extend(["--smt_timeout".to_string(), smt_timeout.to_string()]);
+    }
+    args
+}
+
+/// The `cargo kani` flags of `settings`; Kani always reasons on bitvectors
+/// and has no solver timeout.
+pub fn kani_args(settings: &ProverSettings) -> Vec<String> {
+    settings
+        .loop_iter
+        .map(|loop_iter| vec!["--default-unwind".to_string(), loop_iter.to_string()])
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings() {
+        let spec = BTreeMap::from([
+            (
+                "rule_*".to_string(),
+                ProverSettings {
+                    loop_iter: Some(2),
+                    smt_timeout: Some(300),
+                    ..ProverSettings::default()
+                },
+            ),
+            (
+                "rule_*_solvency".to_string(),
+                ProverSettings {
+                    arithmetic: Some(Arithmetic::Bitvector),
+                    smt_timeout: Some(1800),
+                    ..ProverSettings::default()
+                },
+            ),
+            (
+                "rule_withdraw_solvency".to_string(),
+                ProverSettings {
+                    loop_iter: Some(8),
+                    ..ProverSettings::default()
+                },
+            ),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Settings::read(dir.path()).unwrap(), Settings::default());
+        std::fs::create_dir_all(dir.path().join("certora")).unwrap();
+        let toml = rules_toml(&spec);
+        assert!(toml.contains("[rule_withdraw_solvency]\nloop_iter = 8\n"));
+        assert!(toml.contains("[\"rule_*_solvency\"]\narithmetic = \"bitvector\"\n"));
+        std::fs::write(dir.path().join(RULES_TOML), toml).unwrap();
+        let settings = Settings::read(dir.path()).unwrap();
+        assert_eq!(settings.0, spec);
+
+        assert_eq!(
+            certora_args(&settings.for_rule("rule_withdraw_solvency")),
+            [
+                "--loop_iter",
+                "8",
+                "--precise_bitwise_ops",
+                "--smt_timeout",
+                "1800"
+            ]
+        );
+        assert_eq!(
+            certora_args(&settings.for_rule("rule_deposit_sanity")),
+            ["--loop_iter", "2", "--smt_timeout", "300"]
+        );
+        assert_eq!(
+            kani_args(&settings.for_rule("rule_withdraw_solvency")),
+            ["--default-unwind", "8"]
+        );
+        assert!(certora_args(&settings.for_rule("other")).is_empty());
+
+        std::fs::write(dir.path().join(RULES_TOML), "[rule_a]\nloop_bound = 2\n").unwrap();
+        let err = Settings::read(dir.path()).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `loop_bound`"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,303 @@
+//! The registry of materialized examples.
+//!
+//! Every crate under `examples/<target>/materialized_<name>` is an example.
+//! `spec-coder registry` lists them in [`EXAMPLES_TOML`] at the project
+//! root with what tooling and docs need to know about each: its target, the
+//! spec it is materialized from, if it is under `examples/specs`, and the
+//! version of the spec, if it has one, where its requirements come from, if
+//! the spec names a source, the names of its rules and whether it is
+//! verified. [`Registry`] reads the file back, or scans the examples
+//! directly.
+//!
+//! ```toml
+//! [[example]]
+//! name = "vault"
+//! target = "svm"
+//! dir = "examples/svm/materialized_vault"
+//! spec = "examples/specs/vault.yaml"
+//! spec_version = "1.0.0"
+//! rules = ["rule_vault_solvency_withdraw", "rule_vault_solvency_deposit"]
+//! status = "unverified"
+//! ```
+
+use crate::drift::{self, DriftError, VERIFIED_JSON};
+use crate::migrate::{self, MigrateError};
+use crate::prover::{self, ProverError};
+use serde::{Deserialize, Serialize};
+use spec::{Spec, SpecError, Target, Version};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The registry, relative to the project root.
+pub const EXAMPLES_TOML: &str = "examples.toml";
+
+/// The prefix of the directories of materialized examples.
+const PREFIX: &str = "materialized_";
+
+/// The extensions of spec files, in the order they are looked for.
+const SPEC_EXTENSIONS: &[&str] = &["yaml", "yml", "toml", "md"];
+
+/// An error reading or writing the registry.
+#[derive(Debug, thiserror::Error)]
+pub enum RegistryError {
+    #[error("failed to access {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("malformed {}: {source}", .path.display())]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error(transparent)]
+    Prover(#[from] ProverError),
+    #[error(transparent)]
+    Drift(#[from] DriftError),
+    #[error(transparent)]
+    Migrate(#[from] MigrateError),
+}
+
+/// Whether an example passed verification.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// Verified, and neither the code nor the spec changed since
+    Verified,
+    /// Verified, but the code or the spec changed since
+    Drifted,
+    /// Never verified by `implement`
+    Unverified,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Verified => "verified",
+            Status::Drifted => "drifted",
+            Status::Unverified => "unverified",
+        })
+    }
+}
+
+/// A materialized example.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Example {
+    pub name: String,
+    pub target: Target,
+    /// The crate directory
+    pub dir: PathBuf,
+    /// The spec the crate is materialized from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spec: Option<PathBuf>,
+    /// The version of the spec the crate was last materialized from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spec_version: Option<Version>,
+    /// Where the requirements of the spec come from, e.g. an issue
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// The names of the rules under `src/certora`
+    #[serde(default)]
+    pub rules: Vec<String>,
+    pub status: Status,
+}
+
+/// The examples, by target and then name.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Registry {
+    #[serde(default, rename = "example")]
+    pub examples: Vec<Example>,
+}
+
+fn io(path: &Path) -> impl FnOnce(std::io::Error) -> RegistryError + '_ {
+    move |source| RegistryError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// The sorted entries of `dir`.
+fn entries(dir: &Path) -> Result<Vec<PathBuf>, RegistryError> {
+    let mut entries = std::fs::read_dir(dir)
+        .map_err(io(dir))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io(dir))?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// The spec of the example `name` under `<examples_dir>/specs`, if any.
+fn spec_path(examples_dir: &Path, name: &str) -> Option<PathBuf> {
+    SPEC_EXTENSIONS
+        .iter()
+        .map(|ext| examples_dir.join("specs").join(format!("{name}.{ext}")))
+        .find(|path| path.is_file())
+}
+
+/// The status of the crate in `dir`, checked for drift against the spec at
+/// `spec` if there is one.
+fn status(dir: &Path, spec: Option<&Path>) -> Result<Status, RegistryError> {
+    if !dir.join(VERIFIED_JSON).exists() {
+        return Ok(Status::Unverified);
+    }
+    let Some(spec) = spec else {
+        return Ok(Status::Verified);
+    };
+    let drifts = drift::check(&Spec::from_path(spec)?, dir)?;
+    Ok(match drifts.is_empty() {
+        true => Status::Verified,
+        false => Status::Drifted,
+    })
+}
+
+impl Registry {
+    /// Lists the examples materialized under `examples_dir`.
+    pub fn scan(examples_dir: &Path) -> Result<Registry, RegistryError> {
+        let mut examples = Vec::new();
+        for target_dir in entries(examples_dir)? {
+            let Some(target) = target_dir
+                .file_name()
+                .and_then(|name| name.to_str()?.parse::<Target>().ok())
+            else {
+                continue;
+            };
+            if !target_dir.is_dir() {
+                continue;
+            }
+            for dir in entries(&target_dir)? {
+                let Some(name) = dir
+                    .file_name()
+                    .and_then(|name| name.to_str()?.strip_prefix(PREFIX))
+                else {
+                    continue;
+                };
+                if !dir.join("Cargo.toml").is_file() {
+                    continue;
+                }
+                let spec = spec_path(examples_dir, name);
+                examples.push(Example {
+                    name: name.to_string(),
+                    target,
+                    rules: prover::find_rules(&dir)?,
+                    status: status(&dir, spec.as_deref())?,
+                    spec,
+                    spec_version: migrate::recorded(&dir)?,
+                    source: migrate::recorded_source(&dir)?,
+                    dir,
+                });
+            }
+        }
+        Ok(Registry { examples })
+    }
+
+    /// Reads the registry at `path`.
+    pub fn load(path: &Path) -> Result<Registry, RegistryError> {
+        let text = std::fs::read_to_string(path).map_err(io(path))?;
+        toml::from_str(&text).map_err(|source| RegistryError::Toml {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut out = String::from(
+            "# The materialized examples; regenerate with `cargo run -p spec-coder -- registry`.\n\n",
+        );
+        out.push_str(&toml::to_string(self).expect("the registry serializes"));
+        out
+    }
+
+    /// Writes the registry to `path`.
+    pub fn save(&self, path: &Path) -> Result<(), RegistryError> {
+        std::fs::write(path, self.to_toml()).map_err(io(path))
+    }
+
+    /// The example called `name`, of the first target listing it.
+    pub fn get(&self, name: &str) -> Option<&Example> {
+        self.examples.iter().find(|example| example.name == name)
+    }
+
+    /// The examples of `target`.
+    pub fn by_target(&self, target: Target) -> impl Iterator<Item = &Example> {
+        self.examples
+            .iter()
+            .filter(move |example| example.target == target)
+    }
+
+    /// The examples with `status`.
+    pub fn with_status(&self, status: Status) -> impl Iterator<Item = &Example> {
+        self.examples
+            .iter()
+            .filter(move |example| example.status == status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materialize;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_scan() {
+        let examples = tempfile::tempdir().unwrap();
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        std::fs::create_dir_all(examples.path().join("specs")).unwrap();
+        std::fs::write(examples.path().join("specs/vault.yaml"), VAULT).unwrap();
+        let vault = materialize::crate_dir(examples.path(), &spec, Target::Svm);
+        materialize::write(
+            &materialize::generate(&spec, Target::Svm).unwrap(),
+            &vault,
+            false,
+        )
+        .unwrap();
+        let counter = examples.path().join("rust/materialized_counter");
+        std::fs::create_dir_all(&counter).unwrap();
+        std::fs::write(counter.join("Cargo.toml"), "[package]\n").unwrap();
+        // Neither examples nor targets
+        std::fs::create_dir_all(examples.path().join("svm/scratch")).unwrap();
+        std::fs::create_dir_all(examples.path().join("cccp/materialized_pool")).unwrap();
+
+        let registry = Registry::scan(examples.path()).unwrap();
+        let names: Vec<_> = registry
+            .examples
+            .iter()
+            .map(|example| (example.target, example.name.as_str()))
+            .collect();
+        assert_eq!(names, [(Target::Rust, "counter"), (Target::Svm, "vault")]);
+        let example = registry.get("vault").unwrap();
+        assert_eq!(example.dir, vault);
+        assert_eq!(example.spec, Some(examples.path().join("specs/vault.yaml")));
+        assert!(example.rules.contains(&"rule_deposit_solvency".to_string()));
+        assert_eq!(example.status, Status::Unverified);
+        assert_eq!(example.spec_version, None);
+        assert_eq!(registry.get("counter").unwrap().spec, None);
+        assert_eq!(registry.by_target(Target::Svm).count(), 1);
+
+        drift::record(&spec, &vault).unwrap();
+        let versioned = Spec {
+            version: Some(Version::new(1, 2, 0)),
+            source: Some("https://github.com/acme/vault/issues/7".to_string()),
+            ..spec.clone()
+        };
+        migrate::record(&versioned, &vault).unwrap();
+        let registry = Registry::scan(examples.path()).unwrap();
+        assert_eq!(registry.get("vault").unwrap().status, Status::Verified);
+        assert_eq!(
+            registry.get("vault").unwrap().spec_version,
+            versioned.version
+        );
+        assert_eq!(registry.get("vault").unwrap().source, versioned.source);
+        assert_eq!(registry.with_status(Status::Verified).count(), 1);
+
+        let path = examples.path().join(EXAMPLES_TOML);
+        registry.save(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("[[example]]\nname = \"counter\"\ntarget = \"rust\"\n"));
+        assert_eq!(Registry::load(&path).unwrap(), registry);
+    }
+}
@@ -0,0 +1,222 @@
+//! Regression tests from counterexamples.
+//!
+//! When the prover violates a rule, the counterexample is turned into a plain
+//! `#[test]` in `src/regressions.rs` of the example crate: the accounts are
+//! set up with the field values of the counterexample, the instruction is
+//! called with its arguments, and a successful call must establish the
+//! postconditions of the function. The failure then stays covered by
+//! `cargo test` without running the prover again.
+
+use crate::materialize;
+use crate::materialize::svm::harness::{self, Postconditions};
+use crate::materialize::svm::{native_int, Framework};
+use crate::prover::report::Counterexample;
+use spec::{Function, Spec, Target, Type};
+use std::path::Path;
+
+/// The module holding the tests, relative to the crate root.
+pub const REGRESSIONS_RS: &str = "src/regressions.rs";
+
+const HEADER: &str = "\
+//! Regression tests reproducing counterexamples found by the prover.
+
+use solana_program::{account_info::AccountInfo, pubkey::Pubkey};
+";
+
+/// A generated test.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegressionTest {
+    /// The test function name
+    pub name: String,
+    pub code: String,
+}
+
+/// The function a rule checks: rules are named `rule_<function>` or
+/// `rule_<function>_<property>`.
+pub(crate) fn rule_function<'a>(spec: &'a Spec, rule: &str) -> Option<&'a Function> {
+    let rest = rule.strip_prefix("rule_")?;
+    spec.functions
+        .iter()
+        .filter(|function| {
+            rest == function.name
+                || rest
+                    .strip_prefix(function.name.as_str())
+                    .is_some_and(|suffix| suffix.starts_with('_'))
+        })
+        .max_by_key(|function| function.name.len())
+}
+
+/// Reads an integer the prover printed, e.g. `42`, `-1` or `0x2a`.
+fn integer(value: &str) -> Option<i128> {
+    let value = value.split_whitespace().next()?;
+    match value.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// The value of `name` in the counterexample as a literal of `ty`.
+fn literal(counterexample: &Counterexample, name: &str, ty: &Type) -> Option<String> {
+    let value = counterexample.value(name)?;
+    if *ty == Type::Bool {
+        return Some(match value {
+            "true" => "1".to_string(),
+            "false" => "0".to_string(),
+            _ => u8::from(integer(value)? != 0).to_string(),
+        });
+    }
+    let native = native_int(ty)?;
+    let value = integer(value)?;
+    Some(format!("{value}{native}"))
+}
+
+/// Generates the test reproducing `counterexample` to `rule`, or `None` if
+/// the rule does not check a single function. Counterexamples are only
+/// replayed for Solana programs, whose accounts the prover prints.
+pub fn generate(
+    spec: &Spec,
+    rule: &str,
+    counterexample: &Counterexample,
+) -> Option<RegressionTest> {
+    if spec.target == Target::Rust {
+        return None;
+    }
+    let function = rule_function(spec, rule)?;
+    let name = format!("test_{}", rule.strip_prefix("rule_").unwrap_or(rule));
+    let framework = Framework::of(spec.target);
+    let mut code = format!(
+        "\n/// Reproduces the counterexample to `{rule}`.\n#[test]\nfn {name}() {{\n    \
+         let program_id = {};\n",
+        harness::program_id(framework)
+    );
+    let postconditions = Postconditions::new(spec, function, framework);
+    harness::call(
+        &mut code,
+        spec,
+        function,
+        framework,
+        |name, ty| literal(counterexample, name, ty),
+        &postconditions.before,
+        "",
+    );
+    if postconditions.checks.is_empty() {
+        code.push_str("\n    // The rule requires the call to succeed\n    result.unwrap();\n}\n");
+        return Some(RegressionTest { name, code });
+    }
+    code.push_str("\n    // A successful call must establish the postconditions\n");
+    code.push_str("    if result.is_ok() {\n");
+    postconditions.assert(&mut code, function, framework, "        ");
+    code.push_str("    }\n}\n");
+    Some(RegressionTest { name, code })
+}
+
+/// Adds `test` to the crate in `dir` unless a test of that name exists,
+/// declaring the module in `src/lib.rs` if needed. Returns whether the test
+/// was added.
+pub fn add(dir: &Path, test: &RegressionTest) -> std::io::Result<bool> {
+    let path = dir.join(REGRESSIONS_RS);
+    let mut contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => HEADER.to_string(),
+        Err(err) => return Err(err),
+    };
+    if contents.contains(&format!("fn {}()", test.name)) {
+        return Ok(false);
+    }
+    contents.push_str(&test.code);
+    std::fs::write(&path, contents)?;
+
+    materialize::declare_module(&dir.join("src/lib.rs"), "test", "regressions")?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::report::{Assignment, TraceNode};
+
+    fn spec() -> Spec {
+        Spec::from_yaml_str(include_str!("../../../examples/specs/vault.yaml")).unwrap()
+    }
+
+    fn counterexample(assignments: &[(&str, &str)]) -> Counterexample {
+        Counterexample {
+            call_trace: TraceNode {
+                message: "rule".to_string(),
+                children: Vec::new(),
+            },
+            assignments: assignments
+                .iter()
+                .map(|(name, value)| Assignment {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_rule_function() {
+        let spec = spec();
+        assert_eq!(
+            rule_function(&spec, "rule_withdraw_sanity").map(|f| f.name.as_str()),
+            Some("withdraw")
+        );
+        assert_eq!(
+            rule_function(&spec, "rule_deposit").map(|f| f.name.as_str()),
+            Some("deposit")
+        );
+        assert!(rule_function(&spec, "rule_withdrawal").is_none());
+        assert!(rule_function(&spec, "solvency").is_none());
+    }
+
+    #[test]
+    fn test_generate() {
+        let spec = spec();
+        let cex = counterexample(&[
+            ("shares", "7"),
+            ("vault.shares_total", "0x64"),
+            ("vault.token_total", "100"),
+        ]);
+        let test = generate(&spec, "rule_withdraw_sanity", &cex).unwrap();
+        assert_eq!(test.name, "test_withdraw_sanity");
+        for line in [
+            "let shares = 7u64;",
+            "vault.shares_total = 100u64.into();",
+            "let old_vault = vault;",
+            "let result = crate::processor::process_withdraw(&accounts, &shares.to_le_bytes());",
+            "if result.is_ok() {",
+        ] {
+            assert!(
+                test.code.contains(line),
+                "missing `{line}` in\n{}",
+                test.code
+            );
+        }
+        assert!(generate(&spec, "rule_solvency", &cex).is_none());
+    }
+
+    #[test]
+    fn test_add() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("src/lib.rs"),
+            "pub mod processor;\npub mod state;\n\nfn f() {}\n",
+        )
+        .unwrap();
+        let test = RegressionTest {
+            name: "test_x".to_string(),
+            code: "\n#[test]\nfn test_x() {}\n".to_string(),
+        };
+        assert!(add(dir.path(), &test).unwrap());
+        assert!(!add(dir.path(), &test).unwrap());
+        let lib = std::fs::read_to_string(dir.path().join("src/lib.rs")).unwrap();
+        assert_eq!(
+            lib,
+            "pub mod processor;\npub mod state;\n\n#[cfg(test)]\nmod regressions;\n\nfn f() {}\n"
+        );
+        let regressions = std::fs::read_to_string(dir.path().join(REGRESSIONS_RS)).unwrap();
+        assert_eq!(regressions.matches("fn test_x()").count(), 1);
+    }
+}
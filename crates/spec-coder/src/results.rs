@@ -0,0 +1,584 @@
+//! Machine-readable verification results.
+//!
+//! The verdicts of the last check are written as JSON and as SARIF 2.1.0, so
+//! CI systems and code-review tools can annotate the rules that failed and
+//! the processor functions they check, and as JUnit XML, with a test case
+//! per rule, for the test reports of CI dashboards. Each rule is located at its `#[rule]`
+//! function under `src/certora` and, when named after a spec function, at
+//! the processor function implementing it. The [overview] gathers the
+//! results of every example.
+
+pub mod overview;
+
+use crate::drift;
+use crate::prover::{self, cache, ProverError, RuleStatus};
+use crate::regression;
+use serde::{Deserialize, Serialize};
+use spec::Spec;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The JSON results, relative to the crate root.
+pub const RESULTS_JSON: &str = ".spec-coder/results.json";
+
+/// The SARIF results, relative to the crate root.
+pub const RESULTS_SARIF: &str = ".spec-coder/results.sarif";
+
+/// The JUnit XML results, relative to the crate root.
+pub const RESULTS_JUNIT: &str = ".spec-coder/results.xml";
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// An error collecting or writing results.
+#[derive(Debug, thiserror::Error)]
+pub enum ResultsError {
+    #[error(transparent)]
+    Prover(#[from] ProverError),
+    #[error("failed to access {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("malformed results {}: {source}", .path.display())]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+/// The verdict on a rule, as CI systems see it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Pass,
+    Fail,
+    Timeout,
+    Error,
+    /// The rule was not proved, e.g. for lack of prover jobs
+    NotRun,
+}
+
+impl From<Option<RuleStatus>> for Outcome {
+    fn from(status: Option<RuleStatus>) -> Outcome {
+        match status {
+            Some(RuleStatus::Verified) => Outcome::Pass,
+            Some(RuleStatus::Violated | RuleStatus::SanityFailed) => Outcome::Fail,
+            Some(RuleStatus::Timeout) => Outcome::Timeout,
+            Some(RuleStatus::Error) => Outcome::Error,
+            None => Outcome::NotRun,
+        }
+    }
+}
+
+/// A range of lines in a source file, counted from one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    pub path: PathBuf,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// The result of one rule.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleOutcome {
+    pub rule: String,
+    pub outcome: Outcome,
+    pub status: Option<RuleStatus>,
+    /// The spec function the rule checks
+    pub function: Option<String>,
+    /// Where the rule is defined
+    pub location: Option<Location>,
+    /// Where the function the rule checks is implemented
+    pub implementation: Option<Location>,
+}
+
+/// The results of a crate.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Results {
+    pub spec: String,
+    pub rules: Vec<RuleOutcome>,
+}
+
+/// The lines of the function `name` in `source`, from its signature to the
+/// end of its body.
+fn locate(path: &Path, source: &str, name: &str) -> Option<Location> {
+    let start = [format!("fn {name}("), format!("fn {name}<")]
+        .iter()
+        .find_map(|signature| source.find(signature))?;
+    let end = start + drift::block_end(&source[start..])?;
+    let line = |at: usize| source[..at].matches('\n').count() + 1;
+    Some(Location {
+        path: path.to_path_buf(),
+        start_line: line(start),
+        end_line: line(end),
+    })
+}
+
+/// Collects the results of the crate in `dir` implementing `spec`, given
+/// the verdicts of its last check. Every rule of the crate is listed, those
+/// without a verdict as not run.
+pub fn collect(
+    spec: &Spec,
+    dir: &Path,
+    verdicts: &BTreeMap<String, RuleStatus>,
+) -> Result<Results, ResultsError> {
+    let read = |path: &Path| {
+        std::fs::read_to_string(path).map_err(|source| ResultsError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    };
+    let mut sources = Vec::new();
+    for path in cache::files(&dir.join("src").join("certora"))? {
+        if path.extension().is_some_and(|ext| ext == "rs") {
+            let source = read(&path)?;
+            sources.push((path, source));
+        }
+    }
+    let processor_path = dir.join(drift::PROCESSOR_RS);
+    let processor = match std::fs::read_to_string(&processor_path) {
+        Ok(source) => source,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(source) => {
+            return Err(ResultsError::Io {
+                path: processor_path,
+                source,
+            })
+        }
+    };
+
+    let mut rules = Vec::new();
+    for (path, source) in &sources {
+        for rule in prover::rules(source) {
+            let function = regression::rule_function(spec, &rule).map(|f| f.name.clone());
+            let implementation = function
+                .as_ref()
+                .and_then(|name| locate(&processor_path, &processor, &format!("process_{name}")));
+            let status = verdicts.get(&rule).copied();
+            rules.push(RuleOutcome {
+                location: locate(path, source, &rule),
+                outcome: status.into(),
+                status,
+                function,
+                implementation,
+                rule,
+            });
+        }
+    }
+    Ok(Results {
+        spec: spec.name.clone(),
+        rules,
+    })
+}
+
+/// The results last written to [`RESULTS_JSON`] in the crate in `dir`, if
+/// any.
+pub fn load(dir: &Path) -> Result<Option<Results>, ResultsError> {
+    let path = dir.join(RESULTS_JSON);
+    let json = match std::fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => return Err(ResultsError::Io { path, source }),
+    };
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|source| ResultsError::Json { path, source })
+}
+
+/// Records `verdicts` in the results of the crate in `dir` implementing
+/// `spec`, writing them to the default paths. The rules without a new
+/// verdict keep the one last recorded.
+pub fn record(
+    spec: &Spec,
+    dir: &Path,
+    verdicts: &BTreeMap<String, RuleStatus>,
+) -> Result<Results, ResultsError> {
+    let mut merged: BTreeMap<String, RuleStatus> = load(dir)?
+        .into_iter()
+        .flat_map(|results| results.rules)
+        .filter_map(|rule| Some((rule.rule, rule.status?)))
+        .collect();
+    merged.extend(
+        verdicts
+            .iter()
+            .map(|(rule, status)| (rule.clone(), *status)),
+    );
+    let results = collect(spec, dir, &merged)?;
+    results.write(
+        &dir.join(RESULTS_JSON),
+        &dir.join(RESULTS_SARIF),
+        &dir.join(RESULTS_JUNIT),
+    )?;
+    Ok(results)
+}
+
+/// A SARIF location of `location`.
+fn sarif_location(location: &Location, message: Option<String>) -> serde_json::Value {
+    let mut value = serde_json::json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": location.path.display().to_string().replace('\\', "/") },
+            "region": { "startLine": location.start_line, "endLine": location.end_line },
+        },
+    });
+    if let Some(message) = message {
+        value["message"] = serde_json::json!({ "text": message });
+    }
+    value
+}
+
+/// `text` escaped for XML text and attribute values.
+pub(crate) fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+impl Results {
+    /// Whether every rule passed.
+    pub fn passed(&self) -> bool {
+        self.rules.iter().all(|rule| rule.outcome == Outcome::Pass)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("results serialize") + "\n"
+    }
+
+    /// The results as a SARIF log with one run. A result is located at the
+    /// implementation of the function its rule checks, with the rule as a
+    /// related location, or at the rule alone.
+    pub fn to_sarif(&self) -> String {
+        let rules: Vec<_> = self
+            .rules
+            .iter()
+            .map(|rule| {
+                serde_json::json!({
+                    "id": rule.rule,
+                    "shortDescription": { "text": match &rule.function {
+                        Some(function) => format!("Prover rule on `{function}`"),
+                        None => "Prover rule".to_string(),
+                    }},
+                })
+            })
+            .collect();
+        let results: Vec<_> = self
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                // Only failing results carry a level other than none
+                let (kind, level) = match rule.outcome {
+                    Outcome::Pass => ("pass", "none"),
+                    Outcome::Fail | Outcome::Error => ("fail", "error"),
+                    Outcome::Timeout => ("fail", "warning"),
+                    Outcome::NotRun => ("notApplicable", "none"),
+                };
+                let verdict = match rule.status {
+                    Some(status) => status.to_string(),
+                    None => "NOT RUN".to_string(),
+                };
+                let text = match &rule.function {
+                    Some(function) => format!("{}: {verdict} for `{function}`", rule.rule),
+                    None => format!("{}: {verdict}", rule.rule),
+                };
+                let mut result = serde_json::json!({
+                    "ruleId": rule.rule,
+                    "ruleIndex": index,
+                    "kind": kind,
+                    "level": level,
+                    "message": { "text": text },
+                });
+                let rule_location = rule
+                    .location
+                    .as_ref()
+                    .map(|location| sarif_location(location, Some(format!("`{}`", rule.rule))));
+                match (&rule.implementation, rule_location) {
+                    (Some(implementation), related) => {
+                        result["locations"] =
+                            serde_json::json!([sarif_location(implementation, None)]);
+                        if let Some(related) = related {
+                            result["relatedLocations"] = serde_json::json!([related]);
+                        }
+                    }
+                    (None, Some(location)) => {
+                        result["locations"] = serde_json::json!([location]);
+                    }
+                    (None, None) => {}
+                }
+                result
+            })
+            .collect();
+        let log = serde_json::json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "spec-coder",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    },
+                },
+                "results": results,
+            }],
+        });
+        serde_json::to_string_pretty(&log).expect("SARIF serializes") + "\n"
+    }
+
+    /// The results as a JUnit XML report with one test suite, named after
+    /// the spec, and a test case per rule, classed under the spec function
+    /// it checks. A violated rule is a failure, a rule the prover errored or
+    /// timed out on an error, and a rule not run is skipped.
+    pub fn to_junit(&self) -> String {
+        let count = |outcomes: &[Outcome]| {
+            self.rules
+                .iter()
+                .filter(|rule| outcomes.contains(&rule.outcome))
+                .count()
+        };
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let suite = format!(
+            "name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\"",
+            xml_escape(&self.spec),
+            self.rules.len(),
+            count(&[Outcome::Fail]),
+            count(&[Outcome::Error, Outcome::Timeout]),
+            count(&[Outcome::NotRun]),
+        );
+        xml.push_str(&format!("<testsuites {suite}>\n  <testsuite {suite}>\n"));
+        for rule in &self.rules {
+            let class = match &rule.function {
+                Some(function) => format!("{}.{function}", self.spec),
+                None => self.spec.clone(),
+            };
+            let mut attributes = format!(
+                "name=\"{}\" classname=\"{}\"",
+                xml_escape(&rule.rule),
+                xml_escape(&class)
+            );
+            if let Some(location) = &rule.location {
+                attributes.push_str(&format!(
+                    " file=\"{}\" line=\"{}\"",
+                    xml_escape(&location.path.display().to_string().replace('\\', "/")),
+                    location.start_line
+                ));
+            }
+            let verdict = match rule.status {
+                Some(status) => status.to_string(),
+                None => "NOT RUN".to_string(),
+            };
+            let body = match rule.outcome {
+                Outcome::Pass => None,
+                Outcome::Fail => Some(format!(
+                    "<failure message=\"{}\" type=\"{verdict}\"/>",
+                    xml_escape(&format!("{}: {verdict}", rule.rule))
+                )),
+                Outcome::Error | Outcome::Timeout => Some(format!(
+                    "<error message=\"{}\" type=\"{verdict}\"/>",
+                    xml_escape(&format!("{}: {verdict}", rule.rule))
+                )),
+                Outcome::NotRun => Some("<skipped/>".to_string()),
+            };
+            match body {
+                Some(body) => xml.push_str(&format!(
+                    "    <testcase {attributes}>\n      {body}\n    </testcase>\n"
+                )),
+                None => xml.push_str(&format!("    <testcase {attributes}/>\n")),
+            }
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+
+    /// Writes the JSON, SARIF and JUnit XML results to `json`, `sarif` and
+    /// `junit`.
+    pub fn write(&self, json: &Path, sarif: &Path, junit: &Path) -> Result<(), ResultsError> {
+        for (path, contents) in [
+            (json, self.to_json()),
+            (sarif, self.to_sarif()),
+            (junit, self.to_junit()),
+        ] {
+            let io = |source| ResultsError::Io {
+                path: path.to_path_buf(),
+                source,
+            };
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(io)?;
+            }
+            std::fs::write(path, contents).map_err(io)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materialize;
+    use spec::Target;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_collect_and_sarif() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let files = materialize::generate(&spec, Target::Svm).unwrap();
+        materialize::write(&files, dir.path(), true).unwrap();
+        let verdicts = BTreeMap::from([
+            ("rule_deposit_sanity".to_string(), RuleStatus::Verified),
+            ("rule_withdraw_sanity".to_string(), RuleStatus::Violated),
+        ]);
+        let results = collect(&spec, dir.path(), &verdicts).unwrap();
+        assert!(!results.passed());
+
+        let rule = |name: &str| results.rules.iter().find(|r| r.rule == name).unwrap();
+        assert_eq!(rule("rule_deposit_sanity").outcome, Outcome::Pass);
+        assert_eq!(rule("rule_reward_sanity").outcome, Outcome::NotRun);
+        let withdraw = rule("rule_withdraw_sanity");
+        assert_eq!(withdraw.outcome, Outcome::Fail);
+        assert_eq!(withdraw.function.as_deref(), Some("withdraw"));
+        let implementation = withdraw.implementation.as_ref().unwrap();
+        let processor = std::fs::read_to_string(&implementation.path).unwrap();
+        let lines: Vec<&str> = processor.lines().collect();
+        assert!(lines[implementation.start_line - 1].contains("fn process_withdraw("));
+        assert_eq!(lines[implementation.end_line - 1], "}");
+        let location = withdraw.location.as_ref().unwrap();
+        let rules = std::fs::read_to_string(&location.path).unwrap();
+        assert!(rules
+            .lines()
+            .nth(location.start_line - 1)
+            .unwrap()
+            .contains("rule_withdraw_sanity"));
+
+        let sarif: serde_json::Value = serde_json::from_str(&results.to_sarif()).unwrap();
+        let run = &sarif["runs"][0];
+        assert_eq!(
+            run["tool"]["driver"]["rules"].as_array().unwrap().len(),
+            results.rules.len()
+        );
+        let failed: Vec<_> = run["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|result| result["kind"] == "fail")
+            .collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0]["ruleId"], "rule_withdraw_sanity");
+        assert_eq!(failed[0]["level"], "error");
+        let region = &failed[0]["locations"][0]["physicalLocation"]["region"];
+        assert_eq!(region["startLine"], implementation.start_line);
+        assert!(failed[0]["relatedLocations"][0].is_object());
+
+        let json: serde_json::Value = serde_json::from_str(&results.to_json()).unwrap();
+        let deposit = json["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|rule| rule["rule"] == "rule_deposit_sanity")
+            .unwrap();
+        assert_eq!(deposit["outcome"], "pass");
+        assert_eq!(deposit["status"], "VERIFIED");
+    }
+
+    #[test]
+    fn test_record() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let files = materialize::generate(&spec, Target::Svm).unwrap();
+        materialize::write(&files, dir.path(), true).unwrap();
+        assert_eq!(load(dir.path()).unwrap(), None);
+
+        let verdicts = BTreeMap::from([
+            ("rule_deposit_sanity".to_string(), RuleStatus::Violated),
+            ("rule_withdraw_sanity".to_string(), RuleStatus::Verified),
+        ]);
+        record(&spec, dir.path(), &verdicts).unwrap();
+        // A later run of one rule keeps the verdict on the other
+        let verdicts = BTreeMap::from([("rule_deposit_sanity".to_string(), RuleStatus::Verified)]);
+        let results = record(&spec, dir.path(), &verdicts).unwrap();
+        assert_eq!(load(dir.path()).unwrap().as_ref(), Some(&results));
+        let outcome = |name: &str| {
+            results
+                .rules
+                .iter()
+                .find(|rule| rule.rule == name)
+                .unwrap()
+                .outcome
+        };
+        assert_eq!(outcome("rule_deposit_sanity"), Outcome::Pass);
+        assert_eq!(outcome("rule_withdraw_sanity"), Outcome::Pass);
+        assert_eq!(outcome("rule_reward_sanity"), Outcome::NotRun);
+        assert!(dir.path().join(RESULTS_JUNIT).is_file());
+    }
+
+    #[test]
+    fn test_junit() {
+        let location = |line| {
+            Some(Location {
+                path: PathBuf::from("src/certora/spec.rs"),
+                start_line: line,
+                end_line: line + 5,
+            })
+        };
+        let rule =
+            |rule: &str, status: Option<RuleStatus>, function: Option<&str>, line| RuleOutcome {
+                rule: rule.to_string(),
+                outcome: status.into(),
+                status,
+                function: function.map(str::to_string),
+                location: location(line),
+                implementation: None,
+            };
+        let results = Results {
+            spec: "vault".to_string(),
+            rules: vec![
+                rule(
+                    "rule_deposit_sanity",
+                    Some(RuleStatus::Verified),
+                    Some("deposit"),
+                    10,
+                ),
+                rule(
+                    "rule_withdraw_sanity",
+                    Some(RuleStatus::Violated),
+                    Some("withdraw"),
+                    20,
+                ),
+                rule(
+                    "rule_reward_sanity",
+                    Some(RuleStatus::Timeout),
+                    Some("reward"),
+                    30,
+                ),
+                rule("rule_a<b", None, None, 40),
+            ],
+        };
+        let junit = results.to_junit();
+        assert!(junit.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites "));
+        assert!(junit.contains(
+            "<testsuite name=\"vault\" tests=\"4\" failures=\"1\" errors=\"1\" skipped=\"1\">"
+        ));
+        assert!(junit.contains(
+            "<testcase name=\"rule_deposit_sanity\" classname=\"vault.deposit\" \
+             file=\"src/certora/spec.rs\" line=\"10\"/>"
+        ));
+        assert!(junit
+            .contains("<failure message=\"rule_withdraw_sanity: VIOLATED\" type=\"VIOLATED\"/>"));
+        assert!(junit.contains("<error message=\"rule_reward_sanity: TIMEOUT\" type=\"TIMEOUT\"/>"));
+        assert!(junit.contains(
+            "<testcase name=\"rule_a&lt;b\" classname=\"vault\" file=\"src/certora/spec.rs\" \
+             line=\"40\">\n      <skipped/>"
+        ));
+        assert_eq!(junit.matches("<testcase ").count(), 4);
+        assert_eq!(junit.matches("</testcase>").count(), 3);
+    }
+}
@@ -0,0 +1,256 @@
+//! The results of every example at a glance.
+//!
+//! `spec-coder report --all` reads the results last recorded in each example
+//! of the [registry](crate::registry) and counts its rules by outcome, so
+//! that after an upgrade of the toolchain or the prover the examples that
+//! are no longer green stand out. The overview is printed as a table and
+//! written as an HTML page. Rules added since the results were recorded
+//! count as not run, and rules removed since are left out.
+
+use super::{load, xml_escape, Outcome, ResultsError};
+use crate::registry::{Registry, Status};
+use spec::Target;
+use std::fmt;
+use std::path::PathBuf;
+
+/// The default HTML overview, relative to the project root.
+pub const REPORT_HTML: &str = ".spec-coder/report.html";
+
+/// The rules of an example, counted by outcome.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub pass: usize,
+    pub fail: usize,
+    pub timeout: usize,
+    pub error: usize,
+    pub not_run: usize,
+}
+
+impl Counts {
+    pub fn total(&self) -> usize {
+        self.pass + self.fail + self.timeout + self.error + self.not_run
+    }
+
+    fn add(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Pass => self.pass += 1,
+            Outcome::Fail => self.fail += 1,
+            Outcome::Timeout => self.timeout += 1,
+            Outcome::Error => self.error += 1,
+            Outcome::NotRun => self.not_run += 1,
+        }
+    }
+}
+
+/// The results of one example.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExampleResults {
+    pub name: String,
+    pub target: Target,
+    pub dir: PathBuf,
+    /// Whether the example is verified, as the registry has it
+    pub status: Status,
+    pub counts: Counts,
+    /// Whether any results were recorded
+    pub recorded: bool,
+}
+
+impl ExampleResults {
+    /// Whether the example has rules and every one of them passed.
+    pub fn green(&self) -> bool {
+        self.counts.total() > 0 && self.counts.pass == self.counts.total()
+    }
+}
+
+/// The results of every example, in the order of the registry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Overview {
+    pub examples: Vec<ExampleResults>,
+}
+
+impl Overview {
+    /// Collects the results last recorded in the examples of `registry`.
+    pub fn collect(registry: &Registry) -> Result<Overview, ResultsError> {
+        let mut examples = Vec::new();
+        for example in &registry.examples {
+            let results = load(&example.dir)?;
+            let mut counts = Counts::default();
+            for rule in &example.rules {
+                let outcome = results
+                    .iter()
+                    .flat_map(|results| &results.rules)
+                    .find(|outcome| outcome.rule == *rule)
+                    .map_or(Outcome::NotRun, |outcome| outcome.outcome);
+                counts.add(outcome);
+            }
+            examples.push(ExampleResults {
+                name: example.name.clone(),
+                target: example.target,
+                dir: example.dir.clone(),
+                status: example.status,
+                counts,
+                recorded: results.is_some(),
+            });
+        }
+        Ok(Overview { examples })
+    }
+
+    /// How many examples are green.
+    pub fn green(&self) -> usize {
+        self.examples
+            .iter()
+            .filter(|example| example.green())
+            .count()
+    }
+
+    /// The overview as a standalone HTML page with a row per example.
+    pub fn to_html(&self) -> String {
+        let mut rows = String::new();
+        for example in &self.examples {
+            let class = match (example.green(), example.recorded) {
+                (true, _) => "green",
+                (false, false) => "unrecorded",
+                (false, true) => "red",
+            };
+            let counts = &example.counts;
+            rows.push_str(&format!(
+                "      <tr class=\"{class}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+                 <td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                xml_escape(&example.name),
+                example.target,
+                counts.total(),
+                counts.pass,
+                counts.fail,
+                counts.timeout,
+                counts.error,
+                counts.not_run,
+                example.status,
+            ));
+        }
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <title>spec-coder examples</title>
+    <style>
+      body {{ font-family: sans-serif; margin: 2em; }}
+      table {{ border-collapse: collapse; }}
+      th, td {{ padding: 0.3em 0.8em; border-bottom: 1px solid #ddd; text-align: right; }}
+      th:first-child, td:first-child, td:nth-child(2), td:last-child {{ text-align: left; }}
+      tr.green td:first-child {{ border-left: 0.4em solid #2da44e; }}
+      tr.red td:first-child {{ border-left: 0.4em solid #cf222e; }}
+      tr.unrecorded td:first-child {{ border-left: 0.4em solid #bbb; }}
+    </style>
+  </head>
+  <body>
+    <h1>spec-coder examples</h1>
+    <p>{} of {} examples green, generated by spec-coder {}.</p>
+    <table>
+      <tr><th>example</th><th>target</th><th>rules</th><th>pass</th><th>fail</th><th>timeout</th><th>error</th><th>not run</th><th>status</th></tr>
+{rows}    </table>
+  </body>
+</html>
+"#,
+            self.green(),
+            self.examples.len(),
+            env!("CARGO_PKG_VERSION"),
+        )
+    }
+}
+
+impl fmt::Display for Overview {
+    /// A table with a row per example.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .examples
+            .iter()
+            .map(|example| example.name.len() + example.target.to_string().len() + 1)
+            .chain([7])
+            .max()
+            .unwrap_or(7);
+        writeln!(
+            f,
+            "{:width$}  {:>5}  {:>4}  {:>4}  {:>7}  {:>5}  {:>7}  status",
+            "example", "rules", "pass", "fail", "timeout", "error", "not run",
+        )?;
+        for example in &self.examples {
+            let counts = &example.counts;
+            let state = match (example.green(), example.recorded) {
+                (true, _) => "green",
+                (false, false) => "no results",
+                (false, true) => "red",
+            };
+            writeln!(
+                f,
+                "{:width$}  {:>5}  {:>4}  {:>4}  {:>7}  {:>5}  {:>7}  {state}, {}",
+                format!("{}/{}", example.target, example.name),
+                counts.total(),
+                counts.pass,
+                counts.fail,
+                counts.timeout,
+                counts.error,
+                counts.not_run,
+                example.status,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materialize;
+    use crate::prover::RuleStatus;
+    use spec::Spec;
+    use std::collections::BTreeMap;
+
+    const VAULT: &str = include_str!("../../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_overview() {
+        let examples = tempfile::tempdir().unwrap();
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let files = materialize::generate(&spec, Target::Svm).unwrap();
+        for target in [Target::Svm, Target::Pinocchio] {
+            let dir = materialize::crate_dir(examples.path(), &spec, target);
+            materialize::write(&files, &dir, false).unwrap();
+        }
+        let svm = materialize::crate_dir(examples.path(), &spec, Target::Svm);
+        let rules = crate::prover::find_rules(&svm).unwrap();
+        let verdicts: BTreeMap<String, RuleStatus> = rules
+            .iter()
+            .map(|rule| (rule.clone(), RuleStatus::Verified))
+            .collect();
+        super::super::record(&spec, &svm, &verdicts).unwrap();
+
+        let overview = Overview::collect(&Registry::scan(examples.path()).unwrap()).unwrap();
+        assert_eq!(overview.examples.len(), 2);
+        assert_eq!(overview.green(), 1);
+        let pinocchio = &overview.examples[0];
+        assert_eq!(pinocchio.target, Target::Pinocchio);
+        assert!(!pinocchio.recorded && !pinocchio.green());
+        assert_eq!(pinocchio.counts.not_run, rules.len());
+        let svm = &overview.examples[1];
+        assert!(svm.recorded && svm.green());
+        assert_eq!(svm.counts.pass, rules.len());
+
+        let table = overview.to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("example  "), "{table}");
+        assert!(lines[0].ends_with("error  not run  status"), "{table}");
+        assert!(lines[1].starts_with("pinocchio/vault  "));
+        assert!(lines[1].ends_with("no results, unverified"));
+        assert!(lines[2].starts_with("svm/vault  "));
+        assert!(
+            lines[2].ends_with("0        0  green, unverified"),
+            "{table}"
+        );
+
+        let html = overview.to_html();
+        assert!(html.contains("<p>1 of 2 examples green"));
+        assert!(html.contains("<tr class=\"green\"><td>vault</td><td>svm</td>"));
+        assert!(html.contains("<tr class=\"unrecorded\"><td>vault</td><td>pinocchio</td>"));
+    }
+}
@@ -0,0 +1,135 @@
+//! Review of the files the model wants to write.
+//!
+//! Before a file of a response touches the crate, the driver shows its
+//! change as a unified diff to a [`Reviewer`], which approves or declines
+//! it. Declined files are not written and the model is told so, like files
+//! it may not change. Every decision is kept in the session, see
+//! [`crate::session`].
+
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// A decision on a file of a response.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Review {
+    /// The iteration of the response
+    pub iteration: usize,
+    pub path: PathBuf,
+    pub approved: bool,
+    /// The [`Reviewer::name`] of who decided
+    pub reviewer: String,
+}
+
+/// Approves or declines changes to the crate.
+pub trait Reviewer {
+    /// Recorded with the decisions, e.g. `user`.
+    fn name(&self) -> &str;
+
+    /// Whether the change to `path`, shown by `diff`, may be written.
+    fn review(&self, path: &Path, diff: &str) -> bool;
+}
+
+/// Approves every change, for unattended runs.
+pub struct AutoApprove;
+
+impl Reviewer for AutoApprove {
+    fn name(&self) -> &str {
+        "auto"
+    }
+
+    fn review(&self, _: &Path, _: &str) -> bool {
+        true
+    }
+}
+
+/// Asks on a terminal, or any other pair of streams, about each change.
+/// Answering `a` approves it and every change after it.
+pub struct Prompt<R, W> {
+    io: RefCell<(R, W)>,
+    approve_all: Cell<bool>,
+}
+
+impl<R: BufRead, W: Write> Prompt<R, W> {
+    pub fn new(input: R, output: W) -> Prompt<R, W> {
+        Prompt {
+            io: RefCell::new((input, output)),
+            approve_all: Cell::new(false),
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Reviewer for Prompt<R, W> {
+    fn name(&self) -> &str {
+        "user"
+    }
+
+    fn review(&self, path: &Path, diff: &str) -> bool {
+        if self.approve_all.get() {
+            return true;
+        }
+        let (input, output) = &mut *self.io.borrow_mut();
+        let _ = write!(output, "{diff}Write {}? [y/n/a] ", path.display());
+        let _ = output.flush();
+        // A closed stream declines what is left
+        let mut answer = String::new();
+        if input.read_line(&mut answer).is_err() {
+            return false;
+        }
+        match answer.trim() {
+            "y" | "yes" => true,
+            "a" | "all" => {
+                self.approve_all.set(true);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The unified diff from `old` to `new` of the file at `path`, relative to
+/// the crate root; a new file is diffed against `/dev/null`.
+pub fn unified_diff(path: &Path, old: Option<&str>, new: &str) -> String {
+    let b = format!("b/{}", path.display());
+    let a = match old {
+        Some(_) => format!("a/{}", path.display()),
+        None => "/dev/null".to_string(),
+    };
+    similar::TextDiff::from_lines(old.unwrap_or_default(), new)
+        .unified_diff()
+        .header(&a, &b)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let path = Path::new("src/processor.rs");
+        assert_eq!(
+            unified_diff(path, Some("a\nb\n"), "a\nc\n"),
+            "--- a/src/processor.rs\n+++ b/src/processor.rs\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n"
+        );
+        assert!(
+            unified_diff(path, None, "a\n").starts_with("--- /dev/null\n+++ b/src/processor.rs\n")
+        );
+    }
+
+    #[test]
+    fn test_prompt() {
+        let mut output = Vec::new();
+        let prompt = Prompt::new(&b"y\nn\na\n"[..], &mut output);
+        let path = Path::new("src/processor.rs");
+        assert!(prompt.review(path, "-a\n+b\n"));
+        assert!(!prompt.review(path, ""));
+        assert!(prompt.review(path, ""));
+        // Nothing is asked once everything is approved
+        assert!(prompt.review(path, ""));
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("-a\n+b\nWrite src/processor.rs? [y/n/a] "));
+        assert_eq!(output.matches("Write").count(), 3);
+    }
+}
@@ -0,0 +1,75 @@
+//! The specifier and critic roles of a run.
+//!
+//! With roles, the coder that writes the implementation is assisted by two
+//! models with prompts of their own. Before the first response, the
+//! specifier reads the spec and the scaffold and returns a [`Refinement`]:
+//! notes per function on what the spec leaves implicit, such as rounding,
+//! overflow and edge cases, which the coder gets with the spec. After a
+//! check that violates rules, the critic reads the failures and the sources
+//! and returns a [`Critique`], a diagnosis per rule that the coder gets with
+//! the failures. Each role answers in a single exchange with a JSON block,
+//! so the artifacts passed between them stay structured; a malformed answer
+//! is ignored rather than failing the run.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Notes of the specifier, by function.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Refinement {
+    pub functions: Vec<FunctionNotes>,
+}
+
+/// What the spec leaves implicit about a function.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionNotes {
+    pub name: String,
+    pub notes: Vec<String>,
+}
+
+/// Diagnoses of the critic, by rule.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Critique {
+    pub diagnoses: Vec<Diagnosis>,
+}
+
+/// Why a rule fails and how to fix it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnosis {
+    pub rule: String,
+    /// What the counterexample or the output shows the implementation does
+    pub cause: String,
+    /// The change to make
+    pub fix: String,
+}
+
+/// Reads the artifact in the first ```json block of `response`.
+pub fn parse_artifact<T: DeserializeOwned>(response: &str) -> Option<T> {
+    let start = response.find("```json")? + "```json".len();
+    let block = &response[start..];
+    let end = block.find("```")?;
+    serde_json::from_str(&block[..end]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_artifact() {
+        let response =
+            "The withdrawal rounds.\n\n```json\n{\"functions\": [{\"name\": \"withdraw\", \
+                        \"notes\": [\"round the tokens down\"]}]}\n```\n";
+        assert_eq!(
+            parse_artifact::<Refinement>(response),
+            Some(Refinement {
+                functions: vec![FunctionNotes {
+                    name: "withdraw".to_string(),
+                    notes: vec!["round the tokens down".to_string()],
+                }],
+            })
+        );
+        assert_eq!(parse_artifact::<Critique>(response), None);
+        assert_eq!(parse_artifact::<Critique>("No block."), None);
+    }
+}
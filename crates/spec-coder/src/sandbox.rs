@@ -0,0 +1,521 @@
+//! Isolated builds of model output.
+//!
+//! Building a crate runs its build scripts and proc macros, which the model
+//! writes as freely as the rest of the crate. Unless disabled, cargo is run
+//! on a copy of the crate in a temporary directory, with an environment
+//! holding only what the toolchain needs: no API keys, and a home directory
+//! of its own. That keeps stray writes and secrets out of reach but is not a
+//! security boundary; a container runtime is, running the build with no
+//! network on a copy of the crate whose dependencies were fetched first.
+//!
+//! The path dependencies of the copy are rewritten to the absolute paths of
+//! the crates they name, e.g. `../vault_math` of an example, and mounted
+//! read-only at those paths in a container, as is the registry.
+//!
+//! Cargo reports compiler messages as JSON, so every build also yields
+//! [`Diagnostic`]s locating its errors in the crate.
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where builds run, relative to the crate root: a target directory kept
+/// across builds so that they stay incremental.
+pub const SANDBOX_TARGET: &str = ".spec-coder/target";
+
+/// Entries of the crate that are not copied into the sandbox.
+const SKIPPED: &[&str] = &["target", ".spec-coder", ".git"];
+
+/// The image containers run by default.
+const DEFAULT_IMAGE: &str = "rust:1";
+
+/// Environment variables passed to cargo in the sandbox.
+const KEPT_ENV: &[&str] = &["PATH", "RUSTUP_TOOLCHAIN", "TERM", "LANG"];
+
+/// How cargo is isolated from the environment it is run from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Sandbox {
+    /// In the crate itself, with the full environment
+    None,
+    /// In a copy of the crate with a restricted environment
+    #[default]
+    TempDir,
+    /// In a container of `image` run by `runtime`, e.g. `docker` or `podman`
+    Container { runtime: String, image: String },
+}
+
+impl std::str::FromStr for Sandbox {
+    type Err = String;
+
+    /// `none`, `tempdir`, or a container runtime with an optional image,
+    /// e.g. `docker` or `podman:rust:1.80`.
+    fn from_str(s: &str) -> Result<Sandbox, String> {
+        let (runtime, image) = match s.split_once(':') {
+            Some((runtime, image)) => (runtime, image),
+            None => (s, DEFAULT_IMAGE),
+        };
+        match runtime {
+            "none" if s == "none" => Ok(Sandbox::None),
+            "tempdir" if s == "tempdir" => Ok(Sandbox::TempDir),
+            "docker" | "podman" => Ok(Sandbox::Container {
+                runtime: runtime.to_string(),
+                image: image.to_string(),
+            }),
+            _ => Err(format!(
+                "unknown sandbox `{s}`, expected `none`, `tempdir`, `docker[:<image>]` or \
+                 `podman[:<image>]`"
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Sandbox {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Sandbox, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// A compiler message of a build.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// `error`, `warning`, ...
+    pub level: String,
+    pub message: String,
+    /// The file of the primary span, relative to the crate root
+    pub path: Option<PathBuf>,
+    /// The line of the primary span, from 1
+    pub line: Option<usize>,
+    /// The message as rustc prints it
+    pub rendered: String,
+}
+
+/// The outcome of a cargo command.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CargoOutput {
+    pub success: bool,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Everything cargo printed besides the compiler messages, e.g. the
+    /// test results
+    pub output: String,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    reason: String,
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Deserialize)]
+struct CompilerMessage {
+    level: String,
+    message: String,
+    spans: Vec<Span>,
+    rendered: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Span {
+    file_name: PathBuf,
+    line_start: usize,
+    is_primary: bool,
+}
+
+/// Splits the stdout of cargo run with `--message-format=json` into the
+/// compiler messages and the other lines.
+fn parse_output(stdout: &str) -> (Vec<Diagnostic>, String) {
+    let mut diagnostics = Vec::new();
+    let mut output = String::new();
+    for line in stdout.lines() {
+        let Ok(message) = serde_json::from_str::<Message>(line) else {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        };
+        let (true, Some(message)) = (message.reason == "compiler-message", message.message) else {
+            continue;
+        };
+        let primary = message.spans.iter().find(|span| span.is_primary);
+        diagnostics.push(Diagnostic {
+            level: message.level,
+            rendered: message.rendered.unwrap_or_else(|| message.message.clone()),
+            message: message.message,
+            path: primary.map(|span| span.file_name.clone()),
+            line: primary.map(|span| span.line_start),
+        });
+    }
+    (diagnostics, output)
+}
+
+/// Copies the crate in `from` to `to`, leaving out [`SKIPPED`] entries.
+fn copy_crate(from: &Path, to: &Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if SKIPPED.iter().any(|skipped| name == *skipped) {
+            continue;
+        }
+        copy(&entry.path(), &to.join(name))?;
+    }
+    Ok(())
+}
+
+fn copy(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        std::fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// Dependency tables of a manifest, at its top level or under a
+/// `[target.<cfg>]` table.
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// The path dependencies declared in `manifest`, a `Cargo.toml` of the crate
+/// in `dir`, resolved against `dir`. With `rewrite`, their paths in
+/// `manifest` are made absolute.
+fn path_dependencies(manifest: &mut toml::Table, dir: &Path, rewrite: bool) -> Vec<PathBuf> {
+    let mut tables: Vec<&mut toml::Value> = Vec::new();
+    for (key, value) in manifest.iter_mut() {
+        if DEPENDENCY_TABLES.contains(&key.as_str()) {
+            tables.push(value);
+        } else if key == "target" {
+            let Some(targets) = value.as_table_mut() else {
+                continue;
+            };
+            for target in targets
+                .iter_mut()
+                .map(|(_, target)| target)
+                .filter_map(toml::Value::as_table_mut)
+            {
+                tables.extend(
+                    target
+                        .iter_mut()
+                        .filter(|(key, _)| DEPENDENCY_TABLES.contains(&key.as_str()))
+                        .map(|(_, value)| value),
+                );
+            }
+        }
+    }
+    let mut paths = Vec::new();
+    for table in tables.into_iter().filter_map(toml::Value::as_table_mut) {
+        for dependency in table
+            .iter_mut()
+            .map(|(_, dependency)| dependency)
+            .filter_map(toml::Value::as_table_mut)
+        {
+            let Some(path) = dependency.get("path").and_then(toml::Value::as_str) else {
+                continue;
+            };
+            let path = dir.join(path);
+            let path = path.canonicalize().unwrap_or(path);
+            if rewrite {
+                dependency.insert(
+                    "path".to_string(),
+                    toml::Value::String(path.display().to_string()),
+                );
+            }
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Rewrites the path dependencies of the copy in `copy` of the crate in
+/// `dir` to absolute paths, and returns the directories of those crates and
+/// of their own path dependencies, transitively.
+fn rewrite_path_dependencies(dir: &Path, copy: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let manifest_path = copy.join("Cargo.toml");
+    // Cargo reports a manifest it cannot read when building
+    let Ok(mut manifest) = toml::from_str::<toml::Table>(&std::fs::read_to_string(&manifest_path)?)
+    else {
+        return Ok(Vec::new());
+    };
+    let mut pending = path_dependencies(&mut manifest, dir, true);
+    if !pending.is_empty() {
+        let text = toml::to_string(&manifest).expect("the manifest serializes");
+        std::fs::write(&manifest_path, text)?;
+    }
+    let mut crates = Vec::new();
+    while let Some(path) = pending.pop() {
+        if crates.contains(&path) {
+            continue;
+        }
+        let text = std::fs::read_to_string(path.join("Cargo.toml")).unwrap_or_default();
+        if let Ok(mut manifest) = toml::from_str::<toml::Table>(&text) {
+            pending.extend(path_dependencies(&mut manifest, &path, false));
+        }
+        crates.push(path);
+    }
+    Ok(crates)
+}
+
+/// A directory of the toolchain: `var` if set, or `default` below the home
+/// directory.
+fn toolchain_dir(var: &str, default: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(default)))
+}
+
+impl Sandbox {
+    /// The target directory of the builds of the crate in `dir`.
+    pub fn target_dir(&self, dir: &Path) -> PathBuf {
+        match self {
+            Sandbox::None => dir.join("target"),
+            Sandbox::TempDir | Sandbox::Container { .. } => dir.join(SANDBOX_TARGET),
+        }
+    }
+
+    /// Runs `cargo <args>` on the crate in `dir`, with compiler messages
+    /// reported as JSON.
+    pub fn cargo(&self, dir: &Path, args: &[&str]) -> std::io::Result<CargoOutput> {
+        let (subcommand, rest) = args.split_first().expect("a cargo subcommand");
+        let mut cargo_args = vec![*subcommand, "--message-format=json"];
+        cargo_args.extend(rest);
+        self.run(dir, &cargo_args)
+    }
+
+    /// Runs `cargo <cargo_args>` on the crate in `dir` as given, e.g. for
+    /// cargo subcommands that take no `--message-format`.
+    pub fn run(&self, dir: &Path, cargo_args: &[&str]) -> std::io::Result<CargoOutput> {
+        self.exec(dir, cargo_args, false)
+    }
+
+    /// Runs `cargo <cargo_args>` like [`Sandbox::run`], for subcommands that
+    /// rewrite the sources, such as `fmt` and `clippy --fix`: what they
+    /// write to the copy of the crate is copied back to `dir`.
+    pub fn fix(&self, dir: &Path, cargo_args: &[&str]) -> std::io::Result<CargoOutput> {
+        self.exec(dir, cargo_args, true)
+    }
+
+    fn exec(
+        &self,
+        dir: &Path,
+        cargo_args: &[&str],
+        write_back: bool,
+    ) -> std::io::Result<CargoOutput> {
+        let mut copy = None;
+        let output = match self {
+            Sandbox::None => Command::new("cargo")
+                .args(cargo_args)
+                .current_dir(dir)
+                .output()?,
+            Sandbox::TempDir => {
+                let copy = copy.insert(tempfile::tempdir()?);
+                copy_crate(dir, copy.path())?;
+                rewrite_path_dependencies(dir, copy.path())?;
+                let mut command = Command::new("cargo");
+                command
+                    .args(cargo_args)
+                    .current_dir(copy.path())
+                    .env_clear()
+                    .env("HOME", copy.path())
+                    .env("CARGO_TARGET_DIR", dir.join(SANDBOX_TARGET));
+                for var in KEPT_ENV {
+                    if let Some(value) = std::env::var_os(var) {
+                        command.env(var, value);
+                    }
+                }
+                // The toolchain and the registry stay where they are
+                for (var, default) in [("CARGO_HOME", ".cargo"), ("RUSTUP_HOME", ".rustup")] {
+                    if let Some(path) = toolchain_dir(var, default) {
+                        command.env(var, path);
+                    }
+                }
+                command.output()?
+            }
+            Sandbox::Container { runtime, image } => {
+                let copy = copy.insert(tempfile::tempdir()?);
+                copy_crate(dir, copy.path())?;
+                let dependencies = rewrite_path_dependencies(dir, copy.path())?;
+                // Fetching runs no code of the crate, so it needs no sandbox
+                let fetch = Command::new("cargo")
+                    .arg("fetch")
+                    .current_dir(copy.path())
+                    .output()?;
+                if !fetch.status.success() {
+                    return Ok(CargoOutput {
+                        success: false,
+                        diagnostics: Vec::new(),
+                        output: String::from_utf8_lossy(&fetch.stderr).into_owned(),
+                    });
+                }
+                let target = dir.join(SANDBOX_TARGET);
+                std::fs::create_dir_all(&target)?;
+                let mut command = Command::new(runtime);
+                command.args(["run", "--rm", "--network", "none", "-w", "/work"]);
+                command
+                    .arg("-v")
+                    .arg(format!("{}:/work", copy.path().display()));
+                command
+                    .arg("-v")
+                    .arg(format!("{}:/work/target", target.display()));
+                for dependency in &dependencies {
+                    command
+                        .arg("-v")
+                        .arg(format!("{0}:{0}:ro", dependency.display()));
+                }
+                if let Some(cargo_home) = toolchain_dir("CARGO_HOME", ".cargo") {
+                    command.arg("-v").arg(format!(
+                        "{}:/usr/local/cargo/registry:ro",
+                        cargo_home.join("registry").display()
+                    ));
+                }
+                command
+                    .arg(image)
+                    .args(["cargo", "--offline"])
+                    .args(cargo_args)
+                    .output()?
+            }
+        };
+        if let Some(copy) = copy.filter(|_| write_back) {
+            // Keep the manifest as it was, with its relative paths
+            std::fs::copy(dir.join("Cargo.toml"), copy.path().join("Cargo.toml"))?;
+            copy_crate(copy.path(), dir)?;
+        }
+        let (diagnostics, mut text) = parse_output(&String::from_utf8_lossy(&output.stdout));
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        Ok(CargoOutput {
+            success: output.status.success(),
+            diagnostics,
+            output: text,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("none".parse(), Ok(Sandbox::None));
+        assert_eq!("tempdir".parse(), Ok(Sandbox::TempDir));
+        assert_eq!(
+            "docker".parse(),
+            Ok(Sandbox::Container {
+                runtime: "docker".to_string(),
+                image: "rust:1".to_string(),
+            })
+        );
+        assert_eq!(
+            "podman:rust:1.80".parse(),
+            Ok(Sandbox::Container {
+                runtime: "podman".to_string(),
+                image: "rust:1.80".to_string(),
+            })
+        );
+        assert!("tempdir:x".parse::<Sandbox>().is_err());
+        assert!("vm".parse::<Sandbox>().is_err());
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let stdout = r#"{"reason":"compiler-artifact","target":{"name":"vault"}}
+{"reason":"compiler-message","message":{"level":"error","message":"cannot find value `x` in this scope","spans":[{"file_name":"src/processor.rs","line_start":12,"is_primary":true}],"rendered":"error[E0425]: cannot find value `x` in this scope\n"}}
+running 2 tests
+test tests::deposit ... ok
+{"reason":"build-finished","success":false}
+"#;
+        let (diagnostics, output) = parse_output(stdout);
+        assert_eq!(
+            diagnostics,
+            [Diagnostic {
+                level: "error".to_string(),
+                message: "cannot find value `x` in this scope".to_string(),
+                path: Some(PathBuf::from("src/processor.rs")),
+                line: Some(12),
+                rendered: "error[E0425]: cannot find value `x` in this scope\n".to_string(),
+            }]
+        );
+        assert_eq!(output, "running 2 tests\ntest tests::deposit ... ok\n");
+    }
+
+    #[test]
+    fn test_copy_crate() {
+        let from = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(from.path().join("src/certora")).unwrap();
+        std::fs::create_dir_all(from.path().join("target/debug")).unwrap();
+        std::fs::create_dir_all(from.path().join(".spec-coder")).unwrap();
+        std::fs::write(from.path().join("Cargo.toml"), "[package]\n").unwrap();
+        std::fs::write(from.path().join("src/certora/spec.rs"), "").unwrap();
+        std::fs::write(from.path().join(".spec-coder/session.json"), "{}").unwrap();
+        let to = tempfile::tempdir().unwrap();
+        copy_crate(from.path(), to.path()).unwrap();
+        assert!(to.path().join("Cargo.toml").exists());
+        assert!(to.path().join("src/certora/spec.rs").exists());
+        assert!(!to.path().join("target").exists());
+        assert!(!to.path().join(".spec-coder").exists());
+    }
+
+    #[test]
+    fn test_rewrite_path_dependencies() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path().canonicalize().unwrap();
+        let manifest = |name: &str, dependencies: &str| {
+            std::fs::create_dir_all(root.join(name)).unwrap();
+            std::fs::write(
+                root.join(name).join("Cargo.toml"),
+                format!("[package]\nname = \"{name}\"\n\n{dependencies}"),
+            )
+            .unwrap();
+        };
+        manifest("fv_support", "");
+        manifest(
+            "vault_math",
+            "[dependencies]\nsvm-fv-support = { path = \"../fv_support\" }\n",
+        );
+        manifest(
+            "vault",
+            "[dependencies]\nsvm-vault-math = { path = \"../vault_math\" }\nbytemuck = \"1\"\n\n\
+             [target.'cfg(unix)'.dev-dependencies]\nsvm-fv-support = { path = \"../fv_support\" }\n",
+        );
+        let copy = tempfile::tempdir().unwrap();
+        copy_crate(&root.join("vault"), copy.path()).unwrap();
+
+        let mut crates = rewrite_path_dependencies(&root.join("vault"), copy.path()).unwrap();
+        crates.sort();
+        assert_eq!(crates, [root.join("fv_support"), root.join("vault_math")]);
+        let manifest: toml::Table =
+            toml::from_str(&std::fs::read_to_string(copy.path().join("Cargo.toml")).unwrap())
+                .unwrap();
+        assert_eq!(
+            manifest["dependencies"]["svm-vault-math"]["path"].as_str(),
+            Some(root.join("vault_math").to_str().unwrap())
+        );
+        assert_eq!(manifest["dependencies"]["bytemuck"].as_str(), Some("1"));
+        assert_eq!(
+            manifest["target"]["cfg(unix)"]["dev-dependencies"]["svm-fv-support"]["path"].as_str(),
+            Some(root.join("fv_support").to_str().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_fix() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"fixed\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "pub fn one()->u64{1}\n").unwrap();
+        let output = Sandbox::TempDir.fix(dir.path(), &["fmt"]).unwrap();
+        if !output.success {
+            // A toolchain without rustfmt
+            return;
+        }
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("src/lib.rs")).unwrap(),
+            "pub fn one() -> u64 {\n    1\n}\n"
+        );
+    }
+}
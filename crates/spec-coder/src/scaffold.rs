@@ -0,0 +1,217 @@
+//! Scaffolding of new examples.
+//!
+//! A new example starts from a spec template under `examples/specs`, with a
+//! placeholder account, function and invariant, and the crate materialized
+//! from it. The crate has the canonical layout, manifest and features of the
+//! other examples and a prover configuration listing its rules, so that
+//! nothing has to be copied from an existing example.
+
+use crate::materialize::svm::pascal_case;
+use crate::materialize::{self, GeneratedFile, MaterializeError};
+use crate::migrate::{self, MigrateError};
+use crate::provenance::{self, Provenance};
+use crate::prover::{self, DEFAULT_PROVER_ARGS};
+use spec::{Spec, SpecError, Target};
+use std::path::{Path, PathBuf};
+
+const SVM_SPEC: &str = include_str!("../assets/svm/spec.yaml.j2");
+const RUST_SPEC: &str = include_str!("../assets/rust/spec.yaml.j2");
+
+/// The prover configuration of a new crate, relative to its root.
+pub const CONF: &str = "certora/conf/default.conf";
+
+/// An error creating an example.
+#[derive(Debug, thiserror::Error)]
+pub enum ScaffoldError {
+    #[error("`{0}` is not a valid example name; use lowercase letters, digits and underscores")]
+    InvalidName(String),
+    #[error("there is no spec template for {0} programs")]
+    UnsupportedTarget(Target),
+    #[error("{} already exists", .0.display())]
+    Exists(PathBuf),
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error(transparent)]
+    Materialize(#[from] MaterializeError),
+    #[error(transparent)]
+    Migrate(#[from] MigrateError),
+    #[error("failed to write {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// The files of a new example.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NewExample {
+    pub spec: PathBuf,
+    pub dir: PathBuf,
+}
+
+/// Whether `name` is a snake case identifier, as crate and module names are.
+fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !name.ends_with('_')
+}
+
+/// The spec template of an example called `name`.
+pub fn spec_template(name: &str, target: Target) -> Result<String, ScaffoldError> {
+    if !valid_name(name) {
+        return Err(ScaffoldError::InvalidName(name.to_string()));
+    }
+    let template = match target {
+        Target::Svm | Target::Anchor | Target::Pinocchio => SVM_SPEC,
+        Target::Rust => RUST_SPEC,
+        Target::Evm => return Err(ScaffoldError::UnsupportedTarget(target)),
+    };
+    let account = pascal_case(name);
+    let binding = spec::Account {
+        name: account.clone(),
+        description: None,
+        fields: Vec::new(),
+    }
+    .binding();
+    Ok(minijinja::render!(
+        template,
+        name,
+        target => target.to_string(),
+        title => name.replace('_', " "),
+        account,
+        binding,
+    ) + "\n")
+}
+
+/// The prover configuration running `rules` as the composer does.
+fn conf(spec: &Spec, rules: &[String]) -> String {
+    let conf = serde_json::json!({
+        "cargo_features": ["certora"],
+        "msg": spec.name,
+        "rule": rules,
+        "rule_sanity": "basic",
+        "prover_args": DEFAULT_PROVER_ARGS,
+    });
+    serde_json::to_string_pretty(&conf).expect("configurations serialize") + "\n"
+}
+
+/// Creates the example `name`: its spec template in
+/// `<examples-dir>/specs/<name>.yaml` and the crate materialized from it.
+/// Neither may exist unless `force` is set. The generated files start with
+/// `license`, if any, see [`provenance`].
+pub fn new_example(
+    name: &str,
+    target: Target,
+    examples_dir: &Path,
+    license: Option<&str>,
+    force: bool,
+) -> Result<NewExample, ScaffoldError> {
+    let template = spec_template(name, target)?;
+    example_from(template, target, examples_dir, license, force)
+}
+
+/// Creates an example from the spec `template`, e.g. a draft filled in from
+/// [`spec_template`], as [`new_example`] does.
+pub fn example_from(
+    template: String,
+    target: Target,
+    examples_dir: &Path,
+    license: Option<&str>,
+    force: bool,
+) -> Result<NewExample, ScaffoldError> {
+    let spec = Spec::from_yaml_str(&template)?;
+    let name = &spec.name;
+    let spec_path = examples_dir.join("specs").join(format!("{name}.yaml"));
+    let dir = materialize::crate_dir(examples_dir, &spec, target);
+    for path in [&spec_path, &dir] {
+        if path.exists() && !force {
+            return Err(ScaffoldError::Exists(path.clone()));
+        }
+    }
+
+    let mut files = materialize::generate(&spec, target)?;
+    let rules: Vec<String> = files
+        .iter()
+        .filter(|file| materialize::is_compiled(&file.path))
+        .flat_map(|file| prover::rules(&file.contents))
+        .collect();
+    files.push(GeneratedFile {
+        path: PathBuf::from(CONF),
+        contents: conf(&spec, &rules),
+    });
+    let provenance = Provenance::new(provenance::fingerprint(&spec), None);
+    provenance::stamp_files(&mut files, &provenance, license);
+    let io = |source| ScaffoldError::Io {
+        path: spec_path.clone(),
+        source,
+    };
+    std::fs::create_dir_all(spec_path.parent().expect("has a parent")).map_err(io)?;
+    std::fs::write(&spec_path, template).map_err(io)?;
+    materialize::write(&files, &dir, force)?;
+    migrate::record(&spec, &dir)?;
+    Ok(NewExample {
+        spec: spec_path,
+        dir,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_template() {
+        let template = spec_template("bonding_curve", Target::Svm).unwrap();
+        let spec = Spec::from_yaml_str(&template).unwrap();
+        assert_eq!(spec.name, "bonding_curve");
+        assert_eq!(spec.state[0].name, "BondingCurve");
+        assert_eq!(spec.functions[0].accounts[0].name, "bonding_curve");
+        assert!(!spec.functions[0].examples.is_empty());
+        assert!(!spec.invariants.is_empty());
+
+        for name in ["", "Vault", "my-vault", "1vault", "vault_"] {
+            assert!(matches!(
+                spec_template(name, Target::Svm),
+                Err(ScaffoldError::InvalidName(_))
+            ));
+        }
+        let template = spec_template("frame_decoder", Target::Rust).unwrap();
+        let spec = Spec::from_yaml_str(&template).unwrap();
+        assert_eq!(spec.target, Target::Rust);
+        materialize::generate(&spec, spec.target).unwrap();
+        assert!(matches!(
+            spec_template("vault", Target::Evm),
+            Err(ScaffoldError::UnsupportedTarget(_))
+        ));
+    }
+
+    #[test]
+    fn test_new_example() {
+        let examples = tempfile::tempdir().unwrap();
+        let created = new_example("counter", Target::Svm, examples.path(), None, false).unwrap();
+        assert_eq!(created.spec, examples.path().join("specs/counter.yaml"));
+        assert_eq!(
+            created.dir,
+            examples.path().join("svm/materialized_counter")
+        );
+        Spec::from_path(&created.spec).unwrap();
+        for path in ["Cargo.toml", "src/lib.rs", "src/certora/spec.rs", CONF] {
+            assert!(created.dir.join(path).exists(), "missing {path}");
+        }
+        let conf: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(created.dir.join(CONF)).unwrap())
+                .unwrap();
+        assert_eq!(conf["rule"][0], "rule_add_sanity");
+        assert_eq!(
+            migrate::recorded(&created.dir).unwrap(),
+            Some(spec::Version::new(0, 1, 0))
+        );
+
+        assert!(matches!(
+            new_example("counter", Target::Svm, examples.path(), None, false),
+            Err(ScaffoldError::Exists(_))
+        ));
+        new_example("counter", Target::Svm, examples.path(), None, true).unwrap();
+    }
+}
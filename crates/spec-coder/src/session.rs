@@ -0,0 +1,165 @@
+//! Persistence of runs of the verify-and-fix loop.
+//!
+//! The driver saves its state to [`SESSION_JSON`] in the crate after every
+//! step: the conversation, the iterations, tokens and prover jobs spent, the
+//! files the model wrote and the verdicts of the last check. An interrupted
+//! run, or one that ran out of budget, is resumed from there without asking
+//! the model again for the responses it already gave.
+
+use crate::drift;
+use crate::llm::{Message, Usage};
+use crate::prover::RuleStatus;
+use crate::review::Review;
+use serde::{Deserialize, Serialize};
+use spec::{Spec, SpecError};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The saved state of the last run, relative to the crate root.
+pub const SESSION_JSON: &str = ".spec-coder/session.json";
+
+/// An error saving or resuming a session.
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("there is no session to resume in {}", .0.display())]
+    NotFound(PathBuf),
+    #[error("the session in {} implements another version of the spec", .0.display())]
+    SpecChanged(PathBuf),
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error("failed to access {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("malformed session {}: {source}", .path.display())]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+/// The state of a run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// The fingerprint of the spec being implemented
+    pub spec: String,
+    pub system: Option<String>,
+    pub messages: Vec<Message>,
+    pub iterations: usize,
+    pub usage: Usage,
+    /// Files written from model responses, in the order first written
+    pub written: Vec<PathBuf>,
+    /// The decisions on the files of model responses, in order
+    #[serde(default)]
+    pub reviews: Vec<Review>,
+    /// How many rules were proved
+    #[serde(default)]
+    pub prover_jobs: usize,
+    /// The verdicts of the last check, by rule
+    pub rules: BTreeMap<String, RuleStatus>,
+    /// Rules the last check left unproved for lack of prover jobs
+    #[serde(default)]
+    pub unproved: Vec<String>,
+    /// The feedback the last response received, unless it succeeded
+    pub feedback: Option<String>,
+    pub success: bool,
+}
+
+/// The fingerprint of `spec`, which a resumed session must match.
+fn fingerprint(spec: &Spec) -> Result<String, SpecError> {
+    Ok(drift::fingerprint(spec.to_yaml_string()?.as_bytes()))
+}
+
+impl Session {
+    /// A session implementing `spec`, starting with the `initial` message.
+    pub fn new(spec: &Spec, system: Option<String>, initial: String) -> Result<Session, SpecError> {
+        Ok(Session {
+            spec: fingerprint(spec)?,
+            system,
+            messages: vec![Message::user(initial)],
+            iterations: 0,
+            usage: Usage::default(),
+            written: Vec::new(),
+            reviews: Vec::new(),
+            prover_jobs: 0,
+            rules: BTreeMap::new(),
+            unproved: Vec::new(),
+            feedback: None,
+            success: false,
+        })
+    }
+
+    /// Loads the session saved in the crate in `dir`, which must implement
+    /// `spec`.
+    pub fn load(dir: &Path, spec: &Spec) -> Result<Session, SessionError> {
+        let session =
+            Session::find(dir)?.ok_or_else(|| SessionError::NotFound(dir.to_path_buf()))?;
+        if session.spec != fingerprint(spec)? {
+            return Err(SessionError::SpecChanged(dir.join(SESSION_JSON)));
+        }
+        Ok(session)
+    }
+
+    /// The session saved in the crate in `dir`, if any, whatever spec it
+    /// implements.
+    pub fn find(dir: &Path) -> Result<Option<Session>, SessionError> {
+        let path = dir.join(SESSION_JSON);
+        let json = match std::fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(SessionError::Io { path, source }),
+        };
+        serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|source| SessionError::Json { path, source })
+    }
+
+    /// Saves the session in the crate in `dir`.
+    pub fn save(&self, dir: &Path) -> Result<(), SessionError> {
+        let path = dir.join(SESSION_JSON);
+        let io = |source| SessionError::Io {
+            path: path.clone(),
+            source,
+        };
+        std::fs::create_dir_all(path.parent().expect("has a parent")).map_err(io)?;
+        let json = serde_json::to_string_pretty(self).expect("sessions serialize");
+        std::fs::write(&path, json + "\n").map_err(io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    #[test]
+    fn test_save_and_load() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            Session::load(dir.path(), &spec),
+            Err(SessionError::NotFound(_))
+        ));
+        assert_eq!(Session::find(dir.path()).unwrap(), None);
+
+        let mut session =
+            Session::new(&spec, Some("system".to_string()), "go".to_string()).unwrap();
+        session.messages.push(Message::assistant("done"));
+        session.iterations = 1;
+        session
+            .rules
+            .insert("rule_deposit_sanity".to_string(), RuleStatus::Violated);
+        session.save(dir.path()).unwrap();
+        assert_eq!(Session::load(dir.path(), &spec).unwrap(), session);
+        assert_eq!(Session::find(dir.path()).unwrap(), Some(session.clone()));
+
+        let mut changed = spec.clone();
+        changed.invariants.clear();
+        assert!(matches!(
+            Session::load(dir.path(), &changed),
+            Err(SessionError::SpecChanged(_))
+        ));
+    }
+}
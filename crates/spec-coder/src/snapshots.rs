@@ -0,0 +1,241 @@
+//! Snapshot tests of the generated code.
+//!
+//! A fixed spec, `snapshots/counter.yaml`, is materialized for every target,
+//! and implemented by the driver from the model responses recorded in
+//! `snapshots/responses/`. The files generated, and for the driver the
+//! conversation with the model, are compared with the snapshots in
+//! `snapshots/`, so that a change to the generators, the prompts or the
+//! templates that changes what is generated fails a test with the diff of
+//! the change, instead of going unnoticed.
+//!
+//! The rule compiler is pinned by golden files of its own: every spec in
+//! `snapshots/rules/`, each exercising one part of the lowering, from
+//! invariants and pre- and postconditions to frame conditions and rules the
+//! prover is expected to report violated, is compared by the
+//! `certora.rs` and `certora/spec.rs` it compiles to with the
+//! `snapshots/rules/<name>.snap` next to it.
+//!
+//! A test that fails writes what it got next to the snapshot, as
+//! `<name>.snap.new`. Once the change is intended, run the tests with
+//! `UPDATE_SNAPSHOTS=1` to overwrite the snapshots, and review them in the
+//! commit like any other change.
+
+use crate::driver::{Driver, DriverError, DriverOptions, Verdict, Verifier};
+use crate::llm::{ChatRequest, ChatResponse, ContentBlock, LlmError, Provider, StopReason, Usage};
+use crate::materialize::{self, GeneratedFile};
+use crate::prover::{RuleResult, RuleStatus};
+use crate::session::Session;
+use crate::templates::Templates;
+use similar::TextDiff;
+use spec::{Spec, Target};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The directory of the snapshots and their inputs.
+const SNAPSHOTS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots");
+
+/// The environment variable accepting the new output as the snapshots.
+const UPDATE_SNAPSHOTS: &str = "UPDATE_SNAPSHOTS";
+
+fn spec() -> Spec {
+    let yaml = std::fs::read_to_string(Path::new(SNAPSHOTS_DIR).join("counter.yaml")).unwrap();
+    Spec::from_yaml_str(&yaml).unwrap()
+}
+
+/// `line` with the fields of a provenance stamp that change from run to
+/// run, the version of spec-coder and the time, redacted.
+fn redact(line: &str) -> String {
+    if !line.contains("spec-coder: generated ") {
+        return line.to_string();
+    }
+    line.split(' ')
+        .map(|field| match field.split_once('=') {
+            Some(("generator", _)) => "generator=[version]",
+            Some(("at", _)) => "at=[time]",
+            _ => field,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `files` as the text of a snapshot: each file under a header naming it,
+/// in path order.
+fn render(files: &[GeneratedFile]) -> String {
+    let mut files: Vec<&GeneratedFile> = files.iter().collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut out = String::new();
+    for file in files {
+        out.push_str(&format!("=== {} ===\n", file.path.display()));
+        for line in file.contents.lines() {
+            out.push_str(&redact(line));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Compares `actual` with the snapshot `name`, failing with their diff.
+fn assert_snapshot(name: &str, actual: &str) {
+    let path = Path::new(SNAPSHOTS_DIR).join(format!("{name}.snap"));
+    let new = path.with_extension("snap.new");
+    if std::env::var_os(UPDATE_SNAPSHOTS).is_some() {
+        std::fs::write(&path, actual).unwrap();
+        let _ = std::fs::remove_file(&new);
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_default();
+    if expected == actual {
+        let _ = std::fs::remove_file(&new);
+        return;
+    }
+    std::fs::write(&new, actual).unwrap();
+    let diff = TextDiff::from_lines(expected.as_str(), actual)
+        .unified_diff()
+        .header(&format!("{name}.snap"), &format!("{name}.snap.new"))
+        .to_string();
+    panic!("snapshot `{name}` changed, run with {UPDATE_SNAPSHOTS}=1 to accept it:\n{diff}");
+}
+
+/// Replies with the recorded responses in order, whatever the request.
+struct Recorded(Mutex<Vec<String>>);
+
+impl Recorded {
+    /// The responses in `snapshots/responses/`, in file name order.
+    fn load() -> Recorded {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(Path::new(SNAPSHOTS_DIR).join("responses"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        let responses = paths
+            .iter()
+            .map(|path| std::fs::read_to_string(path).unwrap())
+            .collect();
+        Recorded(Mutex::new(responses))
+    }
+}
+
+impl Provider for Recorded {
+    fn name(&self) -> &str {
+        "recorded"
+    }
+
+    fn model(&self) -> &str {
+        "recorded"
+    }
+
+    fn chat(&self, _: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let text = self.0.lock().unwrap().remove(0);
+        Ok(ChatResponse {
+            content: vec![ContentBlock::Text { text }],
+            stop_reason: StopReason::EndTurn,
+            usage: Usage {
+                input_tokens: 100,
+                output_tokens: 10,
+            },
+        })
+    }
+}
+
+/// Builds unless the processor has a `todo!()` left, and proves every rule.
+struct Todo;
+
+impl Verifier for Todo {
+    fn build(&self, dir: &Path) -> Result<Verdict, DriverError> {
+        let processor = std::fs::read_to_string(dir.join("src/processor.rs")).unwrap();
+        Ok(if processor.contains("todo!()") {
+            Verdict::Failed("panicked at src/processor.rs: not yet implemented".to_string())
+        } else {
+            Verdict::Passed
+        })
+    }
+
+    fn verify(&self, _: &Path, rules: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+        Ok(rules
+            .iter()
+            .map(|rule| RuleResult {
+                rule: rule.clone(),
+                status: RuleStatus::Verified,
+                output: String::new(),
+                counterexample: None,
+                cached: false,
+            })
+            .collect())
+    }
+}
+
+#[test]
+fn test_materialize_snapshots() {
+    let spec = spec();
+    for target in [
+        Target::Svm,
+        Target::Anchor,
+        Target::Pinocchio,
+        Target::Evm,
+        Target::Rust,
+    ] {
+        let files = materialize::generate(&spec, target).unwrap();
+        assert_snapshot(&format!("materialize_{target}"), &render(&files));
+    }
+}
+
+#[test]
+fn test_rule_compiler_golden_files() {
+    let mut fixtures: Vec<PathBuf> = std::fs::read_dir(Path::new(SNAPSHOTS_DIR).join("rules"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yaml"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty());
+    for fixture in fixtures {
+        let spec = Spec::from_yaml_str(&std::fs::read_to_string(&fixture).unwrap()).unwrap();
+        let rules: Vec<GeneratedFile> = materialize::generate(&spec, Target::Svm)
+            .unwrap()
+            .into_iter()
+            .filter(|file| {
+                file.path == Path::new("src/certora.rs") || file.path.starts_with("src/certora")
+            })
+            .collect();
+        assert_eq!(rules.len(), 2, "{}", fixture.display());
+        let name = fixture.file_stem().unwrap().to_str().unwrap();
+        assert_snapshot(&format!("rules/{name}"), &render(&rules));
+    }
+}
+
+#[test]
+fn test_driver_snapshot() {
+    let spec = spec();
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().join("counter");
+    // Written as generated, since `materialize::write` formats with whatever
+    // rustfmt is installed
+    for file in materialize::generate(&spec, Target::Svm).unwrap() {
+        let path = dir.join(&file.path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, file.contents).unwrap();
+    }
+    let provider = Recorded::load();
+    let templates = Templates::builtin("svm");
+    let report = Driver::new(&provider, &Todo, &templates, DriverOptions::default())
+        .run(&spec, &dir, &mut |_| {})
+        .unwrap();
+    assert!(report.success);
+    assert!(provider.0.lock().unwrap().is_empty());
+
+    let session = Session::load(&dir, &spec).unwrap();
+    let mut out = format!("=== system ===\n{}\n", session.system.unwrap_or_default());
+    for message in &session.messages {
+        out.push_str(&format!("=== {:?} ===\n{}\n", message.role, message.text()));
+    }
+    let written: Vec<GeneratedFile> = session
+        .written
+        .iter()
+        .map(|path| GeneratedFile {
+            path: path.clone(),
+            contents: std::fs::read_to_string(dir.join(path)).unwrap(),
+        })
+        .collect();
+    out.push_str(&render(&written));
+    assert_snapshot("driver_svm", &out);
+}
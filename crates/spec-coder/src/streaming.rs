@@ -0,0 +1,296 @@
+//! File blocks of a response as it is streamed.
+//!
+//! [`FileBlocks`] takes the text of a response piece by piece, in whatever
+//! chunks the provider sends it, and hands out each file block as soon as
+//! its closing fence arrives, with the same result as
+//! [`parse_edits`](crate::driver::parse_edits) on the whole response. The
+//! driver writes every block it is handed right away, after [`check_syntax`]
+//! found nothing wrong with it; a block that does not parse ends the
+//! response, since what follows is rarely better.
+
+use crate::driver::FileEdit;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Splits streamed text into file blocks.
+#[derive(Debug, Default)]
+pub struct FileBlocks {
+    /// The text after the last complete line
+    line: String,
+    /// The info string and contents of the block being read, if any
+    open: Option<(String, String)>,
+}
+
+impl FileBlocks {
+    /// Takes the next piece of the response, returning the blocks it
+    /// completes.
+    pub fn push(&mut self, text: &str) -> Vec<FileEdit> {
+        let mut edits = Vec::new();
+        self.line.push_str(text);
+        while let Some(end) = self.line.find('\n') {
+            let line: String = self.line.drain(..=end).collect();
+            let line = line.trim_end_matches('\n');
+            edits.extend(self.line_done(line.strip_suffix('\r').unwrap_or(line)));
+        }
+        edits
+    }
+
+    /// Ends the response, returning the block it cuts off, if any.
+    pub fn finish(mut self) -> Option<FileEdit> {
+        let line = std::mem::take(&mut self.line);
+        if !line.is_empty() {
+            if let Some(edit) = self.line_done(&line) {
+                return Some(edit);
+            }
+        }
+        let (info, contents) = self.open?;
+        edit(&info, contents)
+    }
+
+    fn line_done(&mut self, line: &str) -> Option<FileEdit> {
+        let fence = line.trim_start().strip_prefix("```");
+        match (&mut self.open, fence) {
+            (None, Some(info)) => {
+                self.open = Some((info.to_string(), String::new()));
+                None
+            }
+            (None, None) => None,
+            (Some(_), Some(_)) => {
+                let (info, contents) = self.open.take().expect("an open block");
+                edit(&info, contents)
+            }
+            (Some((_, contents)), None) => {
+                contents.push_str(line);
+                contents.push('\n');
+                None
+            }
+        }
+    }
+}
+
+/// The edit of a block, if its info string is a language and a path.
+fn edit(info: &str, contents: String) -> Option<FileEdit> {
+    match info.split_whitespace().collect::<Vec<_>>()[..] {
+        [_, path] => Some(FileEdit {
+            path: PathBuf::from(path),
+            contents,
+        }),
+        _ => None,
+    }
+}
+
+/// Why a file does not parse.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// The line of the problem, counting from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Checks the contents of a file block as far as its extension tells how:
+/// TOML and JSON are parsed, and in Rust the delimiters must balance.
+/// Anything else passes.
+pub fn check_syntax(path: &Path, contents: &str) -> Result<(), SyntaxError> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("rs") => delimiters(contents),
+        Some("toml") => contents
+            .parse::<toml::Table>()
+            .map(drop)
+            .map_err(|err| SyntaxError {
+                line: err.span().map_or(1, |span| line_of(contents, span.start)),
+                message: err.message().to_string(),
+            }),
+        Some("json") => serde_json::from_str::<serde_json::Value>(contents)
+            .map(drop)
+            .map_err(|err| SyntaxError {
+                line: err.line(),
+                message: err.to_string(),
+            }),
+        _ => Ok(()),
+    }
+}
+
+fn line_of(contents: &str, offset: usize) -> usize {
+    contents[..offset.min(contents.len())].matches('\n').count() + 1
+}
+
+/// Checks that the brackets, braces and parentheses of Rust source balance,
+/// outside comments and string and character literals.
+fn delimiters(source: &str) -> Result<(), SyntaxError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut open: Vec<(char, usize)> = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    let error = |line, message| Err(SyntaxError { line, message });
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            '\n' => line += 1,
+            '/' if next == Some('/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '/' if next == Some('*') => {
+                let start = line;
+                let mut depth = 0;
+                loop {
+                    match (chars.get(i), chars.get(i + 1)) {
+                        (None, _) => return error(start, "unterminated block comment".to_string()),
+                        (Some('/'), Some('*')) => {
+                            depth += 1;
+                            i += 1;
+                        }
+                        (Some('*'), Some('/')) => {
+                            depth -= 1;
+                            i += 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        (Some('\n'), _) => line += 1,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+            }
+            'r' if matches!(next, Some('"' | '#'))
+                && !chars[..i]
+                    .last()
+                    .is_some_and(|c| c.is_alphanumeric() || *c == '_') =>
+            {
+                let start = line;
+                let hashes = chars[i + 1..].iter().take_while(|c| **c == '#').count();
+                if chars.get(i + 1 + hashes) != Some(&'"') {
+                    // An identifier such as `r#type`
+                    i += 1 + hashes;
+                    continue;
+                }
+                i += 2 + hashes;
+                loop {
+                    match chars.get(i) {
+                        None => return error(start, "unterminated raw string".to_string()),
+                        Some('"')
+                            if chars[i + 1..].iter().take_while(|c| **c == '#').count()
+                                >= hashes =>
+                        {
+                            i += hashes;
+                            break;
+                        }
+                        Some('\n') => line += 1,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+            }
+            '"' => {
+                let start = line;
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return error(start, "unterminated string".to_string()),
+                        Some('\\') => i += 1,
+                        Some('"') => break,
+                        Some('\n') => line += 1,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+            }
+            // A character literal, or else a lifetime or label
+            '\'' if next == Some('\\') => {
+                i += 2;
+                while chars.get(i).is_some_and(|c| *c != '\'' && *c != '\n') {
+                    i += 1;
+                }
+            }
+            '\'' if chars.get(i + 2) == Some(&'\'') => i += 2,
+            '(' | '[' | '{' => open.push((c, line)),
+            ')' | ']' | '}' => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                match open.pop() {
+                    Some((opening, _)) if opening == expected => {}
+                    Some((opening, opened)) => {
+                        return error(
+                            line,
+                            format!("`{c}` does not close the `{opening}` of line {opened}"),
+                        )
+                    }
+                    None => return error(line, format!("unmatched `{c}`")),
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    match open.pop() {
+        Some((opening, opened)) => error(opened, format!("`{opening}` is never closed")),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::parse_edits;
+
+    #[test]
+    fn test_file_blocks() {
+        let response = "Here it is.\r\n\n```rust src/processor.rs\nfn a() {}\n```\n\n\
+                        ```rust\nnot a file\n```\n```toml Cargo.toml\n[package]\n```\n\
+                        ```rust src/lib.rs\nmod a;";
+        // In chunks of every size, the blocks are those of the whole response
+        for size in 1..response.len() {
+            let mut blocks = FileBlocks::default();
+            let mut edits = Vec::new();
+            let chars: Vec<char> = response.chars().collect();
+            for chunk in chars.chunks(size) {
+                edits.extend(blocks.push(&chunk.iter().collect::<String>()));
+            }
+            assert_eq!(edits.len(), 2);
+            edits.extend(blocks.finish());
+            assert_eq!(edits, parse_edits(response));
+        }
+    }
+
+    #[test]
+    fn test_check_syntax() {
+        let rust = |source: &str| check_syntax(Path::new("src/lib.rs"), source);
+        assert_eq!(
+            rust(
+                "// }\n/* { /* } */ */\nfn a<'a>(x: &'a str) -> char {\n    \
+                 let _ = (\"}\\\"\", r#\"\"{\"#, b'{', '\\'', r#type);\n    '}'\n}\n"
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            rust("fn a() {\n    if b {\n}\n").unwrap_err().to_string(),
+            "line 1: `{` is never closed"
+        );
+        assert_eq!(
+            rust("fn a() {\n    (b]\n}\n").unwrap_err().to_string(),
+            "line 2: `]` does not close the `(` of line 2"
+        );
+        assert_eq!(
+            rust("fn a() {}\n\"abc\n").unwrap_err().to_string(),
+            "line 2: unterminated string"
+        );
+
+        let toml = check_syntax(Path::new("Cargo.toml"), "[package]\nname = \n").unwrap_err();
+        assert_eq!(toml.line, 2);
+        assert!(check_syntax(Path::new("idl.json"), "{\"a\": [1,}").is_err());
+        assert_eq!(check_syntax(Path::new("README.md"), "```"), Ok(()));
+    }
+}
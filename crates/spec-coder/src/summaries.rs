@@ -0,0 +1,152 @@
+//! The registry of the CPI and syscall summaries the Solana examples share.
+//!
+//! The prover cannot see the programs a crate calls, so each call needs a
+//! summary of what the callee may do. Rather than every crate rolling its
+//! own next to its processor, the calls go through the `cpi` module of
+//! `svm-fv-support` (`examples/svm/fv_support`), whose summaries are
+//! vetted once: each [`Summary`] here names one of them, the direct calls it
+//! stands for and what the rules assume of it. [`audit`] finds the direct
+//! calls left in a crate.
+
+use crate::prover::{cache, ProverError};
+use std::path::{Path, PathBuf};
+
+/// A summarized call of `svm_fv_support::cpi`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Summary {
+    /// The path of the call under `svm_fv_support::cpi`
+    pub name: &'static str,
+    /// What the direct calls it stands for contain
+    pub replaces: &'static [&'static str],
+    /// What the rules assume the call does
+    pub model: &'static str,
+}
+
+/// The vetted summaries.
+pub const SUMMARIES: &[Summary] = &[
+    Summary {
+        name: "system::transfer",
+        replaces: &["system_instruction::transfer("],
+        model: "fails, or moves the lamports between the accounts, conserving their sum; \
+                fails when the payer cannot pay or does not sign",
+    },
+    Summary {
+        name: "token::transfer",
+        replaces: &[
+            "spl_token::instruction::transfer(",
+            "token_instruction::transfer(",
+        ],
+        model: "fails, or moves the tokens between the token accounts, conserving their sum; \
+                fails when the source holds fewer or the authority does not sign",
+    },
+    Summary {
+        name: "sysvar::clock",
+        replaces: &["Clock::get()"],
+        model: "any clock",
+    },
+    Summary {
+        name: "sysvar::rent",
+        replaces: &["Rent::get()"],
+        model: "any rent per byte-year, with the default exemption threshold",
+    },
+];
+
+/// The summary called `name`, e.g. `token::transfer`.
+pub fn get(name: &str) -> Option<&'static Summary> {
+    SUMMARIES.iter().find(|summary| summary.name == name)
+}
+
+/// A direct call a summary stands for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// Relative to the crate root
+    pub path: PathBuf,
+    /// 1-based
+    pub line: usize,
+    pub summary: &'static Summary,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: call `svm_fv_support::cpi::{}` instead, which the rules see as: {}",
+            self.path.display(),
+            self.line,
+            self.summary.name,
+            self.summary.model
+        )
+    }
+}
+
+/// The direct calls in `source`, leaving out comments.
+pub fn scan(path: &Path, source: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let code = line.split("//").next().unwrap_or_default();
+        for summary in SUMMARIES {
+            if summary.replaces.iter().any(|call| code.contains(call)) {
+                findings.push(Finding {
+                    path: path.to_path_buf(),
+                    line: i + 1,
+                    summary,
+                });
+            }
+        }
+    }
+    findings
+}
+
+/// The direct calls in the program sources of the crate in `dir`: its
+/// rules, proofs and tests make no calls on chain.
+pub fn audit(dir: &Path) -> Result<Vec<Finding>, ProverError> {
+    let mut findings = Vec::new();
+    for path in cache::files(&dir.join("src"))? {
+        let relative = path.strip_prefix(dir).unwrap_or(&path).to_path_buf();
+        if relative.extension().is_none_or(|ext| ext != "rs")
+            || relative.starts_with("src/certora")
+            || relative.file_stem().is_some_and(|stem| {
+                ["certora", "proofs", "regressions"].contains(&&*stem.to_string_lossy())
+                    || stem.to_string_lossy().ends_with("tests")
+            })
+        {
+            continue;
+        }
+        let source =
+            std::fs::read_to_string(&path).map_err(|source| ProverError::Io { path, source })?;
+        findings.extend(scan(&relative, &source));
+    }
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("certora")).unwrap();
+        std::fs::write(
+            src.join("processor.rs"),
+            "fn pay() {\n    // not Clock::get()\n    invoke(&system_instruction::transfer(a, b, 1), &[])?;\n    \
+             let now = Clock::get()?.slot;\n    let rent = svm_fv_support::cpi::sysvar::rent()?;\n}\n",
+        )
+        .unwrap();
+        std::fs::write(src.join("spec_tests.rs"), "Clock::get()\n").unwrap();
+        std::fs::write(src.join("certora/spec.rs"), "Clock::get()\n").unwrap();
+
+        let findings = audit(dir.path()).unwrap();
+        let found: Vec<_> = findings
+            .iter()
+            .map(|finding| (finding.line, finding.summary.name))
+            .collect();
+        assert_eq!(found, [(3, "system::transfer"), (4, "sysvar::clock")]);
+        assert!(findings[1]
+            .to_string()
+            .starts_with("src/processor.rs:4: call `svm_fv_support::cpi::sysvar::clock` instead"));
+        assert_eq!(get("token::transfer").unwrap().replaces.len(), 2);
+        assert!(get("token::mint_to").is_none());
+    }
+}
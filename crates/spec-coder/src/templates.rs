@@ -0,0 +1,217 @@
+//! Prompt templates.
+//!
+//! Prompts are Jinja templates, as in the composer, rendered with
+//! minijinja. A template is looked up by name first for the target, e.g.
+//! `svm/system.j2`, and then among those shared by all targets, e.g.
+//! `system.j2`; within each step a project's template directory takes
+//! precedence over the built-in templates under `assets/templates`. A
+//! project thus overrides a prompt by adding a file of the same name.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// The built-in templates, by path below `assets/templates`.
+const BUILTIN: &[(&str, &str)] = &[
+    (
+        "anchor/initial.j2",
+        include_str!("../assets/templates/anchor/initial.j2"),
+    ),
+    (
+        "anchor/system.j2",
+        include_str!("../assets/templates/anchor/system.j2"),
+    ),
+    (
+        "below_throughput.j2",
+        include_str!("../assets/templates/below_throughput.j2"),
+    ),
+    (
+        "build_failure.j2",
+        include_str!("../assets/templates/build_failure.j2"),
+    ),
+    ("critic.j2", include_str!("../assets/templates/critic.j2")),
+    (
+        "critic_request.j2",
+        include_str!("../assets/templates/critic_request.j2"),
+    ),
+    (
+        "diagnoses.j2",
+        include_str!("../assets/templates/diagnoses.j2"),
+    ),
+    ("helpers.j2", include_str!("../assets/templates/helpers.j2")),
+    (
+        "lint_failure.j2",
+        include_str!("../assets/templates/lint_failure.j2"),
+    ),
+    (
+        "no_edits.j2",
+        include_str!("../assets/templates/no_edits.j2"),
+    ),
+    (
+        "over_budget.j2",
+        include_str!("../assets/templates/over_budget.j2"),
+    ),
+    ("patches.j2", include_str!("../assets/templates/patches.j2")),
+    (
+        "pinocchio/initial.j2",
+        include_str!("../assets/templates/pinocchio/initial.j2"),
+    ),
+    (
+        "pinocchio/system.j2",
+        include_str!("../assets/templates/pinocchio/system.j2"),
+    ),
+    (
+        "properties.j2",
+        include_str!("../assets/templates/properties.j2"),
+    ),
+    (
+        "properties_request.j2",
+        include_str!("../assets/templates/properties_request.j2"),
+    ),
+    (
+        "rejected_edits.j2",
+        include_str!("../assets/templates/rejected_edits.j2"),
+    ),
+    (
+        "rule_failure.j2",
+        include_str!("../assets/templates/rule_failure.j2"),
+    ),
+    (
+        "rust/initial.j2",
+        include_str!("../assets/templates/rust/initial.j2"),
+    ),
+    (
+        "rust/system.j2",
+        include_str!("../assets/templates/rust/system.j2"),
+    ),
+    (
+        "specifier.j2",
+        include_str!("../assets/templates/specifier.j2"),
+    ),
+    (
+        "surviving_mutants.j2",
+        include_str!("../assets/templates/surviving_mutants.j2"),
+    ),
+    (
+        "svm/initial.j2",
+        include_str!("../assets/templates/svm/initial.j2"),
+    ),
+    (
+        "svm/system.j2",
+        include_str!("../assets/templates/svm/system.j2"),
+    ),
+];
+
+/// An error rendering a template.
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("no template `{name}` for target `{target}`")]
+    NotFound { name: String, target: String },
+    #[error(transparent)]
+    Render(#[from] minijinja::Error),
+}
+
+/// The prompt templates of a target.
+pub struct Templates {
+    env: minijinja::Environment<'static>,
+    target: String,
+}
+
+impl Templates {
+    /// The built-in templates for `target`, e.g. `svm`.
+    pub fn builtin(target: &str) -> Templates {
+        Templates::new(target, None)
+    }
+
+    /// The templates for `target`, with those in `dir` taking precedence
+    /// over the built-in ones.
+    pub fn with_overrides(target: &str, dir: &Path) -> Templates {
+        Templates::new(target, Some(dir.to_path_buf()))
+    }
+
+    fn new(target: &str, dir: Option<PathBuf>) -> Templates {
+        let mut env = minijinja::Environment::new();
+        env.set_loader(move |name| {
+            if let Some(dir) = &dir {
+                match std::fs::read_to_string(dir.join(name)) {
+                    Ok(source) => return Ok(Some(source)),
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(err) => {
+                        return Err(minijinja::Error::new(
+                            minijinja::ErrorKind::InvalidOperation,
+                            format!("failed to read template {}", dir.join(name).display()),
+                        )
+                        .with_source(err));
+                    }
+                }
+            }
+            Ok(BUILTIN
+                .iter()
+                .find(|(path, _)| *path == name)
+                .map(|(_, source)| source.to_string()))
+        });
+        Templates {
+            env,
+            target: target.to_string(),
+        }
+    }
+
+    /// Renders the template `name`, e.g. `system`, with `context`.
+    pub fn render(&self, name: &str, context: impl Serialize) -> Result<String, TemplateError> {
+        for path in [format!("{}/{name}.j2", self.target), format!("{name}.j2")] {
+            match self.env.get_template(&path) {
+                Ok(template) => return Ok(template.render(context)?),
+                Err(err) if err.kind() == minijinja::ErrorKind::TemplateNotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Err(TemplateError::NotFound {
+            name: name.to_string(),
+            target: self.target.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minijinja::context;
+
+    #[test]
+    fn test_builtin() {
+        let templates = Templates::builtin("svm");
+        let system = templates.render("system", context! {}).unwrap();
+        assert!(system.starts_with("You are implementing a Solana program"));
+        let feedback = templates
+            .render("build_failure", context! { output => "error[E0308]" })
+            .unwrap();
+        assert_eq!(
+            feedback,
+            "The crate does not build or its tests fail:\n\nerror[E0308]"
+        );
+        assert!(matches!(
+            Templates::builtin("evm").render("system", context! {}),
+            Err(TemplateError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("svm")).unwrap();
+        std::fs::write(
+            dir.path().join("svm/system.j2"),
+            "{% include 'base.j2' %} Target: {{ target }}.",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("base.j2"), "Be careful.").unwrap();
+        let templates = Templates::with_overrides("svm", dir.path());
+        assert_eq!(
+            templates
+                .render("system", context! { target => "svm" })
+                .unwrap(),
+            "Be careful. Target: svm."
+        );
+        // Templates that are not overridden remain available
+        assert!(templates.render("no_edits", context! {}).is_ok());
+    }
+}
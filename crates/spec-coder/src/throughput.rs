@@ -0,0 +1,132 @@
+//! Throughput targets of the helpers of a spec.
+//!
+//! A helper of the spec may declare the fewest calls per second it must
+//! sustain. Once everything else passes, the generated `benches/helpers.rs`
+//! is run with criterion and the time of a call of each helper is checked
+//! against its target, and a run whose helpers are too slow does not
+//! succeed.
+
+use spec::Spec;
+
+/// The nanoseconds in a second.
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// The time criterion measured a call of a helper to take.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Timing {
+    pub helper: String,
+    /// The nanoseconds of a call
+    pub nanos: u64,
+}
+
+/// The measured throughput of a helper with a target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rate {
+    pub helper: String,
+    /// The calls per second measured
+    pub calls: u64,
+    /// The calls per second the spec asks for
+    pub target: u64,
+}
+
+impl Rate {
+    pub fn below_target(&self) -> bool {
+        self.calls < self.target
+    }
+}
+
+/// The timings in `output`, as criterion prints them with
+/// `cargo bench --bench helpers -- --output-format bencher`:
+///
+/// ```text
+/// test shares_for_deposit ... bench:          12 ns/iter (+/- 0)
+/// ```
+pub fn parse(output: &str) -> Vec<Timing> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (helper, rest) = line
+                .trim()
+                .strip_prefix("test ")?
+                .split_once(" ... bench:")?;
+            let nanos = rest.split_whitespace().next()?.replace(',', "");
+            Some(Timing {
+                helper: helper.trim().to_string(),
+                nanos: nanos.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// The throughput of every helper of `spec` with a target that was
+/// measured, in the order of the spec.
+pub fn rates(spec: &Spec, timings: &[Timing]) -> Vec<Rate> {
+    spec.helpers
+        .iter()
+        .filter_map(|helper| {
+            let target = helper.throughput?;
+            let timing = timings.iter().find(|timing| timing.helper == helper.name)?;
+            Some(Rate {
+                helper: helper.name.clone(),
+                calls: NANOS_PER_SEC / timing.nanos.max(1),
+                target,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rates() {
+        let output = "\
+   Compiling solana-vault v0.1.0
+test shares_for_deposit ... bench:          25 ns/iter (+/- 1)
+test is_dust ... bench:           0 ns/iter (+/- 0)
+test mul_div ... bench:       1,250 ns/iter (+/- 30)
+test result: ok
+";
+        let timings = parse(output);
+        assert_eq!(timings.len(), 3);
+        assert_eq!(
+            timings[2],
+            Timing {
+                helper: "mul_div".to_string(),
+                nanos: 1250,
+            }
+        );
+
+        let spec = Spec::from_yaml_str(&format!(
+            "{}
+helpers:
+  - {{ name: shares_for_deposit, returns: u64, throughput: 50000000 }}
+  - {{ name: is_dust, returns: bool, throughput: 1000 }}
+  - {{ name: mul_div, returns: u64 }}
+  - {{ name: unmeasured, returns: u64, throughput: 1 }}
+",
+            include_str!("../../../examples/specs/vault.yaml")
+        ))
+        .unwrap();
+        let rates = rates(&spec, &timings);
+        // mul_div has no target and unmeasured was not measured
+        assert_eq!(
+            rates,
+            [
+                Rate {
+                    helper: "shares_for_deposit".to_string(),
+                    calls: 40_000_000,
+                    target: 50_000_000,
+                },
+                Rate {
+                    helper: "is_dust".to_string(),
+                    calls: NANOS_PER_SEC,
+                    target: 1000,
+                },
+            ]
+        );
+        assert!(rates[0].below_target());
+        assert!(!rates[1].below_target());
+    }
+}
@@ -0,0 +1,646 @@
+//! The interactive mode of `implement --tui`.
+//!
+//! The loop runs as usual while the terminal shows the current iteration,
+//! the model's output as it arrives, the build and the verdict of every
+//! rule, instead of a scrolling log. Every change the model makes is shown
+//! as a diff to approve or decline, as [`Approval`]; a rule can be skipped
+//! from then on, or the run aborted, through the [`Control`] of the driver.
+//!
+//! The driver sends an [`Event`] for each step through a channel, and
+//! [`run`] draws them on another thread until the run is over.
+
+use crate::driver::{Control, Progress, Verdict};
+use crate::prover::RuleStatus;
+use crate::review::Reviewer;
+use ratatui::crossterm::event::{self as terminal, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, Clear, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::time::Duration;
+
+/// The most model output kept, in bytes; older output scrolls away.
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// The most log lines kept.
+const MAX_LOG_LINES: usize = 500;
+
+/// Something for the interface to show.
+#[derive(Debug)]
+pub enum Event {
+    Progress(Progress),
+    /// A change to approve, answered through `reply`
+    Review {
+        path: PathBuf,
+        diff: String,
+        reply: Sender<bool>,
+    },
+    /// The run is over, with how it ended
+    Finished(String),
+}
+
+/// Has the user approve each change in the interface.
+pub struct Approval {
+    events: Sender<Event>,
+}
+
+impl Approval {
+    pub fn new(events: Sender<Event>) -> Approval {
+        Approval { events }
+    }
+}
+
+impl Reviewer for Approval {
+    fn name(&self) -> &str {
+        "user"
+    }
+
+    fn review(&self, path: &Path, diff: &str) -> bool {
+        let (reply, answer) = mpsc::channel();
+        let review = Event::Review {
+            path: path.to_path_buf(),
+            diff: diff.to_string(),
+            reply,
+        };
+        // A closed interface declines what is left
+        self.events.send(review).is_ok() && answer.recv().unwrap_or(false)
+    }
+}
+
+/// A change waiting for approval.
+#[derive(Debug)]
+struct PendingReview {
+    path: PathBuf,
+    diff: String,
+    reply: Sender<bool>,
+    scroll: u16,
+}
+
+/// The state of the interface.
+#[derive(Debug)]
+pub struct App {
+    title: String,
+    max_iterations: usize,
+    iteration: usize,
+    /// The model output of the current iteration
+    output: String,
+    log: Vec<String>,
+    build: Option<bool>,
+    lints: Option<bool>,
+    /// The last verdict of every rule, and whether it was cached
+    rules: BTreeMap<String, Option<(RuleStatus, bool)>>,
+    skipped: BTreeSet<String>,
+    selected: usize,
+    review: Option<PendingReview>,
+    approve_all: bool,
+    aborting: bool,
+    finished: Option<String>,
+}
+
+impl App {
+    /// The interface of a run titled `title` with at most `max_iterations`,
+    /// proving `rules`, the rules known before the first check.
+    pub fn new(title: impl Into<String>, max_iterations: usize, rules: &[String]) -> App {
+        App {
+            title: title.into(),
+            max_iterations,
+            iteration: 0,
+            output: String::new(),
+            log: Vec::new(),
+            build: None,
+            lints: None,
+            rules: rules.iter().map(|rule| (rule.clone(), None)).collect(),
+            skipped: BTreeSet::new(),
+            selected: 0,
+            review: None,
+            approve_all: false,
+            aborting: false,
+            finished: None,
+        }
+    }
+
+    fn log(&mut self, line: String) {
+        self.log.push(line);
+        if self.log.len() > MAX_LOG_LINES {
+            self.log.remove(0);
+        }
+    }
+
+    /// Updates the state with `event`, answering reviews the user no longer
+    /// has to.
+    pub fn handle(&mut self, event: Event, control: &Control) {
+        match event {
+            Event::Progress(progress) => self.progress(progress),
+            Event::Review { path, diff, reply } => {
+                if self.approve_all || control.is_aborted() {
+                    let _ = reply.send(self.approve_all && !control.is_aborted());
+                } else {
+                    self.review = Some(PendingReview {
+                        path,
+                        diff,
+                        reply,
+                        scroll: 0,
+                    });
+                }
+            }
+            Event::Finished(message) => {
+                self.log(message.clone());
+                self.finished = Some(message);
+            }
+        }
+    }
+
+    fn progress(&mut self, progress: Progress) {
+        match progress {
+            Progress::Iteration(iteration) => {
+                self.iteration = iteration;
+                self.output.clear();
+                self.build = None;
+                self.lints = None;
+                self.log(format!("Iteration {iteration}"));
+            }
+            Progress::Output(text) => {
+                self.output.push_str(&text);
+                if self.output.len() > MAX_OUTPUT_BYTES {
+                    let mut cut = self.output.len() - MAX_OUTPUT_BYTES;
+                    while !self.output.is_char_boundary(cut) {
+                        cut += 1;
+                    }
+                    self.output.drain(..cut);
+                }
+            }
+            Progress::Wrote(paths) => {
+                for path in paths {
+                    self.log(format!("wrote {}", path.display()));
+                }
+            }
+            Progress::SyntaxError { path, error } => {
+                self.log(format!("{} does not parse: {error}", path.display()))
+            }
+            Progress::Regression(test) => self.log(format!("added regression test {test}")),
+            Progress::Build(verdict) => {
+                let passed = verdict == Verdict::Passed;
+                self.build = Some(passed);
+                self.log(if passed {
+                    "build and tests passed".to_string()
+                } else {
+                    "build or tests failed".to_string()
+                });
+            }
+            Progress::Lints(verdict) => self.lints = Some(verdict == Verdict::Passed),
+            Progress::Rule(result) => {
+                self.rules
+                    .insert(result.rule, Some((result.status, result.cached)));
+            }
+            Progress::ComputeUnits {
+                function,
+                units,
+                budget,
+            } => self.log(format!("{function}: {units} of {budget} compute units")),
+            Progress::Throughput {
+                helper,
+                calls,
+                target,
+            } => self.log(format!("{helper}: {calls} of {target} calls per second")),
+            Progress::Mutants { killed, total } => {
+                self.log(format!("killed {killed} of {total} mutants"))
+            }
+        }
+    }
+
+    /// Acts on a key, returning whether to close the interface.
+    pub fn key(&mut self, key: KeyCode, control: &Control) -> bool {
+        if let Some(review) = &mut self.review {
+            let answer = match key {
+                KeyCode::Char('y') => true,
+                KeyCode::Char('n') => false,
+                KeyCode::Char('a') => {
+                    self.approve_all = true;
+                    true
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    review.scroll = review.scroll.saturating_add(1);
+                    return false;
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    review.scroll = review.scroll.saturating_sub(1);
+                    return false;
+                }
+                KeyCode::PageDown => {
+                    review.scroll = review.scroll.saturating_add(20);
+                    return false;
+                }
+                KeyCode::PageUp => {
+                    review.scroll = review.scroll.saturating_sub(20);
+                    return false;
+                }
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.abort(control);
+                    return false;
+                }
+                _ => return false,
+            };
+            let review = self.review.take().expect("a pending review");
+            let verb = if answer { "approved" } else { "declined" };
+            self.log(format!("{verb} {}", review.path.display()));
+            let _ = review.reply.send(answer);
+            return false;
+        }
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.rules.len().saturating_sub(1));
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('s') if self.finished.is_none() => {
+                if let Some(rule) = self.rules.keys().nth(self.selected).cloned() {
+                    if control.skip(&rule) {
+                        self.log(format!("skipping {rule}"));
+                        self.skipped.insert(rule);
+                    } else {
+                        self.log(format!("proving {rule} again"));
+                        self.skipped.remove(&rule);
+                    }
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                if self.finished.is_some() {
+                    return true;
+                }
+                self.abort(control);
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Aborts the run, declining the change waiting for approval.
+    fn abort(&mut self, control: &Control) {
+        if self.aborting {
+            return;
+        }
+        control.abort();
+        self.aborting = true;
+        if let Some(review) = self.review.take() {
+            let _ = review.reply.send(false);
+        }
+        self.log("aborting after the current step".to_string());
+    }
+
+    /// The status line at the top.
+    fn header(&self) -> Line<'_> {
+        let verdict = |name: &'static str, passed: Option<bool>| match passed {
+            None => Span::raw(format!("{name}: -")).dark_gray(),
+            Some(true) => Span::raw(format!("{name}: passed")).green(),
+            Some(false) => Span::raw(format!("{name}: failed")).red(),
+        };
+        let state = match (&self.finished, self.aborting) {
+            (Some(_), _) => Span::raw("finished").bold(),
+            (None, true) => Span::raw("aborting").yellow().bold(),
+            (None, false) => Span::raw("running").bold(),
+        };
+        Line::from(vec![
+            Span::raw(self.title.clone()).bold(),
+            Span::raw(format!(
+                "  iteration {}/{}  ",
+                self.iteration, self.max_iterations
+            )),
+            verdict("build", self.build),
+            Span::raw("  "),
+            verdict("clippy", self.lints),
+            Span::raw("  "),
+            state,
+        ])
+    }
+
+    /// A line per rule, with its last verdict.
+    fn rule_items(&self) -> Vec<ListItem<'_>> {
+        self.rules
+            .iter()
+            .map(|(rule, verdict)| {
+                let (label, color) = if self.skipped.contains(rule) {
+                    ("skipped".to_string(), Color::DarkGray)
+                } else {
+                    match verdict {
+                        None => ("pending".to_string(), Color::DarkGray),
+                        Some((status, cached)) => {
+                            let color = match status {
+                                RuleStatus::Verified => Color::Green,
+                                RuleStatus::Timeout => Color::Yellow,
+                                _ => Color::Red,
+                            };
+                            let cached = if *cached { " (cached)" } else { "" };
+                            (format!("{status}{cached}"), color)
+                        }
+                    }
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{rule} ")),
+                    Span::styled(label, Style::new().fg(color)),
+                ]))
+            })
+            .collect()
+    }
+
+    /// The keys that do something now.
+    fn keys(&self) -> &'static str {
+        match (&self.review, &self.finished) {
+            (Some(_), _) => "y approve  n decline  a approve all  ↑↓ scroll  q abort",
+            (None, Some(_)) => "↑↓ select  q quit",
+            (None, None) => "↑↓ select  s skip rule  q abort",
+        }
+    }
+}
+
+/// The last lines of `text` that fit in `height`.
+fn tail(text: &str, height: u16) -> Text<'_> {
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines.len().saturating_sub(usize::from(height));
+    Text::from(
+        lines[start..]
+            .iter()
+            .map(|line| Line::raw(*line))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// A diff with added lines in green and removed ones in red.
+fn colored_diff(diff: &str) -> Text<'_> {
+    Text::from(
+        diff.lines()
+            .map(|line| {
+                let style = if line.starts_with("+++") || line.starts_with("---") {
+                    Style::new().add_modifier(Modifier::BOLD)
+                } else if line.starts_with('+') {
+                    Style::new().fg(Color::Green)
+                } else if line.starts_with('-') {
+                    Style::new().fg(Color::Red)
+                } else if line.starts_with("@@") {
+                    Style::new().fg(Color::Cyan)
+                } else {
+                    Style::new()
+                };
+                Line::styled(line, style)
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// The middle of `area`, `percent_x` wide and `percent_y` high.
+fn centered(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let [_, area, _] = Layout::vertical([
+        Constraint::Percentage((100 - percent_y) / 2),
+        Constraint::Percentage(percent_y),
+        Constraint::Percentage((100 - percent_y) / 2),
+    ])
+    .areas(area);
+    let [_, area, _] = Layout::horizontal([
+        Constraint::Percentage((100 - percent_x) / 2),
+        Constraint::Percentage(percent_x),
+        Constraint::Percentage((100 - percent_x) / 2),
+    ])
+    .areas(area);
+    area
+}
+
+/// Draws `app`.
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [header, body, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [output, side] =
+        Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(body);
+    let [rules, log] =
+        Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(side);
+
+    frame.render_widget(Paragraph::new(app.header()), header);
+    let inner = output.height.saturating_sub(2);
+    frame.render_widget(
+        Paragraph::new(tail(&app.output, inner)).block(Block::bordered().title(" Model output ")),
+        output,
+    );
+    let mut selection = ListState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(
+        List::new(app.rule_items())
+            .block(Block::bordered().title(" Rules "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+        rules,
+        &mut selection,
+    );
+    let inner = log.height.saturating_sub(2);
+    frame.render_widget(
+        Paragraph::new(tail(&app.log.join("\n"), inner)).block(Block::bordered().title(" Log ")),
+        log,
+    );
+    frame.render_widget(Paragraph::new(app.keys()).dark_gray(), footer);
+
+    if let Some(review) = &app.review {
+        let area = centered(frame.area(), 90, 80);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(colored_diff(&review.diff))
+                .wrap(Wrap { trim: false })
+                .scroll((review.scroll, 0))
+                .block(
+                    Block::bordered().title(format!(" Write {}? [y/n/a] ", review.path.display())),
+                ),
+            area,
+        );
+    }
+}
+
+/// Shows the run as `events` arrive, until the user closes the interface
+/// once the run is over, or right away if it was aborted. The run is
+/// aborted if the interface fails.
+pub fn run(mut app: App, events: Receiver<Event>, control: &Control) -> std::io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app, &events, control);
+    ratatui::restore();
+    if result.is_err() {
+        control.abort();
+    }
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    events: &Receiver<Event>,
+    control: &Control,
+) -> std::io::Result<()> {
+    loop {
+        loop {
+            match events.try_recv() {
+                Ok(event) => app.handle(event, control),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if app.finished.is_none() {
+                        app.finished = Some("the run ended".to_string());
+                    }
+                    break;
+                }
+            }
+        }
+        if app.finished.is_some() && app.aborting {
+            return Ok(());
+        }
+        terminal.draw(|frame| draw(frame, app))?;
+        if !terminal::poll(Duration::from_millis(50))? {
+            continue;
+        }
+        if let terminal::Event::Key(key) = terminal::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let code = match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => KeyCode::Esc,
+                code => code,
+            };
+            if app.key(code, control) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::RuleResult;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn result(rule: &str, status: RuleStatus) -> Progress {
+        Progress::Rule(RuleResult {
+            rule: rule.to_string(),
+            status,
+            output: String::new(),
+            counterexample: None,
+            cached: false,
+        })
+    }
+
+    fn screen(app: &App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| draw(frame, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let mut screen = String::new();
+        for y in 0..buffer.area.height {
+            for x in 0..buffer.area.width {
+                screen.push_str(buffer[(x, y)].symbol());
+            }
+            screen.push('\n');
+        }
+        screen
+    }
+
+    #[test]
+    fn test_progress_and_skip() {
+        let control = Control::default();
+        let rules = [
+            "rule_deposit_sanity".to_string(),
+            "rule_withdraw_sanity".to_string(),
+        ];
+        let mut app = App::new("vault", 8, &rules);
+        app.handle(Event::Progress(Progress::Iteration(2)), &control);
+        for piece in ["```rust src/processor.rs\n", "fn deposit() {}\n"] {
+            app.handle(
+                Event::Progress(Progress::Output(piece.to_string())),
+                &control,
+            );
+        }
+        app.handle(Event::Progress(Progress::Build(Verdict::Passed)), &control);
+        app.handle(
+            Event::Progress(result("rule_deposit_sanity", RuleStatus::Verified)),
+            &control,
+        );
+        assert_eq!(app.iteration, 2);
+        assert_eq!(app.build, Some(true));
+
+        assert!(!app.key(KeyCode::Down, &control));
+        app.key(KeyCode::Char('s'), &control);
+        assert_eq!(
+            control.skipped(),
+            BTreeSet::from(["rule_withdraw_sanity".to_string()])
+        );
+        let screen = screen(&app);
+        assert!(
+            screen.contains("vault  iteration 2/8  build: passed"),
+            "{screen}"
+        );
+        assert!(screen.contains("fn deposit() {}"), "{screen}");
+        assert!(screen.contains("rule_deposit_sanity VERIFIED"), "{screen}");
+        assert!(screen.contains("rule_withdraw_sanity skipped"), "{screen}");
+        assert!(screen.contains("skipping rule_withdraw_sanity"), "{screen}");
+
+        app.key(KeyCode::Char('s'), &control);
+        assert!(control.skipped().is_empty());
+        assert!(!app.key(KeyCode::Char('q'), &control));
+        assert!(control.is_aborted());
+        app.handle(Event::Finished("aborted".to_string()), &control);
+        assert!(app.key(KeyCode::Char('q'), &control));
+    }
+
+    #[test]
+    fn test_review() {
+        let control = Control::default();
+        let mut app = App::new("vault", 8, &[]);
+        let review = |app: &mut App| {
+            let (reply, answer) = mpsc::channel();
+            let diff = "--- a/src/lib.rs\n+++ b/src/lib.rs\n-old\n+new\n".to_string();
+            let path = PathBuf::from("src/lib.rs");
+            app.handle(Event::Review { path, diff, reply }, &control);
+            answer
+        };
+
+        let answer = review(&mut app);
+        let screen = screen(&app);
+        assert!(screen.contains("Write src/lib.rs? [y/n/a]"), "{screen}");
+        assert!(screen.contains("+new"), "{screen}");
+        app.key(KeyCode::Char('x'), &control);
+        assert!(answer.try_recv().is_err());
+        app.key(KeyCode::Char('n'), &control);
+        assert_eq!(answer.try_recv(), Ok(false));
+
+        let answer = review(&mut app);
+        app.key(KeyCode::Char('a'), &control);
+        assert_eq!(answer.try_recv(), Ok(true));
+        // Nothing is asked once everything is approved
+        assert_eq!(review(&mut app).try_recv(), Ok(true));
+        assert!(app.review.is_none());
+
+        let mut app = App::new("vault", 8, &[]);
+        let answer = review(&mut app);
+        app.key(KeyCode::Esc, &control);
+        assert_eq!(answer.try_recv(), Ok(false));
+        assert_eq!(review(&mut app).try_recv(), Ok(false));
+    }
+
+    #[test]
+    fn test_approval() {
+        let (events, received) = mpsc::channel();
+        let approval = Approval::new(events);
+        let received = std::thread::scope(|scope| {
+            let ui = scope.spawn(move || {
+                match received.recv().unwrap() {
+                    Event::Review { path, reply, .. } => {
+                        assert_eq!(path, Path::new("src/lib.rs"));
+                        reply.send(true).unwrap();
+                    }
+                    event => panic!("unexpected {event:?}"),
+                }
+                received
+            });
+            assert!(approval.review(Path::new("src/lib.rs"), "+new\n"));
+            ui.join().unwrap()
+        });
+        drop(received);
+        assert!(!approval.review(Path::new("src/lib.rs"), "+new\n"));
+    }
+}
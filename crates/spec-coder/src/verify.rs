@@ -0,0 +1,474 @@
+//! Verifying a materialized example in one step.
+//!
+//! Proving an example by hand takes a build with the `certora` feature, a
+//! prover configuration in the manifest and one prover job per rule.
+//! [`verify`] takes those steps for the crate of an example, found by its
+//! name in the [registry](crate::registry) or by its directory: the
+//! configuration, `[package.metadata.certora]`, is checked and written if
+//! the manifest has none, the crate is built and tested with the feature,
+//! and its rules, or the ones asked for, are proved with their verdicts
+//! reported as each batch of jobs finishes. The verdicts of an example with
+//! a known spec are then [recorded](crate::results::record) with its
+//! results.
+
+use crate::driver::{DriverError, Verdict, Verifier};
+use crate::materialize::{self, CERTORA_METADATA};
+use crate::prover::{self, ProverError, RuleResult, RuleStatus};
+use crate::registry::{Registry, RegistryError};
+use spec::Target;
+use std::path::{Path, PathBuf};
+
+/// An error verifying an example.
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError {
+    #[error("no example called `{0}` under the examples directory")]
+    UnknownExample(String),
+    #[error("{target} examples are verified with `certoraRun certora/conf/default.conf` in their directory")]
+    Unsupported { target: Target },
+    #[error("the crate has no rule matching `{rule}`; its rules are: {}", .rules.join(", "))]
+    UnknownRule { rule: String, rules: Vec<String> },
+    #[error("no rule of the crate is tagged `{tag}`; its tags are: {}", .tags.join(", "))]
+    UnknownTag { tag: String, tags: Vec<String> },
+    #[error("invalid prover configuration in {}: {message}", .path.display())]
+    Conf { path: PathBuf, message: String },
+    #[error("failed to access {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
+    #[error(transparent)]
+    Prover(#[from] ProverError),
+    #[error(transparent)]
+    Driver(#[from] DriverError),
+}
+
+/// Something that happened while verifying.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyEvent {
+    /// The prover configuration was missing, and these files were written
+    /// to add it
+    ConfWritten(Vec<PathBuf>),
+    Build(Verdict),
+    /// The rules are being proved
+    Proving(Vec<String>),
+    Rule(RuleResult),
+}
+
+/// The outcome of verifying an example.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Summary {
+    pub build: Verdict,
+    /// The verdicts on the rules, none if the build failed
+    pub results: Vec<RuleResult>,
+}
+
+impl Summary {
+    /// Whether the crate built and every rule was verified.
+    pub fn passed(&self) -> bool {
+        self.build == Verdict::Passed
+            && self
+                .results
+                .iter()
+                .all(|result| result.status == RuleStatus::Verified)
+    }
+
+    /// The number of rules with each verdict, in the order of
+    /// [`RuleStatus`], leaving out verdicts no rule got.
+    pub fn counts(&self) -> Vec<(RuleStatus, usize)> {
+        [
+            RuleStatus::Verified,
+            RuleStatus::Violated,
+            RuleStatus::Timeout,
+            RuleStatus::SanityFailed,
+            RuleStatus::Error,
+        ]
+        .into_iter()
+        .map(|status| {
+            let count = self
+                .results
+                .iter()
+                .filter(|result| result.status == status)
+                .count();
+            (status, count)
+        })
+        .filter(|&(_, count)| count > 0)
+        .collect()
+    }
+}
+
+/// The crate directory of `example`, and the spec it is materialized from
+/// if the registry knows it: the directory itself if it holds a crate, or
+/// else the example of that name materialized under `examples_dir`.
+pub fn locate(
+    examples_dir: &Path,
+    example: &str,
+) -> Result<(PathBuf, Option<PathBuf>), VerifyError> {
+    let path = Path::new(example);
+    if path.join("Cargo.toml").is_file() {
+        // A crate outside the examples directory has no spec to go by
+        let dir = path.canonicalize().ok();
+        let spec = Registry::scan(examples_dir).ok().and_then(|registry| {
+            registry
+                .examples
+                .into_iter()
+                .find(|example| example.dir.canonicalize().ok() == dir)?
+                .spec
+        });
+        return Ok((path.to_path_buf(), spec));
+    }
+    let registry = Registry::scan(examples_dir)?;
+    let example = registry
+        .get(example)
+        .ok_or_else(|| VerifyError::UnknownExample(example.to_string()))?;
+    if example.target == Target::Evm {
+        return Err(VerifyError::Unsupported {
+            target: example.target,
+        });
+    }
+    Ok((example.dir.clone(), example.spec.clone()))
+}
+
+/// Checks the prover configuration of the crate in `dir`: the `certora`
+/// feature the rules are built with, and `[package.metadata.certora]` with
+/// the sources and the summary files it names. A manifest without the
+/// configuration gets the one crates are materialized with, and the files
+/// written for it are returned.
+pub fn prepare_conf(dir: &Path) -> Result<Vec<PathBuf>, VerifyError> {
+    let path = dir.join("Cargo.toml");
+    let io = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| VerifyError::Io { path, source }
+    };
+    let conf = |message: String| VerifyError::Conf {
+        path: path.clone(),
+        message,
+    };
+    let text = std::fs::read_to_string(&path).map_err(io(&path))?;
+    let manifest: toml::Table = toml::from_str(&text).map_err(|err| conf(err.to_string()))?;
+    if manifest
+        .get("features")
+        .and_then(|features| features.get("certora"))
+        .is_none()
+    {
+        return Err(conf(
+            "no `certora` feature to build the rules with".to_string(),
+        ));
+    }
+
+    let metadata = manifest
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get("certora"));
+    let Some(metadata) = metadata else {
+        let mut written = Vec::new();
+        for file in materialize::certora_summaries() {
+            let target = dir.join(&file.path);
+            if target.exists() {
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(io(parent))?;
+            }
+            std::fs::write(&target, &file.contents).map_err(io(&target))?;
+            written.push(file.path);
+        }
+        let separator = if text.ends_with('\n') { "\n" } else { "\n\n" };
+        std::fs::write(&path, format!("{text}{separator}{CERTORA_METADATA}")).map_err(io(&path))?;
+        written.insert(0, PathBuf::from("Cargo.toml"));
+        return Ok(written);
+    };
+
+    let list = |key: &str| -> Result<Vec<String>, VerifyError> {
+        let Some(value) = metadata.get(key) else {
+            return Ok(Vec::new());
+        };
+        value
+            .as_array()
+            .and_then(|items| {
+                items
+                    .iter()
+                    .map(|item| item.as_str().map(str::to_string))
+                    .collect()
+            })
+            .ok_or_else(|| conf(format!("`{key}` is not a list of paths")))
+    };
+    if list("sources")?.is_empty() {
+        return Err(conf("`sources` lists no files for the prover".to_string()));
+    }
+    for key in ["solana_inlining", "solana_summaries"] {
+        for file in list(key)? {
+            if !dir.join(&file).is_file() {
+                return Err(conf(format!("`{key}` names {file}, which does not exist")));
+            }
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// The rules of the crate in `dir` matching any of the names or patterns
+/// in `only` and tagged with any of `tags`, see [`prover::find_tags`]; an
+/// empty list selects every rule.
+pub fn select_rules(
+    dir: &Path,
+    only: &[String],
+    tags: &[String],
+) -> Result<Vec<String>, VerifyError> {
+    let mut rules = prover::find_rules(dir)?;
+    if let Some(pattern) = only
+        .iter()
+        .find(|pattern| prover::tagged(&rules, std::slice::from_ref(pattern)).is_empty())
+    {
+        return Err(VerifyError::UnknownRule {
+            rule: pattern.clone(),
+            rules,
+        });
+    }
+    if !only.is_empty() {
+        rules = prover::tagged(&rules, only).into_iter().cloned().collect();
+    }
+    if tags.is_empty() {
+        return Ok(rules);
+    }
+    let registry = prover::find_tags(dir)?;
+    let mut patterns = Vec::new();
+    for tag in tags {
+        match registry.get(tag) {
+            Some(tagged) => patterns.extend(tagged.iter().cloned()),
+            None => {
+                return Err(VerifyError::UnknownTag {
+                    tag: tag.clone(),
+                    tags: registry.into_keys().collect(),
+                })
+            }
+        }
+    }
+    Ok(prover::tagged(&rules, &patterns)
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
+/// Verifies the crate in `dir`, proving the rules [`select_rules`] picks
+/// from `only` and `tags`, `jobs` at a time once the crate builds.
+pub fn verify(
+    dir: &Path,
+    only: &[String],
+    tags: &[String],
+    verifier: &dyn Verifier,
+    jobs: usize,
+    on_event: &mut dyn FnMut(&VerifyEvent),
+) -> Result<Summary, VerifyError> {
+    let written = prepare_conf(dir)?;
+    if !written.is_empty() {
+        on_event(&VerifyEvent::ConfWritten(written));
+    }
+    let rules = select_rules(dir, only, tags)?;
+    let build = verifier.build(dir)?;
+    on_event(&VerifyEvent::Build(build.clone()));
+    if build != Verdict::Passed {
+        return Ok(Summary {
+            build,
+            results: Vec::new(),
+        });
+    }
+    on_event(&VerifyEvent::Proving(rules.clone()));
+    let mut results = Vec::new();
+    // In batches of as many rules as run at once, so verdicts are reported
+    // as they come rather than all at the end
+    for batch in rules.chunks(jobs.max(1)) {
+        for result in verifier.verify(dir, batch)? {
+            on_event(&VerifyEvent::Rule(result.clone()));
+            results.push(result);
+        }
+    }
+    Ok(Summary { build, results })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spec::Spec;
+    use std::cell::RefCell;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    /// Passes every build, violating the sanity rule of `withdraw` and
+    /// verifying the other rules, and records the batches proved.
+    #[derive(Default)]
+    struct Recording {
+        batches: RefCell<Vec<Vec<String>>>,
+    }
+
+    impl Verifier for Recording {
+        fn build(&self, _: &Path) -> Result<Verdict, DriverError> {
+            Ok(Verdict::Passed)
+        }
+
+        fn verify(&self, _: &Path, rules: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+            self.batches.borrow_mut().push(rules.to_vec());
+            Ok(rules
+                .iter()
+                .map(|rule| RuleResult {
+                    rule: rule.clone(),
+                    status: match rule.as_str() {
+                        "rule_withdraw_sanity" => RuleStatus::Violated,
+                        _ => RuleStatus::Verified,
+                    },
+                    output: String::new(),
+                    counterexample: None,
+                    cached: false,
+                })
+                .collect())
+        }
+    }
+
+    fn vault(examples: &Path) -> PathBuf {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = materialize::crate_dir(examples, &spec, Target::Svm);
+        let files = materialize::generate(&spec, Target::Svm).unwrap();
+        materialize::write(&files, &dir, false).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_locate() {
+        let examples = tempfile::tempdir().unwrap();
+        let dir = vault(examples.path());
+        assert_eq!(
+            locate(examples.path(), "vault").unwrap(),
+            (dir.clone(), None)
+        );
+        std::fs::create_dir_all(examples.path().join("specs")).unwrap();
+        let spec = examples.path().join("specs/vault.yaml");
+        std::fs::write(&spec, VAULT).unwrap();
+        let by_path = locate(examples.path(), dir.to_str().unwrap()).unwrap();
+        assert_eq!(by_path, (dir, Some(spec)));
+        assert!(matches!(
+            locate(examples.path(), "pool"),
+            Err(VerifyError::UnknownExample(name)) if name == "pool"
+        ));
+    }
+
+    #[test]
+    fn test_prepare_conf() {
+        let examples = tempfile::tempdir().unwrap();
+        let dir = vault(examples.path());
+        assert_eq!(prepare_conf(&dir).unwrap(), Vec::<PathBuf>::new());
+
+        // A manifest without the configuration gets it, with the summaries
+        let manifest = std::fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+        let (bare, _) = manifest.split_once("[package.metadata.certora]").unwrap();
+        std::fs::write(dir.join("Cargo.toml"), bare).unwrap();
+        std::fs::remove_dir_all(dir.join("certora")).unwrap();
+        let written = prepare_conf(&dir).unwrap();
+        assert_eq!(written[0], PathBuf::from("Cargo.toml"));
+        assert_eq!(written.len(), 3);
+        assert!(dir
+            .join("certora/summaries/cvlr_summaries_core.txt")
+            .is_file());
+        assert_eq!(prepare_conf(&dir).unwrap(), Vec::<PathBuf>::new());
+
+        std::fs::remove_file(dir.join("certora/summaries/cvlr_inlining_core.txt")).unwrap();
+        let err = prepare_conf(&dir).unwrap_err().to_string();
+        assert!(err.contains("`solana_inlining` names certora/summaries/cvlr_inlining_core.txt"));
+
+        let manifest = std::fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            manifest.replace("certora = [", "fv = ["),
+        )
+        .unwrap();
+        let err = prepare_conf(&dir).unwrap_err().to_string();
+        assert!(err.contains("no `certora` feature"), "{err}");
+    }
+
+    #[test]
+    fn test_verify() {
+        let examples = tempfile::tempdir().unwrap();
+        let dir = vault(examples.path());
+        let verifier = Recording::default();
+        let events = RefCell::new(Vec::new());
+        let summary = verify(&dir, &[], &[], &verifier, 2, &mut |event| {
+            events.borrow_mut().push(event.clone())
+        })
+        .unwrap();
+        let rules = prover::find_rules(&dir).unwrap();
+        assert_eq!(summary.results.len(), rules.len());
+        assert!(!summary.passed());
+        assert_eq!(
+            summary.counts(),
+            [
+                (RuleStatus::Verified, rules.len() - 1),
+                (RuleStatus::Violated, 1)
+            ]
+        );
+        assert!(verifier
+            .batches
+            .borrow()
+            .iter()
+            .all(|batch| batch.len() <= 2));
+        let events = events.into_inner();
+        assert_eq!(events[0], VerifyEvent::Build(Verdict::Passed));
+        assert_eq!(events[1], VerifyEvent::Proving(rules.clone()));
+        assert_eq!(events.len(), 2 + rules.len());
+
+        let only = ["rule_deposit_sanity".to_string()];
+        let summary = verify(&dir, &only, &[], &verifier, 4, &mut |_| {}).unwrap();
+        assert!(summary.passed());
+        assert_eq!(verifier.batches.borrow().last().unwrap(), &only);
+        let unknown = ["rule_nothing".to_string()];
+        let err = verify(&dir, &unknown, &[], &verifier, 1, &mut |_| {}).unwrap_err();
+        assert!(matches!(err, VerifyError::UnknownRule { rule, .. } if rule == "rule_nothing"));
+    }
+
+    #[test]
+    fn test_select_rules() {
+        let examples = tempfile::tempdir().unwrap();
+        let dir = vault(examples.path());
+        let manifest = std::fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            format!(
+                "{manifest}\n[package.metadata.certora.tags]\n\
+                 fast = [\"rule_*_sanity\"]\nsolvency = [\"rule_*_solvency\"]\n"
+            ),
+        )
+        .unwrap();
+        let select = |only: &[&str], tags: &[&str]| {
+            let only: Vec<String> = only.iter().map(|s| s.to_string()).collect();
+            let tags: Vec<String> = tags.iter().map(|s| s.to_string()).collect();
+            select_rules(&dir, &only, &tags)
+        };
+        assert_eq!(
+            select(&["rule_deposit_*"], &[]).unwrap(),
+            [
+                "rule_deposit_sanity",
+                "rule_deposit_ensures",
+                "rule_deposit_solvency"
+            ]
+        );
+        assert_eq!(
+            select(&[], &["fast"]).unwrap(),
+            [
+                "rule_deposit_sanity",
+                "rule_withdraw_sanity",
+                "rule_reward_sanity"
+            ]
+        );
+        // Patterns and tags both narrow the selection
+        assert_eq!(
+            select(&["rule_withdraw_*"], &["fast", "solvency"]).unwrap(),
+            ["rule_withdraw_sanity", "rule_withdraw_solvency"]
+        );
+        assert!(matches!(
+            select(&["rule_*_nothing"], &[]),
+            Err(VerifyError::UnknownRule { rule, .. }) if rule == "rule_*_nothing"
+        ));
+        assert!(matches!(
+            select(&[], &["slow"]),
+            Err(VerifyError::UnknownTag { tag, tags }) if tag == "slow" && tags == ["fast", "solvency"]
+        ));
+    }
+}
@@ -0,0 +1,241 @@
+//! Watching a crate and its spec for changes.
+//!
+//! The spec and the sources of the crate are polled for changes. After every
+//! change the crate is brought up to date with the spec, see
+//! [`incremental::regenerate`], and built and tested at once; the rules are
+//! proved once the files have been left alone for a while, so that a burst
+//! of edits costs one prover run. The prover [cache](crate::prover::cache)
+//! keeps that run to the rules the edits affected.
+
+use crate::driver::{DriverError, Verdict, Verifier};
+use crate::incremental::{self, Plan};
+use crate::prover::{self, RuleResult};
+use spec::{Spec, Target};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The parts of a crate whose changes are checked, relative to its root.
+const WATCHED: &[&str] = &["Cargo.toml", "src", "certora"];
+
+/// How often and how patiently to watch.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WatchOptions {
+    /// How often to look for changes
+    pub interval: Duration,
+    /// How long the files must be left alone before the rules are proved
+    pub debounce: Duration,
+    /// Skip the prover, only building and testing
+    pub no_fv: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> WatchOptions {
+        WatchOptions {
+            interval: Duration::from_millis(500),
+            debounce: Duration::from_secs(5),
+            no_fv: false,
+        }
+    }
+}
+
+/// Something that happened while watching.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// Files changed since the last check
+    Changed(Vec<PathBuf>),
+    /// The crate was brought up to date with a changed spec
+    Regenerated(Plan),
+    Build(Verdict),
+    Rule(RuleResult),
+    /// A check failed to run; watching goes on
+    Error(String),
+}
+
+/// The modification time and length of every watched file.
+type Snapshot = BTreeMap<PathBuf, (SystemTime, u64)>;
+
+fn snapshot(spec: &Path, dir: &Path) -> Snapshot {
+    let mut files = Snapshot::new();
+    let mut pending = vec![spec.to_path_buf()];
+    pending.extend(WATCHED.iter().map(|path| dir.join(path)));
+    while let Some(path) = pending.pop() {
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if let Ok(entries) = std::fs::read_dir(&path) {
+                pending.extend(entries.filter_map(Result::ok).map(|entry| entry.path()));
+            }
+        } else if let Ok(modified) = metadata.modified() {
+            files.insert(path, (modified, metadata.len()));
+        }
+    }
+    files
+}
+
+/// The files added, changed or removed between `before` and `after`.
+fn changes(before: &Snapshot, after: &Snapshot) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = after
+        .iter()
+        .filter(|(path, stamp)| before.get(*path) != Some(stamp))
+        .map(|(path, _)| path.clone())
+        .collect();
+    changed.extend(
+        before
+            .keys()
+            .filter(|path| !after.contains_key(*path))
+            .cloned(),
+    );
+    changed.sort();
+    changed
+}
+
+/// Watches the crate in `dir` implementing the spec at `spec_path`, checking
+/// it once at the start and again after every change, until `stop` returns
+/// true. `stop` is asked once per poll.
+pub fn watch(
+    spec_path: &Path,
+    target: Target,
+    dir: &Path,
+    verifier: &dyn Verifier,
+    options: WatchOptions,
+    on_event: &mut dyn FnMut(&WatchEvent),
+    stop: &mut dyn FnMut() -> bool,
+) -> Result<(), DriverError> {
+    let mut seen = snapshot(spec_path, dir);
+    let mut build = true;
+    let mut prove_at = None;
+    while !stop() {
+        let current = snapshot(spec_path, dir);
+        let changed = changes(&seen, &current);
+        if !changed.is_empty() {
+            on_event(&WatchEvent::Changed(changed.clone()));
+            if changed.iter().any(|path| path == spec_path) {
+                let regenerated = Spec::from_path(spec_path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|spec| {
+                        incremental::regenerate(&spec, target, dir).map_err(|err| err.to_string())
+                    });
+                match regenerated {
+                    Ok(plan) => on_event(&WatchEvent::Regenerated(plan)),
+                    Err(err) => on_event(&WatchEvent::Error(err)),
+                }
+            }
+            build = true;
+        }
+        // Files written while checking are not changes of their own
+        seen = snapshot(spec_path, dir);
+
+        if build {
+            build = false;
+            prove_at = None;
+            let verdict = verifier.build(dir)?;
+            on_event(&WatchEvent::Build(verdict.clone()));
+            if verdict == Verdict::Passed && !options.no_fv {
+                prove_at = Some(Instant::now() + options.debounce);
+            }
+        } else if prove_at.is_some_and(|at| Instant::now() >= at) {
+            prove_at = None;
+            match prover::find_rules(dir) {
+                Ok(rules) => {
+                    for result in verifier.verify(dir, &rules)? {
+                        on_event(&WatchEvent::Rule(result));
+                    }
+                }
+                Err(err) => on_event(&WatchEvent::Error(err.to_string())),
+            }
+        }
+        std::thread::sleep(options.interval);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materialize;
+    use crate::prover::RuleStatus;
+    use std::cell::{Cell, RefCell};
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    /// Passes every build and proves every rule, counting both.
+    #[derive(Default)]
+    struct Counting {
+        builds: Cell<usize>,
+        proofs: Cell<usize>,
+    }
+
+    impl Verifier for Counting {
+        fn build(&self, _: &Path) -> Result<Verdict, DriverError> {
+            self.builds.set(self.builds.get() + 1);
+            Ok(Verdict::Passed)
+        }
+
+        fn verify(&self, _: &Path, rules: &[String]) -> Result<Vec<RuleResult>, DriverError> {
+            self.proofs.set(self.proofs.get() + 1);
+            Ok(rules
+                .iter()
+                .map(|rule| RuleResult {
+                    rule: rule.clone(),
+                    status: RuleStatus::Verified,
+                    output: String::new(),
+                    counterexample: None,
+                    cached: false,
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_watch() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let spec_path = dir.path().join("vault.yaml");
+        std::fs::write(&spec_path, VAULT).unwrap();
+        let crate_dir = dir.path().join("vault");
+        let files = materialize::generate(&spec, Target::Svm).unwrap();
+        materialize::write(&files, &crate_dir, true).unwrap();
+
+        let verifier = Counting::default();
+        let options = WatchOptions {
+            interval: Duration::from_millis(5),
+            debounce: Duration::from_millis(20),
+            no_fv: false,
+        };
+        let events = RefCell::new(Vec::new());
+        let mut polls = 0;
+        let processor = crate_dir.join("src/processor.rs");
+        watch(
+            &spec_path,
+            Target::Svm,
+            &crate_dir,
+            &verifier,
+            options,
+            &mut |event| events.borrow_mut().push(event.clone()),
+            &mut || {
+                polls += 1;
+                // Edit the processor once the first proof is done
+                if verifier.proofs.get() == 1 && verifier.builds.get() == 1 {
+                    let source = std::fs::read_to_string(&processor).unwrap();
+                    std::fs::write(&processor, source + "\n// edited\n").unwrap();
+                }
+                verifier.proofs.get() == 2 || polls > 2_000
+            },
+        )
+        .unwrap();
+
+        assert_eq!(verifier.proofs.get(), 2);
+        assert_eq!(verifier.builds.get(), 2);
+        let events = events.into_inner();
+        assert!(matches!(events[0], WatchEvent::Build(Verdict::Passed)));
+        assert!(events
+            .iter()
+            .any(|event| *event == WatchEvent::Changed(vec![processor.clone()])));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            WatchEvent::Rule(result) if result.rule == "rule_deposit_sanity"
+        )));
+    }
+}
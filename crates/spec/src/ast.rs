@@ -0,0 +1,659 @@
+//! Typed AST of a specification.
+
+use crate::expr::Expr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A complete specification of one program.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Spec {
+    /// Program name, used for the generated crate
+    pub name: String,
+    /// Semantic version of the specification, recorded with the crates
+    /// materialized from it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<Version>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Where the requirements come from, e.g. the URL of the issue the spec
+    /// was drafted from, recorded with the crates materialized from it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub target: Target,
+    /// Account types making up the program state
+    #[serde(default)]
+    pub state: Vec<Account>,
+    /// Entry points; `instructions` is accepted as an alias
+    #[serde(default, alias = "instructions")]
+    pub functions: Vec<Function>,
+    /// Pure helpers the functions are implemented with, benchmarked on
+    /// their own
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub helpers: Vec<Helper>,
+    #[serde(default)]
+    pub invariants: Vec<Invariant>,
+    /// Prover settings of the rules matching each key, a rule name or a
+    /// pattern of names where `*` stands for any run of characters
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prover: BTreeMap<String, ProverSettings>,
+}
+
+impl Spec {
+    /// Looks up a state account type by name.
+    pub fn account(&self, name: &str) -> Option<&Account> {
+        self.state.iter().find(|account| account.name == name)
+    }
+
+    /// Looks up a function by name.
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    /// Looks up a helper by name.
+    pub fn helper(&self, name: &str) -> Option<&Helper> {
+        self.helpers.iter().find(|helper| helper.name == name)
+    }
+}
+
+/// The platform the specified program targets.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    /// Solana programs
+    #[default]
+    Svm,
+    /// Solana programs written with the Anchor framework
+    Anchor,
+    /// Solana programs written against the no-std pinocchio SDK
+    Pinocchio,
+    /// Solidity contracts
+    Evm,
+    /// Plain Rust libraries, e.g. parsers, codecs and state machines
+    Rust,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Target::Svm => "svm",
+            Target::Anchor => "anchor",
+            Target::Pinocchio => "pinocchio",
+            Target::Evm => "evm",
+            Target::Rust => "rust",
+        })
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Target, String> {
+        match s {
+            "svm" => Ok(Target::Svm),
+            "anchor" => Ok(Target::Anchor),
+            "pinocchio" => Ok(Target::Pinocchio),
+            "evm" => Ok(Target::Evm),
+            "rust" => Ok(Target::Rust),
+            _ => Err(format!(
+                "unknown target `{s}`; expected `svm`, `anchor`, `pinocchio`, `evm` or `rust`"
+            )),
+        }
+    }
+}
+
+/// The semantic version of a specification, `major.minor.patch`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Version {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Version, String> {
+        let parts: Vec<Option<u64>> = s.split('.').map(|part| part.parse().ok()).collect();
+        match parts[..] {
+            [Some(major), Some(minor), Some(patch)] => Ok(Version::new(major, minor, patch)),
+            _ => Err(format!(
+                "invalid version `{s}`; expected `major.minor.patch`, e.g. `1.2.0`"
+            )),
+        }
+    }
+}
+
+impl Serialize for Version {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Version, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// An account type and its data layout.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Account {
+    /// Type name, e.g. `Vault`
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Fields in layout order
+    #[serde(default)]
+    pub fields: Vec<Field>,
+}
+
+impl Account {
+    /// Looks up a field by name.
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// The name expressions use for this account type in invariants, e.g.
+    /// `withdrawal_queue` for `WithdrawalQueue`.
+    pub fn binding(&self) -> String {
+        let mut binding = String::new();
+        for (i, c) in self.name.chars().enumerate() {
+            if c.is_ascii_uppercase() {
+                if i > 0 {
+                    binding.push('_');
+                }
+                binding.push(c.to_ascii_lowercase());
+            } else {
+                binding.push(c);
+            }
+        }
+        binding
+    }
+}
+
+/// A field of an account.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Field {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: Type,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// The type of a field or argument.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Type {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I64,
+    Pubkey,
+    /// A fixed-size array, written `[T; N]`
+    Array(Box<Type>, usize),
+}
+
+impl Type {
+    /// Size of the type in bytes, in the zero-copy account layout.
+    pub fn size(&self) -> usize {
+        match self {
+            Type::Bool | Type::U8 => 1,
+            Type::U16 => 2,
+            Type::U32 => 4,
+            Type::U64 | Type::I64 => 8,
+            Type::U128 => 16,
+            Type::Pubkey => 32,
+            Type::Array(inner, len) => inner.size() * len,
+        }
+    }
+
+    /// Returns true for types arithmetic expressions may use.
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Type::U8 | Type::U16 | Type::U32 | Type::U64 | Type::U128 | Type::I64
+        )
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Bool => f.write_str("bool"),
+            Type::U8 => f.write_str("u8"),
+            Type::U16 => f.write_str("u16"),
+            Type::U32 => f.write_str("u32"),
+            Type::U64 => f.write_str("u64"),
+            Type::U128 => f.write_str("u128"),
+            Type::I64 => f.write_str("i64"),
+            Type::Pubkey => f.write_str("pubkey"),
+            Type::Array(inner, len) => write!(f, "[{inner}; {len}]"),
+        }
+    }
+}
+
+impl FromStr for Type {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Type, String> {
+        let s = s.trim();
+        if let Some(body) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            let (inner, len) = body
+                .rsplit_once(';')
+                .ok_or_else(|| format!("array type `{s}` is missing a length"))?;
+            let len = len
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid array length in `{s}`"))?;
+            return Ok(Type::Array(Box::new(inner.parse()?), len));
+        }
+        Ok(match s {
+            "bool" => Type::Bool,
+            "u8" => Type::U8,
+            "u16" => Type::U16,
+            "u32" => Type::U32,
+            "u64" => Type::U64,
+            "u128" => Type::U128,
+            "i64" => Type::I64,
+            "pubkey" | "Pubkey" => Type::Pubkey,
+            _ => return Err(format!("unknown type `{s}`")),
+        })
+    }
+}
+
+impl TryFrom<String> for Type {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Type, String> {
+        s.parse()
+    }
+}
+
+impl From<Type> for String {
+    fn from(ty: Type) -> String {
+        ty.to_string()
+    }
+}
+
+/// A function (instruction) of the program.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Function {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Accounts the function takes, in order
+    #[serde(default)]
+    pub accounts: Vec<AccountInput>,
+    /// Instruction data arguments, in order
+    #[serde(default)]
+    pub args: Vec<Arg>,
+    /// Preconditions; the function must fail when one does not hold
+    #[serde(default, alias = "preconditions")]
+    pub requires: Vec<Condition>,
+    /// Postconditions, which may refer to the pre-state through `old(..)`
+    #[serde(default, alias = "postconditions")]
+    pub ensures: Vec<Condition>,
+    /// Concrete cases, e.g. edge cases such as a zero amount or an empty
+    /// vault
+    #[serde(default)]
+    pub examples: Vec<Example>,
+    /// The most compute units a call may consume on chain, for Solana
+    /// programs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compute_units: Option<u64>,
+}
+
+impl Function {
+    /// Looks up an account input by name.
+    pub fn account(&self, name: &str) -> Option<&AccountInput> {
+        self.accounts.iter().find(|account| account.name == name)
+    }
+
+    /// Looks up an argument by name.
+    pub fn arg(&self, name: &str) -> Option<&Arg> {
+        self.args.iter().find(|arg| arg.name == name)
+    }
+}
+
+/// An account passed to a function.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccountInput {
+    pub name: String,
+    /// The state account type, if the account holds program data
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub ty: Option<String>,
+    #[serde(default)]
+    pub signer: bool,
+    #[serde(default)]
+    pub writable: bool,
+}
+
+/// An instruction data argument.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Arg {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: Type,
+}
+
+/// A pre- or postcondition. Written either as a bare expression string or as
+/// a map with `expr` and an optional `description`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "ConditionRepr", into = "ConditionRepr")]
+pub struct Condition {
+    pub expr: Expr,
+    pub description: Option<String>,
+}
+
+impl From<Expr> for Condition {
+    fn from(expr: Expr) -> Condition {
+        Condition {
+            expr,
+            description: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ConditionRepr {
+    Bare(Expr),
+    Described {
+        expr: Expr,
+        #[serde(default)]
+        description: Option<String>,
+    },
+}
+
+impl From<ConditionRepr> for Condition {
+    fn from(repr: ConditionRepr) -> Condition {
+        match repr {
+            ConditionRepr::Bare(expr) => expr.into(),
+            ConditionRepr::Described { expr, description } => Condition { expr, description },
+        }
+    }
+}
+
+impl From<Condition> for ConditionRepr {
+    fn from(condition: Condition) -> ConditionRepr {
+        match condition.description {
+            None => ConditionRepr::Bare(condition.expr),
+            description => ConditionRepr::Described {
+                expr: condition.expr,
+                description,
+            },
+        }
+    }
+}
+
+/// A concrete call of a function with its expected outcome.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Example {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Field values of the typed accounts before the call, by account name;
+    /// fields not listed are zero
+    #[serde(default)]
+    pub state: BTreeMap<String, BTreeMap<String, Value>>,
+    /// Argument values; arguments not listed are zero
+    #[serde(default)]
+    pub args: BTreeMap<String, Value>,
+    #[serde(default)]
+    pub expect: Outcome,
+    /// Field values after a successful call; fields not listed are only
+    /// checked through the postconditions
+    #[serde(default)]
+    pub after: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+/// Whether a call succeeds.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    #[default]
+    Ok,
+    Error,
+}
+
+/// A literal value in an example.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Int(i128),
+    Bool(bool),
+    /// The largest value of the field or argument type, written `max`
+    Max,
+    /// The smallest value of the field or argument type, written `min`
+    Min,
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{value}"),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Max => f.write_str("max"),
+            Value::Min => f.write_str("min"),
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Value::Int(value) => match (i64::try_from(value), u64::try_from(value)) {
+                (Ok(value), _) => serializer.serialize_i64(value),
+                (_, Ok(value)) => serializer.serialize_u64(value),
+                _ => serializer.serialize_i128(value),
+            },
+            Value::Bool(value) => serializer.serialize_bool(value),
+            Value::Max | Value::Min => serializer.collect_str(self),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = Value;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an integer, a boolean, `max` or `min`")
+            }
+
+            fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+                Ok(Value::Bool(value))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+                Ok(Value::Int(value.into()))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+                Ok(Value::Int(value.into()))
+            }
+
+            fn visit_i128<E>(self, value: i128) -> Result<Value, E> {
+                Ok(Value::Int(value))
+            }
+
+            fn visit_u128<E: serde::de::Error>(self, value: u128) -> Result<Value, E> {
+                i128::try_from(value)
+                    .map(Value::Int)
+                    .map_err(|_| E::custom(format!("{value} is out of range")))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Value, E> {
+                match value {
+                    "max" => Ok(Value::Max),
+                    "min" => Ok(Value::Min),
+                    _ => Err(E::invalid_value(serde::de::Unexpected::Str(value), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// A pure function of the implementation, such as a share conversion or a
+/// fixed-point operation, benchmarked with criterion.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Helper {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub args: Vec<Arg>,
+    pub returns: Type,
+    /// Argument values the benchmark calls the helper with; arguments not
+    /// listed are zero
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bench: BTreeMap<String, Value>,
+    /// The fewest calls per second the benchmark must measure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throughput: Option<u64>,
+}
+
+impl Helper {
+    /// Looks up an argument by name.
+    pub fn arg(&self, name: &str) -> Option<&Arg> {
+        self.args.iter().find(|arg| arg.name == name)
+    }
+}
+
+/// A property over the program state that every function must preserve.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Invariant {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub expr: Expr,
+}
+
+/// How the prover checks a rule, where the global defaults would time out
+/// or waste time on it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProverSettings {
+    /// How many times loops are unrolled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loop_iter: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arithmetic: Option<Arithmetic>,
+    /// Seconds the solver may spend on each query
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smt_timeout: Option<u32>,
+}
+
+impl ProverSettings {
+    /// These settings, with those `other` sets replaced.
+    pub fn merge(&mut self, other: &ProverSettings) {
+        self.loop_iter = other.loop_iter.or(self.loop_iter);
+        self.arithmetic = other.arithmetic.or(self.arithmetic);
+        self.smt_timeout = other.smt_timeout.or(self.smt_timeout);
+    }
+}
+
+/// How the solver models machine integers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Arithmetic {
+    /// As mathematical integers, which is faster but imprecise for bitwise
+    /// operations and overflow
+    Integer,
+    /// As bitvectors of their width
+    Bitvector,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_round_trips() {
+        for src in ["u64", "pubkey", "[u8; 32]", "[[u16; 2]; 4]"] {
+            let ty: Type = src.parse().unwrap();
+            assert_eq!(ty.to_string(), src);
+        }
+        assert_eq!("Pubkey".parse::<Type>().unwrap(), Type::Pubkey);
+        assert_eq!("[u64;4]".parse::<Type>().unwrap().size(), 32);
+        assert!("f64".parse::<Type>().is_err());
+        assert!("[u8]".parse::<Type>().is_err());
+    }
+
+    #[test]
+    fn test_example_values() {
+        let example: Example = serde_yaml::from_str(
+            "name: full\nstate: { vault: { token_total: max, paused: true } }\n\
+             args: { amount: 18446744073709551615 }\nexpect: error",
+        )
+        .unwrap();
+        assert_eq!(example.state["vault"]["token_total"], Value::Max);
+        assert_eq!(example.state["vault"]["paused"], Value::Bool(true));
+        assert_eq!(example.args["amount"], Value::Int(u64::MAX.into()));
+        assert_eq!(example.expect, Outcome::Error);
+        assert!(serde_yaml::from_str::<Example>("name: x\nargs: { amount: lots }").is_err());
+    }
+
+    #[test]
+    fn test_version() {
+        let version: Version = "1.10.0".parse().unwrap();
+        assert_eq!(version, Version::new(1, 10, 0));
+        assert_eq!(version.to_string(), "1.10.0");
+        assert!(version > "1.9.3".parse().unwrap());
+        assert!("1.2".parse::<Version>().is_err());
+        assert!("v1.2.0".parse::<Version>().is_err());
+        assert_eq!(
+            serde_yaml::from_str::<Version>("\"2.0.1\"").unwrap(),
+            Version::new(2, 0, 1)
+        );
+    }
+
+    #[test]
+    fn test_account_binding() {
+        let account = |name: &str| Account {
+            name: name.to_string(),
+            description: None,
+            fields: Vec::new(),
+        };
+        assert_eq!(account("Vault").binding(), "vault");
+        assert_eq!(account("WithdrawalQueue").binding(), "withdrawal_queue");
+    }
+}
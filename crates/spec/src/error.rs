@@ -0,0 +1,25 @@
+//! Errors produced while loading a specification.
+
+use crate::markdown::Diagnostics;
+use crate::validate::ValidationErrors;
+use std::path::PathBuf;
+
+/// An error loading, parsing or validating a specification.
+#[derive(Debug, thiserror::Error)]
+pub enum SpecError {
+    #[error("failed to read {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("cannot infer the format of {}; expected a .yaml, .yml, .toml or .md extension", .0.display())]
+    UnknownFormat(PathBuf),
+    #[error("invalid YAML specification: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("invalid TOML specification: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("invalid Markdown specification:\n{0}")]
+    Markdown(#[from] Diagnostics),
+    #[error("invalid specification:\n{0}")]
+    Invalid(#[from] ValidationErrors),
+}
@@ -0,0 +1,573 @@
+//! Expressions used in invariants and pre/postconditions.
+//!
+//! The grammar is a small, Rust-like subset over unsigned integers and
+//! booleans, from lowest to highest precedence:
+//!
+//! ```text
+//! expr    := or ("==>" expr)?
+//! or      := and ("||" and)*
+//! and     := cmp ("&&" cmp)*
+//! cmp     := sum (("==" | "!=" | "<" | "<=" | ">" | ">=") sum)?
+//! sum     := product (("+" | "-") product)*
+//! product := unary (("*" | "/" | "%") unary)*
+//! unary   := "!" unary | atom
+//! atom    := integer | "true" | "false" | "old" "(" expr ")"
+//!          | ident ("." ident)* | "(" expr ")"
+//! ```
+//!
+//! `old(e)` denotes the value of `e` before the instruction executed and is
+//! only meaningful in postconditions.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A unary operator.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// Boolean negation, `!`
+    Not,
+}
+
+/// A binary operator.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    /// Logical implication, `==>`
+    Implies,
+}
+
+impl BinaryOp {
+    /// The operator's symbol.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::Implies => "==>",
+        }
+    }
+
+    /// Binding strength; higher binds tighter.
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Implies => 1,
+            BinaryOp::Or => 2,
+            BinaryOp::And => 3,
+            BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge => 4,
+            BinaryOp::Add | BinaryOp::Sub => 5,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 6,
+        }
+    }
+
+    /// Returns true for operators producing a boolean.
+    pub fn is_boolean(self) -> bool {
+        self.precedence() <= 4
+    }
+}
+
+/// Precedence of unary operators, above every binary operator.
+const UNARY_PRECEDENCE: u8 = 7;
+
+/// A parsed expression. Serialized as its source text.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Expr {
+    /// An unsigned integer literal
+    Int(u128),
+    /// A boolean literal
+    Bool(bool),
+    /// A dotted path, e.g. `vault.token_total` or `amount`
+    Path(Vec<String>),
+    /// The pre-state value of the inner expression
+    Old(Box<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Builds a binary expression.
+    pub fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+        Expr::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+
+    /// Builds a path expression from its segments.
+    pub fn path<S: Into<String>>(segments: impl IntoIterator<Item = S>) -> Expr {
+        Expr::Path(segments.into_iter().map(Into::into).collect())
+    }
+
+    /// Calls `f` on every path in the expression, with whether it appears
+    /// under `old(..)`.
+    pub fn visit_paths<'a>(&'a self, f: &mut impl FnMut(&'a [String], bool)) {
+        self.visit_paths_inner(false, f);
+    }
+
+    fn visit_paths_inner<'a>(&'a self, in_old: bool, f: &mut impl FnMut(&'a [String], bool)) {
+        match self {
+            Expr::Int(_) | Expr::Bool(_) => {}
+            Expr::Path(segments) => f(segments, in_old),
+            Expr::Old(inner) => inner.visit_paths_inner(true, f),
+            Expr::Unary(_, inner) => inner.visit_paths_inner(in_old, f),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.visit_paths_inner(in_old, f);
+                rhs.visit_paths_inner(in_old, f);
+            }
+        }
+    }
+
+    /// Returns true if the expression refers to the pre-state via `old(..)`.
+    pub fn mentions_old(&self) -> bool {
+        match self {
+            Expr::Int(_) | Expr::Bool(_) | Expr::Path(_) => false,
+            Expr::Old(_) => true,
+            Expr::Unary(_, inner) => inner.mentions_old(),
+            Expr::Binary(_, lhs, rhs) => lhs.mentions_old() || rhs.mentions_old(),
+        }
+    }
+
+    /// Returns true if the expression applies `op` anywhere.
+    pub fn mentions_op(&self, op: BinaryOp) -> bool {
+        match self {
+            Expr::Int(_) | Expr::Bool(_) | Expr::Path(_) => false,
+            Expr::Old(inner) | Expr::Unary(_, inner) => inner.mentions_op(op),
+            Expr::Binary(other, lhs, rhs) => {
+                *other == op || lhs.mentions_op(op) || rhs.mentions_op(op)
+            }
+        }
+    }
+
+    fn fmt_prec(&self, parent: u8, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Int(value) => write!(f, "{value}"),
+            Expr::Bool(value) => write!(f, "{value}"),
+            Expr::Path(segments) => write!(f, "{}", segments.join(".")),
+            Expr::Old(inner) => {
+                write!(f, "old(")?;
+                inner.fmt_prec(0, f)?;
+                write!(f, ")")
+            }
+            Expr::Unary(UnaryOp::Not, inner) => {
+                write!(f, "!")?;
+                inner.fmt_prec(UNARY_PRECEDENCE, f)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let prec = op.precedence();
+                // Implication is right-associative, comparisons do not chain,
+                // everything else is left-associative
+                let (lhs_prec, rhs_prec) = match op {
+                    BinaryOp::Implies => (prec + 1, prec),
+                    _ if prec == 4 => (prec + 1, prec + 1),
+                    _ => (prec, prec + 1),
+                };
+                let parens = prec < parent;
+                if parens {
+                    write!(f, "(")?;
+                }
+                lhs.fmt_prec(lhs_prec, f)?;
+                write!(f, " {} ", op.symbol())?;
+                rhs.fmt_prec(rhs_prec, f)?;
+                if parens {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    /// Formats the expression with the minimal parentheses needed to parse it
+    /// back to the same tree.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_prec(0, f)
+    }
+}
+
+/// An error parsing an expression.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("column {}: {message}", .offset + 1)]
+pub struct ExprError {
+    /// Byte offset of the error in the source
+    pub offset: usize,
+    pub message: String,
+}
+
+impl ExprError {
+    fn new(offset: usize, message: impl Into<String>) -> ExprError {
+        ExprError {
+            offset,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Int(u128),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Dot,
+}
+
+/// Symbols, longest first so that e.g. `==>` is not lexed as `==` `>`.
+const SYMBOLS: [&str; 17] = [
+    "==>", "==", "!=", "<=", ">=", "&&", "||", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")",
+];
+
+fn tokenize(src: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+    let mut tokens = Vec::new();
+    let bytes = src.as_bytes();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        if c.is_ascii_whitespace() {
+            pos += 1;
+        } else if c.is_ascii_digit() {
+            let start = pos;
+            while pos < bytes.len() && (bytes[pos].is_ascii_digit() || bytes[pos] == b'_') {
+                pos += 1;
+            }
+            let digits = src[start..pos].replace('_', "");
+            let value = digits
+                .parse()
+                .map_err(|_| ExprError::new(start, "integer literal out of range"))?;
+            tokens.push((start, Token::Int(value)));
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let start = pos;
+            while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_') {
+                pos += 1;
+            }
+            tokens.push((start, Token::Ident(src[start..pos].to_string())));
+        } else if c == b'.' {
+            tokens.push((pos, Token::Dot));
+            pos += 1;
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| src[pos..].starts_with(**s)) {
+            let token = match *symbol {
+                "(" => Token::LParen,
+                ")" => Token::RParen,
+                op => Token::Op(op),
+            };
+            tokens.push((pos, token));
+            pos += symbol.len();
+        } else {
+            let found = src[pos..].chars().next().unwrap_or_default();
+            return Err(ExprError::new(
+                pos,
+                format!("unexpected character `{found}`"),
+            ));
+        }
+    }
+    Ok(tokens)
+}
+
+fn binary_op(symbol: &str) -> Option<BinaryOp> {
+    Some(match symbol {
+        "+" => BinaryOp::Add,
+        "-" => BinaryOp::Sub,
+        "*" => BinaryOp::Mul,
+        "/" => BinaryOp::Div,
+        "%" => BinaryOp::Rem,
+        "==" => BinaryOp::Eq,
+        "!=" => BinaryOp::Ne,
+        "<" => BinaryOp::Lt,
+        "<=" => BinaryOp::Le,
+        ">" => BinaryOp::Gt,
+        ">=" => BinaryOp::Ge,
+        "&&" => BinaryOp::And,
+        "||" => BinaryOp::Or,
+        "==>" => BinaryOp::Implies,
+        _ => return None,
+    })
+}
+
+/// How deeply an expression may nest, counting parentheses, `old(`, `!` and
+/// the operands of each chain of binary operators. Deeper expressions are
+/// rejected rather than overflowing the stack of the parser, or of the code
+/// walking the tree.
+const MAX_DEPTH: usize = 128;
+
+/// Precedence-climbing parser over the token stream.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    end: usize,
+    /// The nesting at the current position
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |(offset, _)| *offset)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, token)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<(), ExprError> {
+        let offset = self.offset();
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(ExprError::new(offset, format!("expected {what}"))),
+        }
+    }
+
+    /// Enters one more level of nesting, failing past [`MAX_DEPTH`].
+    fn nest(&mut self) -> Result<(), ExprError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ExprError::new(
+                self.offset(),
+                format!("expression nested more than {MAX_DEPTH} deep"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, min_prec: u8) -> Result<Expr, ExprError> {
+        let depth = self.depth;
+        self.nest()?;
+        let mut lhs = self.unary()?;
+        let mut compared = false;
+        while let Some(op) = self.peek_binary_op(min_prec) {
+            self.nest()?;
+            let prec = op.precedence();
+            // Comparisons do not chain: `a < b < c` is rejected
+            if prec == 4 && compared {
+                return Err(ExprError::new(
+                    self.offset(),
+                    "comparisons cannot be chained",
+                ));
+            }
+            compared = prec == 4;
+            self.pos += 1;
+            let rhs = match op {
+                BinaryOp::Implies => self.expr(prec)?,
+                _ => self.expr(prec + 1)?,
+            };
+            lhs = Expr::binary(op, lhs, rhs);
+        }
+        self.depth = depth;
+        Ok(lhs)
+    }
+
+    /// The binary operator at the current position, if it binds at least as
+    /// tightly as `min_prec`.
+    fn peek_binary_op(&self, min_prec: u8) -> Option<BinaryOp> {
+        match self.peek() {
+            Some(Token::Op(symbol)) => binary_op(symbol).filter(|op| op.precedence() >= min_prec),
+            _ => None,
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        if self.peek() == Some(&Token::Op("!")) {
+            self.pos += 1;
+            self.nest()?;
+            let inner = self.unary()?;
+            self.depth -= 1;
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(inner)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, ExprError> {
+        let offset = self.offset();
+        match self.next() {
+            Some(Token::Int(value)) => Ok(Expr::Int(value)),
+            Some(Token::LParen) => {
+                let inner = self.expr(0)?;
+                self.expect(Token::RParen, "`)`")?;
+                Ok(inner)
+            }
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "true" => Ok(Expr::Bool(true)),
+                "false" => Ok(Expr::Bool(false)),
+                "old" if self.peek() == Some(&Token::LParen) => {
+                    self.pos += 1;
+                    let inner = self.expr(0)?;
+                    self.expect(Token::RParen, "`)` closing `old(`")?;
+                    Ok(Expr::Old(Box::new(inner)))
+                }
+                _ => {
+                    let mut segments = vec![ident];
+                    while self.peek() == Some(&Token::Dot) {
+                        self.pos += 1;
+                        let offset = self.offset();
+                        match self.next() {
+                            Some(Token::Ident(segment)) => segments.push(segment),
+                            _ => return Err(ExprError::new(offset, "expected field name")),
+                        }
+                    }
+                    Ok(Expr::Path(segments))
+                }
+            },
+            Some(_) => Err(ExprError::new(offset, "expected an expression")),
+            None => Err(ExprError::new(offset, "unexpected end of expression")),
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ExprError;
+
+    fn from_str(src: &str) -> Result<Expr, ExprError> {
+        let mut parser = Parser {
+            tokens: tokenize(src)?,
+            pos: 0,
+            end: src.len(),
+            depth: 0,
+        };
+        let expr = parser.expr(0)?;
+        if parser.pos < parser.tokens.len() {
+            return Err(ExprError::new(parser.offset(), "unexpected trailing input"));
+        }
+        Ok(expr)
+    }
+}
+
+impl TryFrom<String> for Expr {
+    type Error = ExprError;
+
+    fn try_from(src: String) -> Result<Expr, ExprError> {
+        src.parse()
+    }
+}
+
+impl From<Expr> for String {
+    fn from(expr: Expr) -> String {
+        expr.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str) -> Expr {
+        src.parse().unwrap()
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(
+            parse("a + b * 2 <= c"),
+            Expr::binary(
+                BinaryOp::Le,
+                Expr::binary(
+                    BinaryOp::Add,
+                    Expr::path(["a"]),
+                    Expr::binary(BinaryOp::Mul, Expr::path(["b"]), Expr::Int(2)),
+                ),
+                Expr::path(["c"]),
+            )
+        );
+        // Implication binds loosest and associates to the right
+        assert_eq!(parse("a ==> b ==> c"), parse("a ==> (b ==> c)"));
+        assert_eq!(parse("a - b - c"), parse("(a - b) - c"));
+    }
+
+    #[test]
+    fn test_old_and_paths() {
+        let expr = parse("vault.token_total == old(vault.token_total) + amount");
+        let mut paths = Vec::new();
+        expr.visit_paths(&mut |path, in_old| paths.push((path.join("."), in_old)));
+        assert_eq!(
+            paths,
+            [
+                ("vault.token_total".to_string(), false),
+                ("vault.token_total".to_string(), true),
+                ("amount".to_string(), false),
+            ]
+        );
+        assert!(expr.mentions_old());
+        assert!(expr.mentions_op(BinaryOp::Add));
+        assert!(!expr.mentions_op(BinaryOp::Div));
+        assert!(parse("!(a / (b % 2) > 0)").mentions_op(BinaryOp::Rem));
+        // `old` without parentheses is an ordinary identifier
+        assert_eq!(parse("old"), Expr::path(["old"]));
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for src in [
+            "a + b * 2 <= c",
+            "(a + b) * 2 <= c",
+            "a - (b - c) == 1_000",
+            "!(a && b) || c ==> d",
+            "(a ==> b) ==> c",
+            "(a == b) == c",
+            "vault.shares_total <= old(vault.token_total / 2)",
+        ] {
+            let expr = parse(src);
+            assert_eq!(parse(&expr.to_string()), expr, "{src}");
+        }
+        assert_eq!(parse("((a)) + (b * c)").to_string(), "a + b * c");
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = "a < b < c".parse::<Expr>().unwrap_err();
+        assert_eq!(err.message, "comparisons cannot be chained");
+        assert_eq!("a +".parse::<Expr>().unwrap_err().offset, 3);
+        assert_eq!(
+            "a $ b".parse::<Expr>().unwrap_err().to_string(),
+            "column 3: unexpected character `$`"
+        );
+        assert!("old(a".parse::<Expr>().is_err());
+        assert!("a b".parse::<Expr>().is_err());
+        assert!("a.".parse::<Expr>().is_err());
+        assert!("340282366920938463463374607431768211456"
+            .parse::<Expr>()
+            .is_err());
+
+        let nested = |depth| format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(nested(100).parse::<Expr>().is_ok());
+        for src in [
+            nested(100_000),
+            "!".repeat(100_000) + "a",
+            vec!["a"; 100_000].join(" + "),
+            vec!["a"; 100_000].join(" ==> "),
+        ] {
+            let err = src.parse::<Expr>().unwrap_err();
+            assert_eq!(err.message, "expression nested more than 128 deep");
+        }
+    }
+}
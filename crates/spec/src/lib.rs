@@ -0,0 +1,193 @@
+//! Machine-readable program specifications.
+//!
+//! A specification describes a program's state layout, its functions with
+//! their pre- and postconditions, and the invariants every function must
+//! preserve. It is written in YAML or TOML, or as a Markdown design document
+//! (see [`markdown`]), parsed into the typed AST of [`ast`] and checked by
+//! [`validate`]. [`lint`] goes further and flags behavior a valid
+//! specification leaves open.
+
+pub mod ast;
+pub mod error;
+pub mod expr;
+pub mod lint;
+pub mod markdown;
+pub mod validate;
+
+pub use ast::{
+    Account, AccountInput, Arg, Arithmetic, Condition, Example, Field, Function, Helper, Invariant,
+    Outcome, ProverSettings, Spec, Target, Type, Value, Version,
+};
+pub use error::SpecError;
+pub use expr::{BinaryOp, Expr, ExprError, UnaryOp};
+pub use lint::{lint, Lint};
+pub use markdown::{Diagnostic, Diagnostics, Severity};
+pub use validate::{validate, ValidationError, ValidationErrors};
+
+use std::path::Path;
+
+/// Serialization formats a specification may be written in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    Yaml,
+    Toml,
+    Markdown,
+}
+
+impl Format {
+    /// Infers the format from a file extension.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()? {
+            "yaml" | "yml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            "md" | "markdown" => Some(Format::Markdown),
+            _ => None,
+        }
+    }
+}
+
+impl Spec {
+    /// Parses and validates a specification.
+    pub fn parse(src: &str, format: Format) -> Result<Spec, SpecError> {
+        let spec = Spec::parse_unvalidated(src, format)?;
+        validate(&spec)?;
+        Ok(spec)
+    }
+
+    /// Parses a specification without validating it, so that [`lint`] can
+    /// report its problems alongside the rest.
+    pub fn parse_unvalidated(src: &str, format: Format) -> Result<Spec, SpecError> {
+        let spec: Spec = match format {
+            Format::Yaml => serde_yaml::from_str(src)?,
+            Format::Toml => toml::from_str(src)?,
+            Format::Markdown => {
+                let (spec, diagnostics) = markdown::lower(src);
+                if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+                    return Err(Diagnostics(diagnostics).into());
+                }
+                spec
+            }
+        };
+        Ok(spec)
+    }
+
+    /// Parses and validates a YAML specification.
+    pub fn from_yaml_str(src: &str) -> Result<Spec, SpecError> {
+        Spec::parse(src, Format::Yaml)
+    }
+
+    /// Parses and validates a TOML specification.
+    pub fn from_toml_str(src: &str) -> Result<Spec, SpecError> {
+        Spec::parse(src, Format::Toml)
+    }
+
+    /// Lowers and validates a Markdown specification. Warnings are dropped;
+    /// use [`markdown::lower`] to inspect them.
+    pub fn from_markdown_str(src: &str) -> Result<Spec, SpecError> {
+        Spec::parse(src, Format::Markdown)
+    }
+
+    /// Reads, parses and validates a specification file, inferring its format
+    /// from the extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Spec, SpecError> {
+        let spec = Spec::from_path_unvalidated(path)?;
+        validate(&spec)?;
+        Ok(spec)
+    }
+
+    /// Reads and parses a specification file without validating it.
+    pub fn from_path_unvalidated(path: impl AsRef<Path>) -> Result<Spec, SpecError> {
+        let path = path.as_ref();
+        let format =
+            Format::from_path(path).ok_or_else(|| SpecError::UnknownFormat(path.to_path_buf()))?;
+        let src = std::fs::read_to_string(path).map_err(|source| SpecError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Spec::parse_unvalidated(&src, format)
+    }
+
+    /// Serializes the specification to YAML.
+    pub fn to_yaml_string(&self) -> Result<String, SpecError> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+    const VAULT_MD: &str = include_str!("../../../examples/specs/vault.md");
+
+    #[test]
+    fn test_vault_example() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        assert_eq!(spec.name, "vault");
+        assert_eq!(spec.target, Target::Svm);
+        let vault = spec.account("Vault").unwrap();
+        assert_eq!(vault.field("shares_total").unwrap().ty, Type::U64);
+        let deposit = spec.function("deposit").unwrap();
+        assert_eq!(deposit.args[0].ty, Type::U64);
+        assert!(deposit.ensures.iter().any(|c| c.expr.mentions_old()));
+        assert!(!spec.invariants.is_empty());
+    }
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let spec = Spec::from_yaml_str(VAULT).unwrap();
+        let yaml = spec.to_yaml_string().unwrap();
+        assert_eq!(Spec::from_yaml_str(&yaml).unwrap(), spec);
+    }
+
+    #[test]
+    fn test_markdown_matches_yaml() {
+        let (_, diagnostics) = markdown::lower(VAULT_MD);
+        assert_eq!(diagnostics, []);
+        assert_eq!(
+            Spec::from_markdown_str(VAULT_MD).unwrap(),
+            Spec::from_yaml_str(VAULT).unwrap()
+        );
+        let err = Spec::from_markdown_str("# x\n## State\n").unwrap_err();
+        assert!(matches!(err, SpecError::Markdown(_)), "{err}");
+    }
+
+    #[test]
+    fn test_toml() {
+        let spec = Spec::from_toml_str(
+            r#"
+name = "counter"
+
+[[state]]
+name = "Counter"
+fields = [{ name = "count", type = "u64" }]
+
+[[instructions]]
+name = "reset"
+accounts = [{ name = "counter", type = "Counter", writable = true }]
+postconditions = [{ expr = "counter.count == 0", description = "starts over" }]
+"#,
+        )
+        .unwrap();
+        let reset = spec.function("reset").unwrap();
+        assert_eq!(reset.ensures[0].description.as_deref(), Some("starts over"));
+        assert_eq!(reset.ensures[0].expr.to_string(), "counter.count == 0");
+    }
+
+    #[test]
+    fn test_errors() {
+        let err =
+            Spec::from_yaml_str("name: x\ninvariants: [{ name: i, expr: 'a +' }]").unwrap_err();
+        assert!(matches!(err, SpecError::Yaml(_)), "{err}");
+        assert!(
+            err.to_string().contains("unexpected end of expression"),
+            "{err}"
+        );
+        let err = Spec::from_yaml_str("name: x\nfunctions: [{ name: f, bogus: 1 }]").unwrap_err();
+        assert!(err.to_string().contains("unknown field `bogus`"), "{err}");
+        assert!(matches!(
+            Spec::from_path("spec.json").unwrap_err(),
+            SpecError::UnknownFormat(_)
+        ));
+    }
+}
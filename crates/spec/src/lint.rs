@@ -0,0 +1,368 @@
+//! Checks for behavior a specification leaves open.
+//!
+//! [`validate`] rejects specifications that cannot mean anything; [`lint`]
+//! also flags valid ones that leave the implementation to guess, such as a
+//! division whose rounding direction is not stated or a function writing an
+//! account with an owner that no signer is checked against. Every guess is a
+//! chance to implement something the prover then rejects, so these are best
+//! settled in the spec before anything is generated.
+
+use crate::ast::{Condition, Function, Spec, Type};
+use crate::expr::{BinaryOp, Expr};
+use crate::markdown::Severity;
+use crate::validate::validate;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Words stating how a division rounds.
+const ROUNDING_WORDS: &[&str] = &["round", "floor", "ceil", "truncat"];
+
+/// A problem found in a specification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lint {
+    /// Where the problem is, e.g. `functions.deposit.ensures[0]`
+    pub location: String,
+    /// The check that found it, e.g. `rounding`
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}: {severity}: {} [{}]",
+            self.location, self.message, self.rule
+        )
+    }
+}
+
+#[derive(Default)]
+struct Linter {
+    lints: Vec<Lint>,
+}
+
+impl Linter {
+    fn warn(&mut self, location: impl Into<String>, rule: &'static str, message: String) {
+        self.lints.push(Lint {
+            location: location.into(),
+            rule,
+            severity: Severity::Warning,
+            message,
+        });
+    }
+
+    /// Flags a division in `expr` unless `descriptions` say how it rounds.
+    fn rounding<'a>(
+        &mut self,
+        location: &str,
+        expr: &Expr,
+        descriptions: impl IntoIterator<Item = Option<&'a String>>,
+    ) {
+        if !expr.mentions_op(BinaryOp::Div) && !expr.mentions_op(BinaryOp::Rem) {
+            return;
+        }
+        let stated = descriptions.into_iter().flatten().any(|description| {
+            let description = description.to_lowercase();
+            ROUNDING_WORDS.iter().any(|word| description.contains(word))
+        });
+        if !stated {
+            self.warn(
+                location,
+                "rounding",
+                format!("`{expr}` divides but does not say which way it rounds"),
+            );
+        }
+    }
+
+    fn function(&mut self, spec: &Spec, function: &Function) {
+        let location = format!("functions.{}", function.name);
+        for (kind, conditions) in [
+            ("requires", &function.requires),
+            ("ensures", &function.ensures),
+        ] {
+            for (i, condition) in conditions.iter().enumerate() {
+                self.rounding(
+                    &format!("{location}.{kind}[{i}]"),
+                    &condition.expr,
+                    [
+                        condition.description.as_ref(),
+                        function.description.as_ref(),
+                    ],
+                );
+            }
+        }
+
+        let required = roots(&function.requires, false);
+        let ensured = roots(&function.ensures, true);
+        let signers: Vec<&str> = function
+            .accounts
+            .iter()
+            .filter(|account| account.signer)
+            .map(|account| account.name.as_str())
+            .collect();
+        for account in &function.accounts {
+            if account.signer && !required.contains(account.name.as_str()) {
+                self.warn(
+                    &location,
+                    "authorization",
+                    format!(
+                        "signer `{}` is not checked by any precondition, so anyone may sign",
+                        account.name
+                    ),
+                );
+            }
+            let Some(ty) = account.ty.as_deref().and_then(|ty| spec.account(ty)) else {
+                continue;
+            };
+            if !account.writable {
+                continue;
+            }
+            let authority = ty.fields.iter().find(|field| field.ty == Type::Pubkey);
+            if let (Some(authority), true) = (authority, signers.is_empty()) {
+                self.warn(
+                    &location,
+                    "authorization",
+                    format!(
+                        "`{}` is writable and `{}` has the key `{}`, but no signer says who may call `{}`",
+                        account.name, ty.name, authority.name, function.name
+                    ),
+                );
+            }
+            if !ensured.contains(account.name.as_str()) {
+                self.warn(
+                    &location,
+                    "unconstrained-write",
+                    format!(
+                        "`{}` is writable but no postcondition says how it changes",
+                        account.name
+                    ),
+                );
+            }
+        }
+
+        let mentioned: BTreeSet<&str> = required
+            .union(&roots(&function.ensures, false))
+            .copied()
+            .collect();
+        for arg in &function.args {
+            if !mentioned.contains(arg.name.as_str()) {
+                self.warn(
+                    &location,
+                    "unused-argument",
+                    format!("argument `{}` appears in no condition", arg.name),
+                );
+            }
+        }
+    }
+    /// Flags the functions writing only state no invariant ranges over,
+    /// which the invariants then leave unchecked.
+    fn unreferenced(&mut self, spec: &Spec) {
+        if spec.invariants.is_empty() {
+            return;
+        }
+        let mut referenced = BTreeSet::new();
+        for invariant in &spec.invariants {
+            invariant.expr.visit_paths(&mut |path, _| {
+                referenced.insert(path[0].clone());
+            });
+        }
+        for function in &spec.functions {
+            let written: Vec<_> = function
+                .accounts
+                .iter()
+                .filter(|account| account.writable)
+                .filter_map(|account| spec.account(account.ty.as_deref()?))
+                .collect();
+            if written.is_empty()
+                || written
+                    .iter()
+                    .any(|account| referenced.contains(&account.binding()))
+            {
+                continue;
+            }
+            let names: Vec<&str> = written
+                .iter()
+                .map(|account| account.name.as_str())
+                .collect();
+            self.warn(
+                format!("functions.{}", function.name),
+                "unreferenced",
+                format!(
+                    "no invariant refers to the state `{}` writes ({}), so none constrains it",
+                    function.name,
+                    names.join(", ")
+                ),
+            );
+        }
+    }
+}
+
+/// The names the expressions of `conditions` refer to, leaving out those
+/// only read under `old(..)` if `current` is set.
+fn roots(conditions: &[Condition], current: bool) -> BTreeSet<&str> {
+    let mut roots = BTreeSet::new();
+    for condition in conditions {
+        condition.expr.visit_paths(&mut |path, in_old| {
+            if !(current && in_old) {
+                roots.insert(path[0].as_str());
+            }
+        });
+    }
+    roots
+}
+
+/// Checks `spec` for problems, from the errors [`validate`] reports to
+/// behavior it leaves unspecified, in the order they appear.
+pub fn lint(spec: &Spec) -> Vec<Lint> {
+    let mut linter = Linter::default();
+    if let Err(errors) = validate(spec) {
+        linter.lints.extend(errors.0.into_iter().map(|error| Lint {
+            location: error.location,
+            rule: "invalid",
+            severity: Severity::Error,
+            message: error.message,
+        }));
+    }
+    for function in &spec.functions {
+        linter.function(spec, function);
+    }
+    linter.unreferenced(spec);
+    for invariant in &spec.invariants {
+        linter.rounding(
+            &format!("invariants.{}", invariant.name),
+            &invariant.expr,
+            [invariant.description.as_ref()],
+        );
+    }
+    linter.lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Format;
+
+    const VAULT: &str = include_str!("../../../examples/specs/vault.yaml");
+
+    fn lints(src: &str) -> Vec<String> {
+        let spec = Spec::parse_unvalidated(src, Format::Yaml).unwrap();
+        lint(&spec).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_vault() {
+        // Nobody signs for the vault
+        let lints = lints(VAULT);
+        assert_eq!(lints.len(), 3);
+        assert_eq!(
+            lints[0],
+            "functions.deposit: warning: `vault` is writable and `Vault` has the key `owner`, \
+             but no signer says who may call `deposit` [authorization]"
+        );
+    }
+
+    #[test]
+    fn test_lint() {
+        let lints = lints(
+            r#"
+name: pool
+target: rust
+state:
+  - name: Pool
+    fields:
+      - { name: admin, type: pubkey }
+      - { name: shares, type: u64 }
+      - { name: tokens, type: u64 }
+functions:
+  - name: redeem
+    accounts:
+      - { name: pool, type: Pool, writable: true }
+      - { name: user, signer: true }
+    args:
+      - { name: shares, type: u64 }
+      - { name: memo, type: u64 }
+    requires:
+      - shares <= pool.shares
+    ensures:
+      - pool.tokens == old(pool.tokens) - shares * old(pool.tokens) / old(pool.shares)
+      - expr: pool.shares == old(pool.shares) - shares
+  - name: sweep
+    description: Sends the remainder, rounded down, to the admin.
+    accounts:
+      - { name: pool, type: Pool, writable: true }
+      - { name: admin, signer: true }
+    requires:
+      - admin == pool.admin
+    ensures:
+      - pool.tokens == old(pool.tokens) - old(pool.tokens) % 10
+      - pool.shares == pool.missing
+invariants:
+  - name: ratio
+    expr: pool.tokens / 2 <= pool.shares
+"#,
+        );
+        assert_eq!(
+            lints,
+            [
+                "functions.sweep.ensures[1]: error: `Pool` has no field `missing` [invalid]",
+                "functions.redeem.ensures[0]: warning: `pool.tokens == old(pool.tokens) - shares * \
+                 old(pool.tokens) / old(pool.shares)` divides but does not say which way it \
+                 rounds [rounding]",
+                "functions.redeem: warning: signer `user` is not checked by any precondition, so \
+                 anyone may sign [authorization]",
+                "functions.redeem: warning: argument `memo` appears in no condition [unused-argument]",
+                "invariants.ratio: warning: `pool.tokens / 2 <= pool.shares` divides but does not \
+                 say which way it rounds [rounding]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unconstrained_write() {
+        let src = VAULT.replace(
+            "      - vault.token_total == old(vault.token_total) + amount\n      - vault.shares_total == old(vault.shares_total)\n",
+            "      - amount > 0\n",
+        );
+        let lints = lints(&src);
+        assert!(lints.contains(
+            &"functions.reward: warning: `vault` is writable but no postcondition says how it \
+              changes [unconstrained-write]"
+                .to_string()
+        ));
+    }
+
+    #[test]
+    fn test_unreferenced() {
+        let src = VAULT.replace(
+            "\nfunctions:\n",
+            "  - name: Config
+    fields:
+      - { name: fee, type: u64 }
+
+functions:
+  - name: set_fee
+    accounts:
+      - { name: config, type: Config, writable: true }
+    args:
+      - { name: fee, type: u64 }
+    ensures:
+      - config.fee == fee
+",
+        );
+        let lints = lints(&src);
+        assert!(lints.contains(
+            &"functions.set_fee: warning: no invariant refers to the state `set_fee` writes \
+              (Config), so none constrains it [unreferenced]"
+                .to_string()
+        ));
+        assert!(!lints
+            .iter()
+            .any(|lint| lint.starts_with("functions.deposit: warning: no invariant")));
+    }
+}
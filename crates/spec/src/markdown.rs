@@ -0,0 +1,856 @@
+//! Lowering of human-written Markdown specifications.
+//!
+//! The document title names the program and level-two headings split it into
+//! sections:
+//!
+//! ```markdown
+//! # Vault
+//!
+//! ## Overview
+//! A vault that issues shares against deposited tokens.
+//!
+//! ## State
+//! ### Vault
+//! - `shares_total`: u64 — Total shares outstanding
+//!
+//! ## Instructions
+//! ### deposit
+//! Deposits tokens.
+//!
+//! Accounts:
+//! - `vault`: Vault, writable
+//!
+//! Arguments:
+//! - `amount`: u64
+//!
+//! Requires:
+//! - `amount > 0`
+//!
+//! Ensures:
+//! - `vault.token_total == old(vault.token_total) + amount` — Tokens are added.
+//!
+//! Examples:
+//! - **first**: `amount = 100` → `vault.token_total = 100` — Fills an empty vault.
+//! - **overflow**: `vault.token_total = max, amount = 1` → error
+//!
+//! ## Invariants
+//! - **solvency**: `vault.shares_total <= vault.token_total`
+//! ```
+//!
+//! Text other than list items becomes the description of the enclosing item.
+//! Problems are reported as [`Diagnostic`]s rather than aborting, so that one
+//! pass surfaces everything a document is missing.
+
+use crate::ast::{
+    Account, AccountInput, Arg, Condition, Example, Field, Function, Invariant, Outcome, Spec,
+    Value,
+};
+use crate::expr::Expr;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// How serious a diagnostic is.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The document cannot be lowered faithfully
+    Error,
+    /// Lowering succeeded but something was ignored or is missing
+    Warning,
+}
+
+/// A problem found while lowering a Markdown specification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// 1-based line of the problem; 0 when it concerns the whole document
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        if self.line == 0 {
+            write!(f, "{severity}: {}", self.message)
+        } else {
+            write!(f, "line {}: {severity}: {}", self.line, self.message)
+        }
+    }
+}
+
+/// The diagnostics of a Markdown specification that failed to lower.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Section {
+    Overview,
+    State,
+    Instructions,
+    Invariants,
+}
+
+impl Section {
+    fn from_heading(heading: &str) -> Option<Section> {
+        Some(match heading.to_ascii_lowercase().as_str() {
+            "overview" | "description" => Section::Overview,
+            "state" | "state layout" | "accounts" => Section::State,
+            "instructions" | "functions" => Section::Instructions,
+            "invariants" => Section::Invariants,
+            _ => return None,
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Section::Overview => "Overview",
+            Section::State => "State",
+            Section::Instructions => "Instructions",
+            Section::Invariants => "Invariants",
+        }
+    }
+}
+
+/// Which list of an instruction the following items belong to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Label {
+    Accounts,
+    Arguments,
+    Requires,
+    Ensures,
+    Examples,
+}
+
+impl Label {
+    /// Recognizes `Accounts:`, `**Accounts**`, `#### Accounts` and the like.
+    fn parse(line: &str) -> Option<Label> {
+        let text = line
+            .trim_start_matches('#')
+            .trim()
+            .replace(['*', '_', ':'], "");
+        Some(match text.trim().to_ascii_lowercase().as_str() {
+            "accounts" => Label::Accounts,
+            "arguments" | "args" => Label::Arguments,
+            "requires" | "preconditions" => Label::Requires,
+            "ensures" | "postconditions" => Label::Ensures,
+            "examples" => Label::Examples,
+            _ => return None,
+        })
+    }
+}
+
+/// Accumulates free text into a description: lines are joined with spaces,
+/// paragraphs with a blank line.
+#[derive(Default)]
+struct Text {
+    text: String,
+    paragraph_break: bool,
+}
+
+impl Text {
+    fn push(&mut self, line: &str) {
+        if line.is_empty() {
+            self.paragraph_break = !self.text.is_empty();
+            return;
+        }
+        if self.paragraph_break {
+            self.text.push_str("\n\n");
+        } else if !self.text.is_empty() {
+            self.text.push(' ');
+        }
+        self.paragraph_break = false;
+        self.text.push_str(line);
+    }
+
+    fn take(&mut self) -> Option<String> {
+        self.paragraph_break = false;
+        Some(std::mem::take(&mut self.text)).filter(|text| !text.is_empty())
+    }
+}
+
+/// Separators between the structured part of a list item and its description.
+const DESCRIPTION_SEPARATORS: [&str; 3] = [" — ", " -- ", " - "];
+
+/// Splits off a trailing description.
+fn split_description(text: &str) -> (&str, Option<String>) {
+    DESCRIPTION_SEPARATORS
+        .iter()
+        .filter_map(|separator| text.find(separator).map(|i| (i, separator.len())))
+        .min()
+        .map_or((text.trim(), None), |(i, len)| {
+            let description = text[i + len..].trim();
+            (
+                text[..i].trim(),
+                Some(description.to_string()).filter(|d| !d.is_empty()),
+            )
+        })
+}
+
+/// Returns the contents of the leading code span and the text after it.
+fn code_span(text: &str) -> Option<(&str, &str)> {
+    let rest = text.trim_start().strip_prefix('`')?;
+    let end = rest.find('`')?;
+    Some((&rest[..end], &rest[end + 1..]))
+}
+
+/// Returns the item name, written as a code span or in bold, and the text
+/// after the colon following it.
+fn item_name(text: &str) -> Option<(&str, &str)> {
+    let (name, rest) = match code_span(text) {
+        Some(span) => span,
+        None => {
+            let rest = text.trim_start().strip_prefix("**")?;
+            let end = rest.find("**")?;
+            (&rest[..end], &rest[end + 2..])
+        }
+    };
+    let rest = rest.trim_start();
+    Some((name.trim(), rest.strip_prefix(':').unwrap_or(rest)))
+}
+
+/// Separators between the inputs of an example and its outcome.
+const OUTCOME_SEPARATORS: [&str; 2] = ["→", "->"];
+
+/// Field and argument values written as `account.field = value, arg = value`.
+type Assignments = (
+    BTreeMap<String, BTreeMap<String, Value>>,
+    BTreeMap<String, Value>,
+);
+
+/// Parses the assignments of an example.
+fn assignments(src: &str) -> Result<Assignments, String> {
+    let mut state: BTreeMap<String, BTreeMap<String, Value>> = BTreeMap::new();
+    let mut args = BTreeMap::new();
+    for assignment in src.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("expected `name = value`, found `{assignment}`"))?;
+        let value = match value.trim() {
+            "max" => Value::Max,
+            "min" => Value::Min,
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            value => Value::Int(
+                value
+                    .replace('_', "")
+                    .parse()
+                    .map_err(|_| format!("invalid value `{value}`"))?,
+            ),
+        };
+        match name.trim().split_once('.') {
+            Some((account, field)) => {
+                state
+                    .entry(account.to_string())
+                    .or_default()
+                    .insert(field.to_string(), value);
+            }
+            None => {
+                args.insert(name.trim().to_string(), value);
+            }
+        }
+    }
+    Ok((state, args))
+}
+
+/// Turns a document title into a program name, e.g. `Share Vault` into
+/// `share_vault`.
+fn program_name(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+struct Lowering {
+    spec: Spec,
+    diagnostics: Vec<Diagnostic>,
+    /// Line each section was first seen on
+    seen: Vec<(Section, usize)>,
+    section: Option<Section>,
+    label: Option<Label>,
+    text: Text,
+}
+
+impl Lowering {
+    fn error(&mut self, line: usize, message: impl Into<String>) {
+        self.report(line, Severity::Error, message);
+    }
+
+    fn warning(&mut self, line: usize, message: impl Into<String>) {
+        self.report(line, Severity::Warning, message);
+    }
+
+    fn report(&mut self, line: usize, severity: Severity, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            severity,
+            message: message.into(),
+        });
+    }
+
+    /// Attaches the pending free text to the innermost open item.
+    fn flush_text(&mut self) {
+        let Some(text) = self.text.take() else {
+            return;
+        };
+        let description = match self.section {
+            Some(Section::Overview) => &mut self.spec.description,
+            Some(Section::State) => match self.spec.state.last_mut() {
+                Some(account) => &mut account.description,
+                None => return,
+            },
+            Some(Section::Instructions) => match self.spec.functions.last_mut() {
+                Some(function) => &mut function.description,
+                None => return,
+            },
+            Some(Section::Invariants) | None => return,
+        };
+        match description {
+            Some(existing) => {
+                existing.push_str("\n\n");
+                existing.push_str(&text);
+            }
+            None => *description = Some(text),
+        }
+    }
+
+    fn title(&mut self, line: usize, title: &str) {
+        if !self.spec.name.is_empty() {
+            self.error(line, format!("second document title `{title}`"));
+            return;
+        }
+        self.spec.name = program_name(title);
+        if self.spec.name.is_empty() {
+            self.error(
+                line,
+                format!("title `{title}` does not yield a program name"),
+            );
+        }
+    }
+
+    fn section(&mut self, line: usize, heading: &str) {
+        self.label = None;
+        self.section = Section::from_heading(heading);
+        let Some(section) = self.section else {
+            self.warning(line, format!("ignoring unknown section `{heading}`"));
+            return;
+        };
+        match self.seen.iter().find(|(seen, _)| *seen == section) {
+            Some(&(_, first)) => self.error(
+                line,
+                format!(
+                    "ambiguous specification: section `{}` already appears on line {first}",
+                    section.name()
+                ),
+            ),
+            None => self.seen.push((section, line)),
+        }
+    }
+
+    fn item(&mut self, line: usize, heading: &str) {
+        self.label = None;
+        let name = heading.trim_matches('`').to_string();
+        match self.section {
+            Some(Section::State) => self.spec.state.push(Account {
+                name,
+                description: None,
+                fields: Vec::new(),
+            }),
+            Some(Section::Instructions) => self.spec.functions.push(Function {
+                name,
+                description: None,
+                accounts: Vec::new(),
+                args: Vec::new(),
+                requires: Vec::new(),
+                ensures: Vec::new(),
+                examples: Vec::new(),
+                compute_units: None,
+            }),
+            Some(section) => self.warning(
+                line,
+                format!("ignoring subsection `{heading}` of `{}`", section.name()),
+            ),
+            None => {}
+        }
+    }
+
+    fn list_item(&mut self, line: usize, item: &str) {
+        match self.section {
+            Some(Section::State) => self.field(line, item),
+            Some(Section::Instructions) => self.instruction_item(line, item),
+            Some(Section::Invariants) => self.invariant(line, item),
+            Some(Section::Overview) => self.text.push(item),
+            None => {}
+        }
+    }
+
+    fn field(&mut self, line: usize, item: &str) {
+        let Some((name, rest)) = item_name(item) else {
+            self.error(line, "expected a field written as `name`: type");
+            return;
+        };
+        let (ty, description) = split_description(rest);
+        let ty = match ty.trim_matches('`').parse() {
+            Ok(ty) => ty,
+            Err(message) => {
+                self.error(line, format!("field `{name}`: {message}"));
+                return;
+            }
+        };
+        let field = Field {
+            name: name.to_string(),
+            ty,
+            description,
+        };
+        match self.spec.state.last_mut() {
+            Some(account) => account.fields.push(field),
+            None => self.error(line, "field outside of an account; add a `###` heading"),
+        }
+    }
+
+    fn instruction_item(&mut self, line: usize, item: &str) {
+        if self.spec.functions.is_empty() {
+            self.error(
+                line,
+                "list item outside of an instruction; add a `###` heading",
+            );
+            return;
+        }
+        match self.label {
+            Some(Label::Accounts) => self.account_input(line, item),
+            Some(Label::Arguments) => self.arg(line, item),
+            Some(label @ (Label::Requires | Label::Ensures)) => {
+                let Some(condition) = self.condition(line, item) else {
+                    return;
+                };
+                let function = self.spec.functions.last_mut().expect("checked above");
+                match label {
+                    Label::Requires => function.requires.push(condition),
+                    _ => function.ensures.push(condition),
+                }
+            }
+            Some(Label::Examples) => self.example(line, item),
+            None => self.error(
+                line,
+                "ambiguous list item; precede it with `Accounts:`, `Arguments:`, \
+                 `Requires:`, `Ensures:` or `Examples:`",
+            ),
+        }
+    }
+
+    fn account_input(&mut self, line: usize, item: &str) {
+        let Some((name, rest)) = item_name(item) else {
+            self.error(line, "expected an account written as `name`: Type, flags");
+            return;
+        };
+        let (flags, _) = split_description(rest);
+        let mut account = AccountInput {
+            name: name.to_string(),
+            ty: None,
+            signer: false,
+            writable: false,
+        };
+        for flag in flags.split(',').map(|flag| flag.trim().trim_matches('`')) {
+            match flag {
+                "" => {}
+                "signer" => account.signer = true,
+                "writable" | "mut" | "mutable" => account.writable = true,
+                ty if account.ty.is_none() => account.ty = Some(ty.to_string()),
+                ty => self.error(line, format!("account `{name}` has a second type `{ty}`")),
+            }
+        }
+        self.spec
+            .functions
+            .last_mut()
+            .unwrap()
+            .accounts
+            .push(account);
+    }
+
+    fn arg(&mut self, line: usize, item: &str) {
+        let Some((name, rest)) = item_name(item) else {
+            self.error(line, "expected an argument written as `name`: type");
+            return;
+        };
+        let (ty, _) = split_description(rest);
+        match ty.trim_matches('`').parse() {
+            Ok(ty) => self.spec.functions.last_mut().unwrap().args.push(Arg {
+                name: name.to_string(),
+                ty,
+            }),
+            Err(message) => self.error(line, format!("argument `{name}`: {message}")),
+        }
+    }
+
+    fn condition(&mut self, line: usize, item: &str) -> Option<Condition> {
+        let Some((src, rest)) = code_span(item) else {
+            self.error(line, "expected a condition written as a `code span`");
+            return None;
+        };
+        let expr = self.expr(line, src)?;
+        let (_, description) = split_description(rest);
+        Some(Condition { expr, description })
+    }
+
+    fn example(&mut self, line: usize, item: &str) {
+        const USAGE: &str = "expected an example written as **name**: `inputs` → outcome";
+        let parts = item_name(item).and_then(|(name, rest)| {
+            OUTCOME_SEPARATORS
+                .iter()
+                .find_map(|separator| rest.split_once(separator))
+                .map(|(inputs, outcome)| (name, inputs.trim(), outcome))
+        });
+        let Some((name, inputs, outcome)) = parts else {
+            self.error(line, USAGE);
+            return;
+        };
+        let inputs = match inputs {
+            "" => "",
+            inputs => match code_span(inputs) {
+                Some((src, "")) => src,
+                _ => {
+                    self.error(line, USAGE);
+                    return;
+                }
+            },
+        };
+        let (outcome, description) = split_description(outcome);
+        let (expect, after) = match (outcome, code_span(outcome)) {
+            ("ok", _) => (Outcome::Ok, ""),
+            ("error", _) => (Outcome::Error, ""),
+            (_, Some((src, ""))) => (Outcome::Ok, src),
+            _ => {
+                self.error(
+                    line,
+                    format!("example `{name}`: expected `ok`, `error` or the state after the call"),
+                );
+                return;
+            }
+        };
+        let parsed = assignments(inputs).and_then(|(state, args)| {
+            let (after, args_after) = assignments(after)?;
+            match args_after.keys().next() {
+                Some(arg) => Err(format!("`{arg}` is not an account field")),
+                None => Ok((state, args, after)),
+            }
+        });
+        match parsed {
+            Ok((state, args, after)) => {
+                let function = self
+                    .spec
+                    .functions
+                    .last_mut()
+                    .expect("checked by the caller");
+                function.examples.push(Example {
+                    name: name.to_string(),
+                    description,
+                    state,
+                    args,
+                    expect,
+                    after,
+                });
+            }
+            Err(message) => self.error(line, format!("example `{name}`: {message}")),
+        }
+    }
+
+    fn invariant(&mut self, line: usize, item: &str) {
+        let named = item_name(item).and_then(|(name, rest)| Some((name, code_span(rest)?)));
+        let Some((name, (src, rest))) = named else {
+            self.error(
+                line,
+                "expected an invariant written as **name**: `expression`",
+            );
+            return;
+        };
+        let Some(expr) = self.expr(line, src) else {
+            return;
+        };
+        let (_, description) = split_description(rest);
+        self.spec.invariants.push(Invariant {
+            name: name.to_string(),
+            description,
+            expr,
+        });
+    }
+
+    fn expr(&mut self, line: usize, src: &str) -> Option<Expr> {
+        match src.parse() {
+            Ok(expr) => Some(expr),
+            Err(err) => {
+                self.error(line, format!("invalid expression `{src}`: {err}"));
+                None
+            }
+        }
+    }
+}
+
+/// Lowers a Markdown specification into the spec AST.
+///
+/// The returned spec is only meaningful when no diagnostic is an error; it
+/// has not been [validated](crate::validate()).
+pub fn lower(src: &str) -> (Spec, Vec<Diagnostic>) {
+    let mut lowering = Lowering {
+        spec: Spec {
+            name: String::new(),
+            version: None,
+            description: None,
+            source: None,
+            target: Default::default(),
+            state: Vec::new(),
+            functions: Vec::new(),
+            helpers: Vec::new(),
+            invariants: Vec::new(),
+            prover: Default::default(),
+        },
+        diagnostics: Vec::new(),
+        seen: Vec::new(),
+        section: None,
+        label: None,
+        text: Text::default(),
+    };
+    let mut in_fence = false;
+
+    for (i, raw) in src.lines().enumerate() {
+        let line = i + 1;
+        let trimmed = raw.trim();
+        if trimmed.starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+
+        let hashes = trimmed.len() - trimmed.trim_start_matches('#').len();
+        let heading = trimmed[hashes..].trim();
+        let is_heading = hashes > 0 && trimmed[hashes..].starts_with(' ');
+        let list_item = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "));
+
+        if (is_heading || list_item.is_some()) && lowering.section.is_some() {
+            lowering.flush_text();
+        }
+        if let Some(label) = Label::parse(trimmed)
+            .filter(|_| lowering.section == Some(Section::Instructions) && hashes != 2)
+        {
+            lowering.flush_text();
+            lowering.label = Some(label);
+        } else if is_heading && hashes == 1 {
+            lowering.title(line, heading);
+        } else if is_heading && hashes == 2 {
+            lowering.section(line, heading);
+        } else if is_heading && hashes == 3 {
+            lowering.item(line, heading);
+        } else if let Some(item) = list_item {
+            lowering.list_item(line, item.trim());
+        } else if lowering.section.is_some() {
+            lowering.text.push(trimmed);
+        }
+    }
+    lowering.flush_text();
+
+    if lowering.spec.name.is_empty()
+        && !lowering
+            .diagnostics
+            .iter()
+            .any(|d| d.message.contains("title"))
+    {
+        lowering.error(1, "missing document title (`# Name`)");
+    }
+    for (section, severity) in [
+        (Section::Overview, Severity::Warning),
+        (Section::State, Severity::Error),
+        (Section::Instructions, Severity::Error),
+        (Section::Invariants, Severity::Warning),
+    ] {
+        if !lowering.seen.iter().any(|(seen, _)| *seen == section) {
+            let message = format!("missing `## {}` section", section.name());
+            lowering.report(0, severity, message);
+        }
+    }
+
+    (lowering.spec, lowering.diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Type;
+
+    fn errors(src: &str) -> Vec<String> {
+        let (_, diagnostics) = lower(src);
+        diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_lower_sections() {
+        let (spec, diagnostics) = lower(
+            "# Share Vault
+
+## Overview
+Issues shares
+against tokens.
+
+More detail.
+
+## State
+### Vault
+The vault account.
+- `owner`: `pubkey`
+- **token_total**: u64 — Total tokens
+
+## Instructions
+### deposit
+**Accounts**
+- `vault`: Vault, mut
+- `payer`: signer - Pays the tokens
+#### Arguments
+- `amount`: u64
+Ensures:
+- `vault.token_total == old(vault.token_total) + amount` — Adds tokens.
+",
+        );
+        assert_eq!(spec.name, "share_vault");
+        assert_eq!(
+            spec.description.as_deref(),
+            Some("Issues shares against tokens.\n\nMore detail.")
+        );
+        let vault = &spec.state[0];
+        assert_eq!(vault.description.as_deref(), Some("The vault account."));
+        assert_eq!(vault.fields[0].ty, Type::Pubkey);
+        assert_eq!(vault.fields[1].description.as_deref(), Some("Total tokens"));
+        let deposit = &spec.functions[0];
+        assert_eq!(deposit.accounts[0].ty.as_deref(), Some("Vault"));
+        assert!(deposit.accounts[0].writable && !deposit.accounts[0].signer);
+        assert!(deposit.accounts[1].signer && deposit.accounts[1].ty.is_none());
+        assert_eq!(deposit.args[0].ty, Type::U64);
+        assert_eq!(
+            deposit.ensures[0].description.as_deref(),
+            Some("Adds tokens.")
+        );
+        // Only the missing invariants are reported
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["warning: missing `## Invariants` section"]
+        );
+    }
+
+    #[test]
+    fn test_missing_and_ambiguous_sections() {
+        assert_eq!(
+            errors("## State\n### A\n- `x`: u8\n## State\n"),
+            [
+                "line 4: error: ambiguous specification: section `State` already appears on line 1",
+                "line 1: error: missing document title (`# Name`)",
+                "error: missing `## Instructions` section",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_item_errors() {
+        assert_eq!(
+            errors(
+                "# P
+## State
+- `x`: u8
+### A
+- `y`: float
+## Instructions
+### f
+- `a`: u8
+Requires:
+- `a +`
+- a > 0
+## Invariants
+- `i`: a > 0
+"
+            ),
+            [
+                "line 3: error: field outside of an account; add a `###` heading",
+                "line 5: error: field `y`: unknown type `float`",
+                "line 8: error: ambiguous list item; precede it with `Accounts:`, \
+                 `Arguments:`, `Requires:`, `Ensures:` or `Examples:`",
+                "line 10: error: invalid expression `a +`: column 4: unexpected end of expression",
+                "line 11: error: expected a condition written as a `code span`",
+                "line 13: error: expected an invariant written as **name**: `expression`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_examples() {
+        let src = "# P
+## State
+### Counter
+- `count`: u64
+## Instructions
+### add
+Accounts:
+- `counter`: Counter, writable
+Arguments:
+- `by`: u64
+Examples:
+- **first**: `by = 1_000` → `counter.count = 1000` — Counts from zero.
+- **overflow**: `counter.count = max, by = 1` -> error
+- **noop**: → ok
+- **bad**: `by = lots` → ok
+- **worse**: `by = 1` → maybe
+## Invariants
+";
+        let (spec, _) = lower(src);
+        let examples = &spec.functions[0].examples;
+        assert_eq!(examples.len(), 3);
+        assert_eq!(examples[0].args["by"], Value::Int(1000));
+        assert_eq!(examples[0].after["counter"]["count"], Value::Int(1000));
+        assert_eq!(
+            examples[0].description.as_deref(),
+            Some("Counts from zero.")
+        );
+        assert_eq!(examples[1].state["counter"]["count"], Value::Max);
+        assert_eq!(examples[1].expect, Outcome::Error);
+        assert!(examples[2].args.is_empty() && examples[2].expect == Outcome::Ok);
+        assert_eq!(
+            errors(src),
+            [
+                "line 15: error: example `bad`: invalid value `lots`",
+                "line 16: error: example `worse`: expected `ok`, `error` or the state after the call",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fenced_code_is_ignored() {
+        let (spec, _) = lower("# P\n## State\n```\n### Fake\n```\n### Real\n- `x`: u8\n");
+        assert_eq!(spec.state.len(), 1);
+        assert_eq!(spec.state[0].name, "Real");
+    }
+}
@@ -0,0 +1,555 @@
+//! Semantic checks on a parsed specification.
+
+use crate::ast::{Example, Function, Outcome, Spec, Type, Value};
+use crate::expr::Expr;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// The most compute units a Solana transaction may consume.
+const MAX_COMPUTE_UNITS: u64 = 1_400_000;
+
+/// A single semantic problem in a specification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    /// Where the problem is, e.g. `functions.deposit.ensures[0]`
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Every problem found while validating a specification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Collects errors while walking the specification.
+#[derive(Default)]
+struct Validator {
+    errors: Vec<ValidationError>,
+}
+
+impl Validator {
+    fn error(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(ValidationError {
+            location: location.into(),
+            message: message.into(),
+        });
+    }
+
+    fn identifier(&mut self, location: &str, name: &str) {
+        let mut chars = name.chars();
+        let valid = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            self.error(location, format!("`{name}` is not a valid identifier"));
+        }
+    }
+
+    fn unique<'a>(&mut self, location: &str, what: &str, names: impl Iterator<Item = &'a str>) {
+        let mut seen = HashSet::new();
+        for name in names {
+            self.identifier(location, name);
+            if !seen.insert(name) {
+                self.error(location, format!("duplicate {what} `{name}`"));
+            }
+        }
+    }
+
+    /// Checks that `segments` names a field of `ty`, or the whole account
+    /// when empty.
+    fn field_path(&mut self, spec: &Spec, location: &str, ty: &str, segments: &[String]) {
+        let Some(account) = spec.account(ty) else {
+            // Unknown account types are reported where they are declared
+            return;
+        };
+        match segments {
+            [] => {}
+            [field] if account.field(field).is_some() => {}
+            [field] => self.error(location, format!("`{ty}` has no field `{field}`")),
+            _ => self.error(
+                location,
+                format!("`{}` does not name a field of `{ty}`", segments.join(".")),
+            ),
+        }
+    }
+
+    /// Resolves the paths of a function condition against its accounts and
+    /// arguments.
+    fn condition(&mut self, spec: &Spec, function: &Function, location: &str, expr: &Expr) {
+        expr.visit_paths(&mut |path, _| {
+            let (root, rest) = path.split_first().expect("paths are never empty");
+            if let Some(account) = function.account(root) {
+                match &account.ty {
+                    Some(ty) => self.field_path(spec, location, ty, rest),
+                    None if rest.is_empty() => {}
+                    None => self.error(
+                        location,
+                        format!("account `{root}` has no declared type, so it has no fields"),
+                    ),
+                }
+            } else if function.arg(root).is_some() {
+                if !rest.is_empty() {
+                    self.error(location, format!("argument `{root}` has no fields"));
+                }
+            } else {
+                self.error(location, format!("unknown name `{root}`"));
+            }
+        });
+    }
+
+    /// Checks that `value`, given for `name`, fits `ty`.
+    fn value(&mut self, location: &str, name: &str, ty: &Type, value: Value) {
+        let fits = match (ty, value) {
+            (Type::Bool, Value::Bool(_)) => true,
+            (Type::Bool | Type::Pubkey | Type::Array(..), _) => {
+                return self.error(location, format!("`{name}` cannot be given a value"));
+            }
+            (_, Value::Bool(_)) => false,
+            (_, Value::Max | Value::Min) => true,
+            (ty, Value::Int(value)) => {
+                let (min, max) = match ty {
+                    Type::U8 => (0, u8::MAX.into()),
+                    Type::U16 => (0, u16::MAX.into()),
+                    Type::U32 => (0, u32::MAX.into()),
+                    Type::U64 => (0, u64::MAX.into()),
+                    Type::I64 => (i64::MIN.into(), i64::MAX.into()),
+                    _ => (0, i128::MAX),
+                };
+                (min..=max).contains(&value)
+            }
+        };
+        if !fits {
+            self.error(
+                location,
+                format!("`{value}` is not a valid `{ty}` for `{name}`"),
+            );
+        }
+    }
+
+    /// Checks the account field values of an example against the accounts
+    /// of `function`.
+    fn state_values(
+        &mut self,
+        spec: &Spec,
+        function: &Function,
+        location: &str,
+        state: &BTreeMap<String, BTreeMap<String, Value>>,
+    ) {
+        for (name, fields) in state {
+            let Some(account) = function.account(name) else {
+                self.error(location, format!("unknown account `{name}`"));
+                continue;
+            };
+            let Some(ty) = account.ty.as_deref().and_then(|ty| spec.account(ty)) else {
+                self.error(location, format!("account `{name}` has no declared type"));
+                continue;
+            };
+            for (field, &value) in fields {
+                match ty.field(field) {
+                    Some(field) => self.value(
+                        location,
+                        &format!("{name}.{}", field.name),
+                        &field.ty,
+                        value,
+                    ),
+                    None => self.error(location, format!("`{}` has no field `{field}`", ty.name)),
+                }
+            }
+        }
+    }
+
+    fn example(&mut self, spec: &Spec, function: &Function, location: &str, example: &Example) {
+        self.state_values(spec, function, location, &example.state);
+        self.state_values(spec, function, location, &example.after);
+        for (name, &value) in &example.args {
+            match function.arg(name) {
+                Some(arg) => self.value(location, name, &arg.ty, value),
+                None => self.error(location, format!("unknown argument `{name}`")),
+            }
+        }
+        if example.expect == Outcome::Error && !example.after.is_empty() {
+            self.error(location, "a failing call has no `after` state");
+        }
+    }
+}
+
+/// Checks names, references and expressions of `spec`, returning every
+/// problem found.
+pub fn validate(spec: &Spec) -> Result<(), ValidationErrors> {
+    let mut v = Validator::default();
+
+    // The name becomes a directory and a package name
+    v.identifier("name", &spec.name);
+
+    v.unique(
+        "state",
+        "account",
+        spec.state.iter().map(|a| a.name.as_str()),
+    );
+    for account in &spec.state {
+        let location = format!("state.{}", account.name);
+        if account.fields.is_empty() {
+            v.error(&location, "account has no fields");
+        }
+        v.unique(
+            &location,
+            "field",
+            account.fields.iter().map(|f| f.name.as_str()),
+        );
+        for field in &account.fields {
+            if let Type::Array(_, 0) = field.ty {
+                v.error(
+                    &location,
+                    format!("field `{}` is an empty array", field.name),
+                );
+            }
+        }
+    }
+
+    v.unique(
+        "functions",
+        "function",
+        spec.functions.iter().map(|f| f.name.as_str()),
+    );
+    for function in &spec.functions {
+        let location = format!("functions.{}", function.name);
+        let names = function
+            .accounts
+            .iter()
+            .map(|a| a.name.as_str())
+            .chain(function.args.iter().map(|a| a.name.as_str()));
+        v.unique(&location, "account or argument", names);
+        for account in &function.accounts {
+            if let Some(ty) = &account.ty {
+                if spec.account(ty).is_none() {
+                    v.error(
+                        &location,
+                        format!("account `{}` has unknown type `{ty}`", account.name),
+                    );
+                }
+            }
+        }
+        for (i, condition) in function.requires.iter().enumerate() {
+            let location = format!("{location}.requires[{i}]");
+            if condition.expr.mentions_old() {
+                v.error(&location, "`old(..)` is only allowed in postconditions");
+            }
+            v.condition(spec, function, &location, &condition.expr);
+        }
+        for (i, condition) in function.ensures.iter().enumerate() {
+            let location = format!("{location}.ensures[{i}]");
+            v.condition(spec, function, &location, &condition.expr);
+        }
+        if let Some(units) = function.compute_units {
+            if !(1..=MAX_COMPUTE_UNITS).contains(&units) {
+                v.error(
+                    &location,
+                    format!("a budget of {units} compute units is not between 1 and {MAX_COMPUTE_UNITS}"),
+                );
+            }
+        }
+        v.unique(
+            &location,
+            "example",
+            function.examples.iter().map(|e| e.name.as_str()),
+        );
+        for example in &function.examples {
+            let location = format!("{location}.examples.{}", example.name);
+            v.example(spec, function, &location, example);
+        }
+    }
+
+    v.unique(
+        "helpers",
+        "helper",
+        spec.helpers.iter().map(|h| h.name.as_str()),
+    );
+    for helper in &spec.helpers {
+        let location = format!("helpers.{}", helper.name);
+        v.unique(
+            &location,
+            "argument",
+            helper.args.iter().map(|a| a.name.as_str()),
+        );
+        // Benchmarks pass the arguments by value
+        let types = helper
+            .args
+            .iter()
+            .map(|arg| (arg.name.as_str(), &arg.ty))
+            .chain([("the result", &helper.returns)]);
+        for (name, ty) in types {
+            if !ty.is_integer() && *ty != Type::Bool {
+                v.error(
+                    &location,
+                    format!("`{name}` is a `{ty}`; helpers take and return integers and booleans"),
+                );
+            }
+        }
+        for (name, &value) in &helper.bench {
+            match helper.arg(name) {
+                Some(arg) => v.value(&location, name, &arg.ty, value),
+                None => v.error(&location, format!("unknown argument `{name}`")),
+            }
+        }
+        if helper.throughput == Some(0) {
+            v.error(&location, "a throughput of 0 calls per second is no target");
+        }
+    }
+
+    v.unique(
+        "invariants",
+        "invariant",
+        spec.invariants.iter().map(|i| i.name.as_str()),
+    );
+    for invariant in &spec.invariants {
+        let location = format!("invariants.{}", invariant.name);
+        if invariant.expr.mentions_old() {
+            v.error(&location, "`old(..)` is not allowed in invariants");
+        }
+        // Invariants range over the state, which they name by binding
+        invariant.expr.visit_paths(&mut |path, _| {
+            let (root, rest) = path.split_first().expect("paths are never empty");
+            match spec.state.iter().find(|a| a.binding() == *root) {
+                Some(account) => v.field_path(spec, &location, &account.name, rest),
+                None => v.error(&location, format!("unknown state account `{root}`")),
+            }
+        });
+    }
+
+    for (pattern, settings) in &spec.prover {
+        let location = format!("prover.{pattern}");
+        if pattern.is_empty() {
+            v.error("prover", "a rule name or pattern must not be empty");
+        }
+        if settings.loop_iter == Some(0) {
+            v.error(&location, "`loop_iter` must unroll loops at least once");
+        }
+        if settings.smt_timeout == Some(0) {
+            v.error(
+                &location,
+                "`smt_timeout` of 0 seconds leaves no time to solve",
+            );
+        }
+    }
+
+    if v.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationErrors(v.errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(yaml: &str) -> Vec<String> {
+        let spec: Spec = serde_yaml::from_str(yaml).unwrap();
+        match validate(&spec) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.0.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    const STATE: &str = "
+name: counter
+state:
+  - name: Counter
+    fields:
+      - { name: count, type: u64 }
+";
+
+    #[test]
+    fn test_valid_spec() {
+        let yaml = format!(
+            "{STATE}
+functions:
+  - name: increment
+    accounts:
+      - {{ name: counter, type: Counter, writable: true }}
+      - {{ name: authority, signer: true }}
+    args:
+      - {{ name: by, type: u64 }}
+    compute_units: 2000
+    requires: [counter.count + by >= by]
+    ensures: [counter.count == old(counter.count) + by]
+    examples:
+      - {{ name: from_zero, args: {{ by: 3 }}, after: {{ counter: {{ count: 3 }} }} }}
+      - {{ name: overflow, state: {{ counter: {{ count: max }} }}, args: {{ by: 1 }}, expect: error }}
+invariants:
+  - {{ name: bounded, expr: counter.count <= 1000 }}
+"
+        );
+        assert_eq!(errors(&yaml), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_examples() {
+        let yaml = format!(
+            "{STATE}
+functions:
+  - name: increment
+    accounts:
+      - {{ name: counter, type: Counter }}
+      - {{ name: authority }}
+    args:
+      - {{ name: by, type: u8 }}
+    compute_units: 0
+    examples:
+      - name: bad
+        state: {{ counter: {{ count: -1, total: 0 }}, authority: {{ lamports: 0 }} }}
+        args: {{ by: 256, to: 0 }}
+        expect: error
+        after: {{ counter: {{ count: 0 }} }}
+      - {{ name: bad }}
+"
+        );
+        assert_eq!(
+            errors(&yaml),
+            [
+                "functions.increment: a budget of 0 compute units is not between 1 and 1400000",
+                "functions.increment: duplicate example `bad`",
+                "functions.increment.examples.bad: account `authority` has no declared type",
+                "functions.increment.examples.bad: `-1` is not a valid `u64` for `counter.count`",
+                "functions.increment.examples.bad: `Counter` has no field `total`",
+                "functions.increment.examples.bad: `256` is not a valid `u8` for `by`",
+                "functions.increment.examples.bad: unknown argument `to`",
+                "functions.increment.examples.bad: a failing call has no `after` state",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unresolved_names() {
+        let yaml = format!(
+            "{STATE}
+functions:
+  - name: increment
+    accounts:
+      - {{ name: counter, type: Counter }}
+      - {{ name: other, type: Missing }}
+    requires: [old(counter.count) == 0, counter.total == by]
+invariants:
+  - {{ name: bounded, expr: vault.count <= 1000 }}
+"
+        );
+        assert_eq!(
+            errors(&yaml),
+            [
+                "functions.increment: account `other` has unknown type `Missing`",
+                "functions.increment.requires[0]: `old(..)` is only allowed in postconditions",
+                "functions.increment.requires[1]: `Counter` has no field `total`",
+                "functions.increment.requires[1]: unknown name `by`",
+                "invariants.bounded: unknown state account `vault`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_helpers() {
+        let yaml = "
+name: math
+helpers:
+  - name: mul_div
+    args:
+      - { name: a, type: u64 }
+      - { name: b, type: u64 }
+      - { name: c, type: u64 }
+    returns: u128
+    bench: { a: 1000000, b: max, c: 3 }
+    throughput: 10000000
+  - name: scale
+    args:
+      - { name: key, type: pubkey }
+    returns: '[u8; 4]'
+    bench: { key: 1, by: 2 }
+    throughput: 0
+";
+        assert_eq!(
+            errors(yaml),
+            [
+                "helpers.scale: `key` is a `pubkey`; helpers take and return integers and booleans",
+                "helpers.scale: `the result` is a `[u8; 4]`; helpers take and return integers and booleans",
+                "helpers.scale: unknown argument `by`",
+                "helpers.scale: `key` cannot be given a value",
+                "helpers.scale: a throughput of 0 calls per second is no target",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prover_settings() {
+        let yaml = format!(
+            "{STATE}
+prover:
+  'rule_*_solvency': {{ loop_iter: 4, arithmetic: bitvector, smt_timeout: 600 }}
+  rule_reset_sanity: {{ loop_iter: 0, smt_timeout: 0 }}
+"
+        );
+        assert_eq!(
+            errors(&yaml),
+            [
+                "prover.rule_reset_sanity: `loop_iter` must unroll loops at least once",
+                "prover.rule_reset_sanity: `smt_timeout` of 0 seconds leaves no time to solve",
+            ]
+        );
+        let spec: Spec = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(
+            spec.prover["rule_*_solvency"].arithmetic,
+            Some(crate::Arithmetic::Bitvector)
+        );
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let yaml = format!(
+            "{STATE}
+      - {{ name: count, type: u32 }}
+functions:
+  - name: f
+    accounts: [{{ name: x }}]
+    args: [{{ name: x, type: u8 }}]
+  - name: f
+"
+        );
+        assert_eq!(
+            errors(&yaml),
+            [
+                "state.Counter: duplicate field `count`",
+                "functions: duplicate function `f`",
+                "functions.f: duplicate account or argument `x`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spec_name() {
+        for name in ["''", "../../../tmp/evil", "share-vault", "2vault"] {
+            let yaml = STATE.replace("name: counter", &format!("name: {name}"));
+            let errors = errors(&yaml);
+            assert_eq!(errors.len(), 1, "{name}: {errors:?}");
+            assert!(errors[0].starts_with("name: `"), "{}", errors[0]);
+        }
+        assert!(errors(&STATE.replace("name: counter", "name: share_vault")).is_empty());
+    }
+}
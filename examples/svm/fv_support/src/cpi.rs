@@ -0,0 +1,276 @@
+//! Calls into the system and SPL token programs, and sysvar reads, shared by
+//! the Solana examples.
+//!
+//! The prover cannot see the code of a program called through CPI, so each
+//! call here has two bodies: the `invoke` a program makes on chain and, with
+//! the `certora` feature, a summary of what the callee may do, which the
+//! rules see instead. The summaries are vetted once here rather than rolled
+//! by hand next to each processor: a transfer either fails or moves exactly
+//! its amount, conserving the sum of the two balances, and a sysvar holds
+//! any value.
+//!
+//! ```ignore
+//! cpi::system::transfer(payer, vault, system_program, amount, &[])?;
+//! let now = cpi::sysvar::clock()?.unix_timestamp;
+//! let expired = cpi::sysvar::slot()? >= end_slot;
+//! ```
+
+use solana_program::program_error::ProgramError;
+
+/// The balances of two accounts after `amount` moves from the first to the
+/// second, which fails when the first cannot pay it. A transfer to the same
+/// account leaves its balance alone.
+pub fn moved(from: u64, to: u64, amount: u64, same: bool) -> Result<(u64, u64), ProgramError> {
+    let from = from
+        .checked_sub(amount)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    if same {
+        return Ok((from + amount, to));
+    }
+    let to = to
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    Ok((from, to))
+}
+
+/// Fails nondeterministically, standing for the failures of a callee the
+/// summary does not model, e.g. a frozen account.
+#[cfg(feature = "certora")]
+fn may_fail() -> Result<(), ProgramError> {
+    if cvlr::nondet::<bool>() {
+        return Err(ProgramError::Custom(cvlr::nondet()));
+    }
+    Ok(())
+}
+
+/// The system program.
+pub mod system {
+    use solana_program::account_info::AccountInfo;
+    use solana_program::entrypoint::ProgramResult;
+
+    /// Moves `lamports` from `from` to `to`, signing for `from` with
+    /// `signer_seeds` when it is a PDA.
+    #[cfg(not(feature = "certora"))]
+    pub fn transfer<'a>(
+        from: &AccountInfo<'a>,
+        to: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        lamports: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        use solana_program::{program::invoke_signed, system_instruction};
+
+        invoke_signed(
+            &system_instruction::transfer(from.key, to.key, lamports),
+            &[from.clone(), to.clone(), system_program.clone()],
+            signer_seeds,
+        )
+    }
+
+    /// Fails, or moves `lamports` from `from` to `to`, conserving their sum:
+    /// the system program fails when `from` cannot pay them or does not
+    /// sign.
+    #[cfg(feature = "certora")]
+    pub fn transfer(
+        from: &AccountInfo,
+        to: &AccountInfo,
+        _system_program: &AccountInfo,
+        lamports: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        use solana_program::program_error::ProgramError;
+
+        super::may_fail()?;
+        if !from.is_signer && signer_seeds.is_empty() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let (from_lamports, to_lamports) =
+            super::moved(from.lamports(), to.lamports(), lamports, from.key == to.key)?;
+        **from.try_borrow_mut_lamports()? = from_lamports;
+        **to.try_borrow_mut_lamports()? = to_lamports;
+        Ok(())
+    }
+}
+
+/// The SPL token program.
+pub mod token {
+    use solana_program::account_info::AccountInfo;
+    use solana_program::entrypoint::ProgramResult;
+    use solana_program::instruction::{AccountMeta, Instruction};
+    use solana_program::program_error::ProgramError;
+    use solana_program::pubkey::Pubkey;
+
+    /// The address of the SPL token program.
+    pub const ID: Pubkey = solana_program::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+    /// The size of a token account.
+    pub const ACCOUNT_LEN: usize = 165;
+
+    /// Where a token account keeps its amount, after its mint and owner.
+    const AMOUNT: std::ops::Range<usize> = 64..72;
+
+    /// The amount held by the token account `account`.
+    pub fn amount(account: &AccountInfo) -> Result<u64, ProgramError> {
+        let data = account.try_borrow_data()?;
+        if data.len() != ACCOUNT_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(u64::from_le_bytes(
+            data[AMOUNT].try_into().expect("eight bytes"),
+        ))
+    }
+
+    /// The `Transfer` instruction of `amount` from `source` to
+    /// `destination`, signed by `authority`.
+    pub fn transfer_ix(
+        source: &Pubkey,
+        destination: &Pubkey,
+        authority: &Pubkey,
+        amount: u64,
+    ) -> Instruction {
+        let mut data = vec![3];
+        data.extend_from_slice(&amount.to_le_bytes());
+        Instruction {
+            program_id: ID,
+            accounts: vec![
+                AccountMeta::new(*source, false),
+                AccountMeta::new(*destination, false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+            data,
+        }
+    }
+
+    /// Moves `amount` tokens from `source` to `destination`, signing for
+    /// `authority` with `signer_seeds` when it is a PDA.
+    #[cfg(not(feature = "certora"))]
+    pub fn transfer<'a>(
+        token_program: &AccountInfo<'a>,
+        source: &AccountInfo<'a>,
+        destination: &AccountInfo<'a>,
+        authority: &AccountInfo<'a>,
+        amount: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        solana_program::program::invoke_signed(
+            &transfer_ix(source.key, destination.key, authority.key, amount),
+            &[
+                source.clone(),
+                destination.clone(),
+                authority.clone(),
+                token_program.clone(),
+            ],
+            signer_seeds,
+        )
+    }
+
+    /// Fails, or moves `amount` tokens from `source` to `destination`,
+    /// conserving their sum: the token program fails when `source` holds
+    /// fewer or `authority` does not sign.
+    #[cfg(feature = "certora")]
+    pub fn transfer(
+        _token_program: &AccountInfo,
+        source: &AccountInfo,
+        destination: &AccountInfo,
+        authority: &AccountInfo,
+        amount: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        super::may_fail()?;
+        if !authority.is_signer && signer_seeds.is_empty() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let (source_amount, destination_amount) = super::moved(
+            self::amount(source)?,
+            self::amount(destination)?,
+            amount,
+            source.key == destination.key,
+        )?;
+        source.try_borrow_mut_data()?[AMOUNT].copy_from_slice(&source_amount.to_le_bytes());
+        destination.try_borrow_mut_data()?[AMOUNT]
+            .copy_from_slice(&destination_amount.to_le_bytes());
+        Ok(())
+    }
+}
+
+/// The sysvars, read through their syscalls.
+pub mod sysvar {
+    use solana_program::clock::{Clock, Slot};
+    use solana_program::program_error::ProgramError;
+    use solana_program::rent::Rent;
+
+    /// The current `Clock`.
+    #[cfg(not(feature = "certora"))]
+    pub fn clock() -> Result<Clock, ProgramError> {
+        solana_program::sysvar::Sysvar::get()
+    }
+
+    /// Any `Clock`.
+    #[cfg(feature = "certora")]
+    pub fn clock() -> Result<Clock, ProgramError> {
+        Ok(crate::nondet::clock())
+    }
+
+    /// The current slot.
+    #[cfg(not(feature = "certora"))]
+    pub fn slot() -> Result<Slot, ProgramError> {
+        Ok(clock()?.slot)
+    }
+
+    /// Any slot after those read before, as the slot only moves forward
+    /// between the calls a rule makes.
+    #[cfg(feature = "certora")]
+    pub fn slot() -> Result<Slot, ProgramError> {
+        Ok(cvlr_solana::cvt_get_next_clock_slot())
+    }
+
+    /// The current `Rent`.
+    #[cfg(not(feature = "certora"))]
+    pub fn rent() -> Result<Rent, ProgramError> {
+        solana_program::sysvar::Sysvar::get()
+    }
+
+    /// A `Rent` with any charge per byte-year.
+    #[cfg(feature = "certora")]
+    pub fn rent() -> Result<Rent, ProgramError> {
+        Ok(crate::nondet::rent())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestAccount;
+    use solana_program::pubkey::Pubkey;
+
+    #[test]
+    fn test_moved() {
+        assert_eq!(moved(10, 5, 4, false), Ok((6, 9)));
+        assert_eq!(moved(10, 5, 4, true), Ok((10, 5)));
+        assert_eq!(moved(3, 5, 4, false), Err(ProgramError::InsufficientFunds));
+        assert_eq!(
+            moved(10, u64::MAX, 1, false),
+            Err(ProgramError::ArithmeticOverflow)
+        );
+    }
+
+    #[test]
+    fn test_token_accounts() {
+        let mut data = vec![0; token::ACCOUNT_LEN];
+        data[64..72].copy_from_slice(&42u64.to_le_bytes());
+        let mut account = TestAccount::new(token::ID).data(data);
+        assert_eq!(token::amount(&account.info()), Ok(42));
+        let mut short = TestAccount::new(token::ID).data(vec![0; 72]);
+        assert_eq!(
+            token::amount(&short.info()),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let (source, destination, authority) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let ix = token::transfer_ix(&source, &destination, &authority, 7);
+        assert_eq!(ix.program_id, token::ID);
+        assert_eq!(ix.data, [3, 7, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(ix.accounts[2].is_signer && !ix.accounts[2].is_writable);
+    }
+}
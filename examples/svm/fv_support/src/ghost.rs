@@ -0,0 +1,310 @@
+//! Ghost state: values the rules track across calls that no account holds.
+//!
+//! A property such as "the tokens withdrawn never exceed those deposited plus
+//! the rewards" depends on the history of the vault, not on its state after
+//! one call. Each such history is a ghost, declared by name with the
+//! [`Hook`]s that update it: a hook follows one field of a state account
+//! and adds each increase or decrease of the field to its ghost. The
+//! processor loads its state accounts through [`load`], and with the
+//! `ghosts` feature (which `certora` turns on) the [`Tracked`] it returns
+//! runs the hooks on the account's fields when it is stored, i.e. dropped.
+//! Without the feature it is a plain cast of the data, as
+//! `bytemuck::from_bytes_mut` is.
+//!
+//! ```ignore
+//! ghost::declare("deposited", &[hook!(Vault, token_total, On::Increase)]);
+//! ghost::declare("withdrawn", &[hook!(Vault, token_total, On::Decrease)]);
+//!
+//! let mut vault = ghost::load::<Vault>("Vault", &mut vault_data);
+//! vault.token_total = new_total.into();
+//! drop(vault);
+//! cvlr_assert!(ghost::get("withdrawn") <= ghost::get("deposited"));
+//! ```
+//!
+//! The registry is per thread, so the rules and tests running side by side
+//! each see their own ghosts.
+
+use bytemuck::Pod;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+/// Which changes of its field a hook adds to its ghost.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum On {
+    Increase,
+    Decrease,
+}
+
+/// A field of a state account a ghost follows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hook {
+    /// The name the account is loaded under, e.g. its type
+    pub account: &'static str,
+    /// The bytes of the field in the account's state, holding a
+    /// little-endian unsigned integer of at most 16 bytes
+    pub offset: usize,
+    pub size: usize,
+    pub on: On,
+}
+
+/// The [`Hook`] following `$field` of the state account `$ty`, loaded under
+/// the name of its type.
+#[macro_export]
+macro_rules! hook {
+    ($ty:ident, $field:ident, $on:expr) => {
+        $crate::ghost::Hook {
+            account: stringify!($ty),
+            offset: ::core::mem::offset_of!($ty, $field),
+            size: $crate::ghost::field_size(|state: &$ty| &state.$field),
+            on: $on,
+        }
+    };
+}
+pub use hook;
+
+/// The size of the field `field` borrows.
+pub fn field_size<T, F>(_field: fn(&T) -> &F) -> usize {
+    std::mem::size_of::<F>()
+}
+
+#[derive(Default)]
+struct Registry {
+    values: BTreeMap<&'static str, u128>,
+    hooks: Vec<(&'static str, Hook)>,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+}
+
+/// Declares the ghost `name`, starting at zero and updated by `hooks`. A
+/// ghost declared again starts over with the new hooks.
+pub fn declare(name: &'static str, hooks: &[Hook]) {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.values.insert(name, 0);
+        registry.hooks.retain(|(ghost, _)| *ghost != name);
+        registry
+            .hooks
+            .extend(hooks.iter().map(|hook| (name, *hook)));
+    });
+}
+
+/// The value of the ghost `name`.
+///
+/// # Panics
+///
+/// If no ghost `name` is declared, so a misspelt name fails the rule rather
+/// than reading zero.
+pub fn get(name: &str) -> u128 {
+    REGISTRY.with(|registry| {
+        *registry
+            .borrow()
+            .values
+            .get(name)
+            .unwrap_or_else(|| panic!("no ghost `{name}` is declared"))
+    })
+}
+
+/// Sets the declared ghost `name` to `value`, e.g. to any value a rule then
+/// constrains.
+///
+/// # Panics
+///
+/// If no ghost `name` is declared.
+pub fn set(name: &str, value: u128) {
+    REGISTRY.with(|registry| {
+        *registry
+            .borrow_mut()
+            .values
+            .get_mut(name)
+            .unwrap_or_else(|| panic!("no ghost `{name}` is declared")) = value;
+    });
+}
+
+/// The declared ghosts, by name.
+pub fn ghosts() -> BTreeMap<&'static str, u128> {
+    REGISTRY.with(|registry| registry.borrow().values.clone())
+}
+
+/// Forgets every ghost and hook.
+pub fn clear() {
+    REGISTRY.with(|registry| *registry.borrow_mut() = Registry::default());
+}
+
+/// The little-endian unsigned integer in `bytes`.
+#[cfg(feature = "ghosts")]
+fn value(bytes: &[u8]) -> u128 {
+    let mut le = [0u8; 16];
+    le[..bytes.len()].copy_from_slice(bytes);
+    u128::from_le_bytes(le)
+}
+
+/// Runs the hooks on `account` over its state going from `before` to
+/// `after`.
+#[cfg(feature = "ghosts")]
+fn run_hooks(account: &str, before: &[u8], after: &[u8]) {
+    REGISTRY.with(|registry| {
+        let registry = &mut *registry.borrow_mut();
+        for (ghost, hook) in &registry.hooks {
+            if hook.account != account {
+                continue;
+            }
+            let field = hook.offset..hook.offset + hook.size;
+            let (old, new) = (value(&before[field.clone()]), value(&after[field]));
+            let change = match hook.on {
+                On::Increase => new.saturating_sub(old),
+                On::Decrease => old.saturating_sub(new),
+            };
+            let total = registry
+                .values
+                .get_mut(ghost)
+                .expect("hooked ghosts are declared");
+            *total = total.saturating_add(change);
+        }
+    });
+}
+
+/// The state `T` of an account, loaded by [`load`]. With the `ghosts`
+/// feature, storing it back, i.e. dropping it, runs the hooks on the
+/// account.
+pub struct Tracked<'d, T: Pod> {
+    account: &'static str,
+    data: &'d mut [u8],
+    #[cfg(feature = "ghosts")]
+    before: Vec<u8>,
+    state: PhantomData<T>,
+}
+
+/// Loads the state `T` held in `data` of the account called `account` in
+/// the hooks.
+///
+/// # Panics
+///
+/// As `bytemuck::from_bytes_mut`, if `data` does not hold a `T`.
+pub fn load<'d, T: Pod>(account: &'static str, data: &'d mut [u8]) -> Tracked<'d, T> {
+    let _: &T = bytemuck::from_bytes(data);
+    Tracked {
+        account,
+        #[cfg(feature = "ghosts")]
+        before: data.to_vec(),
+        data,
+        state: PhantomData,
+    }
+}
+
+impl<T: Pod> Tracked<'_, T> {
+    /// The name the account was loaded under.
+    pub fn account(&self) -> &'static str {
+        self.account
+    }
+}
+
+impl<T: Pod> Deref for Tracked<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        bytemuck::from_bytes(self.data)
+    }
+}
+
+impl<T: Pod> DerefMut for Tracked<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        bytemuck::from_bytes_mut(self.data)
+    }
+}
+
+#[cfg(feature = "ghosts")]
+impl<T: Pod> Drop for Tracked<'_, T> {
+    fn drop(&mut self) {
+        run_hooks(self.account, &self.before, self.data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::{Pod, Zeroable};
+
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    #[repr(C)]
+    struct Vault {
+        principal: u64,
+        rewards: u32,
+        _padding: u32,
+    }
+
+    fn deposit(data: &mut [u8], amount: u64) {
+        let mut vault = load::<Vault>("Vault", data);
+        vault.principal += amount;
+    }
+
+    fn reward(data: &mut [u8], amount: u32) {
+        let mut vault = load::<Vault>("Vault", data);
+        vault.rewards += amount;
+    }
+
+    /// Pays out of the rewards first.
+    fn withdraw(data: &mut [u8], amount: u64) -> Result<(), ()> {
+        let mut vault = load::<Vault>("Vault", data);
+        let from_rewards = amount.min(u64::from(vault.rewards));
+        let principal = vault
+            .principal
+            .checked_sub(amount - from_rewards)
+            .ok_or(())?;
+        vault.rewards -= from_rewards as u32;
+        vault.principal = principal;
+        Ok(())
+    }
+
+    #[test]
+    fn test_registry() {
+        clear();
+        let hook = hook!(Vault, rewards, On::Increase);
+        assert_eq!((hook.account, hook.offset, hook.size), ("Vault", 8, 4));
+        declare("rewarded", &[hook]);
+        set("rewarded", 7);
+        assert_eq!(get("rewarded"), 7);
+        declare("rewarded", &[]);
+        assert_eq!(ghosts(), BTreeMap::from([("rewarded", 0)]));
+        assert!(std::panic::catch_unwind(|| get("rewarde")).is_err());
+
+        let mut data = vec![0u8; std::mem::size_of::<Vault>()];
+        deposit(&mut data, 5);
+        reward(&mut data, 2);
+        withdraw(&mut data, 6).unwrap();
+        let vault: &Vault = bytemuck::from_bytes(&data);
+        assert_eq!((vault.principal, vault.rewards), (1, 0));
+    }
+
+    #[cfg(feature = "ghosts")]
+    #[test]
+    fn test_hooks() {
+        clear();
+        declare("deposited", &[hook!(Vault, principal, On::Increase)]);
+        declare("rewarded", &[hook!(Vault, rewards, On::Increase)]);
+        declare(
+            "withdrawn",
+            &[
+                hook!(Vault, principal, On::Decrease),
+                hook!(Vault, rewards, On::Decrease),
+            ],
+        );
+
+        let mut data = vec![0u8; std::mem::size_of::<Vault>()];
+        deposit(&mut data, 100);
+        reward(&mut data, 10);
+        withdraw(&mut data, 30).unwrap();
+        assert!(withdraw(&mut data, 1000).is_err());
+        withdraw(&mut data, 75).unwrap();
+        // Another account's state is not the vault's
+        load::<Vault>("Other", &mut data).principal += 1;
+        assert_eq!(
+            ghosts(),
+            BTreeMap::from([("deposited", 100), ("rewarded", 10), ("withdrawn", 105)])
+        );
+        assert!(get("withdrawn") <= get("deposited") + get("rewarded"));
+    }
+}
@@ -0,0 +1,275 @@
+//! Accounts and sysvars for the unit tests and rules of the Solana examples.
+//!
+//! An `AccountInfo` only borrows its lamports and data, so a test building
+//! one by hand has to keep both alive in locals next to it. A [`TestAccount`]
+//! owns them instead: it is built up from its owner, then
+//! [`TestAccount::info`] lends out the `AccountInfo` over it, and [`infos`]
+//! does so for a whole instruction's accounts at once. [`TestAccount::sysvar`]
+//! holds a sysvar such as [`clock`] or `Rent` at its address.
+//!
+//! ```ignore
+//! let mut vault = TestAccount::new(crate::id()).state(&Vault::new(owner));
+//! let mut owner = TestAccount::wallet().key(owner);
+//! process_set_deposit_cap(&[vault.info(), owner.info()], &100u64.to_le_bytes())?;
+//! ```
+//!
+//! With the `certora` feature, [`nondet`] builds the same accounts and
+//! sysvars with every field the rule does not pin down left nondeterministic,
+//! so the prover covers all of their values.
+//!
+//! Programs make their calls into the system and token programs and read
+//! sysvars through [`cpi`], whose summaries the rules see instead. Rules
+//! over the history of a program, rather than its state, read the ghosts of
+//! [`ghost`], which the program updates by loading its state through it.
+
+pub mod cpi;
+pub mod ghost;
+
+use bytemuck::Pod;
+use solana_program::account_info::AccountInfo;
+use solana_program::clock::{Clock, Slot, UnixTimestamp};
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_program;
+use solana_program::sysvar::{self, Sysvar};
+
+/// An account owning the lamports and data its `AccountInfo` borrows.
+#[derive(Clone, Debug)]
+pub struct TestAccount {
+    key: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    data: Vec<u8>,
+    is_signer: bool,
+    is_writable: bool,
+    executable: bool,
+}
+
+impl TestAccount {
+    /// A writable account of `owner` at a fresh key, holding neither
+    /// lamports nor data.
+    pub fn new(owner: Pubkey) -> Self {
+        TestAccount {
+            key: Pubkey::new_unique(),
+            owner,
+            lamports: 0,
+            data: Vec::new(),
+            is_signer: false,
+            is_writable: true,
+            executable: false,
+        }
+    }
+
+    /// A wallet: a writable signer owned by the system program.
+    pub fn wallet() -> Self {
+        TestAccount::new(system_program::id()).signer()
+    }
+
+    /// A read-only account holding `sysvar` at its address.
+    pub fn sysvar<S: Sysvar>(sysvar: &S) -> Self {
+        let mut account = TestAccount::new(sysvar::id())
+            .key(S::id())
+            .data(vec![0; S::size_of()])
+            .readonly();
+        sysvar
+            .to_account_info(&mut account.info())
+            .expect("the data is sized for the sysvar");
+        account
+    }
+
+    /// Sets the key of the account.
+    pub fn key(mut self, key: Pubkey) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Makes the account sign the instruction.
+    pub fn signer(mut self) -> Self {
+        self.is_signer = true;
+        self
+    }
+
+    /// Makes the account read-only.
+    pub fn readonly(mut self) -> Self {
+        self.is_writable = false;
+        self
+    }
+
+    /// Makes the account an executable program.
+    pub fn executable(mut self) -> Self {
+        self.executable = true;
+        self
+    }
+
+    /// Sets the lamports of the account.
+    pub fn lamports(mut self, lamports: u64) -> Self {
+        self.lamports = lamports;
+        self
+    }
+
+    /// Sets the lamports of the account to the rent-exempt minimum for its
+    /// data under the default `Rent`.
+    pub fn rent_exempt(mut self) -> Self {
+        self.lamports = Rent::default().minimum_balance(self.data.len());
+        self
+    }
+
+    /// Sets the data of the account.
+    pub fn data(mut self, data: Vec<u8>) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Sets the data of the account to the bytes of `state`.
+    pub fn state<T: Pod>(self, state: &T) -> Self {
+        self.data(bytemuck::bytes_of(state).to_vec())
+    }
+
+    /// The key of the account.
+    pub fn pubkey(&self) -> Pubkey {
+        self.key
+    }
+
+    /// The `AccountInfo` over the account.
+    pub fn info(&mut self) -> AccountInfo<'_> {
+        AccountInfo::new(
+            &self.key,
+            self.is_signer,
+            self.is_writable,
+            &mut self.lamports,
+            &mut self.data,
+            &self.owner,
+            self.executable,
+            0,
+        )
+    }
+}
+
+/// The `AccountInfo`s over `accounts`, in order.
+pub fn infos(accounts: &mut [TestAccount]) -> Vec<AccountInfo<'_>> {
+    accounts.iter_mut().map(TestAccount::info).collect()
+}
+
+/// A `Clock` at `slot` and `unix_timestamp`, in epoch 0.
+pub fn clock(slot: Slot, unix_timestamp: UnixTimestamp) -> Clock {
+    Clock {
+        slot,
+        unix_timestamp,
+        ..Clock::default()
+    }
+}
+
+/// The state stored in `account`, copied out.
+pub fn state<T: Pod>(account: &AccountInfo) -> T {
+    bytemuck::pod_read_unaligned(&account.data.borrow()[..std::mem::size_of::<T>()])
+}
+
+/// Nondeterministic accounts and sysvars for the rules.
+#[cfg(feature = "certora")]
+pub mod nondet {
+    use super::*;
+    use cvlr::prelude::*;
+
+    /// A nondeterministic key.
+    pub fn pubkey() -> Pubkey {
+        let mut bytes = [0u8; 32];
+        for chunk in bytes.chunks_exact_mut(8) {
+            chunk.copy_from_slice(&nondet::<u64>().to_le_bytes());
+        }
+        Pubkey::new_from_array(bytes)
+    }
+
+    /// `len` nondeterministic bytes.
+    pub fn bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|_| nondet()).collect()
+    }
+
+    /// An account of `owner` at a nondeterministic key, with `len` bytes of
+    /// nondeterministic data, nondeterministic lamports and nondeterministic
+    /// signer and writable flags.
+    pub fn account(owner: Pubkey, len: usize) -> TestAccount {
+        TestAccount {
+            key: pubkey(),
+            owner,
+            lamports: nondet(),
+            data: bytes(len),
+            is_signer: nondet(),
+            is_writable: nondet(),
+            executable: false,
+        }
+    }
+
+    /// An account of `owner` holding a nondeterministic `T`.
+    pub fn state<T: Pod>(owner: Pubkey) -> TestAccount {
+        account(owner, std::mem::size_of::<T>())
+    }
+
+    /// A `Clock` with every field nondeterministic.
+    pub fn clock() -> Clock {
+        Clock {
+            slot: nondet(),
+            epoch_start_timestamp: nondet(),
+            epoch: nondet(),
+            leader_schedule_epoch: nondet(),
+            unix_timestamp: nondet(),
+        }
+    }
+
+    /// A `Rent` charging a nondeterministic number of lamports per byte-year,
+    /// with the default exemption threshold and burn percentage.
+    pub fn rent() -> Rent {
+        Rent {
+            lamports_per_byte_year: nondet(),
+            ..Rent::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info_borrows_the_account() {
+        let owner = Pubkey::new_unique();
+        let mut account = TestAccount::new(owner).state(&7u64).lamports(10);
+        let key = account.pubkey();
+        {
+            let info = account.info();
+            assert_eq!((*info.key, *info.owner), (key, owner));
+            assert!(info.is_writable && !info.is_signer);
+            **info.try_borrow_mut_lamports().unwrap() += 5;
+            info.try_borrow_mut_data().unwrap()[0] = 8;
+        }
+        let info = account.info();
+        assert_eq!(info.lamports(), 15);
+        assert_eq!(state::<u64>(&info), 8);
+    }
+
+    #[test]
+    fn test_infos() {
+        let mut accounts = [
+            TestAccount::wallet(),
+            TestAccount::new(system_program::id()),
+        ];
+        let keys = [accounts[0].pubkey(), accounts[1].pubkey()];
+        let infos = infos(&mut accounts);
+        assert_eq!([*infos[0].key, *infos[1].key], keys);
+        assert!(infos[0].is_signer && !infos[1].is_signer);
+    }
+
+    #[test]
+    fn test_sysvar() {
+        let mut account = TestAccount::sysvar(&clock(42, 1_700_000_000));
+        let info = account.info();
+        assert_eq!(*info.key, sysvar::clock::id());
+        assert!(!info.is_writable);
+        let clock = Clock::from_account_info(&info).unwrap();
+        assert_eq!((clock.slot, clock.unix_timestamp), (42, 1_700_000_000));
+
+        let rent = TestAccount::new(Pubkey::new_unique())
+            .data(vec![0; 100])
+            .rent_exempt();
+        assert_eq!(rent.lamports, Rent::default().minimum_balance(100));
+    }
+}
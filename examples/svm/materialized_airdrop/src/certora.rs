@@ -0,0 +1,21 @@
+//! Formal verification module for the Merkle airdrop.
+
+pub mod spec;
+
+/// Macro to assume the distribution bound holds.
+/// Distribution bound: claimed_amount <= total_amount
+#[macro_export]
+macro_rules! assume_within_total {
+    ($fv_airdrop:expr) => {
+        cvlr::cvlr_assume!($fv_airdrop.claimed_amount <= $fv_airdrop.total_amount);
+    };
+}
+
+/// Macro to assert the distribution bound holds.
+/// Distribution bound: claimed_amount <= total_amount
+#[macro_export]
+macro_rules! assert_within_total {
+    ($fv_airdrop:expr) => {
+        cvlr::cvlr_assert!($fv_airdrop.claimed_amount <= $fv_airdrop.total_amount);
+    };
+}
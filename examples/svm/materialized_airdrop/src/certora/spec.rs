@@ -0,0 +1,117 @@
+//! This module contains the specification for the Merkle airdrop.
+
+use crate::{
+    assert_within_total, assume_within_total,
+    processor::*,
+    state::{Airdrop, MAX_LEAVES},
+};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::{cvlr_deserialize_nondet_accounts, cvlr_nondet_pubkey};
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the state for the formal verification (FV) of the
+/// airdrop.
+struct FvAirdrop {
+    total_amount: NativeInt,
+    claimed_amount: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvAirdrop {
+    fn from(acc_info: &AccountInfo) -> FvAirdrop {
+        let data = acc_info.data.borrow();
+        let airdrop: &Airdrop = bytemuck::from_bytes(&data[..]);
+        let total_amount: u64 = airdrop.total_amount.into();
+        let claimed_amount: u64 = airdrop.claimed_amount.into();
+        FvAirdrop {
+            total_amount: total_amount.into(),
+            claimed_amount: claimed_amount.into(),
+        }
+    }
+}
+
+/// Returns whether leaf `index` is marked as claimed in the airdrop account.
+fn fv_is_claimed(acc_info: &AccountInfo, index: usize) -> bool {
+    let data = acc_info.data.borrow();
+    let airdrop: &Airdrop = bytemuck::from_bytes(&data[..]);
+    airdrop.is_claimed(index)
+}
+
+fn fv_lamports(acc_info: &AccountInfo) -> NativeInt {
+    acc_info.lamports().into()
+}
+
+/// Returns nondeterministic 32 bytes, e.g. a Merkle root or proof node.
+fn nondet_bytes32() -> [u8; 32] {
+    cvlr_nondet_pubkey().to_bytes()
+}
+
+/// Builds claim instruction data for a leaf with a single-node proof.
+fn claim_instruction_data(index: u64, amount: u64, sibling: [u8; 32]) -> [u8; 49] {
+    let mut data = [0u8; 49];
+    data[..8].copy_from_slice(&index.to_le_bytes());
+    data[8..16].copy_from_slice(&amount.to_le_bytes());
+    data[16] = 1;
+    data[17..].copy_from_slice(&sibling);
+    data
+}
+
+/// Verifies that setting a bit in the claim bitmap marks exactly that leaf.
+#[rule]
+pub fn rule_bitmap_set_only_target() {
+    let mut airdrop: Airdrop = Airdrop::new(Default::default(), nondet_bytes32(), nondet());
+    for byte in airdrop.claimed_bitmap.iter_mut() {
+        *byte = nondet();
+    }
+    let index: usize = nondet();
+    let other: usize = nondet();
+    cvlr_assume!(index < MAX_LEAVES);
+    cvlr_assume!(other < MAX_LEAVES);
+    cvlr_assume!(index != other);
+    let other_pre = airdrop.is_claimed(other);
+
+    airdrop.set_claimed(index);
+
+    cvlr_assert!(airdrop.is_claimed(index));
+    cvlr_assert!(airdrop.is_claimed(other) == other_pre);
+}
+
+/// Verifies that a claim pays exactly the leaf amount to the claimant, out of
+/// the airdrop's lamports, and marks the leaf.
+#[rule]
+pub fn rule_claim_pays_leaf_amount() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let airdrop_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let claimant: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_airdrop_pre: FvAirdrop = airdrop_account.into();
+    assume_within_total!(fv_airdrop_pre);
+    let airdrop_lamports_pre = fv_lamports(airdrop_account);
+    let claimant_lamports_pre = fv_lamports(claimant);
+
+    let index: u64 = nondet();
+    let amount: u64 = nondet();
+    let claim_data = claim_instruction_data(index, amount, nondet_bytes32());
+    process_claim(&account_infos, &claim_data).unwrap();
+
+    let fv_airdrop_post: FvAirdrop = airdrop_account.into();
+    let amount: NativeInt = amount.into();
+    cvlr_assert!(fv_airdrop_post.claimed_amount == fv_airdrop_pre.claimed_amount + amount);
+    cvlr_assert!(fv_lamports(airdrop_account) == airdrop_lamports_pre - amount);
+    cvlr_assert!(fv_lamports(claimant) == claimant_lamports_pre + amount);
+    cvlr_assert!(fv_is_claimed(airdrop_account, index as usize));
+    assert_within_total!(fv_airdrop_post);
+}
+
+/// Verifies that each leaf can be claimed at most once.
+#[rule]
+pub fn rule_leaf_claimed_at_most_once() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+
+    let index: u64 = nondet();
+    let amount: u64 = nondet();
+    let claim_data = claim_instruction_data(index, amount, nondet_bytes32());
+    process_claim(&account_infos, &claim_data).unwrap();
+
+    cvlr_assert!(process_claim(&account_infos, &claim_data).is_err());
+}
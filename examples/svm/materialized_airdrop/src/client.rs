@@ -0,0 +1,61 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::Airdrop;
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// Initializes the airdrop with a Merkle root and the total distributable
+/// amount, which `airdrop` must already hold as lamports.
+pub fn create_ix(airdrop: Pubkey, authority: Pubkey, root: [u8; 32], total: u64) -> Instruction {
+    let mut data = vec![0u8];
+    data.extend_from_slice(&root);
+    data.extend_from_slice(&total.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(airdrop, false),
+            AccountMeta::new_readonly(authority, true),
+        ],
+        data,
+    }
+}
+
+/// Pays `amount`, the amount in leaf `index`, to `claimant`, proven by the
+/// sibling nodes in `proof` from the leaf up.
+pub fn claim_ix(
+    airdrop: Pubkey,
+    claimant: Pubkey,
+    index: u64,
+    amount: u64,
+    proof: &[[u8; 32]],
+) -> Instruction {
+    let mut data = vec![1u8];
+    data.extend_from_slice(&index.to_le_bytes());
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(proof.len() as u8);
+    for node in proof {
+        data.extend_from_slice(node);
+    }
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(airdrop, false),
+            AccountMeta::new(claimant, true),
+        ],
+        data,
+    }
+}
+
+/// Decodes the data of an Airdrop account.
+pub fn decode_airdrop(data: &[u8]) -> Result<Airdrop, ProgramError> {
+    data.get(..core::mem::size_of::<Airdrop>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
@@ -0,0 +1,264 @@
+use crate::state::{Airdrop, MAX_LEAVES, MAX_PROOF_LEN};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, hash::hashv, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+/// Domain separator for leaf hashes, so a leaf can never pass as a node.
+const LEAF_PREFIX: &[u8] = &[0];
+/// Domain separator for internal node hashes.
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Hash of the leaf granting `amount` to `claimant` at position `index`.
+pub fn leaf_hash(index: u64, claimant: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[
+        LEAF_PREFIX,
+        &index.to_le_bytes(),
+        claimant.as_ref(),
+        &amount.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Hash of an internal node. Children are sorted so proofs need no direction
+/// bits.
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if left <= right {
+        (left, right)
+    } else {
+        (right, left)
+    };
+    hashv(&[NODE_PREFIX, first, second]).to_bytes()
+}
+
+/// Returns true iff `proof` connects `leaf` to `root`.
+pub fn verify_proof(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(leaf, |node, sibling| node_hash(&node, sibling));
+    computed == *root
+}
+
+/// Process a create instruction.
+/// Initializes the airdrop with a Merkle root and the total distributable
+/// amount, which the airdrop account must already hold as lamports.
+///
+/// # Arguments
+/// - `accounts`: Account array (airdrop account first, signing authority
+///   second)
+/// - `instruction_data`: 32 bytes Merkle root followed by 8 bytes total amount
+pub fn process_create(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let merkle_root: [u8; 32] = instruction_data
+        .get(..32)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let total_amount = read_u64(instruction_data, 32)?;
+
+    let airdrop_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let authority = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if *airdrop_account.owner != crate::id() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if airdrop_account.lamports() < total_amount {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    // Borrow and read airdrop data
+    let mut airdrop_data = airdrop_account.data.borrow_mut();
+    let airdrop: &mut Airdrop = bytemuck::from_bytes_mut(&mut airdrop_data);
+    if airdrop.merkle_root != [0; 32] {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    *airdrop = Airdrop::new(*authority.key, merkle_root, total_amount);
+
+    Ok(())
+}
+
+/// Process a claim instruction.
+/// Pays the amount in a proven leaf to its claimant, at most once per leaf,
+/// out of the lamports of the airdrop account.
+///
+/// # Arguments
+/// - `accounts`: Account array (airdrop account first, signing claimant
+///   second)
+/// - `instruction_data`: 8 bytes leaf index, 8 bytes amount, 1 byte proof
+///   length, followed by the 32-byte proof nodes
+pub fn process_claim(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let index = read_u64(instruction_data, 0)?;
+    let amount = read_u64(instruction_data, 8)?;
+    let proof_len = *instruction_data
+        .get(16)
+        .ok_or(ProgramError::InvalidInstructionData)? as usize;
+    if proof_len > MAX_PROOF_LEN || index >= MAX_LEAVES as u64 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let proof_bytes = instruction_data
+        .get(17..17 + proof_len * 32)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut proof = [[0u8; 32]; MAX_PROOF_LEN];
+    for (node, bytes) in proof.iter_mut().zip(proof_bytes.chunks_exact(32)) {
+        node.copy_from_slice(bytes);
+    }
+
+    let airdrop_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let claimant = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if *airdrop_account.owner != crate::id() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if !claimant.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if airdrop_account.key == claimant.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Borrow and read airdrop data
+    let mut airdrop_data = airdrop_account.data.borrow_mut();
+    let airdrop: &mut Airdrop = bytemuck::from_bytes_mut(&mut airdrop_data);
+
+    let leaf = leaf_hash(index, claimant.key, amount);
+    if !verify_proof(&airdrop.merkle_root, leaf, &proof[..proof_len]) {
+        return Err(ProgramError::InvalidArgument);
+    }
+    let index = index as usize;
+    if airdrop.is_claimed(index) {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let claimed_amount: u64 = airdrop.claimed_amount.into();
+    let total_amount: u64 = airdrop.total_amount.into();
+    let new_claimed_amount = claimed_amount
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if new_claimed_amount > total_amount {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    let airdrop_lamports = airdrop_account.lamports();
+    let claimant_lamports = claimant.lamports();
+    **airdrop_account.try_borrow_mut_lamports()? = airdrop_lamports
+        .checked_sub(amount)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    **claimant.try_borrow_mut_lamports()? = claimant_lamports
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    airdrop.set_claimed(index);
+    airdrop.claimed_amount = new_claimed_amount.into();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use svm_fv_support::TestAccount;
+
+    /// Claim instruction data for leaf `index` with `proof`.
+    fn claim_data(index: u64, amount: u64, proof: &[[u8; 32]]) -> Vec<u8> {
+        let mut data = [index.to_le_bytes(), amount.to_le_bytes()].concat();
+        data.push(proof.len() as u8);
+        data.extend(proof.iter().flatten());
+        data
+    }
+
+    #[test]
+    fn test_verify_proof_two_leaves() {
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let alice_leaf = leaf_hash(0, &alice, 100);
+        let bob_leaf = leaf_hash(1, &bob, 200);
+        let root = node_hash(&alice_leaf, &bob_leaf);
+
+        assert!(verify_proof(&root, alice_leaf, &[bob_leaf]));
+        assert!(verify_proof(&root, bob_leaf, &[alice_leaf]));
+        assert!(!verify_proof(&root, leaf_hash(0, &alice, 101), &[bob_leaf]));
+        assert!(!verify_proof(&root, leaf_hash(0, &bob, 100), &[bob_leaf]));
+    }
+
+    #[test]
+    fn test_bitmap_sets_single_bit() {
+        let mut airdrop = Airdrop::new(Pubkey::default(), [1; 32], 0);
+        airdrop.set_claimed(9);
+        assert!(airdrop.is_claimed(9));
+        assert!(!airdrop.is_claimed(8));
+        assert!(!airdrop.is_claimed(10));
+        assert!(!airdrop.is_claimed(MAX_LEAVES - 1));
+    }
+
+    #[test]
+    fn test_create_requires_funds() {
+        let create = [[3u8; 32].as_slice(), &500u64.to_le_bytes()].concat();
+        let mut authority = TestAccount::wallet();
+        let airdrop = Airdrop::new(Pubkey::default(), [0; 32], 0);
+
+        let mut foreign = TestAccount::new(Pubkey::new_unique())
+            .state(&airdrop)
+            .lamports(500);
+        assert_eq!(
+            process_create(&[foreign.info(), authority.info()], &create),
+            Err(ProgramError::IllegalOwner)
+        );
+        let mut short = TestAccount::new(crate::id()).state(&airdrop).lamports(499);
+        assert_eq!(
+            process_create(&[short.info(), authority.info()], &create),
+            Err(ProgramError::InsufficientFunds)
+        );
+        let mut funded = TestAccount::new(crate::id()).state(&airdrop).lamports(500);
+        process_create(&[funded.info(), authority.info()], &create).unwrap();
+        let airdrop: Airdrop = svm_fv_support::state(&funded.info());
+        assert_eq!(u64::from(airdrop.total_amount), 500);
+    }
+
+    #[test]
+    fn test_claim_pays_claimant() {
+        let mut alice = TestAccount::wallet();
+        let mut bob = TestAccount::wallet();
+        let alice_leaf = leaf_hash(0, &alice.pubkey(), 100);
+        let bob_leaf = leaf_hash(1, &bob.pubkey(), 200);
+        let airdrop = Airdrop::new(Pubkey::default(), node_hash(&alice_leaf, &bob_leaf), 300);
+
+        let mut foreign = TestAccount::new(Pubkey::new_unique())
+            .state(&airdrop)
+            .lamports(300);
+        let claim = claim_data(0, 100, &[bob_leaf]);
+        assert_eq!(
+            process_claim(&[foreign.info(), alice.info()], &claim),
+            Err(ProgramError::IllegalOwner)
+        );
+
+        let mut account = TestAccount::new(crate::id()).state(&airdrop).lamports(300);
+        let airdrop_account = account.info();
+        let alice_account = alice.info();
+        process_claim(&[airdrop_account.clone(), alice_account.clone()], &claim).unwrap();
+        assert_eq!(
+            (airdrop_account.lamports(), alice_account.lamports()),
+            (200, 100)
+        );
+        assert!(process_claim(&[airdrop_account.clone(), alice_account.clone()], &claim).is_err());
+        // A leaf only pays its own claimant
+        let bob_claim = claim_data(1, 200, &[alice_leaf]);
+        let bob_account = bob.info();
+        assert!(process_claim(&[airdrop_account.clone(), alice_account], &bob_claim).is_err());
+        process_claim(&[airdrop_account.clone(), bob_account.clone()], &bob_claim).unwrap();
+        assert_eq!(
+            (airdrop_account.lamports(), bob_account.lamports()),
+            (0, 200)
+        );
+        let airdrop: Airdrop = svm_fv_support::state(&airdrop_account);
+        assert_eq!(u64::from(airdrop.claimed_amount), 300);
+    }
+}
@@ -0,0 +1,51 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::PodU64;
+
+/// Maximum number of leaves in the airdrop tree.
+pub const MAX_LEAVES: usize = 1024;
+
+/// Maximum Merkle proof length, `log2(MAX_LEAVES)`.
+pub const MAX_PROOF_LEN: usize = 10;
+
+/// The airdrop account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct Airdrop {
+    /// The airdrop authority
+    pub authority: Pubkey,
+    /// Root of the Merkle tree of `(index, claimant, amount)` leaves
+    pub merkle_root: [u8; 32],
+    /// Total amount distributable by the airdrop
+    pub total_amount: PodU64,
+    /// Total amount claimed so far
+    pub claimed_amount: PodU64,
+    /// Bit `i` is set iff leaf `i` has been claimed
+    pub claimed_bitmap: [u8; MAX_LEAVES / 8],
+}
+
+impl Airdrop {
+    /// Create a new airdrop distributing `total_amount` over `merkle_root`
+    pub fn new(authority: Pubkey, merkle_root: [u8; 32], total_amount: u64) -> Self {
+        Airdrop {
+            authority,
+            merkle_root,
+            total_amount: PodU64::from(total_amount),
+            claimed_amount: PodU64::from(0),
+            claimed_bitmap: [0; MAX_LEAVES / 8],
+        }
+    }
+
+    /// Returns true iff leaf `index` has been claimed.
+    /// Requires `index < MAX_LEAVES`.
+    pub fn is_claimed(&self, index: usize) -> bool {
+        self.claimed_bitmap[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    /// Marks leaf `index` as claimed.
+    /// Requires `index < MAX_LEAVES`.
+    pub fn set_claimed(&mut self, index: usize) {
+        self.claimed_bitmap[index / 8] |= 1 << (index % 8);
+    }
+}
@@ -0,0 +1,53 @@
+//! End-to-end scenarios of the airdrop: sequences of instructions sent to the
+//! program in a bank, checking the lamports and state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_airdrop::client::{claim_ix, create_ix};
+use solana_airdrop::processor::{leaf_hash, node_hash};
+use solana_airdrop::state::Airdrop;
+use solana_airdrop::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, ScenarioBuilder};
+
+#[test]
+fn create_then_claim_each_leaf_once() {
+    let (authority, alice, bob) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let airdrop = Pubkey::new_unique();
+    let mut scenario =
+        ScenarioBuilder::new("solana_airdrop", id(), processor!(process_instruction))
+            .account(airdrop, vec![0; core::mem::size_of::<Airdrop>()])
+            .wallet(alice.pubkey())
+            .wallet(bob.pubkey())
+            .start();
+    // A tree of two leaves, each the proof of the other
+    let alice_leaf = leaf_hash(0, &alice.pubkey(), 300);
+    let bob_leaf = leaf_hash(1, &bob.pubkey(), 200);
+    let root = node_hash(&alice_leaf, &bob_leaf);
+
+    let create = create_ix(airdrop, authority.pubkey(), root, 500);
+    scenario.send_instruction(create, &[&authority]).unwrap();
+    // The root is set once
+    let create = create_ix(airdrop, authority.pubkey(), [1; 32], 500);
+    assert!(scenario.send_instruction(create, &[&authority]).is_err());
+
+    let held = scenario.lamports(alice.pubkey());
+    let claim = || claim_ix(airdrop, alice.pubkey(), 0, 300, &[bob_leaf]);
+    scenario.send_instruction(claim(), &[&alice]).unwrap();
+    assert_eq!(scenario.lamports(alice.pubkey()), held + 300);
+    // Each leaf is paid once
+    assert!(scenario.send_instruction(claim(), &[&alice]).is_err());
+    // Bob cannot claim Alice's leaf, nor more than his own
+    let theft = claim_ix(airdrop, bob.pubkey(), 0, 300, &[bob_leaf]);
+    assert!(scenario.send_instruction(theft, &[&bob]).is_err());
+    let inflated = claim_ix(airdrop, bob.pubkey(), 1, 201, &[alice_leaf]);
+    assert!(scenario.send_instruction(inflated, &[&bob]).is_err());
+
+    let claim = claim_ix(airdrop, bob.pubkey(), 1, 200, &[alice_leaf]);
+    scenario.send_instruction(claim, &[&bob]).unwrap();
+    let state: Airdrop = scenario.state(airdrop);
+    assert_eq!(u64::from(state.claimed_amount), 500);
+    assert!(state.is_claimed(0) && state.is_claimed(1));
+}
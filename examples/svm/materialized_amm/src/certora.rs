@@ -0,0 +1,25 @@
+//! Formal verification module for the constant-product pool.
+
+pub mod spec;
+
+/// Macro to assume the LP-share solvency property holds.
+/// LP solvency: lp_total * lp_total <= reserve_a * reserve_b
+#[macro_export]
+macro_rules! assume_lp_solvency {
+    ($fv_pool:expr) => {
+        cvlr::cvlr_assume!(
+            $fv_pool.lp_total * $fv_pool.lp_total <= $fv_pool.reserve_a * $fv_pool.reserve_b
+        );
+    };
+}
+
+/// Macro to assert the LP-share solvency property holds.
+/// LP solvency: lp_total * lp_total <= reserve_a * reserve_b
+#[macro_export]
+macro_rules! assert_lp_solvency {
+    ($fv_pool:expr) => {
+        cvlr::cvlr_assert!(
+            $fv_pool.lp_total * $fv_pool.lp_total <= $fv_pool.reserve_a * $fv_pool.reserve_b
+        );
+    };
+}
@@ -0,0 +1,127 @@
+//! This module contains the specification for the constant-product pool.
+
+use crate::{assert_lp_solvency, assume_lp_solvency, processor::*, state::Pool};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the state for the formal verification (FV) of the pool.
+struct FvPool {
+    reserve_a: NativeInt,
+    reserve_b: NativeInt,
+    lp_total: NativeInt,
+}
+
+impl FvPool {
+    /// The constant-product invariant `k = reserve_a * reserve_b`.
+    fn product(&self) -> NativeInt {
+        self.reserve_a * self.reserve_b
+    }
+}
+
+impl From<&Pool> for FvPool {
+    fn from(pool: &Pool) -> FvPool {
+        let reserve_a: u64 = pool.reserve_a.into();
+        let reserve_b: u64 = pool.reserve_b.into();
+        let lp_total: u64 = pool.lp_total.into();
+        FvPool {
+            reserve_a: reserve_a.into(),
+            reserve_b: reserve_b.into(),
+            lp_total: lp_total.into(),
+        }
+    }
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvPool {
+    fn from(acc_info: &AccountInfo) -> FvPool {
+        let mut data = acc_info.data.borrow_mut();
+        let pool: &Pool = bytemuck::from_bytes_mut(&mut data[..]);
+        FvPool::from(pool)
+    }
+}
+
+/// Builds nondeterministic swap instruction data with a valid direction byte.
+fn nondet_swap_instruction_data() -> [u8; 9] {
+    let amount_in: u64 = nondet();
+    let direction: u8 = nondet();
+    cvlr_assume!(direction <= 1);
+    let mut data = [0u8; 9];
+    data[..8].copy_from_slice(&amount_in.to_le_bytes());
+    data[8] = direction;
+    data
+}
+
+/// Verifies that the product of the reserves never decreases on a swap.
+#[rule]
+pub fn rule_swap_product_non_decreasing() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let pool_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_pool_pre: FvPool = pool_account.into();
+
+    let swap_instruction_data = nondet_swap_instruction_data();
+    process_swap(&account_infos, &swap_instruction_data).unwrap();
+
+    let fv_pool_post: FvPool = pool_account.into();
+    cvlr_assert!(fv_pool_post.product() >= fv_pool_pre.product());
+}
+
+/// Verifies that the pool remains LP-solvent before and after a swap
+/// operation.
+#[rule]
+pub fn rule_lp_solvency_swap() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let pool_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_pool_pre: FvPool = pool_account.into();
+    assume_lp_solvency!(fv_pool_pre);
+
+    let swap_instruction_data = nondet_swap_instruction_data();
+    process_swap(&account_infos, &swap_instruction_data).unwrap();
+
+    let fv_pool_post: FvPool = pool_account.into();
+    assert_lp_solvency!(fv_pool_post);
+}
+
+/// Verifies that the pool remains LP-solvent before and after an
+/// add-liquidity operation.
+#[rule]
+pub fn rule_lp_solvency_add_liquidity() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let pool_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_pool_pre: FvPool = pool_account.into();
+    assume_lp_solvency!(fv_pool_pre);
+
+    let amount_a: u64 = nondet();
+    let amount_b: u64 = nondet();
+    let mut add_instruction_data = [0u8; 16];
+    add_instruction_data[..8].copy_from_slice(&amount_a.to_le_bytes());
+    add_instruction_data[8..].copy_from_slice(&amount_b.to_le_bytes());
+    process_add_liquidity(&account_infos, &add_instruction_data).unwrap();
+
+    let fv_pool_post: FvPool = pool_account.into();
+    assert_lp_solvency!(fv_pool_post);
+}
+
+/// Verifies that the pool remains LP-solvent before and after a
+/// remove-liquidity operation.
+#[rule]
+pub fn rule_lp_solvency_remove_liquidity() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let pool_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_pool_pre: FvPool = pool_account.into();
+    assume_lp_solvency!(fv_pool_pre);
+
+    let lp_amount: u64 = nondet();
+    let remove_instruction_data = &lp_amount.to_le_bytes();
+    process_remove_liquidity(&account_infos, remove_instruction_data).unwrap();
+
+    let fv_pool_post: FvPool = pool_account.into();
+    assert_lp_solvency!(fv_pool_post);
+}
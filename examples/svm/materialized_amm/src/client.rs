@@ -0,0 +1,55 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::Pool;
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// Takes `amount_in` tokens of one side and returns tokens of the other
+/// side, from A to B if `a_to_b` and from B to A otherwise.
+pub fn swap_ix(pool: Pubkey, amount_in: u64, a_to_b: bool) -> Instruction {
+    let mut data = vec![0u8];
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.push(u8::from(!a_to_b));
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new(pool, false)],
+        data,
+    }
+}
+
+/// Takes tokens of both sides and returns LP shares.
+pub fn add_liquidity_ix(pool: Pubkey, amount_a: u64, amount_b: u64) -> Instruction {
+    let mut data = vec![1u8];
+    data.extend_from_slice(&amount_a.to_le_bytes());
+    data.extend_from_slice(&amount_b.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new(pool, false)],
+        data,
+    }
+}
+
+/// Burns LP shares and returns a proportional amount of both reserves.
+pub fn remove_liquidity_ix(pool: Pubkey, shares: u64) -> Instruction {
+    let mut data = vec![2u8];
+    data.extend_from_slice(&shares.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new(pool, false)],
+        data,
+    }
+}
+
+/// Decodes the data of a Pool account.
+pub fn decode_pool(data: &[u8]) -> Result<Pool, ProgramError> {
+    data.get(..core::mem::size_of::<Pool>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
@@ -0,0 +1,212 @@
+use crate::state::{Pool, BPS_DENOMINATOR, SWAP_FEE_BPS};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Computes the output amount of a swap against the given reserves.
+/// The fee is deducted from the input and stays in the pool, and the result is
+/// rounded down, so the product of the reserves never decreases.
+/// Returns `None` if the intermediate product overflows.
+pub fn swap_output(reserve_in: u64, reserve_out: u64, amount_in: u64) -> Option<u64> {
+    let amount_in_with_fee = (amount_in as u128) * ((BPS_DENOMINATOR - SWAP_FEE_BPS) as u128);
+    let numerator = amount_in_with_fee.checked_mul(reserve_out as u128)?;
+    let denominator = (reserve_in as u128) * (BPS_DENOMINATOR as u128) + amount_in_with_fee;
+    if denominator == 0 {
+        return Some(0);
+    }
+    Some((numerator / denominator) as u64)
+}
+
+/// Computes the LP shares minted for depositing `amount_a` and `amount_b`.
+/// The first deposit mints `min(amount_a, amount_b)`; later deposits mint
+/// proportionally to the scarcer side, rounded down in favour of the pool.
+pub fn lp_to_mint(pool_a: u64, pool_b: u64, lp_total: u64, amount_a: u64, amount_b: u64) -> u64 {
+    if lp_total == 0 {
+        return amount_a.min(amount_b);
+    }
+    if pool_a == 0 || pool_b == 0 {
+        return 0;
+    }
+    let by_a = (amount_a as u128) * (lp_total as u128) / (pool_a as u128);
+    let by_b = (amount_b as u128) * (lp_total as u128) / (pool_b as u128);
+    by_a.min(by_b) as u64
+}
+
+/// Process a swap instruction.
+/// Takes tokens of one side, returns tokens of the other side.
+///
+/// # Arguments
+/// - `accounts`: Account array (must have pool account as first)
+/// - `instruction_data`: 8 bytes representing the input amount, followed by
+///   1 byte direction (0 = A to B, 1 = B to A)
+pub fn process_swap(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount_in = read_u64(instruction_data, 0)?;
+    let a_to_b = match instruction_data.get(8) {
+        Some(0) => true,
+        Some(1) => false,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+
+    // Get pool account
+    let pool_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // Borrow and read pool data
+    let mut pool_data = pool_account.data.borrow_mut();
+    let pool: &mut Pool = bytemuck::from_bytes_mut(&mut pool_data);
+
+    let reserve_a: u64 = pool.reserve_a.into();
+    let reserve_b: u64 = pool.reserve_b.into();
+    let (reserve_in, reserve_out) = if a_to_b {
+        (reserve_a, reserve_b)
+    } else {
+        (reserve_b, reserve_a)
+    };
+
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    let amount_out =
+        swap_output(reserve_in, reserve_out, amount_in).ok_or(ProgramError::ArithmeticOverflow)?;
+    let new_reserve_in = reserve_in
+        .checked_add(amount_in)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    // amount_out < reserve_out by construction of the constant-product formula
+    let new_reserve_out = reserve_out - amount_out;
+
+    // Update pool
+    if a_to_b {
+        pool.reserve_a = new_reserve_in.into();
+        pool.reserve_b = new_reserve_out.into();
+    } else {
+        pool.reserve_b = new_reserve_in.into();
+        pool.reserve_a = new_reserve_out.into();
+    }
+
+    Ok(())
+}
+
+/// Process an add-liquidity instruction.
+/// Takes tokens of both sides, returns LP shares.
+///
+/// # Arguments
+/// - `accounts`: Account array (must have pool account as first)
+/// - `instruction_data`: 8 bytes amount of token A followed by 8 bytes amount
+///   of token B
+pub fn process_add_liquidity(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount_a = read_u64(instruction_data, 0)?;
+    let amount_b = read_u64(instruction_data, 8)?;
+
+    // Get pool account
+    let pool_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // Borrow and read pool data
+    let mut pool_data = pool_account.data.borrow_mut();
+    let pool: &mut Pool = bytemuck::from_bytes_mut(&mut pool_data);
+
+    let reserve_a: u64 = pool.reserve_a.into();
+    let reserve_b: u64 = pool.reserve_b.into();
+    let lp_total: u64 = pool.lp_total.into();
+
+    let lp_minted = lp_to_mint(reserve_a, reserve_b, lp_total, amount_a, amount_b);
+    if lp_minted == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Update pool
+    pool.reserve_a = reserve_a
+        .checked_add(amount_a)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    pool.reserve_b = reserve_b
+        .checked_add(amount_b)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    pool.lp_total = lp_total
+        .checked_add(lp_minted)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+
+    Ok(())
+}
+
+/// Process a remove-liquidity instruction.
+/// Burns LP shares, returns a proportional amount of both reserves.
+///
+/// # Arguments
+/// - `accounts`: Account array (must have pool account as first)
+/// - `instruction_data`: 8 bytes representing the number of LP shares to burn
+pub fn process_remove_liquidity(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let lp_amount = read_u64(instruction_data, 0)?;
+
+    // Get pool account
+    let pool_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // Borrow and read pool data
+    let mut pool_data = pool_account.data.borrow_mut();
+    let pool: &mut Pool = bytemuck::from_bytes_mut(&mut pool_data);
+
+    let reserve_a: u64 = pool.reserve_a.into();
+    let reserve_b: u64 = pool.reserve_b.into();
+    let lp_total: u64 = pool.lp_total.into();
+
+    if lp_amount > lp_total {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    if lp_amount == 0 {
+        return Ok(());
+    }
+
+    // Pay out pro rata, rounded down in favour of the pool
+    let out_a = ((lp_amount as u128) * (reserve_a as u128) / (lp_total as u128)) as u64;
+    let out_b = ((lp_amount as u128) * (reserve_b as u128) / (lp_total as u128)) as u64;
+
+    // Update pool
+    pool.reserve_a = (reserve_a - out_a).into();
+    pool.reserve_b = (reserve_b - out_b).into();
+    pool.lp_total = (lp_total - lp_amount).into();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_output_keeps_product() {
+        let (reserve_in, reserve_out) = (1_000_000u64, 2_000_000u64);
+        let amount_out = swap_output(reserve_in, reserve_out, 10_000).unwrap();
+        assert!(amount_out > 0);
+        let k_pre = reserve_in as u128 * reserve_out as u128;
+        let k_post = (reserve_in + 10_000) as u128 * (reserve_out - amount_out) as u128;
+        assert!(k_post >= k_pre);
+    }
+
+    #[test]
+    fn test_swap_output_empty_pool() {
+        assert_eq!(swap_output(0, 0, 0), Some(0));
+        assert_eq!(swap_output(0, 100, 0), Some(0));
+        assert_eq!(swap_output(1, u64::MAX, u64::MAX), None);
+    }
+
+    #[test]
+    fn test_lp_to_mint_first_and_proportional() {
+        assert_eq!(lp_to_mint(0, 0, 0, 100, 400), 100);
+        assert_eq!(lp_to_mint(100, 400, 100, 50, 400), 50);
+        assert_eq!(lp_to_mint(100, 400, 100, 100, 100), 25);
+    }
+}
@@ -0,0 +1,36 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::PodU64;
+
+/// Swap fee charged on the input amount, in basis points.
+pub const SWAP_FEE_BPS: u64 = 30;
+
+/// Denominator for basis-point arithmetic.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// The constant-product pool account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Pool {
+    /// The pool owner (authority)
+    pub owner: Pubkey,
+    /// Reserve of token A held by the pool
+    pub reserve_a: PodU64,
+    /// Reserve of token B held by the pool
+    pub reserve_b: PodU64,
+    /// Total LP shares outstanding
+    pub lp_total: PodU64,
+}
+
+impl Pool {
+    /// Create a new, empty pool with the given owner
+    pub fn new(owner: Pubkey) -> Self {
+        Pool {
+            owner,
+            reserve_a: PodU64::from(0),
+            reserve_b: PodU64::from(0),
+            lp_total: PodU64::from(0),
+        }
+    }
+}
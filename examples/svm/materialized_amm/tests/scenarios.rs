@@ -0,0 +1,63 @@
+//! End-to-end scenarios of the AMM: sequences of instructions sent to the
+//! program in a bank, checking the reserves they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_amm::client::{add_liquidity_ix, remove_liquidity_ix, swap_ix};
+use solana_amm::processor::swap_output;
+use solana_amm::state::Pool;
+use solana_amm::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::{processor, Scenario, ScenarioBuilder};
+
+/// The reserves and the LP shares of the pool at `key`.
+fn totals(scenario: &mut Scenario, key: Pubkey) -> (u64, u64, u64) {
+    let pool: Pool = scenario.state(key);
+    (
+        pool.reserve_a.into(),
+        pool.reserve_b.into(),
+        pool.lp_total.into(),
+    )
+}
+
+#[test]
+fn add_swap_remove() {
+    let pool = Pubkey::new_unique();
+    let mut scenario = ScenarioBuilder::new("solana_amm", id(), processor!(process_instruction))
+        .state(pool, &Pool::new(Pubkey::new_unique()))
+        .start();
+
+    // An empty pool has nothing to swap against
+    assert!(scenario
+        .send_instruction(swap_ix(pool, 100, true), &[])
+        .is_err());
+
+    // The first deposit mints the scarcer side in shares
+    let add = add_liquidity_ix(pool, 1_000, 2_000);
+    scenario.send_instruction(add, &[]).unwrap();
+    assert_eq!(totals(&mut scenario, pool), (1_000, 2_000, 1_000));
+
+    // A swap keeps the fee in the pool, so the product of the reserves grows
+    let out = swap_output(1_000, 2_000, 100).unwrap();
+    scenario
+        .send_instruction(swap_ix(pool, 100, true), &[])
+        .unwrap();
+    let (reserve_a, reserve_b, _) = totals(&mut scenario, pool);
+    assert_eq!((reserve_a, reserve_b), (1_100, 2_000 - out));
+    assert!(reserve_a as u128 * reserve_b as u128 >= 1_000 * 2_000);
+    let back = swap_output(reserve_b, reserve_a, out).unwrap();
+    scenario
+        .send_instruction(swap_ix(pool, out, false), &[])
+        .unwrap();
+    // The round trip costs the fee twice
+    assert!(back < 100);
+    assert_eq!(totals(&mut scenario, pool).0, 1_100 - back);
+
+    // Nobody burns more shares than there are; burning all empties the pool
+    let remove = remove_liquidity_ix(pool, 1_001);
+    assert!(scenario.send_instruction(remove, &[]).is_err());
+    let remove = remove_liquidity_ix(pool, 1_000);
+    scenario.send_instruction(remove, &[]).unwrap();
+    assert_eq!(totals(&mut scenario, pool), (0, 0, 0));
+}
@@ -0,0 +1,4 @@
+//! Formal verification module for the arithmetic library.
+
+pub mod spec;
+pub mod widths;
@@ -0,0 +1,196 @@
+//! This module contains the specification for the arithmetic library.
+//!
+//! Every operation is checked against its result computed in mathematical
+//! integers, over all `u64` inputs. The rules of each operation tell its
+//! variants apart: the plain operator is exact whenever the result fits,
+//! the checked variant fails exactly when the wrapping one differs from the
+//! exact result, the wrapping one is the exact result modulo 2^64 and the
+//! saturating one clamps it.
+
+use crate::*;
+use cvlr::{mathint::NativeInt, prelude::*};
+
+/// `x` as a mathematical integer.
+fn fv(x: u64) -> NativeInt {
+    x.into()
+}
+
+/// 2^64, the modulus of wrapping arithmetic.
+fn modulus() -> NativeInt {
+    fv(u64::MAX) + 1u64
+}
+
+/// Verifies that `add` computes the sum whenever it fits a `u64`.
+#[rule]
+pub fn rule_add_is_exact() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    cvlr_assume!(fv(x) + fv(y) <= fv(u64::MAX));
+    cvlr_assert!(fv(add(x, y)) == fv(x) + fv(y));
+}
+
+/// Verifies that `checked_add` returns `None` exactly when `wrapping_add`
+/// differs from the sum, and the sum otherwise.
+#[rule]
+pub fn rule_checked_add_none_iff_wrapping_differs() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    let sum = fv(x) + fv(y);
+    let wrapped = fv(wrapping_add(x, y));
+    match checked_add(x, y) {
+        Some(result) => cvlr_assert!(fv(result) == sum),
+        None => cvlr_assert!(wrapped != sum),
+    }
+    if wrapped == sum {
+        cvlr_assert!(checked_add(x, y).is_some());
+    }
+}
+
+/// Verifies that `wrapping_add` is the sum modulo 2^64.
+#[rule]
+pub fn rule_wrapping_add_is_modular() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    let sum = fv(x) + fv(y);
+    let result = fv(wrapping_add(x, y));
+    cvlr_assert!(result == sum || result + modulus() == sum);
+}
+
+/// Verifies that `saturating_add` is the sum clamped to `u64::MAX`.
+#[rule]
+pub fn rule_saturating_add_clamps() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    let sum = fv(x) + fv(y);
+    let result = saturating_add(x, y);
+    if sum <= fv(u64::MAX) {
+        cvlr_assert!(fv(result) == sum);
+    } else {
+        cvlr_assert!(result == u64::MAX);
+    }
+}
+
+/// Verifies that `sub` computes the difference whenever it is not negative.
+#[rule]
+pub fn rule_sub_is_exact() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    cvlr_assume!(y <= x);
+    cvlr_assert!(fv(sub(x, y)) + fv(y) == fv(x));
+}
+
+/// Verifies that `checked_sub` returns `None` exactly when `wrapping_sub`
+/// differs from the difference, and the difference otherwise.
+#[rule]
+pub fn rule_checked_sub_none_iff_wrapping_differs() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    // x - y == r is written r + y == x, keeping every integer non-negative
+    let wrapped_is_exact = fv(wrapping_sub(x, y)) + fv(y) == fv(x);
+    match checked_sub(x, y) {
+        Some(result) => cvlr_assert!(fv(result) + fv(y) == fv(x)),
+        None => cvlr_assert!(!wrapped_is_exact),
+    }
+    if wrapped_is_exact {
+        cvlr_assert!(checked_sub(x, y).is_some());
+    }
+}
+
+/// Verifies that `wrapping_sub` is the difference modulo 2^64.
+#[rule]
+pub fn rule_wrapping_sub_is_modular() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    let result = fv(wrapping_sub(x, y));
+    cvlr_assert!(result + fv(y) == fv(x) || result + fv(y) == fv(x) + modulus());
+}
+
+/// Verifies that `saturating_sub` is the difference clamped to 0.
+#[rule]
+pub fn rule_saturating_sub_clamps() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    let result = saturating_sub(x, y);
+    if y <= x {
+        cvlr_assert!(fv(result) + fv(y) == fv(x));
+    } else {
+        cvlr_assert!(result == 0);
+    }
+}
+
+/// Verifies that `mul` computes the product whenever it fits a `u64`.
+#[rule]
+pub fn rule_mul_is_exact() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    cvlr_assume!(fv(x) * fv(y) <= fv(u64::MAX));
+    cvlr_assert!(fv(mul(x, y)) == fv(x) * fv(y));
+}
+
+/// Verifies that `checked_mul` returns `None` exactly when `wrapping_mul`
+/// differs from the product, and the product otherwise.
+#[rule]
+pub fn rule_checked_mul_none_iff_wrapping_differs() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    let product = fv(x) * fv(y);
+    let wrapped = fv(wrapping_mul(x, y));
+    match checked_mul(x, y) {
+        Some(result) => cvlr_assert!(fv(result) == product),
+        None => cvlr_assert!(wrapped != product),
+    }
+    if wrapped == product {
+        cvlr_assert!(checked_mul(x, y).is_some());
+    }
+}
+
+/// Verifies that `wrapping_mul` is the product modulo 2^64: the product is
+/// its high word times 2^64 plus the wrapped result.
+#[rule]
+pub fn rule_wrapping_mul_is_modular() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    let high = ((x as u128 * y as u128) >> 64) as u64;
+    cvlr_assert!(fv(high) * modulus() + fv(wrapping_mul(x, y)) == fv(x) * fv(y));
+}
+
+/// Verifies that `saturating_mul` is the product clamped to `u64::MAX`.
+#[rule]
+pub fn rule_saturating_mul_clamps() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    let product = fv(x) * fv(y);
+    let result = saturating_mul(x, y);
+    if product <= fv(u64::MAX) {
+        cvlr_assert!(fv(result) == product);
+    } else {
+        cvlr_assert!(result == u64::MAX);
+    }
+}
+
+/// Verifies that `div` and `rem` are the Euclidean quotient and remainder:
+/// x == q * y + r with r < y.
+#[rule]
+pub fn rule_div_rem_are_euclidean() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    cvlr_assume!(y != 0);
+    let (q, r) = (div(x, y), rem(x, y));
+    cvlr_assert!(fv(q) * fv(y) + fv(r) == fv(x));
+    cvlr_assert!(r < y);
+}
+
+/// Verifies that `checked_div` returns `None` exactly for a zero divisor,
+/// and the quotient otherwise.
+#[rule]
+pub fn rule_checked_div_none_iff_zero() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    match checked_div(x, y) {
+        Some(q) => {
+            cvlr_assert!(y != 0);
+            cvlr_assert!(q == div(x, y));
+        }
+        None => cvlr_assert!(y == 0),
+    }
+}
+
+/// Verifies that `checked_rem` returns `None` exactly for a zero divisor,
+/// and the remainder otherwise.
+#[rule]
+pub fn rule_checked_rem_none_iff_zero() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    match checked_rem(x, y) {
+        Some(r) => {
+            cvlr_assert!(y != 0);
+            cvlr_assert!(r == rem(x, y));
+        }
+        None => cvlr_assert!(y == 0),
+    }
+}
@@ -0,0 +1,376 @@
+//! This module contains the specification of the arithmetic at every width.
+//!
+//! The rules are written once, generic over a [`Word`], and instantiated
+//! per type by a `rule_<type>_<operation>` rule per width and operation,
+//! the way the rule compiler monomorphizes a parameterized specification.
+//! Each generic rule checks all variants of one operation against its
+//! result in mathematical integers: the plain operator is exact whenever
+//! the result fits, the checked variant fails exactly when it does not,
+//! the saturating one clamps it and the wrapping one is modular.
+
+use crate::{int64, uint128, uint32, uint64};
+use cvlr::{mathint::NativeInt, prelude::*};
+
+/// An integer type the rules are instantiated at.
+trait Word: Copy + PartialOrd {
+    const MIN: Self;
+    const MAX: Self;
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// The value as a mathematical integer.
+    fn fv(self) -> NativeInt;
+}
+
+impl Word for u32 {
+    const MIN: Self = u32::MIN;
+    const MAX: Self = u32::MAX;
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+
+    fn fv(self) -> NativeInt {
+        (self as u64).into()
+    }
+}
+
+impl Word for u64 {
+    const MIN: Self = u64::MIN;
+    const MAX: Self = u64::MAX;
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+
+    fn fv(self) -> NativeInt {
+        self.into()
+    }
+}
+
+impl Word for u128 {
+    const MIN: Self = u128::MIN;
+    const MAX: Self = u128::MAX;
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+
+    fn fv(self) -> NativeInt {
+        self.into()
+    }
+}
+
+impl Word for i64 {
+    const MIN: Self = i64::MIN;
+    const MAX: Self = i64::MAX;
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+
+    fn fv(self) -> NativeInt {
+        let magnitude: NativeInt = self.unsigned_abs().into();
+        if self < 0 {
+            NativeInt::from(0u64) - magnitude
+        } else {
+            magnitude
+        }
+    }
+}
+
+/// Whether the mathematical integer `exact` fits a `T`.
+fn fits<T: Word>(exact: NativeInt) -> bool {
+    T::MIN.fv() <= exact && exact <= T::MAX.fv()
+}
+
+/// 2^bits of a `T`.
+fn modulus<T: Word>() -> NativeInt {
+    T::MAX.fv() - T::MIN.fv() + 1u64
+}
+
+/// The variants of a binary operation.
+struct Variants<T> {
+    plain: fn(T, T) -> T,
+    checked: fn(T, T) -> Option<T>,
+    wrapping: fn(T, T) -> T,
+    saturating: fn(T, T) -> T,
+}
+
+/// Checks the plain, checked and saturating variants of an operation on
+/// `x` and `y`, whose mathematical result is `exact`.
+fn check_exact<T: Word>(x: T, y: T, exact: NativeInt, variants: &Variants<T>) {
+    let fits = fits::<T>(exact);
+    if fits {
+        cvlr_assert!((variants.plain)(x, y).fv() == exact);
+    }
+    match (variants.checked)(x, y) {
+        Some(result) => cvlr_assert!(result.fv() == exact),
+        None => cvlr_assert!(!fits),
+    }
+    let saturated = (variants.saturating)(x, y);
+    if fits {
+        cvlr_assert!(saturated.fv() == exact);
+    } else if exact < T::MIN.fv() {
+        cvlr_assert!(saturated == T::MIN);
+    } else {
+        cvlr_assert!(saturated == T::MAX);
+    }
+}
+
+/// Checks addition on `T`; wrapping around at most once either way.
+fn check_add<T: Word>(x: T, y: T, variants: Variants<T>) {
+    let exact = x.fv() + y.fv();
+    check_exact(x, y, exact, &variants);
+    let wrapped = (variants.wrapping)(x, y).fv();
+    cvlr_assert!(
+        wrapped == exact || wrapped + modulus::<T>() == exact || wrapped == exact + modulus::<T>()
+    );
+}
+
+/// Checks subtraction on `T`; wrapping around at most once either way.
+fn check_sub<T: Word>(x: T, y: T, variants: Variants<T>) {
+    let exact = x.fv() - y.fv();
+    check_exact(x, y, exact, &variants);
+    let wrapped = (variants.wrapping)(x, y).fv();
+    cvlr_assert!(
+        wrapped == exact || wrapped + modulus::<T>() == exact || wrapped == exact + modulus::<T>()
+    );
+}
+
+/// Checks multiplication on `T`. A product may wrap around many times, so
+/// the wrapping one is pinned down by distributing over wrapping addition:
+/// x * 0 == 0 and x * (y + 1) == x * y + x, modulo 2^bits.
+fn check_mul<T: Word>(x: T, y: T, variants: Variants<T>, wrapping_add: fn(T, T) -> T) {
+    check_exact(x, y, x.fv() * y.fv(), &variants);
+    let wrapping_mul = variants.wrapping;
+    cvlr_assert!(wrapping_mul(x, T::ZERO) == T::ZERO);
+    cvlr_assert!(wrapping_mul(x, wrapping_add(y, T::ONE)) == wrapping_add(wrapping_mul(x, y), x));
+}
+
+/// Checks division and remainder on `T`, rounding towards zero: whenever
+/// the quotient fits, x == q * y + r, with r of the sign of x and
+/// |r| < |y|. `checked_div` and `checked_rem` fail exactly for a zero
+/// divisor and for the one quotient that overflows, `MIN / -1`.
+fn check_div_rem<T: Word>(
+    x: T,
+    y: T,
+    div: fn(T, T) -> T,
+    rem: fn(T, T) -> T,
+    checked_div: fn(T, T) -> Option<T>,
+    checked_rem: fn(T, T) -> Option<T>,
+) {
+    let zero = T::ZERO.fv();
+    let overflows = y == T::ZERO || (x == T::MIN && y.fv() + 1u64 == zero);
+    cvlr_assert!(checked_div(x, y).is_none() == overflows);
+    cvlr_assert!(checked_rem(x, y).is_none() == overflows);
+    if overflows {
+        return;
+    }
+    let (q, r) = (div(x, y).fv(), rem(x, y).fv());
+    cvlr_assert!(q * y.fv() + r == x.fv());
+    let divisor = if y < T::ZERO { zero - y.fv() } else { y.fv() };
+    if x < T::ZERO {
+        cvlr_assert!(r <= zero && zero - r < divisor);
+    } else {
+        cvlr_assert!(zero <= r && r < divisor);
+    }
+}
+
+/// The [`Variants`] of an operation in `$module`.
+macro_rules! variants {
+    ($module:ident, $plain:ident, $checked:ident, $wrapping:ident, $saturating:ident) => {
+        Variants {
+            plain: $module::$plain,
+            checked: $module::$checked,
+            wrapping: $module::$wrapping,
+            saturating: $module::$saturating,
+        }
+    };
+}
+
+/// Verifies addition on `u32`.
+#[rule]
+pub fn rule_u32_add() {
+    let (x, y): (u32, u32) = (nondet(), nondet());
+    check_add(
+        x,
+        y,
+        variants!(uint32, add, checked_add, wrapping_add, saturating_add),
+    );
+}
+
+/// Verifies subtraction on `u32`.
+#[rule]
+pub fn rule_u32_sub() {
+    let (x, y): (u32, u32) = (nondet(), nondet());
+    check_sub(
+        x,
+        y,
+        variants!(uint32, sub, checked_sub, wrapping_sub, saturating_sub),
+    );
+}
+
+/// Verifies multiplication on `u32`.
+#[rule]
+pub fn rule_u32_mul() {
+    let (x, y): (u32, u32) = (nondet(), nondet());
+    let variants = variants!(uint32, mul, checked_mul, wrapping_mul, saturating_mul);
+    check_mul(x, y, variants, uint32::wrapping_add);
+}
+
+/// Verifies division and remainder on `u32`.
+#[rule]
+pub fn rule_u32_div_rem() {
+    let (x, y): (u32, u32) = (nondet(), nondet());
+    check_div_rem(
+        x,
+        y,
+        uint32::div,
+        uint32::rem,
+        uint32::checked_div,
+        uint32::checked_rem,
+    );
+}
+
+/// Verifies addition on `u64`.
+#[rule]
+pub fn rule_u64_add() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    check_add(
+        x,
+        y,
+        variants!(uint64, add, checked_add, wrapping_add, saturating_add),
+    );
+}
+
+/// Verifies subtraction on `u64`.
+#[rule]
+pub fn rule_u64_sub() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    check_sub(
+        x,
+        y,
+        variants!(uint64, sub, checked_sub, wrapping_sub, saturating_sub),
+    );
+}
+
+/// Verifies multiplication on `u64`.
+#[rule]
+pub fn rule_u64_mul() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    let variants = variants!(uint64, mul, checked_mul, wrapping_mul, saturating_mul);
+    check_mul(x, y, variants, uint64::wrapping_add);
+}
+
+/// Verifies division and remainder on `u64`.
+#[rule]
+pub fn rule_u64_div_rem() {
+    let (x, y): (u64, u64) = (nondet(), nondet());
+    check_div_rem(
+        x,
+        y,
+        uint64::div,
+        uint64::rem,
+        uint64::checked_div,
+        uint64::checked_rem,
+    );
+}
+
+/// Verifies addition on `u128`.
+#[rule]
+pub fn rule_u128_add() {
+    let (x, y): (u128, u128) = (nondet(), nondet());
+    check_add(
+        x,
+        y,
+        variants!(uint128, add, checked_add, wrapping_add, saturating_add),
+    );
+}
+
+/// Verifies subtraction on `u128`.
+#[rule]
+pub fn rule_u128_sub() {
+    let (x, y): (u128, u128) = (nondet(), nondet());
+    check_sub(
+        x,
+        y,
+        variants!(uint128, sub, checked_sub, wrapping_sub, saturating_sub),
+    );
+}
+
+/// Verifies multiplication on `u128`.
+#[rule]
+pub fn rule_u128_mul() {
+    let (x, y): (u128, u128) = (nondet(), nondet());
+    let variants = variants!(uint128, mul, checked_mul, wrapping_mul, saturating_mul);
+    check_mul(x, y, variants, uint128::wrapping_add);
+}
+
+/// Verifies division and remainder on `u128`.
+#[rule]
+pub fn rule_u128_div_rem() {
+    let (x, y): (u128, u128) = (nondet(), nondet());
+    check_div_rem(
+        x,
+        y,
+        uint128::div,
+        uint128::rem,
+        uint128::checked_div,
+        uint128::checked_rem,
+    );
+}
+
+/// Verifies addition on `i64`.
+#[rule]
+pub fn rule_i64_add() {
+    let (x, y): (i64, i64) = (nondet(), nondet());
+    check_add(
+        x,
+        y,
+        variants!(int64, add, checked_add, wrapping_add, saturating_add),
+    );
+}
+
+/// Verifies subtraction on `i64`.
+#[rule]
+pub fn rule_i64_sub() {
+    let (x, y): (i64, i64) = (nondet(), nondet());
+    check_sub(
+        x,
+        y,
+        variants!(int64, sub, checked_sub, wrapping_sub, saturating_sub),
+    );
+}
+
+/// Verifies multiplication on `i64`.
+#[rule]
+pub fn rule_i64_mul() {
+    let (x, y): (i64, i64) = (nondet(), nondet());
+    let variants = variants!(int64, mul, checked_mul, wrapping_mul, saturating_mul);
+    check_mul(x, y, variants, int64::wrapping_add);
+}
+
+/// Verifies division and remainder on `i64`.
+#[rule]
+pub fn rule_i64_div_rem() {
+    let (x, y): (i64, i64) = (nondet(), nondet());
+    check_div_rem(
+        x,
+        y,
+        int64::div,
+        int64::rem,
+        int64::checked_div,
+        int64::checked_rem,
+    );
+}
+
+/// Verifies that the wrapping and saturating divisions on `i64` agree with
+/// `div` and `wrapping_rem` with `rem` whenever the quotient fits, and that
+/// `MIN / -1` wraps to `MIN`, saturates to `MAX` and leaves no remainder.
+#[rule]
+pub fn rule_i64_div_overflow() {
+    let (x, y): (i64, i64) = (nondet(), nondet());
+    cvlr_assume!(y != 0);
+    if x == i64::MIN && y == -1 {
+        cvlr_assert!(int64::wrapping_div(x, y) == i64::MIN);
+        cvlr_assert!(int64::saturating_div(x, y) == i64::MAX);
+        cvlr_assert!(int64::wrapping_rem(x, y) == 0);
+    } else {
+        cvlr_assert!(int64::wrapping_div(x, y) == int64::div(x, y));
+        cvlr_assert!(int64::saturating_div(x, y) == int64::div(x, y));
+        cvlr_assert!(int64::wrapping_rem(x, y) == int64::rem(x, y));
+    }
+}
@@ -0,0 +1,312 @@
+//! Integer arithmetic with explicit overflow semantics, at every width.
+//!
+//! Every operation comes as the plain operator, which panics on overflow in
+//! debug builds, and as checked, wrapping and saturating variants: checked
+//! ones return `None` exactly when the mathematical result does not fit the
+//! type, wrapping ones return it modulo 2^bits and saturating ones clamp it
+//! to the bounds of the type. Unsigned division and remainder never
+//! overflow, so they only come checked, returning `None` for a zero
+//! divisor; their wrapping and saturating variants would be the operators
+//! themselves. Signed division overflows for `MIN / -1`, so it also comes
+//! wrapping and saturating.
+//!
+//! The operations are generated once per type by the `unsigned!` and
+//! `signed!` macros, into [`uint32`], [`uint64`], [`uint128`] and
+//! [`int64`]; the `u64` ones are also at the crate root. The rules in
+//! `src/certora/spec.rs` pin the `u64` variants down against the result
+//! computed in mathematical integers, and those in `src/certora/widths.rs`
+//! are generic over the width, instantiated once per type, making this
+//! crate the smallest example of the rule compiler's output.
+
+/// Defines the module `$module` of the operations on the unsigned `$t`.
+macro_rules! unsigned {
+    ($module:ident, $t:ty) => {
+        #[doc = concat!("Arithmetic on `", stringify!($t), "`.")]
+        pub mod $module {
+            /// `x + y`; panics on overflow in debug builds.
+            pub fn add(x: $t, y: $t) -> $t {
+                x + y
+            }
+
+            /// `x + y`, or `None` if it overflows.
+            pub fn checked_add(x: $t, y: $t) -> Option<$t> {
+                let sum = wrapping_add(x, y);
+                // The sum wrapped around if and only if it is below either operand
+                (sum >= x).then_some(sum)
+            }
+
+            /// `x + y` modulo 2^bits.
+            pub fn wrapping_add(x: $t, y: $t) -> $t {
+                x.wrapping_add(y)
+            }
+
+            /// `x + y`, or the maximum if it overflows.
+            pub fn saturating_add(x: $t, y: $t) -> $t {
+                checked_add(x, y).unwrap_or(<$t>::MAX)
+            }
+
+            /// `x - y`; panics on underflow in debug builds.
+            pub fn sub(x: $t, y: $t) -> $t {
+                x - y
+            }
+
+            /// `x - y`, or `None` if `y > x`.
+            pub fn checked_sub(x: $t, y: $t) -> Option<$t> {
+                (y <= x).then(|| x - y)
+            }
+
+            /// `x - y` modulo 2^bits.
+            pub fn wrapping_sub(x: $t, y: $t) -> $t {
+                x.wrapping_sub(y)
+            }
+
+            /// `x - y`, or 0 if `y > x`.
+            pub fn saturating_sub(x: $t, y: $t) -> $t {
+                checked_sub(x, y).unwrap_or(0)
+            }
+
+            /// `x * y`; panics on overflow in debug builds.
+            pub fn mul(x: $t, y: $t) -> $t {
+                x * y
+            }
+
+            /// `x * y`, or `None` if it overflows.
+            pub fn checked_mul(x: $t, y: $t) -> Option<$t> {
+                let product = wrapping_mul(x, y);
+                // Dividing back recovers `y` if and only if nothing wrapped around
+                (x == 0 || product / x == y).then_some(product)
+            }
+
+            /// `x * y` modulo 2^bits.
+            pub fn wrapping_mul(x: $t, y: $t) -> $t {
+                x.wrapping_mul(y)
+            }
+
+            /// `x * y`, or the maximum if it overflows.
+            pub fn saturating_mul(x: $t, y: $t) -> $t {
+                checked_mul(x, y).unwrap_or(<$t>::MAX)
+            }
+
+            /// `x / y`, rounded down; panics if `y` is zero.
+            pub fn div(x: $t, y: $t) -> $t {
+                x / y
+            }
+
+            /// `x / y`, rounded down, or `None` if `y` is zero.
+            pub fn checked_div(x: $t, y: $t) -> Option<$t> {
+                (y != 0).then(|| x / y)
+            }
+
+            /// The remainder of `x / y`; panics if `y` is zero.
+            pub fn rem(x: $t, y: $t) -> $t {
+                x % y
+            }
+
+            /// The remainder of `x / y`, or `None` if `y` is zero.
+            pub fn checked_rem(x: $t, y: $t) -> Option<$t> {
+                (y != 0).then(|| x % y)
+            }
+        }
+    };
+}
+
+/// Defines the module `$module` of the operations on the signed `$t`.
+macro_rules! signed {
+    ($module:ident, $t:ty) => {
+        #[doc = concat!("Arithmetic on `", stringify!($t), "`.")]
+        pub mod $module {
+            /// `x + y`; panics on overflow in debug builds.
+            pub fn add(x: $t, y: $t) -> $t {
+                x + y
+            }
+
+            /// `x + y`, or `None` if it overflows.
+            pub fn checked_add(x: $t, y: $t) -> Option<$t> {
+                let sum = wrapping_add(x, y);
+                // Adding a negative number must decrease `x`, and any other increase it
+                ((y < 0) == (sum < x)).then_some(sum)
+            }
+
+            /// `x + y` modulo 2^bits.
+            pub fn wrapping_add(x: $t, y: $t) -> $t {
+                x.wrapping_add(y)
+            }
+
+            /// `x + y`, clamped to the bounds of the type.
+            pub fn saturating_add(x: $t, y: $t) -> $t {
+                checked_add(x, y).unwrap_or(if y < 0 { <$t>::MIN } else { <$t>::MAX })
+            }
+
+            /// `x - y`; panics on overflow in debug builds.
+            pub fn sub(x: $t, y: $t) -> $t {
+                x - y
+            }
+
+            /// `x - y`, or `None` if it overflows.
+            pub fn checked_sub(x: $t, y: $t) -> Option<$t> {
+                let difference = wrapping_sub(x, y);
+                // Subtracting a negative number must increase `x`, and any other decrease it
+                ((y < 0) == (difference > x)).then_some(difference)
+            }
+
+            /// `x - y` modulo 2^bits.
+            pub fn wrapping_sub(x: $t, y: $t) -> $t {
+                x.wrapping_sub(y)
+            }
+
+            /// `x - y`, clamped to the bounds of the type.
+            pub fn saturating_sub(x: $t, y: $t) -> $t {
+                checked_sub(x, y).unwrap_or(if y < 0 { <$t>::MAX } else { <$t>::MIN })
+            }
+
+            /// `x * y`; panics on overflow in debug builds.
+            pub fn mul(x: $t, y: $t) -> $t {
+                x * y
+            }
+
+            /// `x * y`, or `None` if it overflows.
+            pub fn checked_mul(x: $t, y: $t) -> Option<$t> {
+                x.checked_mul(y)
+            }
+
+            /// `x * y` modulo 2^bits.
+            pub fn wrapping_mul(x: $t, y: $t) -> $t {
+                x.wrapping_mul(y)
+            }
+
+            /// `x * y`, clamped to the bounds of the type.
+            pub fn saturating_mul(x: $t, y: $t) -> $t {
+                checked_mul(x, y).unwrap_or(if (x < 0) == (y < 0) {
+                    <$t>::MAX
+                } else {
+                    <$t>::MIN
+                })
+            }
+
+            /// `x / y`, rounded towards zero; panics if `y` is zero or the
+            /// quotient overflows.
+            pub fn div(x: $t, y: $t) -> $t {
+                x / y
+            }
+
+            /// `x / y`, rounded towards zero, or `None` if `y` is zero or the
+            /// quotient overflows.
+            pub fn checked_div(x: $t, y: $t) -> Option<$t> {
+                x.checked_div(y)
+            }
+
+            /// `x / y`, rounded towards zero, modulo 2^bits; panics if `y` is
+            /// zero.
+            pub fn wrapping_div(x: $t, y: $t) -> $t {
+                x.wrapping_div(y)
+            }
+
+            /// `x / y`, rounded towards zero and clamped to the bounds of the
+            /// type; panics if `y` is zero.
+            pub fn saturating_div(x: $t, y: $t) -> $t {
+                x.saturating_div(y)
+            }
+
+            /// The remainder of `x / y`, of the sign of `x`; panics if `y` is
+            /// zero or the quotient overflows.
+            pub fn rem(x: $t, y: $t) -> $t {
+                x % y
+            }
+
+            /// The remainder of `x / y`, or `None` if `y` is zero or the
+            /// quotient overflows.
+            pub fn checked_rem(x: $t, y: $t) -> Option<$t> {
+                x.checked_rem(y)
+            }
+
+            /// The remainder of `x / y`, 0 if the quotient overflows; panics
+            /// if `y` is zero.
+            pub fn wrapping_rem(x: $t, y: $t) -> $t {
+                x.wrapping_rem(y)
+            }
+        }
+    };
+}
+
+unsigned!(uint32, u32);
+unsigned!(uint64, u64);
+unsigned!(uint128, u128);
+signed!(int64, i64);
+
+pub use uint64::*;
+
+// Include formal verification module only if certora feature is enabled.
+#[cfg(feature = "certora")]
+pub mod certora;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        assert_eq!(add(2, 3), 5);
+        assert_eq!(add(u64::MAX - 1, 1), u64::MAX);
+        assert_eq!(checked_add(u64::MAX, 0), Some(u64::MAX));
+        assert_eq!(checked_add(u64::MAX, 1), None);
+        assert_eq!(wrapping_add(u64::MAX, 2), 1);
+        assert_eq!(saturating_add(u64::MAX, 2), u64::MAX);
+    }
+
+    #[test]
+    fn test_sub() {
+        assert_eq!(sub(5, 3), 2);
+        assert_eq!(checked_sub(3, 3), Some(0));
+        assert_eq!(checked_sub(3, 4), None);
+        assert_eq!(wrapping_sub(0, 1), u64::MAX);
+        assert_eq!(saturating_sub(3, 4), 0);
+    }
+
+    #[test]
+    fn test_mul() {
+        assert_eq!(mul(6, 7), 42);
+        assert_eq!(checked_mul(u64::MAX, 1), Some(u64::MAX));
+        assert_eq!(checked_mul(1 << 32, 1 << 32), None);
+        assert_eq!(wrapping_mul(1 << 32, 1 << 32), 0);
+        assert_eq!(wrapping_mul(u64::MAX, 3), u64::MAX - 2);
+        assert_eq!(saturating_mul(1 << 32, 1 << 32), u64::MAX);
+    }
+
+    #[test]
+    fn test_div_rem() {
+        assert_eq!(div(7, 2), 3);
+        assert_eq!(rem(7, 2), 1);
+        assert_eq!(checked_div(7, 0), None);
+        assert_eq!(checked_rem(7, 0), None);
+        assert_eq!(checked_div(u64::MAX, 1), Some(u64::MAX));
+        assert_eq!(checked_rem(u64::MAX, u64::MAX), Some(0));
+    }
+
+    #[test]
+    fn test_widths() {
+        assert_eq!(uint32::checked_add(u32::MAX, 1), None);
+        assert_eq!(uint32::wrapping_mul(1 << 16, 1 << 16), 0);
+        assert_eq!(uint32::saturating_sub(1, 2), 0);
+        assert_eq!(uint128::checked_mul(1 << 64, 1 << 63), Some(1 << 127));
+        assert_eq!(uint128::checked_mul(1 << 64, 1 << 64), None);
+        assert_eq!(uint128::saturating_add(u128::MAX, 1), u128::MAX);
+    }
+
+    #[test]
+    fn test_signed() {
+        assert_eq!(int64::checked_add(i64::MAX, 1), None);
+        assert_eq!(int64::checked_add(i64::MIN, -1), None);
+        assert_eq!(int64::checked_add(-1, 1), Some(0));
+        assert_eq!(int64::saturating_add(i64::MIN, -1), i64::MIN);
+        assert_eq!(int64::checked_sub(i64::MIN, 1), None);
+        assert_eq!(int64::checked_sub(0, i64::MIN), None);
+        assert_eq!(int64::saturating_sub(0, i64::MIN), i64::MAX);
+        assert_eq!(int64::saturating_mul(i64::MIN, 2), i64::MIN);
+        assert_eq!(int64::saturating_mul(i64::MIN, -2), i64::MAX);
+        assert_eq!(int64::checked_div(i64::MIN, -1), None);
+        assert_eq!(int64::wrapping_div(i64::MIN, -1), i64::MIN);
+        assert_eq!(int64::saturating_div(i64::MIN, -1), i64::MAX);
+        assert_eq!(int64::rem(-7, 2), -1);
+        assert_eq!(int64::wrapping_rem(i64::MIN, -1), 0);
+    }
+}
@@ -0,0 +1,109 @@
+//! Property tests of the arithmetic library, mirroring its rules on random
+//! inputs with `u128` standing in for mathematical integers, and checking
+//! every width against the integer methods of std.
+
+use arithmetic::*;
+use proptest::prelude::*;
+
+const MODULUS: u128 = 1 << 64;
+
+proptest! {
+    #[test]
+    fn add_variants(x: u64, y: u64) {
+        let sum = x as u128 + y as u128;
+        let wrapped = wrapping_add(x, y);
+        prop_assert_eq!(wrapped as u128, sum % MODULUS);
+        prop_assert_eq!(checked_add(x, y).is_none(), wrapped as u128 != sum);
+        prop_assert_eq!(saturating_add(x, y) as u128, sum.min(u64::MAX as u128));
+        if let Some(result) = checked_add(x, y) {
+            prop_assert_eq!(result, add(x, y));
+        }
+    }
+
+    #[test]
+    fn sub_variants(x: u64, y: u64) {
+        let wrapped = wrapping_sub(x, y);
+        prop_assert_eq!((wrapped as u128 + y as u128) % MODULUS, x as u128);
+        prop_assert_eq!(checked_sub(x, y).is_none(), y > x);
+        prop_assert_eq!(saturating_sub(x, y), if y > x { 0 } else { sub(x, y) });
+    }
+
+    #[test]
+    fn mul_variants(x: u64, y: u64) {
+        let product = x as u128 * y as u128;
+        let wrapped = wrapping_mul(x, y);
+        prop_assert_eq!(wrapped as u128, product % MODULUS);
+        prop_assert_eq!(checked_mul(x, y).is_none(), wrapped as u128 != product);
+        prop_assert_eq!(saturating_mul(x, y) as u128, product.min(u64::MAX as u128));
+        if let Some(result) = checked_mul(x, y) {
+            prop_assert_eq!(result, mul(x, y));
+        }
+    }
+
+    #[test]
+    fn div_rem_variants(x: u64, y: u64) {
+        prop_assert_eq!(checked_div(x, y).is_none(), y == 0);
+        prop_assert_eq!(checked_rem(x, y).is_none(), y == 0);
+        if y != 0 {
+            let (q, r) = (div(x, y), rem(x, y));
+            prop_assert_eq!(q as u128 * y as u128 + r as u128, x as u128);
+            prop_assert!(r < y);
+        }
+    }
+}
+
+/// Checks the operations of the unsigned `$module` against those of std.
+macro_rules! unsigned_matches_std {
+    ($test:ident, $module:ident, $t:ty) => {
+        proptest! {
+            #[test]
+            fn $test(x: $t, y: $t) {
+                prop_assert_eq!($module::checked_add(x, y), x.checked_add(y));
+                prop_assert_eq!($module::wrapping_add(x, y), x.wrapping_add(y));
+                prop_assert_eq!($module::saturating_add(x, y), x.saturating_add(y));
+                prop_assert_eq!($module::checked_sub(x, y), x.checked_sub(y));
+                prop_assert_eq!($module::wrapping_sub(x, y), x.wrapping_sub(y));
+                prop_assert_eq!($module::saturating_sub(x, y), x.saturating_sub(y));
+                prop_assert_eq!($module::checked_mul(x, y), x.checked_mul(y));
+                prop_assert_eq!($module::wrapping_mul(x, y), x.wrapping_mul(y));
+                prop_assert_eq!($module::saturating_mul(x, y), x.saturating_mul(y));
+                prop_assert_eq!($module::checked_div(x, y), x.checked_div(y));
+                prop_assert_eq!($module::checked_rem(x, y), x.checked_rem(y));
+            }
+        }
+    };
+}
+
+unsigned_matches_std!(uint32_matches_std, uint32, u32);
+unsigned_matches_std!(uint64_matches_std, uint64, u64);
+unsigned_matches_std!(uint128_matches_std, uint128, u128);
+
+proptest! {
+    #[test]
+    fn int64_matches_std(x: i64, y: i64) {
+        prop_assert_eq!(int64::checked_add(x, y), x.checked_add(y));
+        prop_assert_eq!(int64::wrapping_add(x, y), x.wrapping_add(y));
+        prop_assert_eq!(int64::saturating_add(x, y), x.saturating_add(y));
+        prop_assert_eq!(int64::checked_sub(x, y), x.checked_sub(y));
+        prop_assert_eq!(int64::wrapping_sub(x, y), x.wrapping_sub(y));
+        prop_assert_eq!(int64::saturating_sub(x, y), x.saturating_sub(y));
+        prop_assert_eq!(int64::checked_mul(x, y), x.checked_mul(y));
+        prop_assert_eq!(int64::wrapping_mul(x, y), x.wrapping_mul(y));
+        prop_assert_eq!(int64::saturating_mul(x, y), x.saturating_mul(y));
+        prop_assert_eq!(int64::checked_div(x, y), x.checked_div(y));
+        prop_assert_eq!(int64::checked_rem(x, y), x.checked_rem(y));
+    }
+
+    #[test]
+    fn int64_overflow_edges(
+        x in prop_oneof![Just(i64::MIN), Just(i64::MAX), any::<i64>()],
+        y in prop_oneof![Just(-1i64), Just(1i64), Just(i64::MIN), any::<i64>()],
+    ) {
+        prop_assert_eq!(int64::checked_add(x, y), x.checked_add(y));
+        prop_assert_eq!(int64::checked_sub(x, y), x.checked_sub(y));
+        prop_assert_eq!(int64::saturating_mul(x, y), x.saturating_mul(y));
+        prop_assert_eq!(int64::wrapping_div(x, y), x.wrapping_div(y));
+        prop_assert_eq!(int64::saturating_div(x, y), x.saturating_div(y));
+        prop_assert_eq!(int64::wrapping_rem(x, y), x.wrapping_rem(y));
+    }
+}
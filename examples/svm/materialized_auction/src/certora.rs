@@ -0,0 +1,13 @@
+//! Formal verification module for the English auction.
+
+pub mod spec;
+
+/// Macro to assert that `$recipient` received exactly `$amount` lamports out
+/// of the auction account.
+#[macro_export]
+macro_rules! assert_paid_from_auction {
+    ($auction_pre:expr, $auction_post:expr, $recipient_pre:expr, $recipient_post:expr, $amount:expr) => {
+        cvlr::cvlr_assert!($recipient_post == $recipient_pre + $amount);
+        cvlr::cvlr_assert!($auction_post + $amount == $auction_pre);
+    };
+}
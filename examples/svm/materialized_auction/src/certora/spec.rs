@@ -0,0 +1,105 @@
+//! This module contains the specification for the English auction.
+
+use crate::{assert_paid_from_auction, processor::*, state::Auction};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    pubkey::Pubkey,
+};
+
+/// Structure tracking the state for the formal verification (FV) of the
+/// auction.
+struct FvAuction {
+    seller: Pubkey,
+    highest_bidder: Pubkey,
+    highest_bid: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvAuction {
+    fn from(acc_info: &AccountInfo) -> FvAuction {
+        let data = acc_info.data.borrow();
+        let auction: &Auction = bytemuck::from_bytes(&data[..]);
+        let highest_bid: u64 = auction.highest_bid.into();
+        FvAuction {
+            seller: auction.seller,
+            highest_bidder: auction.highest_bidder,
+            highest_bid: highest_bid.into(),
+        }
+    }
+}
+
+fn fv_lamports(acc_info: &AccountInfo) -> NativeInt {
+    acc_info.lamports().into()
+}
+
+/// Verifies that the stored highest bid is monotone non-decreasing.
+#[rule]
+pub fn rule_highest_bid_monotone() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let auction_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_auction_pre: FvAuction = auction_account.into();
+
+    let amount: u64 = nondet();
+    process_bid(&account_infos, &amount.to_le_bytes()).unwrap();
+
+    let fv_auction_post: FvAuction = auction_account.into();
+    cvlr_assert!(fv_auction_post.highest_bid >= fv_auction_pre.highest_bid);
+}
+
+/// Verifies that an outbid bidder is refunded exactly its escrowed bid.
+#[rule]
+pub fn rule_outbid_refund_equals_escrowed() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let auction_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let _bidder: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let previous_bidder: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_auction_pre: FvAuction = auction_account.into();
+    cvlr_assume!(fv_auction_pre.highest_bid > NativeInt::from(0u64));
+    let auction_pre = fv_lamports(auction_account);
+    let previous_pre = fv_lamports(previous_bidder);
+
+    let amount: u64 = nondet();
+    process_bid(&account_infos, &amount.to_le_bytes()).unwrap();
+
+    let auction_post = fv_lamports(auction_account);
+    let previous_post = fv_lamports(previous_bidder);
+    cvlr_assert!(*previous_bidder.key == fv_auction_pre.highest_bidder);
+    assert_paid_from_auction!(
+        auction_pre,
+        auction_post,
+        previous_pre,
+        previous_post,
+        fv_auction_pre.highest_bid
+    );
+}
+
+/// Verifies that settlement pays exactly the highest bid to the seller.
+#[rule]
+pub fn rule_settle_pays_highest_bid() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let auction_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let seller: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_auction_pre: FvAuction = auction_account.into();
+    let auction_pre = fv_lamports(auction_account);
+    let seller_pre = fv_lamports(seller);
+
+    process_settle(&account_infos, &[]).unwrap();
+
+    let auction_post = fv_lamports(auction_account);
+    let seller_post = fv_lamports(seller);
+    cvlr_assert!(*seller.key == fv_auction_pre.seller);
+    assert_paid_from_auction!(
+        auction_pre,
+        auction_post,
+        seller_pre,
+        seller_post,
+        fv_auction_pre.highest_bid
+    );
+}
@@ -0,0 +1,68 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::Auction;
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// Opens an auction ending at `end_slot` with no bids.
+pub fn create_ix(auction: Pubkey, seller: Pubkey, end_slot: u64) -> Instruction {
+    let mut data = vec![0u8];
+    data.extend_from_slice(&end_slot.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(auction, false),
+            AccountMeta::new_readonly(seller, true),
+        ],
+        data,
+    }
+}
+
+/// Bids `amount`, already transferred into the auction account, refunding
+/// the previous highest bid to `previous_bidder` unless this is the first
+/// bid.
+pub fn bid_ix(
+    auction: Pubkey,
+    bidder: Pubkey,
+    previous_bidder: Option<Pubkey>,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![1u8];
+    data.extend_from_slice(&amount.to_le_bytes());
+    let mut accounts = vec![
+        AccountMeta::new(auction, false),
+        AccountMeta::new_readonly(bidder, true),
+    ];
+    accounts.extend(previous_bidder.map(|key| AccountMeta::new(key, false)));
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data,
+    }
+}
+
+/// Pays the highest bid to the seller once the auction has ended.
+pub fn settle_ix(auction: Pubkey, seller: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(auction, false),
+            AccountMeta::new(seller, false),
+        ],
+        data: vec![2u8],
+    }
+}
+
+/// Decodes the data of an Auction account.
+pub fn decode_auction(data: &[u8]) -> Result<Auction, ProgramError> {
+    data.get(..core::mem::size_of::<Auction>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
@@ -0,0 +1,135 @@
+use crate::state::Auction;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+use svm_fv_support::cpi;
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Moves `amount` lamports from the auction account to `recipient`.
+fn pay_out(auction_account: &AccountInfo, recipient: &AccountInfo, amount: u64) -> ProgramResult {
+    if auction_account.key == recipient.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    let auction_lamports = auction_account.lamports();
+    let recipient_lamports = recipient.lamports();
+    **auction_account.try_borrow_mut_lamports()? = auction_lamports
+        .checked_sub(amount)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    **recipient.try_borrow_mut_lamports()? = recipient_lamports
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+/// Process a create instruction.
+/// Opens an auction with no bids.
+///
+/// # Arguments
+/// - `accounts`: Account array (auction account first, signing seller second)
+/// - `instruction_data`: 8 bytes representing the end slot
+pub fn process_create(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let end_slot = read_u64(instruction_data, 0)?;
+
+    let auction_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let seller = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !seller.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read auction data
+    let mut auction_data = auction_account.data.borrow_mut();
+    let auction: &mut Auction = bytemuck::from_bytes_mut(&mut auction_data);
+    let end: u64 = auction.end_slot.into();
+    if end != 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    *auction = Auction::new(*seller.key, end_slot);
+
+    Ok(())
+}
+
+/// Process a bid instruction.
+/// Accepts a strictly higher bid, already transferred into the auction
+/// account, and refunds the previous highest bid to its bidder.
+///
+/// # Arguments
+/// - `accounts`: Account array (auction account first, signing bidder second,
+///   previous highest bidder third unless this is the first bid)
+/// - `instruction_data`: 8 bytes representing the bid
+pub fn process_bid(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+
+    let auction_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let bidder = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !bidder.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read auction data
+    let mut auction_data = auction_account.data.borrow_mut();
+    let auction: &mut Auction = bytemuck::from_bytes_mut(&mut auction_data);
+    let end_slot: u64 = auction.end_slot.into();
+    if auction.settled != 0 || cpi::sysvar::slot()? >= end_slot {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let highest_bid: u64 = auction.highest_bid.into();
+    if amount <= highest_bid {
+        return Err(ProgramError::InvalidArgument);
+    }
+    // Everything above the escrowed bid must cover the new bid
+    let unaccounted = auction_account.lamports().saturating_sub(highest_bid);
+    if unaccounted < amount {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    if highest_bid > 0 {
+        let previous_bidder = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if *previous_bidder.key != auction.highest_bidder {
+            return Err(ProgramError::InvalidArgument);
+        }
+        pay_out(auction_account, previous_bidder, highest_bid)?;
+    }
+
+    auction.highest_bidder = *bidder.key;
+    auction.highest_bid = amount.into();
+
+    Ok(())
+}
+
+/// Process a settle instruction.
+/// Pays the highest bid to the seller once the auction has ended.
+///
+/// # Arguments
+/// - `accounts`: Account array (auction account first, seller second)
+/// - `instruction_data`: unused
+pub fn process_settle(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    let auction_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let seller = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // Borrow and read auction data
+    let mut auction_data = auction_account.data.borrow_mut();
+    let auction: &mut Auction = bytemuck::from_bytes_mut(&mut auction_data);
+    if *seller.key != auction.seller {
+        return Err(ProgramError::IllegalOwner);
+    }
+    let end_slot: u64 = auction.end_slot.into();
+    if auction.settled != 0 || cpi::sysvar::slot()? < end_slot {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    pay_out(auction_account, seller, auction.highest_bid.into())?;
+    auction.settled = 1;
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::PodU64;
+
+/// The English auction account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+/// The highest bid is escrowed as lamports of the auction account itself.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Auction {
+    /// The seller, paid the highest bid on settlement
+    pub seller: Pubkey,
+    /// The current highest bidder (default while there is no bid)
+    pub highest_bidder: Pubkey,
+    /// The current highest bid, held in escrow
+    pub highest_bid: PodU64,
+    /// Slot from which the auction can be settled and no longer accepts bids
+    pub end_slot: PodU64,
+    /// Non-zero once the auction has been settled
+    pub settled: u8,
+}
+
+impl Auction {
+    /// Create a new auction for `seller` ending at `end_slot`
+    pub fn new(seller: Pubkey, end_slot: u64) -> Self {
+        Auction {
+            seller,
+            highest_bidder: Pubkey::default(),
+            highest_bid: PodU64::from(0),
+            end_slot: PodU64::from(end_slot),
+            settled: 0,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! End-to-end scenarios of the auction: sequences of instructions sent to the
+//! program in a bank, checking the lamports and state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_auction::client::{bid_ix, create_ix, settle_ix};
+use solana_auction::state::Auction;
+use solana_auction::{id, process_instruction};
+use svm_test_support::solana_sdk::instruction::Instruction;
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::solana_sdk::system_instruction;
+use svm_test_support::{processor, ScenarioBuilder};
+
+/// The slot the auctions below end at.
+const END_SLOT: u64 = 1_000;
+
+/// A bid of `amount` by `bidder`, transferred into the auction in the same
+/// transaction, outbidding `previous` unless it is the first.
+fn bid(
+    auction: Pubkey,
+    bidder: &Keypair,
+    previous: Option<&Keypair>,
+    amount: u64,
+) -> [Instruction; 2] {
+    [
+        system_instruction::transfer(&bidder.pubkey(), &auction, amount),
+        bid_ix(
+            auction,
+            bidder.pubkey(),
+            previous.map(Signer::pubkey),
+            amount,
+        ),
+    ]
+}
+
+#[test]
+fn outbid_refunds_and_settle_pays_the_seller() {
+    let (seller, alice, bob) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let auction = Pubkey::new_unique();
+    let mut scenario =
+        ScenarioBuilder::new("solana_auction", id(), processor!(process_instruction))
+            .state(auction, &Auction::default())
+            .wallet(seller.pubkey())
+            .wallet(alice.pubkey())
+            .wallet(bob.pubkey())
+            .start();
+    let create = create_ix(auction, seller.pubkey(), END_SLOT);
+    scenario.send_instruction(create, &[&seller]).unwrap();
+
+    let alice_held = scenario.lamports(alice.pubkey());
+    scenario
+        .send_instructions(&bid(auction, &alice, None, 500), &[&alice])
+        .unwrap();
+    // A bid must beat the highest one
+    let result = scenario.send_instructions(&bid(auction, &bob, Some(&alice), 500), &[&bob]);
+    assert!(result.is_err());
+    // Outbidding refunds the previous bidder, and only them
+    let result = scenario.send_instructions(&bid(auction, &bob, Some(&seller), 800), &[&bob]);
+    assert!(result.is_err());
+    scenario
+        .send_instructions(&bid(auction, &bob, Some(&alice), 800), &[&bob])
+        .unwrap();
+    assert_eq!(scenario.lamports(alice.pubkey()), alice_held);
+    let state: Auction = scenario.state(auction);
+    assert_eq!(
+        (state.highest_bidder, u64::from(state.highest_bid)),
+        (bob.pubkey(), 800)
+    );
+
+    // Nobody settles before the end, nor bids after it
+    let settle = || settle_ix(auction, seller.pubkey());
+    assert!(scenario.send_instruction(settle(), &[]).is_err());
+    scenario.warp_to_slot(END_SLOT);
+    let result = scenario.send_instructions(&bid(auction, &alice, Some(&bob), 900), &[&alice]);
+    assert!(result.is_err());
+
+    let seller_held = scenario.lamports(seller.pubkey());
+    scenario.send_instruction(settle(), &[]).unwrap();
+    assert_eq!(scenario.lamports(seller.pubkey()), seller_held + 800);
+    // The seller is paid once
+    assert!(scenario.send_instruction(settle(), &[]).is_err());
+}
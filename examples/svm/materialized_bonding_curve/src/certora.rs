@@ -0,0 +1,27 @@
+//! Formal verification module for the bonding curve.
+
+pub mod spec;
+
+/// Macro to assume the reserve solvency property holds.
+/// Solvency: supply <= MAX_SUPPLY && reserve >= curve_integral(supply)
+#[macro_export]
+macro_rules! assume_reserve_solvency {
+    ($fv_curve:expr) => {
+        cvlr::cvlr_assume!($fv_curve.supply <= $crate::state::MAX_SUPPLY);
+        cvlr::cvlr_assume!(
+            $fv_curve.reserve >= $crate::processor::curve_integral($fv_curve.supply)
+        );
+    };
+}
+
+/// Macro to assert the reserve solvency property holds.
+/// Solvency: supply <= MAX_SUPPLY && reserve >= curve_integral(supply)
+#[macro_export]
+macro_rules! assert_reserve_solvency {
+    ($fv_curve:expr) => {
+        cvlr::cvlr_assert!($fv_curve.supply <= $crate::state::MAX_SUPPLY);
+        cvlr::cvlr_assert!(
+            $fv_curve.reserve >= $crate::processor::curve_integral($fv_curve.supply)
+        );
+    };
+}
@@ -0,0 +1,80 @@
+//! This module contains the specification for the bonding curve.
+
+use crate::{assert_reserve_solvency, assume_reserve_solvency, processor::*, state::Curve};
+use cvlr::prelude::*;
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the state for the formal verification (FV) of the
+/// bonding curve. Kept in `u64` because the curve integral is evaluated by
+/// the implementation's own `curve_integral`.
+struct FvCurve {
+    supply: u64,
+    reserve: u64,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvCurve {
+    fn from(acc_info: &AccountInfo) -> FvCurve {
+        let data = acc_info.data.borrow();
+        let curve: &Curve = bytemuck::from_bytes(&data[..]);
+        FvCurve {
+            supply: curve.supply.into(),
+            reserve: curve.reserve.into(),
+        }
+    }
+}
+
+/// Verifies that the reserve covers the curve integral after a buy.
+#[rule]
+pub fn rule_reserve_solvency_buy() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let curve_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_curve_pre: FvCurve = curve_account.into();
+    assume_reserve_solvency!(fv_curve_pre);
+
+    let amount: u64 = nondet();
+    process_buy(&account_infos, &amount.to_le_bytes()).unwrap();
+
+    let fv_curve_post: FvCurve = curve_account.into();
+    assert_reserve_solvency!(fv_curve_post);
+}
+
+/// Verifies that the reserve covers the curve integral after a sell.
+#[rule]
+pub fn rule_reserve_solvency_sell() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let curve_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_curve_pre: FvCurve = curve_account.into();
+    assume_reserve_solvency!(fv_curve_pre);
+
+    let amount: u64 = nondet();
+    process_sell(&account_infos, &amount.to_le_bytes()).unwrap();
+
+    let fv_curve_post: FvCurve = curve_account.into();
+    assert_reserve_solvency!(fv_curve_post);
+}
+
+/// Verifies that a buy followed by a sell of the same amount never refunds
+/// more than was paid, and loses at most one unit to rounding.
+#[rule]
+pub fn rule_round_trip_rounding_bounded() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let curve_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_curve_start: FvCurve = curve_account.into();
+    let amount: u64 = nondet();
+    process_buy(&account_infos, &amount.to_le_bytes()).unwrap();
+    let fv_curve_bought: FvCurve = curve_account.into();
+    process_sell(&account_infos, &amount.to_le_bytes()).unwrap();
+    let fv_curve_sold: FvCurve = curve_account.into();
+
+    let cost = fv_curve_bought.reserve - fv_curve_start.reserve;
+    let refund = fv_curve_bought.reserve - fv_curve_sold.reserve;
+    cvlr_assert!(refund <= cost);
+    cvlr_assert!(cost - refund <= 1);
+}
@@ -0,0 +1,57 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::{Curve, Holder};
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// The accounts of a buy or sell by `owner`, the owner of `holder`.
+fn accounts(curve: Pubkey, holder: Pubkey, owner: Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(curve, false),
+        AccountMeta::new(holder, false),
+        AccountMeta::new_readonly(owner, true),
+    ]
+}
+
+/// Mints `amount` tokens to the holder for their cost along the curve.
+pub fn buy_ix(curve: Pubkey, holder: Pubkey, owner: Pubkey, amount: u64) -> Instruction {
+    let mut data = vec![0u8];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: accounts(curve, holder, owner),
+        data,
+    }
+}
+
+/// Burns `amount` tokens of the holder for their refund along the curve.
+pub fn sell_ix(curve: Pubkey, holder: Pubkey, owner: Pubkey, amount: u64) -> Instruction {
+    let mut data = vec![1u8];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: accounts(curve, holder, owner),
+        data,
+    }
+}
+
+/// Decodes the data of a Curve account.
+pub fn decode_curve(data: &[u8]) -> Result<Curve, ProgramError> {
+    data.get(..core::mem::size_of::<Curve>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a Holder account.
+pub fn decode_holder(data: &[u8]) -> Result<Holder, ProgramError> {
+    data.get(..core::mem::size_of::<Holder>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
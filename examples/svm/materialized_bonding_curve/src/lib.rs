@@ -0,0 +1,53 @@
+use solana_program::{
+    account_info::AccountInfo, declare_id, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+pub mod processor;
+pub mod state;
+
+// Include formal verification module only if certora feature is enabled.
+#[cfg(feature = "certora")]
+pub mod certora;
+
+// Include the off-chain client module only if client feature is enabled.
+#[cfg(feature = "client")]
+pub mod client;
+
+#[cfg(not(feature = "certora"))]
+use solana_program::msg;
+// If certora feature is enabled, msg should be substituted with `clog!`.
+#[cfg(feature = "certora")]
+use cvlr::clog as msg;
+
+declare_id!("7snSyz6rbJ8zUK7pdnAzbD8guRMsfS38gpEfXd3TWFX5");
+
+#[cfg(not(feature = "no-entrypoint"))]
+use solana_program::entrypoint;
+
+#[cfg(not(feature = "no-entrypoint"))]
+entrypoint!(process_instruction);
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction_discriminant, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    match instruction_discriminant {
+        0 => {
+            msg!("Instruction: buy");
+            processor::process_buy(accounts, instruction_data)?;
+        }
+        1 => {
+            msg!("Instruction: sell");
+            processor::process_sell(accounts, instruction_data)?;
+        }
+        _ => {
+            msg!("Error: unknown instruction");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+    }
+    Ok(())
+}
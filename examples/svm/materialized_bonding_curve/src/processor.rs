@@ -0,0 +1,160 @@
+use crate::state::{Curve, Holder, BASE_PRICE, MAX_SUPPLY, SLOPE_DENOMINATOR, SLOPE_NUMERATOR};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Reserve backing `supply` tokens: the sum of the linear price
+/// `BASE_PRICE + SLOPE * i` over `i in 0..supply`, rounded down.
+/// Requires `supply <= MAX_SUPPLY`.
+///
+/// Buy costs and sell refunds are differences of this integral, so they
+/// telescope and the reserve always covers the integral at the current supply.
+pub fn curve_integral(supply: u64) -> u64 {
+    let supply = supply as u128;
+    let linear = (BASE_PRICE as u128) * supply;
+    let quadratic = (SLOPE_NUMERATOR as u128) * supply * supply.saturating_sub(1)
+        / (2 * SLOPE_DENOMINATOR as u128);
+    (linear + quadratic) as u64
+}
+
+/// Validates the account layout: curve first, holder of that curve second,
+/// signing holder owner third.
+fn curve_and_holder<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+) -> Result<(&'a AccountInfo<'b>, &'a AccountInfo<'b>), ProgramError> {
+    let curve_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let holder_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let owner = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if curve_account.key == holder_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    {
+        let holder_data = holder_account.data.borrow();
+        let holder: &Holder = bytemuck::from_bytes(&holder_data);
+        if holder.owner != *owner.key {
+            return Err(ProgramError::IllegalOwner);
+        }
+        if holder.curve != *curve_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+    Ok((curve_account, holder_account))
+}
+
+/// Process a buy instruction.
+/// Mints tokens to the holder for their cost along the curve.
+///
+/// # Arguments
+/// - `accounts`: Account array (curve account first, holder account second,
+///   signing holder owner third)
+/// - `instruction_data`: 8 bytes representing the number of tokens to buy
+pub fn process_buy(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let (curve_account, holder_account) = curve_and_holder(accounts)?;
+
+    // Borrow and read curve and holder data
+    let mut curve_data = curve_account.data.borrow_mut();
+    let curve: &mut Curve = bytemuck::from_bytes_mut(&mut curve_data);
+    let mut holder_data = holder_account.data.borrow_mut();
+    let holder: &mut Holder = bytemuck::from_bytes_mut(&mut holder_data);
+
+    let supply: u64 = curve.supply.into();
+    if supply > MAX_SUPPLY {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let new_supply = supply
+        .checked_add(amount)
+        .filter(|s| *s <= MAX_SUPPLY)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let cost = curve_integral(new_supply) - curve_integral(supply);
+
+    let reserve: u64 = curve.reserve.into();
+    let balance: u64 = holder.balance.into();
+    curve.reserve = reserve
+        .checked_add(cost)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    curve.supply = new_supply.into();
+    holder.balance = balance
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+
+    Ok(())
+}
+
+/// Process a sell instruction.
+/// Burns the holder's tokens for their refund along the curve.
+///
+/// # Arguments
+/// - `accounts`: Account array (curve account first, holder account second,
+///   signing holder owner third)
+/// - `instruction_data`: 8 bytes representing the number of tokens to sell
+pub fn process_sell(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let (curve_account, holder_account) = curve_and_holder(accounts)?;
+
+    // Borrow and read curve and holder data
+    let mut curve_data = curve_account.data.borrow_mut();
+    let curve: &mut Curve = bytemuck::from_bytes_mut(&mut curve_data);
+    let mut holder_data = holder_account.data.borrow_mut();
+    let holder: &mut Holder = bytemuck::from_bytes_mut(&mut holder_data);
+
+    let supply: u64 = curve.supply.into();
+    let balance: u64 = holder.balance.into();
+    if supply > MAX_SUPPLY {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if amount > balance || amount > supply {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    let new_supply = supply - amount;
+    let refund = curve_integral(supply) - curve_integral(new_supply);
+
+    let reserve: u64 = curve.reserve.into();
+    curve.reserve = reserve
+        .checked_sub(refund)
+        .ok_or(ProgramError::InsufficientFunds)?
+        .into();
+    curve.supply = new_supply.into();
+    holder.balance = (balance - amount).into();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curve_integral_small_values() {
+        assert_eq!(curve_integral(0), 0);
+        assert_eq!(curve_integral(1), BASE_PRICE);
+        // 1000 + 1000.003 + 1000.006, rounded down
+        assert_eq!(curve_integral(3), 3_000);
+    }
+
+    #[test]
+    fn test_curve_integral_monotone_and_exact_at_max() {
+        let mut previous = 0;
+        for supply in [1, 10, 1_000, 1_000_000, MAX_SUPPLY] {
+            let integral = curve_integral(supply);
+            assert!(integral > previous);
+            previous = integral;
+        }
+        assert_eq!(curve_integral(MAX_SUPPLY), 1_500_999_998_500_000);
+    }
+}
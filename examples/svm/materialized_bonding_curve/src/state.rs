@@ -0,0 +1,51 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::PodU64;
+
+/// Price of the first token, in reserve units.
+pub const BASE_PRICE: u64 = 1_000;
+
+/// Numerator of the price increase per token sold.
+pub const SLOPE_NUMERATOR: u64 = 3;
+
+/// Denominator of the price increase per token sold.
+pub const SLOPE_DENOMINATOR: u64 = 1_000;
+
+/// Maximum supply; keeps the curve integral well within `u64`.
+pub const MAX_SUPPLY: u64 = 1_000_000_000;
+
+/// The bonding curve account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Curve {
+    /// The curve authority
+    pub authority: Pubkey,
+    /// Tokens currently in circulation
+    pub supply: PodU64,
+    /// Reserve tokens held by the curve
+    pub reserve: PodU64,
+}
+
+impl Curve {
+    /// Create a new curve with nothing sold
+    pub fn new(authority: Pubkey) -> Self {
+        Curve {
+            authority,
+            supply: PodU64::from(0),
+            reserve: PodU64::from(0),
+        }
+    }
+}
+
+/// The token holder account data structure.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Holder {
+    /// The holder (authority)
+    pub owner: Pubkey,
+    /// The curve the tokens were bought from
+    pub curve: Pubkey,
+    /// Tokens held
+    pub balance: PodU64,
+}
@@ -0,0 +1,75 @@
+//! End-to-end scenarios of the bonding curve: sequences of instructions sent
+//! to the program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_bonding_curve::client::{buy_ix, sell_ix};
+use solana_bonding_curve::processor::curve_integral;
+use solana_bonding_curve::state::{Curve, Holder};
+use solana_bonding_curve::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, Scenario, ScenarioBuilder};
+
+/// The supply and the reserve of the curve at `key`.
+fn totals(scenario: &mut Scenario, key: Pubkey) -> (u64, u64) {
+    let curve: Curve = scenario.state(key);
+    (curve.supply.into(), curve.reserve.into())
+}
+
+/// The holder account of `owner` on `curve`, holding no tokens yet.
+fn holder(curve: Pubkey, owner: &Keypair) -> Holder {
+    Holder {
+        owner: owner.pubkey(),
+        curve,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn buy_then_sell_along_the_curve() {
+    let (alice, bob) = (Keypair::new(), Keypair::new());
+    let (curve, other_curve) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (alice_holder, bob_holder) = (Pubkey::new_unique(), Pubkey::new_unique());
+    // A holder account of Alice on another curve
+    let stray = Pubkey::new_unique();
+    let mut scenario = ScenarioBuilder::new(
+        "solana_bonding_curve",
+        id(),
+        processor!(process_instruction),
+    )
+    .state(curve, &Curve::new(Pubkey::new_unique()))
+    .state(alice_holder, &holder(curve, &alice))
+    .state(bob_holder, &holder(curve, &bob))
+    .state(stray, &holder(other_curve, &alice))
+    .start();
+
+    // Each buy costs the area under the curve it moves along
+    let buy = buy_ix(curve, alice_holder, alice.pubkey(), 100);
+    scenario.send_instruction(buy, &[&alice]).unwrap();
+    assert_eq!(totals(&mut scenario, curve), (100, curve_integral(100)));
+    let buy = buy_ix(curve, bob_holder, bob.pubkey(), 50);
+    scenario.send_instruction(buy, &[&bob]).unwrap();
+    assert_eq!(totals(&mut scenario, curve), (150, curve_integral(150)));
+
+    // Nobody sells someone else's tokens, more than they hold, or on
+    // another curve
+    let theft = sell_ix(curve, alice_holder, bob.pubkey(), 10);
+    assert!(scenario.send_instruction(theft, &[&bob]).is_err());
+    let oversold = sell_ix(curve, bob_holder, bob.pubkey(), 51);
+    assert!(scenario.send_instruction(oversold, &[&bob]).is_err());
+    let stray_buy = buy_ix(curve, stray, alice.pubkey(), 10);
+    assert!(scenario.send_instruction(stray_buy, &[&alice]).is_err());
+
+    // Selling refunds the area back down the curve, so the reserve always
+    // backs the supply
+    let sell = sell_ix(curve, alice_holder, alice.pubkey(), 100);
+    scenario.send_instruction(sell, &[&alice]).unwrap();
+    assert_eq!(totals(&mut scenario, curve), (50, curve_integral(50)));
+    let sell = sell_ix(curve, bob_holder, bob.pubkey(), 50);
+    scenario.send_instruction(sell, &[&bob]).unwrap();
+    assert_eq!(totals(&mut scenario, curve), (0, 0));
+    let state: Holder = scenario.state(alice_holder);
+    assert_eq!(u64::from(state.balance), 0);
+}
@@ -0,0 +1,41 @@
+//! Formal verification module for the stablecoin CDP system.
+
+pub mod spec;
+
+/// Macro to assume the minimum collateral ratio property holds.
+/// Collateralized: collateral * price * BPS_DENOMINATOR
+///                 >= debt * PRICE_SCALE * min_collateral_ratio_bps
+#[macro_export]
+macro_rules! assume_collateralized {
+    ($fv_system:expr, $fv_cdp:expr, $price:expr) => {
+        cvlr::cvlr_assume!(
+            $fv_cdp.collateral * $price * $crate::state::BPS_DENOMINATOR
+                >= $fv_cdp.debt * $crate::state::PRICE_SCALE * $fv_system.min_collateral_ratio_bps
+        );
+    };
+}
+
+/// Macro to assert the minimum collateral ratio property holds.
+/// Collateralized: collateral * price * BPS_DENOMINATOR
+///                 >= debt * PRICE_SCALE * min_collateral_ratio_bps
+#[macro_export]
+macro_rules! assert_collateralized {
+    ($fv_system:expr, $fv_cdp:expr, $price:expr) => {
+        cvlr::cvlr_assert!(
+            $fv_cdp.collateral * $price * $crate::state::BPS_DENOMINATOR
+                >= $fv_cdp.debt * $crate::state::PRICE_SCALE * $fv_system.min_collateral_ratio_bps
+        );
+    };
+}
+
+/// Macro to assert the global solvency property holds.
+/// Solvency: total_collateral * price >= total_debt * PRICE_SCALE
+#[macro_export]
+macro_rules! assert_solvent {
+    ($fv_system:expr, $price:expr) => {
+        cvlr::cvlr_assert!(
+            $fv_system.total_collateral * $price
+                >= $fv_system.total_debt * $crate::state::PRICE_SCALE
+        );
+    };
+}
@@ -0,0 +1,117 @@
+//! This module contains the specification for the stablecoin CDP system.
+
+use crate::{
+    assert_collateralized, assert_solvent, assume_collateralized,
+    processor::*,
+    state::{Cdp, Oracle, StableSystem},
+};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the system state for the formal verification (FV) of the
+/// stablecoin CDP system.
+struct FvSystem {
+    min_collateral_ratio_bps: NativeInt,
+    total_collateral: NativeInt,
+    total_debt: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvSystem {
+    fn from(acc_info: &AccountInfo) -> FvSystem {
+        let mut data = acc_info.data.borrow_mut();
+        let system: &StableSystem = bytemuck::from_bytes_mut(&mut data[..]);
+        let min_collateral_ratio_bps: u16 = system.min_collateral_ratio_bps.into();
+        let total_collateral: u64 = system.total_collateral.into();
+        let total_debt: u64 = system.total_debt.into();
+        FvSystem {
+            min_collateral_ratio_bps: (min_collateral_ratio_bps as u64).into(),
+            total_collateral: total_collateral.into(),
+            total_debt: total_debt.into(),
+        }
+    }
+}
+
+/// Structure tracking the CDP state for the formal verification (FV) of the
+/// stablecoin CDP system.
+struct FvCdp {
+    collateral: NativeInt,
+    debt: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvCdp {
+    fn from(acc_info: &AccountInfo) -> FvCdp {
+        let mut data = acc_info.data.borrow_mut();
+        let cdp: &Cdp = bytemuck::from_bytes_mut(&mut data[..]);
+        let collateral: u64 = cdp.collateral.into();
+        let debt: u64 = cdp.debt.into();
+        FvCdp {
+            collateral: collateral.into(),
+            debt: debt.into(),
+        }
+    }
+}
+
+/// Reads the (nondeterministic) oracle price.
+fn fv_price(acc_info: &AccountInfo) -> NativeInt {
+    let data = acc_info.data.borrow();
+    let oracle: &Oracle = bytemuck::from_bytes(&data[..]);
+    let price: u64 = oracle.price.into();
+    price.into()
+}
+
+/// Verifies that minting never leaves a CDP below the minimum collateral
+/// ratio at the oracle price, whatever its state before.
+#[rule]
+pub fn rule_mint_keeps_min_collateral_ratio() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let system_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let cdp_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let _owner: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let oracle_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_system: FvSystem = system_account.into();
+    let price = fv_price(oracle_account);
+
+    let amount: u64 = nondet();
+    let mint_instruction_data = &amount.to_le_bytes();
+    process_mint_stable(&account_infos, mint_instruction_data).unwrap();
+
+    let fv_cdp_post: FvCdp = cdp_account.into();
+    assert_collateralized!(fv_system, fv_cdp_post, price);
+}
+
+/// Verifies that minting keeps the global debt covered by the global
+/// collateral value at a nondeterministic oracle price, assuming every CDP
+/// was at or above the minimum collateral ratio before.
+#[rule]
+pub fn rule_mint_keeps_system_solvent() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let system_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let cdp_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let _owner: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let oracle_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_system_pre: FvSystem = system_account.into();
+    let fv_cdp_pre: FvCdp = cdp_account.into();
+    let price = fv_price(oracle_account);
+
+    // All other CDPs, summarized as a single collateralized position
+    let others = FvCdp {
+        collateral: nondet(),
+        debt: nondet(),
+    };
+    cvlr_assume!(fv_system_pre.total_collateral == fv_cdp_pre.collateral + others.collateral);
+    cvlr_assume!(fv_system_pre.total_debt == fv_cdp_pre.debt + others.debt);
+    assume_collateralized!(fv_system_pre, fv_cdp_pre, price);
+    assume_collateralized!(fv_system_pre, others, price);
+
+    let amount: u64 = nondet();
+    let mint_instruction_data = &amount.to_le_bytes();
+    process_mint_stable(&account_infos, mint_instruction_data).unwrap();
+
+    let fv_system_post: FvSystem = system_account.into();
+    assert_solvent!(fv_system_post, price);
+}
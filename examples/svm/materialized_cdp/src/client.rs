@@ -0,0 +1,100 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::{Cdp, Oracle, StableSystem};
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// The instruction `discriminant` with `data` over the accounts every
+/// instruction takes: the system, the CDP, the signer and the system's
+/// oracle.
+fn instruction(
+    discriminant: u8,
+    system: Pubkey,
+    cdp: Pubkey,
+    signer: Pubkey,
+    oracle: Pubkey,
+    data: &[u8],
+) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(system, false),
+            AccountMeta::new(cdp, false),
+            AccountMeta::new_readonly(signer, true),
+            AccountMeta::new_readonly(oracle, false),
+        ],
+        data: [[discriminant].as_slice(), data].concat(),
+    }
+}
+
+/// Creates a CDP for `owner` and locks the initial collateral.
+pub fn open_vault_ix(
+    system: Pubkey,
+    cdp: Pubkey,
+    owner: Pubkey,
+    oracle: Pubkey,
+    collateral: u64,
+) -> Instruction {
+    instruction(0, system, cdp, owner, oracle, &collateral.to_le_bytes())
+}
+
+/// Mints stablecoins against the collateral of the CDP of `owner`.
+pub fn mint_stable_ix(
+    system: Pubkey,
+    cdp: Pubkey,
+    owner: Pubkey,
+    oracle: Pubkey,
+    amount: u64,
+) -> Instruction {
+    instruction(1, system, cdp, owner, oracle, &amount.to_le_bytes())
+}
+
+/// Burns stablecoins of `payer` to reduce the debt of the CDP.
+pub fn repay_ix(
+    system: Pubkey,
+    cdp: Pubkey,
+    payer: Pubkey,
+    oracle: Pubkey,
+    amount: u64,
+) -> Instruction {
+    instruction(2, system, cdp, payer, oracle, &amount.to_le_bytes())
+}
+
+/// Closes a CDP below the minimum collateral ratio, its debt burnt by and
+/// its collateral paid to `liquidator`.
+pub fn liquidate_ix(
+    system: Pubkey,
+    cdp: Pubkey,
+    liquidator: Pubkey,
+    oracle: Pubkey,
+) -> Instruction {
+    instruction(3, system, cdp, liquidator, oracle, &[])
+}
+
+/// Decodes the data of a StableSystem account.
+pub fn decode_stable_system(data: &[u8]) -> Result<StableSystem, ProgramError> {
+    data.get(..core::mem::size_of::<StableSystem>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a Cdp account.
+pub fn decode_cdp(data: &[u8]) -> Result<Cdp, ProgramError> {
+    data.get(..core::mem::size_of::<Cdp>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of an Oracle account.
+pub fn decode_oracle(data: &[u8]) -> Result<Oracle, ProgramError> {
+    data.get(..core::mem::size_of::<Oracle>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
@@ -0,0 +1,257 @@
+use crate::state::{Cdp, Oracle, StableSystem, BPS_DENOMINATOR, PRICE_SCALE};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Returns true iff `collateral` valued at `price` covers `debt` at
+/// `ratio_bps`, i.e. `collateral * price / PRICE_SCALE >= debt * ratio_bps / BPS`,
+/// computed without intermediate rounding.
+pub fn meets_ratio(collateral: u64, debt: u64, price: u64, ratio_bps: u16) -> bool {
+    let rhs = (debt as u128) * (PRICE_SCALE as u128) * (ratio_bps as u128);
+    // An overflowing left-hand side is necessarily larger than any debt
+    match ((collateral as u128) * (price as u128)).checked_mul(BPS_DENOMINATOR as u128) {
+        Some(lhs) => lhs >= rhs,
+        None => true,
+    }
+}
+
+/// The accounts every CDP instruction operates on, after validation.
+struct CdpAccounts<'a, 'b> {
+    system: &'a AccountInfo<'b>,
+    cdp: &'a AccountInfo<'b>,
+    signer: &'a AccountInfo<'b>,
+    price: u64,
+}
+
+/// Validates the common account layout: system first, CDP of that system
+/// second, a signer third and the system's oracle fourth.
+fn cdp_accounts<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+) -> Result<CdpAccounts<'a, 'b>, ProgramError> {
+    let system = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let cdp = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let signer = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let oracle = accounts.get(3).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if system.key == cdp.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if !signer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let price = {
+        let system_data = system.data.borrow();
+        let stable_system: &StableSystem = bytemuck::from_bytes(&system_data);
+        if stable_system.oracle != *oracle.key {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let min_ratio: u16 = stable_system.min_collateral_ratio_bps.into();
+        if (min_ratio as u64) < BPS_DENOMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let oracle_data = oracle.data.borrow();
+        let oracle_state: &Oracle = bytemuck::from_bytes(&oracle_data);
+        oracle_state.price.into()
+    };
+
+    Ok(CdpAccounts {
+        system,
+        cdp,
+        signer,
+        price,
+    })
+}
+
+/// Process an open-vault instruction.
+/// Creates a CDP for the signer and locks the initial collateral.
+///
+/// # Arguments
+/// - `accounts`: Account array (system account first, CDP account second,
+///   signing owner third, oracle fourth)
+/// - `instruction_data`: 8 bytes representing the collateral to lock
+pub fn process_open_vault(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let CdpAccounts {
+        system,
+        cdp,
+        signer,
+        ..
+    } = cdp_accounts(accounts)?;
+
+    // Borrow and read system and CDP data
+    let mut system_data = system.data.borrow_mut();
+    let stable_system: &mut StableSystem = bytemuck::from_bytes_mut(&mut system_data);
+    let mut cdp_data = cdp.data.borrow_mut();
+    let position: &mut Cdp = bytemuck::from_bytes_mut(&mut cdp_data);
+
+    if position.system != Default::default() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let total_collateral: u64 = stable_system.total_collateral.into();
+    stable_system.total_collateral = total_collateral
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    *position = Cdp {
+        owner: *signer.key,
+        system: *system.key,
+        collateral: amount.into(),
+        debt: 0.into(),
+    };
+
+    Ok(())
+}
+
+/// Process a mint-stable instruction.
+/// Mints stablecoins against the CDP's collateral, as long as the CDP stays
+/// at or above the minimum collateral ratio at the oracle price.
+///
+/// # Arguments
+/// - `accounts`: Account array (system account first, CDP account second,
+///   signing owner third, oracle fourth)
+/// - `instruction_data`: 8 bytes representing the stablecoins to mint
+pub fn process_mint_stable(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let CdpAccounts {
+        system,
+        cdp,
+        signer,
+        price,
+    } = cdp_accounts(accounts)?;
+
+    // Borrow and read system and CDP data
+    let mut system_data = system.data.borrow_mut();
+    let stable_system: &mut StableSystem = bytemuck::from_bytes_mut(&mut system_data);
+    let mut cdp_data = cdp.data.borrow_mut();
+    let position: &mut Cdp = bytemuck::from_bytes_mut(&mut cdp_data);
+
+    if position.system != *system.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if position.owner != *signer.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let debt: u64 = position.debt.into();
+    let new_debt = debt
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if !meets_ratio(
+        position.collateral.into(),
+        new_debt,
+        price,
+        stable_system.min_collateral_ratio_bps.into(),
+    ) {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    let total_debt: u64 = stable_system.total_debt.into();
+    stable_system.total_debt = total_debt
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    position.debt = new_debt.into();
+
+    Ok(())
+}
+
+/// Process a repay instruction.
+/// Burns stablecoins to reduce the CDP's debt. Anyone may repay.
+///
+/// # Arguments
+/// - `accounts`: Account array (system account first, CDP account second,
+///   signing payer third, oracle fourth)
+/// - `instruction_data`: 8 bytes representing the stablecoins to burn
+pub fn process_repay(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let CdpAccounts { system, cdp, .. } = cdp_accounts(accounts)?;
+
+    // Borrow and read system and CDP data
+    let mut system_data = system.data.borrow_mut();
+    let stable_system: &mut StableSystem = bytemuck::from_bytes_mut(&mut system_data);
+    let mut cdp_data = cdp.data.borrow_mut();
+    let position: &mut Cdp = bytemuck::from_bytes_mut(&mut cdp_data);
+
+    if position.system != *system.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let debt: u64 = position.debt.into();
+    let total_debt: u64 = stable_system.total_debt.into();
+    if amount > debt || amount > total_debt {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    position.debt = (debt - amount).into();
+    stable_system.total_debt = (total_debt - amount).into();
+
+    Ok(())
+}
+
+/// Process a liquidate instruction.
+/// Closes a CDP below the minimum collateral ratio: the liquidator burns its
+/// whole debt and receives its whole collateral.
+///
+/// # Arguments
+/// - `accounts`: Account array (system account first, CDP account second,
+///   signing liquidator third, oracle fourth)
+/// - `instruction_data`: unused
+pub fn process_liquidate(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    let CdpAccounts {
+        system, cdp, price, ..
+    } = cdp_accounts(accounts)?;
+
+    // Borrow and read system and CDP data
+    let mut system_data = system.data.borrow_mut();
+    let stable_system: &mut StableSystem = bytemuck::from_bytes_mut(&mut system_data);
+    let mut cdp_data = cdp.data.borrow_mut();
+    let position: &mut Cdp = bytemuck::from_bytes_mut(&mut cdp_data);
+
+    if position.system != *system.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let collateral: u64 = position.collateral.into();
+    let debt: u64 = position.debt.into();
+    if meets_ratio(
+        collateral,
+        debt,
+        price,
+        stable_system.min_collateral_ratio_bps.into(),
+    ) {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let total_collateral: u64 = stable_system.total_collateral.into();
+    let total_debt: u64 = stable_system.total_debt.into();
+    stable_system.total_collateral = total_collateral.saturating_sub(collateral).into();
+    stable_system.total_debt = total_debt.saturating_sub(debt).into();
+    position.collateral = 0.into();
+    position.debt = 0.into();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meets_ratio() {
+        // 150 collateral at price 1.0 covers 100 debt at exactly 150%
+        assert!(meets_ratio(150, 100, PRICE_SCALE, 15_000));
+        assert!(!meets_ratio(149, 100, PRICE_SCALE, 15_000));
+        assert!(meets_ratio(0, 0, 0, 15_000));
+        assert!(!meets_ratio(u64::MAX, 1, 0, 15_000));
+        assert!(meets_ratio(u64::MAX, u64::MAX, u64::MAX, u16::MAX));
+    }
+}
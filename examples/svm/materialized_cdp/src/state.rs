@@ -0,0 +1,49 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::{PodU16, PodU64};
+
+/// Fixed-point scaling factor of oracle prices.
+pub const PRICE_SCALE: u64 = 1_000_000;
+
+/// Denominator for basis-point arithmetic.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// The stablecoin system account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct StableSystem {
+    /// The system authority
+    pub authority: Pubkey,
+    /// The price oracle account trusted by the system
+    pub oracle: Pubkey,
+    /// Minimum collateral value to debt ratio of every CDP, in bps
+    /// (at least `BPS_DENOMINATOR`)
+    pub min_collateral_ratio_bps: PodU16,
+    /// Total collateral locked across all CDPs
+    pub total_collateral: PodU64,
+    /// Total stablecoin debt across all CDPs
+    pub total_debt: PodU64,
+}
+
+/// The collateralized debt position account data structure.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Cdp {
+    /// The CDP owner (authority)
+    pub owner: Pubkey,
+    /// The system this CDP belongs to
+    pub system: Pubkey,
+    /// Collateral locked
+    pub collateral: PodU64,
+    /// Stablecoins minted against the collateral
+    pub debt: PodU64,
+}
+
+/// The mock price oracle account data structure.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Oracle {
+    /// Price of one collateral unit in stablecoins, scaled by `PRICE_SCALE`
+    pub price: PodU64,
+}
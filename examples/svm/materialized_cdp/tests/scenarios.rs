@@ -0,0 +1,127 @@
+//! End-to-end scenarios of the CDP system: sequences of instructions sent to
+//! the program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_cdp::client::{liquidate_ix, mint_stable_ix, open_vault_ix, repay_ix};
+use solana_cdp::state::{Cdp, Oracle, StableSystem, PRICE_SCALE};
+use solana_cdp::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, Scenario, ScenarioBuilder};
+
+/// A system requiring 150% collateral, trusting `oracle`.
+fn system(oracle: Pubkey) -> StableSystem {
+    StableSystem {
+        authority: Pubkey::new_unique(),
+        oracle,
+        min_collateral_ratio_bps: 15_000.into(),
+        ..Default::default()
+    }
+}
+
+/// The collateral and debt totals of the system at `key`.
+fn totals(scenario: &mut Scenario, key: Pubkey) -> (u64, u64) {
+    let system: StableSystem = scenario.state(key);
+    (system.total_collateral.into(), system.total_debt.into())
+}
+
+#[test]
+fn open_mint_repay() {
+    let (alice, bob) = (Keypair::new(), Keypair::new());
+    let (system_key, oracle, cdp) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let mut scenario = ScenarioBuilder::new("solana_cdp", id(), processor!(process_instruction))
+        .state(system_key, &system(oracle))
+        .state(
+            oracle,
+            &Oracle {
+                price: PRICE_SCALE.into(),
+            },
+        )
+        .state(cdp, &Cdp::default())
+        .start();
+    let open = open_vault_ix(system_key, cdp, alice.pubkey(), oracle, 150);
+    scenario.send_instruction(open, &[&alice]).unwrap();
+    // A CDP is opened once
+    let open = open_vault_ix(system_key, cdp, bob.pubkey(), oracle, 150);
+    assert!(scenario.send_instruction(open, &[&bob]).is_err());
+
+    // 150 collateral at a price of 1 backs up to 100 stablecoins, minted by
+    // the owner alone
+    let mint =
+        |owner: &Keypair, amount| mint_stable_ix(system_key, cdp, owner.pubkey(), oracle, amount);
+    assert!(scenario.send_instruction(mint(&bob, 50), &[&bob]).is_err());
+    scenario
+        .send_instruction(mint(&alice, 100), &[&alice])
+        .unwrap();
+    assert!(scenario
+        .send_instruction(mint(&alice, 1), &[&alice])
+        .is_err());
+    assert_eq!(totals(&mut scenario, system_key), (150, 100));
+    // Nor against the price of another oracle
+    let mut spoofed = mint(&alice, 0);
+    spoofed.accounts[3].pubkey = Pubkey::new_unique();
+    assert!(scenario.send_instruction(spoofed, &[&alice]).is_err());
+
+    // Anyone repays, but no more than the debt
+    let repay = |amount| repay_ix(system_key, cdp, bob.pubkey(), oracle, amount);
+    assert!(scenario.send_instruction(repay(101), &[&bob]).is_err());
+    scenario.send_instruction(repay(40), &[&bob]).unwrap();
+    assert_eq!(totals(&mut scenario, system_key), (150, 60));
+
+    // A healthy CDP is not liquidated
+    let liquidate = liquidate_ix(system_key, cdp, bob.pubkey(), oracle);
+    assert!(scenario.send_instruction(liquidate, &[&bob]).is_err());
+}
+
+#[test]
+fn undercollateralized_cdp_is_liquidated() {
+    let (alice, liquidator) = (Keypair::new(), Keypair::new());
+    let (system_key, oracle, cdp) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    // A CDP left at 120% by a fall of the price
+    let state = StableSystem {
+        total_collateral: 120.into(),
+        total_debt: 100.into(),
+        ..system(oracle)
+    };
+    let position = Cdp {
+        owner: alice.pubkey(),
+        system: system_key,
+        collateral: 120.into(),
+        debt: 100.into(),
+    };
+    let mut scenario = ScenarioBuilder::new("solana_cdp", id(), processor!(process_instruction))
+        .state(system_key, &state)
+        .state(
+            oracle,
+            &Oracle {
+                price: PRICE_SCALE.into(),
+            },
+        )
+        .state(cdp, &position)
+        .start();
+
+    // Its owner cannot dig it deeper
+    let mint = mint_stable_ix(system_key, cdp, alice.pubkey(), oracle, 1);
+    assert!(scenario.send_instruction(mint, &[&alice]).is_err());
+
+    let liquidate = || liquidate_ix(system_key, cdp, liquidator.pubkey(), oracle);
+    scenario
+        .send_instruction(liquidate(), &[&liquidator])
+        .unwrap();
+    let position: Cdp = scenario.state(cdp);
+    assert_eq!(
+        (u64::from(position.collateral), u64::from(position.debt)),
+        (0, 0)
+    );
+    assert_eq!(totals(&mut scenario, system_key), (0, 0));
+}
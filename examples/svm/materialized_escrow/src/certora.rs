@@ -0,0 +1,17 @@
+//! Formal verification module for the escrow.
+
+pub mod spec;
+
+/// Macro to assert that lamports only moved from the escrow to `$recipient`.
+/// Flow: escrow loses exactly what the recipient gains, and the escrow's
+/// bookkept amount drops to zero.
+#[macro_export]
+macro_rules! assert_escrow_flow {
+    ($fv_escrow_pre:expr, $fv_escrow_post:expr, $recipient_pre:expr, $recipient_post:expr) => {
+        cvlr::cvlr_assert!($recipient_post == $recipient_pre + $fv_escrow_pre.amount);
+        cvlr::cvlr_assert!(
+            $fv_escrow_post.lamports + $fv_escrow_pre.amount == $fv_escrow_pre.lamports
+        );
+        cvlr::cvlr_assert!($fv_escrow_post.amount == cvlr::mathint::NativeInt::from(0u64));
+    };
+}
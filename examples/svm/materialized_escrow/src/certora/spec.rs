@@ -0,0 +1,105 @@
+//! This module contains the specification for the escrow application.
+
+use crate::{assert_escrow_flow, processor::*, state::Escrow};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    pubkey::Pubkey,
+};
+
+/// Structure tracking the state for the formal verification (FV) of the escrow.
+struct FvEscrow {
+    maker: Pubkey,
+    taker: Pubkey,
+    amount: NativeInt,
+    lamports: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvEscrow {
+    fn from(acc_info: &AccountInfo) -> FvEscrow {
+        let mut data = acc_info.data.borrow_mut();
+        let escrow: &Escrow = bytemuck::from_bytes_mut(&mut data[..]);
+        let amount: u64 = escrow.amount.into();
+        FvEscrow {
+            maker: escrow.maker,
+            taker: escrow.taker,
+            amount: amount.into(),
+            lamports: acc_info.lamports().into(),
+        }
+    }
+}
+
+fn fv_lamports(acc_info: &AccountInfo) -> NativeInt {
+    acc_info.lamports().into()
+}
+
+/// Verifies that fulfillment pays the escrowed amount to the designated taker.
+#[rule]
+pub fn rule_fulfill_pays_taker() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let escrow_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let taker_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_escrow_pre: FvEscrow = escrow_account.into();
+    let taker_pre = fv_lamports(taker_account);
+
+    process_fulfill(&account_infos, &[]).unwrap();
+
+    let fv_escrow_post: FvEscrow = escrow_account.into();
+    let taker_post = fv_lamports(taker_account);
+    cvlr_assert!(*taker_account.key == fv_escrow_pre.taker);
+    assert_escrow_flow!(fv_escrow_pre, fv_escrow_post, taker_pre, taker_post);
+}
+
+/// Verifies that cancellation refunds the escrowed amount to the maker.
+#[rule]
+pub fn rule_cancel_refunds_maker() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let escrow_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let maker_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_escrow_pre: FvEscrow = escrow_account.into();
+    let maker_pre = fv_lamports(maker_account);
+
+    process_cancel(&account_infos, &[]).unwrap();
+
+    let fv_escrow_post: FvEscrow = escrow_account.into();
+    let maker_post = fv_lamports(maker_account);
+    cvlr_assert!(*maker_account.key == fv_escrow_pre.maker);
+    assert_escrow_flow!(fv_escrow_pre, fv_escrow_post, maker_pre, maker_post);
+}
+
+/// Verifies that neither fulfillment nor cancellation can move funds to any
+/// party other than the taker or the maker.
+#[rule]
+pub fn rule_no_third_party_payout() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let escrow_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let recipient_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let bystander_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_escrow_pre: FvEscrow = escrow_account.into();
+    cvlr_assume!(*bystander_account.key != *escrow_account.key);
+    cvlr_assume!(*bystander_account.key != *recipient_account.key);
+    let recipient_pre = fv_lamports(recipient_account);
+    let bystander_pre = fv_lamports(bystander_account);
+
+    if nondet::<bool>() {
+        process_fulfill(&account_infos, &[]).unwrap();
+    } else {
+        process_cancel(&account_infos, &[]).unwrap();
+    }
+
+    let recipient_post = fv_lamports(recipient_account);
+    cvlr_assert!(fv_lamports(bystander_account) == bystander_pre);
+    if recipient_post > recipient_pre {
+        cvlr_assert!(
+            *recipient_account.key == fv_escrow_pre.taker
+                || *recipient_account.key == fv_escrow_pre.maker
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+
+use crate::state::Escrow;
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// The escrow of `maker` with `seed`.
+fn escrow(maker: Pubkey, seed: u64) -> Pubkey {
+    Escrow::address(&maker, seed).0
+}
+
+/// Opens the escrow of `maker` with `seed` over `amount` lamports paid by the
+/// maker, designating the taker that may receive them.
+pub fn deposit_ix(maker: Pubkey, seed: u64, amount: u64, taker: Pubkey) -> Instruction {
+    let mut data = vec![0u8];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(taker.as_ref());
+    data.extend_from_slice(&seed.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(escrow(maker, seed), false),
+            AccountMeta::new(maker, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data,
+    }
+}
+
+/// Pays the escrowed lamports to the taker.
+pub fn fulfill_ix(escrow: Pubkey, taker: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(escrow, false),
+            AccountMeta::new(taker, true),
+        ],
+        data: vec![1u8],
+    }
+}
+
+/// Refunds the escrowed lamports to the maker.
+pub fn cancel_ix(escrow: Pubkey, maker: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(escrow, false),
+            AccountMeta::new(maker, true),
+        ],
+        data: vec![2u8],
+    }
+}
+
+/// Decodes the data of an Escrow account.
+pub fn decode_escrow(data: &[u8]) -> Result<Escrow, ProgramError> {
+    data.get(..core::mem::size_of::<Escrow>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
@@ -0,0 +1,202 @@
+use crate::state::{Escrow, ESCROW_CANCELLED, ESCROW_EMPTY, ESCROW_FULFILLED, ESCROW_OPEN};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program::invoke_signed,
+    program_error::ProgramError, pubkey::Pubkey, rent::Rent, system_instruction, sysvar::Sysvar,
+};
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Reads a `Pubkey` starting at `offset` in the instruction data.
+fn read_pubkey(instruction_data: &[u8], offset: usize) -> Result<Pubkey, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 32)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    Pubkey::try_from(bytes).map_err(|_| ProgramError::InvalidInstructionData)
+}
+
+/// Process a deposit instruction.
+/// Opens the escrow of the maker with `seed`, at the PDA derived from both,
+/// designating the taker that may receive the escrowed lamports. Unless it
+/// already exists, the escrow account is created and funded by the maker with
+/// the escrowed lamports on top of its rent-exempt minimum; either way, it must
+/// hold them on top of that minimum.
+///
+/// # Arguments
+/// - `accounts`: Account array (escrow account first, signing maker second,
+///   system program third)
+/// - `instruction_data`: 8 bytes representing the escrowed lamports, followed
+///   by the 32-byte taker pubkey and the 8-byte seed
+pub fn process_deposit(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let taker = read_pubkey(instruction_data, 8)?;
+    let seed = read_u64(instruction_data, 40)?;
+
+    let escrow_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let maker_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let system_program = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !maker_account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let (address, bump) = Escrow::address(maker_account.key, seed);
+    if *escrow_account.key != address {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the account, unless it was already
+    let space = core::mem::size_of::<Escrow>();
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(space);
+    if escrow_account.data_is_empty() {
+        let lamports = rent_exempt_minimum
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        invoke_signed(
+            &system_instruction::create_account(
+                maker_account.key,
+                &address,
+                lamports,
+                space as u64,
+                &crate::id(),
+            ),
+            &[
+                maker_account.clone(),
+                escrow_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                Escrow::SEED,
+                maker_account.key.as_ref(),
+                &seed.to_le_bytes(),
+                &[bump],
+            ]],
+        )?;
+    }
+    if *escrow_account.owner != crate::id() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    // The escrowed lamports come on top of the rent-exempt minimum, which
+    // stays behind once they are released
+    if escrow_account
+        .lamports()
+        .saturating_sub(rent_exempt_minimum)
+        < amount
+    {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    // Borrow and read escrow data
+    let mut escrow_data = escrow_account.data.borrow_mut();
+    let escrow: &mut Escrow = bytemuck::from_bytes_mut(&mut escrow_data);
+    if escrow.status != ESCROW_EMPTY {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    *escrow = Escrow::new(*maker_account.key, taker, amount);
+
+    Ok(())
+}
+
+/// Moves the escrowed lamports to `recipient`, which must be `expected`, and
+/// closes the escrow with `final_status`.
+fn release(
+    escrow_account: &AccountInfo,
+    recipient: &AccountInfo,
+    expected: impl Fn(&Escrow) -> Pubkey,
+    final_status: u8,
+) -> ProgramResult {
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if escrow_account.key == recipient.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Borrow and read escrow data
+    let mut escrow_data = escrow_account.data.borrow_mut();
+    let escrow: &mut Escrow = bytemuck::from_bytes_mut(&mut escrow_data);
+    if escrow.status != ESCROW_OPEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if *recipient.key != expected(escrow) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let amount: u64 = escrow.amount.into();
+    let escrow_lamports = escrow_account.lamports();
+    let recipient_lamports = recipient.lamports();
+    **escrow_account.try_borrow_mut_lamports()? = escrow_lamports
+        .checked_sub(amount)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    **recipient.try_borrow_mut_lamports()? = recipient_lamports
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    escrow.amount = 0.into();
+    escrow.status = final_status;
+
+    Ok(())
+}
+
+/// Process a fulfill instruction.
+/// Pays the escrowed lamports to the designated taker.
+///
+/// # Arguments
+/// - `accounts`: Account array (escrow account first, signing taker second)
+/// - `instruction_data`: unused
+pub fn process_fulfill(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    let escrow_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let taker_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    release(escrow_account, taker_account, |e| e.taker, ESCROW_FULFILLED)
+}
+
+/// Process a cancel instruction.
+/// Refunds the escrowed lamports to the maker.
+///
+/// # Arguments
+/// - `accounts`: Account array (escrow account first, signing maker second)
+/// - `instruction_data`: unused
+pub fn process_cancel(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    let escrow_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let maker_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    release(escrow_account, maker_account, |e| e.maker, ESCROW_CANCELLED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use svm_fv_support::TestAccount;
+
+    #[test]
+    fn test_read_pubkey_short_input() {
+        assert!(read_pubkey(&[0u8; 31], 0).is_err());
+        assert_eq!(
+            read_pubkey(&[7u8; 40], 8).unwrap(),
+            Pubkey::new_from_array([7u8; 32])
+        );
+    }
+
+    #[test]
+    fn test_fulfill_pays_only_taker() {
+        let (maker, taker) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut escrow = TestAccount::new(crate::id())
+            .state(&Escrow::new(maker, taker, 600))
+            .lamports(1_000);
+        let escrow_account = escrow.info();
+
+        let mut stranger = TestAccount::wallet();
+        assert!(process_fulfill(&[escrow_account.clone(), stranger.info()], &[]).is_err());
+
+        let mut taker = TestAccount::wallet().key(taker);
+        let taker_account = taker.info();
+        process_fulfill(&[escrow_account.clone(), taker_account.clone()], &[]).unwrap();
+        assert_eq!(taker_account.lamports(), 600);
+        assert_eq!(escrow_account.lamports(), 400);
+    }
+}
@@ -0,0 +1,54 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::PodU64;
+
+/// Escrow has not been initialized yet.
+pub const ESCROW_EMPTY: u8 = 0;
+/// Maker has deposited; waiting for the taker to fulfill or the maker to cancel.
+pub const ESCROW_OPEN: u8 = 1;
+/// Taker has fulfilled and received the escrowed funds.
+pub const ESCROW_FULFILLED: u8 = 2;
+/// Maker has cancelled and received the escrowed funds back.
+pub const ESCROW_CANCELLED: u8 = 3;
+
+/// The escrow account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+/// The escrowed funds are held as lamports of the escrow account itself, on
+/// top of its rent-exempt minimum. Each escrow of a maker is at the PDA of
+/// [`Escrow::address`] for a seed of the maker's choosing.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Escrow {
+    /// The party that deposited the funds
+    pub maker: Pubkey,
+    /// The only party allowed to receive the funds on fulfillment
+    pub taker: Pubkey,
+    /// Lamports currently held in escrow
+    pub amount: PodU64,
+    /// Lifecycle status (one of the `ESCROW_*` constants)
+    pub status: u8,
+}
+
+impl Escrow {
+    /// Create a new open escrow between `maker` and `taker`
+    pub fn new(maker: Pubkey, taker: Pubkey, amount: u64) -> Self {
+        Escrow {
+            maker,
+            taker,
+            amount: PodU64::from(amount),
+            status: ESCROW_OPEN,
+        }
+    }
+
+    /// Seed of the escrow PDA, followed by the maker and the maker's seed
+    pub const SEED: &'static [u8] = b"escrow";
+
+    /// The address of the escrow of `maker` with `seed`, a PDA of the
+    /// program, and its bump seed.
+    pub fn address(maker: &Pubkey, seed: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[Self::SEED, maker.as_ref(), &seed.to_le_bytes()],
+            &crate::id(),
+        )
+    }
+}
@@ -0,0 +1,90 @@
+//! End-to-end scenarios of the escrow: sequences of instructions sent to the
+//! program in a bank, checking the lamports and state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_escrow::client::{cancel_ix, deposit_ix, fulfill_ix};
+use solana_escrow::state::{Escrow, ESCROW_CANCELLED, ESCROW_FULFILLED, ESCROW_OPEN};
+use solana_escrow::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, Scenario, ScenarioBuilder};
+
+/// A scenario with an empty escrow account at `stray`, not an escrow PDA,
+/// and wallets for `parties`.
+fn scenario(stray: Pubkey, parties: &[&Keypair]) -> Scenario {
+    let builder = ScenarioBuilder::new("solana_escrow", id(), processor!(process_instruction))
+        .state(stray, &Escrow::default());
+    parties
+        .iter()
+        .fold(builder, |builder, party| builder.wallet(party.pubkey()))
+        .start()
+}
+
+#[test]
+fn deposit_then_fulfill() {
+    let (maker, taker, stranger) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let stray = Pubkey::new_unique();
+    let mut scenario = scenario(stray, &[&maker, &taker, &stranger]);
+    let escrow = Escrow::address(&maker.pubkey(), 7).0;
+
+    // The escrow is opened at the PDA of the maker and the seed only
+    let mut elsewhere = deposit_ix(maker.pubkey(), 7, 600, taker.pubkey());
+    elsewhere.accounts[0].pubkey = stray;
+    assert!(scenario.send_instruction(elsewhere, &[&maker]).is_err());
+    let held = scenario.lamports(maker.pubkey());
+    let deposit = deposit_ix(maker.pubkey(), 7, 600, taker.pubkey());
+    scenario.send_instruction(deposit, &[&maker]).unwrap();
+    let state: Escrow = scenario.state(escrow);
+    assert_eq!((state.maker, state.status), (maker.pubkey(), ESCROW_OPEN));
+    // The maker pays the escrowed lamports on top of the rent
+    let rent = scenario.lamports(escrow) - 600;
+    assert_eq!(scenario.lamports(maker.pubkey()), held - rent - 600);
+    // An open escrow cannot be opened again
+    let deposit = deposit_ix(maker.pubkey(), 7, 1, stranger.pubkey());
+    assert!(scenario.send_instruction(deposit, &[&maker]).is_err());
+
+    // Only the taker is paid
+    let result = scenario.send_instruction(fulfill_ix(escrow, stranger.pubkey()), &[&stranger]);
+    assert!(result.is_err());
+    let (escrowed, held) = (scenario.lamports(escrow), scenario.lamports(taker.pubkey()));
+    scenario
+        .send_instruction(fulfill_ix(escrow, taker.pubkey()), &[&taker])
+        .unwrap();
+    assert_eq!(scenario.lamports(escrow), escrowed - 600);
+    assert_eq!(scenario.lamports(escrow), rent);
+    assert_eq!(scenario.lamports(taker.pubkey()), held + 600);
+    assert_eq!(scenario.state::<Escrow>(escrow).status, ESCROW_FULFILLED);
+
+    // Once paid, the escrow is closed to both parties
+    assert!(scenario
+        .send_instruction(fulfill_ix(escrow, taker.pubkey()), &[&taker])
+        .is_err());
+    assert!(scenario
+        .send_instruction(cancel_ix(escrow, maker.pubkey()), &[&maker])
+        .is_err());
+}
+
+#[test]
+fn deposit_then_cancel() {
+    let (maker, taker) = (Keypair::new(), Keypair::new());
+    let mut scenario = scenario(Pubkey::new_unique(), &[&maker, &taker]);
+    let escrow = Escrow::address(&maker.pubkey(), 0).0;
+
+    let deposit = deposit_ix(maker.pubkey(), 0, 600, taker.pubkey());
+    scenario.send_instruction(deposit, &[&maker]).unwrap();
+    // Only the maker cancels
+    assert!(scenario
+        .send_instruction(cancel_ix(escrow, taker.pubkey()), &[&taker])
+        .is_err());
+    let held = scenario.lamports(maker.pubkey());
+    scenario
+        .send_instruction(cancel_ix(escrow, maker.pubkey()), &[&maker])
+        .unwrap();
+    assert_eq!(scenario.lamports(maker.pubkey()), held + 600);
+    assert_eq!(scenario.state::<Escrow>(escrow).status, ESCROW_CANCELLED);
+    assert!(scenario
+        .send_instruction(fulfill_ix(escrow, taker.pubkey()), &[&taker])
+        .is_err());
+}
@@ -0,0 +1,3 @@
+//! Formal verification module for the fee splitter.
+
+pub mod spec;
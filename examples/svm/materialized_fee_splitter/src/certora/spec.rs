@@ -0,0 +1,94 @@
+//! This module contains the specification for the fee splitter.
+
+use crate::{
+    processor::*,
+    state::{Splitter, MAX_RECIPIENTS},
+};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the splitter state for the formal verification (FV) of
+/// the fee splitter.
+struct FvSplitter {
+    recipient_count: u64,
+    balances: [u64; MAX_RECIPIENTS],
+    dust: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvSplitter {
+    fn from(acc_info: &AccountInfo) -> FvSplitter {
+        let mut data = acc_info.data.borrow_mut();
+        let splitter: &Splitter = bytemuck::from_bytes_mut(&mut data[..]);
+        let dust: u64 = splitter.dust.into();
+        FvSplitter {
+            recipient_count: splitter.count() as u64,
+            balances: splitter.balances.map(u64::from),
+            dust: dust.into(),
+        }
+    }
+}
+
+impl FvSplitter {
+    /// Sum of the balances credited to the recipients.
+    fn total_balance(&self) -> NativeInt {
+        let mut total = NativeInt::from(0u64);
+        for balance in self.balances {
+            total = total + NativeInt::from(balance);
+        }
+        total
+    }
+}
+
+/// Verifies that a distribution credits the recipients with exactly the input
+/// minus at most N - 1 units of dust, and that the dust is carried forward
+/// rather than lost.
+#[rule]
+pub fn rule_distribute_conserves_amount() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let splitter_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_splitter_pre: FvSplitter = splitter_account.into();
+    let amount: u64 = nondet();
+    let distribute_instruction_data = &amount.to_le_bytes();
+    process_distribute(&account_infos, distribute_instruction_data).unwrap();
+
+    let fv_splitter_post: FvSplitter = splitter_account.into();
+    let amount = NativeInt::from(amount);
+    let distributed = fv_splitter_post.total_balance() - fv_splitter_pre.total_balance();
+    // Nothing is lost: credited amounts plus the new dust account for the
+    // input and the old dust
+    cvlr_assert!(distributed + fv_splitter_post.dust == amount + fv_splitter_pre.dust);
+    // Every recipient loses less than one unit to rounding
+    cvlr_assert!(fv_splitter_post.dust < NativeInt::from(fv_splitter_post.recipient_count));
+}
+
+/// Verifies that claiming only clears the claimant's own balance.
+#[rule]
+pub fn rule_claim_clears_only_own_balance() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let splitter_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let recipient: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let index: usize = nondet::<u8>() as usize;
+    cvlr_assume!(index < MAX_RECIPIENTS);
+    let claimant_index = {
+        let data = splitter_account.data.borrow();
+        let splitter: &Splitter = bytemuck::from_bytes(&data[..]);
+        splitter.recipients[..splitter.count()]
+            .iter()
+            .position(|key| key == recipient.key)
+    };
+    cvlr_assume!(claimant_index != Some(index));
+
+    let fv_splitter_pre: FvSplitter = splitter_account.into();
+    process_claim(&account_infos, &[]).unwrap();
+
+    let fv_splitter_post: FvSplitter = splitter_account.into();
+    cvlr_assert_eq!(
+        fv_splitter_post.balances[index],
+        fv_splitter_pre.balances[index]
+    );
+}
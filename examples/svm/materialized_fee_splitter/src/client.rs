@@ -0,0 +1,60 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::Splitter;
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// Initializes a splitter over `recipients`, each with its weight.
+pub fn create_ix(splitter: Pubkey, recipients: &[(Pubkey, u16)]) -> Instruction {
+    let mut data = vec![0u8, recipients.len() as u8];
+    for (recipient, weight) in recipients {
+        data.extend_from_slice(recipient.as_ref());
+        data.extend_from_slice(&weight.to_le_bytes());
+    }
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new(splitter, false)],
+        data,
+    }
+}
+
+/// Credits `amount` incoming tokens, paid by `payer`, to the recipients by
+/// weight.
+pub fn distribute_ix(splitter: Pubkey, payer: Pubkey, amount: u64) -> Instruction {
+    let mut data = vec![1u8];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(splitter, false),
+            AccountMeta::new_readonly(payer, true),
+        ],
+        data,
+    }
+}
+
+/// Pays out everything credited to `recipient`.
+pub fn claim_ix(splitter: Pubkey, recipient: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(splitter, false),
+            AccountMeta::new_readonly(recipient, true),
+        ],
+        data: vec![2u8],
+    }
+}
+
+/// Decodes the data of a Splitter account.
+pub fn decode_splitter(data: &[u8]) -> Result<Splitter, ProgramError> {
+    data.get(..core::mem::size_of::<Splitter>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
@@ -0,0 +1,190 @@
+use crate::state::{Splitter, MAX_RECIPIENTS};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Splits `amount` plus the carried dust among the recipients by weight,
+/// rounding every share down. The remainder, less than one unit per
+/// recipient, is carried forward as the new dust.
+pub fn distribute(splitter: &mut Splitter, amount: u64) -> ProgramResult {
+    let total_weight = splitter.total_weight();
+    if total_weight == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    let dust: u64 = splitter.dust.into();
+    let pool = amount
+        .checked_add(dust)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    let mut distributed: u64 = 0;
+    for i in 0..splitter.count() {
+        let weight = u16::from(splitter.weights[i]) as u128;
+        // weight <= total_weight, so the share is at most the pool
+        let share = ((pool as u128) * weight / (total_weight as u128)) as u64;
+        let balance: u64 = splitter.balances[i].into();
+        splitter.balances[i] = balance
+            .checked_add(share)
+            .ok_or(ProgramError::ArithmeticOverflow)?
+            .into();
+        distributed += share;
+    }
+    splitter.dust = (pool - distributed).into();
+
+    Ok(())
+}
+
+/// Process a create instruction.
+/// Initializes a splitter over N weighted recipients.
+///
+/// # Arguments
+/// - `accounts`: Account array (must have splitter account as first)
+/// - `instruction_data`: 1 byte recipient count (N), followed by N entries of
+///   a 32-byte recipient pubkey and a 2-byte weight
+pub fn process_create(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let (&recipient_count, entries) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    if recipient_count as usize > MAX_RECIPIENTS || entries.len() != recipient_count as usize * 34 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // Get splitter account
+    let splitter_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // Borrow and read splitter data
+    let mut splitter_data = splitter_account.data.borrow_mut();
+    let splitter: &mut Splitter = bytemuck::from_bytes_mut(&mut splitter_data);
+    if splitter.recipient_count != 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let mut new_splitter = Splitter {
+        recipient_count,
+        ..Default::default()
+    };
+    for (i, entry) in entries.chunks_exact(34).enumerate() {
+        let key =
+            Pubkey::try_from(&entry[..32]).map_err(|_| ProgramError::InvalidInstructionData)?;
+        // A recipient listed twice could not tell its two balances apart
+        if new_splitter.recipients[..i].contains(&key) {
+            return Err(ProgramError::InvalidArgument);
+        }
+        new_splitter.recipients[i] = key;
+        new_splitter.weights[i] = u16::from_le_bytes([entry[32], entry[33]]).into();
+    }
+    if new_splitter.total_weight() == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    *splitter = new_splitter;
+
+    Ok(())
+}
+
+/// Process a distribute instruction.
+/// Credits incoming tokens to the recipients by weight.
+///
+/// # Arguments
+/// - `accounts`: Account array (splitter account first, signing payer second)
+/// - `instruction_data`: 8 bytes representing the incoming amount
+pub fn process_distribute(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+
+    let splitter_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let payer = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read splitter data
+    let mut splitter_data = splitter_account.data.borrow_mut();
+    let splitter: &mut Splitter = bytemuck::from_bytes_mut(&mut splitter_data);
+
+    distribute(splitter, amount)
+}
+
+/// Process a claim instruction.
+/// Pays out everything credited to the signing recipient.
+///
+/// # Arguments
+/// - `accounts`: Account array (splitter account first, signing recipient
+///   second)
+/// - `instruction_data`: unused
+pub fn process_claim(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    let splitter_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let recipient = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read splitter data
+    let mut splitter_data = splitter_account.data.borrow_mut();
+    let splitter: &mut Splitter = bytemuck::from_bytes_mut(&mut splitter_data);
+    let index = splitter.recipients[..splitter.count()]
+        .iter()
+        .position(|key| key == recipient.key)
+        .ok_or(ProgramError::IllegalOwner)?;
+
+    splitter.balances[index] = 0.into();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn splitter(weights: &[u16]) -> Splitter {
+        let mut splitter = Splitter {
+            recipient_count: weights.len() as u8,
+            ..Default::default()
+        };
+        for (i, weight) in weights.iter().enumerate() {
+            splitter.weights[i] = (*weight).into();
+        }
+        splitter
+    }
+
+    fn balances(splitter: &Splitter) -> [u64; MAX_RECIPIENTS] {
+        splitter.balances.map(u64::from)
+    }
+
+    #[test]
+    fn test_distribute_by_weight() {
+        let mut splitter = splitter(&[1, 3]);
+        distribute(&mut splitter, 100).unwrap();
+        assert_eq!(balances(&splitter), [25, 75, 0, 0]);
+        assert_eq!(u64::from(splitter.dust), 0);
+    }
+
+    #[test]
+    fn test_distribute_carries_dust_forward() {
+        let mut splitter = splitter(&[1, 1, 1]);
+        distribute(&mut splitter, 10).unwrap();
+        assert_eq!(balances(&splitter), [3, 3, 3, 0]);
+        assert_eq!(u64::from(splitter.dust), 1);
+        // The carried unit joins the next distribution
+        distribute(&mut splitter, 2).unwrap();
+        assert_eq!(balances(&splitter), [4, 4, 4, 0]);
+        assert_eq!(u64::from(splitter.dust), 0);
+    }
+
+    #[test]
+    fn test_distribute_uninitialized() {
+        assert_eq!(
+            distribute(&mut Splitter::default(), 1),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+}
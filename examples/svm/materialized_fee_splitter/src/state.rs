@@ -0,0 +1,38 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::{PodU16, PodU64};
+
+/// Maximum number of recipients of a splitter.
+pub const MAX_RECIPIENTS: usize = 4;
+
+/// The fee splitter account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Splitter {
+    /// The recipients; only the first `recipient_count` entries are meaningful
+    pub recipients: [Pubkey; MAX_RECIPIENTS],
+    /// Weight of each recipient
+    pub weights: [PodU16; MAX_RECIPIENTS],
+    /// Amount credited to each recipient and not claimed yet
+    pub balances: [PodU64; MAX_RECIPIENTS],
+    /// Rounding remainder carried forward into the next distribution
+    pub dust: PodU64,
+    /// Number of recipients (N)
+    pub recipient_count: u8,
+}
+
+impl Splitter {
+    /// Returns the number of recipients, capped at `MAX_RECIPIENTS`.
+    pub fn count(&self) -> usize {
+        (self.recipient_count as usize).min(MAX_RECIPIENTS)
+    }
+
+    /// Returns the sum of the recipients' weights.
+    pub fn total_weight(&self) -> u64 {
+        self.weights[..self.count()]
+            .iter()
+            .map(|weight| u16::from(*weight) as u64)
+            .sum()
+    }
+}
@@ -0,0 +1,54 @@
+//! End-to-end scenarios of the fee splitter: sequences of instructions sent
+//! to the program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_fee_splitter::client::{claim_ix, create_ix, distribute_ix};
+use solana_fee_splitter::state::{Splitter, MAX_RECIPIENTS};
+use solana_fee_splitter::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, Scenario, ScenarioBuilder};
+
+/// The balances and the dust of the splitter at `key`.
+fn balances(scenario: &mut Scenario, key: Pubkey) -> ([u64; MAX_RECIPIENTS], u64) {
+    let splitter: Splitter = scenario.state(key);
+    (splitter.balances.map(u64::from), splitter.dust.into())
+}
+
+#[test]
+fn distribute_by_weight_and_claim() {
+    let (alice, bob, payer) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let splitter = Pubkey::new_unique();
+    let mut scenario =
+        ScenarioBuilder::new("solana_fee_splitter", id(), processor!(process_instruction))
+            .state(splitter, &Splitter::default())
+            .start();
+
+    // A recipient is listed once
+    let twice = create_ix(splitter, &[(alice.pubkey(), 1), (alice.pubkey(), 2)]);
+    assert!(scenario.send_instruction(twice, &[]).is_err());
+    let create = || create_ix(splitter, &[(alice.pubkey(), 1), (bob.pubkey(), 2)]);
+    scenario.send_instruction(create(), &[]).unwrap();
+    // And the recipients are set once
+    assert!(scenario.send_instruction(create(), &[]).is_err());
+
+    // The unit left over by the split joins the next distribution
+    let distribute = |amount| distribute_ix(splitter, payer.pubkey(), amount);
+    scenario
+        .send_instruction(distribute(10), &[&payer])
+        .unwrap();
+    assert_eq!(balances(&mut scenario, splitter), ([3, 6, 0, 0], 1));
+    scenario.send_instruction(distribute(2), &[&payer]).unwrap();
+    assert_eq!(balances(&mut scenario, splitter), ([4, 8, 0, 0], 0));
+
+    // Only a recipient claims, and only their own balance
+    let stranger = Keypair::new();
+    let claim = claim_ix(splitter, stranger.pubkey());
+    assert!(scenario.send_instruction(claim, &[&stranger]).is_err());
+    scenario
+        .send_instruction(claim_ix(splitter, bob.pubkey()), &[&bob])
+        .unwrap();
+    assert_eq!(balances(&mut scenario, splitter), ([4, 0, 0, 0], 0));
+}
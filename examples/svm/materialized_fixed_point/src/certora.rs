@@ -0,0 +1,3 @@
+//! Formal verification module for the Q64.64 fixed-point library.
+
+pub mod spec;
@@ -0,0 +1,100 @@
+//! This module contains the specification for the Q64.64 fixed-point library.
+//!
+//! Every operation is checked against the exact result computed in
+//! mathematical integers: results are rounded down by less than one unit in
+//! the last place, and are monotonic in each operand.
+
+use crate::Q64;
+use cvlr::{mathint::NativeInt, prelude::*};
+
+/// `2^64`, the raw representation of one.
+fn fv_one() -> NativeInt {
+    NativeInt::from(u64::MAX) + 1u64
+}
+
+/// The raw representation of `x` as a mathematical integer.
+fn fv_bits(x: Q64) -> NativeInt {
+    x.to_bits().into()
+}
+
+/// A nondeterministic fixed-point value.
+fn nondet_q64() -> Q64 {
+    Q64::from_bits(nondet())
+}
+
+/// Verifies that `checked_mul` rounds down by less than 2^-64:
+/// result * 2^64 <= a * b < (result + 1) * 2^64.
+#[rule]
+pub fn rule_mul_error_bound() {
+    let (a, b) = (nondet_q64(), nondet_q64());
+    let result = a.checked_mul(b);
+    cvlr_assume!(result.is_some());
+    let result = fv_bits(result.unwrap());
+
+    let exact = fv_bits(a) * fv_bits(b);
+    cvlr_assert!(result * fv_one() <= exact);
+    cvlr_assert!(exact < (result + 1u64) * fv_one());
+}
+
+/// Verifies that `checked_mul` is monotonic in its first operand.
+#[rule]
+pub fn rule_mul_monotonic() {
+    let (a, b, c) = (nondet_q64(), nondet_q64(), nondet_q64());
+    cvlr_assume!(a <= b);
+    let (lo, hi) = (a.checked_mul(c), b.checked_mul(c));
+    cvlr_assume!(hi.is_some());
+    cvlr_assert!(lo.is_some());
+    cvlr_assert!(lo.unwrap() <= hi.unwrap());
+}
+
+/// Verifies that `checked_div` rounds down by less than 2^-64:
+/// result * b <= a * 2^64 < (result + 1) * b.
+#[rule]
+pub fn rule_div_error_bound() {
+    let (a, b) = (nondet_q64(), nondet_q64());
+    let result = a.checked_div(b);
+    cvlr_assume!(result.is_some());
+    let result = fv_bits(result.unwrap());
+
+    let exact = fv_bits(a) * fv_one();
+    cvlr_assert!(result * fv_bits(b) <= exact);
+    cvlr_assert!(exact < (result + 1u64) * fv_bits(b));
+}
+
+/// Verifies that `checked_div` is monotonically increasing in the dividend
+/// and decreasing in the divisor.
+#[rule]
+pub fn rule_div_monotonic() {
+    let (a, b, c) = (nondet_q64(), nondet_q64(), nondet_q64());
+    cvlr_assume!(a <= b);
+
+    let (lo, hi) = (a.checked_div(c), b.checked_div(c));
+    cvlr_assume!(hi.is_some());
+    cvlr_assert!(lo.is_some());
+    cvlr_assert!(lo.unwrap() <= hi.unwrap());
+
+    let (small, large) = (c.checked_div(b), c.checked_div(a));
+    cvlr_assume!(large.is_some());
+    cvlr_assert!(small.is_some());
+    cvlr_assert!(small.unwrap() <= large.unwrap());
+}
+
+/// Verifies that `sqrt` rounds down by less than 2^-64:
+/// result^2 <= x * 2^64 < (result + 1)^2.
+#[rule]
+pub fn rule_sqrt_error_bound() {
+    let x = nondet_q64();
+    let result = fv_bits(x.sqrt());
+
+    let exact = fv_bits(x) * fv_one();
+    cvlr_assert!(result * result <= exact);
+    cvlr_assert!(exact < (result + 1u64) * (result + 1u64));
+}
+
+/// Verifies that `sqrt` is monotonic.
+#[rule]
+pub fn rule_sqrt_monotonic() {
+    let (a, b) = (nondet_q64(), nondet_q64());
+    cvlr_assume!(a <= b);
+    cvlr_assert!(a.sqrt() <= b.sqrt());
+}
@@ -0,0 +1,12 @@
+//! Unsigned Q64.64 fixed-point arithmetic for on-chain programs.
+//!
+//! Every operation rounds down, so results are always within one unit in the
+//! last place (2^-64) below the exact value.
+
+pub mod q64;
+
+pub use q64::Q64;
+
+// Include formal verification module only if certora feature is enabled.
+#[cfg(feature = "certora")]
+pub mod certora;
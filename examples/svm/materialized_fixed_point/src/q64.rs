@@ -0,0 +1,156 @@
+/// Number of fractional bits.
+pub const FRAC_BITS: u32 = 64;
+
+/// An unsigned Q64.64 fixed-point number: 64 integer bits followed by 64
+/// fractional bits, stored as the raw `u128` value `x * 2^64`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Q64(u128);
+
+impl Q64 {
+    /// Zero.
+    pub const ZERO: Q64 = Q64(0);
+    /// One.
+    pub const ONE: Q64 = Q64(1 << FRAC_BITS);
+    /// The largest representable value, `2^64 - 2^-64`.
+    pub const MAX: Q64 = Q64(u128::MAX);
+
+    /// Creates a value from its raw representation `x * 2^64`.
+    pub const fn from_bits(bits: u128) -> Q64 {
+        Q64(bits)
+    }
+
+    /// Returns the raw representation `x * 2^64`.
+    pub const fn to_bits(self) -> u128 {
+        self.0
+    }
+
+    /// Creates a value from an integer.
+    pub const fn from_int(value: u64) -> Q64 {
+        Q64((value as u128) << FRAC_BITS)
+    }
+
+    /// Returns the integer part, rounded down.
+    pub const fn floor(self) -> u64 {
+        (self.0 >> FRAC_BITS) as u64
+    }
+
+    /// Multiplies two values, rounding down.
+    /// Returns `None` if the result does not fit 64 integer bits.
+    pub fn checked_mul(self, rhs: Q64) -> Option<Q64> {
+        // Split both operands into 64-bit halves so that every partial product
+        // fits a u128: a * b / 2^64 = ah*bh*2^64 + ah*bl + al*bh + al*bl/2^64
+        let (ah, al) = (self.0 >> FRAC_BITS, self.0 as u64 as u128);
+        let (bh, bl) = (rhs.0 >> FRAC_BITS, rhs.0 as u64 as u128);
+        let high = (ah * bh).checked_mul(1 << FRAC_BITS)?;
+        let bits = high
+            .checked_add(ah * bl)?
+            .checked_add(al * bh)?
+            .checked_add((al * bl) >> FRAC_BITS)?;
+        Some(Q64(bits))
+    }
+
+    /// Divides two values, rounding down.
+    /// Returns `None` on division by zero or if the result does not fit 64
+    /// integer bits.
+    pub fn checked_div(self, rhs: Q64) -> Option<Q64> {
+        if rhs.0 == 0 {
+            return None;
+        }
+        // Integer part of the quotient, then the 64 fractional bits by
+        // schoolbook long division of the remainder
+        let quotient = self.0 / rhs.0;
+        let mut remainder = self.0 % rhs.0;
+        let mut fraction: u128 = 0;
+        for _ in 0..FRAC_BITS {
+            // remainder < rhs, so doubling it may carry out of the u128
+            let carry = remainder >> 127;
+            remainder <<= 1;
+            fraction <<= 1;
+            if carry == 1 || remainder >= rhs.0 {
+                remainder = remainder.wrapping_sub(rhs.0);
+                fraction |= 1;
+            }
+        }
+        let bits = quotient
+            .checked_mul(1 << FRAC_BITS)?
+            .checked_add(fraction)?;
+        Some(Q64(bits))
+    }
+
+    /// Square root, rounded down. Never overflows.
+    pub fn sqrt(self) -> Q64 {
+        // sqrt(x) * 2^64 = sqrt(bits * 2^64): take the integer square root of
+        // the 192-bit value `bits << 64`, two bits at a time from the top
+        let mut remainder: u128 = 0;
+        let mut root: u128 = 0;
+        for pair in (0..96).rev() {
+            let shift = 2 * pair;
+            let digits = if shift >= FRAC_BITS {
+                (self.0 >> (shift - FRAC_BITS)) & 0b11
+            } else {
+                0
+            };
+            // remainder <= 2 * root < 2^97, so the shift cannot overflow
+            remainder = (remainder << 2) | digits;
+            let trial = (root << 2) | 1;
+            root <<= 1;
+            if remainder >= trial {
+                remainder -= trial;
+                root |= 1;
+            }
+        }
+        Q64(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mul_exact_and_rounding() {
+        let half = Q64::ONE.checked_div(Q64::from_int(2)).unwrap();
+        assert_eq!(
+            Q64::from_int(3).checked_mul(Q64::from_int(7)),
+            Some(Q64::from_int(21))
+        );
+        assert_eq!(half.checked_mul(half).unwrap().to_bits(), 1 << 62);
+        // 2^-64 * 2^-64 rounds down to zero
+        assert_eq!(
+            Q64::from_bits(1).checked_mul(Q64::from_bits(1)),
+            Some(Q64::ZERO)
+        );
+        assert_eq!(Q64::MAX.checked_mul(Q64::from_int(2)), None);
+    }
+
+    #[test]
+    fn test_div_rounding_and_errors() {
+        let third = Q64::ONE.checked_div(Q64::from_int(3)).unwrap();
+        assert_eq!(third.to_bits(), u64::MAX as u128 / 3);
+        assert_eq!(
+            Q64::from_int(21).checked_div(Q64::from_int(7)),
+            Some(Q64::from_int(3))
+        );
+        assert_eq!(Q64::ONE.checked_div(Q64::ZERO), None);
+        assert_eq!(Q64::MAX.checked_div(Q64::from_bits(1)), None);
+        // Divisors above 2^127 exercise the carry of the long division
+        let big = Q64::from_bits((1 << 127) + 1);
+        assert_eq!(big.checked_div(big), Some(Q64::ONE));
+    }
+
+    #[test]
+    fn test_sqrt_bounds() {
+        assert_eq!(Q64::from_int(9).sqrt(), Q64::from_int(3));
+        assert_eq!(Q64::ZERO.sqrt(), Q64::ZERO);
+        let quarter = Q64::from_bits(1 << 62);
+        assert_eq!(quarter.sqrt().to_bits(), 1 << 63);
+        for x in [Q64::from_int(2), Q64::MAX, Q64::from_bits(12345)] {
+            let root = x.sqrt().to_bits();
+            // root^2 <= x * 2^64 < (root + 1)^2, rounded to units of 2^-64
+            let square = Q64::from_bits(root).checked_mul(Q64::from_bits(root));
+            let next_square = Q64::from_bits(root + 1).checked_mul(Q64::from_bits(root + 1));
+            assert!(square.unwrap() <= x);
+            assert!(next_square.is_none_or(|next| next >= x));
+        }
+    }
+}
@@ -0,0 +1,21 @@
+//! Formal verification module for governance voting.
+
+pub mod spec;
+
+/// Macro to assume the tally property holds.
+/// Tally: yes_weight + no_weight == sum of the weights of all cast votes
+#[macro_export]
+macro_rules! assume_tally {
+    ($fv_proposal:expr, $cast_weights:expr) => {
+        cvlr::cvlr_assume!($fv_proposal.yes_weight + $fv_proposal.no_weight == $cast_weights);
+    };
+}
+
+/// Macro to assert the tally property holds.
+/// Tally: yes_weight + no_weight == sum of the weights of all cast votes
+#[macro_export]
+macro_rules! assert_tally {
+    ($fv_proposal:expr, $cast_weights:expr) => {
+        cvlr::cvlr_assert!($fv_proposal.yes_weight + $fv_proposal.no_weight == $cast_weights);
+    };
+}
@@ -0,0 +1,114 @@
+//! This module contains the specification for governance voting.
+
+use crate::{
+    assert_tally, assume_tally,
+    processor::*,
+    state::{Proposal, VoteRecord, Voter},
+};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the proposal state for the formal verification (FV) of
+/// governance voting.
+struct FvProposal {
+    yes_weight: NativeInt,
+    no_weight: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvProposal {
+    fn from(acc_info: &AccountInfo) -> FvProposal {
+        let data = acc_info.data.borrow();
+        let proposal: &Proposal = bytemuck::from_bytes(&data[..]);
+        let yes_weight: u64 = proposal.yes_weight.into();
+        let no_weight: u64 = proposal.no_weight.into();
+        FvProposal {
+            yes_weight: yes_weight.into(),
+            no_weight: no_weight.into(),
+        }
+    }
+}
+
+/// Returns the weight recorded in a vote record.
+fn fv_record_weight(acc_info: &AccountInfo) -> NativeInt {
+    let data = acc_info.data.borrow();
+    let record: &VoteRecord = bytemuck::from_bytes(&data[..]);
+    let weight: u64 = record.weight.into();
+    weight.into()
+}
+
+/// Returns the weight held by a voter account.
+fn fv_voter_weight(acc_info: &AccountInfo) -> NativeInt {
+    let data = acc_info.data.borrow();
+    let voter: &Voter = bytemuck::from_bytes(&data[..]);
+    let weight: u64 = voter.weight.into();
+    weight.into()
+}
+
+/// Verifies that the tallies remain equal to the sum of cast weights after a
+/// vote, and that the vote is counted with the voter's weight.
+#[rule]
+pub fn rule_tally_equals_cast_weights() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let proposal_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let record_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let voter_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    // Sum of the weights of all votes cast before this one
+    let cast_weights: NativeInt = nondet();
+    let fv_proposal_pre: FvProposal = proposal_account.into();
+    assume_tally!(fv_proposal_pre, cast_weights);
+
+    let choice: u8 = nondet();
+    process_cast_vote(&account_infos, &[choice]).unwrap();
+
+    let fv_proposal_post: FvProposal = proposal_account.into();
+    let recorded = fv_record_weight(record_account);
+    cvlr_assert!(recorded == fv_voter_weight(voter_account));
+    assert_tally!(fv_proposal_post, cast_weights + recorded);
+}
+
+/// Verifies that a voter's weight is counted at most once per proposal, be it
+/// with the same vote record or with any other record for the same proposal
+/// and voter.
+#[rule]
+pub fn rule_vote_counted_once() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let proposal_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let record_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let voter_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let voter: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let second_record_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    cvlr_assume!(second_record_account.key != record_account.key);
+
+    let first_choice: u8 = nondet();
+    process_cast_vote(&account_infos[..4], &[first_choice]).unwrap();
+
+    let second_choice: u8 = nondet();
+    let second_accounts = [
+        proposal_account.clone(),
+        second_record_account.clone(),
+        voter_account.clone(),
+        voter.clone(),
+    ];
+    cvlr_assert!(process_cast_vote(&second_accounts, &[second_choice]).is_err());
+    cvlr_assert!(process_cast_vote(&account_infos[..4], &[second_choice]).is_err());
+}
+
+/// Verifies that no vote can be cast once the proposal is finalized.
+#[rule]
+pub fn rule_no_vote_after_finalize() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let proposal_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let _record_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let _voter_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let creator: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    process_finalize(&[proposal_account.clone(), creator.clone()], &[]).unwrap();
+
+    let choice: u8 = nondet();
+    cvlr_assert!(process_cast_vote(&account_infos, &[choice]).is_err());
+}
@@ -0,0 +1,99 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+
+use crate::state::{Proposal, VoteRecord, Voter, VOTE_NO, VOTE_YES};
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// The vote record of `voter` on `proposal`.
+fn record(proposal: Pubkey, voter: Pubkey) -> Pubkey {
+    VoteRecord::address(&proposal, &voter).0
+}
+
+/// Opens a proposal of `creator` with empty tallies.
+pub fn create_proposal_ix(proposal: Pubkey, creator: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(proposal, false),
+            AccountMeta::new_readonly(creator, true),
+        ],
+        data: vec![0u8],
+    }
+}
+
+/// Adds the weight in the `voter_weight` account of `voter` to the yes tally
+/// if `yes` and to the no tally otherwise, recording the vote in the voter's
+/// vote record on `proposal`.
+pub fn cast_vote_ix(
+    proposal: Pubkey,
+    voter_weight: Pubkey,
+    voter: Pubkey,
+    yes: bool,
+) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(proposal, false),
+            AccountMeta::new(record(proposal, voter), false),
+            AccountMeta::new_readonly(voter_weight, false),
+            AccountMeta::new_readonly(voter, true),
+        ],
+        data: vec![1u8, if yes { VOTE_YES } else { VOTE_NO }],
+    }
+}
+
+/// Builds an init-vote-record instruction creating `voter`'s vote record on
+/// `proposal`, paid for by the voter.
+pub fn init_vote_record_ix(proposal: Pubkey, voter: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(proposal, false),
+            AccountMeta::new(record(proposal, voter), false),
+            AccountMeta::new(voter, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: vec![3u8],
+    }
+}
+
+/// Closes voting on the proposal of `creator`.
+pub fn finalize_ix(proposal: Pubkey, creator: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(proposal, false),
+            AccountMeta::new_readonly(creator, true),
+        ],
+        data: vec![2u8],
+    }
+}
+
+/// Decodes the data of a Proposal account.
+pub fn decode_proposal(data: &[u8]) -> Result<Proposal, ProgramError> {
+    data.get(..core::mem::size_of::<Proposal>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a Voter account.
+pub fn decode_voter(data: &[u8]) -> Result<Voter, ProgramError> {
+    data.get(..core::mem::size_of::<Voter>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a VoteRecord account.
+pub fn decode_vote_record(data: &[u8]) -> Result<VoteRecord, ProgramError> {
+    data.get(..core::mem::size_of::<VoteRecord>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
@@ -0,0 +1,235 @@
+use crate::state::{Proposal, VoteRecord, Voter, VOTE_NO, VOTE_YES};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program::invoke_signed,
+    program_error::ProgramError, pubkey::Pubkey, rent::Rent, system_instruction, sysvar::Sysvar,
+};
+
+/// Checks that `record_account` is the vote record of `voter` on the proposal
+/// at `proposal`: the PDA derived from both, owned by the program. Any other
+/// account, e.g. a fresh one per vote, would let the voter count their weight
+/// again.
+fn check_record_account(
+    proposal: &Pubkey,
+    record_account: &AccountInfo,
+    voter: &Pubkey,
+) -> ProgramResult {
+    if *record_account.owner != crate::id() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if *record_account.key != VoteRecord::address(proposal, voter).0 {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+/// Process a create-proposal instruction.
+/// Opens a proposal with empty tallies.
+///
+/// # Arguments
+/// - `accounts`: Account array (proposal account first, signing creator
+///   second)
+/// - `instruction_data`: unused
+pub fn process_create_proposal(
+    accounts: &[AccountInfo],
+    _instruction_data: &[u8],
+) -> ProgramResult {
+    let proposal_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let creator = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !creator.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read proposal data
+    let mut proposal_data = proposal_account.data.borrow_mut();
+    let proposal: &mut Proposal = bytemuck::from_bytes_mut(&mut proposal_data);
+    if *proposal != Proposal::default() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    *proposal = Proposal::new(*creator.key);
+
+    Ok(())
+}
+
+/// Process an init-vote-record instruction.
+/// Creates the vote record of the signing voter on the proposal, at the PDA
+/// derived from the proposal and the voter, who pays for it. Each voter thus
+/// has a single record per proposal.
+///
+/// # Arguments
+/// - `accounts`: Account array (proposal account first, vote record second,
+///   signing voter third, system program fourth)
+/// - `instruction_data`: unused
+pub fn process_init_vote_record(
+    accounts: &[AccountInfo],
+    _instruction_data: &[u8],
+) -> ProgramResult {
+    let proposal_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let record_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let voter = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let system_program = accounts.get(3).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !voter.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *proposal_account.owner != crate::id() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    let (address, bump) = VoteRecord::address(proposal_account.key, voter.key);
+    if *record_account.key != address {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the account, unless it was already
+    if record_account.data_is_empty() {
+        let space = core::mem::size_of::<VoteRecord>();
+        invoke_signed(
+            &system_instruction::create_account(
+                voter.key,
+                &address,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                &crate::id(),
+            ),
+            &[
+                voter.clone(),
+                record_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                VoteRecord::SEED,
+                proposal_account.key.as_ref(),
+                voter.key.as_ref(),
+                &[bump],
+            ]],
+        )?;
+    }
+    check_record_account(proposal_account.key, record_account, voter.key)?;
+
+    // Borrow and write record data
+    let mut record_data = record_account.data.borrow_mut();
+    let record: &mut VoteRecord = bytemuck::from_bytes_mut(&mut record_data);
+    if record.proposal != Pubkey::default() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    *record = VoteRecord {
+        proposal: *proposal_account.key,
+        voter: *voter.key,
+        ..VoteRecord::default()
+    };
+
+    Ok(())
+}
+
+/// Process a cast-vote instruction.
+/// Adds the voter's weight to the chosen tally, at most once per proposal:
+/// the vote is recorded in the voter's record on the proposal, created by
+/// init-vote-record, and the weight is read from a voter account of the
+/// program.
+///
+/// # Arguments
+/// - `accounts`: Account array (proposal account first, vote record second,
+///   voter weight account third, signing voter fourth)
+/// - `instruction_data`: 1 byte vote choice (0 = no, 1 = yes)
+pub fn process_cast_vote(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let choice = *instruction_data
+        .first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    if choice != VOTE_NO && choice != VOTE_YES {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let proposal_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let record_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let voter_account = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let voter = accounts.get(3).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !voter.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if proposal_account.key == record_account.key
+        || proposal_account.key == voter_account.key
+        || record_account.key == voter_account.key
+    {
+        return Err(ProgramError::InvalidArgument);
+    }
+    // Only the program sets voting weights
+    if *voter_account.owner != crate::id() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    check_record_account(proposal_account.key, record_account, voter.key)?;
+
+    // Borrow and read proposal, vote record and voter data
+    let mut proposal_data = proposal_account.data.borrow_mut();
+    let proposal: &mut Proposal = bytemuck::from_bytes_mut(&mut proposal_data);
+    let mut record_data = record_account.data.borrow_mut();
+    let record: &mut VoteRecord = bytemuck::from_bytes_mut(&mut record_data);
+    let voter_data = voter_account.data.borrow();
+    let voter_weight: &Voter = bytemuck::from_bytes(&voter_data);
+
+    if proposal.finalized != 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if voter_weight.owner != *voter.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+    // A cast record means this voter's weight is already in the tallies
+    if record.cast != 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let weight: u64 = voter_weight.weight.into();
+    if choice == VOTE_YES {
+        let yes_weight: u64 = proposal.yes_weight.into();
+        proposal.yes_weight = yes_weight
+            .checked_add(weight)
+            .ok_or(ProgramError::ArithmeticOverflow)?
+            .into();
+    } else {
+        let no_weight: u64 = proposal.no_weight.into();
+        proposal.no_weight = no_weight
+            .checked_add(weight)
+            .ok_or(ProgramError::ArithmeticOverflow)?
+            .into();
+    }
+
+    *record = VoteRecord {
+        proposal: *proposal_account.key,
+        voter: *voter.key,
+        weight: weight.into(),
+        choice,
+        cast: 1,
+    };
+
+    Ok(())
+}
+
+/// Process a finalize instruction.
+/// Closes voting; the proposal passes iff the yes weight exceeds the no weight.
+///
+/// # Arguments
+/// - `accounts`: Account array (proposal account first, signing creator
+///   second)
+/// - `instruction_data`: unused
+pub fn process_finalize(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    let proposal_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let creator = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !creator.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read proposal data
+    let mut proposal_data = proposal_account.data.borrow_mut();
+    let proposal: &mut Proposal = bytemuck::from_bytes_mut(&mut proposal_data);
+    if proposal.creator != *creator.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if proposal.finalized != 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let yes_weight: u64 = proposal.yes_weight.into();
+    let no_weight: u64 = proposal.no_weight.into();
+    proposal.finalized = 1;
+    proposal.passed = (yes_weight > no_weight) as u8;
+
+    Ok(())
+}
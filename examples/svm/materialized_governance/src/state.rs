@@ -0,0 +1,81 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::PodU64;
+
+/// Vote choice: against the proposal.
+pub const VOTE_NO: u8 = 0;
+/// Vote choice: in favour of the proposal.
+pub const VOTE_YES: u8 = 1;
+
+/// The governance proposal account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct Proposal {
+    /// The proposal creator (authority allowed to finalize)
+    pub creator: Pubkey,
+    /// Total weight cast in favour
+    pub yes_weight: PodU64,
+    /// Total weight cast against
+    pub no_weight: PodU64,
+    /// Non-zero once voting is closed
+    pub finalized: u8,
+    /// Non-zero if the proposal passed when finalized
+    pub passed: u8,
+}
+
+impl Proposal {
+    /// Create a new open proposal
+    pub fn new(creator: Pubkey) -> Self {
+        Proposal {
+            creator,
+            yes_weight: PodU64::from(0),
+            no_weight: PodU64::from(0),
+            finalized: 0,
+            passed: 0,
+        }
+    }
+}
+
+/// The voter account data structure, holding the voting weight of a voter.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Voter {
+    /// The voter (authority)
+    pub owner: Pubkey,
+    /// Voting weight
+    pub weight: PodU64,
+}
+
+/// The per-voter, per-proposal vote record account data structure.
+/// Each voter has a single record per proposal, at the PDA of
+/// [`VoteRecord::address`], and its `cast` flag is what prevents double
+/// voting.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct VoteRecord {
+    /// The proposal voted on
+    pub proposal: Pubkey,
+    /// The voter
+    pub voter: Pubkey,
+    /// Weight counted for this vote
+    pub weight: PodU64,
+    /// The vote choice (one of the `VOTE_*` constants)
+    pub choice: u8,
+    /// Non-zero once the vote has been cast
+    pub cast: u8,
+}
+
+impl VoteRecord {
+    /// Seed of the vote record PDA, followed by the proposal and the voter
+    pub const SEED: &'static [u8] = b"vote_record";
+
+    /// The address of the vote record of `voter` on `proposal`, a PDA of the
+    /// program, and its bump seed.
+    pub fn address(proposal: &Pubkey, voter: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[Self::SEED, proposal.as_ref(), voter.as_ref()],
+            &crate::id(),
+        )
+    }
+}
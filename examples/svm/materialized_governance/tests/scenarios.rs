@@ -0,0 +1,100 @@
+//! End-to-end scenarios of the governance program: sequences of instructions
+//! sent to the program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_governance::client::{
+    cast_vote_ix, create_proposal_ix, finalize_ix, init_vote_record_ix,
+};
+use solana_governance::state::{Proposal, VoteRecord, Voter};
+use solana_governance::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, ScenarioBuilder};
+
+/// The voting weight `weight` of `owner`.
+fn voter(owner: &Keypair, weight: u64) -> Voter {
+    Voter {
+        owner: owner.pubkey(),
+        weight: weight.into(),
+    }
+}
+
+#[test]
+fn vote_then_finalize() {
+    let (creator, alice, bob) = (Keypair::new(), Keypair::new(), Keypair::new());
+    // Carol only votes once the proposal is finalized
+    let carol = Keypair::new();
+    let proposal = Pubkey::new_unique();
+    let (alice_weight, bob_weight) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let carol_weight = Pubkey::new_unique();
+    // A record of Alice on the proposal, but not at its PDA
+    let stray_record = Pubkey::new_unique();
+    let mut scenario =
+        ScenarioBuilder::new("solana_governance", id(), processor!(process_instruction))
+            .state(proposal, &Proposal::default())
+            .state(alice_weight, &voter(&alice, 30))
+            .state(bob_weight, &voter(&bob, 20))
+            .state(carol_weight, &voter(&carol, 10))
+            .wallet(alice.pubkey())
+            .wallet(bob.pubkey())
+            .wallet(carol.pubkey())
+            .state(
+                stray_record,
+                &VoteRecord {
+                    proposal,
+                    voter: alice.pubkey(),
+                    ..Default::default()
+                },
+            )
+            .start();
+    let create = create_proposal_ix(proposal, creator.pubkey());
+    scenario.send_instruction(create, &[&creator]).unwrap();
+
+    // Each voter has a single record on the proposal, at its PDA
+    for voter in [&alice, &bob, &carol] {
+        let init = || init_vote_record_ix(proposal, voter.pubkey());
+        scenario.send_instruction(init(), &[voter]).unwrap();
+        assert!(scenario.send_instruction(init(), &[voter]).is_err());
+    }
+    let alice_record = VoteRecord::address(&proposal, &alice.pubkey()).0;
+    let record: VoteRecord = scenario.state(alice_record);
+    assert_eq!((record.proposal, record.voter), (proposal, alice.pubkey()));
+
+    // Bob cannot vote with the weight of Alice
+    let stolen = cast_vote_ix(proposal, alice_weight, bob.pubkey(), false);
+    assert!(scenario.send_instruction(stolen, &[&bob]).is_err());
+    let yes = || cast_vote_ix(proposal, alice_weight, alice.pubkey(), true);
+    scenario.send_instruction(yes(), &[&alice]).unwrap();
+    // Nor is her weight counted twice, be it with her record or another one
+    assert!(scenario.send_instruction(yes(), &[&alice]).is_err());
+    let mut again = yes();
+    again.accounts[1].pubkey = stray_record;
+    assert!(scenario.send_instruction(again, &[&alice]).is_err());
+    let no = cast_vote_ix(proposal, bob_weight, bob.pubkey(), false);
+    scenario.send_instruction(no, &[&bob]).unwrap();
+    let tallies: Proposal = scenario.state(proposal);
+    assert_eq!(
+        (u64::from(tallies.yes_weight), u64::from(tallies.no_weight)),
+        (30, 20)
+    );
+
+    // Only the creator finalizes, once
+    let finalize = |signer: &Keypair| finalize_ix(proposal, signer.pubkey());
+    assert!(scenario
+        .send_instruction(finalize(&alice), &[&alice])
+        .is_err());
+    scenario
+        .send_instruction(finalize(&creator), &[&creator])
+        .unwrap();
+    assert!(scenario
+        .send_instruction(finalize(&creator), &[&creator])
+        .is_err());
+    let result: Proposal = scenario.state(proposal);
+    assert_eq!((result.finalized, result.passed), (1, 1));
+
+    // And no vote is cast on a finalized proposal
+    let late = cast_vote_ix(proposal, carol_weight, carol.pubkey(), true);
+    assert!(scenario.send_instruction(late, &[&carol]).is_err());
+}
@@ -0,0 +1,29 @@
+//! Formal verification module for the lending market.
+
+pub mod spec;
+
+/// Macro to assume the health property holds.
+/// Health: collateral * price * liquidation_threshold_bps
+///         >= debt * PRICE_SCALE * BPS_DENOMINATOR
+#[macro_export]
+macro_rules! assume_healthy {
+    ($fv_market:expr, $fv_position:expr) => {
+        cvlr::cvlr_assume!(
+            $fv_position.collateral * $fv_market.price * $fv_market.liquidation_threshold_bps
+                >= $fv_position.debt * $crate::state::PRICE_SCALE * $crate::state::BPS_DENOMINATOR
+        );
+    };
+}
+
+/// Macro to assert the health property holds.
+/// Health: collateral * price * liquidation_threshold_bps
+///         >= debt * PRICE_SCALE * BPS_DENOMINATOR
+#[macro_export]
+macro_rules! assert_healthy {
+    ($fv_market:expr, $fv_position:expr) => {
+        cvlr::cvlr_assert!(
+            $fv_position.collateral * $fv_market.price * $fv_market.liquidation_threshold_bps
+                >= $fv_position.debt * $crate::state::PRICE_SCALE * $crate::state::BPS_DENOMINATOR
+        );
+    };
+}
@@ -0,0 +1,108 @@
+//! This module contains the specification for the lending market.
+
+use crate::{
+    assert_healthy, assume_healthy,
+    processor::*,
+    state::{Market, Position},
+};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+};
+
+/// Structure tracking the market state for the formal verification (FV) of the
+/// lending market.
+struct FvMarket {
+    price: NativeInt,
+    liquidation_threshold_bps: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvMarket {
+    fn from(acc_info: &AccountInfo) -> FvMarket {
+        let mut data = acc_info.data.borrow_mut();
+        let market: &Market = bytemuck::from_bytes_mut(&mut data[..]);
+        let price: u64 = market.price.into();
+        let liquidation_threshold_bps: u16 = market.liquidation_threshold_bps.into();
+        FvMarket {
+            price: price.into(),
+            liquidation_threshold_bps: (liquidation_threshold_bps as u64).into(),
+        }
+    }
+}
+
+/// Structure tracking the position state for the formal verification (FV) of
+/// the lending market.
+struct FvPosition {
+    collateral: NativeInt,
+    debt: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvPosition {
+    fn from(acc_info: &AccountInfo) -> FvPosition {
+        let mut data = acc_info.data.borrow_mut();
+        let position: &Position = bytemuck::from_bytes_mut(&mut data[..]);
+        let collateral: u64 = position.collateral.into();
+        let debt: u64 = position.debt.into();
+        FvPosition {
+            collateral: collateral.into(),
+            debt: debt.into(),
+        }
+    }
+}
+
+/// Runs `handler` on a healthy position and asserts it is still healthy
+/// afterwards.
+fn check_preserves_health(handler: fn(&[AccountInfo], &[u8]) -> ProgramResult) {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let market_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let position_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_market: FvMarket = market_account.into();
+    let fv_position_pre: FvPosition = position_account.into();
+    assume_healthy!(fv_market, fv_position_pre);
+
+    let amount: u64 = nondet();
+    let instruction_data = &amount.to_le_bytes();
+    handler(&account_infos, instruction_data).unwrap();
+
+    let fv_position_post: FvPosition = position_account.into();
+    assert_healthy!(fv_market, fv_position_post);
+}
+
+/// Verifies that depositing collateral keeps a healthy position healthy.
+#[rule]
+pub fn rule_healthy_deposit_collateral() {
+    check_preserves_health(process_deposit_collateral);
+}
+
+/// Verifies that borrowing keeps a healthy position healthy.
+#[rule]
+pub fn rule_healthy_borrow() {
+    check_preserves_health(process_borrow);
+}
+
+/// Verifies that repaying keeps a healthy position healthy.
+#[rule]
+pub fn rule_healthy_repay() {
+    check_preserves_health(process_repay);
+}
+
+/// Verifies that liquidation only applies to unhealthy positions.
+#[rule]
+pub fn rule_liquidate_only_unhealthy() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let market_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let position_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_market: FvMarket = market_account.into();
+    let fv_position: FvPosition = position_account.into();
+    assume_healthy!(fv_market, fv_position);
+
+    let amount: u64 = nondet();
+    let liquidate_instruction_data = &amount.to_le_bytes();
+    cvlr_assert!(process_liquidate(&account_infos, liquidate_instruction_data).is_err());
+}
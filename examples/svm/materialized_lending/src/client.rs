@@ -0,0 +1,81 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::{Market, Position};
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// The instruction `discriminant` with the argument `amount` over the
+/// accounts every instruction takes: the market, the position and the
+/// signer.
+fn instruction(
+    discriminant: u8,
+    market: Pubkey,
+    position: Pubkey,
+    signer: Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![discriminant];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(market, false),
+            AccountMeta::new(position, false),
+            AccountMeta::new_readonly(signer, true),
+        ],
+        data,
+    }
+}
+
+/// Adds collateral to the position of `owner`.
+pub fn deposit_collateral_ix(
+    market: Pubkey,
+    position: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+) -> Instruction {
+    instruction(0, market, position, owner, amount)
+}
+
+/// Borrows against the position of `owner`, within the market's
+/// loan-to-value ratio.
+pub fn borrow_ix(market: Pubkey, position: Pubkey, owner: Pubkey, amount: u64) -> Instruction {
+    instruction(1, market, position, owner, amount)
+}
+
+/// Repays debt of the position on behalf of its owner.
+pub fn repay_ix(market: Pubkey, position: Pubkey, payer: Pubkey, amount: u64) -> Instruction {
+    instruction(2, market, position, payer, amount)
+}
+
+/// Repays debt of an unhealthy position in exchange for its collateral plus
+/// a bonus.
+pub fn liquidate_ix(
+    market: Pubkey,
+    position: Pubkey,
+    liquidator: Pubkey,
+    amount: u64,
+) -> Instruction {
+    instruction(3, market, position, liquidator, amount)
+}
+
+/// Decodes the data of a Market account.
+pub fn decode_market(data: &[u8]) -> Result<Market, ProgramError> {
+    data.get(..core::mem::size_of::<Market>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a Position account.
+pub fn decode_position(data: &[u8]) -> Result<Position, ProgramError> {
+    data.get(..core::mem::size_of::<Position>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
@@ -0,0 +1,271 @@
+use crate::state::{Market, Position, BPS_DENOMINATOR, LIQUIDATION_BONUS_BPS, PRICE_SCALE};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+use svm_vault_math::mul_div_floor;
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Returns true iff `debt` is at most `ratio_bps` of the collateral value,
+/// i.e. `collateral * price / PRICE_SCALE * ratio_bps / BPS >= debt`, with
+/// the value and its share rounded down so rounding never lends more.
+pub fn within_ratio(collateral: u64, debt: u64, price: u64, ratio_bps: u16) -> bool {
+    let limit = mul_div_floor(collateral, price, PRICE_SCALE)
+        .and_then(|value| mul_div_floor(value, ratio_bps.into(), BPS_DENOMINATOR));
+    match limit {
+        Some(limit) => debt <= limit,
+        // A limit that does not fit a u64 is necessarily larger than any debt
+        None => true,
+    }
+}
+
+/// Returns true iff the position is at or above the liquidation threshold.
+pub fn is_healthy(market: &Market, position: &Position) -> bool {
+    within_ratio(
+        position.collateral.into(),
+        position.debt.into(),
+        market.price.into(),
+        market.liquidation_threshold_bps.into(),
+    )
+}
+
+/// Collateral seized for repaying `repaid` debt, including the liquidation
+/// bonus and capped by the position's collateral. Both are rounded down, in
+/// favour of the position.
+pub fn collateral_to_seize(repaid: u64, price: u64, collateral: u64) -> u64 {
+    // At a price of zero, or beyond a u64, the repayment is worth more than
+    // any collateral
+    mul_div_floor(repaid, PRICE_SCALE, price)
+        .and_then(|value| {
+            mul_div_floor(
+                value,
+                BPS_DENOMINATOR + LIQUIDATION_BONUS_BPS,
+                BPS_DENOMINATOR,
+            )
+        })
+        .map_or(collateral, |seized| seized.min(collateral))
+}
+
+/// Validates the common account layout: market first, position second (which
+/// must belong to the market) and a signer third.
+fn market_position_signer<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+) -> Result<[&'a AccountInfo<'b>; 3], ProgramError> {
+    let market_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let position_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let signer = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if market_account.key == position_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if !signer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok([market_account, position_account, signer])
+}
+
+/// Process a deposit-collateral instruction.
+/// Adds collateral to the position.
+///
+/// # Arguments
+/// - `accounts`: Account array (market account first, position account
+///   second, signing position owner third)
+/// - `instruction_data`: 8 bytes representing the collateral to deposit
+pub fn process_deposit_collateral(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let [market_account, position_account, owner] = market_position_signer(accounts)?;
+
+    // Borrow and read market and position data
+    let mut market_data = market_account.data.borrow_mut();
+    let market: &mut Market = bytemuck::from_bytes_mut(&mut market_data);
+    let mut position_data = position_account.data.borrow_mut();
+    let position: &mut Position = bytemuck::from_bytes_mut(&mut position_data);
+
+    if position.market != *market_account.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if position.owner != *owner.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let collateral: u64 = position.collateral.into();
+    let total_collateral: u64 = market.total_collateral.into();
+    position.collateral = collateral
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    market.total_collateral = total_collateral
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+
+    Ok(())
+}
+
+/// Process a borrow instruction.
+/// Increases the position's debt, as long as it stays within the market's
+/// loan-to-value ratio.
+///
+/// # Arguments
+/// - `accounts`: Account array (market account first, position account
+///   second, signing position owner third)
+/// - `instruction_data`: 8 bytes representing the amount to borrow
+pub fn process_borrow(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let [market_account, position_account, owner] = market_position_signer(accounts)?;
+
+    // Borrow and read market and position data
+    let mut market_data = market_account.data.borrow_mut();
+    let market: &mut Market = bytemuck::from_bytes_mut(&mut market_data);
+    let mut position_data = position_account.data.borrow_mut();
+    let position: &mut Position = bytemuck::from_bytes_mut(&mut position_data);
+
+    if position.market != *market_account.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if position.owner != *owner.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+    // A borrowing limit above the liquidation threshold would allow opening
+    // positions that are liquidatable immediately
+    let ltv_bps: u16 = market.ltv_bps.into();
+    let liquidation_threshold_bps: u16 = market.liquidation_threshold_bps.into();
+    if ltv_bps > liquidation_threshold_bps {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let debt: u64 = position.debt.into();
+    let new_debt = debt
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if !within_ratio(
+        position.collateral.into(),
+        new_debt,
+        market.price.into(),
+        ltv_bps,
+    ) {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    let total_debt: u64 = market.total_debt.into();
+    market.total_debt = total_debt
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    position.debt = new_debt.into();
+
+    Ok(())
+}
+
+/// Process a repay instruction.
+/// Decreases the position's debt. Anyone may repay on behalf of the owner.
+///
+/// # Arguments
+/// - `accounts`: Account array (market account first, position account
+///   second, signing payer third)
+/// - `instruction_data`: 8 bytes representing the amount to repay
+pub fn process_repay(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let [market_account, position_account, _payer] = market_position_signer(accounts)?;
+
+    // Borrow and read market and position data
+    let mut market_data = market_account.data.borrow_mut();
+    let market: &mut Market = bytemuck::from_bytes_mut(&mut market_data);
+    let mut position_data = position_account.data.borrow_mut();
+    let position: &mut Position = bytemuck::from_bytes_mut(&mut position_data);
+
+    if position.market != *market_account.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let debt: u64 = position.debt.into();
+    let total_debt: u64 = market.total_debt.into();
+    if amount > debt {
+        return Err(ProgramError::InvalidArgument);
+    }
+    position.debt = (debt - amount).into();
+    market.total_debt = total_debt.saturating_sub(amount).into();
+
+    Ok(())
+}
+
+/// Process a liquidate instruction.
+/// Repays debt of an unhealthy position in exchange for its collateral plus a
+/// bonus. Healthy positions cannot be liquidated.
+///
+/// # Arguments
+/// - `accounts`: Account array (market account first, position account
+///   second, signing liquidator third)
+/// - `instruction_data`: 8 bytes representing the debt to repay
+pub fn process_liquidate(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let [market_account, position_account, _liquidator] = market_position_signer(accounts)?;
+
+    // Borrow and read market and position data
+    let mut market_data = market_account.data.borrow_mut();
+    let market: &mut Market = bytemuck::from_bytes_mut(&mut market_data);
+    let mut position_data = position_account.data.borrow_mut();
+    let position: &mut Position = bytemuck::from_bytes_mut(&mut position_data);
+
+    if position.market != *market_account.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if is_healthy(market, position) {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let debt: u64 = position.debt.into();
+    let collateral: u64 = position.collateral.into();
+    if amount == 0 || amount > debt {
+        return Err(ProgramError::InvalidArgument);
+    }
+    let seized = collateral_to_seize(amount, market.price.into(), collateral);
+
+    let total_debt: u64 = market.total_debt.into();
+    let total_collateral: u64 = market.total_collateral.into();
+    position.debt = (debt - amount).into();
+    position.collateral = (collateral - seized).into();
+    market.total_debt = total_debt.saturating_sub(amount).into();
+    market.total_collateral = total_collateral.saturating_sub(seized).into();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_within_ratio() {
+        // 100 collateral at price 2.0 is worth 200; 80% of that is 160
+        let price = 2 * PRICE_SCALE;
+        assert!(within_ratio(100, 160, price, 8_000));
+        assert!(!within_ratio(100, 161, price, 8_000));
+        assert!(within_ratio(0, 0, price, 8_000));
+        assert!(!within_ratio(100, 1, 0, 8_000));
+        assert!(within_ratio(u64::MAX, u64::MAX, u64::MAX, u16::MAX));
+        // 3 collateral at price 0.5 is worth 1.5, rounded down to 1, of
+        // which 66.67% rounds down to nothing
+        assert!(!within_ratio(3, 1, PRICE_SCALE / 2, 6_667));
+    }
+
+    #[test]
+    fn test_collateral_to_seize_bonus_and_cap() {
+        let price = 2 * PRICE_SCALE;
+        // 100 debt buys 50 collateral, plus a 5% bonus
+        assert_eq!(collateral_to_seize(100, price, 1_000), 52);
+        assert_eq!(collateral_to_seize(100, price, 10), 10);
+        assert_eq!(collateral_to_seize(100, 0, 10), 10);
+        assert_eq!(collateral_to_seize(u64::MAX, 1, 10), 10);
+    }
+}
@@ -0,0 +1,73 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::{PodU16, PodU64};
+
+/// Fixed-point scaling factor of the collateral price.
+pub const PRICE_SCALE: u64 = 1_000_000;
+
+/// Denominator for basis-point arithmetic.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Extra collateral a liquidator seizes on top of the repaid debt, in basis
+/// points.
+pub const LIQUIDATION_BONUS_BPS: u64 = 500;
+
+/// The lending market account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Market {
+    /// The market owner (authority)
+    pub owner: Pubkey,
+    /// Price of one collateral unit in debt units, scaled by `PRICE_SCALE`
+    pub price: PodU64,
+    /// Maximum debt-to-collateral-value ratio allowed when borrowing, in bps
+    pub ltv_bps: PodU16,
+    /// Debt-to-collateral-value ratio above which a position is liquidatable,
+    /// in bps
+    pub liquidation_threshold_bps: PodU16,
+    /// Total collateral deposited across all positions
+    pub total_collateral: PodU64,
+    /// Total debt outstanding across all positions
+    pub total_debt: PodU64,
+}
+
+impl Market {
+    /// Create a new market with the given owner, price and risk parameters
+    pub fn new(owner: Pubkey, price: u64, ltv_bps: u16, liquidation_threshold_bps: u16) -> Self {
+        Market {
+            owner,
+            price: PodU64::from(price),
+            ltv_bps: PodU16::from(ltv_bps),
+            liquidation_threshold_bps: PodU16::from(liquidation_threshold_bps),
+            total_collateral: PodU64::from(0),
+            total_debt: PodU64::from(0),
+        }
+    }
+}
+
+/// The borrower position account data structure.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Position {
+    /// The borrower (authority)
+    pub owner: Pubkey,
+    /// The market this position belongs to
+    pub market: Pubkey,
+    /// Collateral deposited
+    pub collateral: PodU64,
+    /// Debt owed
+    pub debt: PodU64,
+}
+
+impl Position {
+    /// Create a new, empty position for `owner` in `market`
+    pub fn new(owner: Pubkey, market: Pubkey) -> Self {
+        Position {
+            owner,
+            market,
+            collateral: PodU64::from(0),
+            debt: PodU64::from(0),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! End-to-end scenarios of the lending market: sequences of instructions
+//! sent to the program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_lending::client::{borrow_ix, deposit_collateral_ix, liquidate_ix, repay_ix};
+use solana_lending::state::{Market, Position, PRICE_SCALE};
+use solana_lending::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, Scenario, ScenarioBuilder};
+
+/// A market at a price of 1, lending up to 75% of the collateral and
+/// liquidating above 80%.
+fn market() -> Market {
+    Market::new(Pubkey::new_unique(), PRICE_SCALE, 7_500, 8_000)
+}
+
+/// The collateral and the debt of the position at `key`.
+fn balances(scenario: &mut Scenario, key: Pubkey) -> (u64, u64) {
+    let position: Position = scenario.state(key);
+    (position.collateral.into(), position.debt.into())
+}
+
+/// The collateral and debt totals of the market at `key`.
+fn totals(scenario: &mut Scenario, key: Pubkey) -> (u64, u64) {
+    let market: Market = scenario.state(key);
+    (market.total_collateral.into(), market.total_debt.into())
+}
+
+#[test]
+fn deposit_borrow_repay() {
+    let (alice, bob) = (Keypair::new(), Keypair::new());
+    let (market_key, position) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut scenario =
+        ScenarioBuilder::new("solana_lending", id(), processor!(process_instruction))
+            .state(market_key, &market())
+            .state(position, &Position::new(alice.pubkey(), market_key))
+            .start();
+
+    // Only the owner adds collateral and borrows against it
+    let deposit =
+        |owner: &Keypair| deposit_collateral_ix(market_key, position, owner.pubkey(), 100);
+    assert!(scenario.send_instruction(deposit(&bob), &[&bob]).is_err());
+    scenario
+        .send_instruction(deposit(&alice), &[&alice])
+        .unwrap();
+    let borrow = |owner: &Keypair, amount| borrow_ix(market_key, position, owner.pubkey(), amount);
+    assert!(scenario
+        .send_instruction(borrow(&bob, 10), &[&bob])
+        .is_err());
+    scenario
+        .send_instruction(borrow(&alice, 75), &[&alice])
+        .unwrap();
+    // Up to the loan to value
+    assert!(scenario
+        .send_instruction(borrow(&alice, 1), &[&alice])
+        .is_err());
+    assert_eq!(balances(&mut scenario, position), (100, 75));
+
+    // A healthy position is not liquidated
+    let liquidate = liquidate_ix(market_key, position, bob.pubkey(), 10);
+    assert!(scenario.send_instruction(liquidate, &[&bob]).is_err());
+
+    // Anyone repays, but no more than the debt
+    let repay = |amount| repay_ix(market_key, position, bob.pubkey(), amount);
+    assert!(scenario.send_instruction(repay(76), &[&bob]).is_err());
+    scenario.send_instruction(repay(25), &[&bob]).unwrap();
+    assert_eq!(balances(&mut scenario, position), (100, 50));
+    assert_eq!(totals(&mut scenario, market_key), (100, 50));
+}
+
+#[test]
+fn unhealthy_position_is_liquidated_with_a_bonus() {
+    let (alice, liquidator) = (Keypair::new(), Keypair::new());
+    let (market_key, position) = (Pubkey::new_unique(), Pubkey::new_unique());
+    // A position left at 90% by a fall of the price
+    let state = Market {
+        total_collateral: 100.into(),
+        total_debt: 90.into(),
+        ..market()
+    };
+    let unhealthy = Position {
+        collateral: 100.into(),
+        debt: 90.into(),
+        ..Position::new(alice.pubkey(), market_key)
+    };
+    let mut scenario =
+        ScenarioBuilder::new("solana_lending", id(), processor!(process_instruction))
+            .state(market_key, &state)
+            .state(position, &unhealthy)
+            .start();
+
+    // No more than the debt is repaid
+    let liquidate = |amount| liquidate_ix(market_key, position, liquidator.pubkey(), amount);
+    assert!(scenario
+        .send_instruction(liquidate(91), &[&liquidator])
+        .is_err());
+    // Repaying 40 seizes 40 of collateral and a bonus of 5%
+    scenario
+        .send_instruction(liquidate(40), &[&liquidator])
+        .unwrap();
+    assert_eq!(balances(&mut scenario, position), (58, 50));
+    assert_eq!(totals(&mut scenario, market_key), (58, 50));
+}
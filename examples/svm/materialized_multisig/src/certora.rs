@@ -0,0 +1,13 @@
+//! Formal verification module for the multisig.
+
+pub mod spec;
+
+/// Macro to assert the threshold property holds.
+/// Threshold: 1 <= threshold <= approval_count
+#[macro_export]
+macro_rules! assert_threshold_met {
+    ($fv_multisig:expr, $fv_proposal:expr) => {
+        cvlr::cvlr_assert!($fv_multisig.threshold >= 1);
+        cvlr::cvlr_assert!($fv_proposal.approval_count >= $fv_multisig.threshold);
+    };
+}
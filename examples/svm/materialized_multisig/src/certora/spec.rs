@@ -0,0 +1,90 @@
+//! This module contains the specification for the multisig application.
+
+use crate::{
+    assert_threshold_met,
+    processor::*,
+    state::{Multisig, Proposal},
+};
+use cvlr::prelude::*;
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the multisig state for the formal verification (FV) of
+/// the multisig.
+struct FvMultisig {
+    threshold: u8,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvMultisig {
+    fn from(acc_info: &AccountInfo) -> FvMultisig {
+        let data = acc_info.data.borrow();
+        let multisig: &Multisig = bytemuck::from_bytes(&data[..]);
+        FvMultisig {
+            threshold: multisig.threshold,
+        }
+    }
+}
+
+/// Structure tracking the proposal state for the formal verification (FV) of
+/// the multisig.
+struct FvProposal {
+    approval_count: u8,
+    executed: bool,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvProposal {
+    fn from(acc_info: &AccountInfo) -> FvProposal {
+        let data = acc_info.data.borrow();
+        let proposal: &Proposal = bytemuck::from_bytes(&data[..]);
+        FvProposal {
+            approval_count: proposal.approval_count(),
+            executed: proposal.executed != 0,
+        }
+    }
+}
+
+/// Verifies that a proposal can only execute with at least M distinct
+/// approvals.
+#[rule]
+pub fn rule_execute_requires_threshold() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let multisig_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let proposal_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_multisig: FvMultisig = multisig_account.into();
+    let fv_proposal_pre: FvProposal = proposal_account.into();
+
+    process_execute(&account_infos, &[]).unwrap();
+
+    let fv_proposal_post: FvProposal = proposal_account.into();
+    cvlr_assert!(!fv_proposal_pre.executed);
+    cvlr_assert!(fv_proposal_post.executed);
+    assert_threshold_met!(fv_multisig, fv_proposal_pre);
+}
+
+/// Verifies that a successful approval adds exactly one distinct approval.
+#[rule]
+pub fn rule_approve_adds_one_approval() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let _multisig_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let proposal_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_proposal_pre: FvProposal = proposal_account.into();
+
+    process_approve(&account_infos, &[]).unwrap();
+
+    let fv_proposal_post: FvProposal = proposal_account.into();
+    cvlr_assert!(fv_proposal_post.approval_count == fv_proposal_pre.approval_count + 1);
+}
+
+/// Verifies that a signer cannot approve the same proposal twice.
+#[rule]
+pub fn rule_signer_cannot_approve_twice() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+
+    process_approve(&account_infos, &[]).unwrap();
+
+    cvlr_assert!(process_approve(&account_infos, &[]).is_err());
+}
@@ -0,0 +1,85 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::{Multisig, Proposal};
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// Initializes a `threshold`-of-N multisig over the N `signers`.
+pub fn create_ix(multisig: Pubkey, threshold: u8, signers: &[Pubkey]) -> Instruction {
+    let mut data = vec![0u8, threshold, signers.len() as u8];
+    for signer in signers {
+        data.extend_from_slice(signer.as_ref());
+    }
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new(multisig, false)],
+        data,
+    }
+}
+
+/// Opens a proposal of the transaction payload hashing to `payload_hash`,
+/// with no approvals.
+pub fn propose_ix(
+    multisig: Pubkey,
+    proposal: Pubkey,
+    proposer: Pubkey,
+    payload_hash: [u8; 32],
+) -> Instruction {
+    let mut data = vec![1u8];
+    data.extend_from_slice(&payload_hash);
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(multisig, false),
+            AccountMeta::new(proposal, false),
+            AccountMeta::new_readonly(proposer, true),
+        ],
+        data,
+    }
+}
+
+/// Records the approval of `approver`, one of the signers.
+pub fn approve_ix(multisig: Pubkey, proposal: Pubkey, approver: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(multisig, false),
+            AccountMeta::new(proposal, false),
+            AccountMeta::new_readonly(approver, true),
+        ],
+        data: vec![2u8],
+    }
+}
+
+/// Marks the proposal executed once enough signers approved it.
+pub fn execute_ix(multisig: Pubkey, proposal: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(multisig, false),
+            AccountMeta::new(proposal, false),
+        ],
+        data: vec![3u8],
+    }
+}
+
+/// Decodes the data of a Multisig account.
+pub fn decode_multisig(data: &[u8]) -> Result<Multisig, ProgramError> {
+    data.get(..core::mem::size_of::<Multisig>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a Proposal account.
+pub fn decode_proposal(data: &[u8]) -> Result<Proposal, ProgramError> {
+    data.get(..core::mem::size_of::<Proposal>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
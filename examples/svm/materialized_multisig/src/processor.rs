@@ -0,0 +1,192 @@
+use crate::state::{Multisig, Proposal, MAX_SIGNERS};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+/// Borrows the multisig and proposal accounts, rejecting aliased accounts.
+fn multisig_and_proposal<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+) -> Result<(&'a AccountInfo<'b>, &'a AccountInfo<'b>), ProgramError> {
+    let multisig_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let proposal_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if multisig_account.key == proposal_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok((multisig_account, proposal_account))
+}
+
+/// Process a create instruction.
+/// Initializes an M-of-N multisig.
+///
+/// # Arguments
+/// - `accounts`: Account array (must have multisig account as first)
+/// - `instruction_data`: 1 byte threshold (M), 1 byte signer count (N),
+///   followed by N 32-byte signer pubkeys
+pub fn process_create(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let (&threshold, rest) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let (&signer_count, keys) = rest
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    if signer_count as usize > MAX_SIGNERS || keys.len() != signer_count as usize * 32 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if threshold == 0 || threshold > signer_count {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Get multisig account
+    let multisig_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // Borrow and read multisig data
+    let mut multisig_data = multisig_account.data.borrow_mut();
+    let multisig: &mut Multisig = bytemuck::from_bytes_mut(&mut multisig_data);
+    if multisig.signer_count != 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let mut signers = [Pubkey::default(); MAX_SIGNERS];
+    for (i, key) in keys.chunks_exact(32).enumerate() {
+        let key = Pubkey::try_from(key).map_err(|_| ProgramError::InvalidInstructionData)?;
+        // Duplicate signers would let one key count twice towards M
+        if signers[..i].contains(&key) {
+            return Err(ProgramError::InvalidArgument);
+        }
+        signers[i] = key;
+    }
+
+    multisig.signers = signers;
+    multisig.signer_count = signer_count;
+    multisig.threshold = threshold;
+
+    Ok(())
+}
+
+/// Process a propose instruction.
+/// Opens a new proposal with no approvals.
+///
+/// # Arguments
+/// - `accounts`: Account array (multisig account first, proposal account
+///   second, signing proposer third)
+/// - `instruction_data`: 32 bytes hash of the proposed transaction payload
+pub fn process_propose(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let payload_hash: [u8; 32] = instruction_data
+        .get(..32)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let (multisig_account, proposal_account) = multisig_and_proposal(accounts)?;
+    let proposer = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !proposer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read multisig and proposal data
+    let multisig_data = multisig_account.data.borrow();
+    let multisig: &Multisig = bytemuck::from_bytes(&multisig_data);
+    let mut proposal_data = proposal_account.data.borrow_mut();
+    let proposal: &mut Proposal = bytemuck::from_bytes_mut(&mut proposal_data);
+
+    if multisig.signer_index(proposer.key).is_none() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if proposal.multisig != Pubkey::default() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    proposal.multisig = *multisig_account.key;
+    proposal.payload_hash = payload_hash;
+    proposal.approvals = 0;
+    proposal.executed = 0;
+
+    Ok(())
+}
+
+/// Process an approve instruction.
+/// Records the approval of one signer; a signer cannot approve twice.
+///
+/// # Arguments
+/// - `accounts`: Account array (multisig account first, proposal account
+///   second, approving signer third)
+/// - `instruction_data`: unused
+pub fn process_approve(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    let (multisig_account, proposal_account) = multisig_and_proposal(accounts)?;
+    let approver = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !approver.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read multisig and proposal data
+    let multisig_data = multisig_account.data.borrow();
+    let multisig: &Multisig = bytemuck::from_bytes(&multisig_data);
+    let mut proposal_data = proposal_account.data.borrow_mut();
+    let proposal: &mut Proposal = bytemuck::from_bytes_mut(&mut proposal_data);
+
+    if proposal.multisig != *multisig_account.key || proposal.executed != 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let index = multisig
+        .signer_index(approver.key)
+        .ok_or(ProgramError::IllegalOwner)?;
+    let bit = 1u8 << index;
+    if proposal.approvals & bit != 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    proposal.approvals |= bit;
+
+    Ok(())
+}
+
+/// Process an execute instruction.
+/// Marks the proposal executed once at least M distinct signers approved.
+///
+/// # Arguments
+/// - `accounts`: Account array (multisig account first, proposal account
+///   second)
+/// - `instruction_data`: unused
+pub fn process_execute(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    let (multisig_account, proposal_account) = multisig_and_proposal(accounts)?;
+
+    // Borrow and read multisig and proposal data
+    let multisig_data = multisig_account.data.borrow();
+    let multisig: &Multisig = bytemuck::from_bytes(&multisig_data);
+    let mut proposal_data = proposal_account.data.borrow_mut();
+    let proposal: &mut Proposal = bytemuck::from_bytes_mut(&mut proposal_data);
+
+    if proposal.multisig != *multisig_account.key || proposal.executed != 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if multisig.threshold == 0 || proposal.approval_count() < multisig.threshold {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    proposal.executed = 1;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approval_count_counts_distinct_bits() {
+        let mut proposal = Proposal::default();
+        assert_eq!(proposal.approval_count(), 0);
+        proposal.approvals = 0b10101;
+        assert_eq!(proposal.approval_count(), 3);
+    }
+
+    #[test]
+    fn test_signer_index_ignores_unused_slots() {
+        let key = Pubkey::new_unique();
+        let mut multisig = Multisig::default();
+        multisig.signers[0] = key;
+        assert_eq!(multisig.signer_index(&key), None);
+        multisig.signer_count = 1;
+        assert_eq!(multisig.signer_index(&key), Some(0));
+        assert_eq!(multisig.signer_index(&Pubkey::default()), None);
+    }
+}
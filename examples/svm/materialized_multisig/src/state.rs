@@ -0,0 +1,54 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+
+/// Maximum number of signers of a multisig.
+pub const MAX_SIGNERS: usize = 5;
+
+/// The multisig account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Multisig {
+    /// The signer set; only the first `signer_count` entries are meaningful
+    pub signers: [Pubkey; MAX_SIGNERS],
+    /// Number of signers (N)
+    pub signer_count: u8,
+    /// Number of distinct approvals required to execute (M)
+    pub threshold: u8,
+}
+
+impl Multisig {
+    /// Returns the index of `key` in the signer set, if it is a signer.
+    pub fn signer_index(&self, key: &Pubkey) -> Option<usize> {
+        self.signers[..(self.signer_count as usize).min(MAX_SIGNERS)]
+            .iter()
+            .position(|signer| signer == key)
+    }
+}
+
+/// The proposed transaction account data structure.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Proposal {
+    /// The multisig this proposal belongs to
+    pub multisig: Pubkey,
+    /// Hash of the proposed transaction payload
+    pub payload_hash: [u8; 32],
+    /// Bitmask of approvals, bit `i` set iff signer `i` approved
+    pub approvals: u8,
+    /// Non-zero once the proposal has been executed
+    pub executed: u8,
+}
+
+impl Proposal {
+    /// Number of distinct signers that approved this proposal.
+    pub fn approval_count(&self) -> u8 {
+        let mut count = 0;
+        for i in 0..MAX_SIGNERS {
+            if self.approvals & (1 << i) != 0 {
+                count += 1;
+            }
+        }
+        count
+    }
+}
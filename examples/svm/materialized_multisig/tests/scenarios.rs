@@ -0,0 +1,70 @@
+//! End-to-end scenarios of the multisig: sequences of instructions sent to
+//! the program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_multisig::client::{approve_ix, create_ix, execute_ix, propose_ix};
+use solana_multisig::state::{Multisig, Proposal};
+use solana_multisig::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, ScenarioBuilder};
+
+#[test]
+fn two_of_three_approvals_execute() {
+    let owners = [Keypair::new(), Keypair::new(), Keypair::new()];
+    let keys: Vec<Pubkey> = owners.iter().map(Keypair::pubkey).collect();
+    let stranger = Keypair::new();
+    let (multisig, proposal) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut scenario =
+        ScenarioBuilder::new("solana_multisig", id(), processor!(process_instruction))
+            .state(multisig, &Multisig::default())
+            .state(proposal, &Proposal::default())
+            .start();
+
+    // A threshold above the number of signers is never reached
+    assert!(scenario
+        .send_instruction(create_ix(multisig, 4, &keys), &[])
+        .is_err());
+    scenario
+        .send_instruction(create_ix(multisig, 2, &keys), &[])
+        .unwrap();
+
+    // Only a signer proposes and approves
+    let propose = |proposer: &Keypair| propose_ix(multisig, proposal, proposer.pubkey(), [7; 32]);
+    assert!(scenario
+        .send_instruction(propose(&stranger), &[&stranger])
+        .is_err());
+    scenario
+        .send_instruction(propose(&owners[0]), &[&owners[0]])
+        .unwrap();
+    let approve = |approver: &Keypair| approve_ix(multisig, proposal, approver.pubkey());
+    assert!(scenario
+        .send_instruction(approve(&stranger), &[&stranger])
+        .is_err());
+    scenario
+        .send_instruction(approve(&owners[1]), &[&owners[1]])
+        .unwrap();
+    // Once each
+    assert!(scenario
+        .send_instruction(approve(&owners[1]), &[&owners[1]])
+        .is_err());
+    assert!(scenario
+        .send_instruction(execute_ix(multisig, proposal), &[])
+        .is_err());
+
+    scenario
+        .send_instruction(approve(&owners[2]), &[&owners[2]])
+        .unwrap();
+    scenario
+        .send_instruction(execute_ix(multisig, proposal), &[])
+        .unwrap();
+    let executed: Proposal = scenario.state(proposal);
+    assert_eq!((executed.approval_count(), executed.executed), (2, 1));
+    assert_eq!(executed.payload_hash, [7; 32]);
+    // And once only
+    assert!(scenario
+        .send_instruction(execute_ix(multisig, proposal), &[])
+        .is_err());
+}
@@ -0,0 +1,3 @@
+//! Formal verification module for the oracle consumer.
+
+pub mod spec;
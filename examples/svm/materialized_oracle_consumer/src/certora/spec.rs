@@ -0,0 +1,112 @@
+//! This module contains the specification for the oracle consumer.
+//!
+//! The slot the program reads is not visible to the rules, but slots only
+//! move forward: reading one in the rule before invoking the program gives a
+//! lower bound on the slot the program observes.
+
+use crate::{
+    processor::*,
+    state::{Consumer, PriceFeed},
+};
+use cvlr::prelude::*;
+use cvlr_solana::{cvlr_deserialize_nondet_accounts, cvt_get_next_clock_slot};
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the consumer state for the formal verification (FV) of
+/// the oracle consumer.
+struct FvConsumer {
+    max_staleness_slots: u64,
+    min_price: u64,
+    max_price: u64,
+    last_price: u64,
+    executions: u64,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvConsumer {
+    fn from(acc_info: &AccountInfo) -> FvConsumer {
+        let mut data = acc_info.data.borrow_mut();
+        let consumer: &Consumer = bytemuck::from_bytes_mut(&mut data[..]);
+        FvConsumer {
+            max_staleness_slots: consumer.max_staleness_slots.into(),
+            min_price: consumer.min_price.into(),
+            max_price: consumer.max_price.into(),
+            last_price: consumer.last_price.into(),
+            executions: consumer.executions.into(),
+        }
+    }
+}
+
+/// Structure tracking the price feed state for the formal verification (FV) of
+/// the oracle consumer.
+struct FvFeed {
+    price: u64,
+    publish_slot: u64,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvFeed {
+    fn from(acc_info: &AccountInfo) -> FvFeed {
+        let mut data = acc_info.data.borrow_mut();
+        let feed: &PriceFeed = bytemuck::from_bytes_mut(&mut data[..]);
+        FvFeed {
+            price: feed.price.into(),
+            publish_slot: feed.publish_slot.into(),
+        }
+    }
+}
+
+/// Verifies that the action is impossible once the price is stale.
+#[rule]
+pub fn rule_execute_stale_fails() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let consumer_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let feed_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_consumer: FvConsumer = consumer_account.into();
+    let fv_feed: FvFeed = feed_account.into();
+    // The program observes a slot after this one
+    let slot = cvt_get_next_clock_slot();
+    cvlr_assume!(slot >= fv_feed.publish_slot);
+    cvlr_assume!(slot - fv_feed.publish_slot > fv_consumer.max_staleness_slots);
+
+    cvlr_assert!(process_execute(&account_infos, &[]).is_err());
+}
+
+/// Verifies that the action is impossible with an out-of-range price.
+#[rule]
+pub fn rule_execute_out_of_range_fails() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let consumer_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let feed_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_consumer: FvConsumer = consumer_account.into();
+    let fv_feed: FvFeed = feed_account.into();
+    cvlr_assume!(fv_feed.price < fv_consumer.min_price || fv_feed.price > fv_consumer.max_price);
+
+    cvlr_assert!(process_execute(&account_infos, &[]).is_err());
+}
+
+/// Verifies that an executed action used the feed's price, which was within
+/// bounds and published no later than the action.
+#[rule]
+pub fn rule_execute_uses_usable_price() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let consumer_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let feed_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_consumer_pre: FvConsumer = consumer_account.into();
+    let fv_feed: FvFeed = feed_account.into();
+
+    process_execute(&account_infos, &[]).unwrap();
+
+    let fv_consumer_post: FvConsumer = consumer_account.into();
+    // The program observed a slot before this one
+    let slot = cvt_get_next_clock_slot();
+    cvlr_assert!(fv_feed.publish_slot < slot);
+    cvlr_assert_eq!(fv_consumer_post.last_price, fv_feed.price);
+    cvlr_assert!(fv_consumer_pre.min_price <= fv_consumer_post.last_price);
+    cvlr_assert!(fv_consumer_post.last_price <= fv_consumer_pre.max_price);
+    cvlr_assert_eq!(fv_consumer_post.executions, fv_consumer_pre.executions + 1);
+}
@@ -0,0 +1,75 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::{Consumer, PriceFeed};
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// Binds the consumer to `feed`, accepting prices from `min_price` to
+/// `max_price` at most `max_staleness_slots` old.
+pub fn initialize_ix(
+    consumer: Pubkey,
+    feed: Pubkey,
+    max_staleness_slots: u64,
+    min_price: u64,
+    max_price: u64,
+) -> Instruction {
+    let mut data = vec![0u8];
+    data.extend_from_slice(&max_staleness_slots.to_le_bytes());
+    data.extend_from_slice(&min_price.to_le_bytes());
+    data.extend_from_slice(&max_price.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(consumer, false),
+            AccountMeta::new_readonly(feed, false),
+        ],
+        data,
+    }
+}
+
+/// Publishes `price` at the current slot.
+pub fn publish_ix(feed: Pubkey, authority: Pubkey, price: u64) -> Instruction {
+    let mut data = vec![1u8];
+    data.extend_from_slice(&price.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(feed, false),
+            AccountMeta::new_readonly(authority, true),
+        ],
+        data,
+    }
+}
+
+/// Performs the gated action at the feed's price.
+pub fn execute_ix(consumer: Pubkey, feed: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(consumer, false),
+            AccountMeta::new_readonly(feed, false),
+        ],
+        data: vec![2u8],
+    }
+}
+
+/// Decodes the data of a PriceFeed account.
+pub fn decode_price_feed(data: &[u8]) -> Result<PriceFeed, ProgramError> {
+    data.get(..core::mem::size_of::<PriceFeed>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a Consumer account.
+pub fn decode_consumer(data: &[u8]) -> Result<Consumer, ProgramError> {
+    data.get(..core::mem::size_of::<Consumer>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
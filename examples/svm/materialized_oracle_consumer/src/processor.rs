@@ -0,0 +1,181 @@
+use crate::state::{Consumer, PriceFeed};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use svm_fv_support::cpi;
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Returns the feed's price if it is usable by `consumer` at `slot`: published
+/// at most `max_staleness_slots` ago and within `[min_price, max_price]`.
+pub fn usable_price(consumer: &Consumer, feed: &PriceFeed, slot: u64) -> Result<u64, ProgramError> {
+    let publish_slot: u64 = feed.publish_slot.into();
+    // A price from the future could only come from a corrupted feed
+    let age = slot
+        .checked_sub(publish_slot)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    if age > consumer.max_staleness_slots.into() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let price: u64 = feed.price.into();
+    if price < consumer.min_price.into() || price > consumer.max_price.into() {
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(price)
+}
+
+/// Process an initialize instruction.
+/// Binds the consumer to a price feed with staleness and price bounds.
+///
+/// # Arguments
+/// - `accounts`: Account array (consumer account first, price feed second)
+/// - `instruction_data`: 8 bytes each for the maximum staleness in slots, the
+///   minimum price and the maximum price
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let max_staleness_slots = read_u64(instruction_data, 0)?;
+    let min_price = read_u64(instruction_data, 8)?;
+    let max_price = read_u64(instruction_data, 16)?;
+    if min_price > max_price {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let consumer_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let feed_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // Borrow and read consumer data
+    let mut consumer_data = consumer_account.data.borrow_mut();
+    let consumer: &mut Consumer = bytemuck::from_bytes_mut(&mut consumer_data);
+    if consumer.feed != Pubkey::default() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    *consumer = Consumer {
+        feed: *feed_account.key,
+        max_staleness_slots: max_staleness_slots.into(),
+        min_price: min_price.into(),
+        max_price: max_price.into(),
+        ..Default::default()
+    };
+
+    Ok(())
+}
+
+/// Process a publish instruction.
+/// Publishes a new price at the current slot.
+///
+/// # Arguments
+/// - `accounts`: Account array (price feed first, signing feed authority
+///   second)
+/// - `instruction_data`: 8 bytes representing the price
+pub fn process_publish(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let price = read_u64(instruction_data, 0)?;
+
+    let feed_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let authority = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read feed data
+    let mut feed_data = feed_account.data.borrow_mut();
+    let feed: &mut PriceFeed = bytemuck::from_bytes_mut(&mut feed_data);
+    // The first publisher claims the feed
+    if feed.authority == Pubkey::default() {
+        feed.authority = *authority.key;
+    } else if feed.authority != *authority.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    feed.price = price.into();
+    feed.publish_slot = cpi::sysvar::slot()?.into();
+
+    Ok(())
+}
+
+/// Process an execute instruction.
+/// Performs the gated action at the feed's price, which must be fresh and
+/// within bounds.
+///
+/// # Arguments
+/// - `accounts`: Account array (consumer account first, price feed second)
+/// - `instruction_data`: unused
+pub fn process_execute(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    let consumer_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let feed_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if consumer_account.key == feed_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Borrow and read consumer and feed data
+    let mut consumer_data = consumer_account.data.borrow_mut();
+    let consumer: &mut Consumer = bytemuck::from_bytes_mut(&mut consumer_data);
+    let feed_data = feed_account.data.borrow();
+    let feed: &PriceFeed = bytemuck::from_bytes(&feed_data);
+    if consumer.feed != *feed_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let price = usable_price(consumer, feed, cpi::sysvar::slot()?)?;
+    let executions: u64 = consumer.executions.into();
+    consumer.last_price = price.into();
+    consumer.executions = executions
+        .checked_add(1)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn consumer() -> Consumer {
+        Consumer {
+            max_staleness_slots: 10.into(),
+            min_price: 100.into(),
+            max_price: 200.into(),
+            ..Default::default()
+        }
+    }
+
+    fn feed(price: u64, publish_slot: u64) -> PriceFeed {
+        PriceFeed {
+            price: price.into(),
+            publish_slot: publish_slot.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_usable_price_staleness() {
+        let consumer = consumer();
+        assert_eq!(usable_price(&consumer, &feed(150, 50), 60), Ok(150));
+        assert_eq!(
+            usable_price(&consumer, &feed(150, 50), 61),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            usable_price(&consumer, &feed(150, 50), 49),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_usable_price_bounds() {
+        let consumer = consumer();
+        assert_eq!(usable_price(&consumer, &feed(100, 0), 0), Ok(100));
+        assert_eq!(usable_price(&consumer, &feed(200, 0), 0), Ok(200));
+        assert!(usable_price(&consumer, &feed(99, 0), 0).is_err());
+        assert!(usable_price(&consumer, &feed(201, 0), 0).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::PodU64;
+
+/// The mock price feed account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct PriceFeed {
+    /// The authority allowed to publish prices
+    pub authority: Pubkey,
+    /// Last published price
+    pub price: PodU64,
+    /// Slot at which `price` was published
+    pub publish_slot: PodU64,
+}
+
+/// The consumer account data structure.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Consumer {
+    /// The price feed the consumer trusts
+    pub feed: Pubkey,
+    /// Maximum age of a usable price, in slots
+    pub max_staleness_slots: PodU64,
+    /// Lowest usable price
+    pub min_price: PodU64,
+    /// Highest usable price
+    pub max_price: PodU64,
+    /// Price used by the last executed action
+    pub last_price: PodU64,
+    /// Number of executed actions
+    pub executions: PodU64,
+}
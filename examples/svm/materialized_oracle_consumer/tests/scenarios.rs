@@ -0,0 +1,79 @@
+//! End-to-end scenarios of the oracle consumer: sequences of instructions
+//! sent to the program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_oracle_consumer::client::{execute_ix, initialize_ix, publish_ix};
+use solana_oracle_consumer::state::{Consumer, PriceFeed};
+use solana_oracle_consumer::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, Scenario, ScenarioBuilder};
+
+/// The last price used by the consumer at `key` and its executions.
+fn executions(scenario: &mut Scenario, key: Pubkey) -> (u64, u64) {
+    let consumer: Consumer = scenario.state(key);
+    (consumer.last_price.into(), consumer.executions.into())
+}
+
+#[test]
+fn execute_on_fresh_prices_within_bounds() {
+    let (publisher, impostor) = (Keypair::new(), Keypair::new());
+    let (consumer, feed, other_feed) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let mut scenario = ScenarioBuilder::new(
+        "solana_oracle_consumer",
+        id(),
+        processor!(process_instruction),
+    )
+    .state(consumer, &Consumer::default())
+    .state(feed, &PriceFeed::default())
+    .state(other_feed, &PriceFeed::default())
+    .start();
+    // Prices at most 10 slots old, between 100 and 200
+    let initialize = initialize_ix(consumer, feed, 10, 100, 200);
+    scenario.send_instruction(initialize, &[]).unwrap();
+
+    // The first publisher claims the feed
+    let publish = |authority: &Keypair, price| publish_ix(feed, authority.pubkey(), price);
+    scenario
+        .send_instruction(publish(&publisher, 150), &[&publisher])
+        .unwrap();
+    assert!(scenario
+        .send_instruction(publish(&impostor, 120), &[&impostor])
+        .is_err());
+    scenario
+        .send_instruction(execute_ix(consumer, feed), &[])
+        .unwrap();
+    assert_eq!(executions(&mut scenario, consumer), (150, 1));
+    // Only the price of its own feed is used
+    let other = publish_ix(other_feed, impostor.pubkey(), 120);
+    scenario.send_instruction(other, &[&impostor]).unwrap();
+    assert!(scenario
+        .send_instruction(execute_ix(consumer, other_feed), &[])
+        .is_err());
+
+    // A stale price is not used
+    scenario.warp_to_slot(1_000);
+    assert!(scenario
+        .send_instruction(execute_ix(consumer, feed), &[])
+        .is_err());
+    // Nor a fresh one out of bounds
+    scenario
+        .send_instruction(publish(&publisher, 250), &[&publisher])
+        .unwrap();
+    assert!(scenario
+        .send_instruction(execute_ix(consumer, feed), &[])
+        .is_err());
+    scenario
+        .send_instruction(publish(&publisher, 180), &[&publisher])
+        .unwrap();
+    scenario
+        .send_instruction(execute_ix(consumer, feed), &[])
+        .unwrap();
+    assert_eq!(executions(&mut scenario, consumer), (180, 2));
+}
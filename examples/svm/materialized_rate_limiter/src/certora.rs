@@ -0,0 +1,13 @@
+//! Formal verification module for the rate limiter.
+
+pub mod spec;
+
+/// Macro to assert the rate limit property holds.
+/// Within limit: sum of bucket amounts with current - NUM_BUCKETS < index <= current
+///               <= limit
+#[macro_export]
+macro_rules! assert_within_limit {
+    ($fv_limiter:expr, $current:expr) => {
+        cvlr::cvlr_assert!($fv_limiter.window_outflow($current) <= $fv_limiter.limit);
+    };
+}
@@ -0,0 +1,115 @@
+//! This module contains the specification for the rate limiter.
+
+use crate::{
+    assert_within_limit,
+    processor::*,
+    state::{RateLimiter, NUM_BUCKETS},
+};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the limiter state for the formal verification (FV) of
+/// the rate limiter.
+struct FvLimiter {
+    limit: NativeInt,
+    bucket_slots: u64,
+    indices: [u64; NUM_BUCKETS],
+    amounts: [u64; NUM_BUCKETS],
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvLimiter {
+    fn from(acc_info: &AccountInfo) -> FvLimiter {
+        let mut data = acc_info.data.borrow_mut();
+        let limiter: &RateLimiter = bytemuck::from_bytes_mut(&mut data[..]);
+        let limit: u64 = limiter.limit.into();
+        FvLimiter {
+            limit: limit.into(),
+            bucket_slots: limiter.bucket_slots.into(),
+            indices: limiter.buckets.map(|bucket| bucket.index.into()),
+            amounts: limiter.buckets.map(|bucket| bucket.amount.into()),
+        }
+    }
+}
+
+impl FvLimiter {
+    /// Total outflow of the buckets in the window ending with bucket `current`.
+    fn window_outflow(&self, current: u64) -> NativeInt {
+        let mut total = NativeInt::from(0u64);
+        for i in 0..NUM_BUCKETS {
+            let index = self.indices[i];
+            if index <= current && current - index < NUM_BUCKETS as u64 {
+                total = total + NativeInt::from(self.amounts[i]);
+            }
+        }
+        total
+    }
+}
+
+/// Verifies that a recorded outflow never makes the window ending at its slot
+/// exceed the limit.
+#[rule]
+pub fn rule_outflow_within_limit() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let limiter_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let slot: u64 = nondet();
+    let amount: u64 = nondet();
+    {
+        let mut data = limiter_account.data.borrow_mut();
+        let limiter: &mut RateLimiter = bytemuck::from_bytes_mut(&mut data[..]);
+        record_outflow(limiter, slot, amount).unwrap();
+    }
+
+    let fv_limiter: FvLimiter = limiter_account.into();
+    assert_within_limit!(fv_limiter, slot / fv_limiter.bucket_slots);
+}
+
+/// Verifies that, after a recorded outflow, every window ending at a later
+/// slot is within the limit as well, so no window can ever exceed it.
+#[rule]
+pub fn rule_later_windows_within_limit() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let limiter_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let slot: u64 = nondet();
+    let later_slot: u64 = nondet();
+    cvlr_assume!(slot <= later_slot);
+    let amount: u64 = nondet();
+    {
+        let mut data = limiter_account.data.borrow_mut();
+        let limiter: &mut RateLimiter = bytemuck::from_bytes_mut(&mut data[..]);
+        record_outflow(limiter, slot, amount).unwrap();
+    }
+
+    let fv_limiter: FvLimiter = limiter_account.into();
+    assert_within_limit!(fv_limiter, later_slot / fv_limiter.bucket_slots);
+}
+
+/// Verifies that the outflow instruction records exactly the requested amount
+/// in the current bucket window.
+#[rule]
+pub fn rule_outflow_records_amount() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let limiter_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let slot: u64 = nondet();
+    let amount: u64 = nondet();
+    let fv_limiter_pre: FvLimiter = limiter_account.into();
+    cvlr_assume!(fv_limiter_pre.bucket_slots > 0);
+    let current = slot / fv_limiter_pre.bucket_slots;
+    {
+        let mut data = limiter_account.data.borrow_mut();
+        let limiter: &mut RateLimiter = bytemuck::from_bytes_mut(&mut data[..]);
+        record_outflow(limiter, slot, amount).unwrap();
+    }
+
+    let fv_limiter_post: FvLimiter = limiter_account.into();
+    cvlr_assert_eq!(
+        fv_limiter_post.window_outflow(current),
+        fv_limiter_pre.window_outflow(current) + NativeInt::from(amount)
+    );
+}
@@ -0,0 +1,54 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::RateLimiter;
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// Configures the limit over the sliding window and the number of slots per
+/// bucket.
+pub fn initialize_ix(
+    limiter: Pubkey,
+    authority: Pubkey,
+    limit: u64,
+    bucket_slots: u64,
+) -> Instruction {
+    let mut data = vec![0u8];
+    data.extend_from_slice(&limit.to_le_bytes());
+    data.extend_from_slice(&bucket_slots.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(limiter, false),
+            AccountMeta::new_readonly(authority, true),
+        ],
+        data,
+    }
+}
+
+/// Records an outflow of `amount` at the current slot.
+pub fn outflow_ix(limiter: Pubkey, authority: Pubkey, amount: u64) -> Instruction {
+    let mut data = vec![1u8];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(limiter, false),
+            AccountMeta::new_readonly(authority, true),
+        ],
+        data,
+    }
+}
+
+/// Decodes the data of a RateLimiter account.
+pub fn decode_rate_limiter(data: &[u8]) -> Result<RateLimiter, ProgramError> {
+    data.get(..core::mem::size_of::<RateLimiter>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
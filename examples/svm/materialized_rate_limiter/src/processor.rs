@@ -0,0 +1,177 @@
+use crate::state::{RateLimiter, NUM_BUCKETS};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+use svm_fv_support::cpi;
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Total outflow recorded in the window of `NUM_BUCKETS` buckets ending with
+/// bucket number `current`.
+pub fn window_outflow(limiter: &RateLimiter, current: u64) -> u128 {
+    limiter
+        .buckets
+        .iter()
+        .filter(|bucket| {
+            let index: u64 = bucket.index.into();
+            index <= current && current - index < NUM_BUCKETS as u64
+        })
+        .map(|bucket| u64::from(bucket.amount) as u128)
+        .sum()
+}
+
+/// Records an outflow of `amount` at `slot`, as long as the window ending at
+/// the current bucket stays within the limit.
+///
+/// Checking only that window is enough: any later window contains a subset of
+/// the outflows recorded so far, all of which lie in earlier windows that were
+/// checked when the outflow was recorded.
+pub fn record_outflow(limiter: &mut RateLimiter, slot: u64, amount: u64) -> ProgramResult {
+    let bucket_slots: u64 = limiter.bucket_slots.into();
+    if bucket_slots == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    let current = slot / bucket_slots;
+    // Buckets from the future could only come from a clock going backwards
+    if limiter
+        .buckets
+        .iter()
+        .any(|bucket| u64::from(bucket.index) > current)
+    {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let limit: u64 = limiter.limit.into();
+    if window_outflow(limiter, current) + amount as u128 > limit as u128 {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    // Reuse the ring slot, dropping the bucket that fell out of the window
+    let bucket = &mut limiter.buckets[(current % NUM_BUCKETS as u64) as usize];
+    let recorded: u64 = if u64::from(bucket.index) == current {
+        bucket.amount.into()
+    } else {
+        0
+    };
+    bucket.index = current.into();
+    // recorded + amount <= limit, so this cannot overflow
+    bucket.amount = (recorded + amount).into();
+
+    Ok(())
+}
+
+/// Process an initialize instruction.
+/// Configures the limit and the bucket size.
+///
+/// # Arguments
+/// - `accounts`: Account array (limiter account first, signing authority
+///   second)
+/// - `instruction_data`: 8 bytes representing the limit, followed by 8 bytes
+///   representing the number of slots per bucket
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let limit = read_u64(instruction_data, 0)?;
+    let bucket_slots = read_u64(instruction_data, 8)?;
+    if bucket_slots == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let limiter_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let authority = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read limiter data
+    let mut limiter_data = limiter_account.data.borrow_mut();
+    let limiter: &mut RateLimiter = bytemuck::from_bytes_mut(&mut limiter_data);
+    if u64::from(limiter.bucket_slots) != 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    *limiter = RateLimiter {
+        authority: *authority.key,
+        limit: limit.into(),
+        bucket_slots: bucket_slots.into(),
+        buckets: Default::default(),
+    };
+
+    Ok(())
+}
+
+/// Process an outflow instruction.
+/// Records an outflow at the current slot, failing if it would exceed the
+/// limit of the sliding window.
+///
+/// # Arguments
+/// - `accounts`: Account array (limiter account first, signing authority
+///   second)
+/// - `instruction_data`: 8 bytes representing the outflow amount
+pub fn process_outflow(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+
+    let limiter_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let authority = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read limiter data
+    let mut limiter_data = limiter_account.data.borrow_mut();
+    let limiter: &mut RateLimiter = bytemuck::from_bytes_mut(&mut limiter_data);
+    if limiter.authority != *authority.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    record_outflow(limiter, cpi::sysvar::slot()?, amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(limit: u64, bucket_slots: u64) -> RateLimiter {
+        RateLimiter {
+            limit: limit.into(),
+            bucket_slots: bucket_slots.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_outflow_limited_within_window() {
+        // Window of 4 buckets of 10 slots
+        let mut limiter = limiter(100, 10);
+        record_outflow(&mut limiter, 0, 60).unwrap();
+        record_outflow(&mut limiter, 15, 40).unwrap();
+        assert_eq!(
+            record_outflow(&mut limiter, 39, 1),
+            Err(ProgramError::InsufficientFunds)
+        );
+        // Bucket 0 leaves the window at bucket 4
+        record_outflow(&mut limiter, 40, 60).unwrap();
+        assert_eq!(window_outflow(&limiter, 4), 100);
+        assert_eq!(window_outflow(&limiter, 5), 60);
+    }
+
+    #[test]
+    fn test_outflow_rejects_clock_going_backwards() {
+        let mut limiter = limiter(100, 10);
+        record_outflow(&mut limiter, 50, 1).unwrap();
+        assert_eq!(
+            record_outflow(&mut limiter, 49, 1),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            record_outflow(&mut RateLimiter::default(), 0, 0),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::PodU64;
+
+/// Number of buckets making up the sliding window.
+pub const NUM_BUCKETS: usize = 4;
+
+/// Outflow recorded during one bucket of `bucket_slots` slots.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct Bucket {
+    /// Bucket number, i.e. `slot / bucket_slots` of the recorded slots
+    pub index: PodU64,
+    /// Total outflow recorded in the bucket
+    pub amount: PodU64,
+}
+
+/// The rate limiter account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct RateLimiter {
+    /// The authority allowed to record outflows
+    pub authority: Pubkey,
+    /// Maximum total outflow within any window of `NUM_BUCKETS` consecutive
+    /// buckets
+    pub limit: PodU64,
+    /// Number of slots per bucket; zero while uninitialized
+    pub bucket_slots: PodU64,
+    /// Ring buffer of buckets, bucket number `i` stored at `i % NUM_BUCKETS`
+    pub buckets: [Bucket; NUM_BUCKETS],
+}
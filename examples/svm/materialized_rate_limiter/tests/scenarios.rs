@@ -0,0 +1,50 @@
+//! End-to-end scenarios of the rate limiter: sequences of instructions sent
+//! to the program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_rate_limiter::client::{initialize_ix, outflow_ix};
+use solana_rate_limiter::state::RateLimiter;
+use solana_rate_limiter::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, ScenarioBuilder};
+
+#[test]
+fn outflows_are_limited_per_window() {
+    let (authority, impostor) = (Keypair::new(), Keypair::new());
+    let limiter = Pubkey::new_unique();
+    let mut scenario =
+        ScenarioBuilder::new("solana_rate_limiter", id(), processor!(process_instruction))
+            .state(limiter, &RateLimiter::default())
+            .start();
+    // At most 100 over the last four buckets of 100 slots
+    let initialize = initialize_ix(limiter, authority.pubkey(), 100, 100);
+    scenario
+        .send_instruction(initialize, &[&authority])
+        .unwrap();
+
+    let outflow = |signer: &Keypair, amount| outflow_ix(limiter, signer.pubkey(), amount);
+    assert!(scenario
+        .send_instruction(outflow(&impostor, 10), &[&impostor])
+        .is_err());
+    scenario
+        .send_instruction(outflow(&authority, 60), &[&authority])
+        .unwrap();
+    scenario
+        .send_instruction(outflow(&authority, 40), &[&authority])
+        .unwrap();
+    assert!(scenario
+        .send_instruction(outflow(&authority, 1), &[&authority])
+        .is_err());
+
+    // The outflows leave the window once their buckets are old enough
+    scenario.warp_to_slot(10_000);
+    scenario
+        .send_instruction(outflow(&authority, 100), &[&authority])
+        .unwrap();
+    assert!(scenario
+        .send_instruction(outflow(&authority, 1), &[&authority])
+        .is_err());
+}
@@ -0,0 +1,25 @@
+//! Formal verification module for the sorted list.
+
+pub mod spec;
+
+/// Macro to assume the well-formedness property holds.
+/// Well-formed: len <= CAPACITY
+///              && items[i] <= items[i + 1] for all i + 1 < len
+///              && items[i] == 0 for all i >= len
+#[macro_export]
+macro_rules! assume_well_formed {
+    ($fv_list:expr) => {
+        cvlr::cvlr_assume!($fv_list.is_well_formed());
+    };
+}
+
+/// Macro to assert the well-formedness property holds.
+/// Well-formed: len <= CAPACITY
+///              && items[i] <= items[i + 1] for all i + 1 < len
+///              && items[i] == 0 for all i >= len
+#[macro_export]
+macro_rules! assert_well_formed {
+    ($fv_list:expr) => {
+        cvlr::cvlr_assert!($fv_list.is_well_formed());
+    };
+}
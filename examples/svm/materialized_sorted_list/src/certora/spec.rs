@@ -0,0 +1,112 @@
+//! This module contains the specification for the sorted list.
+
+use crate::{
+    assert_well_formed, assume_well_formed,
+    processor::*,
+    state::{SortedList, CAPACITY},
+};
+use cvlr::prelude::*;
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the list state for the formal verification (FV) of the
+/// sorted list.
+struct FvList {
+    len: u64,
+    items: [u64; CAPACITY],
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvList {
+    fn from(acc_info: &AccountInfo) -> FvList {
+        let mut data = acc_info.data.borrow_mut();
+        let list: &SortedList = bytemuck::from_bytes_mut(&mut data[..]);
+        let len: u32 = list.len.into();
+        FvList {
+            len: len as u64,
+            items: list.values(),
+        }
+    }
+}
+
+impl FvList {
+    /// Sorted prefix of `len` elements followed by zeroed slots.
+    fn is_well_formed(&self) -> bool {
+        if self.len > CAPACITY as u64 {
+            return false;
+        }
+        let len = self.len as usize;
+        (1..len).all(|i| self.items[i - 1] <= self.items[i])
+            && self.items[len..].iter().all(|item| *item == 0)
+    }
+
+    /// Number of occurrences of `value` among the elements.
+    fn count(&self, value: u64) -> u64 {
+        self.items[..self.len as usize]
+            .iter()
+            .filter(|item| **item == value)
+            .count() as u64
+    }
+}
+
+/// Verifies that inserting keeps the list sorted and adds exactly one
+/// occurrence of the inserted value, leaving all other values untouched.
+#[rule]
+pub fn rule_insert_keeps_sorted() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let list_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_list_pre: FvList = list_account.into();
+    assume_well_formed!(fv_list_pre);
+
+    let value: u64 = nondet();
+    let other: u64 = nondet();
+    cvlr_assume!(other != value);
+    let insert_instruction_data = &value.to_le_bytes();
+    process_insert(&account_infos, insert_instruction_data).unwrap();
+
+    let fv_list_post: FvList = list_account.into();
+    assert_well_formed!(fv_list_post);
+    cvlr_assert_eq!(fv_list_post.len, fv_list_pre.len + 1);
+    cvlr_assert_eq!(fv_list_post.count(value), fv_list_pre.count(value) + 1);
+    cvlr_assert_eq!(fv_list_post.count(other), fv_list_pre.count(other));
+}
+
+/// Verifies that removing keeps the list sorted and removes exactly one
+/// occurrence of the removed value, leaving all other values untouched.
+#[rule]
+pub fn rule_remove_keeps_sorted() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let list_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_list_pre: FvList = list_account.into();
+    assume_well_formed!(fv_list_pre);
+
+    let value: u64 = nondet();
+    let other: u64 = nondet();
+    cvlr_assume!(other != value);
+    let remove_instruction_data = &value.to_le_bytes();
+    process_remove(&account_infos, remove_instruction_data).unwrap();
+
+    let fv_list_post: FvList = list_account.into();
+    assert_well_formed!(fv_list_post);
+    cvlr_assert_eq!(fv_list_post.len + 1, fv_list_pre.len);
+    cvlr_assert_eq!(fv_list_post.count(value) + 1, fv_list_pre.count(value));
+    cvlr_assert_eq!(fv_list_post.count(other), fv_list_pre.count(other));
+}
+
+/// Verifies that inserting into a full list fails.
+#[rule]
+pub fn rule_insert_full_fails() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let list_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_list: FvList = list_account.into();
+    cvlr_assume!(fv_list.len == CAPACITY as u64);
+
+    let value: u64 = nondet();
+    let insert_instruction_data = &value.to_le_bytes();
+    cvlr_assert!(process_insert(&account_infos, insert_instruction_data).is_err());
+}
@@ -0,0 +1,41 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::SortedList;
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// Adds `value` to the list, keeping it sorted.
+pub fn insert_ix(list: Pubkey, value: u64) -> Instruction {
+    let mut data = vec![0u8];
+    data.extend_from_slice(&value.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new(list, false)],
+        data,
+    }
+}
+
+/// Removes one occurrence of `value` from the list, keeping it sorted.
+pub fn remove_ix(list: Pubkey, value: u64) -> Instruction {
+    let mut data = vec![1u8];
+    data.extend_from_slice(&value.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new(list, false)],
+        data,
+    }
+}
+
+/// Decodes the data of a SortedList account.
+pub fn decode_sorted_list(data: &[u8]) -> Result<SortedList, ProgramError> {
+    data.get(..core::mem::size_of::<SortedList>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
@@ -0,0 +1,133 @@
+use crate::state::{SortedList, CAPACITY};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Inserts `value` after every element not greater than it, shifting the
+/// greater elements one slot to the right.
+pub fn insert(list: &mut SortedList, value: u64) -> ProgramResult {
+    let len = u32::from(list.len) as usize;
+    if len > CAPACITY {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if len == CAPACITY {
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+
+    let index = list.items[..len]
+        .iter()
+        .position(|item| u64::from(*item) > value)
+        .unwrap_or(len);
+    list.items.copy_within(index..len, index + 1);
+    list.items[index] = value.into();
+    list.len = ((len + 1) as u32).into();
+
+    Ok(())
+}
+
+/// Removes the first occurrence of `value`, shifting the following elements
+/// one slot to the left and zeroing the vacated slot.
+pub fn remove(list: &mut SortedList, value: u64) -> ProgramResult {
+    let len = u32::from(list.len) as usize;
+    if len > CAPACITY {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let index = list.items[..len]
+        .iter()
+        .position(|item| u64::from(*item) == value)
+        .ok_or(ProgramError::InvalidArgument)?;
+    list.items.copy_within(index + 1..len, index);
+    list.items[len - 1] = 0.into();
+    list.len = ((len - 1) as u32).into();
+
+    Ok(())
+}
+
+/// Process an insert instruction.
+/// Adds a value to the list, keeping it sorted.
+///
+/// # Arguments
+/// - `accounts`: Account array (must have list account as first)
+/// - `instruction_data`: 8 bytes representing the value to insert
+pub fn process_insert(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let value = read_u64(instruction_data, 0)?;
+
+    // Get list account
+    let list_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // Borrow and read list data
+    let mut list_data = list_account.data.borrow_mut();
+    let list: &mut SortedList = bytemuck::from_bytes_mut(&mut list_data);
+
+    insert(list, value)
+}
+
+/// Process a remove instruction.
+/// Removes one occurrence of a value from the list, keeping it sorted.
+///
+/// # Arguments
+/// - `accounts`: Account array (must have list account as first)
+/// - `instruction_data`: 8 bytes representing the value to remove
+pub fn process_remove(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let value = read_u64(instruction_data, 0)?;
+
+    // Get list account
+    let list_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // Borrow and read list data
+    let mut list_data = list_account.data.borrow_mut();
+    let list: &mut SortedList = bytemuck::from_bytes_mut(&mut list_data);
+
+    remove(list, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_keeps_order_and_duplicates() {
+        let mut list = SortedList::default();
+        for value in [5, 1, 3, 5, 0] {
+            insert(&mut list, value).unwrap();
+        }
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.values(), [0, 1, 3, 5, 5, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_insert_full_list() {
+        let mut list = SortedList::default();
+        for value in 0..CAPACITY as u64 {
+            insert(&mut list, value).unwrap();
+        }
+        assert_eq!(insert(&mut list, 1), Err(ProgramError::AccountDataTooSmall));
+        assert_eq!(list.len(), CAPACITY);
+    }
+
+    #[test]
+    fn test_remove_zeroes_vacated_slot() {
+        let mut list = SortedList::default();
+        for value in [4, 2, 9] {
+            insert(&mut list, value).unwrap();
+        }
+        remove(&mut list, 4).unwrap();
+        assert_eq!(list.values(), [2, 9, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(remove(&mut list, 4), Err(ProgramError::InvalidArgument));
+        remove(&mut list, 9).unwrap();
+        remove(&mut list, 2).unwrap();
+        assert!(list.is_empty());
+        assert_eq!(remove(&mut list, 0), Err(ProgramError::InvalidArgument));
+    }
+}
@@ -0,0 +1,33 @@
+use bytemuck::{Pod, Zeroable};
+use spl_pod::primitives::{PodU32, PodU64};
+
+/// Maximum number of elements of a list.
+pub const CAPACITY: usize = 8;
+
+/// The sorted list account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct SortedList {
+    /// Number of elements; only the first `len` items are meaningful
+    pub len: PodU32,
+    /// The elements in non-decreasing order, followed by zeroed slots
+    pub items: [PodU64; CAPACITY],
+}
+
+impl SortedList {
+    /// Returns the number of elements, capped at `CAPACITY`.
+    pub fn len(&self) -> usize {
+        (u32::from(self.len) as usize).min(CAPACITY)
+    }
+
+    /// Returns true iff the list has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns all item slots as plain integers.
+    pub fn values(&self) -> [u64; CAPACITY] {
+        self.items.map(u64::from)
+    }
+}
@@ -0,0 +1,47 @@
+//! End-to-end scenarios of the sorted list: sequences of instructions sent
+//! to the program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_sorted_list::client::{insert_ix, remove_ix};
+use solana_sorted_list::state::{SortedList, CAPACITY};
+use solana_sorted_list::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::{processor, Scenario, ScenarioBuilder};
+
+/// The values held by the list at `key`, in order.
+fn values(scenario: &mut Scenario, key: Pubkey) -> Vec<u64> {
+    let list: SortedList = scenario.state(key);
+    list.values()[..list.len()].to_vec()
+}
+
+#[test]
+fn insert_and_remove_keep_the_order() {
+    let list = Pubkey::new_unique();
+    let mut scenario =
+        ScenarioBuilder::new("solana_sorted_list", id(), processor!(process_instruction))
+            .state(list, &SortedList::default())
+            .start();
+
+    for value in [5, 1, 3] {
+        scenario
+            .send_instruction(insert_ix(list, value), &[])
+            .unwrap();
+    }
+    assert_eq!(values(&mut scenario, list), [1, 3, 5]);
+    scenario.send_instruction(remove_ix(list, 3), &[]).unwrap();
+    assert_eq!(values(&mut scenario, list), [1, 5]);
+    // Only a value in the list is removed
+    assert!(scenario.send_instruction(remove_ix(list, 3), &[]).is_err());
+
+    // Up to the capacity of the account
+    for value in 2..CAPACITY as u64 {
+        scenario
+            .send_instruction(insert_ix(list, 10 * value), &[])
+            .unwrap();
+    }
+    assert_eq!(values(&mut scenario, list).len(), CAPACITY);
+    assert!(scenario.send_instruction(insert_ix(list, 0), &[]).is_err());
+    assert_eq!(values(&mut scenario, list)[..3], [1, 5, 20]);
+}
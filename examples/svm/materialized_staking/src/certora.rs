@@ -0,0 +1,21 @@
+//! Formal verification module for the staking pool.
+
+pub mod spec;
+
+/// Macro to assume the reward-funding property holds.
+/// Reward funding: rewards_claimed <= rewards_funded
+#[macro_export]
+macro_rules! assume_rewards_funded {
+    ($fv_pool:expr) => {
+        cvlr::cvlr_assume!($fv_pool.rewards_claimed <= $fv_pool.rewards_funded);
+    };
+}
+
+/// Macro to assert the reward-funding property holds.
+/// Reward funding: rewards_claimed <= rewards_funded
+#[macro_export]
+macro_rules! assert_rewards_funded {
+    ($fv_pool:expr) => {
+        cvlr::cvlr_assert!($fv_pool.rewards_claimed <= $fv_pool.rewards_funded);
+    };
+}
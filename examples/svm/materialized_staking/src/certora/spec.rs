@@ -0,0 +1,143 @@
+//! This module contains the specification for the staking pool.
+
+use crate::{
+    assert_rewards_funded, assume_rewards_funded,
+    processor::*,
+    state::{StakePool, Staker},
+};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the pool state for the formal verification (FV) of the
+/// staking pool.
+struct FvStakePool {
+    total_staked: NativeInt,
+    rewards_funded: NativeInt,
+    rewards_claimed: NativeInt,
+}
+
+impl From<&StakePool> for FvStakePool {
+    fn from(pool: &StakePool) -> FvStakePool {
+        let total_staked: u64 = pool.total_staked.into();
+        let rewards_funded: u64 = pool.rewards_funded.into();
+        let rewards_claimed: u64 = pool.rewards_claimed.into();
+        FvStakePool {
+            total_staked: total_staked.into(),
+            rewards_funded: rewards_funded.into(),
+            rewards_claimed: rewards_claimed.into(),
+        }
+    }
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvStakePool {
+    fn from(acc_info: &AccountInfo) -> FvStakePool {
+        let mut data = acc_info.data.borrow_mut();
+        let pool: &StakePool = bytemuck::from_bytes_mut(&mut data[..]);
+        FvStakePool::from(pool)
+    }
+}
+
+/// Structure tracking the staker state for the formal verification (FV) of the
+/// staking pool.
+struct FvStaker {
+    staked: NativeInt,
+    pending: NativeInt,
+}
+
+impl From<&Staker> for FvStaker {
+    fn from(staker: &Staker) -> FvStaker {
+        let staked: u64 = staker.staked.into();
+        let pending: u64 = staker.pending.into();
+        FvStaker {
+            staked: staked.into(),
+            pending: pending.into(),
+        }
+    }
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvStaker {
+    fn from(acc_info: &AccountInfo) -> FvStaker {
+        let mut data = acc_info.data.borrow_mut();
+        let staker: &Staker = bytemuck::from_bytes_mut(&mut data[..]);
+        FvStaker::from(staker)
+    }
+}
+
+/// Verifies that claiming never pays out more rewards than were funded.
+#[rule]
+pub fn rule_claim_within_funded() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let pool_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_pool_pre: FvStakePool = pool_account.into();
+    assume_rewards_funded!(fv_pool_pre);
+
+    process_claim(&account_infos, &[]).unwrap();
+
+    let fv_pool_post: FvStakePool = pool_account.into();
+    assert_rewards_funded!(fv_pool_post);
+}
+
+/// Verifies that funding keeps claimed rewards within funded rewards.
+#[rule]
+pub fn rule_fund_within_funded() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let pool_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_pool_pre: FvStakePool = pool_account.into();
+    assume_rewards_funded!(fv_pool_pre);
+
+    let amount: u64 = nondet();
+    let fund_instruction_data = &amount.to_le_bytes();
+    process_fund(&account_infos, fund_instruction_data).unwrap();
+
+    let fv_pool_post: FvStakePool = pool_account.into();
+    assert_rewards_funded!(fv_pool_post);
+}
+
+/// Verifies that unstaking removes exactly the requested principal from the
+/// staker and the pool, without forfeiting accrued rewards.
+#[rule]
+pub fn rule_unstake_returns_principal() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let pool_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let staker_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_pool_pre: FvStakePool = pool_account.into();
+    let fv_staker_pre: FvStaker = staker_account.into();
+
+    let amount: u64 = nondet();
+    let unstake_instruction_data = &amount.to_le_bytes();
+    process_unstake(&account_infos, unstake_instruction_data).unwrap();
+
+    let fv_pool_post: FvStakePool = pool_account.into();
+    let fv_staker_post: FvStaker = staker_account.into();
+    let amount: NativeInt = amount.into();
+    cvlr_assert!(fv_staker_post.staked + amount == fv_staker_pre.staked);
+    cvlr_assert!(fv_pool_post.total_staked + amount == fv_pool_pre.total_staked);
+    cvlr_assert!(fv_staker_post.pending >= fv_staker_pre.pending);
+}
+
+/// Verifies that staking and then unstaking the same amount restores the
+/// staker's principal.
+#[rule]
+pub fn rule_stake_unstake_round_trip() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let _pool_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let staker_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_staker_pre: FvStaker = staker_account.into();
+
+    let amount: u64 = nondet();
+    let instruction_data = &amount.to_le_bytes();
+    process_stake(&account_infos, instruction_data).unwrap();
+    process_unstake(&account_infos, instruction_data).unwrap();
+
+    let fv_staker_post: FvStaker = staker_account.into();
+    cvlr_assert!(fv_staker_post.staked == fv_staker_pre.staked);
+}
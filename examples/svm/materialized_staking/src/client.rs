@@ -0,0 +1,72 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::{StakePool, Staker};
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// The instruction `discriminant` with `data` over the pool, the staker
+/// account and its signing owner.
+fn instruction(
+    discriminant: u8,
+    pool: Pubkey,
+    staker: Pubkey,
+    owner: Pubkey,
+    data: &[u8],
+) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(pool, false),
+            AccountMeta::new(staker, false),
+            AccountMeta::new_readonly(owner, true),
+        ],
+        data: [[discriminant].as_slice(), data].concat(),
+    }
+}
+
+/// Stakes `amount`, binding a fresh staker account to `owner` and the pool.
+pub fn stake_ix(pool: Pubkey, staker: Pubkey, owner: Pubkey, amount: u64) -> Instruction {
+    instruction(0, pool, staker, owner, &amount.to_le_bytes())
+}
+
+/// Unstakes exactly `amount` of the principal.
+pub fn unstake_ix(pool: Pubkey, staker: Pubkey, owner: Pubkey, amount: u64) -> Instruction {
+    instruction(1, pool, staker, owner, &amount.to_le_bytes())
+}
+
+/// Pays out the settled rewards of the staker.
+pub fn claim_ix(pool: Pubkey, staker: Pubkey, owner: Pubkey) -> Instruction {
+    instruction(2, pool, staker, owner, &[])
+}
+
+/// Distributes `amount` new rewards over the staked principal.
+pub fn fund_ix(pool: Pubkey, amount: u64) -> Instruction {
+    let mut data = vec![3u8];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new(pool, false)],
+        data,
+    }
+}
+
+/// Decodes the data of a StakePool account.
+pub fn decode_stake_pool(data: &[u8]) -> Result<StakePool, ProgramError> {
+    data.get(..core::mem::size_of::<StakePool>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a Staker account.
+pub fn decode_staker(data: &[u8]) -> Result<Staker, ProgramError> {
+    data.get(..core::mem::size_of::<Staker>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
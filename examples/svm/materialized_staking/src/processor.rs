@@ -0,0 +1,318 @@
+use crate::state::{StakePool, Staker, REWARD_PRECISION};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use svm_vault_math::mul_div_floor;
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Rewards accrued by `staked` tokens at the accumulator value `reward_per_token`.
+pub fn accrued_rewards(staked: u64, reward_per_token: u64) -> Result<u64, ProgramError> {
+    mul_div_floor(staked, reward_per_token, REWARD_PRECISION)
+        .ok_or(ProgramError::ArithmeticOverflow)
+}
+
+/// Moves everything the staker earned since its last update into `pending`.
+fn settle(pool: &StakePool, staker: &mut Staker) -> ProgramResult {
+    let accrued = accrued_rewards(staker.staked.into(), pool.reward_per_token.into())?;
+    let reward_debt: u64 = staker.reward_debt.into();
+    // The accumulator never decreases, so the debt never exceeds the accrual
+    let owed = accrued
+        .checked_sub(reward_debt)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let pending: u64 = staker.pending.into();
+    staker.pending = pending
+        .checked_add(owed)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    staker.reward_debt = accrued.into();
+    Ok(())
+}
+
+/// Gets the pool and staker accounts, both owned by the program, and the
+/// signing staker owner, rejecting aliased accounts.
+fn pool_staker_and_owner<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+) -> Result<
+    (
+        &'a AccountInfo<'b>,
+        &'a AccountInfo<'b>,
+        &'a AccountInfo<'b>,
+    ),
+    ProgramError,
+> {
+    let pool_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let staker_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let owner = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if pool_account.key == staker_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *pool_account.owner != crate::id() || *staker_account.owner != crate::id() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok((pool_account, staker_account, owner))
+}
+
+/// Checks that `staker` records the principal of `owner` in the pool at
+/// `pool`, so that it settles against that pool's accumulator only.
+fn check_staker(pool: &Pubkey, staker: &Staker, owner: &Pubkey) -> ProgramResult {
+    if staker.pool != *pool {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if staker.owner != *owner {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+/// Process a stake instruction.
+/// Adds principal to the staker's position after settling its rewards. The
+/// first stake into a fresh staker account binds it to the signing owner and
+/// the pool.
+///
+/// # Arguments
+/// - `accounts`: Account array (pool account first, staker account second,
+///   signing staker owner third)
+/// - `instruction_data`: 8 bytes representing the number of tokens to stake
+pub fn process_stake(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let (pool_account, staker_account, owner) = pool_staker_and_owner(accounts)?;
+
+    // Borrow and read pool and staker data
+    let mut pool_data = pool_account.data.borrow_mut();
+    let pool: &mut StakePool = bytemuck::from_bytes_mut(&mut pool_data);
+    let mut staker_data = staker_account.data.borrow_mut();
+    let staker: &mut Staker = bytemuck::from_bytes_mut(&mut staker_data);
+    if staker.pool == Pubkey::default() {
+        *staker = Staker::new(*owner.key, *pool_account.key);
+    }
+    check_staker(pool_account.key, staker, owner.key)?;
+
+    settle(pool, staker)?;
+
+    let staked: u64 = staker.staked.into();
+    let total_staked: u64 = pool.total_staked.into();
+    let new_staked = staked
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    pool.total_staked = total_staked
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    staker.staked = new_staked.into();
+    staker.reward_debt = accrued_rewards(new_staked, pool.reward_per_token.into())?.into();
+
+    Ok(())
+}
+
+/// Process an unstake instruction.
+/// Returns exactly the requested principal; accrued rewards stay pending.
+///
+/// # Arguments
+/// - `accounts`: Account array (pool account first, staker account second,
+///   signing staker owner third)
+/// - `instruction_data`: 8 bytes representing the number of tokens to unstake
+pub fn process_unstake(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let (pool_account, staker_account, owner) = pool_staker_and_owner(accounts)?;
+
+    // Borrow and read pool and staker data
+    let mut pool_data = pool_account.data.borrow_mut();
+    let pool: &mut StakePool = bytemuck::from_bytes_mut(&mut pool_data);
+    let mut staker_data = staker_account.data.borrow_mut();
+    let staker: &mut Staker = bytemuck::from_bytes_mut(&mut staker_data);
+    check_staker(pool_account.key, staker, owner.key)?;
+
+    let staked: u64 = staker.staked.into();
+    let total_staked: u64 = pool.total_staked.into();
+    if amount > staked || amount > total_staked {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    settle(pool, staker)?;
+
+    let new_staked = staked - amount;
+    pool.total_staked = (total_staked - amount).into();
+    staker.staked = new_staked.into();
+    staker.reward_debt = accrued_rewards(new_staked, pool.reward_per_token.into())?.into();
+
+    Ok(())
+}
+
+/// Process a claim instruction.
+/// Pays out the staker's settled rewards, capped by the unclaimed funded rewards.
+///
+/// # Arguments
+/// - `accounts`: Account array (pool account first, staker account second,
+///   signing staker owner third)
+/// - `instruction_data`: unused
+pub fn process_claim(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    let (pool_account, staker_account, owner) = pool_staker_and_owner(accounts)?;
+
+    // Borrow and read pool and staker data
+    let mut pool_data = pool_account.data.borrow_mut();
+    let pool: &mut StakePool = bytemuck::from_bytes_mut(&mut pool_data);
+    let mut staker_data = staker_account.data.borrow_mut();
+    let staker: &mut Staker = bytemuck::from_bytes_mut(&mut staker_data);
+    check_staker(pool_account.key, staker, owner.key)?;
+
+    settle(pool, staker)?;
+
+    let pending: u64 = staker.pending.into();
+    let rewards_funded: u64 = pool.rewards_funded.into();
+    let rewards_claimed: u64 = pool.rewards_claimed.into();
+    // Rounding in the accumulator must never let claims outrun funding
+    let payout = pending.min(rewards_funded.saturating_sub(rewards_claimed));
+
+    staker.pending = (pending - payout).into();
+    pool.rewards_claimed = (rewards_claimed + payout).into();
+
+    Ok(())
+}
+
+/// Process a fund instruction.
+/// Distributes new rewards over the currently staked principal.
+///
+/// # Arguments
+/// - `accounts`: Account array (must have pool account as first)
+/// - `instruction_data`: 8 bytes representing the number of reward tokens
+pub fn process_fund(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+
+    // Get pool account
+    let pool_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // Borrow and read pool data
+    let mut pool_data = pool_account.data.borrow_mut();
+    let pool: &mut StakePool = bytemuck::from_bytes_mut(&mut pool_data);
+
+    let total_staked: u64 = pool.total_staked.into();
+    if total_staked == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let reward_per_token: u64 = pool.reward_per_token.into();
+    let new_reward_per_token = mul_div_floor(amount, REWARD_PRECISION, total_staked)
+        .and_then(|increment| reward_per_token.checked_add(increment))
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let rewards_funded: u64 = pool.rewards_funded.into();
+
+    pool.reward_per_token = new_reward_per_token.into();
+    pool.rewards_funded = rewards_funded
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::system_program;
+    use svm_fv_support::TestAccount;
+
+    #[test]
+    fn test_accrued_rewards_scaling() {
+        assert_eq!(accrued_rewards(0, 5 * REWARD_PRECISION).unwrap(), 0);
+        assert_eq!(accrued_rewards(10, 5 * REWARD_PRECISION).unwrap(), 50);
+        assert_eq!(accrued_rewards(3, REWARD_PRECISION / 2).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_accrued_rewards_overflow() {
+        assert!(accrued_rewards(u64::MAX, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_only_the_staker_owner_unstakes_and_claims() {
+        let mut owner = TestAccount::wallet();
+        let mut stranger = TestAccount::wallet();
+        let mut pool = TestAccount::new(crate::id()).state(&StakePool::new(Pubkey::new_unique()));
+        let mut staker = TestAccount::new(crate::id()).state(&Staker::default());
+        let (pool_account, staker_account) = (pool.info(), staker.info());
+
+        // The first stake binds the staker account to its owner and the pool
+        let accounts = [pool_account.clone(), staker_account.clone(), owner.info()];
+        process_stake(&accounts, &100u64.to_le_bytes()).unwrap();
+        process_fund(&accounts, &50u64.to_le_bytes()).unwrap();
+
+        let accounts = [
+            pool_account.clone(),
+            staker_account.clone(),
+            stranger.info(),
+        ];
+        assert_eq!(
+            process_stake(&accounts, &1u64.to_le_bytes()),
+            Err(ProgramError::IllegalOwner)
+        );
+        assert_eq!(
+            process_unstake(&accounts, &100u64.to_le_bytes()),
+            Err(ProgramError::IllegalOwner)
+        );
+        assert_eq!(
+            process_claim(&accounts, &[]),
+            Err(ProgramError::IllegalOwner)
+        );
+
+        // Nor without the owner's signature
+        let mut unsigned = TestAccount::new(system_program::id()).key(owner.pubkey());
+        let accounts = [
+            pool_account.clone(),
+            staker_account.clone(),
+            unsigned.info(),
+        ];
+        assert_eq!(
+            process_claim(&accounts, &[]),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+
+        let accounts = [pool_account.clone(), staker_account.clone(), owner.info()];
+        process_claim(&accounts, &[]).unwrap();
+        process_unstake(&accounts, &100u64.to_le_bytes()).unwrap();
+        let data = staker_account.data.borrow();
+        let staker: &Staker = bytemuck::from_bytes(&data);
+        assert_eq!(u64::from(staker.staked), 0);
+        assert_eq!(u64::from(staker.pending), 0);
+    }
+
+    #[test]
+    fn test_staker_settles_only_against_its_pool() {
+        let mut owner = TestAccount::wallet();
+        let owner_key = owner.pubkey();
+        let mut pool = TestAccount::new(crate::id()).state(&StakePool::new(Pubkey::new_unique()));
+        // A pool with a larger accumulator than the staker's own
+        let mut richer = StakePool::new(Pubkey::new_unique());
+        richer.total_staked = 1.into();
+        richer.reward_per_token = (1_000 * REWARD_PRECISION).into();
+        richer.rewards_funded = 1_000.into();
+        let mut richer = TestAccount::new(crate::id()).state(&richer);
+        let mut staker =
+            TestAccount::new(crate::id()).state(&Staker::new(owner_key, pool.pubkey()));
+        let (staker_account, owner_account) = (staker.info(), owner.info());
+
+        let accounts = [pool.info(), staker_account.clone(), owner_account.clone()];
+        process_stake(&accounts, &100u64.to_le_bytes()).unwrap();
+        let accounts = [richer.info(), staker_account, owner_account];
+        assert_eq!(
+            process_claim(&accounts, &[]),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            process_unstake(&accounts, &100u64.to_le_bytes()),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::PodU64;
+
+/// Fixed-point scaling factor of the reward-per-token accumulator.
+pub const REWARD_PRECISION: u64 = 1_000_000;
+
+/// The staking pool account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct StakePool {
+    /// The pool owner (authority)
+    pub owner: Pubkey,
+    /// Total principal staked across all stakers
+    pub total_staked: PodU64,
+    /// Accumulated rewards per staked token, scaled by `REWARD_PRECISION`
+    pub reward_per_token: PodU64,
+    /// Total rewards ever funded into the pool
+    pub rewards_funded: PodU64,
+    /// Total rewards ever claimed from the pool
+    pub rewards_claimed: PodU64,
+}
+
+impl StakePool {
+    /// Create a new staking pool with the given owner
+    pub fn new(owner: Pubkey) -> Self {
+        StakePool {
+            owner,
+            total_staked: PodU64::from(0),
+            reward_per_token: PodU64::from(0),
+            rewards_funded: PodU64::from(0),
+            rewards_claimed: PodU64::from(0),
+        }
+    }
+}
+
+/// The per-staker account data structure.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Staker {
+    /// The staker (authority)
+    pub owner: Pubkey,
+    /// The pool the principal is staked in
+    pub pool: Pubkey,
+    /// Principal staked by this staker
+    pub staked: PodU64,
+    /// Rewards already accounted for at the current `reward_per_token`
+    pub reward_debt: PodU64,
+    /// Rewards settled on stake changes but not yet claimed
+    pub pending: PodU64,
+}
+
+impl Staker {
+    /// Create a new staker record of the given owner in the given pool
+    pub fn new(owner: Pubkey, pool: Pubkey) -> Self {
+        Staker {
+            owner,
+            pool,
+            staked: PodU64::from(0),
+            reward_debt: PodU64::from(0),
+            pending: PodU64::from(0),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! End-to-end scenarios of the staking pool: sequences of instructions sent
+//! to the program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_staking::client::{claim_ix, fund_ix, stake_ix, unstake_ix};
+use solana_staking::state::{StakePool, Staker};
+use solana_staking::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, Scenario, ScenarioBuilder};
+
+/// The stake and the rewards pending of the staker at `key`.
+fn balances(scenario: &mut Scenario, key: Pubkey) -> (u64, u64) {
+    let staker: Staker = scenario.state(key);
+    (staker.staked.into(), staker.pending.into())
+}
+
+#[test]
+fn rewards_follow_the_stake() {
+    let (alice, bob) = (Keypair::new(), Keypair::new());
+    let pool = Pubkey::new_unique();
+    let (alice_staker, bob_staker) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut scenario =
+        ScenarioBuilder::new("solana_staking", id(), processor!(process_instruction))
+            .state(pool, &StakePool::new(Pubkey::new_unique()))
+            .state(alice_staker, &Staker::default())
+            .state(bob_staker, &Staker::default())
+            .start();
+
+    // Nothing to reward before anything is staked
+    assert!(scenario.send_instruction(fund_ix(pool, 100), &[]).is_err());
+    let stake = stake_ix(pool, alice_staker, alice.pubkey(), 100);
+    scenario.send_instruction(stake, &[&alice]).unwrap();
+    let stake = stake_ix(pool, bob_staker, bob.pubkey(), 300);
+    scenario.send_instruction(stake, &[&bob]).unwrap();
+    // The first stake binds the account to its owner
+    let theft = stake_ix(pool, alice_staker, bob.pubkey(), 1);
+    assert!(scenario.send_instruction(theft, &[&bob]).is_err());
+
+    // Rewards are shared by stake
+    scenario.send_instruction(fund_ix(pool, 400), &[]).unwrap();
+    let claim = claim_ix(pool, alice_staker, alice.pubkey());
+    scenario.send_instruction(claim, &[&alice]).unwrap();
+    assert_eq!(balances(&mut scenario, alice_staker), (100, 0));
+
+    // And go to the stake left after unstaking, up to the stake
+    let unstake = |amount| unstake_ix(pool, alice_staker, alice.pubkey(), amount);
+    assert!(scenario.send_instruction(unstake(101), &[&alice]).is_err());
+    scenario.send_instruction(unstake(100), &[&alice]).unwrap();
+    scenario.send_instruction(fund_ix(pool, 300), &[]).unwrap();
+    let claim = claim_ix(pool, bob_staker, bob.pubkey());
+    scenario.send_instruction(claim, &[&bob]).unwrap();
+    assert_eq!(balances(&mut scenario, alice_staker), (0, 0));
+    assert_eq!(balances(&mut scenario, bob_staker), (300, 0));
+    let state: StakePool = scenario.state(pool);
+    assert_eq!(
+        (
+            u64::from(state.rewards_funded),
+            u64::from(state.rewards_claimed)
+        ),
+        (700, 700)
+    );
+}
@@ -0,0 +1,16 @@
+//! Formal verification module for the payment stream.
+
+pub mod spec;
+
+/// Macro to assume the funding property holds.
+/// Funded: start_slot <= end_slot && deposit == rate_per_slot * (end_slot - start_slot)
+#[macro_export]
+macro_rules! assume_funded {
+    ($fv_stream:expr) => {
+        cvlr::cvlr_assume!($fv_stream.start_slot <= $fv_stream.end_slot);
+        cvlr::cvlr_assume!(
+            $fv_stream.deposit
+                == $fv_stream.rate_per_slot * ($fv_stream.end_slot - $fv_stream.start_slot)
+        );
+    };
+}
@@ -0,0 +1,119 @@
+//! This module contains the specification for the payment stream.
+
+use crate::{assume_funded, processor::*, state::Stream};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the stream state for the formal verification (FV) of the
+/// payment stream.
+struct FvStream {
+    deposit: NativeInt,
+    rate_per_slot: NativeInt,
+    start_slot: NativeInt,
+    end_slot: NativeInt,
+    withdrawn: NativeInt,
+    refunded: NativeInt,
+    cancelled: bool,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvStream {
+    fn from(acc_info: &AccountInfo) -> FvStream {
+        let mut data = acc_info.data.borrow_mut();
+        let stream: &Stream = bytemuck::from_bytes_mut(&mut data[..]);
+        let deposit: u64 = stream.deposit.into();
+        let rate_per_slot: u64 = stream.rate_per_slot.into();
+        let start_slot: u64 = stream.start_slot.into();
+        let end_slot: u64 = stream.end_slot.into();
+        let withdrawn: u64 = stream.withdrawn.into();
+        let refunded: u64 = stream.refunded.into();
+        FvStream {
+            deposit: deposit.into(),
+            rate_per_slot: rate_per_slot.into(),
+            start_slot: start_slot.into(),
+            end_slot: end_slot.into(),
+            withdrawn: withdrawn.into(),
+            refunded: refunded.into(),
+            cancelled: stream.cancelled != 0,
+        }
+    }
+}
+
+/// Runs `f` on the stream stored in `acc_info`.
+fn with_stream<T>(acc_info: &AccountInfo, f: impl FnOnce(&mut Stream) -> T) -> T {
+    let mut data = acc_info.data.borrow_mut();
+    f(bytemuck::from_bytes_mut(&mut data[..]))
+}
+
+/// Verifies that the payee can never have withdrawn more than the time
+/// elapsed since the start times the rate.
+#[rule]
+pub fn rule_withdraw_bounded_by_elapsed() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let stream_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let slot: u64 = nondet();
+    let amount: u64 = nondet();
+    with_stream(stream_account, |stream| withdraw(stream, slot, amount)).unwrap();
+
+    let fv_stream: FvStream = stream_account.into();
+    let slot = NativeInt::from(slot);
+    if slot < fv_stream.start_slot {
+        cvlr_assert!(fv_stream.withdrawn == NativeInt::from(0u64));
+    } else {
+        cvlr_assert!(
+            fv_stream.withdrawn <= (slot - fv_stream.start_slot) * fv_stream.rate_per_slot
+        );
+    }
+}
+
+/// Verifies that cancelling refunds the payer exactly the part of the deposit
+/// that has not streamed yet, and that the payee is left with exactly the
+/// streamed part.
+#[rule]
+pub fn rule_cancel_refunds_unstreamed() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let stream_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_stream_pre: FvStream = stream_account.into();
+    assume_funded!(fv_stream_pre);
+    cvlr_assume!(!fv_stream_pre.cancelled);
+
+    let slot: u64 = nondet();
+    let refund = with_stream(stream_account, |stream| cancel(stream, slot)).unwrap();
+
+    let fv_stream_post: FvStream = stream_account.into();
+    let slot = NativeInt::from(slot);
+    let elapsed = if slot <= fv_stream_pre.start_slot {
+        NativeInt::from(0u64)
+    } else if slot >= fv_stream_pre.end_slot {
+        fv_stream_pre.end_slot - fv_stream_pre.start_slot
+    } else {
+        slot - fv_stream_pre.start_slot
+    };
+    let unstreamed = fv_stream_pre.deposit - elapsed * fv_stream_pre.rate_per_slot;
+    cvlr_assert!(NativeInt::from(refund) == unstreamed);
+    cvlr_assert!(fv_stream_post.refunded == unstreamed);
+    // What is left for the payee streams by the new end slot
+    cvlr_assert!(
+        fv_stream_post.refunded
+            + (fv_stream_post.end_slot - fv_stream_post.start_slot) * fv_stream_post.rate_per_slot
+            == fv_stream_pre.deposit
+    );
+}
+
+/// Verifies that a stream cannot be cancelled twice.
+#[rule]
+pub fn rule_cancel_once() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let stream_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_stream: FvStream = stream_account.into();
+    cvlr_assume!(fv_stream.cancelled);
+
+    let slot: u64 = nondet();
+    cvlr_assert!(with_stream(stream_account, |stream| cancel(stream, slot)).is_err());
+}
@@ -0,0 +1,70 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::Stream;
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// Opens a stream from `payer` to `payee` of `rate_per_slot` from
+/// `start_slot` to `end_slot`, funded by the payer's deposit.
+pub fn create_ix(
+    stream: Pubkey,
+    payer: Pubkey,
+    payee: Pubkey,
+    rate_per_slot: u64,
+    start_slot: u64,
+    end_slot: u64,
+) -> Instruction {
+    let mut data = vec![0u8];
+    data.extend_from_slice(&rate_per_slot.to_le_bytes());
+    data.extend_from_slice(&start_slot.to_le_bytes());
+    data.extend_from_slice(&end_slot.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(stream, false),
+            AccountMeta::new_readonly(payer, true),
+            AccountMeta::new_readonly(payee, false),
+        ],
+        data,
+    }
+}
+
+/// Pays `amount` of the streamed funds out to the payee.
+pub fn withdraw_ix(stream: Pubkey, payee: Pubkey, amount: u64) -> Instruction {
+    let mut data = vec![1u8];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(stream, false),
+            AccountMeta::new_readonly(payee, true),
+        ],
+        data,
+    }
+}
+
+/// Stops the stream and refunds the unstreamed remainder to the payer.
+pub fn cancel_ix(stream: Pubkey, payer: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(stream, false),
+            AccountMeta::new_readonly(payer, true),
+        ],
+        data: vec![2u8],
+    }
+}
+
+/// Decodes the data of a Stream account.
+pub fn decode_stream(data: &[u8]) -> Result<Stream, ProgramError> {
+    data.get(..core::mem::size_of::<Stream>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
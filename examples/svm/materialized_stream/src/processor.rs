@@ -0,0 +1,216 @@
+use crate::state::Stream;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use svm_fv_support::cpi;
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Amount streamed by `slot`: `rate_per_slot` for every slot elapsed between
+/// `start_slot` and `end_slot`.
+/// Requires `start_slot <= end_slot` and a deposit covering the whole stream.
+pub fn streamed_amount(rate_per_slot: u64, start_slot: u64, end_slot: u64, slot: u64) -> u64 {
+    let elapsed = slot.clamp(start_slot, end_slot.max(start_slot)) - start_slot;
+    // At most the deposit, which fits a u64
+    ((rate_per_slot as u128) * (elapsed as u128)).min(u64::MAX as u128) as u64
+}
+
+/// Withdraws `amount` to the payee at `slot`, as long as the payee's total
+/// withdrawals stay within the amount streamed so far.
+pub fn withdraw(stream: &mut Stream, slot: u64, amount: u64) -> ProgramResult {
+    let streamed = streamed_amount(
+        stream.rate_per_slot.into(),
+        stream.start_slot.into(),
+        stream.end_slot.into(),
+        slot,
+    );
+    let withdrawn: u64 = stream.withdrawn.into();
+    let new_withdrawn = withdrawn
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if new_withdrawn > streamed {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    stream.withdrawn = new_withdrawn.into();
+
+    Ok(())
+}
+
+/// Cancels the stream at `slot`, refunding the unstreamed remainder to the
+/// payer. Returns the refunded amount.
+pub fn cancel(stream: &mut Stream, slot: u64) -> Result<u64, ProgramError> {
+    if stream.cancelled != 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let start_slot: u64 = stream.start_slot.into();
+    let end_slot: u64 = stream.end_slot.into();
+    let streamed = streamed_amount(stream.rate_per_slot.into(), start_slot, end_slot, slot);
+    let deposit: u64 = stream.deposit.into();
+    let refund = deposit
+        .checked_sub(streamed)
+        .ok_or(ProgramError::InvalidAccountData)?;
+
+    // Stop streaming: nothing streams after the cancellation slot
+    stream.end_slot = slot.clamp(start_slot, end_slot.max(start_slot)).into();
+    stream.refunded = refund.into();
+    stream.cancelled = 1;
+
+    Ok(refund)
+}
+
+/// Process a create instruction.
+/// Opens a stream funded by the payer's deposit.
+///
+/// # Arguments
+/// - `accounts`: Account array (stream account first, signing payer second,
+///   payee third)
+/// - `instruction_data`: 8 bytes representing the rate per slot, followed by
+///   8 bytes each for the start and end slots
+pub fn process_create(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let rate_per_slot = read_u64(instruction_data, 0)?;
+    let start_slot = read_u64(instruction_data, 8)?;
+    let end_slot = read_u64(instruction_data, 16)?;
+    if start_slot > end_slot {
+        return Err(ProgramError::InvalidArgument);
+    }
+    let deposit = rate_per_slot
+        .checked_mul(end_slot - start_slot)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    let stream_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let payer = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let payee = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read stream data
+    let mut stream_data = stream_account.data.borrow_mut();
+    let stream: &mut Stream = bytemuck::from_bytes_mut(&mut stream_data);
+    if stream.payer != Pubkey::default() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    *stream = Stream {
+        payer: *payer.key,
+        payee: *payee.key,
+        deposit: deposit.into(),
+        rate_per_slot: rate_per_slot.into(),
+        start_slot: start_slot.into(),
+        end_slot: end_slot.into(),
+        ..Default::default()
+    };
+
+    Ok(())
+}
+
+/// Process a withdraw instruction.
+/// Pays streamed funds out to the payee.
+///
+/// # Arguments
+/// - `accounts`: Account array (stream account first, signing payee second)
+/// - `instruction_data`: 8 bytes representing the amount to withdraw
+pub fn process_withdraw(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+
+    let stream_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let payee = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !payee.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read stream data
+    let mut stream_data = stream_account.data.borrow_mut();
+    let stream: &mut Stream = bytemuck::from_bytes_mut(&mut stream_data);
+    if stream.payee != *payee.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    withdraw(stream, cpi::sysvar::slot()?, amount)
+}
+
+/// Process a cancel instruction.
+/// Stops the stream and refunds the unstreamed remainder to the payer; the
+/// payee keeps everything streamed so far.
+///
+/// # Arguments
+/// - `accounts`: Account array (stream account first, signing payer second)
+/// - `instruction_data`: unused
+pub fn process_cancel(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    let stream_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let payer = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read stream data
+    let mut stream_data = stream_account.data.borrow_mut();
+    let stream: &mut Stream = bytemuck::from_bytes_mut(&mut stream_data);
+    if stream.payer != *payer.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    cancel(stream, cpi::sysvar::slot()?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(rate_per_slot: u64, start_slot: u64, end_slot: u64) -> Stream {
+        Stream {
+            deposit: (rate_per_slot * (end_slot - start_slot)).into(),
+            rate_per_slot: rate_per_slot.into(),
+            start_slot: start_slot.into(),
+            end_slot: end_slot.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_streamed_amount_clamps_to_schedule() {
+        assert_eq!(streamed_amount(5, 10, 20, 0), 0);
+        assert_eq!(streamed_amount(5, 10, 20, 13), 15);
+        assert_eq!(streamed_amount(5, 10, 20, 1_000), 50);
+    }
+
+    #[test]
+    fn test_withdraw_bounded_by_elapsed() {
+        let mut stream = stream(5, 10, 20);
+        withdraw(&mut stream, 12, 10).unwrap();
+        assert_eq!(
+            withdraw(&mut stream, 12, 1),
+            Err(ProgramError::InsufficientFunds)
+        );
+        withdraw(&mut stream, 14, 10).unwrap();
+        assert_eq!(u64::from(stream.withdrawn), 20);
+    }
+
+    #[test]
+    fn test_cancel_refunds_remainder() {
+        let mut stream = stream(5, 10, 20);
+        assert_eq!(cancel(&mut stream, 16), Ok(20));
+        // The payee keeps what streamed before cancelling, and nothing more
+        withdraw(&mut stream, 100, 30).unwrap();
+        assert_eq!(
+            withdraw(&mut stream, 100, 1),
+            Err(ProgramError::InsufficientFunds)
+        );
+        assert_eq!(
+            cancel(&mut stream, 17),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
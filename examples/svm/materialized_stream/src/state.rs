@@ -0,0 +1,33 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::PodU64;
+
+/// The payment stream account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+///
+/// The payer deposits `rate_per_slot * (end_slot - start_slot)` up front, which
+/// streams to the payee at `rate_per_slot` from `start_slot` to `end_slot`.
+/// Cancelling moves `end_slot` back to the cancellation slot and refunds the
+/// unstreamed remainder to the payer.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Stream {
+    /// The payer (authority allowed to cancel)
+    pub payer: Pubkey,
+    /// The payee (authority allowed to withdraw)
+    pub payee: Pubkey,
+    /// Total amount deposited by the payer
+    pub deposit: PodU64,
+    /// Amount streamed per slot
+    pub rate_per_slot: PodU64,
+    /// Slot at which streaming starts
+    pub start_slot: PodU64,
+    /// Slot at which streaming stops
+    pub end_slot: PodU64,
+    /// Total amount withdrawn by the payee so far
+    pub withdrawn: PodU64,
+    /// Amount refunded to the payer on cancel
+    pub refunded: PodU64,
+    /// Non-zero once the stream has been cancelled
+    pub cancelled: u8,
+}
@@ -0,0 +1,65 @@
+//! End-to-end scenarios of the payment stream: sequences of instructions
+//! sent to the program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_stream::client::{cancel_ix, create_ix, withdraw_ix};
+use solana_stream::state::Stream;
+use solana_stream::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, ScenarioBuilder};
+
+#[test]
+fn withdraw_as_streamed_then_cancel() {
+    let (payer, payee) = (Keypair::new(), Keypair::new());
+    let stream = Pubkey::new_unique();
+    let mut scenario = ScenarioBuilder::new("solana_stream", id(), processor!(process_instruction))
+        .state(stream, &Stream::default())
+        .start();
+    // 2 per slot from slot 1_000 to 2_000
+    let create = create_ix(stream, payer.pubkey(), payee.pubkey(), 2, 1_000, 2_000);
+    scenario.send_instruction(create, &[&payer]).unwrap();
+
+    // Nothing streams before the start
+    let withdraw = |signer: &Keypair, amount| withdraw_ix(stream, signer.pubkey(), amount);
+    assert!(scenario
+        .send_instruction(withdraw(&payee, 1), &[&payee])
+        .is_err());
+    scenario.warp_to_slot(1_500);
+    // Only the payee withdraws, and only what has streamed
+    assert!(scenario
+        .send_instruction(withdraw(&payer, 1_000), &[&payer])
+        .is_err());
+    scenario
+        .send_instruction(withdraw(&payee, 1_000), &[&payee])
+        .unwrap();
+    assert!(scenario
+        .send_instruction(withdraw(&payee, 1_001), &[&payee])
+        .is_err());
+
+    // Only the payer cancels, once, and is refunded what has not streamed
+    assert!(scenario
+        .send_instruction(cancel_ix(stream, payee.pubkey()), &[&payee])
+        .is_err());
+    scenario
+        .send_instruction(cancel_ix(stream, payer.pubkey()), &[&payer])
+        .unwrap();
+    assert!(scenario
+        .send_instruction(cancel_ix(stream, payer.pubkey()), &[&payer])
+        .is_err());
+    let cancelled: Stream = scenario.state(stream);
+    let refunded = u64::from(cancelled.refunded);
+    assert!(refunded <= 1_000);
+
+    // Which the payee cannot withdraw, however late
+    scenario.warp_to_slot(3_000);
+    let rest = 2_000 - refunded - 1_000;
+    assert!(scenario
+        .send_instruction(withdraw(&payee, rest + 1), &[&payee])
+        .is_err());
+    scenario
+        .send_instruction(withdraw(&payee, rest), &[&payee])
+        .unwrap();
+}
@@ -0,0 +1,21 @@
+//! Formal verification module for the token mint.
+
+pub mod spec;
+
+/// Macro to assume the supply-conservation property holds.
+/// Supply: supply == tracked balance + balances of all other accounts
+#[macro_export]
+macro_rules! assume_supply_sum {
+    ($fv_mint:expr, $fv_balance:expr, $others:expr) => {
+        cvlr::cvlr_assume!($fv_mint.supply == $fv_balance.amount + $others);
+    };
+}
+
+/// Macro to assert the supply-conservation property holds.
+/// Supply: supply == tracked balance + balances of all other accounts
+#[macro_export]
+macro_rules! assert_supply_sum {
+    ($fv_mint:expr, $fv_balance:expr, $others:expr) => {
+        cvlr::cvlr_assert!($fv_mint.supply == $fv_balance.amount + $others);
+    };
+}
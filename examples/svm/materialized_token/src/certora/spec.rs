@@ -0,0 +1,113 @@
+//! This module contains the specification for the token mint.
+
+use crate::{
+    assert_supply_sum, assume_supply_sum,
+    processor::*,
+    state::{Mint, TokenAccount},
+};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the mint state for the formal verification (FV) of the
+/// token.
+struct FvMint {
+    supply: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvMint {
+    fn from(acc_info: &AccountInfo) -> FvMint {
+        let mut data = acc_info.data.borrow_mut();
+        let mint: &Mint = bytemuck::from_bytes_mut(&mut data[..]);
+        let supply: u64 = mint.supply.into();
+        FvMint {
+            supply: supply.into(),
+        }
+    }
+}
+
+/// Structure tracking a balance for the formal verification (FV) of the token.
+struct FvBalance {
+    amount: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvBalance {
+    fn from(acc_info: &AccountInfo) -> FvBalance {
+        let mut data = acc_info.data.borrow_mut();
+        let balance: &TokenAccount = bytemuck::from_bytes_mut(&mut data[..]);
+        let amount: u64 = balance.amount.into();
+        FvBalance {
+            amount: amount.into(),
+        }
+    }
+}
+
+/// Verifies that minting keeps the supply equal to the sum of balances.
+#[rule]
+pub fn rule_supply_sum_mint_to() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let mint_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let destination_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    // Sum of the balances of every account not touched by the instruction
+    let others: NativeInt = nondet();
+    let fv_mint_pre: FvMint = mint_account.into();
+    let fv_balance_pre: FvBalance = destination_account.into();
+    assume_supply_sum!(fv_mint_pre, fv_balance_pre, others);
+
+    let amount: u64 = nondet();
+    let mint_instruction_data = &amount.to_le_bytes();
+    process_mint_to(&account_infos, mint_instruction_data).unwrap();
+
+    let fv_mint_post: FvMint = mint_account.into();
+    let fv_balance_post: FvBalance = destination_account.into();
+    assert_supply_sum!(fv_mint_post, fv_balance_post, others);
+}
+
+/// Verifies that burning keeps the supply equal to the sum of balances.
+#[rule]
+pub fn rule_supply_sum_burn() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let mint_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let source_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    // Sum of the balances of every account not touched by the instruction
+    let others: NativeInt = nondet();
+    let fv_mint_pre: FvMint = mint_account.into();
+    let fv_balance_pre: FvBalance = source_account.into();
+    assume_supply_sum!(fv_mint_pre, fv_balance_pre, others);
+
+    let amount: u64 = nondet();
+    let burn_instruction_data = &amount.to_le_bytes();
+    process_burn(&account_infos, burn_instruction_data).unwrap();
+
+    let fv_mint_post: FvMint = mint_account.into();
+    let fv_balance_post: FvBalance = source_account.into();
+    assert_supply_sum!(fv_mint_post, fv_balance_post, others);
+}
+
+/// Verifies that a transfer conserves the sum of the sender and receiver
+/// balances.
+#[rule]
+pub fn rule_transfer_conserves_balances() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let source_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let destination_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_source_pre: FvBalance = source_account.into();
+    let fv_destination_pre: FvBalance = destination_account.into();
+
+    let amount: u64 = nondet();
+    let transfer_instruction_data = &amount.to_le_bytes();
+    process_transfer(&account_infos, transfer_instruction_data).unwrap();
+
+    let fv_source_post: FvBalance = source_account.into();
+    let fv_destination_post: FvBalance = destination_account.into();
+    cvlr_assert!(
+        fv_source_post.amount + fv_destination_post.amount
+            == fv_source_pre.amount + fv_destination_pre.amount
+    );
+}
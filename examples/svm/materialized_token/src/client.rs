@@ -0,0 +1,68 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::{Mint, TokenAccount};
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// The instruction `discriminant` moving `amount` tokens over two accounts
+/// and their signing authority.
+fn instruction(
+    discriminant: u8,
+    first: Pubkey,
+    second: Pubkey,
+    signer: Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![discriminant];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(first, false),
+            AccountMeta::new(second, false),
+            AccountMeta::new_readonly(signer, true),
+        ],
+        data,
+    }
+}
+
+/// Mints `amount` new tokens to `destination`, increasing the supply.
+pub fn mint_to_ix(
+    mint: Pubkey,
+    destination: Pubkey,
+    authority: Pubkey,
+    amount: u64,
+) -> Instruction {
+    instruction(0, mint, destination, authority, amount)
+}
+
+/// Burns `amount` tokens of `source`, decreasing the supply.
+pub fn burn_ix(mint: Pubkey, source: Pubkey, owner: Pubkey, amount: u64) -> Instruction {
+    instruction(1, mint, source, owner, amount)
+}
+
+/// Moves `amount` tokens between two balances of the same mint.
+pub fn transfer_ix(source: Pubkey, destination: Pubkey, owner: Pubkey, amount: u64) -> Instruction {
+    instruction(2, source, destination, owner, amount)
+}
+
+/// Decodes the data of a Mint account.
+pub fn decode_mint(data: &[u8]) -> Result<Mint, ProgramError> {
+    data.get(..core::mem::size_of::<Mint>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a TokenAccount account.
+pub fn decode_token_account(data: &[u8]) -> Result<TokenAccount, ProgramError> {
+    data.get(..core::mem::size_of::<TokenAccount>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
@@ -0,0 +1,143 @@
+use crate::state::{Mint, TokenAccount};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Returns the first two accounts plus a signing authority third, rejecting
+/// aliased first and second accounts.
+fn accounts_with_signer<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+) -> Result<[&'a AccountInfo<'b>; 3], ProgramError> {
+    let first = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let second = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let signer = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if first.key == second.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if !signer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok([first, second, signer])
+}
+
+/// Process a mint-to instruction.
+/// Creates new tokens in the destination balance, increasing the supply.
+///
+/// # Arguments
+/// - `accounts`: Account array (mint account first, destination balance
+///   second, signing mint authority third)
+/// - `instruction_data`: 8 bytes representing the number of tokens to mint
+pub fn process_mint_to(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let [mint_account, destination_account, authority] = accounts_with_signer(accounts)?;
+
+    // Borrow and read mint and balance data
+    let mut mint_data = mint_account.data.borrow_mut();
+    let mint: &mut Mint = bytemuck::from_bytes_mut(&mut mint_data);
+    let mut destination_data = destination_account.data.borrow_mut();
+    let destination: &mut TokenAccount = bytemuck::from_bytes_mut(&mut destination_data);
+
+    if mint.authority != *authority.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if destination.mint != *mint_account.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Supply bounds every balance, so checking the supply suffices
+    let supply: u64 = mint.supply.into();
+    let balance: u64 = destination.amount.into();
+    mint.supply = supply
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    destination.amount = balance
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+
+    Ok(())
+}
+
+/// Process a burn instruction.
+/// Destroys tokens from the source balance, decreasing the supply.
+///
+/// # Arguments
+/// - `accounts`: Account array (mint account first, source balance second,
+///   signing balance owner third)
+/// - `instruction_data`: 8 bytes representing the number of tokens to burn
+pub fn process_burn(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let [mint_account, source_account, owner] = accounts_with_signer(accounts)?;
+
+    // Borrow and read mint and balance data
+    let mut mint_data = mint_account.data.borrow_mut();
+    let mint: &mut Mint = bytemuck::from_bytes_mut(&mut mint_data);
+    let mut source_data = source_account.data.borrow_mut();
+    let source: &mut TokenAccount = bytemuck::from_bytes_mut(&mut source_data);
+
+    if source.owner != *owner.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if source.mint != *mint_account.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let supply: u64 = mint.supply.into();
+    let balance: u64 = source.amount.into();
+    if amount > balance || amount > supply {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    mint.supply = (supply - amount).into();
+    source.amount = (balance - amount).into();
+
+    Ok(())
+}
+
+/// Process a transfer instruction.
+/// Moves tokens between two balances of the same mint.
+///
+/// # Arguments
+/// - `accounts`: Account array (source balance first, destination balance
+///   second, signing source owner third)
+/// - `instruction_data`: 8 bytes representing the number of tokens to move
+pub fn process_transfer(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let amount = read_u64(instruction_data, 0)?;
+    let [source_account, destination_account, owner] = accounts_with_signer(accounts)?;
+
+    // Borrow and read balance data
+    let mut source_data = source_account.data.borrow_mut();
+    let source: &mut TokenAccount = bytemuck::from_bytes_mut(&mut source_data);
+    let mut destination_data = destination_account.data.borrow_mut();
+    let destination: &mut TokenAccount = bytemuck::from_bytes_mut(&mut destination_data);
+
+    if source.owner != *owner.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if source.mint != destination.mint {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let source_balance: u64 = source.amount.into();
+    let destination_balance: u64 = destination.amount.into();
+    if amount > source_balance {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    source.amount = (source_balance - amount).into();
+    destination.amount = destination_balance
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+
+    Ok(())
+}
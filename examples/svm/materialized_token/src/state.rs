@@ -0,0 +1,47 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::PodU64;
+
+/// The mint account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Mint {
+    /// The mint authority, the only key allowed to mint new tokens
+    pub authority: Pubkey,
+    /// Total supply of tokens outstanding
+    pub supply: PodU64,
+}
+
+impl Mint {
+    /// Create a new mint with zero supply
+    pub fn new(authority: Pubkey) -> Self {
+        Mint {
+            authority,
+            supply: PodU64::from(0),
+        }
+    }
+}
+
+/// The token balance account data structure.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct TokenAccount {
+    /// The mint this balance is denominated in
+    pub mint: Pubkey,
+    /// The owner allowed to transfer or burn the balance
+    pub owner: Pubkey,
+    /// Token balance
+    pub amount: PodU64,
+}
+
+impl TokenAccount {
+    /// Create a new, empty balance account
+    pub fn new(mint: Pubkey, owner: Pubkey) -> Self {
+        TokenAccount {
+            mint,
+            owner,
+            amount: PodU64::from(0),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! End-to-end scenarios of the token: sequences of instructions sent to the
+//! program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_token::client::{burn_ix, mint_to_ix, transfer_ix};
+use solana_token::state::{Mint, TokenAccount};
+use solana_token::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, Scenario, ScenarioBuilder};
+
+/// The balance of the token account at `key`.
+fn balance(scenario: &mut Scenario, key: Pubkey) -> u64 {
+    scenario.state::<TokenAccount>(key).amount.into()
+}
+
+#[test]
+fn mint_transfer_burn() {
+    let (authority, alice, bob) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let (mint, other_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (alice_account, bob_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    // An account of Bob for another token
+    let stray = Pubkey::new_unique();
+    let mut scenario = ScenarioBuilder::new("solana_token", id(), processor!(process_instruction))
+        .state(mint, &Mint::new(authority.pubkey()))
+        .state(alice_account, &TokenAccount::new(mint, alice.pubkey()))
+        .state(bob_account, &TokenAccount::new(mint, bob.pubkey()))
+        .state(stray, &TokenAccount::new(other_mint, bob.pubkey()))
+        .start();
+
+    // Only the authority mints
+    let mint_to = |signer: &Keypair| mint_to_ix(mint, alice_account, signer.pubkey(), 100);
+    assert!(scenario
+        .send_instruction(mint_to(&alice), &[&alice])
+        .is_err());
+    scenario
+        .send_instruction(mint_to(&authority), &[&authority])
+        .unwrap();
+
+    // Only the owner transfers, within the balance and the token
+    let transfer = |destination, signer: &Keypair, amount| {
+        transfer_ix(alice_account, destination, signer.pubkey(), amount)
+    };
+    assert!(scenario
+        .send_instruction(transfer(bob_account, &bob, 30), &[&bob])
+        .is_err());
+    assert!(scenario
+        .send_instruction(transfer(bob_account, &alice, 101), &[&alice])
+        .is_err());
+    assert!(scenario
+        .send_instruction(transfer(stray, &alice, 30), &[&alice])
+        .is_err());
+    scenario
+        .send_instruction(transfer(bob_account, &alice, 30), &[&alice])
+        .unwrap();
+    assert_eq!(balance(&mut scenario, alice_account), 70);
+    assert_eq!(balance(&mut scenario, bob_account), 30);
+
+    // Burning takes the tokens out of the supply
+    let burn = |signer: &Keypair, amount| burn_ix(mint, alice_account, signer.pubkey(), amount);
+    assert!(scenario.send_instruction(burn(&bob, 20), &[&bob]).is_err());
+    assert!(scenario
+        .send_instruction(burn(&alice, 71), &[&alice])
+        .is_err());
+    scenario
+        .send_instruction(burn(&alice, 20), &[&alice])
+        .unwrap();
+    assert_eq!(balance(&mut scenario, alice_account), 50);
+    assert_eq!(u64::from(scenario.state::<Mint>(mint).supply), 80);
+}
@@ -0,0 +1,48 @@
+//! Fuzzes `process_deposit` with arbitrary account and instruction data.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use svm_fv_support::{infos, state, TestAccount};
+
+const VAULT_LEN: usize = core::mem::size_of::<solana_vault::state::Vault>();
+const DEPOSITOR_LEN: usize = core::mem::size_of::<solana_vault::state::Depositor>();
+
+fuzz_target!(|data: &[u8]| {
+    let program_id = solana_vault::id();
+    if data.len() < VAULT_LEN + DEPOSITOR_LEN {
+        return;
+    }
+    let (vault_state, vault_rest) = data.split_at(VAULT_LEN);
+    let (depositor_state, depositor_rest) = vault_rest.split_at(DEPOSITOR_LEN);
+    let instruction_data = depositor_rest;
+
+    let vault = TestAccount::new(program_id).data(vault_state.to_vec());
+    let owner = TestAccount::wallet().readonly();
+    // The depositor account of the owner, holding any shares
+    let (depositor_key, _) =
+        solana_vault::state::Depositor::address(&vault.pubkey(), &owner.pubkey());
+    let mut depositor: solana_vault::state::Depositor =
+        bytemuck::pod_read_unaligned(depositor_state);
+    depositor.owner = owner.pubkey();
+    depositor.vault = vault.pubkey();
+    let depositor = TestAccount::new(program_id)
+        .key(depositor_key)
+        .state(&depositor);
+
+    let mut accounts = [vault, depositor, owner];
+    let accounts = infos(&mut accounts);
+    let solvency_held = {
+        let vault: solana_vault::state::Vault = state(&accounts[0]);
+        u64::from(vault.shares_total) <= u64::from(vault.token_total)
+    };
+    let result = solana_vault::processor::process_deposit(&accounts, instruction_data);
+
+    if result.is_ok() {
+        let vault: solana_vault::state::Vault = state(&accounts[0]);
+        assert!(
+            !solvency_held || u64::from(vault.shares_total) <= u64::from(vault.token_total),
+            "invariant `solvency` violated"
+        );
+    }
+});
@@ -0,0 +1,33 @@
+//! Fuzzes `process_reward` with arbitrary account and instruction data.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use svm_fv_support::{infos, state, TestAccount};
+
+const VAULT_LEN: usize = core::mem::size_of::<solana_vault::state::Vault>();
+
+fuzz_target!(|data: &[u8]| {
+    let program_id = solana_vault::id();
+    if data.len() < VAULT_LEN {
+        return;
+    }
+    let (vault_state, vault_rest) = data.split_at(VAULT_LEN);
+    let instruction_data = vault_rest;
+
+    let mut accounts = [TestAccount::new(program_id).data(vault_state.to_vec())];
+    let accounts = infos(&mut accounts);
+    let solvency_held = {
+        let vault: solana_vault::state::Vault = state(&accounts[0]);
+        u64::from(vault.shares_total) <= u64::from(vault.token_total)
+    };
+    let result = solana_vault::processor::process_reward(&accounts, instruction_data);
+
+    if result.is_ok() {
+        let vault: solana_vault::state::Vault = state(&accounts[0]);
+        assert!(
+            !solvency_held || u64::from(vault.shares_total) <= u64::from(vault.token_total),
+            "invariant `solvency` violated"
+        );
+    }
+});
@@ -1,6 +1,10 @@
 //! This module contains the specification for the vault application.
 
-use crate::{assert_solvency, assume_solvency, processor::*, state::Vault};
+use crate::{
+    assert_solvency, assume_solvency,
+    processor::*,
+    state::{Depositor, QueuedClaim, Vault, WithdrawalQueue, BPS_DENOMINATOR},
+};
 use cvlr::{mathint::NativeInt, prelude::*};
 use cvlr_solana::cvlr_deserialize_nondet_accounts;
 use solana_program::account_info::{next_account_info, AccountInfo};
@@ -30,6 +34,60 @@ impl<'a> From<&AccountInfo<'a>> for FvVault {
     }
 }
 
+/// Structure tracking the depositor state for the formal verification (FV) of
+/// the vault.
+struct FvDepositor {
+    shares: NativeInt,
+    deposited: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvDepositor {
+    fn from(acc_info: &AccountInfo) -> FvDepositor {
+        let mut data = acc_info.data.borrow_mut();
+        let depositor: &Depositor = bytemuck::from_bytes_mut(&mut data[..]);
+        let shares: u64 = depositor.shares.into();
+        let deposited: u64 = depositor.deposited.into();
+        FvDepositor {
+            shares: shares.into(),
+            deposited: deposited.into(),
+        }
+    }
+}
+
+/// Structure tracking the withdrawal queue state for the formal verification
+/// (FV) of the vault.
+struct FvQueue {
+    head: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvQueue {
+    fn from(acc_info: &AccountInfo) -> FvQueue {
+        let mut data = acc_info.data.borrow_mut();
+        let queue: &WithdrawalQueue = bytemuck::from_bytes_mut(&mut data[..]);
+        let head: u64 = queue.head.into();
+        FvQueue { head: head.into() }
+    }
+}
+
+/// Structure tracking the queued claim state for the formal verification (FV)
+/// of the vault.
+struct FvClaim {
+    position: NativeInt,
+    paid: bool,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvClaim {
+    fn from(acc_info: &AccountInfo) -> FvClaim {
+        let mut data = acc_info.data.borrow_mut();
+        let claim: &QueuedClaim = bytemuck::from_bytes_mut(&mut data[..]);
+        let position: u64 = claim.position.into();
+        FvClaim {
+            position: position.into(),
+            paid: claim.paid != 0,
+        }
+    }
+}
+
 /// Verifies that a vault account remains solvent before and after a withdrawal
 /// operation.
 #[rule]
@@ -68,6 +126,37 @@ pub fn rule_vault_solvency_deposit() {
     assert_solvency!(fv_vault_post);
 }
 
+/// Verifies that a deposit credits the depositor with exactly the shares it
+/// mints, and a withdrawal debits them with exactly the shares it burns, so
+/// that depositors never hold more shares than the vault counts.
+#[rule]
+pub fn rule_vault_depositor_shares() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let vault_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let depositor_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_vault_pre: FvVault = vault_account.into();
+    let fv_depositor_pre: FvDepositor = depositor_account.into();
+    cvlr_assume!(fv_depositor_pre.shares <= fv_vault_pre.shares_total);
+
+    let amount: u64 = nondet();
+    let instruction_data = &amount.to_le_bytes();
+    if nondet::<bool>() {
+        process_deposit(&account_infos, instruction_data).unwrap();
+    } else {
+        process_withdraw(&account_infos, instruction_data).unwrap();
+    }
+
+    let fv_vault_post: FvVault = vault_account.into();
+    let fv_depositor_post: FvDepositor = depositor_account.into();
+    cvlr_assert!(
+        fv_depositor_post.shares + fv_vault_pre.shares_total
+            == fv_depositor_pre.shares + fv_vault_post.shares_total
+    );
+    cvlr_assert!(fv_depositor_post.shares <= fv_vault_post.shares_total);
+}
+
 /// Verifies that a vault account remains solvent before and after a reward
 /// operation.
 #[rule]
@@ -106,3 +195,268 @@ pub fn rule_vault_solvency_slash() {
     let fv_vault_post: FvVault = vault_account.into();
     assert_solvency!(fv_vault_post);
 }
+
+/// Verifies that a flash loan never decreases the vault's tokens.
+#[rule]
+pub fn rule_vault_flash_loan_token_total() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let vault_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_vault_pre: FvVault = vault_account.into();
+
+    let token: u64 = nondet();
+    let token_instruction_data = &token.to_le_bytes();
+    process_flash_loan(&account_infos, token_instruction_data).unwrap();
+
+    let fv_vault_post: FvVault = vault_account.into();
+    cvlr_assert!(fv_vault_post.token_total >= fv_vault_pre.token_total);
+}
+
+/// Verifies that a completed flash loan has been repaid with its fee.
+#[rule]
+pub fn rule_vault_flash_loan_repaid() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let vault_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let lamports_pre: NativeInt = vault_account.lamports().into();
+
+    let token: u64 = nondet();
+    let token_instruction_data = &token.to_le_bytes();
+    process_flash_loan(&account_infos, token_instruction_data).unwrap();
+
+    let lamports_post: NativeInt = vault_account.lamports().into();
+    cvlr_assert!(lamports_post >= lamports_pre + NativeInt::from(flash_loan_fee(token)));
+}
+
+/// Verifies that transferring shares conserves the shares of the two
+/// depositors and leaves the vault untouched.
+#[rule]
+pub fn rule_vault_transfer_shares_conserves() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let vault_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let source_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let destination_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_vault_pre: FvVault = vault_account.into();
+    let fv_source_pre: FvDepositor = source_account.into();
+    let fv_destination_pre: FvDepositor = destination_account.into();
+
+    let shares: u64 = nondet();
+    let shares_instruction_data = &shares.to_le_bytes();
+    process_transfer_shares(&account_infos, shares_instruction_data).unwrap();
+
+    let fv_vault_post: FvVault = vault_account.into();
+    let fv_source_post: FvDepositor = source_account.into();
+    let fv_destination_post: FvDepositor = destination_account.into();
+    cvlr_assert!(
+        fv_source_post.shares + fv_destination_post.shares
+            == fv_source_pre.shares + fv_destination_pre.shares
+    );
+    cvlr_assert!(fv_vault_post.shares_total == fv_vault_pre.shares_total);
+    cvlr_assert!(fv_vault_post.token_total == fv_vault_pre.token_total);
+}
+
+/// Verifies that the recipient of a transfer gains no more shares than the
+/// sender held.
+#[rule]
+pub fn rule_vault_transfer_shares_no_creation() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let _vault_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let source_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let destination_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_source_pre: FvDepositor = source_account.into();
+    let fv_destination_pre: FvDepositor = destination_account.into();
+
+    let shares: u64 = nondet();
+    let shares_instruction_data = &shares.to_le_bytes();
+    process_transfer_shares(&account_infos, shares_instruction_data).unwrap();
+
+    let fv_destination_post: FvDepositor = destination_account.into();
+    cvlr_assert!(fv_destination_post.shares - fv_destination_pre.shares <= fv_source_pre.shares);
+}
+
+/// Verifies that a vault account remains solvent before and after a queued
+/// withdrawal request.
+#[rule]
+pub fn rule_vault_solvency_request_withdrawal() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let vault_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_vault_pre: FvVault = vault_account.into();
+    assume_solvency!(fv_vault_pre);
+
+    let shares: u64 = nondet();
+    let shares_instruction_data = &shares.to_le_bytes();
+    process_request_withdrawal(&account_infos, shares_instruction_data).unwrap();
+
+    let fv_vault_post: FvVault = vault_account.into();
+    assert_solvency!(fv_vault_post);
+}
+
+/// Verifies that a queued withdrawal request burns only shares of the
+/// claimant's depositor account.
+#[rule]
+pub fn rule_vault_request_withdrawal_debits_claimant() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let vault_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let _queue_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let _claim_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let _owner: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let depositor_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_vault_pre: FvVault = vault_account.into();
+    let fv_depositor_pre: FvDepositor = depositor_account.into();
+
+    let shares: u64 = nondet();
+    let shares_instruction_data = &shares.to_le_bytes();
+    process_request_withdrawal(&account_infos, shares_instruction_data).unwrap();
+
+    let fv_vault_post: FvVault = vault_account.into();
+    let fv_depositor_post: FvDepositor = depositor_account.into();
+    cvlr_assert!(fv_depositor_post.shares + NativeInt::from(shares) == fv_depositor_pre.shares);
+    cvlr_assert!(fv_vault_post.shares_total + NativeInt::from(shares) == fv_vault_pre.shares_total);
+}
+
+/// Verifies that queued claims are serviced in FIFO order: only the claim at
+/// the front of the queue can be paid, and paying it advances the queue.
+#[rule]
+pub fn rule_vault_queue_fifo() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let _vault_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let queue_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let claim_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_queue_pre: FvQueue = queue_account.into();
+    let fv_claim_pre: FvClaim = claim_account.into();
+
+    process_service_withdrawal(&account_infos, &[]).unwrap();
+
+    let fv_queue_post: FvQueue = queue_account.into();
+    let fv_claim_post: FvClaim = claim_account.into();
+    cvlr_assert!(fv_claim_pre.position == fv_queue_pre.head);
+    cvlr_assert!(fv_queue_post.head == fv_queue_pre.head + NativeInt::from(1u64));
+    cvlr_assert!(fv_claim_post.paid);
+}
+
+/// Verifies that a queued claim can never be paid twice.
+#[rule]
+pub fn rule_vault_queue_paid_once() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+
+    process_service_withdrawal(&account_infos, &[]).unwrap();
+    cvlr_assert!(process_service_withdrawal(&account_infos, &[]).is_err());
+}
+
+/// Verifies that a claim is only serviced from the vault's canonical queue:
+/// the queue PDA of the vault, owned by the program.
+#[rule]
+pub fn rule_vault_queue_canonical() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let vault_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let queue_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    process_service_withdrawal(&account_infos, &[]).unwrap();
+
+    cvlr_assert!(*queue_account.key == WithdrawalQueue::address(vault_account.key).0);
+    cvlr_assert!(*queue_account.owner == crate::id());
+}
+
+/// Verifies that the fee shares minted on a reward never dilute the shares
+/// that existed before: they keep their value, and gain at least the non-fee
+/// part of the reward.
+#[rule]
+pub fn rule_vault_performance_fee_bounded() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let vault_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_vault_pre: FvVault = vault_account.into();
+    let performance_fee_bps: NativeInt = {
+        let data = vault_account.data.borrow();
+        let vault: &Vault = bytemuck::from_bytes(&data[..]);
+        (u16::from(vault.performance_fee_bps) as u64).into()
+    };
+    let bps = NativeInt::from(BPS_DENOMINATOR);
+    cvlr_assume!(performance_fee_bps <= bps);
+
+    let token: u64 = nondet();
+    let reward = NativeInt::from(token);
+    cvlr_assume!(fv_vault_pre.token_total + reward <= NativeInt::u64_max());
+    let token_instruction_data = &token.to_le_bytes();
+    process_reward(&account_infos, token_instruction_data).unwrap();
+
+    let fv_vault_post: FvVault = vault_account.into();
+    // shares_pre * token_total_post / shares_total_post
+    //     >= token_total_pre + reward * (1 - performance_fee_bps / BPS)
+    cvlr_assert!(
+        fv_vault_pre.shares_total * fv_vault_post.token_total * bps
+            >= fv_vault_post.shares_total
+                * (fv_vault_pre.token_total * bps + reward * (bps - performance_fee_bps))
+    );
+}
+
+/// Verifies that claiming the fee shares credits all of them to the owner's
+/// depositor account, where they can be withdrawn, without minting any.
+#[rule]
+pub fn rule_vault_claim_fee_shares() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let vault_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let depositor_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let fee_shares = || -> NativeInt {
+        let data = vault_account.data.borrow();
+        let vault: &Vault = bytemuck::from_bytes(&data[..]);
+        u64::from(vault.fee_shares).into()
+    };
+
+    let fv_vault_pre: FvVault = vault_account.into();
+    let fv_depositor_pre: FvDepositor = depositor_account.into();
+    let fee_shares_pre = fee_shares();
+
+    process_claim_fee_shares(&account_infos, &[]).unwrap();
+
+    let fv_vault_post: FvVault = vault_account.into();
+    let fv_depositor_post: FvDepositor = depositor_account.into();
+    cvlr_assert!(fv_depositor_post.shares == fv_depositor_pre.shares + fee_shares_pre);
+    cvlr_assert!(fee_shares() == NativeInt::from(0u64));
+    cvlr_assert!(fv_vault_post.shares_total == fv_vault_pre.shares_total);
+}
+
+/// Verifies that a deposit always counts towards the depositor's lifetime
+/// deposits, and that while the deposit cap is enabled it never brings them
+/// above the cap.
+#[rule]
+pub fn rule_vault_deposit_cap() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let vault_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+    let depositor_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let deposit_cap: NativeInt = {
+        let data = vault_account.data.borrow();
+        let vault: &Vault = bytemuck::from_bytes(&data[..]);
+        u64::from(vault.deposit_cap).into()
+    };
+    let fv_depositor_pre: FvDepositor = depositor_account.into();
+
+    let token: u64 = nondet();
+    let token_instruction_data = &token.to_le_bytes();
+    process_deposit(&account_infos, token_instruction_data).unwrap();
+
+    let fv_depositor_post: FvDepositor = depositor_account.into();
+    if deposit_cap > NativeInt::from(0u64) {
+        cvlr_assert!(fv_depositor_post.deposited <= deposit_cap);
+    }
+    cvlr_assert!(
+        fv_depositor_post.deposited == fv_depositor_pre.deposited + NativeInt::from(token)
+    );
+}
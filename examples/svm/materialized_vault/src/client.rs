@@ -0,0 +1,242 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+
+use crate::state::{Depositor, QueuedClaim, Vault, WithdrawalQueue};
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// The depositor account of `owner` in `vault`.
+fn depositor(vault: Pubkey, owner: Pubkey) -> Pubkey {
+    Depositor::address(&vault, &owner).0
+}
+
+/// The withdrawal queue of `vault`.
+fn queue(vault: Pubkey) -> Pubkey {
+    WithdrawalQueue::address(&vault).0
+}
+
+/// The data of instruction `discriminant` followed by `args`.
+fn data(discriminant: u8, args: &[u8]) -> Vec<u8> {
+    [[discriminant].as_slice(), args].concat()
+}
+
+/// Deposits tokens and mints shares to the depositor account of `owner`.
+pub fn deposit_ix(vault: Pubkey, owner: Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new(depositor(vault, owner), false),
+            AccountMeta::new_readonly(owner, true),
+        ],
+        data: data(0, &amount.to_le_bytes()),
+    }
+}
+
+/// Burns shares of the depositor account of `owner` and returns the
+/// corresponding tokens.
+pub fn withdraw_ix(vault: Pubkey, owner: Pubkey, shares: u64) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new(depositor(vault, owner), false),
+            AccountMeta::new_readonly(owner, true),
+        ],
+        data: data(1, &shares.to_le_bytes()),
+    }
+}
+
+/// Adds tokens to the vault, minting fee shares for the owner.
+pub fn reward_ix(vault: Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new(vault, false)],
+        data: data(2, &amount.to_le_bytes()),
+    }
+}
+
+/// Removes tokens from the vault without burning shares.
+pub fn slash_ix(vault: Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new(vault, false)],
+        data: data(3, &amount.to_le_bytes()),
+    }
+}
+
+/// Lends tokens to `borrower` for the duration of a call into
+/// `receiver_program`. Any accounts the receiver needs are pushed onto the
+/// accounts of the instruction after the borrower.
+pub fn flash_loan_ix(
+    vault: Pubkey,
+    receiver_program: Pubkey,
+    borrower: Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(receiver_program, false),
+            AccountMeta::new(borrower, false),
+        ],
+        data: data(4, &amount.to_le_bytes()),
+    }
+}
+
+/// Moves shares from the depositor account of `owner` to the depositor
+/// account `destination` of the same vault.
+pub fn transfer_shares_ix(
+    vault: Pubkey,
+    owner: Pubkey,
+    destination: Pubkey,
+    shares: u64,
+) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(vault, false),
+            AccountMeta::new(depositor(vault, owner), false),
+            AccountMeta::new(destination, false),
+            AccountMeta::new_readonly(owner, true),
+        ],
+        data: data(5, &shares.to_le_bytes()),
+    }
+}
+
+/// Burns shares of the depositor account of `owner` and queues a claim for
+/// their tokens in the fresh account `claim`, at the back of the withdrawal
+/// queue of `vault`.
+pub fn request_withdrawal_ix(
+    vault: Pubkey,
+    claim: Pubkey,
+    owner: Pubkey,
+    shares: u64,
+) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new(queue(vault), false),
+            AccountMeta::new(claim, false),
+            AccountMeta::new_readonly(owner, true),
+            AccountMeta::new(depositor(vault, owner), false),
+        ],
+        data: data(6, &shares.to_le_bytes()),
+    }
+}
+
+/// Settles `claim`, the claim at the front of the withdrawal queue of
+/// `vault`.
+pub fn service_withdrawal_ix(vault: Pubkey, claim: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new(queue(vault), false),
+            AccountMeta::new(claim, false),
+        ],
+        data: data(7, &[]),
+    }
+}
+
+/// Sets the share of future rewards charged as performance fee, in bps.
+pub fn set_performance_fee_ix(vault: Pubkey, owner: Pubkey, fee_bps: u16) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(owner, true),
+        ],
+        data: data(8, &fee_bps.to_le_bytes()),
+    }
+}
+
+/// Sets the cap on each depositor's lifetime deposits; zero disables it.
+pub fn set_deposit_cap_ix(vault: Pubkey, owner: Pubkey, cap: u64) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(owner, true),
+        ],
+        data: data(9, &cap.to_le_bytes()),
+    }
+}
+
+/// Creates the depositor account of `owner` in `vault`, paid for by `owner`.
+pub fn init_depositor_ix(vault: Pubkey, owner: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(vault, false),
+            AccountMeta::new(depositor(vault, owner), false),
+            AccountMeta::new(owner, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: data(10, &[]),
+    }
+}
+
+/// Credits the fee shares of the vault owner to their depositor account.
+pub fn claim_fee_shares_ix(vault: Pubkey, owner: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new(depositor(vault, owner), false),
+            AccountMeta::new_readonly(owner, true),
+        ],
+        data: data(11, &[]),
+    }
+}
+
+/// Creates the withdrawal queue of `vault`, paid for by `payer`.
+pub fn init_queue_ix(vault: Pubkey, payer: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(vault, false),
+            AccountMeta::new(queue(vault), false),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: data(12, &[]),
+    }
+}
+
+/// Decodes the data of a Vault account.
+pub fn decode_vault(data: &[u8]) -> Result<Vault, ProgramError> {
+    data.get(..core::mem::size_of::<Vault>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a Depositor account.
+pub fn decode_depositor(data: &[u8]) -> Result<Depositor, ProgramError> {
+    data.get(..core::mem::size_of::<Depositor>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a WithdrawalQueue account.
+pub fn decode_withdrawal_queue(data: &[u8]) -> Result<WithdrawalQueue, ProgramError> {
+    data.get(..core::mem::size_of::<WithdrawalQueue>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a QueuedClaim account.
+pub fn decode_queued_claim(data: &[u8]) -> Result<QueuedClaim, ProgramError> {
+    data.get(..core::mem::size_of::<QueuedClaim>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
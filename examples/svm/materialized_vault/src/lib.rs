@@ -1,5 +1,6 @@
 use solana_program::{
-    account_info::AccountInfo, declare_id, entrypoint::ProgramResult, pubkey::Pubkey,
+    account_info::AccountInfo, declare_id, entrypoint::ProgramResult,
+    program_error::ProgramError, pubkey::Pubkey,
 };
 pub mod processor;
 pub mod state;
@@ -8,6 +9,10 @@ pub mod state;
 #[cfg(feature = "certora")]
 pub mod certora;
 
+// Include the off-chain client module only if client feature is enabled.
+#[cfg(feature = "client")]
+pub mod client;
+
 #[cfg(not(feature = "certora"))]
 use solana_program::msg;
 // If certora feature is enabled, msg should be substituted with `clog!`.
@@ -27,8 +32,10 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    let (instruction_discriminant, instruction_data) = instruction_data.split_at(1);
-    match instruction_discriminant[0] {
+    let (instruction_discriminant, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    match instruction_discriminant {
         0 => {
             msg!("Instruction: deposit");
             processor::process_deposit(accounts, instruction_data)?;
@@ -45,8 +52,45 @@ pub fn process_instruction(
             msg!("Instruction: slash");
             processor::process_slash(accounts, instruction_data)?;
         }
+        4 => {
+            msg!("Instruction: flash loan");
+            processor::process_flash_loan(accounts, instruction_data)?;
+        }
+        5 => {
+            msg!("Instruction: transfer shares");
+            processor::process_transfer_shares(accounts, instruction_data)?;
+        }
+        6 => {
+            msg!("Instruction: request withdrawal");
+            processor::process_request_withdrawal(accounts, instruction_data)?;
+        }
+        7 => {
+            msg!("Instruction: service withdrawal");
+            processor::process_service_withdrawal(accounts, instruction_data)?;
+        }
+        8 => {
+            msg!("Instruction: set performance fee");
+            processor::process_set_performance_fee(accounts, instruction_data)?;
+        }
+        9 => {
+            msg!("Instruction: set deposit cap");
+            processor::process_set_deposit_cap(accounts, instruction_data)?;
+        }
+        10 => {
+            msg!("Instruction: init depositor");
+            processor::process_init_depositor(accounts, instruction_data)?;
+        }
+        11 => {
+            msg!("Instruction: claim fee shares");
+            processor::process_claim_fee_shares(accounts, instruction_data)?;
+        }
+        12 => {
+            msg!("Instruction: init queue");
+            processor::process_init_queue(accounts, instruction_data)?;
+        }
         _ => {
-            msg!("Error: unknown instruction")
+            msg!("Error: unknown instruction");
+            return Err(ProgramError::InvalidInstructionData);
         }
     }
     Ok(())
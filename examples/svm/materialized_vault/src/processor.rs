@@ -1,87 +1,308 @@
-use crate::state::Vault;
+use crate::state::{
+    Depositor, QueuedClaim, Vault, WithdrawalQueue, BPS_DENOMINATOR, FLASH_LOAN_FEE_BPS,
+};
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    account_info::AccountInfo, entrypoint::ProgramResult, program::invoke_signed,
+    program_error::ProgramError, pubkey::Pubkey, rent::Rent, system_instruction,
+    sysvar::Sysvar,
 };
-use bytemuck::Pod;
+use svm_vault_math::{mul_div_ceil, mul_div_floor, shares_for_deposit, tokens_for_shares};
+
+/// Checks that `depositor_account` is the depositor account of `owner` in the
+/// vault at `vault`: the PDA derived from both, owned by the program. Any
+/// other account, e.g. a fresh one, would let the owner keep a second ledger.
+fn check_depositor_account(
+    vault: &Pubkey,
+    depositor_account: &AccountInfo,
+    owner: &Pubkey,
+) -> ProgramResult {
+    if *depositor_account.owner != crate::id() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if *depositor_account.key != Depositor::address(vault, owner).0 {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+/// Checks that `queue_account` is the withdrawal queue of the vault at
+/// `vault`: the PDA derived from it, owned by the program. Any other account,
+/// e.g. a fresh one, would let a claimant open a second queue and sit at its
+/// front.
+fn check_queue_account(vault: &Pubkey, queue_account: &AccountInfo) -> ProgramResult {
+    if *queue_account.owner != crate::id() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if *queue_account.key != WithdrawalQueue::address(vault).0 {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+/// Process an init-depositor instruction.
+/// Creates the depositor account of the signing owner in the vault, at the
+/// PDA derived from the vault and the owner, who pays for it. Each owner thus
+/// has a single depositor account per vault.
+///
+/// # Arguments
+/// - `accounts`: Account array (vault account first, depositor account
+///   second, signing owner third, system program fourth)
+/// - `instruction_data`: unused
+pub fn process_init_depositor(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    // Get vault, depositor, owner and system program accounts
+    let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let depositor_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let owner = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let system_program = accounts.get(3).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *vault_account.owner != crate::id() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    let (address, bump) = Depositor::address(vault_account.key, owner.key);
+    if *depositor_account.key != address {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the account, unless it was already
+    if depositor_account.data_is_empty() {
+        let space = core::mem::size_of::<Depositor>();
+        invoke_signed(
+            &system_instruction::create_account(
+                owner.key,
+                &address,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                &crate::id(),
+            ),
+            &[owner.clone(), depositor_account.clone(), system_program.clone()],
+            &[&[
+                Depositor::SEED,
+                vault_account.key.as_ref(),
+                owner.key.as_ref(),
+                &[bump],
+            ]],
+        )?;
+    }
+    check_depositor_account(vault_account.key, depositor_account, owner.key)?;
+
+    // Borrow and write depositor data
+    let mut depositor_data = depositor_account.data.borrow_mut();
+    let depositor: &mut Depositor = bytemuck::from_bytes_mut(&mut depositor_data);
+    if depositor.vault != Pubkey::default() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    *depositor = Depositor {
+        owner: *owner.key,
+        vault: *vault_account.key,
+        ..Depositor::default()
+    };
+
+    Ok(())
+}
+
+/// Process an init-queue instruction.
+/// Creates the withdrawal queue of the vault, at the PDA derived from the
+/// vault, paid for by the signing payer. Each vault thus has a single queue.
+///
+/// # Arguments
+/// - `accounts`: Account array (vault account first, queue account second,
+///   signing payer third, system program fourth)
+/// - `instruction_data`: unused
+pub fn process_init_queue(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    // Get vault, queue, payer and system program accounts
+    let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let queue_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let payer = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let system_program = accounts.get(3).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *vault_account.owner != crate::id() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    let (address, bump) = WithdrawalQueue::address(vault_account.key);
+    if *queue_account.key != address {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the account, unless it was already
+    if queue_account.data_is_empty() {
+        let space = core::mem::size_of::<WithdrawalQueue>();
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                &address,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                &crate::id(),
+            ),
+            &[payer.clone(), queue_account.clone(), system_program.clone()],
+            &[&[WithdrawalQueue::SEED, vault_account.key.as_ref(), &[bump]]],
+        )?;
+    }
+    check_queue_account(vault_account.key, queue_account)?;
+
+    // Borrow and write queue data
+    let mut queue_data = queue_account.data.borrow_mut();
+    let queue: &mut WithdrawalQueue = bytemuck::from_bytes_mut(&mut queue_data);
+    if queue.vault != Pubkey::default() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    *queue = WithdrawalQueue {
+        vault: *vault_account.key,
+        ..WithdrawalQueue::default()
+    };
+
+    Ok(())
+}
+
+/// Checks that `depositor` records the shares of `owner` in the vault at
+/// `vault`.
+fn check_depositor(vault: &Pubkey, depositor: &Depositor, owner: &Pubkey) -> ProgramResult {
+    if depositor.vault != *vault {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if depositor.owner != *owner {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+/// Adds `amount` to the lifetime deposits of `depositor`, failing if they
+/// would exceed `deposit_cap`, unless it is zero (disabled).
+fn record_deposit(depositor: &mut Depositor, amount: u64, deposit_cap: u64) -> ProgramResult {
+    let deposited: u64 = depositor.deposited.into();
+    let new_deposited = deposited
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if deposit_cap != 0 && new_deposited > deposit_cap {
+        return Err(ProgramError::InvalidArgument);
+    }
+    depositor.deposited = new_deposited.into();
+
+    Ok(())
+}
 
 /// Process a deposit instruction.
-/// Takes tokens, returns shares.
+/// Takes tokens, returns shares, credited to the owner's depositor account,
+/// created by init-depositor. The deposit also counts towards the lifetime
+/// deposits of the depositor account, which may not exceed the deposit cap
+/// while it is set, including deposits made before it was.
 /// 
 /// # Arguments
-/// - `accounts`: Account array (must have vault account as first)
+/// - `accounts`: Account array (vault account first, depositor account
+///   second, signing depositor owner third)
 /// - `instruction_data`: 8 bytes representing the number of tokens to deposit
 pub fn process_deposit(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
-    // Extract token amount from instruction data
-    let token_amount = if instruction_data.len() >= 8 {
-        u64::from_le_bytes([0u8; 8].map(|_| 0)) // Will be filled
-    } else {
+    // Check token amount is present in instruction data
+    if instruction_data.len() < 8 {
         return Err(ProgramError::InvalidInstructionData);
-    };
+    }
     
-    // Get vault account
+    // Get vault, depositor and owner accounts
     let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let depositor_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let owner = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if depositor_account.key == vault_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    check_depositor_account(vault_account.key, depositor_account, owner.key)?;
     
-    // Borrow and read vault data
+    // Borrow and read vault and depositor data
     let mut vault_data = vault_account.data.borrow_mut();
     let vault: &mut Vault = bytemuck::from_bytes_mut(&mut vault_data);
+    let mut depositor_data = depositor_account.data.borrow_mut();
+    let depositor: &mut Depositor = bytemuck::from_bytes_mut(&mut depositor_data);
+    check_depositor(vault_account.key, depositor, owner.key)?;
     
     // Parse token amount
     let mut token_bytes = [0u8; 8];
     token_bytes.copy_from_slice(&instruction_data[..8]);
     let token_amount = u64::from_le_bytes(token_bytes);
+
+    // Count the lifetime deposits, enforcing the per-depositor cap if enabled
+    record_deposit(depositor, token_amount, vault.deposit_cap.into())?;
     
-    // Calculate shares to mint
-    let shares_to_mint = if vault.token_total == 0.into() {
-        // If vault is empty, 1:1 ratio
-        token_amount
-    } else {
-        // shares / tokens = shares_total / token_total
-        // shares_to_mint = (token_amount * shares_total) / token_total
-        let shares_total: u64 = vault.shares_total.into();
-        let token_total: u64 = vault.token_total.into();
-        (token_amount as u128).saturating_mul(shares_total as u128)
-            .saturating_div(token_total as u128) as u64
-    };
+    // Calculate shares to mint, 1:1 while the vault is empty
+    let shares_to_mint = shares_for_deposit(
+        token_amount,
+        vault.shares_total.into(),
+        vault.token_total.into(),
+    )
+    .ok_or(ProgramError::ArithmeticOverflow)?;
     
-    // Update vault
+    // Update vault and depositor
     let current_tokens: u64 = vault.token_total.into();
     let current_shares: u64 = vault.shares_total.into();
-    vault.token_total = (current_tokens.saturating_add(token_amount)).into();
-    vault.shares_total = (current_shares.saturating_add(shares_to_mint)).into();
+    let depositor_shares: u64 = depositor.shares.into();
+    vault.token_total = current_tokens
+        .checked_add(token_amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    vault.shares_total = current_shares
+        .checked_add(shares_to_mint)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    // At most shares_total, so this does not overflow
+    depositor.shares = (depositor_shares + shares_to_mint).into();
     
     Ok(())
 }
 
 /// Process a withdrawal instruction.
-/// Burns shares, returns tokens.
+/// Burns shares of the owner's depositor account, returns tokens.
 /// 
 /// # Arguments
-/// - `accounts`: Account array (must have vault account as first)
+/// - `accounts`: Account array (vault account first, depositor account
+///   second, signing depositor owner third)
 /// - `instruction_data`: 8 bytes representing the number of shares to burn
 pub fn process_withdraw(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
-    // Get vault account
+    // Get vault, depositor and owner accounts
     let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let depositor_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let owner = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if depositor_account.key == vault_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    check_depositor_account(vault_account.key, depositor_account, owner.key)?;
     
     // Parse shares amount
+    if instruction_data.len() < 8 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
     let mut shares_bytes = [0u8; 8];
-    shares_bytes.copy_from_slice(&instruction_data[..8.min(instruction_data.len())]);
+    shares_bytes.copy_from_slice(&instruction_data[..8]);
     let shares_amount = u64::from_le_bytes(shares_bytes);
     
-    // Borrow and read vault data
+    // Borrow and read vault and depositor data
     let mut vault_data = vault_account.data.borrow_mut();
     let vault: &mut Vault = bytemuck::from_bytes_mut(&mut vault_data);
+    let mut depositor_data = depositor_account.data.borrow_mut();
+    let depositor: &mut Depositor = bytemuck::from_bytes_mut(&mut depositor_data);
+    check_depositor(vault_account.key, depositor, owner.key)?;
     
-    // Calculate tokens to return
-    let tokens_to_return = if vault.shares_total == 0.into() {
-        0
-    } else {
-        // tokens / shares = token_total / shares_total
-        // tokens_to_return = (shares_amount * token_total) / shares_total
-        let shares_total: u64 = vault.shares_total.into();
-        let token_total: u64 = vault.token_total.into();
-        (shares_amount as u128).saturating_mul(token_total as u128)
-            .saturating_div(shares_total as u128) as u64
-    };
+    // Debit the depositor, whose shares are part of shares_total
+    let depositor_shares: u64 = depositor.shares.into();
+    depositor.shares = depositor_shares
+        .checked_sub(shares_amount)
+        .ok_or(ProgramError::InsufficientFunds)?
+        .into();
+    
+    // Calculate tokens to return, none while the vault has no shares
+    let tokens_to_return = tokens_for_shares(
+        shares_amount,
+        vault.shares_total.into(),
+        vault.token_total.into(),
+    )
+    .ok_or(ProgramError::ArithmeticOverflow)?;
     
     // Update vault
     let current_tokens: u64 = vault.token_total.into();
@@ -92,8 +313,32 @@ pub fn process_withdraw(accounts: &[AccountInfo], instruction_data: &[u8]) -> Pr
     Ok(())
 }
 
+/// Shares minted to the owner for a reward of `reward` tokens that brought the
+/// vault to `token_total` tokens, so that they are worth `performance_fee_bps`
+/// of the reward. Rounded down, in favour of the depositors.
+/// Returns `None` if the shares do not fit a `u64`.
+pub fn performance_fee_shares(
+    shares_total: u64,
+    token_total: u64,
+    reward: u64,
+    performance_fee_bps: u16,
+) -> Option<u64> {
+    let fee_bps = (performance_fee_bps as u64).min(BPS_DENOMINATOR);
+    // fee_bps <= BPS_DENOMINATOR, so the fee is at most the reward
+    let fee_tokens = mul_div_floor(reward, fee_bps, BPS_DENOMINATOR)?;
+    // reward <= token_total, so this does not underflow
+    let other_tokens = token_total - fee_tokens;
+    if shares_total == 0 || other_tokens == 0 {
+        return Some(0);
+    }
+    // fee_shares / (shares_total + fee_shares) = fee_tokens / token_total
+    mul_div_floor(fee_tokens, shares_total, other_tokens)
+}
+
 /// Process a reward instruction.
-/// Adds tokens to the vault without minting shares.
+/// Adds tokens to the vault, minting fee shares for the owner worth the
+/// performance fee part of the reward. The owner claims them into their
+/// depositor account with claim-fee-shares.
 /// 
 /// # Arguments
 /// - `accounts`: Account array (must have vault account as first)
@@ -103,21 +348,90 @@ pub fn process_reward(accounts: &[AccountInfo], instruction_data: &[u8]) -> Prog
     let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
     
     // Parse token amount
+    if instruction_data.len() < 8 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
     let mut token_bytes = [0u8; 8];
-    token_bytes.copy_from_slice(&instruction_data[..8.min(instruction_data.len())]);
+    token_bytes.copy_from_slice(&instruction_data[..8]);
     let token_amount = u64::from_le_bytes(token_bytes);
     
     // Borrow and read vault data
     let mut vault_data = vault_account.data.borrow_mut();
     let vault: &mut Vault = bytemuck::from_bytes_mut(&mut vault_data);
     
-    // Update vault - only increase tokens, shares stay the same
+    // Update vault - increase tokens, and mint the owner fee shares worth the
+    // performance fee part of the reward
     let current_tokens: u64 = vault.token_total.into();
-    vault.token_total = (current_tokens.saturating_add(token_amount)).into();
+    let new_tokens = current_tokens.saturating_add(token_amount);
+    let shares_total: u64 = vault.shares_total.into();
+    let fee_shares = performance_fee_shares(
+        shares_total,
+        new_tokens,
+        new_tokens - current_tokens,
+        vault.performance_fee_bps.into(),
+    )
+    .ok_or(ProgramError::ArithmeticOverflow)?;
+    let owner_fee_shares: u64 = vault.fee_shares.into();
+    vault.token_total = new_tokens.into();
+    vault.shares_total = shares_total
+        .checked_add(fee_shares)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    vault.fee_shares = owner_fee_shares
+        .checked_add(fee_shares)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
     
     Ok(())
 }
 
+/// Process a claim-fee-shares instruction.
+/// Credits the fee shares minted to the vault owner on rewards to their
+/// depositor account, created by init-depositor, from where they are
+/// withdrawn or transferred like any other shares.
+///
+/// # Arguments
+/// - `accounts`: Account array (vault account first, owner's depositor
+///   account second, signing vault owner third)
+/// - `instruction_data`: unused
+pub fn process_claim_fee_shares(
+    accounts: &[AccountInfo],
+    _instruction_data: &[u8],
+) -> ProgramResult {
+    // Get vault, depositor and owner accounts
+    let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let depositor_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let owner = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if depositor_account.key == vault_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    check_depositor_account(vault_account.key, depositor_account, owner.key)?;
+
+    // Borrow and read vault and depositor data
+    let mut vault_data = vault_account.data.borrow_mut();
+    let vault: &mut Vault = bytemuck::from_bytes_mut(&mut vault_data);
+    if vault.owner != *owner.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+    let mut depositor_data = depositor_account.data.borrow_mut();
+    let depositor: &mut Depositor = bytemuck::from_bytes_mut(&mut depositor_data);
+    check_depositor(vault_account.key, depositor, owner.key)?;
+
+    // Move the fee shares, already part of shares_total, to the depositor
+    let fee_shares: u64 = vault.fee_shares.into();
+    let depositor_shares: u64 = depositor.shares.into();
+    depositor.shares = depositor_shares
+        .checked_add(fee_shares)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    vault.fee_shares = 0.into();
+
+    Ok(())
+}
+
 /// Process a slash instruction.
 /// Removes tokens from the vault without burning shares.
 /// This can cause insolvency.
@@ -130,8 +444,11 @@ pub fn process_slash(accounts: &[AccountInfo], instruction_data: &[u8]) -> Progr
     let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
     
     // Parse token amount
+    if instruction_data.len() < 8 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
     let mut token_bytes = [0u8; 8];
-    token_bytes.copy_from_slice(&instruction_data[..8.min(instruction_data.len())]);
+    token_bytes.copy_from_slice(&instruction_data[..8]);
     let token_amount = u64::from_le_bytes(token_bytes);
     
     // Borrow and read vault data
@@ -145,3 +462,800 @@ pub fn process_slash(accounts: &[AccountInfo], instruction_data: &[u8]) -> Progr
     
     Ok(())
 }
+
+/// Fee charged for flash-borrowing `amount` tokens, rounded up.
+pub fn flash_loan_fee(amount: u64) -> u64 {
+    mul_div_ceil(amount, FLASH_LOAN_FEE_BPS, BPS_DENOMINATOR)
+        .expect("FLASH_LOAN_FEE_BPS < BPS_DENOMINATOR, so the fee is at most the amount")
+}
+
+/// Hands the borrowed tokens over to the receiver program, which must repay
+/// them plus `fee` to the vault account before returning.
+#[cfg(not(feature = "certora"))]
+fn call_receiver<'a>(
+    vault_account: &AccountInfo<'a>,
+    receiver_program: &AccountInfo<'a>,
+    receiver_accounts: &[AccountInfo<'a>],
+    amount: u64,
+    fee: u64,
+) -> ProgramResult {
+    use solana_program::{
+        instruction::{AccountMeta, Instruction},
+        program::invoke,
+    };
+
+    let mut data = Vec::with_capacity(16);
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&fee.to_le_bytes());
+    let mut metas = vec![AccountMeta::new(*vault_account.key, false)];
+    metas.extend(receiver_accounts.iter().map(|account| AccountMeta {
+        pubkey: *account.key,
+        is_signer: account.is_signer,
+        is_writable: account.is_writable,
+    }));
+    let instruction = Instruction {
+        program_id: *receiver_program.key,
+        accounts: metas,
+        data,
+    };
+
+    let mut infos = vec![vault_account.clone()];
+    infos.extend_from_slice(receiver_accounts);
+    invoke(&instruction, &infos)
+}
+
+/// Stands in for the receiver program, which may leave any balance in the
+/// vault account.
+#[cfg(feature = "certora")]
+fn call_receiver(
+    vault_account: &AccountInfo,
+    _receiver_program: &AccountInfo,
+    _receiver_accounts: &[AccountInfo],
+    _amount: u64,
+    _fee: u64,
+) -> ProgramResult {
+    **vault_account.try_borrow_mut_lamports()? = cvlr::nondet();
+    Ok(())
+}
+
+/// Process a flash loan instruction.
+/// Lends tokens to a borrower for the duration of a call into the receiver
+/// program, and fails unless they are repaid with a fee before it returns.
+/// The fee is added to the vault's tokens without minting shares.
+///
+/// # Arguments
+/// - `accounts`: Account array (vault account first, receiver program second,
+///   borrower third, followed by any accounts the receiver needs)
+/// - `instruction_data`: 8 bytes representing the number of tokens to borrow
+pub fn process_flash_loan(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Get vault, receiver and borrower accounts
+    let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let receiver_program = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let borrower = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if vault_account.key == borrower.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Parse token amount
+    if instruction_data.len() < 8 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let mut token_bytes = [0u8; 8];
+    token_bytes.copy_from_slice(&instruction_data[..8]);
+    let token_amount = u64::from_le_bytes(token_bytes);
+    let fee = flash_loan_fee(token_amount);
+
+    // Lend out of the vault's liquid balance
+    let balance_before = vault_account.lamports();
+    let borrower_lamports = borrower.lamports();
+    **vault_account.try_borrow_mut_lamports()? = balance_before
+        .checked_sub(token_amount)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    **borrower.try_borrow_mut_lamports()? = borrower_lamports
+        .checked_add(token_amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    call_receiver(
+        vault_account,
+        receiver_program,
+        &accounts[2..],
+        token_amount,
+        fee,
+    )?;
+
+    // Require repayment plus fee
+    let required = balance_before
+        .checked_add(fee)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if vault_account.lamports() < required {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    // Borrow and read vault data
+    let mut vault_data = vault_account.data.borrow_mut();
+    let vault: &mut Vault = bytemuck::from_bytes_mut(&mut vault_data);
+
+    // Update vault - the fee accrues to share holders
+    let current_tokens: u64 = vault.token_total.into();
+    vault.token_total = current_tokens
+        .checked_add(fee)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+
+    Ok(())
+}
+
+/// Process a transfer-shares instruction.
+/// Moves shares from one depositor account to another of the same vault, both
+/// created by init-depositor. Shares are neither minted nor burnt.
+///
+/// # Arguments
+/// - `accounts`: Account array (vault account first, source depositor second,
+///   destination depositor third, signing source owner fourth)
+/// - `instruction_data`: 8 bytes representing the number of shares to transfer
+pub fn process_transfer_shares(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Get vault, depositor and owner accounts
+    let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let source_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let destination_account = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let owner = accounts.get(3).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    // Transferring to oneself would credit the shares without debiting them
+    if source_account.key == destination_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Parse shares amount
+    if instruction_data.len() < 8 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let mut shares_bytes = [0u8; 8];
+    shares_bytes.copy_from_slice(&instruction_data[..8]);
+    let shares_amount = u64::from_le_bytes(shares_bytes);
+
+    // Borrow and read depositor data
+    let mut source_data = source_account.data.borrow_mut();
+    let source: &mut Depositor = bytemuck::from_bytes_mut(&mut source_data);
+    let mut destination_data = destination_account.data.borrow_mut();
+    let destination: &mut Depositor = bytemuck::from_bytes_mut(&mut destination_data);
+
+    check_depositor(vault_account.key, source, owner.key)?;
+    check_depositor(vault_account.key, destination, &destination.owner)?;
+    check_depositor_account(vault_account.key, source_account, owner.key)?;
+    check_depositor_account(vault_account.key, destination_account, &destination.owner)?;
+
+    // Update depositors
+    let source_shares: u64 = source.shares.into();
+    let destination_shares: u64 = destination.shares.into();
+    source.shares = source_shares
+        .checked_sub(shares_amount)
+        .ok_or(ProgramError::InsufficientFunds)?
+        .into();
+    destination.shares = destination_shares
+        .checked_add(shares_amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+
+    Ok(())
+}
+
+/// Process a request-withdrawal instruction.
+/// Burns shares of the claimant's depositor account like a withdrawal, but
+/// instead of paying out immediately, reserves the tokens for a claim at the
+/// back of the vault's withdrawal queue, created by init-queue. Used when the
+/// vault does not hold enough liquid tokens, e.g. after a slash.
+///
+/// # Arguments
+/// - `accounts`: Account array (vault account first, queue account second,
+///   fresh claim account third, signing claimant fourth, claimant's
+///   depositor account fifth)
+/// - `instruction_data`: 8 bytes representing the number of shares to burn
+pub fn process_request_withdrawal(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Get vault, queue, claim and claimant accounts
+    let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let queue_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let claim_account = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let owner = accounts.get(3).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let depositor_account = accounts.get(4).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if vault_account.key == queue_account.key
+        || vault_account.key == claim_account.key
+        || queue_account.key == claim_account.key
+        || depositor_account.key == vault_account.key
+        || depositor_account.key == queue_account.key
+        || depositor_account.key == claim_account.key
+    {
+        return Err(ProgramError::InvalidArgument);
+    }
+    check_queue_account(vault_account.key, queue_account)?;
+    check_depositor_account(vault_account.key, depositor_account, owner.key)?;
+
+    // Parse shares amount
+    if instruction_data.len() < 8 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let mut shares_bytes = [0u8; 8];
+    shares_bytes.copy_from_slice(&instruction_data[..8]);
+    let shares_amount = u64::from_le_bytes(shares_bytes);
+
+    // Borrow and read vault, queue and claim data
+    let mut vault_data = vault_account.data.borrow_mut();
+    let vault: &mut Vault = bytemuck::from_bytes_mut(&mut vault_data);
+    let mut queue_data = queue_account.data.borrow_mut();
+    let queue: &mut WithdrawalQueue = bytemuck::from_bytes_mut(&mut queue_data);
+    let mut claim_data = claim_account.data.borrow_mut();
+    let claim: &mut QueuedClaim = bytemuck::from_bytes_mut(&mut claim_data);
+    let mut depositor_data = depositor_account.data.borrow_mut();
+    let depositor: &mut Depositor = bytemuck::from_bytes_mut(&mut depositor_data);
+    check_depositor(vault_account.key, depositor, owner.key)?;
+
+    if queue.vault != *vault_account.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if claim.queue != Pubkey::default() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    // Debit the claimant, whose shares are part of shares_total
+    let depositor_shares: u64 = depositor.shares.into();
+    depositor.shares = depositor_shares
+        .checked_sub(shares_amount)
+        .ok_or(ProgramError::InsufficientFunds)?
+        .into();
+
+    // Calculate tokens owed, as for a withdrawal
+    let shares_total: u64 = vault.shares_total.into();
+    let token_total: u64 = vault.token_total.into();
+    if shares_amount > shares_total {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    // shares_amount <= shares_total, so this is at most token_total
+    let tokens_owed = tokens_for_shares(shares_amount, shares_total, token_total)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    // Update vault and queue
+    let tail: u64 = queue.tail.into();
+    let total_queued: u64 = queue.total_queued.into();
+    vault.shares_total = (shares_total - shares_amount).into();
+    vault.token_total = (token_total - tokens_owed).into();
+    queue.tail = tail
+        .checked_add(1)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    queue.total_queued = total_queued
+        .checked_add(tokens_owed)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    *claim = QueuedClaim {
+        queue: *queue_account.key,
+        owner: *owner.key,
+        position: tail.into(),
+        amount: tokens_owed.into(),
+        paid: 0,
+    };
+
+    Ok(())
+}
+
+/// Process a service-withdrawal instruction.
+/// Settles the claim at the front of the vault's withdrawal queue. Like a
+/// withdrawal, it only updates the vault's books: the tokens owed were taken
+/// out of `token_total` when the claim was requested, and leave the queue's
+/// `total_queued` now. No lamports are paid out, since deposits bring none in.
+/// Claims are serviced strictly in request order, each once. Anyone may
+/// service the queue.
+///
+/// # Arguments
+/// - `accounts`: Account array (vault account first, queue account second,
+///   claim account third)
+/// - `instruction_data`: unused
+pub fn process_service_withdrawal(
+    accounts: &[AccountInfo],
+    _instruction_data: &[u8],
+) -> ProgramResult {
+    // Get vault, queue and claim accounts
+    let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let queue_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let claim_account = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if queue_account.key == claim_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    check_queue_account(vault_account.key, queue_account)?;
+
+    // Borrow and read queue and claim data
+    let mut queue_data = queue_account.data.borrow_mut();
+    let queue: &mut WithdrawalQueue = bytemuck::from_bytes_mut(&mut queue_data);
+    let mut claim_data = claim_account.data.borrow_mut();
+    let claim: &mut QueuedClaim = bytemuck::from_bytes_mut(&mut claim_data);
+
+    if queue.vault != *vault_account.key || claim.queue != *queue_account.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    // Only the claim at the front of the queue can be serviced
+    let head: u64 = queue.head.into();
+    if claim.paid != 0 || u64::from(claim.position) != head {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Update queue and claim
+    let amount: u64 = claim.amount.into();
+    let total_queued: u64 = queue.total_queued.into();
+    queue.head = head
+        .checked_add(1)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .into();
+    queue.total_queued = total_queued.saturating_sub(amount).into();
+    claim.paid = 1;
+
+    Ok(())
+}
+
+/// Process a set-performance-fee instruction.
+/// Sets the share of future rewards charged as performance fee.
+///
+/// # Arguments
+/// - `accounts`: Account array (vault account first, signing vault owner
+///   second)
+/// - `instruction_data`: 2 bytes representing the fee in bps, at most
+///   `BPS_DENOMINATOR`
+pub fn process_set_performance_fee(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Get vault and owner accounts
+    let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let owner = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Parse fee
+    if instruction_data.len() < 2 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let performance_fee_bps = u16::from_le_bytes([instruction_data[0], instruction_data[1]]);
+    if performance_fee_bps as u64 > BPS_DENOMINATOR {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Borrow and read vault data
+    let mut vault_data = vault_account.data.borrow_mut();
+    let vault: &mut Vault = bytemuck::from_bytes_mut(&mut vault_data);
+    if vault.owner != *owner.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    vault.performance_fee_bps = performance_fee_bps.into();
+
+    Ok(())
+}
+
+/// Process a set-deposit-cap instruction.
+/// Sets the cap on each depositor's lifetime deposits; zero disables it.
+///
+/// # Arguments
+/// - `accounts`: Account array (vault account first, signing vault owner
+///   second)
+/// - `instruction_data`: 8 bytes representing the cap
+pub fn process_set_deposit_cap(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Get vault and owner accounts
+    let vault_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let owner = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Parse cap
+    if instruction_data.len() < 8 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let mut cap_bytes = [0u8; 8];
+    cap_bytes.copy_from_slice(&instruction_data[..8]);
+    let deposit_cap = u64::from_le_bytes(cap_bytes);
+
+    // Borrow and read vault data
+    let mut vault_data = vault_account.data.borrow_mut();
+    let vault: &mut Vault = bytemuck::from_bytes_mut(&mut vault_data);
+    if vault.owner != *owner.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    vault.deposit_cap = deposit_cap.into();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use svm_fv_support::{infos, TestAccount};
+
+    /// The withdrawal queue of the vault at `vault`, holding no claims yet.
+    fn queue(vault: Pubkey) -> TestAccount {
+        TestAccount::new(crate::id())
+            .key(WithdrawalQueue::address(&vault).0)
+            .state(&WithdrawalQueue {
+                vault,
+                ..WithdrawalQueue::default()
+            })
+    }
+
+    #[test]
+    fn test_flash_loan_fee_rounds_up() {
+        assert_eq!(flash_loan_fee(0), 0);
+        assert_eq!(flash_loan_fee(1), 1);
+        assert_eq!(flash_loan_fee(10_000), FLASH_LOAN_FEE_BPS);
+        assert_eq!(flash_loan_fee(10_001), FLASH_LOAN_FEE_BPS + 1);
+        assert_eq!(
+            flash_loan_fee(u64::MAX),
+            (u64::MAX as u128 * FLASH_LOAN_FEE_BPS as u128).div_ceil(BPS_DENOMINATOR as u128)
+                as u64
+        );
+    }
+
+    #[test]
+    fn test_short_instruction_data_is_rejected() {
+        let mut vault = TestAccount::new(crate::id()).state(&Vault::new(Pubkey::new_unique()));
+        let vault_account = vault.info();
+        for data in [&[][..], &[1, 2, 3][..], &[0; 7][..]] {
+            let accounts = [vault_account.clone()];
+            assert_eq!(
+                process_reward(&accounts, data),
+                Err(ProgramError::InvalidInstructionData)
+            );
+            assert_eq!(
+                process_slash(&accounts, data),
+                Err(ProgramError::InvalidInstructionData)
+            );
+        }
+    }
+
+    #[test]
+    fn test_performance_fee_shares() {
+        // 100 shares backed by 90 tokens after a 10 token reward at 10%: the
+        // 1 token fee is worth 100 / 99 shares
+        assert_eq!(performance_fee_shares(100, 100, 10, 1_000), Some(1));
+        assert_eq!(performance_fee_shares(100, 200, 100, 1_000), Some(5));
+        assert_eq!(performance_fee_shares(100, 200, 100, 0), Some(0));
+        assert_eq!(performance_fee_shares(0, 200, 100, 1_000), Some(0));
+        // A 100% fee on the whole vault leaves nothing to price the shares
+        assert_eq!(performance_fee_shares(100, 100, 100, 10_000), Some(0));
+        assert_eq!(
+            performance_fee_shares(u64::MAX, u64::MAX, u64::MAX, 9_999),
+            None
+        );
+    }
+
+    #[test]
+    fn test_fee_shares_are_claimed_and_withdrawn() {
+        let mut owner = TestAccount::wallet();
+        let owner_key = owner.pubkey();
+        let mut vault = Vault::new(owner_key);
+        vault.shares_total = 100.into();
+        vault.token_total = 90.into();
+        vault.performance_fee_bps = 1_000.into();
+        let mut vault = TestAccount::new(crate::id()).state(&vault);
+        let vault_key = vault.pubkey();
+        let mut depositor = TestAccount::new(crate::id())
+            .key(Depositor::address(&vault_key, &owner_key).0)
+            .state(&Depositor {
+                owner: owner_key,
+                vault: vault_key,
+                ..Depositor::default()
+            });
+        let mut stranger = TestAccount::wallet();
+        let (vault_account, depositor_account) = (vault.info(), depositor.info());
+        let read = || {
+            let data = vault_account.data.borrow();
+            *bytemuck::from_bytes::<Vault>(&data)
+        };
+
+        // A 10 token reward at 10% mints the owner 1 fee share
+        process_reward(&[vault_account.clone()], &10u64.to_le_bytes()).unwrap();
+        assert_eq!(u64::from(read().fee_shares), 1);
+        assert_eq!(u64::from(read().shares_total), 101);
+
+        // Only the vault owner claims them, into their depositor account
+        let accounts = [
+            vault_account.clone(),
+            depositor_account.clone(),
+            stranger.info(),
+        ];
+        assert_eq!(
+            process_claim_fee_shares(&accounts, &[]),
+            Err(ProgramError::InvalidSeeds)
+        );
+        let accounts = [
+            vault_account.clone(),
+            depositor_account.clone(),
+            owner.info(),
+        ];
+        process_claim_fee_shares(&accounts, &[]).unwrap();
+        assert_eq!(u64::from(read().fee_shares), 0);
+        let depositor_shares = || {
+            let data = depositor_account.data.borrow();
+            u64::from(bytemuck::from_bytes::<Depositor>(&data).shares)
+        };
+        assert_eq!(depositor_shares(), 1);
+
+        // From where they are withdrawn like any other shares
+        let accounts = [
+            vault_account.clone(),
+            depositor_account.clone(),
+            owner.info(),
+        ];
+        process_withdraw(&accounts, &1u64.to_le_bytes()).unwrap();
+        assert_eq!(depositor_shares(), 0);
+        assert_eq!(u64::from(read().shares_total), 100);
+    }
+
+    #[test]
+    fn test_withdrawal_queue_fifo_and_paid_once() {
+        let owner = Pubkey::new_unique();
+
+        // Post-slash vault: 100 shares backed by 50 tokens
+        let mut vault = Vault::new(owner);
+        vault.shares_total = 100.into();
+        vault.token_total = 50.into();
+        let mut vault = TestAccount::new(crate::id()).state(&vault);
+        let vault_key = vault.pubkey();
+        let vault_account = vault.info();
+        // The owner holds 50 of the shares
+        let depositor = Depositor {
+            owner,
+            vault: vault_key,
+            shares: 50.into(),
+            ..Depositor::default()
+        };
+        let mut depositor = TestAccount::new(crate::id())
+            .key(Depositor::address(&vault_key, &owner).0)
+            .state(&depositor);
+        let depositor_account = depositor.info();
+        let mut queue = queue(vault_key);
+        let queue_account = queue.info();
+        let mut claims = [
+            TestAccount::new(crate::id()).state(&QueuedClaim::default()),
+            TestAccount::new(crate::id()).state(&QueuedClaim::default()),
+        ];
+        let claims = infos(&mut claims);
+        let mut owner = TestAccount::wallet().key(owner);
+        let owner_account = owner.info();
+
+        for claim in &claims {
+            let accounts = [
+                vault_account.clone(),
+                queue_account.clone(),
+                claim.clone(),
+                owner_account.clone(),
+                depositor_account.clone(),
+            ];
+            process_request_withdrawal(&accounts, &20u64.to_le_bytes()).unwrap();
+        }
+        let shares = |account: &AccountInfo| {
+            let data = account.data.borrow();
+            u64::from(bytemuck::from_bytes::<Depositor>(&data).shares)
+        };
+        assert_eq!(shares(&depositor_account), 10);
+        let total_queued = || {
+            let data = queue_account.data.borrow();
+            u64::from(bytemuck::from_bytes::<WithdrawalQueue>(&data).total_queued)
+        };
+        assert_eq!(total_queued(), 20);
+        let service = |index: usize| {
+            let accounts = [
+                vault_account.clone(),
+                queue_account.clone(),
+                claims[index].clone(),
+            ];
+            process_service_withdrawal(&accounts, &[])
+        };
+
+        // The second claim waits for the first, which is paid only once
+        assert_eq!(service(1), Err(ProgramError::InvalidArgument));
+        service(0).unwrap();
+        assert_eq!(service(0), Err(ProgramError::InvalidArgument));
+        assert_eq!(total_queued(), 10);
+        service(1).unwrap();
+        assert_eq!(total_queued(), 0);
+    }
+
+    #[test]
+    fn test_deposits_before_the_cap_count_towards_it() {
+        let mut vault_owner = TestAccount::wallet();
+        let mut owner = TestAccount::wallet();
+        let owner_key = owner.pubkey();
+        let mut vault = TestAccount::new(crate::id()).state(&Vault::new(vault_owner.pubkey()));
+        let vault_key = vault.pubkey();
+        let mut depositor = TestAccount::new(crate::id())
+            .key(Depositor::address(&vault_key, &owner_key).0)
+            .state(&Depositor {
+                owner: owner_key,
+                vault: vault_key,
+                ..Depositor::default()
+            });
+        let (vault_account, depositor_account, owner_account) =
+            (vault.info(), depositor.info(), owner.info());
+        let deposit_accounts = [
+            vault_account.clone(),
+            depositor_account.clone(),
+            owner_account,
+        ];
+
+        // Deposits are counted while the cap is off
+        process_deposit(&deposit_accounts, &60u64.to_le_bytes()).unwrap();
+        process_deposit(&deposit_accounts, &30u64.to_le_bytes()).unwrap();
+        {
+            let data = depositor_account.data.borrow();
+            let state: &Depositor = bytemuck::from_bytes(&data);
+            assert_eq!(u64::from(state.deposited), 90);
+        }
+
+        // So that once it is set, they leave room for 10 more tokens only
+        let cap_accounts = [vault_account, vault_owner.info()];
+        process_set_deposit_cap(&cap_accounts, &100u64.to_le_bytes()).unwrap();
+        assert_eq!(
+            process_deposit(&deposit_accounts, &20u64.to_le_bytes()),
+            Err(ProgramError::InvalidArgument)
+        );
+        process_deposit(&deposit_accounts, &10u64.to_le_bytes()).unwrap();
+        let data = depositor_account.data.borrow();
+        let state: &Depositor = bytemuck::from_bytes(&data);
+        assert_eq!(u64::from(state.deposited), 100);
+    }
+
+    #[test]
+    fn test_unfunded_deposit_is_not_paid_out() {
+        let mut owner = TestAccount::wallet();
+        let owner_key = owner.pubkey();
+        // A vault holding lamports from elsewhere, e.g. flash loan fees
+        let mut vault = TestAccount::new(crate::id())
+            .lamports(1_000_000)
+            .state(&Vault::new(Pubkey::new_unique()));
+        let vault_key = vault.pubkey();
+        let mut depositor = TestAccount::new(crate::id())
+            .key(Depositor::address(&vault_key, &owner_key).0)
+            .state(&Depositor {
+                owner: owner_key,
+                vault: vault_key,
+                ..Depositor::default()
+            });
+        let mut queue = queue(vault_key);
+        let mut claim = TestAccount::new(crate::id()).state(&QueuedClaim::default());
+        let (vault_account, depositor_account, owner_account) =
+            (vault.info(), depositor.info(), owner.info());
+        let (queue_account, claim_account) = (queue.info(), claim.info());
+
+        // Deposit tokens without sending any lamports, then queue and service
+        // their withdrawal
+        let accounts = [
+            vault_account.clone(),
+            depositor_account.clone(),
+            owner_account.clone(),
+        ];
+        process_deposit(&accounts, &1_000_000u64.to_le_bytes()).unwrap();
+        let accounts = [
+            vault_account.clone(),
+            queue_account.clone(),
+            claim_account.clone(),
+            owner_account.clone(),
+            depositor_account.clone(),
+        ];
+        process_request_withdrawal(&accounts, &1_000_000u64.to_le_bytes()).unwrap();
+        let accounts = [vault_account.clone(), queue_account, claim_account];
+        process_service_withdrawal(&accounts, &[]).unwrap();
+
+        assert_eq!(vault_account.lamports(), 1_000_000);
+        assert_eq!(owner_account.lamports(), 0);
+    }
+
+    #[test]
+    fn test_withdrawal_requests_burn_own_shares() {
+        let owner = Pubkey::new_unique();
+        let mut vault = Vault::new(owner);
+        vault.shares_total = 100.into();
+        vault.token_total = 100.into();
+        let mut vault = TestAccount::new(crate::id()).state(&vault);
+        let vault_key = vault.pubkey();
+        let mut queue = queue(vault_key);
+        let mut claim = TestAccount::new(crate::id()).state(&QueuedClaim::default());
+        // A signer whose depositor account holds 5 shares, and a fresh
+        // account of the program posing as theirs
+        let mut stranger = TestAccount::wallet();
+        let stranger_key = stranger.pubkey();
+        let depositor = Depositor {
+            owner: stranger_key,
+            vault: vault_key,
+            shares: 5.into(),
+            ..Depositor::default()
+        };
+        let mut own = TestAccount::new(crate::id())
+            .key(Depositor::address(&vault_key, &stranger_key).0)
+            .state(&depositor);
+        let mut fresh = TestAccount::new(crate::id()).state(&Depositor {
+            shares: 100.into(),
+            ..depositor
+        });
+
+        let mut request = |depositor: &mut TestAccount, shares: u64| {
+            let accounts = [
+                vault.info(),
+                queue.info(),
+                claim.info(),
+                stranger.info(),
+                depositor.info(),
+            ];
+            process_request_withdrawal(&accounts, &shares.to_le_bytes())
+        };
+        assert_eq!(request(&mut fresh, 50), Err(ProgramError::InvalidSeeds));
+        assert_eq!(request(&mut own, 50), Err(ProgramError::InsufficientFunds));
+        request(&mut own, 5).unwrap();
+
+        let vault_account = vault.info();
+        let data = vault_account.data.borrow();
+        assert_eq!(u64::from(bytemuck::from_bytes::<Vault>(&data).shares_total), 95);
+    }
+
+    #[test]
+    fn test_withdrawal_queue_is_canonical() {
+        let mut owner = TestAccount::wallet();
+        let owner_key = owner.pubkey();
+        let mut vault = Vault::new(Pubkey::new_unique());
+        vault.shares_total = 100.into();
+        vault.token_total = 100.into();
+        let mut vault = TestAccount::new(crate::id()).state(&vault);
+        let vault_key = vault.pubkey();
+        let mut depositor = TestAccount::new(crate::id())
+            .key(Depositor::address(&vault_key, &owner_key).0)
+            .state(&Depositor {
+                owner: owner_key,
+                vault: vault_key,
+                shares: 100.into(),
+                ..Depositor::default()
+            });
+        // A second, empty queue of the program for the same vault, and one at
+        // the address of the queue but owned by another program
+        let mut second = TestAccount::new(crate::id()).state(&WithdrawalQueue {
+            vault: vault_key,
+            ..WithdrawalQueue::default()
+        });
+        let mut foreign = TestAccount::new(Pubkey::new_unique())
+            .key(WithdrawalQueue::address(&vault_key).0)
+            .state(&WithdrawalQueue {
+                vault: vault_key,
+                ..WithdrawalQueue::default()
+            });
+        let mut claim = TestAccount::new(crate::id()).state(&QueuedClaim::default());
+
+        let mut request = |queue: &mut TestAccount| {
+            let accounts = [
+                vault.info(),
+                queue.info(),
+                claim.info(),
+                owner.info(),
+                depositor.info(),
+            ];
+            process_request_withdrawal(&accounts, &10u64.to_le_bytes())
+        };
+        assert_eq!(request(&mut second), Err(ProgramError::InvalidSeeds));
+        assert_eq!(request(&mut foreign), Err(ProgramError::IllegalOwner));
+
+        // Nor is a claim of the second queue serviced from it
+        let mut stray = TestAccount::new(crate::id()).state(&QueuedClaim {
+            queue: second.pubkey(),
+            amount: 10.into(),
+            ..QueuedClaim::default()
+        });
+        let accounts = [vault.info(), second.info(), stray.info()];
+        assert_eq!(
+            process_service_withdrawal(&accounts, &[]),
+            Err(ProgramError::InvalidSeeds)
+        );
+    }
+}
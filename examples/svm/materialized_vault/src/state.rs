@@ -1,6 +1,12 @@
 use bytemuck::{Pod, Zeroable};
 use solana_program::pubkey::Pubkey;
-use spl_pod::primitives::PodU64;
+use spl_pod::primitives::{PodU16, PodU64};
+
+/// Flash loan fee, in bps of the borrowed amount.
+pub const FLASH_LOAN_FEE_BPS: u64 = 9;
+
+/// Denominator for basis-point arithmetic.
+pub const BPS_DENOMINATOR: u64 = 10_000;
 
 /// The vault account data structure.
 /// This is a fixed-layout POD struct suitable for on-chain storage.
@@ -13,6 +19,13 @@ pub struct Vault {
     pub shares_total: PodU64,
     /// Total tokens in vault
     pub token_total: PodU64,
+    /// Share of each reward charged as performance fee, in bps
+    pub performance_fee_bps: PodU16,
+    /// Shares minted to the owner as performance fees and not claimed into
+    /// their depositor account yet, included in `shares_total`
+    pub fee_shares: PodU64,
+    /// Cap on each depositor's lifetime deposits; zero when disabled
+    pub deposit_cap: PodU64,
 }
 
 impl Vault {
@@ -22,6 +35,79 @@ impl Vault {
             owner,
             shares_total: PodU64::from(0),
             token_total: PodU64::from(0),
+            performance_fee_bps: PodU16::from(0),
+            fee_shares: PodU64::from(0),
+            deposit_cap: PodU64::from(0),
         }
     }
 }
+
+/// The depositor account data structure.
+/// Records the shares of the vault held by one owner.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Depositor {
+    /// The depositor (authority)
+    pub owner: Pubkey,
+    /// The vault the shares belong to
+    pub vault: Pubkey,
+    /// Shares held
+    pub shares: PodU64,
+    /// Tokens deposited over the depositor's lifetime, counted whether or
+    /// not the vault's deposit cap is set
+    pub deposited: PodU64,
+}
+
+impl Depositor {
+    /// Seed of the depositor account PDA, followed by the vault and the owner
+    pub const SEED: &'static [u8] = b"depositor";
+
+    /// The address of the depositor account of `owner` in `vault`, a PDA of
+    /// the program, and its bump seed.
+    pub fn address(vault: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::SEED, vault.as_ref(), owner.as_ref()], &crate::id())
+    }
+}
+
+/// The withdrawal queue account data structure.
+/// Claims are numbered in request order and serviced strictly in that order.
+/// Each vault has a single queue, at the PDA of [`WithdrawalQueue::address`].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct WithdrawalQueue {
+    /// The vault the queue belongs to
+    pub vault: Pubkey,
+    /// Position of the next claim to service
+    pub head: PodU64,
+    /// Position assigned to the next requested claim
+    pub tail: PodU64,
+    /// Tokens owed to queued claims not serviced yet
+    pub total_queued: PodU64,
+}
+
+impl WithdrawalQueue {
+    /// Seed of the withdrawal queue PDA, followed by the vault
+    pub const SEED: &'static [u8] = b"withdrawal_queue";
+
+    /// The address of the withdrawal queue of `vault`, a PDA of the program,
+    /// and its bump seed.
+    pub fn address(vault: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::SEED, vault.as_ref()], &crate::id())
+    }
+}
+
+/// The queued claim account data structure.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct QueuedClaim {
+    /// The queue the claim belongs to
+    pub queue: Pubkey,
+    /// The claimant, paid when the claim is serviced
+    pub owner: Pubkey,
+    /// Position of the claim in the queue
+    pub position: PodU64,
+    /// Tokens owed
+    pub amount: PodU64,
+    /// Non-zero once the claim has been paid
+    pub paid: u8,
+}
@@ -0,0 +1,286 @@
+//! End-to-end scenarios of the vault: sequences of instructions sent to the
+//! program in a bank, checking the state of the accounts between them.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_vault::client::{
+    deposit_ix, init_depositor_ix, init_queue_ix, request_withdrawal_ix, reward_ix,
+    service_withdrawal_ix, set_deposit_cap_ix, set_performance_fee_ix, slash_ix,
+    transfer_shares_ix, withdraw_ix,
+};
+use solana_vault::state::{Depositor, QueuedClaim, Vault, WithdrawalQueue};
+use solana_vault::{id, process_instruction};
+use svm_test_support::solana_sdk::instruction::AccountMeta;
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, Scenario, ScenarioBuilder};
+
+fn scenario() -> ScenarioBuilder {
+    ScenarioBuilder::new("solana_vault", id(), processor!(process_instruction))
+}
+
+/// The token and share totals of the vault at `key`.
+fn totals(scenario: &mut Scenario, key: Pubkey) -> (u64, u64) {
+    let vault: Vault = scenario.state(key);
+    (vault.token_total.into(), vault.shares_total.into())
+}
+
+/// The depositor account of `owner` in `vault`, holding no shares yet.
+fn depositor(vault: Pubkey, owner: Pubkey) -> (Pubkey, Depositor) {
+    let depositor = Depositor {
+        owner,
+        vault,
+        ..Default::default()
+    };
+    (Depositor::address(&vault, &owner).0, depositor)
+}
+
+/// The shares held by the depositor account of `owner` in `vault`.
+fn shares(scenario: &mut Scenario, vault: Pubkey, owner: Pubkey) -> u64 {
+    let depositor: Depositor = scenario.state(Depositor::address(&vault, &owner).0);
+    depositor.shares.into()
+}
+
+#[test]
+fn deposit_reward_withdraw_slash() {
+    let alice = Keypair::new();
+    let vault = Pubkey::new_unique();
+    let (key, state) = depositor(vault, alice.pubkey());
+    let mut scenario = scenario()
+        .state(vault, &Vault::new(Pubkey::new_unique()))
+        .state(key, &state)
+        .start();
+    let deposit = |amount| deposit_ix(vault, alice.pubkey(), amount);
+
+    // The first deposit mints shares 1:1
+    scenario.send_instruction(deposit(100), &[&alice]).unwrap();
+    assert_eq!(totals(&mut scenario, vault), (100, 100));
+
+    // A reward raises the value of every share
+    scenario
+        .send_instruction(reward_ix(vault, 50), &[])
+        .unwrap();
+    assert_eq!(totals(&mut scenario, vault), (150, 100));
+
+    // Half of the shares are worth half of the tokens
+    let withdraw = withdraw_ix(vault, alice.pubkey(), 50);
+    scenario.send_instruction(withdraw, &[&alice]).unwrap();
+    assert_eq!(totals(&mut scenario, vault), (75, 50));
+
+    // A slash takes tokens without burning shares
+    scenario.send_instruction(slash_ix(vault, 25), &[]).unwrap();
+    assert_eq!(totals(&mut scenario, vault), (50, 50));
+
+    // The same deposit twice mints the same shares twice
+    scenario.send_instruction(deposit(10), &[&alice]).unwrap();
+    scenario.send_instruction(deposit(10), &[&alice]).unwrap();
+    assert_eq!(totals(&mut scenario, vault), (70, 70));
+    assert_eq!(shares(&mut scenario, vault, alice.pubkey()), 70);
+}
+
+#[test]
+fn performance_fee_is_minted_to_the_owner() {
+    let owner = Keypair::new();
+    let vault = Pubkey::new_unique();
+    let alice = Keypair::new();
+    let (key, state) = depositor(vault, alice.pubkey());
+    let mut scenario = scenario()
+        .state(vault, &Vault::new(owner.pubkey()))
+        .state(key, &state)
+        .start();
+    let fee = |signer: &Keypair| set_performance_fee_ix(vault, signer.pubkey(), 1_000);
+
+    // Only the owner sets the fee
+    let stranger = Keypair::new();
+    let result = scenario.send_instruction(fee(&stranger), &[&stranger]);
+    assert!(result.is_err());
+    scenario.send_instruction(fee(&owner), &[&owner]).unwrap();
+
+    let deposit = deposit_ix(vault, alice.pubkey(), 100);
+    scenario.send_instruction(deposit, &[&alice]).unwrap();
+    scenario
+        .send_instruction(reward_ix(vault, 100), &[])
+        .unwrap();
+    // 10 of the 200 tokens are the fee, worth 5 of 105 shares
+    let state: Vault = scenario.state(vault);
+    assert_eq!(u64::from(state.token_total), 200);
+    assert_eq!(u64::from(state.shares_total), 105);
+    assert_eq!(u64::from(state.fee_shares), 5);
+}
+
+#[test]
+fn depositor_shares_back_the_vault() {
+    let (alice, bob) = (Keypair::new(), Keypair::new());
+    let vault = Pubkey::new_unique();
+    let owner = Keypair::new();
+    let (alice_key, alice_state) = depositor(vault, alice.pubkey());
+    let (bob_key, bob_state) = depositor(vault, bob.pubkey());
+    // An account of the program Bob did not get from init-depositor
+    let stray = Pubkey::new_unique();
+    let mut scenario = scenario()
+        .state(vault, &Vault::new(owner.pubkey()))
+        .state(alice_key, &alice_state)
+        .state(bob_key, &bob_state)
+        .state(stray, &bob_state)
+        .start();
+    let fee = set_performance_fee_ix(vault, owner.pubkey(), 1_000);
+    scenario.send_instruction(fee, &[&owner]).unwrap();
+    let deposit = |owner: &Keypair, amount| deposit_ix(vault, owner.pubkey(), amount);
+    let withdraw = |owner: &Keypair, shares| withdraw_ix(vault, owner.pubkey(), shares);
+    let transfer =
+        |destination, shares| transfer_shares_ix(vault, alice.pubkey(), destination, shares);
+
+    scenario
+        .send_instruction(deposit(&alice, 100), &[&alice])
+        .unwrap();
+    scenario
+        .send_instruction(deposit(&bob, 50), &[&bob])
+        .unwrap();
+    scenario
+        .send_instruction(reward_ix(vault, 30), &[])
+        .unwrap();
+    scenario
+        .send_instruction(transfer(bob_key, 40), &[&alice])
+        .unwrap();
+    scenario
+        .send_instruction(withdraw(&bob, 70), &[&bob])
+        .unwrap();
+
+    // Nobody withdraws, or transfers, more shares than they hold, nor into
+    // an account that is not a depositor account
+    let result = scenario.send_instruction(withdraw(&alice, 61), &[&alice]);
+    assert!(result.is_err());
+    let result = scenario.send_instruction(withdraw(&bob, 21), &[&bob]);
+    assert!(result.is_err());
+    let result = scenario.send_instruction(transfer(stray, 10), &[&alice]);
+    assert!(result.is_err());
+    // Nor from someone else's account
+    let mut theft = withdraw(&alice, 10);
+    theft.accounts[2] = AccountMeta::new_readonly(bob.pubkey(), true);
+    assert!(scenario.send_instruction(theft, &[&bob]).is_err());
+
+    let (alice_shares, bob_shares) = (
+        shares(&mut scenario, vault, alice.pubkey()),
+        shares(&mut scenario, vault, bob.pubkey()),
+    );
+    assert_eq!((alice_shares, bob_shares), (60, 20));
+    let state: Vault = scenario.state(vault);
+    assert!(alice_shares + bob_shares <= u64::from(state.shares_total));
+    // The rest are the fee shares of the owner
+    assert_eq!(
+        alice_shares + bob_shares + u64::from(state.fee_shares),
+        u64::from(state.shares_total)
+    );
+}
+
+#[test]
+fn deposits_are_capped_per_depositor() {
+    let owner = Keypair::new();
+    let alice = Keypair::new();
+    let vault = Pubkey::new_unique();
+    let (depositor, _) = Depositor::address(&vault, &alice.pubkey());
+    // An account of the program posing as a second ledger of Alice
+    let fresh = Pubkey::new_unique();
+    let mut scenario = scenario()
+        .state(vault, &Vault::new(owner.pubkey()))
+        .wallet(alice.pubkey())
+        .state(
+            fresh,
+            &Depositor {
+                owner: alice.pubkey(),
+                vault,
+                ..Default::default()
+            },
+        )
+        .start();
+
+    // Alice's depositor account can only be created once, at its PDA
+    let mut init_fresh = init_depositor_ix(vault, alice.pubkey());
+    init_fresh.accounts[1].pubkey = fresh;
+    let result = scenario.send_instruction(init_fresh, &[&alice]);
+    assert!(result.is_err());
+    let init = || init_depositor_ix(vault, alice.pubkey());
+    scenario.send_instruction(init(), &[&alice]).unwrap();
+    assert!(scenario.send_instruction(init(), &[&alice]).is_err());
+    let state: Depositor = scenario.state(depositor);
+    assert_eq!((state.owner, state.vault), (alice.pubkey(), vault));
+
+    let cap = set_deposit_cap_ix(vault, owner.pubkey(), 100);
+    scenario.send_instruction(cap, &[&owner]).unwrap();
+
+    let deposit = |amount| deposit_ix(vault, alice.pubkey(), amount);
+    scenario.send_instruction(deposit(60), &[&alice]).unwrap();
+    // A failing deposit leaves both accounts as they were
+    assert!(scenario.send_instruction(deposit(50), &[&alice]).is_err());
+    // The cap cannot be dodged with another account
+    let mut dodge = deposit(50);
+    dodge.accounts[1].pubkey = fresh;
+    assert!(scenario.send_instruction(dodge, &[&alice]).is_err());
+    let state: Depositor = scenario.state(depositor);
+    assert_eq!(u64::from(state.deposited), 60);
+    assert_eq!(totals(&mut scenario, vault), (60, 60));
+}
+
+#[test]
+fn queued_withdrawals_go_through_the_vault_queue() {
+    let alice = Keypair::new();
+    let vault = Pubkey::new_unique();
+    let (key, state) = depositor(vault, alice.pubkey());
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+    // An account of the program posing as a second queue of the vault
+    let stray = Pubkey::new_unique();
+    let mut scenario = scenario()
+        .state(
+            vault,
+            &Vault {
+                token_total: 100.into(),
+                shares_total: 100.into(),
+                ..Vault::new(Pubkey::new_unique())
+            },
+        )
+        .state(
+            key,
+            &Depositor {
+                shares: 100.into(),
+                ..state
+            },
+        )
+        .wallet(alice.pubkey())
+        .state(first, &QueuedClaim::default())
+        .state(second, &QueuedClaim::default())
+        .state(
+            stray,
+            &WithdrawalQueue {
+                vault,
+                ..Default::default()
+            },
+        )
+        .start();
+
+    // The queue is created once, at its PDA
+    let init = || init_queue_ix(vault, alice.pubkey());
+    scenario.send_instruction(init(), &[&alice]).unwrap();
+    assert!(scenario.send_instruction(init(), &[&alice]).is_err());
+
+    // Claims only join the queue of the vault
+    let request = |claim| request_withdrawal_ix(vault, claim, alice.pubkey(), 10);
+    let mut jump = request(first);
+    jump.accounts[1].pubkey = stray;
+    assert!(scenario.send_instruction(jump, &[&alice]).is_err());
+    scenario
+        .send_instruction(request(first), &[&alice])
+        .unwrap();
+    scenario
+        .send_instruction(request(second), &[&alice])
+        .unwrap();
+    assert_eq!(shares(&mut scenario, vault, alice.pubkey()), 80);
+
+    // Where they are serviced in order
+    let service = |claim| service_withdrawal_ix(vault, claim);
+    assert!(scenario.send_instruction(service(second), &[]).is_err());
+    scenario.send_instruction(service(first), &[]).unwrap();
+    scenario.send_instruction(service(second), &[]).unwrap();
+    let queue: WithdrawalQueue = scenario.state(WithdrawalQueue::address(&vault).0);
+    assert_eq!(u64::from(queue.total_queued), 0);
+}
@@ -0,0 +1,21 @@
+//! Formal verification module for the vesting schedule.
+
+pub mod spec;
+
+/// Macro to assume the grant bound holds.
+/// Grant bound: claimed <= granted
+#[macro_export]
+macro_rules! assume_within_grant {
+    ($fv_grant:expr) => {
+        cvlr::cvlr_assume!($fv_grant.claimed <= $fv_grant.granted);
+    };
+}
+
+/// Macro to assert the grant bound holds.
+/// Grant bound: claimed <= granted
+#[macro_export]
+macro_rules! assert_within_grant {
+    ($fv_grant:expr) => {
+        cvlr::cvlr_assert!($fv_grant.claimed <= $fv_grant.granted);
+    };
+}
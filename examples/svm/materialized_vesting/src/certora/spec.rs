@@ -0,0 +1,92 @@
+//! This module contains the specification for the vesting schedule.
+
+use crate::{assert_within_grant, assume_within_grant, processor::*, state::Grant};
+use cvlr::{mathint::NativeInt, prelude::*};
+use cvlr_solana::cvlr_deserialize_nondet_accounts;
+use solana_program::account_info::{next_account_info, AccountInfo};
+
+/// Structure tracking the state for the formal verification (FV) of a grant.
+struct FvGrant {
+    granted: NativeInt,
+    claimed: NativeInt,
+}
+
+impl<'a> From<&AccountInfo<'a>> for FvGrant {
+    fn from(acc_info: &AccountInfo) -> FvGrant {
+        let mut data = acc_info.data.borrow_mut();
+        let grant: &Grant = bytemuck::from_bytes_mut(&mut data[..]);
+        let granted: u64 = grant.granted.into();
+        let claimed: u64 = grant.claimed.into();
+        FvGrant {
+            granted: granted.into(),
+            claimed: claimed.into(),
+        }
+    }
+}
+
+/// Returns a nondeterministic well-formed schedule `(start, cliff, end)`.
+fn nondet_schedule() -> (u64, u64, u64) {
+    let start_slot: u64 = nondet();
+    let cliff_slot: u64 = nondet();
+    let end_slot: u64 = nondet();
+    cvlr_assume!(start_slot <= cliff_slot);
+    cvlr_assume!(cliff_slot <= end_slot);
+    (start_slot, cliff_slot, end_slot)
+}
+
+/// Verifies that the vested amount is monotone non-decreasing in time.
+#[rule]
+pub fn rule_vested_monotone_in_time() {
+    let granted: u64 = nondet();
+    let (start_slot, cliff_slot, end_slot) = nondet_schedule();
+    let earlier: u64 = nondet();
+    let later: u64 = nondet();
+    cvlr_assume!(earlier <= later);
+
+    let vested_earlier = vested_amount(granted, start_slot, cliff_slot, end_slot, earlier);
+    let vested_later = vested_amount(granted, start_slot, cliff_slot, end_slot, later);
+    cvlr_assert!(vested_earlier <= vested_later);
+}
+
+/// Verifies that the vested amount never exceeds the granted amount.
+#[rule]
+pub fn rule_vested_within_grant() {
+    let granted: u64 = nondet();
+    let (start_slot, cliff_slot, end_slot) = nondet_schedule();
+    let slot: u64 = nondet();
+
+    cvlr_assert!(vested_amount(granted, start_slot, cliff_slot, end_slot, slot) <= granted);
+}
+
+/// Verifies that claiming never releases more than the granted amount.
+#[rule]
+pub fn rule_claim_within_grant() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let grant_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    let fv_grant_pre: FvGrant = grant_account.into();
+    assume_within_grant!(fv_grant_pre);
+
+    process_claim(&account_infos, &[]).unwrap();
+
+    let fv_grant_post: FvGrant = grant_account.into();
+    assert_within_grant!(fv_grant_post);
+}
+
+/// Verifies that the total claimed amount never decreases across successive
+/// claims.
+#[rule]
+pub fn rule_claimed_monotone() {
+    let account_infos = cvlr_deserialize_nondet_accounts();
+    let account_info_iter = &mut account_infos.iter();
+    let grant_account: &AccountInfo = next_account_info(account_info_iter).unwrap();
+
+    process_claim(&account_infos, &[]).unwrap();
+    let fv_grant_first: FvGrant = grant_account.into();
+
+    process_claim(&account_infos, &[]).unwrap();
+    let fv_grant_second: FvGrant = grant_account.into();
+
+    cvlr_assert!(fv_grant_first.claimed <= fv_grant_second.claimed);
+}
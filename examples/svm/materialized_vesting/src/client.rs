@@ -0,0 +1,56 @@
+//! Instruction builders and state decoders for off-chain clients.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use crate::state::Grant;
+
+/// The id of the program.
+pub fn program_id() -> Pubkey {
+    crate::id()
+}
+
+/// Grants `granted` to `beneficiary`, vesting linearly from `start_slot` to
+/// `end_slot` and claimable from `cliff_slot`.
+pub fn create_ix(
+    grant: Pubkey,
+    beneficiary: Pubkey,
+    granted: u64,
+    start_slot: u64,
+    cliff_slot: u64,
+    end_slot: u64,
+) -> Instruction {
+    let mut data = vec![0u8];
+    data.extend_from_slice(&granted.to_le_bytes());
+    data.extend_from_slice(&start_slot.to_le_bytes());
+    data.extend_from_slice(&cliff_slot.to_le_bytes());
+    data.extend_from_slice(&end_slot.to_le_bytes());
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(grant, false),
+            AccountMeta::new_readonly(beneficiary, false),
+        ],
+        data,
+    }
+}
+
+/// Releases everything vested so far that has not been claimed yet.
+pub fn claim_ix(grant: Pubkey, beneficiary: Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(grant, false),
+            AccountMeta::new_readonly(beneficiary, true),
+        ],
+        data: vec![1u8],
+    }
+}
+
+/// Decodes the data of a Grant account.
+pub fn decode_grant(data: &[u8]) -> Result<Grant, ProgramError> {
+    data.get(..core::mem::size_of::<Grant>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(ProgramError::InvalidAccountData)
+}
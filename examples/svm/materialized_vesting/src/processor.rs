@@ -0,0 +1,130 @@
+use crate::state::Grant;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+use svm_fv_support::cpi;
+
+/// Reads a little-endian `u64` starting at `offset` in the instruction data.
+fn read_u64(instruction_data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = instruction_data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Amount of `granted` vested at `slot` under the given schedule.
+/// Requires `start_slot <= cliff_slot <= end_slot`.
+pub fn vested_amount(
+    granted: u64,
+    start_slot: u64,
+    cliff_slot: u64,
+    end_slot: u64,
+    slot: u64,
+) -> u64 {
+    if slot < cliff_slot {
+        0
+    } else if slot >= end_slot {
+        granted
+    } else {
+        // start_slot <= cliff_slot <= slot < end_slot, so the duration is non-zero
+        let elapsed = (slot - start_slot) as u128;
+        let duration = (end_slot - start_slot) as u128;
+        ((granted as u128) * elapsed / duration) as u64
+    }
+}
+
+/// Process a create instruction.
+/// Initializes a grant with a linear and/or cliff vesting schedule.
+///
+/// # Arguments
+/// - `accounts`: Account array (grant account first, beneficiary second)
+/// - `instruction_data`: 8 bytes granted amount, followed by 8 bytes each for
+///   the start, cliff and end slots
+pub fn process_create(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let granted = read_u64(instruction_data, 0)?;
+    let start_slot = read_u64(instruction_data, 8)?;
+    let cliff_slot = read_u64(instruction_data, 16)?;
+    let end_slot = read_u64(instruction_data, 24)?;
+    if start_slot > cliff_slot || cliff_slot > end_slot {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let grant_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let beneficiary = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // Borrow and read grant data
+    let mut grant_data = grant_account.data.borrow_mut();
+    let grant: &mut Grant = bytemuck::from_bytes_mut(&mut grant_data);
+    let existing: u64 = grant.granted.into();
+    if existing != 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    *grant = Grant::new(*beneficiary.key, granted, start_slot, cliff_slot, end_slot);
+
+    Ok(())
+}
+
+/// Process a claim instruction.
+/// Releases everything vested so far that has not been claimed yet.
+///
+/// # Arguments
+/// - `accounts`: Account array (grant account first, signing beneficiary
+///   second)
+/// - `instruction_data`: unused
+pub fn process_claim(accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    let grant_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let beneficiary = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !beneficiary.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Borrow and read grant data
+    let mut grant_data = grant_account.data.borrow_mut();
+    let grant: &mut Grant = bytemuck::from_bytes_mut(&mut grant_data);
+    if grant.beneficiary != *beneficiary.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+    let start_slot: u64 = grant.start_slot.into();
+    let cliff_slot: u64 = grant.cliff_slot.into();
+    let end_slot: u64 = grant.end_slot.into();
+    if start_slot > cliff_slot || cliff_slot > end_slot {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let vested = vested_amount(
+        grant.granted.into(),
+        start_slot,
+        cliff_slot,
+        end_slot,
+        cpi::sysvar::slot()?,
+    );
+    let claimed: u64 = grant.claimed.into();
+    // Vesting is monotone in time, so only the unclaimed part is released
+    grant.claimed = claimed.max(vested).into();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vested_amount_linear() {
+        assert_eq!(vested_amount(1_000, 100, 100, 200, 99), 0);
+        assert_eq!(vested_amount(1_000, 100, 100, 200, 150), 500);
+        assert_eq!(vested_amount(1_000, 100, 100, 200, 200), 1_000);
+        assert_eq!(vested_amount(1_000, 100, 100, 200, u64::MAX), 1_000);
+    }
+
+    #[test]
+    fn test_vested_amount_cliff() {
+        assert_eq!(vested_amount(1_000, 100, 150, 200, 149), 0);
+        assert_eq!(vested_amount(1_000, 100, 150, 200, 150), 500);
+        assert_eq!(vested_amount(1_000, 100, 200, 200, 199), 0);
+        assert_eq!(vested_amount(1_000, 100, 200, 200, 200), 1_000);
+    }
+}
@@ -0,0 +1,46 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::pubkey::Pubkey;
+use spl_pod::primitives::PodU64;
+
+/// The vesting grant account data structure.
+/// This is a fixed-layout POD struct suitable for on-chain storage.
+///
+/// Nothing vests before `cliff_slot`; from then on the grant vests linearly
+/// from `start_slot` to `end_slot`. A pure cliff schedule sets
+/// `cliff_slot == end_slot`, a pure linear one `cliff_slot == start_slot`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Grant {
+    /// The beneficiary (authority)
+    pub beneficiary: Pubkey,
+    /// Total amount granted
+    pub granted: PodU64,
+    /// Total amount claimed so far
+    pub claimed: PodU64,
+    /// Slot at which linear vesting starts
+    pub start_slot: PodU64,
+    /// Slot before which nothing can be claimed
+    pub cliff_slot: PodU64,
+    /// Slot at which the grant is fully vested
+    pub end_slot: PodU64,
+}
+
+impl Grant {
+    /// Create a new grant for `beneficiary` with the given schedule
+    pub fn new(
+        beneficiary: Pubkey,
+        granted: u64,
+        start_slot: u64,
+        cliff_slot: u64,
+        end_slot: u64,
+    ) -> Self {
+        Grant {
+            beneficiary,
+            granted: PodU64::from(granted),
+            claimed: PodU64::from(0),
+            start_slot: PodU64::from(start_slot),
+            cliff_slot: PodU64::from(cliff_slot),
+            end_slot: PodU64::from(end_slot),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! End-to-end scenarios of the vesting grant: sequences of instructions sent
+//! to the program in a bank, checking the state they leave behind.
+//!
+//! Run with `cargo test`, or with `cargo test-sbf` to send them to the
+//! program built for the SBF.
+
+use solana_vesting::client::{claim_ix, create_ix};
+use solana_vesting::state::Grant;
+use solana_vesting::{id, process_instruction};
+use svm_test_support::solana_sdk::pubkey::Pubkey;
+use svm_test_support::solana_sdk::signature::{Keypair, Signer};
+use svm_test_support::{processor, Scenario, ScenarioBuilder};
+
+/// The amount claimed from the grant at `key`.
+fn claimed(scenario: &mut Scenario, key: Pubkey) -> u64 {
+    scenario.state::<Grant>(key).claimed.into()
+}
+
+#[test]
+fn claims_follow_the_schedule() {
+    let (beneficiary, stranger) = (Keypair::new(), Keypair::new());
+    let grant = Pubkey::new_unique();
+    let mut scenario =
+        ScenarioBuilder::new("solana_vesting", id(), processor!(process_instruction))
+            .state(grant, &Grant::default())
+            .start();
+    // 1_000 vesting from slot 1_000 to 2_000, after a cliff at 1_500
+    let create = || create_ix(grant, beneficiary.pubkey(), 1_000, 1_000, 1_500, 2_000);
+    scenario.send_instruction(create(), &[]).unwrap();
+    // The grant is created once
+    assert!(scenario.send_instruction(create(), &[]).is_err());
+
+    // Nothing vests before the cliff
+    let claim = |signer: &Keypair| claim_ix(grant, signer.pubkey());
+    scenario
+        .send_instruction(claim(&beneficiary), &[&beneficiary])
+        .unwrap();
+    assert_eq!(claimed(&mut scenario, grant), 0);
+
+    // Then what has vested since the start, to the beneficiary alone
+    scenario.warp_to_slot(1_500);
+    assert!(scenario
+        .send_instruction(claim(&stranger), &[&stranger])
+        .is_err());
+    scenario
+        .send_instruction(claim(&beneficiary), &[&beneficiary])
+        .unwrap();
+    assert!((500..1_000).contains(&claimed(&mut scenario, grant)));
+
+    // And everything after the end
+    scenario.warp_to_slot(3_000);
+    scenario
+        .send_instruction(claim(&beneficiary), &[&beneficiary])
+        .unwrap();
+    assert_eq!(claimed(&mut scenario, grant), 1_000);
+}
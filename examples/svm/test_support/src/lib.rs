@@ -0,0 +1,201 @@
+//! End-to-end scenarios for the Solana examples.
+//!
+//! A [`ScenarioBuilder`] sets up `solana-program-test` with the program of an
+//! example and the accounts it owns, and the [`Scenario`] it starts sends
+//! instructions to it one after the other, each from the funded payer, and
+//! reads the state they leave behind. Under `cargo test` the program runs
+//! natively through its `process_instruction`; under `cargo test-sbf` the
+//! shared object that command builds is loaded instead, so the same
+//! scenarios also cover the entrypoint and the runtime checks on chain.
+//!
+//! Every example whose program takes accounts has scenarios, so all but the
+//! arithmetic and fixed-point ones. Like them, this crate is not a member of
+//! the workspace, so it builds against their solana 1.18.
+//!
+//! Scenarios block on a runtime of their own, so tests using them are plain
+//! `#[test]` functions. They build their instructions with the `client`
+//! module of the example, enabled by a dev-dependency of the example on
+//! itself with the `client` feature:
+//!
+//! ```ignore
+//! let vault = Pubkey::new_unique();
+//! let mut scenario =
+//!     ScenarioBuilder::new("solana_vault", id(), processor!(process_instruction))
+//!         .state(vault, &Vault::new(Pubkey::new_unique()))
+//!         .start();
+//! scenario.send_instruction(reward_ix(vault, 100), &[]).unwrap();
+//! assert_eq!(u64::from(scenario.state::<Vault>(vault).token_total), 100);
+//! ```
+
+use bytemuck::Pod;
+use solana_program_test::tokio::runtime::Runtime;
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+
+pub use solana_program_runtime::invoke_context::BuiltinFunctionWithContext;
+pub use solana_program_test::{processor, BanksClientError};
+pub use solana_sdk;
+
+/// Lamports of the accounts created for a scenario, enough to be rent exempt
+/// at any size the examples use.
+const LAMPORTS: u64 = 1_000_000_000;
+
+/// The program and the accounts a [`Scenario`] starts with.
+pub struct ScenarioBuilder {
+    program_test: ProgramTest,
+    program_id: Pubkey,
+}
+
+impl ScenarioBuilder {
+    /// A scenario of the program `name`, the crate name of an example, at
+    /// `program_id`. `entrypoint`, given as
+    /// `processor!(process_instruction)`, runs it natively unless
+    /// `cargo test-sbf` built it.
+    pub fn new(
+        name: &str,
+        program_id: Pubkey,
+        entrypoint: Option<BuiltinFunctionWithContext>,
+    ) -> Self {
+        ScenarioBuilder {
+            program_test: ProgramTest::new(name, program_id, entrypoint),
+            program_id,
+        }
+    }
+
+    /// Creates an account of the program at `key` holding `data`.
+    pub fn account(mut self, key: Pubkey, data: Vec<u8>) -> Self {
+        let account = Account {
+            lamports: LAMPORTS,
+            data,
+            owner: self.program_id,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.program_test.add_account(key, account);
+        self
+    }
+
+    /// Creates a wallet at `key`: an account of the system program holding
+    /// lamports, e.g. to pay for the accounts an instruction creates.
+    pub fn wallet(mut self, key: Pubkey) -> Self {
+        self.program_test.add_account(
+            key,
+            Account::new(LAMPORTS, 0, &solana_sdk::system_program::id()),
+        );
+        self
+    }
+
+    /// Creates an account of the program at `key` holding `state`.
+    pub fn state<T: Pod>(self, key: Pubkey, state: &T) -> Self {
+        self.account(key, bytemuck::bytes_of(state).to_vec())
+    }
+
+    /// Starts the bank with the program and the accounts.
+    pub fn start(self) -> Scenario {
+        let runtime = solana_program_test::tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("a runtime for the scenario");
+        let context = runtime.block_on(self.program_test.start_with_context());
+        Scenario {
+            runtime,
+            context,
+            program_id: self.program_id,
+        }
+    }
+}
+
+/// A running bank with the program of an example.
+pub struct Scenario {
+    runtime: Runtime,
+    context: ProgramTestContext,
+    program_id: Pubkey,
+}
+
+impl Scenario {
+    /// Sends an instruction of the program with `data` over `accounts`,
+    /// signed by `signers` and paid for by the payer of the bank, and waits
+    /// until it is processed.
+    pub fn send(
+        &mut self,
+        data: &[u8],
+        accounts: Vec<AccountMeta>,
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let instruction = Instruction::new_with_bytes(self.program_id, data, accounts);
+        self.send_instruction(instruction, signers)
+    }
+
+    /// Sends `instruction`, e.g. built by the `client` module of the example,
+    /// signed by `signers` and paid for by the payer of the bank, and waits
+    /// until it is processed.
+    pub fn send_instruction(
+        &mut self,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        self.send_instructions(&[instruction], signers)
+    }
+
+    /// Sends `instructions` in one transaction, e.g. a transfer into an
+    /// account of the program followed by the instruction that spends it,
+    /// signed by `signers` and paid for by the payer of the bank, and waits
+    /// until it is processed.
+    pub fn send_instructions(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let context = &mut self.context;
+        self.runtime.block_on(async {
+            // A fresh blockhash, so that sending the same instruction twice
+            // is not rejected as a duplicate
+            let blockhash = context.get_new_latest_blockhash().await?;
+            let mut keypairs = vec![&context.payer];
+            keypairs.extend_from_slice(signers);
+            let transaction = Transaction::new_signed_with_payer(
+                instructions,
+                Some(&context.payer.pubkey()),
+                &keypairs,
+                blockhash,
+            );
+            context.banks_client.process_transaction(transaction).await
+        })
+    }
+
+    /// Moves the bank on to `slot`, e.g. past the end of an auction.
+    pub fn warp_to_slot(&mut self, slot: u64) {
+        self.context
+            .warp_to_slot(slot)
+            .expect("the slot is ahead of the bank");
+    }
+
+    /// The account at `key`.
+    fn get(&mut self, key: Pubkey) -> Account {
+        self.runtime
+            .block_on(self.context.banks_client.get_account(key))
+            .expect("the bank answers")
+            .expect("the account exists")
+    }
+
+    /// The data of the account at `key`.
+    pub fn data(&mut self, key: Pubkey) -> Vec<u8> {
+        self.get(key).data
+    }
+
+    /// The lamports held by the account at `key`.
+    pub fn lamports(&mut self, key: Pubkey) -> u64 {
+        self.get(key).lamports
+    }
+
+    /// The state held by the account at `key`.
+    pub fn state<T: Pod>(&mut self, key: Pubkey) -> T {
+        bytemuck::pod_read_unaligned(&self.data(key))
+    }
+}
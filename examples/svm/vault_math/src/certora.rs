@@ -0,0 +1,3 @@
+//! Formal verification module for the vault math.
+
+pub mod spec;
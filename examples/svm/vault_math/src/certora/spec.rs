@@ -0,0 +1,110 @@
+//! This module contains the specification for the vault math.
+//!
+//! Every conversion is checked against the exact ratio computed in
+//! mathematical integers, over all `u64` inputs: results are rounded by
+//! less than one unit in the direction favouring the vault, and a deposit
+//! followed by a withdrawal of the shares it minted never returns more
+//! than was deposited.
+
+use crate::{mul_div_ceil, mul_div_floor, shares_for_deposit, tokens_for_shares};
+use cvlr::{mathint::NativeInt, prelude::*};
+
+/// `x` as a mathematical integer.
+fn fv(x: u64) -> NativeInt {
+    x.into()
+}
+
+/// Verifies that `mul_div_floor` rounds down by less than one:
+/// result * c <= a * b < (result + 1) * c.
+#[rule]
+pub fn rule_mul_div_floor_error_bound() {
+    let (a, b, c): (u64, u64, u64) = (nondet(), nondet(), nondet());
+    let result = mul_div_floor(a, b, c);
+    cvlr_assume!(result.is_some());
+    let result = fv(result.unwrap());
+
+    let exact = fv(a) * fv(b);
+    cvlr_assert!(result * fv(c) <= exact);
+    cvlr_assert!(exact < (result + 1u64) * fv(c));
+}
+
+/// Verifies that `mul_div_ceil` rounds up by less than one:
+/// (result - 1) * c < a * b <= result * c.
+#[rule]
+pub fn rule_mul_div_ceil_error_bound() {
+    let (a, b, c): (u64, u64, u64) = (nondet(), nondet(), nondet());
+    let result = mul_div_ceil(a, b, c);
+    cvlr_assume!(result.is_some());
+    let result = fv(result.unwrap());
+
+    let exact = fv(a) * fv(b);
+    cvlr_assert!(exact <= result * fv(c));
+    cvlr_assert!(exact + fv(c) > result * fv(c));
+}
+
+/// Verifies that `mul_div_floor` fails only on division by zero or when the
+/// exact result does not fit a `u64`.
+#[rule]
+pub fn rule_mul_div_floor_fails_only_on_overflow() {
+    let (a, b, c): (u64, u64, u64) = (nondet(), nondet(), nondet());
+    cvlr_assume!(c != 0);
+    cvlr_assume!(fv(a) * fv(b) < (fv(u64::MAX) + 1u64) * fv(c));
+    cvlr_assert!(mul_div_floor(a, b, c).is_some());
+}
+
+/// Verifies that a solvent vault, with no more shares than tokens, mints
+/// shares for any deposit.
+#[rule]
+pub fn rule_deposit_never_overflows_when_solvent() {
+    let (amount, shares_total, token_total): (u64, u64, u64) = (nondet(), nondet(), nondet());
+    cvlr_assume!(shares_total <= token_total);
+    let shares = shares_for_deposit(amount, shares_total, token_total);
+    cvlr_assert!(shares.is_some());
+    cvlr_assert!(shares.unwrap() <= amount);
+}
+
+/// Verifies that withdrawing at most every share pays out at most every
+/// token.
+#[rule]
+pub fn rule_withdraw_within_holdings() {
+    let (shares, shares_total, token_total): (u64, u64, u64) = (nondet(), nondet(), nondet());
+    cvlr_assume!(shares <= shares_total);
+    let tokens = tokens_for_shares(shares, shares_total, token_total);
+    cvlr_assert!(tokens.is_some());
+    cvlr_assert!(tokens.unwrap() <= token_total);
+}
+
+/// Verifies that withdrawing the shares minted for a deposit returns at
+/// most the deposit.
+#[rule]
+pub fn rule_deposit_then_withdraw_no_profit() {
+    let (amount, shares_total, token_total): (u64, u64, u64) = (nondet(), nondet(), nondet());
+    let shares = shares_for_deposit(amount, shares_total, token_total);
+    cvlr_assume!(shares.is_some());
+    let shares = shares.unwrap();
+    let (Some(new_shares), Some(new_tokens)) = (
+        shares_total.checked_add(shares),
+        token_total.checked_add(amount),
+    ) else {
+        return;
+    };
+
+    let tokens = tokens_for_shares(shares, new_shares, new_tokens);
+    cvlr_assert!(tokens.is_some());
+    cvlr_assert!(tokens.unwrap() <= amount);
+}
+
+/// Verifies that more tokens never mint fewer shares.
+#[rule]
+pub fn rule_deposit_monotonic() {
+    let (a, b, shares_total, token_total): (u64, u64, u64, u64) =
+        (nondet(), nondet(), nondet(), nondet());
+    cvlr_assume!(a <= b);
+    let (lo, hi) = (
+        shares_for_deposit(a, shares_total, token_total),
+        shares_for_deposit(b, shares_total, token_total),
+    );
+    cvlr_assume!(hi.is_some());
+    cvlr_assert!(lo.is_some());
+    cvlr_assert!(lo.unwrap() <= hi.unwrap());
+}
@@ -0,0 +1,84 @@
+//! Share and token conversions of vaults.
+//!
+//! A vault holding `token_total` tokens against `shares_total` shares
+//! converts between the two at that ratio. Every conversion multiplies in
+//! `u128`, so no intermediate product overflows, and rounds in favour of
+//! the vault: depositors get the shares and tokens rounded down, fees are
+//! rounded up. Results that do not fit a `u64` are `None` rather than
+//! truncated.
+
+/// `a * b / c`, rounded down.
+/// Returns `None` if `c` is zero or the result does not fit a `u64`.
+pub fn mul_div_floor(a: u64, b: u64, c: u64) -> Option<u64> {
+    if c == 0 {
+        return None;
+    }
+    // (2^64 - 1)^2 < 2^128, so the product cannot overflow
+    u64::try_from(a as u128 * b as u128 / c as u128).ok()
+}
+
+/// `a * b / c`, rounded up.
+/// Returns `None` if `c` is zero or the result does not fit a `u64`.
+pub fn mul_div_ceil(a: u64, b: u64, c: u64) -> Option<u64> {
+    if c == 0 {
+        return None;
+    }
+    u64::try_from((a as u128 * b as u128).div_ceil(c as u128)).ok()
+}
+
+/// The shares minted for a deposit of `amount` tokens, rounded down; one
+/// share per token while the vault holds no tokens.
+/// Returns `None` if the shares do not fit a `u64`.
+pub fn shares_for_deposit(amount: u64, shares_total: u64, token_total: u64) -> Option<u64> {
+    if token_total == 0 {
+        return Some(amount);
+    }
+    // shares / amount = shares_total / token_total
+    mul_div_floor(amount, shares_total, token_total)
+}
+
+/// The tokens paid out for `shares` shares, rounded down; none while the
+/// vault has no shares.
+/// Returns `None` if the tokens do not fit a `u64`, which only happens for
+/// more shares than there are.
+pub fn tokens_for_shares(shares: u64, shares_total: u64, token_total: u64) -> Option<u64> {
+    if shares_total == 0 {
+        return Some(0);
+    }
+    // tokens / shares = token_total / shares_total
+    mul_div_floor(shares, token_total, shares_total)
+}
+
+// Include formal verification module only if certora feature is enabled.
+#[cfg(feature = "certora")]
+pub mod certora;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mul_div_rounding() {
+        assert_eq!(mul_div_floor(7, 3, 2), Some(10));
+        assert_eq!(mul_div_ceil(7, 3, 2), Some(11));
+        assert_eq!(mul_div_ceil(6, 3, 2), Some(9));
+        assert_eq!(mul_div_floor(u64::MAX, u64::MAX, u64::MAX), Some(u64::MAX));
+        assert_eq!(mul_div_floor(u64::MAX, 2, 1), None);
+        assert_eq!(mul_div_floor(1, 1, 0), None);
+        assert_eq!(mul_div_ceil(1, 1, 0), None);
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(shares_for_deposit(100, 0, 0), Some(100));
+        assert_eq!(shares_for_deposit(100, 50, 200), Some(25));
+        // 1 token is worth a third of a share, rounded down
+        assert_eq!(shares_for_deposit(1, 1, 3), Some(0));
+        assert_eq!(shares_for_deposit(u64::MAX, u64::MAX, 1), None);
+
+        assert_eq!(tokens_for_shares(25, 0, 200), Some(0));
+        assert_eq!(tokens_for_shares(25, 50, 200), Some(100));
+        assert_eq!(tokens_for_shares(1, 3, 1), Some(0));
+        assert_eq!(tokens_for_shares(u64::MAX, 1, u64::MAX), None);
+    }
+}
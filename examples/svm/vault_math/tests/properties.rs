@@ -0,0 +1,58 @@
+//! Property tests of the vault math, mirroring its rules on random inputs.
+
+use proptest::prelude::*;
+use svm_vault_math::{mul_div_ceil, mul_div_floor, shares_for_deposit, tokens_for_shares};
+
+proptest! {
+    #[test]
+    fn mul_div_error_bounds(a: u64, b: u64, c in 1u64..) {
+        let exact = a as u128 * b as u128;
+        if let Some(floor) = mul_div_floor(a, b, c) {
+            prop_assert!(floor as u128 * c as u128 <= exact);
+            prop_assert!(exact < (floor as u128 + 1) * c as u128);
+        } else {
+            prop_assert!(exact / c as u128 > u64::MAX as u128);
+        }
+        if let Some(ceil) = mul_div_ceil(a, b, c) {
+            prop_assert!(exact <= ceil as u128 * c as u128);
+            prop_assert!(exact + (c as u128) > ceil as u128 * c as u128);
+        }
+    }
+
+    #[test]
+    fn deposit_never_overflows_when_solvent(amount: u64, shares_total: u64, extra: u64) {
+        let token_total = shares_total.saturating_add(extra);
+        let shares = shares_for_deposit(amount, shares_total, token_total);
+        prop_assert!(shares.is_some_and(|shares| shares <= amount));
+    }
+
+    #[test]
+    fn withdraw_within_holdings(shares_total: u64, token_total: u64, fraction in 0.0..=1.0f64) {
+        let shares = (shares_total as f64 * fraction) as u64;
+        let tokens = tokens_for_shares(shares.min(shares_total), shares_total, token_total);
+        prop_assert!(tokens.is_some_and(|tokens| tokens <= token_total));
+    }
+
+    #[test]
+    fn deposit_then_withdraw_no_profit(amount: u64, shares_total: u64, token_total: u64) {
+        let Some(shares) = shares_for_deposit(amount, shares_total, token_total) else {
+            return Ok(());
+        };
+        let (Some(new_shares), Some(new_tokens)) =
+            (shares_total.checked_add(shares), token_total.checked_add(amount))
+        else {
+            return Ok(());
+        };
+        let tokens = tokens_for_shares(shares, new_shares, new_tokens);
+        prop_assert!(tokens.is_some_and(|tokens| tokens <= amount));
+    }
+
+    #[test]
+    fn deposit_monotonic(a: u64, b: u64, shares_total: u64, token_total: u64) {
+        let (a, b) = (a.min(b), a.max(b));
+        if let Some(hi) = shares_for_deposit(b, shares_total, token_total) {
+            let lo = shares_for_deposit(a, shares_total, token_total);
+            prop_assert!(lo.is_some_and(|lo| lo <= hi));
+        }
+    }
+}